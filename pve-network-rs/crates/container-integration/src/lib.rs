@@ -18,12 +18,14 @@ pub use types::*;
 pub use vnet_binding::VNetBinding;
 
 use std::sync::Arc;
+use tokio::sync::RwLock;
 
 use pve_event_bus::{EventBus, EventBusResult};
+use pve_sdn_core::SdnConfiguration;
 
 /// Container integration manager
 pub struct ContainerIntegration {
-    vnet_binding: VNetBinding,
+    vnet_binding: Arc<VNetBinding>,
     hotplug: ContainerNetworkHotplug,
     compat: PveContainerCompat,
     hooks: ContainerNetworkHooks,
@@ -32,9 +34,22 @@ pub struct ContainerIntegration {
 impl ContainerIntegration {
     /// Create new container integration manager
     pub fn new() -> Self {
+        Self::with_vnet_binding(VNetBinding::new())
+    }
+
+    /// Create container integration manager looking up vnets in `config`
+    ///
+    /// Bindings then apply the vnet options, e.g. port isolation.
+    pub fn with_sdn_config(config: Arc<RwLock<SdnConfiguration>>) -> Self {
+        Self::with_vnet_binding(VNetBinding::new().with_sdn_config(config))
+    }
+
+    /// Hotplug operations share the bindings of this manager
+    fn with_vnet_binding(vnet_binding: VNetBinding) -> Self {
+        let vnet_binding = Arc::new(vnet_binding);
         Self {
-            vnet_binding: VNetBinding::new(),
-            hotplug: ContainerNetworkHotplug::new(),
+            hotplug: ContainerNetworkHotplug::with_vnet_binding(vnet_binding.clone()),
+            vnet_binding,
             compat: PveContainerCompat::new(),
             hooks: ContainerNetworkHooks::new(),
         }
@@ -71,3 +86,35 @@ impl Default for ContainerIntegration {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_sdn_core::VNetConfig;
+
+    #[tokio::test]
+    async fn test_bindings_use_sdn_config() {
+        let config = Arc::new(RwLock::new(SdnConfiguration::new()));
+        let integration = ContainerIntegration::with_sdn_config(config.clone());
+
+        let mut interface = ContainerNetworkInterface::new("net0".to_string());
+        interface.vnet = Some("tenant".to_string());
+
+        // Unknown to the SDN configuration
+        assert!(integration
+            .vnet_binding()
+            .bind_vnet("tenant", 100, &interface)
+            .await
+            .is_err());
+
+        config.write().await.vnets.insert(
+            "tenant".to_string(),
+            VNetConfig::new("tenant".to_string(), "zone1".to_string()),
+        );
+        integration
+            .vnet_binding()
+            .bind_vnet("tenant", 100, &interface)
+            .await
+            .unwrap();
+    }
+}
@@ -6,6 +6,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use pve_sdn_core::{RealExecutor, SdnConfiguration, SystemExecutor, VNetConfig};
+
 use crate::error::{ContainerError, Result};
 use crate::types::{
     ContainerId, ContainerNetworkEvent, ContainerNetworkEventType, ContainerNetworkInterface,
@@ -20,6 +22,10 @@ pub struct VNetBinding {
     container_bindings: Arc<RwLock<HashMap<ContainerId, HashMap<String, String>>>>,
     /// Binding metadata
     binding_metadata: Arc<RwLock<HashMap<String, VNetBindingInfo>>>,
+    /// SDN configuration the vnets are looked up in
+    sdn_config: Option<Arc<RwLock<SdnConfiguration>>>,
+    /// Executor setting bridge port flags of the host interfaces
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl VNetBinding {
@@ -29,9 +35,23 @@ impl VNetBinding {
             bindings: Arc::new(RwLock::new(HashMap::new())),
             container_bindings: Arc::new(RwLock::new(HashMap::new())),
             binding_metadata: Arc::new(RwLock::new(HashMap::new())),
+            sdn_config: None,
+            executor: Arc::new(RealExecutor::new()),
         }
     }
 
+    /// Look up vnets and their options in `config`
+    pub fn with_sdn_config(mut self, config: Arc<RwLock<SdnConfiguration>>) -> Self {
+        self.sdn_config = Some(config);
+        self
+    }
+
+    /// Use `executor` for bridge port changes
+    pub fn with_executor(mut self, executor: Arc<dyn SystemExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
     /// Bind VNet to container interface
     pub async fn bind_vnet(
         &self,
@@ -47,12 +67,10 @@ impl VNetBinding {
         // Validate interface configuration
         interface.validate()?;
 
-        // Check if VNet exists (this would normally query the SDN configuration)
-        if !self.vnet_exists(vnet).await? {
-            return Err(ContainerError::VNetNotFound {
-                vnet: vnet.to_string(),
-            });
-        }
+        let isolated = self
+            .vnet_config(vnet)
+            .await?
+            .is_some_and(|config| config.isolate_ports());
 
         // Check for existing binding
         if self
@@ -66,10 +84,16 @@ impl VNetBinding {
             return Ok(());
         }
 
+        if isolated {
+            self.set_port_isolation(container_id, &interface.name)
+                .await?;
+        }
+
         // Create binding
         let binding_key = format!("{}:{}:{}", vnet, container_id, interface.name);
-        let binding_info =
+        let mut binding_info =
             VNetBindingInfo::new(vnet.to_string(), container_id, interface.name.clone());
+        binding_info.isolated = isolated;
 
         // Update data structures
         {
@@ -281,11 +305,60 @@ impl VNetBinding {
         Ok(())
     }
 
-    /// Check if VNet exists (placeholder - would integrate with SDN core)
-    async fn vnet_exists(&self, _vnet: &str) -> Result<bool> {
-        // TODO: Integrate with SDN core to check if VNet exists
-        // For now, assume all VNets exist
-        Ok(true)
+    /// Configuration of `vnet`, `None` if there is no SDN configuration to
+    /// check against
+    async fn vnet_config(&self, vnet: &str) -> Result<Option<VNetConfig>> {
+        let Some(sdn_config) = &self.sdn_config else {
+            return Ok(None);
+        };
+
+        match sdn_config.read().await.vnets.get(vnet) {
+            Some(config) => Ok(Some(config.clone())),
+            None => Err(ContainerError::VNetNotFound {
+                vnet: vnet.to_string(),
+            }),
+        }
+    }
+
+    /// Isolate the host side of `interface_name` from the other bridge ports
+    async fn set_port_isolation(
+        &self,
+        container_id: ContainerId,
+        interface_name: &str,
+    ) -> Result<()> {
+        let host_interface =
+            host_interface_name(container_id, interface_name).ok_or_else(|| {
+                ContainerError::InvalidConfiguration {
+                    field: "name".to_string(),
+                    reason: format!(
+                        "Cannot isolate interface '{}', expected netN",
+                        interface_name
+                    ),
+                }
+            })?;
+
+        let output = self
+            .executor
+            .execute(
+                "bridge",
+                &["link", "set", "dev", &host_interface, "isolated", "on"],
+            )
+            .await
+            .map_err(|e| ContainerError::NetworkOperation {
+                message: e.to_string(),
+            })?;
+
+        if !output.is_success() {
+            return Err(ContainerError::NetworkOperation {
+                message: format!(
+                    "Failed to isolate bridge port '{}': {}",
+                    host_interface,
+                    output.stderr.trim()
+                ),
+            });
+        }
+
+        Ok(())
     }
 
     /// Emit binding event (placeholder - would integrate with event system)
@@ -352,6 +425,12 @@ impl VNetBindingTrait for VNetBinding {
     }
 }
 
+/// Host side veth of container interface `netN`, as created by pve-container
+fn host_interface_name(container_id: ContainerId, interface_name: &str) -> Option<String> {
+    let index: u32 = interface_name.strip_prefix("net")?.parse().ok()?;
+    Some(format!("veth{}i{}", container_id, index))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -436,4 +515,54 @@ mod tests {
             .unwrap();
         assert!(vnets_after.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_isolated_vnet_binding() {
+        use pve_sdn_core::RecordingExecutor;
+
+        let mut sdn = SdnConfiguration::new();
+        let mut isolated = VNetConfig::new("tenant".to_string(), "zone1".to_string());
+        isolated.isolate_ports = Some(true);
+        sdn.vnets.insert("tenant".to_string(), isolated);
+        sdn.vnets.insert(
+            "shared".to_string(),
+            VNetConfig::new("shared".to_string(), "zone1".to_string()),
+        );
+
+        let executor = Arc::new(RecordingExecutor::new());
+        let binding_manager = VNetBinding::new()
+            .with_sdn_config(Arc::new(RwLock::new(sdn)))
+            .with_executor(executor.clone());
+
+        let mut interface = ContainerNetworkInterface::new("net1".to_string());
+        interface.vnet = Some("tenant".to_string());
+        binding_manager
+            .bind_vnet("tenant", 100, &interface)
+            .await
+            .unwrap();
+        executor.assert_commands(&["bridge link set dev veth100i1 isolated on"]);
+
+        let binding = binding_manager
+            .get_binding_metadata("tenant", 100, "net1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(binding.isolated);
+
+        executor.clear();
+        let mut interface = ContainerNetworkInterface::new("net0".to_string());
+        interface.vnet = Some("shared".to_string());
+        binding_manager
+            .bind_vnet("shared", 100, &interface)
+            .await
+            .unwrap();
+        executor.assert_commands(&[]);
+
+        let mut interface = ContainerNetworkInterface::new("net2".to_string());
+        interface.vnet = Some("missing".to_string());
+        assert!(matches!(
+            binding_manager.bind_vnet("missing", 100, &interface).await,
+            Err(ContainerError::VNetNotFound { .. })
+        ));
+    }
 }
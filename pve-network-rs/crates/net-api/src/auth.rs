@@ -0,0 +1,168 @@
+//! Request authentication
+//!
+//! Handlers acting on behalf of a user take the user from the request's
+//! credentials through the [`AuthenticatedUser`] extractor, never from the
+//! request body. Credentials are PVE API tokens
+//! (`Authorization: PVEAPIToken=USER@REALM!TOKENID=SECRET`), checked against
+//! the token secrets in pmxcfs.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap, StatusCode},
+};
+
+use crate::context::AppContext;
+
+/// Default location of the PVE API token secrets
+pub const DEFAULT_TOKEN_CFG_PATH: &str = "/etc/pve/priv/token.cfg";
+
+/// Prefix of API token credentials in the `Authorization` header
+const API_TOKEN_PREFIX: &str = "PVEAPIToken=";
+
+/// Resolves the user a request is made by
+pub trait Authenticator: Send + Sync {
+    /// Authenticated user ID (`user@realm` or `user@realm!token`)
+    fn authenticate(&self, headers: &HeaderMap) -> Result<String>;
+}
+
+/// Authenticates PVE API tokens against `token.cfg`
+///
+/// The file is read on every request, so revoked tokens stop working
+/// immediately.
+#[derive(Debug, Clone)]
+pub struct ApiTokenAuthenticator {
+    token_cfg: PathBuf,
+}
+
+impl ApiTokenAuthenticator {
+    /// Create authenticator using the default token secrets
+    pub fn new() -> Self {
+        Self::with_token_cfg(DEFAULT_TOKEN_CFG_PATH)
+    }
+
+    /// Create authenticator using the given token secrets
+    pub fn with_token_cfg<P: AsRef<Path>>(token_cfg: P) -> Self {
+        Self {
+            token_cfg: token_cfg.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl Default for ApiTokenAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Authenticator for ApiTokenAuthenticator {
+    fn authenticate(&self, headers: &HeaderMap) -> Result<String> {
+        let credentials = headers
+            .get(AUTHORIZATION)
+            .context("missing credentials")?
+            .to_str()
+            .context("invalid credentials")?;
+        let (tokenid, secret) = credentials
+            .strip_prefix(API_TOKEN_PREFIX)
+            .and_then(|token| token.split_once('='))
+            .context("unsupported credentials, expected a PVE API token")?;
+        if !tokenid.contains('!') {
+            bail!("invalid API token ID '{}'", tokenid);
+        }
+
+        let content = std::fs::read_to_string(&self.token_cfg)
+            .with_context(|| format!("failed to read {}", self.token_cfg.display()))?;
+        let valid = content.lines().any(|line| {
+            line.split_once(char::is_whitespace)
+                .is_some_and(|(id, value)| id == tokenid && constant_time_eq(value.trim(), secret))
+        });
+        if !valid {
+            bail!("invalid API token");
+        }
+
+        Ok(tokenid.to_string())
+    }
+}
+
+/// Compare secrets without leaking the position of the first difference
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// User the request was authenticated as
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthenticatedUser(pub String);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppContext>> for AuthenticatedUser {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        context: &Arc<AppContext>,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        context
+            .authenticator
+            .authenticate(&parts.headers)
+            .map(AuthenticatedUser)
+            .map_err(|e| {
+                (
+                    StatusCode::UNAUTHORIZED,
+                    format!("authentication failed: {}", e),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_api_token_authentication() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let token_cfg = temp_dir.path().join("token.cfg");
+        std::fs::write(
+            &token_cfg,
+            "alice@pve!ci 4d8c7a52-3c2b-4f7e-9c84-0e5b1c1f6a11\n\
+             bob@pve!ci 9f0e2a61-6d3b-4a8c-b1e7-5c2d8e4f7a22\n",
+        )
+        .unwrap();
+        let auth = ApiTokenAuthenticator::with_token_cfg(&token_cfg);
+
+        assert_eq!(
+            auth.authenticate(&headers(
+                "PVEAPIToken=alice@pve!ci=4d8c7a52-3c2b-4f7e-9c84-0e5b1c1f6a11"
+            ))
+            .unwrap(),
+            "alice@pve!ci"
+        );
+
+        // Another user's secret does not authenticate
+        assert!(auth
+            .authenticate(&headers(
+                "PVEAPIToken=alice@pve!ci=9f0e2a61-6d3b-4a8c-b1e7-5c2d8e4f7a22"
+            ))
+            .is_err());
+        assert!(auth
+            .authenticate(&headers("PVEAPIToken=alice@pve=anything"))
+            .is_err());
+        assert!(auth.authenticate(&headers("Bearer abc")).is_err());
+        assert!(auth.authenticate(&HeaderMap::new()).is_err());
+    }
+}
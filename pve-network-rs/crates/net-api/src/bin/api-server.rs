@@ -1,15 +1,18 @@
-//! Simple HTTP server for testing the network and SDN API
+//! HTTPS server of the network and SDN API
 
 use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
 use chrono::Utc;
 use serde_json::json;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 
 use pve_network_api::{
-    context::AppContext, ContainerNetworkAPI, NetworkAPI, StorageNetworkAPI, SDNAPI,
+    cluster::DEFAULT_PMXCFS_PATH,
+    context::AppContext,
+    server::{api_router, node_certificate_paths, serve_tls, tls_acceptor, DEFAULT_LISTEN_ADDR},
 };
 
 #[tokio::main]
@@ -20,12 +23,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize shared application context
     let context = AppContext::bootstrap().await?;
 
+    // Run scheduled maintenance window applies in the background
+    context
+        .apply_scheduler
+        .clone()
+        .spawn(std::time::Duration::from_secs(30));
+
     // Build the application router
     let app = Router::new()
-        .merge(NetworkAPI::router())
-        .merge(StorageNetworkAPI::router())
-        .merge(ContainerNetworkAPI::router())
-        .merge(SDNAPI::router())
+        .merge(api_router())
         .route("/", get(root))
         .route("/health", get(health_check))
         .route("/metrics/migration", get(migration_metrics))
@@ -36,14 +42,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .with_state(context.clone());
 
-    // Start the server
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    println!("Starting API server on http://{}", addr);
+    // Start the server; rolling applies need it reachable from the other nodes,
+    // which verify the node certificate against the cluster CA
+    let addr: SocketAddr = match std::env::var("PVE_NETWORK_API_LISTEN") {
+        Ok(listen) => listen.parse()?,
+        Err(_) => DEFAULT_LISTEN_ADDR,
+    };
+    let (cert, key) = node_certificate_paths(Path::new(DEFAULT_PMXCFS_PATH), &context.node);
+    let acceptor = tls_acceptor(&cert, &key).await?;
+    println!("Starting API server on https://{}", addr);
     println!("Available endpoints:");
     println!("  Network API:");
     println!("    GET /api2/json/nodes/{{node}}/network");
     println!("    GET /api2/json/nodes/{{node}}/network/{{iface}}");
     println!("    GET /api2/json/nodes/{{node}}/network/{{iface}}/status");
+    println!("    GET /api2/json/nodes/{{node}}/network/scheduled");
+    println!("    POST /api2/json/nodes/{{node}}/network/scheduled");
+    println!("    DELETE /api2/json/nodes/{{node}}/network/scheduled/{{id}}");
+    println!("    POST /api2/json/nodes/{{node}}/network/scheduled/{{id}}/confirm");
+    println!("    GET /api2/json/nodes/{{node}}/network/change-requests");
+    println!("    POST /api2/json/nodes/{{node}}/network/change-requests");
+    println!("    POST /api2/json/nodes/{{node}}/network/change-requests/{{id}}/approve");
+    println!("    POST /api2/json/nodes/{{node}}/network/change-requests/{{id}}/reject");
+    println!("    POST /api2/json/nodes/{{node}}/network/change-requests/{{id}}/apply");
+    println!("    GET /api2/json/nodes/{{node}}/network/running-config");
+    println!("    POST /api2/json/nodes/{{node}}/network/running-config");
+    println!("  Cluster API:");
+    println!("    POST /api2/json/cluster/network/rolling-apply");
     println!("  Storage API:");
     println!("    GET /api2/json/nodes/{{node}}/storage/network");
     println!("    POST /api2/json/nodes/{{node}}/storage/network/{{storage}}");
@@ -78,12 +103,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("    DELETE /api2/json/nodes/{{node}}/lxc/{{vmid}}/network/{{iface}}");
     println!("    POST /api2/json/nodes/{{node}}/lxc/{{vmid}}/network/{{iface}}/hotplug");
     println!("    GET /api2/json/nodes/{{node}}/lxc/{{vmid}}/network/stats");
+    println!("  Tasks API:");
+    println!("    GET /api2/json/nodes/{{node}}/tasks");
+    println!("    GET /api2/json/nodes/{{node}}/tasks/{{upid}}/status");
     println!("  Other:");
     println!("    GET /health");
     println!("    GET /metrics/migration");
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    serve_tls(listener, acceptor, app).await;
 
     Ok(())
 }
@@ -98,7 +126,21 @@ async fn root() -> Json<serde_json::Value> {
             "network": [
                 "GET /api2/json/nodes/{node}/network",
                 "GET /api2/json/nodes/{node}/network/{iface}",
-                "GET /api2/json/nodes/{node}/network/{iface}/status"
+                "GET /api2/json/nodes/{node}/network/{iface}/status",
+                "GET /api2/json/nodes/{node}/network/scheduled",
+                "POST /api2/json/nodes/{node}/network/scheduled",
+                "DELETE /api2/json/nodes/{node}/network/scheduled/{id}",
+                "POST /api2/json/nodes/{node}/network/scheduled/{id}/confirm",
+                "GET /api2/json/nodes/{node}/network/change-requests",
+                "POST /api2/json/nodes/{node}/network/change-requests",
+                "POST /api2/json/nodes/{node}/network/change-requests/{id}/approve",
+                "POST /api2/json/nodes/{node}/network/change-requests/{id}/reject",
+                "POST /api2/json/nodes/{node}/network/change-requests/{id}/apply",
+                "GET /api2/json/nodes/{node}/network/running-config",
+                "POST /api2/json/nodes/{node}/network/running-config"
+            ],
+            "cluster": [
+                "POST /api2/json/cluster/network/rolling-apply"
             ],
             "storage": [
                 "GET /api2/json/nodes/{node}/storage/network",
@@ -0,0 +1,154 @@
+//! Change request API endpoints
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+
+use pve_network_apply::ChangeRequest;
+use pve_network_core::{error::ApiError, NetworkConfiguration, NetworkError};
+
+use crate::{auth::AuthenticatedUser, context::AppContext};
+
+/// Request body for proposing a change
+#[derive(Debug, Deserialize)]
+pub struct ProposeChangeRequest {
+    /// Description of the change
+    pub description: String,
+    /// Proposed configuration
+    pub config: NetworkConfiguration,
+}
+
+/// Request body for approving or rejecting a change
+#[derive(Debug, Deserialize)]
+pub struct ChangeRequestAction {
+    /// Optional comment
+    pub comment: Option<String>,
+}
+
+/// Change request API
+pub struct ChangeRequestAPI;
+
+impl ChangeRequestAPI {
+    /// Create router with change request endpoints
+    pub fn router() -> Router<Arc<AppContext>> {
+        Router::new()
+            .route(
+                "/api2/json/nodes/:node/network/change-requests",
+                get(list_change_requests).post(propose_change_request),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/change-requests/:id",
+                get(get_change_request),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/change-requests/:id/approve",
+                post(approve_change_request),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/change-requests/:id/reject",
+                post(reject_change_request),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/change-requests/:id/apply",
+                post(apply_change_request),
+            )
+    }
+}
+
+/// Map change request errors to HTTP status codes
+fn change_request_error(e: NetworkError) -> (StatusCode, String) {
+    let status = match &e {
+        NetworkError::Api(ApiError::NotFound { .. }) => StatusCode::NOT_FOUND,
+        NetworkError::Api(ApiError::Conflict { .. }) => StatusCode::CONFLICT,
+        NetworkError::Api(ApiError::PermissionDenied { .. }) => StatusCode::FORBIDDEN,
+        NetworkError::Configuration(_) | NetworkError::Validation(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn list_change_requests(
+    State(context): State<Arc<AppContext>>,
+    Path(_node): Path<String>,
+) -> std::result::Result<Json<Vec<ChangeRequest>>, (StatusCode, String)> {
+    context
+        .change_requests
+        .list()
+        .await
+        .map(Json)
+        .map_err(change_request_error)
+}
+
+async fn propose_change_request(
+    State(context): State<Arc<AppContext>>,
+    Path(_node): Path<String>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+    Json(request): Json<ProposeChangeRequest>,
+) -> std::result::Result<Json<ChangeRequest>, (StatusCode, String)> {
+    context
+        .change_requests
+        .propose(&userid, &request.description, request.config)
+        .await
+        .map(Json)
+        .map_err(change_request_error)
+}
+
+async fn get_change_request(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+) -> std::result::Result<Json<ChangeRequest>, (StatusCode, String)> {
+    context
+        .change_requests
+        .get(&id)
+        .await
+        .map(Json)
+        .map_err(change_request_error)
+}
+
+async fn approve_change_request(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+    Json(action): Json<ChangeRequestAction>,
+) -> std::result::Result<Json<ChangeRequest>, (StatusCode, String)> {
+    context
+        .change_requests
+        .approve(&id, &userid, action.comment)
+        .await
+        .map(Json)
+        .map_err(change_request_error)
+}
+
+async fn reject_change_request(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+    Json(action): Json<ChangeRequestAction>,
+) -> std::result::Result<Json<ChangeRequest>, (StatusCode, String)> {
+    context
+        .change_requests
+        .reject(&id, &userid, action.comment)
+        .await
+        .map(Json)
+        .map_err(change_request_error)
+}
+
+async fn apply_change_request(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<ChangeRequest>, (StatusCode, String)> {
+    context
+        .change_requests
+        .apply(&id, &userid)
+        .await
+        .map(Json)
+        .map_err(change_request_error)
+}
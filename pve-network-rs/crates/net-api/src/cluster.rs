@@ -0,0 +1,519 @@
+//! Cluster-wide network operations
+//!
+//! Rolling applies are started on one node and reach the other cluster nodes
+//! through their network API. Every node exposes its running configuration
+//! for that; the caller's credentials are forwarded, so each node checks the
+//! caller's privileges itself. Node requests only go over HTTPS, with the
+//! node certificates verified against the cluster CA in pmxcfs, so the
+//! forwarded credentials never cross the network in cleartext.
+
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use pve_network_apply::{
+    change_request::MODIFY_PRIVILEGE, ApplyResult, NodeTransport, RollingApplyOptions,
+    RollingApplyOrchestrator, TransactionState,
+};
+use pve_network_core::{
+    error::{ApiError, ConfigError},
+    NetworkConfiguration, NetworkError, Result,
+};
+
+use crate::{auth::AuthenticatedUser, context::AppContext};
+
+/// Default pmxcfs mount point, holding the cluster member list
+pub const DEFAULT_PMXCFS_PATH: &str = "/etc/pve";
+
+/// Default port of the network API on the cluster nodes
+pub const DEFAULT_API_PORT: u16 = 3000;
+
+/// Cluster CA certificate in pmxcfs, signing the node certificates
+pub const CLUSTER_CA_FILE: &str = "pve-root-ca.pem";
+
+/// Timeout of a single node request; applies include the ifreload run
+const NODE_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Request body for a rolling apply
+#[derive(Debug, Deserialize)]
+pub struct RollingApplyRequest {
+    /// Nodes to update, in rollout order
+    pub nodes: Vec<String>,
+    /// Configuration to apply on every node
+    pub config: NetworkConfiguration,
+    /// Number of nodes updated at the same time
+    pub batch_size: Option<usize>,
+    /// Maximum time in seconds to wait for a node to report healthy
+    pub probe_timeout: Option<u64>,
+}
+
+/// Reaches cluster nodes through their network API
+///
+/// Node addresses come from the pmxcfs member list (`.members`), the nodes
+/// are trusted through the cluster CA next to it.
+#[derive(Clone)]
+pub struct HttpNodeTransport {
+    pmxcfs_path: PathBuf,
+    port: u16,
+    authorization: Option<String>,
+}
+
+impl HttpNodeTransport {
+    /// Create transport using the default pmxcfs path and API port
+    pub fn new() -> Self {
+        Self {
+            pmxcfs_path: PathBuf::from(DEFAULT_PMXCFS_PATH),
+            port: DEFAULT_API_PORT,
+            authorization: None,
+        }
+    }
+
+    /// Override the pmxcfs mount point
+    pub fn with_pmxcfs_path<P: AsRef<FsPath>>(mut self, path: P) -> Self {
+        self.pmxcfs_path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Override the API port of the nodes
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Send the given `Authorization` header with every request
+    pub fn with_authorization(mut self, authorization: &str) -> Self {
+        self.authorization = Some(authorization.to_string());
+        self
+    }
+
+    /// Base URL of the network API of `node`
+    pub async fn node_url(&self, node: &str) -> Result<String> {
+        let path = self.pmxcfs_path.join(".members");
+        let content = tokio::fs::read(&path).await?;
+        let members: Value = serde_json::from_slice(&content)?;
+
+        let ip = members
+            .get("nodelist")
+            .and_then(|nodes| nodes.get(node))
+            .and_then(|member| member.get("ip"))
+            .and_then(|ip| ip.as_str())
+            .and_then(|ip| ip.parse::<std::net::IpAddr>().ok())
+            .ok_or_else(|| {
+                NetworkError::Api(ApiError::NotFound {
+                    resource: format!("cluster address of node {}", node),
+                })
+            })?;
+
+        Ok(match ip {
+            std::net::IpAddr::V4(ip) => format!("https://{}:{}", ip, self.port),
+            std::net::IpAddr::V6(ip) => format!("https://[{}]:{}", ip, self.port),
+        })
+    }
+
+    /// HTTPS client trusting only the cluster CA
+    ///
+    /// Without the CA no client is built, so credentials are never sent to
+    /// a node that cannot be verified.
+    async fn client(&self) -> Result<reqwest::Client> {
+        let path = self.pmxcfs_path.join(CLUSTER_CA_FILE);
+        let pem = tokio::fs::read(&path).await.map_err(|e| {
+            NetworkError::Io(std::io::Error::new(
+                e.kind(),
+                format!("cluster CA {} not readable: {}", path.display(), e),
+            ))
+        })?;
+        let ca = reqwest::Certificate::from_pem(&pem).map_err(|e| {
+            NetworkError::Configuration(ConfigError::InvalidValue {
+                field: "cluster CA".to_string(),
+                value: format!("{}: {}", path.display(), e),
+            })
+        })?;
+
+        reqwest::Client::builder()
+            .timeout(NODE_REQUEST_TIMEOUT)
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(ca)
+            .https_only(true)
+            .build()
+            .map_err(|e| {
+                NetworkError::Io(std::io::Error::other(format!(
+                    "failed to create HTTPS client: {}",
+                    e
+                )))
+            })
+    }
+
+    /// Send a request to `node` and return the `data` of the response
+    async fn request(
+        &self,
+        node: &str,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&NetworkConfiguration>,
+    ) -> Result<Value> {
+        let url = format!("{}{}", self.node_url(node).await?, path);
+        let mut request = self.client().await?.request(method, &url);
+        if let Some(ref authorization) = self.authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response = request.send().await.map_err(|e| send_error(node, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(request_error(node, format!("{} {}", status, message)));
+        }
+
+        let mut value: Value = response.json().await.map_err(|e| request_error(node, e))?;
+        Ok(value
+            .get_mut("data")
+            .map(Value::take)
+            .unwrap_or(Value::Null))
+    }
+}
+
+impl Default for HttpNodeTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn request_error(node: &str, error: impl std::fmt::Display) -> NetworkError {
+    NetworkError::Io(std::io::Error::other(format!(
+        "request to node {} failed: {}",
+        node, error
+    )))
+}
+
+/// Map a request to `node` that got no response
+///
+/// Connection failures and timeouts keep the kind of their I/O error, so
+/// they can be told apart from TLS and other failures.
+fn send_error(node: &str, error: reqwest::Error) -> NetworkError {
+    let kind = if error.is_timeout() {
+        std::io::ErrorKind::TimedOut
+    } else if error.is_connect() {
+        let mut source = std::error::Error::source(&error);
+        let mut kind = std::io::ErrorKind::Other;
+        while let Some(e) = source {
+            if let Some(io) = e.downcast_ref::<std::io::Error>() {
+                kind = io.kind();
+                break;
+            }
+            source = e.source();
+        }
+        kind
+    } else {
+        std::io::ErrorKind::Other
+    };
+
+    NetworkError::Io(std::io::Error::new(
+        kind,
+        format!("request to node {} failed: {}", node, error),
+    ))
+}
+
+/// Whether an I/O error means the node does not answer (yet)
+fn is_unreachable(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    matches!(
+        error.kind(),
+        ConnectionRefused
+            | ConnectionReset
+            | ConnectionAborted
+            | NotConnected
+            | TimedOut
+            | HostUnreachable
+            | NetworkUnreachable
+    )
+}
+
+#[async_trait]
+impl NodeTransport for HttpNodeTransport {
+    async fn current_config(&self, node: &str) -> Result<NetworkConfiguration> {
+        let path = format!("/api2/json/nodes/{}/network/running-config", node);
+        let data = self
+            .request(node, reqwest::Method::GET, &path, None)
+            .await?;
+        Ok(serde_json::from_value(data)?)
+    }
+
+    async fn apply_config(&self, node: &str, config: &NetworkConfiguration) -> Result<ApplyResult> {
+        let path = format!("/api2/json/nodes/{}/network/running-config", node);
+        let data = self
+            .request(node, reqwest::Method::POST, &path, Some(config))
+            .await?;
+        Ok(serde_json::from_value(data)?)
+    }
+
+    /// The node is healthy once its API answers over the applied network
+    /// and reports the transaction as committed
+    async fn health_probe(&self, node: &str, transaction_id: &str) -> Result<bool> {
+        let path = format!(
+            "/api2/json/nodes/{}/network/transactions/{}",
+            node, transaction_id
+        );
+        let data = match self.request(node, reqwest::Method::GET, &path, None).await {
+            Ok(data) => data,
+            // Unreachable or not yet answering, probed again until the timeout
+            Err(NetworkError::Io(e)) if is_unreachable(&e) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let state: Option<TransactionState> = serde_json::from_value(data["state"].clone())?;
+        Ok(state == Some(TransactionState::Committed))
+    }
+}
+
+/// Cluster network API
+pub struct ClusterNetworkAPI;
+
+impl ClusterNetworkAPI {
+    /// Create router with cluster endpoints and the node endpoints they use
+    pub fn router() -> Router<Arc<AppContext>> {
+        Router::new()
+            .route(
+                "/api2/json/nodes/:node/network/running-config",
+                get(get_running_config).post(apply_running_config),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/transactions/:id",
+                get(get_transaction_state),
+            )
+            .route(
+                "/api2/json/cluster/network/rolling-apply",
+                post(start_rolling_apply),
+            )
+    }
+}
+
+/// Privilege required to read task and status information
+pub(crate) const AUDIT_PRIVILEGE: &str = "Sys.Audit";
+
+/// Require `privilege` of the authenticated user on `/nodes/<node>`
+pub(crate) fn ensure_privilege(
+    context: &AppContext,
+    user: &str,
+    privilege: &str,
+    operation: &str,
+) -> Result<()> {
+    if context.privileges.has_privilege(user, privilege) {
+        Ok(())
+    } else {
+        Err(NetworkError::Api(ApiError::PermissionDenied {
+            operation: format!("{} requires {}", operation, privilege),
+        }))
+    }
+}
+
+fn cluster_error(e: NetworkError) -> (StatusCode, String) {
+    let status = match &e {
+        NetworkError::Api(ApiError::NotFound { .. }) => StatusCode::NOT_FOUND,
+        NetworkError::Api(ApiError::PermissionDenied { .. }) => StatusCode::FORBIDDEN,
+        NetworkError::Configuration(_) | NetworkError::Validation(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+/// Running network configuration of this node
+async fn get_running_config(
+    State(context): State<Arc<AppContext>>,
+    Path(_node): Path<String>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<Value>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "reading the running configuration",
+    )
+    .map_err(cluster_error)?;
+    let config = context
+        .network_applier
+        .current_configuration()
+        .await
+        .map_err(cluster_error)?;
+
+    Ok(Json(serde_json::json!({ "data": config })))
+}
+
+/// Apply a configuration on this node, used by rolling applies
+async fn apply_running_config(
+    State(context): State<Arc<AppContext>>,
+    Path(_node): Path<String>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+    Json(config): Json<NetworkConfiguration>,
+) -> std::result::Result<Json<Value>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "applying a configuration",
+    )
+    .map_err(cluster_error)?;
+    let result = context
+        .network_applier
+        .apply_configuration(&config)
+        .await
+        .map_err(cluster_error)?;
+
+    Ok(Json(serde_json::json!({ "data": result })))
+}
+
+/// State of a transaction on this node, used to probe rolling applies
+async fn get_transaction_state(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<Value>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "reading transaction state",
+    )
+    .map_err(cluster_error)?;
+    let state = context
+        .network_applier
+        .transaction_state(&id)
+        .await
+        .map_err(cluster_error)?
+        .ok_or_else(|| {
+            cluster_error(NetworkError::Api(ApiError::NotFound {
+                resource: format!("transaction {}", id),
+            }))
+        })?;
+
+    Ok(Json(
+        serde_json::json!({ "data": { "id": id, "state": state } }),
+    ))
+}
+
+/// Start a rolling apply across the given nodes and return its task UPID
+async fn start_rolling_apply(
+    State(context): State<Arc<AppContext>>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+    headers: HeaderMap,
+    Json(request): Json<RollingApplyRequest>,
+) -> std::result::Result<Json<Value>, (StatusCode, String)> {
+    ensure_privilege(&context, &userid, MODIFY_PRIVILEGE, "rolling apply")
+        .map_err(cluster_error)?;
+
+    let mut options = RollingApplyOptions::default();
+    if let Some(batch_size) = request.batch_size {
+        options.batch_size = batch_size;
+    }
+    if let Some(probe_timeout) = request.probe_timeout {
+        options.probe_timeout = Duration::from_secs(probe_timeout);
+    }
+
+    // Forward the caller's credentials to the other nodes
+    let mut transport = context.node_transport.clone();
+    if let Some(authorization) = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    {
+        transport = transport.with_authorization(authorization);
+    }
+
+    let orchestrator = Arc::new(RollingApplyOrchestrator::new(Arc::new(transport), options));
+    let upid = context
+        .task_manager
+        .spawn_rolling_apply(orchestrator, request.nodes, request.config, &userid)
+        .await;
+
+    Ok(Json(serde_json::json!({ "data": upid })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_node_url_from_members() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".members"),
+            r#"{"nodename":"node1","version":3,"cluster":{"name":"c","nodes":2,"quorate":1},
+"nodelist":{"node1":{"id":1,"online":1,"ip":"192.0.2.1"},
+"node2":{"id":2,"online":1,"ip":"2001:db8::2"}}}"#,
+        )
+        .unwrap();
+
+        let transport = HttpNodeTransport::new()
+            .with_pmxcfs_path(temp_dir.path())
+            .with_port(8000);
+        assert_eq!(
+            transport.node_url("node1").await.unwrap(),
+            "https://192.0.2.1:8000"
+        );
+        assert_eq!(
+            transport.node_url("node2").await.unwrap(),
+            "https://[2001:db8::2]:8000"
+        );
+        assert!(matches!(
+            transport.node_url("node3").await,
+            Err(NetworkError::Api(ApiError::NotFound { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_no_request_without_cluster_ca() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".members"),
+            r#"{"nodelist":{"node1":{"id":1,"online":1,"ip":"127.0.0.1"}}}"#,
+        )
+        .unwrap();
+
+        // Credentials are not sent when the node cannot be verified
+        let transport = HttpNodeTransport::new()
+            .with_pmxcfs_path(temp_dir.path())
+            .with_port(1)
+            .with_authorization("PVEAPIToken=root@pam!ci=secret");
+        let error = transport.current_config("node1").await.unwrap_err();
+        assert!(error.to_string().contains("pve-root-ca.pem"));
+        // A missing CA is an error, not a node that is still coming up
+        assert!(transport.health_probe("node1", "txn_0").await.is_err());
+
+        std::fs::write(temp_dir.path().join(CLUSTER_CA_FILE), "not a certificate").unwrap();
+        assert!(transport.current_config("node1").await.is_err());
+        assert!(transport.health_probe("node1", "txn_0").await.is_err());
+    }
+
+    #[test]
+    fn test_unreachable_errors() {
+        use std::io::{Error, ErrorKind};
+        assert!(is_unreachable(&Error::from(ErrorKind::ConnectionRefused)));
+        assert!(is_unreachable(&Error::from(ErrorKind::TimedOut)));
+        assert!(!is_unreachable(&Error::from(ErrorKind::NotFound)));
+        assert!(!is_unreachable(&Error::other("invalid certificate")));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_state_endpoint() {
+        let context = AppContext::bootstrap().await.unwrap();
+        let result = get_transaction_state(
+            State(context),
+            Path(("node1".to_string(), "txn_0".to_string())),
+            AuthenticatedUser("root@pam".to_string()),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+}
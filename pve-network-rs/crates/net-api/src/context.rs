@@ -1,14 +1,19 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
 use container_integration::ContainerIntegration;
 use net_migration::hooks::{MigrationEventLogger, MigrationHooks};
 use pve_event_bus::EventBus;
-use pve_network_apply::{ifupdown::IfUpDownIntegration, rollback::RollbackManager, NetworkApplier};
+use pve_network_apply::{
+    ifupdown::IfUpDownIntegration, rollback::RollbackManager, AclPrivileges, ApplyScheduler,
+    ChangeRequestManager, NetworkApplier, PrivilegeProvider,
+};
 use pve_network_config::{NetworkConfigManager, PmxcfsConfig};
+use pve_network_core::topology::NetworkTopology;
 use pve_network_validate::NetworkValidator;
-use pve_shared_types::MigrationPhase;
+use pve_sdn_drivers::{DhcpService, SdnApplyPipeline, DEFAULT_PLUGIN_DIR};
+use pve_shared_types::{MigrationPhase, SdnConfiguration};
 use storage_integration::{
     future_integration::{DefaultFutureStorageIntegration, FutureStorageIntegration},
     hooks::{StorageEventLogger, StorageHooks, StorageStatusRefresher, StorageVlanReconciler},
@@ -16,11 +21,21 @@ use storage_integration::{
     storage_network::{DefaultStorageNetworkManager, NetworkConfigTrait},
     StorageNetworkManager, StorageVlanManager,
 };
-use crate::{container::ContainerNetworkAPI, sdn::SdnApiState, NetworkAPI, StorageNetworkAPI};
 use tokio::sync::RwLock;
 
+use crate::{
+    auth::{ApiTokenAuthenticator, Authenticator},
+    cluster::HttpNodeTransport,
+    container::ContainerNetworkAPI,
+    sdn::SdnApiState,
+    tasks::TaskManager,
+    NetworkAPI, StorageNetworkAPI,
+};
+
 #[derive(Clone)]
 pub struct AppContext {
+    /// Name of this node
+    pub node: String,
     pub event_bus: Arc<EventBus>,
     pub network_api: Arc<NetworkAPI>,
     pub container_integration: Arc<ContainerIntegration>,
@@ -33,7 +48,13 @@ pub struct AppContext {
     pub migration_hooks: Arc<MigrationHooks>,
     pub future_storage_integration: Arc<dyn FutureStorageIntegration + Send + Sync>,
     pub network_applier: Arc<NetworkApplier>,
+    pub apply_scheduler: Arc<ApplyScheduler>,
+    pub change_requests: Arc<ChangeRequestManager>,
     pub sdn_state: Arc<SdnApiState>,
+    pub task_manager: Arc<TaskManager>,
+    pub authenticator: Arc<dyn Authenticator>,
+    pub privileges: Arc<dyn PrivilegeProvider>,
+    pub node_transport: HttpNodeTransport,
 }
 
 impl AppContext {
@@ -63,11 +84,42 @@ impl AppContext {
         .with_event_bus(event_bus.clone());
         let network_applier = Arc::new(network_applier);
 
+        let apply_scheduler = Arc::new(
+            ApplyScheduler::new(network_applier.clone())
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?,
+        );
+
+        let privileges: Arc<dyn PrivilegeProvider> = Arc::new(AclPrivileges::new(&format!(
+            "/nodes/{}",
+            config_manager.current_node()
+        )));
+        let change_requests = Arc::new(
+            ChangeRequestManager::new(network_applier.clone(), privileges.clone())
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?,
+        );
+
         let network_api = Arc::new(NetworkAPI::with_shared_config_manager(
             config_manager.clone(),
         ));
 
-        let container_integration = Arc::new(ContainerIntegration::new());
+        let sdn_state = Arc::new(
+            SdnApiState::new()
+                .with_apply_pipeline(SdnApplyPipeline::new(config_manager.current_node()))
+                .with_dhcp_service(DhcpService::new(config_manager.current_node())),
+        );
+
+        // Drivers of installed plugins serve the API and the apply pipeline
+        sdn_state
+            .plugin_factory
+            .load_plugin_dir(Path::new(DEFAULT_PLUGIN_DIR))
+            .await?;
+
+        // Container bindings apply the options of the SDN vnets
+        let container_integration = Arc::new(ContainerIntegration::with_sdn_config(
+            sdn_state.config.clone(),
+        ));
         container_integration
             .bind_event_bus(event_bus.clone())
             .await
@@ -130,9 +182,10 @@ impl AppContext {
             .await
             .map_err(|err| anyhow::anyhow!(err))?;
 
-        let sdn_state = Arc::new(SdnApiState::new());
+        let task_manager = Arc::new(TaskManager::with_node(config_manager.current_node()));
 
         Ok(Arc::new(Self {
+            node: config_manager.current_node().to_string(),
             event_bus,
             network_api,
             container_integration,
@@ -145,7 +198,61 @@ impl AppContext {
             migration_hooks,
             future_storage_integration: future_integration,
             network_applier,
+            apply_scheduler,
+            change_requests,
             sdn_state,
+            task_manager,
+            authenticator: Arc::new(ApiTokenAuthenticator::new()),
+            privileges,
+            node_transport: HttpNodeTransport::new(),
         }))
     }
+
+    /// Topology graph of the interfaces, SDN objects, container attachments
+    /// and storage VLANs of `node`
+    pub async fn network_topology(&self, node: &str) -> Result<NetworkTopology> {
+        let sdn = self.sdn_state.config.read().await.clone();
+        self.topology_with_sdn(node, sdn).await
+    }
+
+    /// Topology graph of this node with the running SDN configuration
+    ///
+    /// Processes without the daemon's state, like the CLI, see the
+    /// configuration published to pmxcfs by the last reload.
+    pub async fn running_network_topology(&self) -> Result<NetworkTopology> {
+        let sdn = self.sdn_state.running_config().await?;
+        let node = self.sdn_state.apply_pipeline.node().to_string();
+        self.topology_with_sdn(&node, sdn).await
+    }
+
+    async fn topology_with_sdn(
+        &self,
+        node: &str,
+        mut sdn: SdnConfiguration,
+    ) -> Result<NetworkTopology> {
+        // Zones restricted to other nodes have no devices here
+        sdn.zones.retain(|_, zone| {
+            zone.nodes
+                .as_ref()
+                .map(|nodes| nodes.iter().any(|n| n == node))
+                .unwrap_or(true)
+        });
+        let zones = &sdn.zones;
+        sdn.vnets.retain(|_, vnet| zones.contains_key(&vnet.zone));
+
+        let network = self.network_api.network_config().await?;
+        let bindings = self
+            .container_integration
+            .vnet_binding()
+            .list_bindings()
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+        let storage_vlans = self.storage_vlan_manager.read().await.configured_vlans();
+
+        Ok(NetworkTopology::new()
+            .with_network(&network)
+            .with_sdn(&sdn)
+            .with_vnet_bindings(&bindings)
+            .with_storage_vlans(&storage_vlans))
+    }
 }
@@ -2,19 +2,26 @@
 //!
 //! REST API endpoints for network management
 
+pub mod auth;
+pub mod change_request;
+pub mod cluster;
 pub mod container;
 pub mod context;
 pub mod migration;
 pub mod network;
 pub mod sdn;
+pub mod server;
 pub mod storage;
 pub mod tasks;
 
 #[cfg(test)]
 mod tests;
 
+pub use change_request::ChangeRequestAPI;
+pub use cluster::ClusterNetworkAPI;
 pub use container::ContainerNetworkAPI;
 pub use migration::NetApiRustHandler;
 pub use network::NetworkAPI;
 pub use sdn::SDNAPI;
 pub use storage::StorageNetworkAPI;
+pub use tasks::TaskManager;
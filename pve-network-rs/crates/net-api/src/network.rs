@@ -13,11 +13,15 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use pve_network_apply::{change_request::MODIFY_PRIVILEGE, ScheduledApply};
 use pve_network_config::{InterfaceConfig, InterfacesParser, NetworkConfigManager};
-use pve_network_core::{AddressMethod, Interface, InterfaceType, NetworkError, Result};
+use pve_network_core::{
+    error::ApiError, topology::NetworkTopology, AddressMethod, Interface, InterfaceType,
+    NetworkConfiguration, NetworkError, Result,
+};
 use pve_shared_types::{BondMode, IpAddress};
 
-use crate::context::AppContext;
+use crate::{auth::AuthenticatedUser, cluster::ensure_privilege, context::AppContext};
 
 /// Query parameters for network interface listing
 #[derive(Debug, Deserialize)]
@@ -29,6 +33,21 @@ pub struct NetworkListQuery {
     pub enabled: Option<bool>,
 }
 
+/// Request body for scheduling a maintenance window apply
+#[derive(Debug, Deserialize)]
+pub struct ScheduleApplyRequest {
+    /// Configuration to apply
+    pub config: NetworkConfiguration,
+    /// Execution time (unix epoch)
+    pub execute_at: u64,
+    /// Drop the job if it could not run before this time (unix epoch)
+    pub expires_at: Option<u64>,
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    pub confirm_timeout: Option<u64>,
+    /// Optional comment
+    pub comment: Option<String>,
+}
+
 /// Query parameters for specific interface
 #[derive(Debug, Deserialize)]
 pub struct NetworkGetQuery {
@@ -232,6 +251,19 @@ impl NetworkAPI {
                 "/api2/json/nodes/:node/network/reload",
                 post(reload_network),
             )
+            .route("/api2/json/nodes/:node/network/topology", get(get_topology))
+            .route(
+                "/api2/json/nodes/:node/network/scheduled",
+                get(list_scheduled_applies).post(schedule_apply),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/scheduled/:id",
+                get(get_scheduled_apply).delete(cancel_scheduled_apply),
+            )
+            .route(
+                "/api2/json/nodes/:node/network/scheduled/:id/confirm",
+                post(confirm_scheduled_apply),
+            )
     }
 
     /// List all network interfaces
@@ -455,6 +487,11 @@ impl NetworkAPI {
         })
     }
 
+    /// Current network configuration
+    pub async fn network_config(&self) -> Result<NetworkConfiguration> {
+        self.config_manager.load_network_config().await
+    }
+
     /// Reload network configuration
     pub async fn reload_network(&self, node: &str) -> Result<NetworkOperationResponse> {
         log::debug!("Reloading network configuration for node: {}", node);
@@ -1164,3 +1201,137 @@ async fn reload_network(
         }
     }
 }
+
+/// Axum handler for the network topology graph
+pub(crate) async fn get_topology(
+    State(context): State<Arc<AppContext>>,
+    Path(node): Path<String>,
+) -> std::result::Result<Json<NetworkTopology>, (StatusCode, String)> {
+    match context.network_topology(&node).await {
+        Ok(topology) => Ok(Json(topology)),
+        Err(e) => {
+            log::error!("Failed to build network topology for node {}: {}", node, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// Map scheduler errors to HTTP status codes
+fn scheduled_apply_error(e: NetworkError) -> (StatusCode, String) {
+    let status = match &e {
+        NetworkError::Api(ApiError::NotFound { .. }) => StatusCode::NOT_FOUND,
+        NetworkError::Api(ApiError::Conflict { .. }) => StatusCode::CONFLICT,
+        NetworkError::Api(ApiError::PermissionDenied { .. }) => StatusCode::FORBIDDEN,
+        NetworkError::Configuration(_) | NetworkError::Validation(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn list_scheduled_applies(
+    State(context): State<Arc<AppContext>>,
+    Path(_node): Path<String>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<Vec<ScheduledApply>>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "listing scheduled applies",
+    )
+    .map_err(scheduled_apply_error)?;
+    context
+        .apply_scheduler
+        .list()
+        .await
+        .map(Json)
+        .map_err(scheduled_apply_error)
+}
+
+pub(crate) async fn schedule_apply(
+    State(context): State<Arc<AppContext>>,
+    Path(node): Path<String>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+    Json(request): Json<ScheduleApplyRequest>,
+) -> std::result::Result<Json<ScheduledApply>, (StatusCode, String)> {
+    ensure_privilege(&context, &userid, MODIFY_PRIVILEGE, "scheduling an apply")
+        .map_err(scheduled_apply_error)?;
+    log::info!(
+        "Scheduling network apply for node {} at {}",
+        node,
+        request.execute_at
+    );
+
+    context
+        .apply_scheduler
+        .schedule(
+            request.config,
+            request.execute_at,
+            request.expires_at,
+            request.confirm_timeout.unwrap_or(0),
+            request.comment,
+        )
+        .await
+        .map(Json)
+        .map_err(scheduled_apply_error)
+}
+
+async fn get_scheduled_apply(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<ScheduledApply>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "reading a scheduled apply",
+    )
+    .map_err(scheduled_apply_error)?;
+    context
+        .apply_scheduler
+        .get(&id)
+        .await
+        .map(Json)
+        .map_err(scheduled_apply_error)
+}
+
+async fn cancel_scheduled_apply(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<ScheduledApply>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "cancelling a scheduled apply",
+    )
+    .map_err(scheduled_apply_error)?;
+    context
+        .apply_scheduler
+        .cancel(&id)
+        .await
+        .map(Json)
+        .map_err(scheduled_apply_error)
+}
+
+async fn confirm_scheduled_apply(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, id)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<ScheduledApply>, (StatusCode, String)> {
+    ensure_privilege(
+        &context,
+        &userid,
+        MODIFY_PRIVILEGE,
+        "confirming a scheduled apply",
+    )
+    .map_err(scheduled_apply_error)?;
+    context
+        .apply_scheduler
+        .confirm(&id)
+        .await
+        .map(Json)
+        .map_err(scheduled_apply_error)
+}
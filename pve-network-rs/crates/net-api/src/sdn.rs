@@ -13,9 +13,17 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::context::AppContext;
+use pve_sdn_core::controller::{
+    BgpPeerStatus, ControllerConfig, ControllerStatus, EvpnMacEntry, EvpnVniStatus,
+};
 use pve_sdn_core::{
-    IpAllocation, IpAllocationRequest, IpamConfig, IpamManager, IpamType, SdnConfiguration,
-    SubnetConfig, VNetConfig, ZoneConfig,
+    aggregate_status, ClusterSdnStatus, Controller, DriverRegistry, FirewallRule, IpAllocation,
+    IpAllocationRequest, IpamConfig, IpamManager, IpamType, NodeSdnStatus, RealExecutor,
+    SdnConfiguration, SdnStatusStore, SubnetConfig, SubnetDns, SystemExecutor, VNetConfig,
+    VNetFirewall, ZoneConfig, ZoneType,
+};
+use pve_sdn_drivers::{
+    zone_vnis, DhcpService, EvpnController, PluginFactory, SdnApplyPipeline, SdnApplyResult,
 };
 use std::net::IpAddr;
 
@@ -24,17 +32,495 @@ use std::net::IpAddr;
 pub struct SdnApiState {
     pub config: Arc<RwLock<SdnConfiguration>>,
     pub ipam_manager: Arc<RwLock<IpamManager>>,
+    pub apply_pipeline: Arc<SdnApplyPipeline>,
+    /// Executor used to query runtime status from FRR
+    pub executor: Arc<dyn SystemExecutor>,
+    /// DHCP instances serving the zones of this node
+    pub dhcp: Arc<DhcpService>,
+    /// Drivers validating controllers and reporting their status
+    pub plugin_factory: Arc<PluginFactory>,
+    /// Node status files shared through pmxcfs
+    pub status_store: Arc<SdnStatusStore>,
 }
 
 impl SdnApiState {
     pub fn new() -> Self {
+        let plugin_factory = Arc::new(PluginFactory::new());
         Self {
             config: Arc::new(RwLock::new(SdnConfiguration::new())),
             ipam_manager: Arc::new(RwLock::new(IpamManager::new())),
+            apply_pipeline: Arc::new(
+                SdnApplyPipeline::new("localhost").with_factory(plugin_factory.clone()),
+            ),
+            executor: Arc::new(RealExecutor::new()),
+            dhcp: Arc::new(DhcpService::new("localhost")),
+            plugin_factory,
+            status_store: Arc::new(SdnStatusStore::new()),
+        }
+    }
+
+    /// Use a specific apply pipeline for `/sdn/reload`
+    ///
+    /// The pipeline and the API share one plugin factory, the pipeline's
+    /// if it has one.
+    pub fn with_apply_pipeline(mut self, pipeline: SdnApplyPipeline) -> Self {
+        let pipeline = match pipeline.plugin_factory() {
+            Some(factory) => {
+                self.plugin_factory = factory.clone();
+                pipeline
+            }
+            None => pipeline.with_factory(self.plugin_factory.clone()),
+        };
+        self.apply_pipeline = Arc::new(pipeline);
+        self
+    }
+
+    /// Query runtime status through `executor`
+    pub fn with_executor(mut self, executor: Arc<dyn SystemExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Use specific drivers for controller validation, status and the
+    /// apply pipeline
+    pub fn with_plugin_factory(mut self, factory: PluginFactory) -> Self {
+        self.plugin_factory = Arc::new(factory);
+        self.apply_pipeline = Arc::new(
+            SdnApplyPipeline::clone(&self.apply_pipeline).with_factory(self.plugin_factory.clone()),
+        );
+        self
+    }
+
+    /// Use a specific DHCP service for `/sdn/reload` and lease updates
+    pub fn with_dhcp_service(mut self, dhcp: DhcpService) -> Self {
+        self.dhcp = Arc::new(dhcp);
+        self
+    }
+
+    /// Use a specific location for the node status files
+    pub fn with_status_store(mut self, store: SdnStatusStore) -> Self {
+        self.status_store = Arc::new(store);
+        self
+    }
+
+    /// Register the DNS plugins of `config` with the IPAM manager
+    ///
+    /// Allocations in subnets with DNS settings register their records
+    /// through these, so they follow every configuration change.
+    async fn sync_dns_plugins(&self, config: &SdnConfiguration) -> anyhow::Result<()> {
+        let plugins = self.plugin_factory.create_dns_plugins(config)?;
+        let mut ipam_manager = self.ipam_manager.write().await;
+        ipam_manager.set_dns_plugins(plugins);
+        ipam_manager.load_subnet_dns(config);
+        Ok(())
+    }
+
+    /// Push the IPAM allocations of `subnet` to its DHCP instance
+    ///
+    /// The allocation itself already succeeded, so failures are only logged
+    /// and picked up again by the next reload.
+    async fn sync_dhcp_leases(
+        &self,
+        config: &SdnConfiguration,
+        ipam_manager: &IpamManager,
+        subnet: &str,
+    ) {
+        if let Err(e) = self.dhcp.sync_subnet(config, ipam_manager, subnet).await {
+            log::warn!("Failed to sync DHCP leases of subnet '{}': {:#}", subnet, e);
+        }
+    }
+
+    /// Validate and add a new zone
+    pub async fn create_zone(
+        &self,
+        zone_config: ZoneConfig,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        self.plugin_factory
+            .check_zone_type(&zone_config.zone_type)
+            .map_err(bad_request)?;
+
+        let mut config = self.config.write().await;
+
+        let mut candidate = config.clone();
+        candidate.add_zone(zone_config).map_err(bad_request)?;
+        ensure_unique_vnis(&candidate)?;
+
+        *config = candidate;
+        Ok(())
+    }
+
+    /// Validate and replace an existing zone
+    pub async fn update_zone(
+        &self,
+        name: &str,
+        mut zone_config: ZoneConfig,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut config = self.config.write().await;
+
+        // Ensure zone name matches path
+        zone_config.zone = name.to_string();
+
+        if !config.zones.contains_key(name) {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("Zone '{}' not found", name),
+                }),
+            ));
+        }
+
+        self.plugin_factory
+            .check_zone_type(&zone_config.zone_type)
+            .map_err(bad_request)?;
+        zone_config.validate().map_err(bad_request)?;
+        for vnet in config.vnets.values().filter(|vnet| vnet.zone == name) {
+            vnet.validate_zone(&zone_config).map_err(bad_request)?;
+        }
+
+        let mut candidate = config.clone();
+        candidate.zones.insert(name.to_string(), zone_config);
+        ensure_unique_vnis(&candidate)?;
+
+        *config = candidate;
+        Ok(())
+    }
+
+    /// Validate and add a new VNet
+    pub async fn create_vnet(
+        &self,
+        vnet_config: VNetConfig,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut config = self.config.write().await;
+
+        let mut candidate = config.clone();
+        candidate.add_vnet(vnet_config).map_err(bad_request)?;
+        ensure_unique_vnis(&candidate)?;
+
+        *config = candidate;
+        Ok(())
+    }
+
+    /// Validate and replace an existing VNet
+    pub async fn update_vnet(
+        &self,
+        name: &str,
+        mut vnet_config: VNetConfig,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut config = self.config.write().await;
+
+        // Ensure VNet name matches path
+        vnet_config.vnet = name.to_string();
+
+        if !config.vnets.contains_key(name) {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("VNet '{}' not found", name),
+                }),
+            ));
+        }
+
+        vnet_config.validate().map_err(bad_request)?;
+        let Some(zone) = config.zones.get(&vnet_config.zone) else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Zone '{}' does not exist", vnet_config.zone),
+                }),
+            ));
+        };
+        vnet_config.validate_zone(zone).map_err(bad_request)?;
+
+        let mut candidate = config.clone();
+        candidate.vnets.insert(name.to_string(), vnet_config);
+        ensure_unique_vnis(&candidate)?;
+
+        *config = candidate;
+        Ok(())
+    }
+
+    /// Driver instance of `config`, validated against it
+    async fn validated_controller(
+        &self,
+        config: &ControllerConfig,
+    ) -> Result<Box<dyn Controller>, (StatusCode, Json<ErrorResponse>)> {
+        let bad_request = |e: anyhow::Error| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("{:#}", e),
+                }),
+            )
+        };
+
+        self.plugin_factory
+            .check_controller_type(&config.controller_type)
+            .map_err(bad_request)?;
+        let controller = self
+            .plugin_factory
+            .create_controller(&config.controller_type, config.controller.clone())
+            .map_err(bad_request)?;
+        controller
+            .validate_configuration(config)
+            .await
+            .map_err(bad_request)?;
+
+        Ok(controller)
+    }
+
+    /// List configured controllers, sorted by name, without passwords
+    pub async fn list_controllers(&self) -> Vec<ControllerConfig> {
+        let config = self.config.read().await;
+        let mut controllers: Vec<ControllerConfig> = config
+            .controllers
+            .values()
+            .map(ControllerConfig::redacted)
+            .collect();
+        controllers.sort_by(|a, b| a.controller.cmp(&b.controller));
+        controllers
+    }
+
+    /// Get controller by name, without passwords
+    pub async fn get_controller(
+        &self,
+        name: &str,
+    ) -> Result<ControllerConfig, (StatusCode, Json<ErrorResponse>)> {
+        let config = self.config.read().await;
+        config
+            .controllers
+            .get(name)
+            .map(ControllerConfig::redacted)
+            .ok_or_else(|| controller_not_found(name))
+    }
+
+    /// Validate and add a new controller
+    pub async fn create_controller(
+        &self,
+        controller_config: ControllerConfig,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut config = self.config.write().await;
+
+        if config
+            .controllers
+            .contains_key(&controller_config.controller)
+        {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: format!(
+                        "Controller '{}' already exists",
+                        controller_config.controller
+                    ),
+                }),
+            ));
         }
+
+        self.validated_controller(&controller_config).await?;
+        config.add_controller(controller_config).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Validate and replace an existing controller
+    pub async fn update_controller(
+        &self,
+        name: &str,
+        mut controller_config: ControllerConfig,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut config = self.config.write().await;
+
+        // Ensure controller name matches path
+        controller_config.controller = name.to_string();
+
+        match config.controllers.get(name) {
+            Some(current) => controller_config.keep_passwords(current),
+            None => return Err(controller_not_found(name)),
+        }
+
+        self.validated_controller(&controller_config).await?;
+        config.add_controller(controller_config).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Remove a controller no zone references anymore
+    pub async fn delete_controller(
+        &self,
+        name: &str,
+    ) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let mut config = self.config.write().await;
+
+        if !config.controllers.contains_key(name) {
+            return Err(controller_not_found(name));
+        }
+
+        config.remove_controller(name).map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })
+    }
+
+    /// Runtime status of a controller running on this node
+    pub async fn controller_status(
+        &self,
+        name: &str,
+    ) -> Result<ControllerStatus, (StatusCode, Json<ErrorResponse>)> {
+        let controller_config = self.get_controller(name).await?;
+
+        if let Some(node) = &controller_config.node {
+            if node != self.apply_pipeline.node() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!(
+                            "Controller '{}' runs on node '{}', not on '{}'",
+                            name,
+                            node,
+                            self.apply_pipeline.node()
+                        ),
+                    }),
+                ));
+            }
+        }
+
+        let controller = self.validated_controller(&controller_config).await?;
+        controller.status().await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to query controller '{}': {:#}", name, e),
+                }),
+            )
+        })
+    }
+
+    /// Running configuration of the cluster, as published to pmxcfs
+    ///
+    /// Before the first reload there is none, the local configuration is
+    /// the running one then.
+    pub(crate) async fn running_config(&self) -> anyhow::Result<SdnConfiguration> {
+        match self.status_store.read_running_config().await? {
+            Some(config) => Ok(config),
+            None => Ok(self.config.read().await.clone()),
+        }
+    }
+
+    /// Make `config` the running configuration of the cluster
+    ///
+    /// The version continues from the one in pmxcfs, so it keeps growing
+    /// across restarts and no matter which node reloads.
+    pub async fn publish_running_config(
+        &self,
+        config: &mut SdnConfiguration,
+    ) -> anyhow::Result<u64> {
+        let running = match self.status_store.read_running_config().await? {
+            Some(running) => running.running_version(),
+            None => config.running_version(),
+        };
+
+        config.version = Some(running + 1);
+        self.status_store.write_running_config(config).await?;
+        Ok(running + 1)
+    }
+
+    /// SDN status of the local node
+    ///
+    /// The status is published to pmxcfs for the cluster view. Publishing
+    /// failures are only logged, other nodes then see the previous status.
+    pub async fn node_status(&self) -> Result<NodeSdnStatus, (StatusCode, Json<ErrorResponse>)> {
+        let internal_error = |e: anyhow::Error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to determine SDN status: {:#}", e),
+                }),
+            )
+        };
+
+        let applied_version = self
+            .apply_pipeline
+            .applied_version()
+            .await
+            .map_err(internal_error)?;
+        let config = self.running_config().await.map_err(internal_error)?;
+        let status = self
+            .plugin_factory
+            .clone()
+            .status_collector(self.apply_pipeline.node())
+            .collect(&config, applied_version)
+            .await
+            .map_err(internal_error)?;
+
+        if let Err(e) = self.status_store.publish(&status).await {
+            log::warn!("Failed to publish SDN status: {:#}", e);
+        }
+
+        Ok(status)
+    }
+
+    /// SDN status of every node that published one, with the status of the
+    /// local node refreshed first
+    pub async fn cluster_status(
+        &self,
+    ) -> Result<ClusterSdnStatus, (StatusCode, Json<ErrorResponse>)> {
+        if let Err((_, Json(e))) = self.node_status().await {
+            log::warn!("{}", e.error);
+        }
+
+        let internal_error = |e: anyhow::Error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to read SDN node status: {:#}", e),
+                }),
+            )
+        };
+
+        let nodes = self.status_store.read_all().await.map_err(internal_error)?;
+        let config = self.running_config().await.map_err(internal_error)?;
+        Ok(aggregate_status(&config, nodes))
     }
 }
 
+fn controller_not_found(name: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: format!("Controller '{}' not found", name),
+        }),
+    )
+}
+
+fn bad_request(e: anyhow::Error) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error: e.to_string(),
+        }),
+    )
+}
+
+/// Reject a change that makes a VNI or VRF clash with an existing one
+fn ensure_unique_vnis(
+    candidate: &SdnConfiguration,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    candidate.validate_vni_uniqueness().map_err(bad_request)
+}
+
 /// SDN API handler
 pub struct SDNAPI;
 
@@ -53,21 +539,55 @@ impl SDNAPI {
                 "/sdn/zones/:zone",
                 get(get_zone).put(update_zone).delete(delete_zone),
             )
+            .route("/sdn/zones/:zone/status", get(get_zone_status))
             // VNet endpoints
             .route("/sdn/vnets", get(list_vnets).post(create_vnet))
             .route(
                 "/sdn/vnets/:vnet",
                 get(get_vnet).put(update_vnet).delete(delete_vnet),
             )
+            .route("/sdn/vnets/:vnet/status", get(get_vnet_status))
+            .route(
+                "/sdn/vnets/:vnet/firewall",
+                get(get_vnet_firewall).put(update_vnet_firewall),
+            )
+            .route(
+                "/sdn/vnets/:vnet/firewall/rules",
+                get(list_vnet_firewall_rules).post(create_vnet_firewall_rule),
+            )
+            .route(
+                "/sdn/vnets/:vnet/firewall/rules/:pos",
+                get(get_vnet_firewall_rule)
+                    .put(update_vnet_firewall_rule)
+                    .delete(delete_vnet_firewall_rule),
+            )
             // Subnet endpoints
             .route("/sdn/subnets", get(list_subnets).post(create_subnet))
             .route(
                 "/sdn/subnets/:subnet",
                 get(get_subnet).put(update_subnet).delete(delete_subnet),
             )
+            // Controller endpoints
+            .route(
+                "/sdn/controllers",
+                get(list_controllers).post(create_controller),
+            )
+            .route(
+                "/sdn/controllers/:controller",
+                get(get_controller)
+                    .put(update_controller)
+                    .delete(delete_controller),
+            )
+            .route(
+                "/sdn/controllers/:controller/status",
+                get(get_controller_status),
+            )
             // Configuration endpoints
             .route("/sdn/config", get(get_config).put(update_config))
             .route("/sdn/reload", post(reload_config))
+            // Status endpoints
+            .route("/sdn/status", get(get_node_status))
+            .route("/sdn/cluster/status", get(get_cluster_status))
             // IPAM endpoints
             .route("/sdn/ipam", get(list_ipam_configs).post(create_ipam_config))
             .route(
@@ -185,6 +705,31 @@ pub struct IpamStatusResponse {
     pub message: Option<String>,
 }
 
+/// Runtime status of an EVPN zone
+#[derive(Debug, Serialize)]
+pub struct ZoneRuntimeStatus {
+    pub zone: String,
+    pub controller: String,
+    pub running: bool,
+    pub last_error: Option<String>,
+    /// EVPN sessions of the zone's controller
+    pub peers: Vec<BgpPeerStatus>,
+    /// L3VNI and vnet VNIs of the zone
+    pub vnis: Vec<EvpnVniStatus>,
+}
+
+/// Runtime status of a vnet in an EVPN zone
+#[derive(Debug, Serialize)]
+pub struct VnetRuntimeStatus {
+    pub vnet: String,
+    pub zone: String,
+    pub vni: Option<u32>,
+    pub vni_status: Option<EvpnVniStatus>,
+    pub last_error: Option<String>,
+    /// MAC/IP table of the vnet's VNI
+    pub macs: Vec<EvpnMacEntry>,
+}
+
 /// API response wrapper
 #[derive(Debug, Serialize)]
 pub struct ApiResponse<T> {
@@ -234,11 +779,33 @@ pub async fn get_zone(
 pub async fn create_zone(
     State(context): State<Arc<AppContext>>,
     Json(zone_config): Json<ZoneConfig>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context.sdn_state.create_zone(zone_config).await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Update existing zone
+pub async fn update_zone(
+    State(context): State<Arc<AppContext>>,
+    Path(zone_name): Path<String>,
+    Json(zone_config): Json<ZoneConfig>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context
+        .sdn_state
+        .update_zone(&zone_name, zone_config)
+        .await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Delete zone
+pub async fn delete_zone(
+    State(context): State<Arc<AppContext>>,
+    Path(zone_name): Path<String>,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
     let mut config = state.config.write().await;
 
-    match config.add_zone(zone_config) {
+    match config.remove_zone(&zone_name) {
         Ok(()) => Ok(Json(ApiResponse { data: () })),
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
@@ -249,58 +816,77 @@ pub async fn create_zone(
     }
 }
 
-/// Update existing zone
-pub async fn update_zone(
-    State(context): State<Arc<AppContext>>,
-    Path(zone_name): Path<String>,
-    Json(mut zone_config): Json<ZoneConfig>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
-    let state = context.sdn_state.clone();
-    let mut config = state.config.write().await;
-
-    // Ensure zone name matches path
-    zone_config.zone = zone_name.clone();
-
-    if !config.zones.contains_key(&zone_name) {
-        return Err((
+/// EVPN controller of `zone_name`
+fn zone_evpn_controller(
+    config: &SdnConfiguration,
+    zone_name: &str,
+) -> Result<String, (StatusCode, Json<ErrorResponse>)> {
+    let zone = config.zones.get(zone_name).ok_or_else(|| {
+        (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: format!("Zone '{}' not found", zone_name),
             }),
-        ));
-    }
+        )
+    })?;
 
-    match zone_config.validate() {
-        Ok(()) => {
-            config.zones.insert(zone_name, zone_config);
-            Ok(Json(ApiResponse { data: () }))
-        }
-        Err(e) => Err((
+    let controller = zone
+        .options
+        .get("controller")
+        .and_then(|c| c.as_str())
+        .filter(|c| config.controllers.contains_key(*c));
+
+    match (&zone.zone_type, controller) {
+        (ZoneType::Evpn, Some(controller)) => Ok(controller.to_string()),
+        _ => Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: e.to_string(),
+                error: format!("Zone '{}' has no EVPN controller", zone_name),
             }),
         )),
     }
 }
 
-/// Delete zone
-pub async fn delete_zone(
+/// Get runtime status of an EVPN zone
+pub async fn get_zone_status(
     State(context): State<Arc<AppContext>>,
     Path(zone_name): Path<String>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<ApiResponse<ZoneRuntimeStatus>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
-    let mut config = state.config.write().await;
+    let (controller, vnis) = {
+        let config = state.config.read().await;
+        (
+            zone_evpn_controller(&config, &zone_name)?,
+            zone_vnis(&config, &zone_name),
+        )
+    };
 
-    match config.remove_zone(&zone_name) {
-        Ok(()) => Ok(Json(ApiResponse { data: () })),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        )),
-    }
+    let status = EvpnController::with_executor(controller.clone(), state.executor.clone())
+        .status()
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to get status of zone '{}': {:#}", zone_name, e),
+                }),
+            )
+        })?;
+
+    Ok(Json(ApiResponse {
+        data: ZoneRuntimeStatus {
+            zone: zone_name,
+            controller,
+            running: status.running,
+            last_error: status.last_error,
+            peers: status.bgp_peers,
+            vnis: status
+                .evpn_vnis
+                .into_iter()
+                .filter(|v| vnis.contains(&v.vni))
+                .collect(),
+        },
+    }))
 }
 
 // VNet endpoints
@@ -345,11 +931,33 @@ pub async fn get_vnet(
 pub async fn create_vnet(
     State(context): State<Arc<AppContext>>,
     Json(vnet_config): Json<VNetConfig>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context.sdn_state.create_vnet(vnet_config).await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Update existing VNet
+pub async fn update_vnet(
+    State(context): State<Arc<AppContext>>,
+    Path(vnet_name): Path<String>,
+    Json(vnet_config): Json<VNetConfig>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context
+        .sdn_state
+        .update_vnet(&vnet_name, vnet_config)
+        .await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Delete VNet
+pub async fn delete_vnet(
+    State(context): State<Arc<AppContext>>,
+    Path(vnet_name): Path<String>,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
     let mut config = state.config.write().await;
 
-    match config.add_vnet(vnet_config) {
+    match config.remove_vnet(&vnet_name) {
         Ok(()) => Ok(Json(ApiResponse { data: () })),
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
@@ -360,68 +968,217 @@ pub async fn create_vnet(
     }
 }
 
-/// Update existing VNet
-pub async fn update_vnet(
+/// Get runtime status of a vnet in an EVPN zone
+pub async fn get_vnet_status(
     State(context): State<Arc<AppContext>>,
     Path(vnet_name): Path<String>,
-    Json(mut vnet_config): Json<VNetConfig>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<ApiResponse<VnetRuntimeStatus>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
-    let mut config = state.config.write().await;
+    let (zone, controller, vni) = {
+        let config = state.config.read().await;
+        let vnet = config.vnets.get(&vnet_name).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: format!("VNet '{}' not found", vnet_name),
+                }),
+            )
+        })?;
+        (
+            vnet.zone.clone(),
+            zone_evpn_controller(&config, &vnet.zone)?,
+            vnet.vni(),
+        )
+    };
 
-    // Ensure VNet name matches path
-    vnet_config.vnet = vnet_name.clone();
+    let status = EvpnController::with_executor(controller, state.executor.clone())
+        .status()
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to get status of vnet '{}': {:#}", vnet_name, e),
+                }),
+            )
+        })?;
 
-    if !config.vnets.contains_key(&vnet_name) {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: format!("VNet '{}' not found", vnet_name),
-            }),
-        ));
-    }
+    Ok(Json(ApiResponse {
+        data: VnetRuntimeStatus {
+            vnet: vnet_name,
+            zone,
+            vni,
+            vni_status: status.evpn_vnis.into_iter().find(|v| Some(v.vni) == vni),
+            last_error: status.last_error,
+            macs: status
+                .evpn_macs
+                .into_iter()
+                .filter(|m| Some(m.vni) == vni)
+                .collect(),
+        },
+    }))
+}
 
-    match vnet_config.validate() {
-        Ok(()) => {
-            // Validate zone exists
-            if !config.zones.contains_key(&vnet_config.zone) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: format!("Zone '{}' does not exist", vnet_config.zone),
-                    }),
-                ));
-            }
+// VNet firewall endpoints
+
+/// Firewall of `vnet_name`, an empty one if none is configured yet
+///
+/// Returns a copy, changes only reach the configuration through
+/// [`store_vnet_firewall`] once they are valid.
+fn vnet_firewall(
+    config: &SdnConfiguration,
+    vnet_name: &str,
+) -> Result<VNetFirewall, (StatusCode, Json<ErrorResponse>)> {
+    match config.vnets.get(vnet_name) {
+        Some(vnet) => Ok(vnet.firewall.clone().unwrap_or_default()),
+        None => Err(vnet_not_found(vnet_name)),
+    }
+}
 
-            config.vnets.insert(vnet_name, vnet_config);
-            Ok(Json(ApiResponse { data: () }))
-        }
-        Err(e) => Err((
+/// Validate `firewall` and store it as the firewall of `vnet_name`
+fn store_vnet_firewall(
+    config: &mut SdnConfiguration,
+    vnet_name: &str,
+    firewall: VNetFirewall,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let vnet = config
+        .vnets
+        .get_mut(vnet_name)
+        .ok_or_else(|| vnet_not_found(vnet_name))?;
+
+    if let Err(e) = firewall.validate() {
+        return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
                 error: e.to_string(),
             }),
-        )),
+        ));
     }
+
+    vnet.firewall = Some(firewall);
+    Ok(())
 }
 
-/// Delete VNet
-pub async fn delete_vnet(
+fn vnet_not_found(vnet_name: &str) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: format!("VNet '{}' not found", vnet_name),
+        }),
+    )
+}
+
+fn firewall_rule_not_found(vnet_name: &str, pos: usize) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: format!("VNet '{}' has no firewall rule {}", vnet_name, pos),
+        }),
+    )
+}
+
+/// Get firewall of a VNet
+pub async fn get_vnet_firewall(
+    State(context): State<Arc<AppContext>>,
+    Path(vnet_name): Path<String>,
+) -> Result<Json<ApiResponse<VNetFirewall>>, (StatusCode, Json<ErrorResponse>)> {
+    let state = context.sdn_state.clone();
+    let config = state.config.read().await;
+
+    Ok(Json(ApiResponse {
+        data: vnet_firewall(&config, &vnet_name)?,
+    }))
+}
+
+/// Replace firewall of a VNet, including its rules, ipsets and aliases
+pub async fn update_vnet_firewall(
     State(context): State<Arc<AppContext>>,
     Path(vnet_name): Path<String>,
+    Json(firewall): Json<VNetFirewall>,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
     let mut config = state.config.write().await;
 
-    match config.remove_vnet(&vnet_name) {
-        Ok(()) => Ok(Json(ApiResponse { data: () })),
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        )),
+    store_vnet_firewall(&mut config, &vnet_name, firewall)?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// List firewall rules of a VNet
+pub async fn list_vnet_firewall_rules(
+    State(context): State<Arc<AppContext>>,
+    Path(vnet_name): Path<String>,
+) -> Result<Json<ApiResponse<Vec<FirewallRule>>>, (StatusCode, Json<ErrorResponse>)> {
+    let firewall = get_vnet_firewall(State(context), Path(vnet_name)).await?;
+    Ok(Json(ApiResponse {
+        data: firewall.0.data.rules,
+    }))
+}
+
+/// Append firewall rule to a VNet, returning its position
+pub async fn create_vnet_firewall_rule(
+    State(context): State<Arc<AppContext>>,
+    Path(vnet_name): Path<String>,
+    Json(rule): Json<FirewallRule>,
+) -> Result<Json<ApiResponse<usize>>, (StatusCode, Json<ErrorResponse>)> {
+    let state = context.sdn_state.clone();
+    let mut config = state.config.write().await;
+
+    let mut firewall = vnet_firewall(&config, &vnet_name)?;
+    firewall.rules.push(rule);
+    let pos = firewall.rules.len() - 1;
+
+    store_vnet_firewall(&mut config, &vnet_name, firewall)?;
+    Ok(Json(ApiResponse { data: pos }))
+}
+
+/// Get firewall rule of a VNet by position
+pub async fn get_vnet_firewall_rule(
+    State(context): State<Arc<AppContext>>,
+    Path((vnet_name, pos)): Path<(String, usize)>,
+) -> Result<Json<ApiResponse<FirewallRule>>, (StatusCode, Json<ErrorResponse>)> {
+    let firewall = get_vnet_firewall(State(context), Path(vnet_name.clone())).await?;
+
+    match firewall.0.data.rules.into_iter().nth(pos) {
+        Some(rule) => Ok(Json(ApiResponse { data: rule })),
+        None => Err(firewall_rule_not_found(&vnet_name, pos)),
+    }
+}
+
+/// Replace firewall rule of a VNet
+pub async fn update_vnet_firewall_rule(
+    State(context): State<Arc<AppContext>>,
+    Path((vnet_name, pos)): Path<(String, usize)>,
+    Json(rule): Json<FirewallRule>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    let state = context.sdn_state.clone();
+    let mut config = state.config.write().await;
+
+    let mut firewall = vnet_firewall(&config, &vnet_name)?;
+    match firewall.rules.get_mut(pos) {
+        Some(current) => *current = rule,
+        None => return Err(firewall_rule_not_found(&vnet_name, pos)),
     }
+
+    store_vnet_firewall(&mut config, &vnet_name, firewall)?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Delete firewall rule of a VNet, moving the following rules up
+pub async fn delete_vnet_firewall_rule(
+    State(context): State<Arc<AppContext>>,
+    Path((vnet_name, pos)): Path<(String, usize)>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    let state = context.sdn_state.clone();
+    let mut config = state.config.write().await;
+
+    let mut firewall = vnet_firewall(&config, &vnet_name)?;
+    if pos >= firewall.rules.len() {
+        return Err(firewall_rule_not_found(&vnet_name, pos));
+    }
+    firewall.rules.remove(pos);
+
+    store_vnet_firewall(&mut config, &vnet_name, firewall)?;
+    Ok(Json(ApiResponse { data: () }))
 }
 
 // Subnet endpoints
@@ -547,6 +1304,92 @@ pub async fn delete_subnet(
     }
 }
 
+// Controller endpoints
+
+/// List all controllers
+pub async fn list_controllers(
+    State(context): State<Arc<AppContext>>,
+) -> Result<Json<ApiResponse<Vec<ControllerConfig>>>, (StatusCode, Json<ErrorResponse>)> {
+    let controllers = context.sdn_state.list_controllers().await;
+    Ok(Json(ApiResponse { data: controllers }))
+}
+
+/// Get specific controller
+pub async fn get_controller(
+    State(context): State<Arc<AppContext>>,
+    Path(controller_name): Path<String>,
+) -> Result<Json<ApiResponse<ControllerConfig>>, (StatusCode, Json<ErrorResponse>)> {
+    let controller = context.sdn_state.get_controller(&controller_name).await?;
+    Ok(Json(ApiResponse { data: controller }))
+}
+
+/// Create new controller
+pub async fn create_controller(
+    State(context): State<Arc<AppContext>>,
+    Json(controller_config): Json<ControllerConfig>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context
+        .sdn_state
+        .create_controller(controller_config)
+        .await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Update existing controller
+pub async fn update_controller(
+    State(context): State<Arc<AppContext>>,
+    Path(controller_name): Path<String>,
+    Json(controller_config): Json<ControllerConfig>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context
+        .sdn_state
+        .update_controller(&controller_name, controller_config)
+        .await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Delete controller
+pub async fn delete_controller(
+    State(context): State<Arc<AppContext>>,
+    Path(controller_name): Path<String>,
+) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
+    context
+        .sdn_state
+        .delete_controller(&controller_name)
+        .await?;
+    Ok(Json(ApiResponse { data: () }))
+}
+
+/// Get runtime status of a controller
+pub async fn get_controller_status(
+    State(context): State<Arc<AppContext>>,
+    Path(controller_name): Path<String>,
+) -> Result<Json<ApiResponse<ControllerStatus>>, (StatusCode, Json<ErrorResponse>)> {
+    let status = context
+        .sdn_state
+        .controller_status(&controller_name)
+        .await?;
+    Ok(Json(ApiResponse { data: status }))
+}
+
+// Status endpoints
+
+/// Get the SDN status of the local node
+pub async fn get_node_status(
+    State(context): State<Arc<AppContext>>,
+) -> Result<Json<ApiResponse<NodeSdnStatus>>, (StatusCode, Json<ErrorResponse>)> {
+    let status = context.sdn_state.node_status().await?;
+    Ok(Json(ApiResponse { data: status }))
+}
+
+/// Get the SDN status of all nodes
+pub async fn get_cluster_status(
+    State(context): State<Arc<AppContext>>,
+) -> Result<Json<ApiResponse<ClusterSdnStatus>>, (StatusCode, Json<ErrorResponse>)> {
+    let status = context.sdn_state.cluster_status().await?;
+    Ok(Json(ApiResponse { data: status }))
+}
+
 // Configuration endpoints
 
 /// Get complete SDN configuration
@@ -556,18 +1399,27 @@ pub async fn get_config(
     let state = context.sdn_state.clone();
     let config = state.config.read().await;
     Ok(Json(ApiResponse {
-        data: config.clone(),
+        data: config.redacted(),
     }))
 }
 
 /// Update complete SDN configuration
 pub async fn update_config(
     State(context): State<Arc<AppContext>>,
-    Json(new_config): Json<SdnConfiguration>,
+    Json(mut new_config): Json<SdnConfiguration>,
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
-    match new_config.validate() {
+    new_config.keep_secrets(&*state.config.read().await);
+    match new_config.validate_with(&*state.plugin_factory) {
         Ok(()) => {
+            state.sync_dns_plugins(&new_config).await.map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!("{:#}", e),
+                    }),
+                )
+            })?;
             let mut config = state.config.write().await;
             *config = new_config;
             Ok(Json(ApiResponse { data: () }))
@@ -584,29 +1436,87 @@ pub async fn update_config(
 /// Reload SDN configuration
 pub async fn reload_config(
     State(context): State<Arc<AppContext>>,
-) -> Result<Json<ApiResponse<String>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<ApiResponse<SdnApplyResult>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
-    let config = state.config.read().await;
+    let mut config = state.config.write().await;
 
-    match config.validate() {
-        Ok(()) => {
-            // In a real implementation, this would:
-            // 1. Apply all zone configurations
-            // 2. Regenerate network configurations
-            // 3. Reload network services
-
-            log::info!("SDN configuration reloaded successfully");
-            Ok(Json(ApiResponse {
-                data: "SDN configuration reloaded successfully".to_string(),
-            }))
-        }
-        Err(e) => Err((
+    if let Err(e) = config.validate_with(&*state.plugin_factory) {
+        return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
                 error: format!("Configuration validation failed: {}", e),
             }),
-        )),
+        ));
+    }
+
+    // Every reload is a new running version, nodes report their zones as
+    // pending until they applied it
+    state
+        .publish_running_config(&mut config)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to publish the running SDN configuration: {:#}", e),
+                }),
+            )
+        })?;
+    let config = config.downgrade();
+
+    let mut result = state.apply_pipeline.apply(&config).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to apply SDN configuration: {:#}", e),
+            }),
+        )
+    })?;
+
+    // ifupdown2 does not touch devices of changed or removed zones it
+    // already created, the reconciler updates and collects them
+    let reconciler = state
+        .plugin_factory
+        .clone()
+        .reconciler(state.apply_pipeline.node());
+    match reconciler.reconcile(&config).await {
+        Ok(report) => {
+            if !report.failed.is_empty() {
+                log::warn!(
+                    "{} SDN reconcile steps failed after reload",
+                    report.failed.len()
+                );
+            }
+            result.reconcile = Some(report);
+        }
+        Err(e) => log::error!("Failed to reconcile SDN devices: {:#}", e),
     }
+
+    state.sync_dns_plugins(&config).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to set up SDN DNS plugins: {:#}", e),
+            }),
+        )
+    })?;
+
+    let ipam_manager = state.ipam_manager.read().await;
+    state
+        .dhcp
+        .apply(&config, &ipam_manager)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to apply SDN DHCP configuration: {:#}", e),
+                }),
+            )
+        })?;
+
+    log::info!("SDN configuration reloaded successfully");
+    Ok(Json(ApiResponse { data: result }))
 }
 
 // IPAM Configuration endpoints
@@ -648,7 +1558,11 @@ pub async fn create_ipam_config(
 ) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ErrorResponse>)> {
     let state = context.sdn_state.clone();
     // Validate configuration
-    if let Err(e) = ipam_config.validate() {
+    let validation = state
+        .plugin_factory
+        .check_ipam_type(&ipam_config.ipam_type)
+        .and_then(|()| ipam_config.validate());
+    if let Err(e) = validation {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -684,7 +1598,11 @@ pub async fn update_ipam_config(
     ipam_config.name = ipam_name.clone();
 
     // Validate configuration
-    if let Err(e) = ipam_config.validate() {
+    let validation = state
+        .plugin_factory
+        .check_ipam_type(&ipam_config.ipam_type)
+        .and_then(|()| ipam_config.validate());
+    if let Err(e) = validation {
         return Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -847,6 +1765,9 @@ pub async fn allocate_ip(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    // Records go where the subnet options currently point to
+    ipam_manager.set_subnet_dns(&subnet_name, SubnetDns::from_subnet(subnet_config));
+
     let allocation_request = IpAllocationRequest {
         subnet: subnet_name.clone(),
         vmid: request.vmid,
@@ -860,7 +1781,12 @@ pub async fn allocate_ip(
         .allocate_ip(ipam_name.as_deref(), &allocation_request)
         .await
     {
-        Ok(allocation) => Ok(Json(ApiResponse { data: allocation })),
+        Ok(allocation) => {
+            state
+                .sync_dhcp_leases(&config, &ipam_manager, &subnet_name)
+                .await;
+            Ok(Json(ApiResponse { data: allocation }))
+        }
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -963,11 +1889,19 @@ pub async fn update_ip_allocation(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    // Records go where the subnet options currently point to
+    ipam_manager.set_subnet_dns(&subnet_name, SubnetDns::from_subnet(subnet_config));
+
     match ipam_manager
         .update_ip(ipam_name.as_deref(), &subnet_name, &ip, &allocation)
         .await
     {
-        Ok(()) => Ok(Json(ApiResponse { data: () })),
+        Ok(()) => {
+            state
+                .sync_dhcp_leases(&config, &ipam_manager, &subnet_name)
+                .await;
+            Ok(Json(ApiResponse { data: () }))
+        }
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -1013,11 +1947,19 @@ pub async fn release_ip(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    // Records go where the subnet options currently point to
+    ipam_manager.set_subnet_dns(&subnet_name, SubnetDns::from_subnet(subnet_config));
+
     match ipam_manager
         .release_ip(ipam_name.as_deref(), &subnet_name, &ip)
         .await
     {
-        Ok(()) => Ok(Json(ApiResponse { data: () })),
+        Ok(()) => {
+            state
+                .sync_dhcp_leases(&config, &ipam_manager, &subnet_name)
+                .await;
+            Ok(Json(ApiResponse { data: () }))
+        }
         Err(e) => Err((
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -1066,3 +2008,45 @@ pub async fn get_next_free_ip(
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_sdn_core::{FirewallAction, FirewallDirection};
+
+    #[test]
+    fn test_vnet_firewall_only_stored_when_valid() {
+        let mut config = SdnConfiguration::new();
+        config.vnets.insert(
+            "vnet1".to_string(),
+            VNetConfig::new("vnet1".to_string(), "zone1".to_string()),
+        );
+
+        let (status, _) = vnet_firewall(&config, "missing").unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Failed requests leave the VNet without firewall
+        let mut firewall = vnet_firewall(&config, "vnet1").unwrap();
+        let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Accept);
+        rule.proto = Some("bogus".to_string());
+        firewall.rules.push(rule);
+        let (status, _) = store_vnet_firewall(&mut config, "vnet1", firewall).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(config.vnets["vnet1"].firewall.is_none());
+
+        let (status, _) =
+            store_vnet_firewall(&mut config, "missing", VNetFirewall::default()).unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let mut firewall = vnet_firewall(&config, "vnet1").unwrap();
+        firewall.rules.push(FirewallRule::new(
+            FirewallDirection::In,
+            FirewallAction::Accept,
+        ));
+        store_vnet_firewall(&mut config, "vnet1", firewall).unwrap();
+        assert_eq!(
+            config.vnets["vnet1"].firewall.as_ref().unwrap().rules.len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! HTTPS server of the network API
+//!
+//! The other cluster nodes reach this API for rolling applies, see
+//! [`crate::cluster`]. Like pveproxy, the server presents the node
+//! certificate from pmxcfs, which the nodes verify against the cluster CA.
+
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::Router;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use log::{debug, warn};
+use openssl::pkey::PKey;
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+
+use crate::{
+    cluster::DEFAULT_API_PORT, context::AppContext, ChangeRequestAPI, ClusterNetworkAPI,
+    ContainerNetworkAPI, NetworkAPI, StorageNetworkAPI, TaskManager, SDNAPI,
+};
+
+/// Default listen address, reachable from the other cluster nodes
+pub const DEFAULT_LISTEN_ADDR: SocketAddr =
+    SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), DEFAULT_API_PORT);
+
+/// Router with the endpoints of all APIs
+pub fn api_router() -> Router<Arc<AppContext>> {
+    Router::new()
+        .merge(NetworkAPI::router())
+        .merge(StorageNetworkAPI::router())
+        .merge(ContainerNetworkAPI::router())
+        .merge(SDNAPI::router())
+        .merge(TaskManager::router())
+        .merge(ChangeRequestAPI::router())
+        .merge(ClusterNetworkAPI::router())
+}
+
+/// Certificate and key of `node` below the pmxcfs mount point
+pub fn node_certificate_paths(pmxcfs: &Path, node: &str) -> (PathBuf, PathBuf) {
+    let dir = pmxcfs.join("nodes").join(node);
+    (dir.join("pve-ssl.pem"), dir.join("pve-ssl.key"))
+}
+
+/// TLS acceptor presenting the certificate in `cert` with the key in `key`
+pub async fn tls_acceptor(cert: &Path, key: &Path) -> Result<TlsAcceptor> {
+    let cert_pem = tokio::fs::read(cert)
+        .await
+        .with_context(|| format!("failed to read certificate {}", cert.display()))?;
+    let key_pem = tokio::fs::read(key)
+        .await
+        .with_context(|| format!("failed to read key {}", key.display()))?;
+
+    // Older installations have PKCS#1 keys, native-tls only takes PKCS#8
+    let key_pem = PKey::private_key_from_pem(&key_pem)
+        .and_then(|key| key.private_key_to_pem_pkcs8())
+        .with_context(|| format!("invalid key {}", key.display()))?;
+    let identity = native_tls::Identity::from_pkcs8(&cert_pem, &key_pem)
+        .with_context(|| format!("invalid certificate {}", cert.display()))?;
+
+    Ok(TlsAcceptor::from(native_tls::TlsAcceptor::new(identity)?))
+}
+
+/// Serve `app` over TLS to the connections accepted on `listener`
+pub async fn serve_tls(listener: TcpListener, acceptor: TlsAcceptor, app: Router) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Out of file descriptors and the like, retry once some are released
+                warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::ApiTokenAuthenticator;
+    use crate::cluster::{HttpNodeTransport, CLUSTER_CA_FILE};
+    use openssl::asn1::{Asn1Integer, Asn1Time};
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+    use openssl::x509::extension::{BasicConstraints, KeyUsage, SubjectAlternativeName};
+    use openssl::x509::{X509NameBuilder, X509};
+    use pve_network_apply::{change_request::MODIFY_PRIVILEGE, NodeTransport, StaticPrivileges};
+
+    /// Certificate for `name`, a CA without `issuer`
+    fn certificate(name: &str, issuer: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_nid(Nid::COMMONNAME, name).unwrap();
+        let subject = subject.build();
+        let serial: Asn1Integer = BigNum::from_u32(if issuer.is_some() { 2 } else { 1 })
+            .and_then(|serial| serial.to_asn1_integer())
+            .unwrap();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+
+        match issuer {
+            Some((ca, ca_key)) => {
+                builder.set_issuer_name(ca.subject_name()).unwrap();
+                let san = SubjectAlternativeName::new()
+                    .ip("127.0.0.1")
+                    .build(&builder.x509v3_context(Some(ca), None))
+                    .unwrap();
+                builder.append_extension(san).unwrap();
+                builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                builder.set_issuer_name(&subject).unwrap();
+                let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+                builder.append_extension(constraints).unwrap();
+                let usage = KeyUsage::new().critical().key_cert_sign().build().unwrap();
+                builder.append_extension(usage).unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+        }
+
+        (builder.build(), key)
+    }
+
+    #[tokio::test]
+    async fn test_node_transport_reaches_api_server() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let pmxcfs = temp_dir.path();
+
+        let (ca, ca_key) = certificate("Proxmox Virtual Environment", None);
+        let (cert, key) = certificate("node1", Some((&ca, &ca_key)));
+        std::fs::write(pmxcfs.join(CLUSTER_CA_FILE), ca.to_pem().unwrap()).unwrap();
+        let (cert_path, key_path) = node_certificate_paths(pmxcfs, "node1");
+        std::fs::create_dir_all(cert_path.parent().unwrap()).unwrap();
+        std::fs::write(&cert_path, cert.to_pem().unwrap()).unwrap();
+        // A traditional key, not PKCS#8, like on older installations
+        let key_pem = key.ec_key().unwrap().private_key_to_pem().unwrap();
+        std::fs::write(&key_path, key_pem).unwrap();
+        std::fs::write(pmxcfs.join("token.cfg"), "root@pam!ci secret\n").unwrap();
+
+        let mut context = (*AppContext::bootstrap().await.unwrap()).clone();
+        context.authenticator = Arc::new(ApiTokenAuthenticator::with_token_cfg(
+            pmxcfs.join("token.cfg"),
+        ));
+        context.privileges =
+            Arc::new(StaticPrivileges::new().grant("root@pam!ci", MODIFY_PRIVILEGE));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::fs::write(
+            pmxcfs.join(".members"),
+            r#"{"nodelist":{"node1":{"id":1,"online":1,"ip":"127.0.0.1"}}}"#,
+        )
+        .unwrap();
+        let acceptor = tls_acceptor(&cert_path, &key_path).await.unwrap();
+        let server = tokio::spawn(serve_tls(
+            listener,
+            acceptor,
+            api_router().with_state(Arc::new(context)),
+        ));
+
+        // The receiving node checks the forwarded credentials
+        let transport = HttpNodeTransport::new()
+            .with_pmxcfs_path(pmxcfs)
+            .with_port(port);
+        let error = transport.current_config("node1").await.unwrap_err();
+        assert!(error.to_string().contains("401"), "{}", error);
+
+        let transport = transport.with_authorization("PVEAPIToken=root@pam!ci=secret");
+        let config = transport.current_config("node1").await.unwrap();
+        assert!(config.interfaces.contains_key("lo"));
+
+        // An unknown transaction is an answer, not a node still coming up
+        assert!(transport.health_probe("node1", "txn_0").await.is_err());
+
+        server.abort();
+        let _ = server.await;
+        assert!(!transport.health_probe("node1", "txn_0").await.unwrap());
+    }
+}
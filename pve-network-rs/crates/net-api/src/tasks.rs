@@ -1,14 +1,239 @@
 //! Task management for network operations
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use pve_network_apply::{RollingApplyOrchestrator, RollingApplyState};
+use pve_network_core::NetworkConfiguration;
+
+use crate::{
+    auth::AuthenticatedUser,
+    cluster::{ensure_privilege, AUDIT_PRIVILEGE},
+    context::AppContext,
+};
+
+/// Interval at which progress of long running operations is mirrored into tasks
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Number of finished tasks kept, older ones are dropped
+const MAX_FINISHED_TASKS: usize = 1000;
+
+/// Task status
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    /// Task is still running
+    Running,
+    /// Task finished
+    Stopped,
+}
+
+/// Task information, modelled after the PVE task list entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    /// Unique task ID
+    pub upid: String,
+    /// Node the task runs on
+    pub node: String,
+    /// Task type (e.g. "rollingapply")
+    #[serde(rename = "type")]
+    pub task_type: String,
+    /// Object the task operates on
+    pub id: String,
+    /// User that started the task
+    pub user: String,
+    /// Task status
+    pub status: TaskStatus,
+    /// Exit status once stopped ("OK" or an error message)
+    pub exitstatus: Option<String>,
+    /// Start time (unix epoch)
+    pub starttime: i64,
+    /// End time (unix epoch)
+    pub endtime: Option<i64>,
+    /// Operation specific progress information
+    pub progress: Option<Value>,
+}
+
 /// Task management for network operations
+#[derive(Clone)]
 pub struct TaskManager {
-    // TODO: Add task management implementation
+    node: String,
+    tasks: Arc<RwLock<HashMap<String, TaskInfo>>>,
+    /// Sequence number of the next task, keeps UPIDs unique
+    next_task: Arc<AtomicU64>,
 }
 
 impl TaskManager {
     /// Create new TaskManager instance
     pub fn new() -> Self {
-        Self {}
+        Self::with_node("localhost")
+    }
+
+    /// Create TaskManager for a specific node
+    pub fn with_node(node: &str) -> Self {
+        Self {
+            node: node.to_string(),
+            tasks: Arc::new(RwLock::new(HashMap::new())),
+            next_task: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Register a new running task and return its UPID
+    pub async fn start_task(&self, task_type: &str, id: &str, user: &str) -> String {
+        let starttime = Utc::now().timestamp();
+        let sequence = self.next_task.fetch_add(1, Ordering::Relaxed);
+        let upid = format!(
+            "UPID:{}:{:08X}:{:08X}:{:08X}:{}:{}:{}:",
+            self.node,
+            std::process::id(),
+            sequence,
+            starttime,
+            task_type,
+            id,
+            user
+        );
+
+        self.tasks.write().await.insert(
+            upid.clone(),
+            TaskInfo {
+                upid: upid.clone(),
+                node: self.node.clone(),
+                task_type: task_type.to_string(),
+                id: id.to_string(),
+                user: user.to_string(),
+                status: TaskStatus::Running,
+                exitstatus: None,
+                starttime,
+                endtime: None,
+                progress: None,
+            },
+        );
+
+        upid
+    }
+
+    /// Update the progress information of a task
+    pub async fn update_progress(&self, upid: &str, progress: Value) {
+        if let Some(task) = self.tasks.write().await.get_mut(upid) {
+            task.progress = Some(progress);
+        }
+    }
+
+    /// Mark a task as stopped
+    ///
+    /// Only the newest [`MAX_FINISHED_TASKS`] finished tasks are kept.
+    pub async fn finish_task(&self, upid: &str, result: Result<(), String>) {
+        let mut tasks = self.tasks.write().await;
+        if let Some(task) = tasks.get_mut(upid) {
+            task.status = TaskStatus::Stopped;
+            task.endtime = Some(Utc::now().timestamp());
+            task.exitstatus = Some(match result {
+                Ok(()) => "OK".to_string(),
+                Err(message) => message,
+            });
+        }
+
+        let mut finished: Vec<(i64, String)> = tasks
+            .values()
+            .filter(|task| task.status == TaskStatus::Stopped)
+            .map(|task| (task.endtime.unwrap_or(task.starttime), task.upid.clone()))
+            .collect();
+        if finished.len() > MAX_FINISHED_TASKS {
+            finished.sort();
+            let excess = finished.len() - MAX_FINISHED_TASKS;
+            for (_, upid) in finished.into_iter().take(excess) {
+                tasks.remove(&upid);
+            }
+        }
+    }
+
+    /// Get a task by UPID
+    pub async fn get_task(&self, upid: &str) -> Option<TaskInfo> {
+        self.tasks.read().await.get(upid).cloned()
+    }
+
+    /// List all tasks, newest first
+    pub async fn list_tasks(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = self.tasks.read().await.values().cloned().collect();
+        tasks.sort_by(|a, b| b.starttime.cmp(&a.starttime).then(b.upid.cmp(&a.upid)));
+        tasks
+    }
+
+    /// Run a rolling apply in the background and track it as a task
+    pub async fn spawn_rolling_apply(
+        &self,
+        orchestrator: Arc<RollingApplyOrchestrator>,
+        nodes: Vec<String>,
+        config: NetworkConfiguration,
+        user: &str,
+    ) -> String {
+        let rollout_id = orchestrator.progress().await.id;
+        let upid = self.start_task("rollingapply", &rollout_id, user).await;
+
+        let manager = self.clone();
+        let task_upid = upid.clone();
+        tokio::spawn(async move {
+            let progress_handle = orchestrator.progress_handle();
+            let run = orchestrator.run(&nodes, &config);
+            tokio::pin!(run);
+
+            let mut interval = tokio::time::interval(PROGRESS_POLL_INTERVAL);
+            let result = loop {
+                tokio::select! {
+                    result = &mut run => break result,
+                    _ = interval.tick() => {
+                        let progress = progress_handle.read().await.clone();
+                        if let Ok(value) = serde_json::to_value(&progress) {
+                            manager.update_progress(&task_upid, value).await;
+                        }
+                    }
+                }
+            };
+
+            let exit = match result {
+                Ok(progress) => {
+                    let state = progress.state.clone();
+                    if let Ok(value) = serde_json::to_value(&progress) {
+                        manager.update_progress(&task_upid, value).await;
+                    }
+                    match state {
+                        RollingApplyState::Completed => Ok(()),
+                        _ => Err(progress.error.unwrap_or_else(|| {
+                            format!("rolling apply ended in state {:?}", state)
+                        })),
+                    }
+                }
+                Err(e) => Err(e.to_string()),
+            };
+
+            manager.finish_task(&task_upid, exit).await;
+        });
+
+        upid
+    }
+
+    /// Create router with task endpoints
+    pub fn router() -> Router<Arc<AppContext>> {
+        Router::new()
+            .route("/api2/json/nodes/:node/tasks", get(list_tasks))
+            .route(
+                "/api2/json/nodes/:node/tasks/:upid/status",
+                get(get_task_status),
+            )
     }
 }
 
@@ -17,3 +242,200 @@ impl Default for TaskManager {
         Self::new()
     }
 }
+
+fn task_error(e: pve_network_core::NetworkError) -> (StatusCode, String) {
+    (StatusCode::FORBIDDEN, e.to_string())
+}
+
+/// List tasks of a node
+async fn list_tasks(
+    State(context): State<Arc<AppContext>>,
+    Path(node): Path<String>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<Value>, (StatusCode, String)> {
+    ensure_privilege(&context, &userid, AUDIT_PRIVILEGE, "listing tasks").map_err(task_error)?;
+    let tasks: Vec<TaskInfo> = context
+        .task_manager
+        .list_tasks()
+        .await
+        .into_iter()
+        .filter(|task| task.node == node)
+        .collect();
+
+    Ok(Json(serde_json::json!({ "data": tasks })))
+}
+
+/// Get status of a single task
+async fn get_task_status(
+    State(context): State<Arc<AppContext>>,
+    Path((_node, upid)): Path<(String, String)>,
+    AuthenticatedUser(userid): AuthenticatedUser,
+) -> std::result::Result<Json<Value>, (StatusCode, String)> {
+    ensure_privilege(&context, &userid, AUDIT_PRIVILEGE, "reading task status")
+        .map_err(task_error)?;
+    match context.task_manager.get_task(&upid).await {
+        Some(task) => Ok(Json(serde_json::json!({ "data": task }))),
+        None => Err((StatusCode::NOT_FOUND, format!("Task {} not found", upid))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use pve_network_apply::{ApplyResult, NodeTransport, RollingApplyOptions};
+    use pve_network_core::Result;
+
+    /// Transport whose nodes accept every configuration, except `failing`
+    struct FakeNodes {
+        failing: Option<String>,
+    }
+
+    #[async_trait]
+    impl NodeTransport for FakeNodes {
+        async fn current_config(&self, _node: &str) -> Result<NetworkConfiguration> {
+            Ok(NetworkConfiguration::default())
+        }
+
+        async fn apply_config(
+            &self,
+            node: &str,
+            _config: &NetworkConfiguration,
+        ) -> Result<ApplyResult> {
+            let success = self.failing.as_deref() != Some(node);
+            Ok(ApplyResult {
+                transaction_id: format!("tx_{}", node),
+                success,
+                applied_changes: Vec::new(),
+                warnings: Vec::new(),
+                error: (!success).then(|| "ifreload failed".to_string()),
+                duration_ms: 0,
+            })
+        }
+
+        async fn health_probe(&self, _node: &str, _transaction_id: &str) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    async fn run_rolling_apply(failing: Option<&str>) -> TaskInfo {
+        let manager = TaskManager::with_node("node1");
+        let orchestrator = Arc::new(RollingApplyOrchestrator::new(
+            Arc::new(FakeNodes {
+                failing: failing.map(str::to_string),
+            }),
+            RollingApplyOptions::default(),
+        ));
+        let nodes = vec!["node1".to_string(), "node2".to_string()];
+
+        let upid = manager
+            .spawn_rolling_apply(
+                orchestrator,
+                nodes,
+                NetworkConfiguration::default(),
+                "root@pam",
+            )
+            .await;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let task = manager.get_task(&upid).await.unwrap();
+                if task.status == TaskStatus::Stopped {
+                    return task;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upids_stay_unique() {
+        let manager = TaskManager::with_node("node1");
+        let first = manager.start_task("rollingapply", "a", "root@pam").await;
+        manager.tasks.write().await.remove(&first);
+
+        // Removing a task must not let the next one reuse its UPID
+        let second = manager.start_task("rollingapply", "a", "root@pam").await;
+        let third = manager.start_task("rollingapply", "a", "root@pam").await;
+        assert_ne!(first, second);
+        assert_ne!(second, third);
+    }
+
+    #[tokio::test]
+    async fn test_finished_tasks_are_pruned() {
+        let manager = TaskManager::with_node("node1");
+        let running = manager.start_task("rollingapply", "a", "root@pam").await;
+        let first = manager.start_task("rollingapply", "b", "root@pam").await;
+        manager.finish_task(&first, Ok(())).await;
+
+        for _ in 0..MAX_FINISHED_TASKS {
+            let upid = manager.start_task("rollingapply", "c", "root@pam").await;
+            manager.finish_task(&upid, Ok(())).await;
+        }
+
+        // The oldest finished task is dropped, running tasks are kept
+        assert_eq!(manager.list_tasks().await.len(), MAX_FINISHED_TASKS + 1);
+        assert!(manager.get_task(&first).await.is_none());
+        assert!(manager.get_task(&running).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_task_endpoints_require_audit() {
+        let context = AppContext::bootstrap().await.unwrap();
+        let upid = context
+            .task_manager
+            .start_task("rollingapply", "a", "root@pam")
+            .await;
+
+        let denied = list_tasks(
+            State(context.clone()),
+            Path("localhost".to_string()),
+            AuthenticatedUser("mallory@pve".to_string()),
+        )
+        .await;
+        assert_eq!(denied.unwrap_err().0, StatusCode::FORBIDDEN);
+        let denied = get_task_status(
+            State(context.clone()),
+            Path(("localhost".to_string(), upid.clone())),
+            AuthenticatedUser("mallory@pve".to_string()),
+        )
+        .await;
+        assert_eq!(denied.unwrap_err().0, StatusCode::FORBIDDEN);
+
+        let Json(status) = get_task_status(
+            State(context),
+            Path(("localhost".to_string(), upid.clone())),
+            AuthenticatedUser("root@pam".to_string()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status["data"]["upid"], upid);
+    }
+
+    #[tokio::test]
+    async fn test_rolling_apply_task_completes() {
+        let task = run_rolling_apply(None).await;
+
+        assert_eq!(task.task_type, "rollingapply");
+        assert_eq!(task.user, "root@pam");
+        assert_eq!(task.exitstatus.as_deref(), Some("OK"));
+        let progress = task.progress.unwrap();
+        assert_eq!(progress["state"], "Completed");
+        assert_eq!(progress["nodes"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rolling_apply_task_reports_failure() {
+        let task = run_rolling_apply(Some("node2")).await;
+
+        let exitstatus = task.exitstatus.unwrap();
+        assert!(
+            exitstatus.contains("apply failed on node2"),
+            "{}",
+            exitstatus
+        );
+        assert_eq!(task.progress.unwrap()["state"], "RolledBack");
+    }
+}
@@ -3,8 +3,18 @@
 #[cfg(test)]
 mod tests {
     use crate::network::{NetworkGetQuery, NetworkListQuery};
+    use crate::sdn::SdnApiState;
     use crate::NetworkAPI;
+    use axum::{http::StatusCode, response::Json};
     use pve_network_core::NetworkError;
+    use pve_sdn_core::controller::ControllerConfig;
+    use pve_sdn_core::{
+        CommandOutput, ControllerType, DeploymentStatus, RecordingExecutor, SdnStatusStore,
+        VNetConfig, ZoneConfig, ZoneType,
+    };
+    use pve_sdn_drivers::{PluginFactory, SdnApplyPipeline};
+    use serde_json::json;
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn test_network_api_creation() {
@@ -124,9 +134,22 @@ mod tests {
     #[tokio::test]
     async fn test_create_interface() {
         use crate::network::NetworkInterfaceRequest;
+        use pve_network_config::{NetworkConfigManager, PmxcfsConfig};
         use std::collections::HashMap;
 
-        let api = NetworkAPI::new();
+        // The new interface is added to the node configuration in pmxcfs
+        let dir = tempfile::tempdir().unwrap();
+        let node_dir = dir.path().join("nodes").join("test-node");
+        std::fs::create_dir_all(&node_dir).unwrap();
+        std::fs::write(
+            node_dir.join("network"),
+            "auto lo\niface lo inet loopback\n",
+        )
+        .unwrap();
+
+        let api = NetworkAPI::with_config_manager(NetworkConfigManager::with_pmxcfs(
+            PmxcfsConfig::with_base_path(dir.path()).unwrap(),
+        ));
         let request = NetworkInterfaceRequest {
             iface: "test0".to_string(),
             interface_type: "eth".to_string(),
@@ -153,6 +176,9 @@ mod tests {
         assert!(response.success);
         assert!(response.message.contains("test0"));
         assert!(response.message.contains("created"));
+
+        let written = std::fs::read_to_string(node_dir.join("network")).unwrap();
+        assert!(written.contains("iface test0 inet static"), "{}", written);
     }
 
     #[tokio::test]
@@ -290,4 +316,475 @@ mod tests {
         assert!(response.message.contains("reload"));
         assert!(response.task_id.is_some());
     }
+
+    fn sdn_state(executor: Arc<RecordingExecutor>) -> SdnApiState {
+        SdnApiState::new()
+            .with_plugin_factory(PluginFactory::with_executor(executor.clone()))
+            .with_executor(executor)
+    }
+
+    fn evpn_controller(name: &str) -> ControllerConfig {
+        let mut controller = ControllerConfig::new(ControllerType::Evpn, name.to_string());
+        controller.asn = Some(65000);
+        controller.peers = Some(vec!["192.0.2.1".to_string(), "192.0.2.2".to_string()]);
+        controller
+            .options
+            .insert("vtep-ip".to_string(), json!("192.0.2.10"));
+        controller
+    }
+
+    #[test]
+    fn test_sdn_state_shares_plugin_factory() {
+        let executor = Arc::new(RecordingExecutor::new());
+        let state = sdn_state(executor.clone())
+            .with_apply_pipeline(SdnApplyPipeline::new("node1"))
+            .with_executor(executor);
+
+        // Setting the executor keeps the drivers, the pipeline uses them too
+        let shared = state.apply_pipeline.plugin_factory().unwrap();
+        assert!(Arc::ptr_eq(shared, &state.plugin_factory));
+        assert!(Arc::ptr_eq(
+            &state.plugin_factory.executor(),
+            &state.executor
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sdn_controller_passwords_redacted() {
+        use pve_sdn_core::controller::BgpPeerConfig;
+
+        let state = sdn_state(Arc::new(RecordingExecutor::new()));
+
+        let mut controller = evpn_controller("evpn1");
+        let mut peer = BgpPeerConfig::new("192.0.2.3".parse().unwrap());
+        peer.password = Some("typed-secret".to_string());
+        controller.bgp_peers.push(peer);
+        controller.options.insert(
+            "peer-192.0.2.1".to_string(),
+            json!({ "remote-as": 65001, "password": "plain-secret" }),
+        );
+        state.create_controller(controller).await.unwrap();
+
+        let listed = serde_json::to_string(&state.list_controllers().await).unwrap();
+        let fetched = state.get_controller("evpn1").await.unwrap();
+        for response in [listed, serde_json::to_string(&fetched).unwrap()] {
+            assert!(!response.contains("secret"), "{}", response);
+        }
+        assert_eq!(fetched.options["peer-192.0.2.1"]["remote-as"], 65001);
+
+        // Sending the redacted controller back keeps the passwords
+        state
+            .update_controller("evpn1", fetched.clone())
+            .await
+            .unwrap();
+        {
+            let config = state.config.read().await;
+            let stored = &config.controllers["evpn1"];
+            assert_eq!(
+                stored.bgp_peers[0].password.as_deref(),
+                Some("typed-secret")
+            );
+            assert_eq!(stored.options["peer-192.0.2.1"]["password"], "plain-secret");
+
+            let redacted = serde_json::to_string(&config.redacted()).unwrap();
+            assert!(!redacted.contains("secret"), "{}", redacted);
+        }
+
+        // Empty passwords clear the configured ones
+        let mut cleared = fetched;
+        cleared.bgp_peers[0].password = Some(String::new());
+        cleared.options.insert(
+            "peer-192.0.2.1".to_string(),
+            json!({ "remote-as": 65001, "password": "" }),
+        );
+        state.update_controller("evpn1", cleared).await.unwrap();
+        let config = state.config.read().await;
+        let stored = &config.controllers["evpn1"];
+        assert!(stored.bgp_peers[0].password.is_none());
+        assert!(stored.options["peer-192.0.2.1"].get("password").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sdn_config_dns_key_redacted() {
+        use crate::context::AppContext;
+        use crate::sdn::{get_config, update_config};
+        use axum::extract::State;
+        use pve_shared_types::dns::{DnsConfig, DnsType};
+
+        let context = AppContext::bootstrap().await.unwrap();
+        {
+            let mut dns = DnsConfig::new("pdns".to_string(), DnsType::PowerDns);
+            dns.url = Some("http://192.0.2.53:8081/api/v1/servers/localhost".to_string());
+            dns.key = Some("api-secret".to_string());
+            let mut config = context.sdn_state.config.write().await;
+            config.add_dns(dns).unwrap();
+        }
+
+        let Json(response) = get_config(State(context.clone())).await.unwrap();
+        let fetched = response.data;
+        assert!(fetched.dns["pdns"].key.is_none());
+
+        // Sending the redacted configuration back keeps the key
+        let Json(response) = update_config(State(context.clone()), Json(fetched))
+            .await
+            .unwrap();
+        assert_eq!(response.data, ());
+        let config = context.sdn_state.config.read().await;
+        assert_eq!(config.dns["pdns"].key.as_deref(), Some("api-secret"));
+    }
+
+    #[tokio::test]
+    async fn test_sdn_controller_crud() {
+        let state = sdn_state(Arc::new(RecordingExecutor::new()));
+
+        state
+            .create_controller(evpn_controller("evpn1"))
+            .await
+            .unwrap();
+        state
+            .create_controller(evpn_controller("evpn0"))
+            .await
+            .unwrap();
+
+        let names: Vec<String> = state
+            .list_controllers()
+            .await
+            .into_iter()
+            .map(|c| c.controller)
+            .collect();
+        assert_eq!(names, vec!["evpn0", "evpn1"]);
+        assert_eq!(
+            state.get_controller("evpn1").await.unwrap().asn,
+            Some(65000)
+        );
+
+        let (status, _) = state
+            .create_controller(evpn_controller("evpn1"))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // The EVPN driver rejects controllers without an ASN
+        let mut invalid = evpn_controller("evpn2");
+        invalid.asn = None;
+        let (status, Json(error)) = state.create_controller(invalid).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.error.contains("requires an ASN"), "{}", error.error);
+        assert!(state.get_controller("evpn2").await.is_err());
+
+        let mut update = evpn_controller("ignored");
+        update.peers = Some(vec!["not-an-address".to_string()]);
+        let (status, _) = state
+            .update_controller("evpn1", update.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        update.peers = None;
+        update.asn = Some(65001);
+        state.update_controller("evpn1", update).await.unwrap();
+        let updated = state.get_controller("evpn1").await.unwrap();
+        assert_eq!(updated.controller, "evpn1");
+        assert_eq!(updated.asn, Some(65001));
+        assert!(state.get_controller("ignored").await.is_err());
+
+        let (status, _) = state
+            .update_controller("missing", evpn_controller("missing"))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // Controllers stay while a zone references them
+        let mut zone = ZoneConfig::new(ZoneType::Evpn, "tenant1".to_string());
+        zone.options
+            .insert("controller".to_string(), json!("evpn1"));
+        state
+            .config
+            .write()
+            .await
+            .zones
+            .insert("tenant1".to_string(), zone);
+
+        let (status, _) = state.delete_controller("evpn1").await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        state.config.write().await.zones.remove("tenant1");
+        state.delete_controller("evpn1").await.unwrap();
+
+        let (status, _) = state.get_controller("evpn1").await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = state.delete_controller("evpn1").await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sdn_zone_and_vnet_vni_uniqueness() {
+        let state = sdn_state(Arc::new(RecordingExecutor::new()));
+
+        let vxlan_zone = |name: &str, vni: u64| {
+            let mut zone = ZoneConfig::new(ZoneType::Vxlan, name.to_string());
+            zone.peers = Some(vec!["192.0.2.1".to_string()]);
+            zone.options.insert("vni".to_string(), json!(vni));
+            zone
+        };
+
+        state.create_zone(vxlan_zone("zone1", 100)).await.unwrap();
+        let (status, Json(error)) = state
+            .create_zone(vxlan_zone("zone2", 100))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.error.contains("VNI 100"), "{}", error.error);
+        assert!(!state.config.read().await.zones.contains_key("zone2"));
+
+        state.create_zone(vxlan_zone("zone2", 101)).await.unwrap();
+        let (status, _) = state
+            .update_zone("zone2", vxlan_zone("ignored", 100))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = state
+            .update_zone("missing", vxlan_zone("missing", 102))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let mut vnet = VNetConfig::new("vnet1".to_string(), "zone1".to_string());
+        vnet.tag = Some(101);
+        let (status, _) = state.create_vnet(vnet.clone()).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(state.config.read().await.vnets.is_empty());
+
+        vnet.tag = Some(200);
+        state.create_vnet(vnet.clone()).await.unwrap();
+        vnet.tag = Some(100);
+        let (status, _) = state.update_vnet("vnet1", vnet).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(state.config.read().await.vnets["vnet1"].tag, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_sdn_unknown_driver_types() {
+        let state = sdn_state(Arc::new(RecordingExecutor::new()));
+
+        // A typo deserializes as an external type no plugin provides
+        let zone: ZoneConfig =
+            serde_json::from_value(json!({"type": "vxaln", "zone": "zone1"})).unwrap();
+        assert_eq!(zone.zone_type, ZoneType::External("vxaln".to_string()));
+        let (status, Json(error)) = state.create_zone(zone).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "Unknown zone type 'vxaln'");
+        assert!(state.config.read().await.zones.is_empty());
+
+        state
+            .create_zone(ZoneConfig::new(ZoneType::Simple, "zone1".to_string()))
+            .await
+            .unwrap();
+        let zone = ZoneConfig::new(ZoneType::External("smple".to_string()), String::new());
+        let (status, _) = state.update_zone("zone1", zone).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            state.config.read().await.zones["zone1"].zone_type,
+            ZoneType::Simple
+        );
+
+        let controller =
+            ControllerConfig::new(ControllerType::External("evnp".to_string()), "c".into());
+        let (status, Json(error)) = state.create_controller(controller).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "Unknown controller type 'evnp'");
+
+        // Whole configurations are checked against the loaded drivers as well
+        let mut config = state.config.read().await.clone();
+        config.zones.get_mut("zone1").unwrap().zone_type = ZoneType::External("x".into());
+        assert!(config.validate_with(&*state.plugin_factory).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sdn_controller_status() {
+        let executor = Arc::new(RecordingExecutor::new());
+        executor.respond("vtysh", CommandOutput::failure(1, "bgpd is not running"));
+        let state = sdn_state(executor.clone());
+
+        state
+            .create_controller(evpn_controller("evpn1"))
+            .await
+            .unwrap();
+
+        let status = state.controller_status("evpn1").await.unwrap();
+        assert!(!status.running);
+        assert!(status.bgp_peers.is_empty());
+        assert!(status.last_error.unwrap().contains("bgpd is not running"));
+        assert!(executor
+            .command_lines()
+            .contains(&"vtysh -c show bgp l2vpn evpn summary json".to_string()));
+
+        // Controllers of other nodes are not queried locally
+        let mut remote = evpn_controller("evpn2");
+        remote.node = Some("node2".to_string());
+        state.create_controller(remote).await.unwrap();
+
+        executor.clear();
+        let (status, _) = state.controller_status("evpn2").await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        executor.assert_commands(&[]);
+
+        let (status, _) = state.controller_status("missing").await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sdn_node_and_cluster_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Arc::new(RecordingExecutor::new());
+        executor.respond(
+            "ip -j -d link show dev sdnbr1",
+            CommandOutput::success(r#"[{"ifname": "sdnbr1", "flags": ["UP"]}]"#),
+        );
+
+        let interfaces = dir.path().join("interfaces.d/sdn");
+        let state = sdn_state(executor)
+            .with_apply_pipeline(SdnApplyPipeline::new("node1").with_paths(
+                &interfaces,
+                dir.path().join("frr.conf"),
+                dir.path().join("frr.conf.local"),
+            ))
+            .with_status_store(SdnStatusStore::with_base_path(dir.path().join("pve")));
+
+        {
+            let mut config = state.config.write().await;
+            let mut zone = ZoneConfig::new(ZoneType::Simple, "zone1".to_string());
+            zone.bridge = Some("sdnbr1".to_string());
+            config.add_zone(zone).unwrap();
+            config.version = Some(4);
+        }
+
+        let status = state.node_status().await.unwrap();
+        assert_eq!(status.applied_version, None);
+        assert_eq!(status.zones[0].status, DeploymentStatus::Pending);
+
+        std::fs::create_dir_all(interfaces.parent().unwrap()).unwrap();
+        std::fs::write(&interfaces, "#version:4\n").unwrap();
+
+        let status = state.node_status().await.unwrap();
+        assert_eq!(status.applied_version, Some(4));
+        assert_eq!(status.zones[0].status, DeploymentStatus::Available);
+
+        // Status published by another node for an older version
+        let mut remote = status.clone();
+        remote.node = "node2".to_string();
+        remote.version = 3;
+        state.status_store.publish(&remote).await.unwrap();
+
+        let cluster = state.cluster_status().await.unwrap();
+        assert_eq!(cluster.version, 4);
+        assert_eq!(cluster.nodes.len(), 2);
+        assert_eq!(cluster.zones[0].status, DeploymentStatus::Pending);
+        assert_eq!(cluster.zones[0].nodes["node1"], DeploymentStatus::Available);
+        assert_eq!(cluster.zones[0].nodes["node2"], DeploymentStatus::Pending);
+
+        // A reload continues from the version in pmxcfs, not the local one
+        let mut running = state.config.read().await.clone();
+        running.version = Some(7);
+        state
+            .status_store
+            .write_running_config(&running)
+            .await
+            .unwrap();
+
+        let mut config = state.config.read().await.clone();
+        assert_eq!(state.publish_running_config(&mut config).await.unwrap(), 8);
+        assert_eq!(config.version, Some(8));
+        let published = state.status_store.read_running_config().await.unwrap();
+        assert_eq!(published.unwrap().version, Some(8));
+
+        let status = state.node_status().await.unwrap();
+        assert_eq!(status.version, 8);
+        assert_eq!(status.zones[0].status, DeploymentStatus::Pending);
+        assert_eq!(state.cluster_status().await.unwrap().version, 8);
+    }
+
+    #[tokio::test]
+    async fn test_get_topology() {
+        use crate::context::AppContext;
+        use crate::network::get_topology;
+        use axum::extract::{Path, State};
+        use pve_network_core::topology::{TopologyEdgeKind, TopologyNodeKind};
+
+        let context = AppContext::bootstrap().await.unwrap();
+        {
+            let mut config = context.sdn_state.config.write().await;
+            let mut zone = ZoneConfig::new(ZoneType::Vlan, "zone1".to_string());
+            zone.bridge = Some("vmbr0".to_string());
+            config.add_zone(zone).unwrap();
+            config
+                .add_vnet(VNetConfig::new("vnet1".to_string(), "zone1".to_string()))
+                .unwrap();
+
+            let mut remote = ZoneConfig::new(ZoneType::Vlan, "zone2".to_string());
+            remote.bridge = Some("vmbr1".to_string());
+            remote.nodes = Some(vec!["other-node".to_string()]);
+            config.add_zone(remote).unwrap();
+            config
+                .add_vnet(VNetConfig::new("vnet2".to_string(), "zone2".to_string()))
+                .unwrap();
+        }
+
+        let Json(topology) = get_topology(State(context), Path("test-node".to_string()))
+            .await
+            .unwrap();
+
+        let node = |id: &str| topology.nodes.iter().find(|node| node.id == id);
+        assert_eq!(node("iface:lo").unwrap().kind, TopologyNodeKind::Loopback);
+        assert_eq!(node("zone:zone1").unwrap().kind, TopologyNodeKind::Zone);
+        assert_eq!(node("vnet:vnet1").unwrap().kind, TopologyNodeKind::Vnet);
+        // Zones of other nodes have no devices on this one
+        assert!(node("zone:zone2").is_none());
+        assert!(node("vnet:vnet2").is_none());
+
+        let edge = |from: &str, to: &str| {
+            topology
+                .edges
+                .iter()
+                .find(|edge| edge.from == from && edge.to == to)
+                .map(|edge| edge.kind)
+        };
+        assert_eq!(
+            edge("iface:vmbr0", "zone:zone1"),
+            Some(TopologyEdgeKind::Uplink)
+        );
+        assert_eq!(
+            edge("zone:zone1", "vnet:vnet1"),
+            Some(TopologyEdgeKind::Vnet)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schedule_apply_requires_privilege() {
+        use crate::auth::AuthenticatedUser;
+        use crate::context::AppContext;
+        use crate::network::{schedule_apply, ScheduleApplyRequest};
+        use axum::extract::{Path, State};
+        use pve_network_core::NetworkConfiguration;
+
+        let context = AppContext::bootstrap().await.unwrap();
+        let request = ScheduleApplyRequest {
+            config: NetworkConfiguration::default(),
+            execute_at: 4_000_000_000,
+            expires_at: None,
+            confirm_timeout: None,
+            comment: None,
+        };
+
+        let (status, _) = schedule_apply(
+            State(context.clone()),
+            Path("test-node".to_string()),
+            AuthenticatedUser("nobody@pve".to_string()),
+            Json(request),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(context.apply_scheduler.list().await.unwrap().is_empty());
+    }
 }
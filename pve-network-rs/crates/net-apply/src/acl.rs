@@ -0,0 +1,462 @@
+//! Privilege lookup from the PVE user and ACL configuration
+//!
+//! Reads `/etc/pve/user.cfg` like the PVE access control stack does: users,
+//! API tokens, groups, custom roles and ACL entries. Privileges are resolved
+//! on a single ACL path, walking from `/` down to it; entries on a deeper
+//! path replace the roles granted further up, and user entries take
+//! precedence over group entries on the same path. As in PVE, `root@pam`
+//! holds every privilege and the built-in roles cannot be redefined.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+
+use crate::change_request::PrivilegeProvider;
+
+/// Default location of the PVE user and ACL configuration
+pub const DEFAULT_USER_CFG_PATH: &str = "/etc/pve/user.cfg";
+
+/// Superuser holding every privilege
+const ROOT_USER: &str = "root@pam";
+
+/// Role granting every privilege
+const ADMINISTRATOR_ROLE: &str = "Administrator";
+
+/// Role revoking every privilege
+const NO_ACCESS_ROLE: &str = "NoAccess";
+
+#[derive(Debug, Clone)]
+struct AclEntry {
+    propagate: bool,
+    path: String,
+    ugids: Vec<String>,
+    roles: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct TokenEntry {
+    expire: u64,
+    privsep: bool,
+}
+
+/// Parsed PVE user configuration
+#[derive(Debug, Clone, Default)]
+pub struct UserConfig {
+    /// Enabled users with their expiry (0 = never)
+    users: HashMap<String, (bool, u64)>,
+    tokens: HashMap<String, TokenEntry>,
+    groups: HashMap<String, HashSet<String>>,
+    roles: HashMap<String, HashSet<String>>,
+    acl: Vec<AclEntry>,
+}
+
+/// Resolved set of roles of an ACL path
+enum Roles {
+    All,
+    Some(HashSet<String>),
+}
+
+impl UserConfig {
+    /// Parse the content of `user.cfg`
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(':').collect();
+            match fields.as_slice() {
+                ["user", userid, enable, expire, ..] => {
+                    config.users.insert(
+                        userid.to_string(),
+                        (*enable != "0", expire.parse().unwrap_or(0)),
+                    );
+                }
+                ["token", tokenid, expire, privsep, ..] => {
+                    config.tokens.insert(
+                        tokenid.to_string(),
+                        TokenEntry {
+                            expire: expire.parse().unwrap_or(0),
+                            privsep: *privsep != "0",
+                        },
+                    );
+                }
+                ["group", group, members, ..] => {
+                    config
+                        .groups
+                        .insert(group.to_string(), split_list(members).collect());
+                }
+                ["role", role, privileges, ..] => {
+                    config
+                        .roles
+                        .insert(role.to_string(), split_list(privileges).collect());
+                }
+                ["acl", propagate, path, ugids, roles, ..] => {
+                    config.acl.push(AclEntry {
+                        propagate: *propagate != "0",
+                        path: normalize_path(path),
+                        ugids: split_list(ugids).collect(),
+                        roles: split_list(roles).collect(),
+                    });
+                }
+                _ => warn!("Ignoring unknown user.cfg line: {}", line),
+            }
+        }
+
+        config
+    }
+
+    /// Whether `userid` (a user or an API token) holds `privilege` on `path`
+    pub fn has_privilege(&self, userid: &str, path: &str, privilege: &str) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        match userid.split_once('!') {
+            Some((user, _)) => {
+                let token = match self.tokens.get(userid) {
+                    Some(token) if token.expire == 0 || token.expire > now => token,
+                    _ => return false,
+                };
+                if user != ROOT_USER
+                    && (!self.user_enabled(user, now) || !self.grants(user, path, privilege))
+                {
+                    return false;
+                }
+                // Privilege separated tokens only get what both user and token have
+                !token.privsep || self.grants(userid, path, privilege)
+            }
+            None if userid == ROOT_USER => true,
+            None => self.user_enabled(userid, now) && self.grants(userid, path, privilege),
+        }
+    }
+
+    fn user_enabled(&self, user: &str, now: u64) -> bool {
+        matches!(self.users.get(user), Some((true, expire)) if *expire == 0 || *expire > now)
+    }
+
+    fn grants(&self, ugid: &str, path: &str, privilege: &str) -> bool {
+        match self.path_roles(ugid, path) {
+            Roles::All => true,
+            Roles::Some(roles) => roles.iter().any(|role| match builtin_role(role) {
+                Some(privileges) => privileges.contains(&privilege),
+                None => self
+                    .roles
+                    .get(role)
+                    .is_some_and(|privileges| privileges.contains(privilege)),
+            }),
+        }
+    }
+
+    fn path_roles(&self, ugid: &str, path: &str) -> Roles {
+        let target = normalize_path(path);
+        let groups: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, members)| members.contains(ugid))
+            .map(|(group, _)| format!("@{}", group))
+            .collect();
+
+        let mut roles = HashSet::new();
+        for level in path_levels(&target) {
+            let is_target = level == target;
+            let entries: Vec<&AclEntry> = self
+                .acl
+                .iter()
+                .filter(|entry| entry.path == level && (is_target || entry.propagate))
+                .collect();
+
+            let user_roles: HashSet<String> = entries
+                .iter()
+                .filter(|entry| entry.ugids.iter().any(|id| id == ugid))
+                .flat_map(|entry| entry.roles.iter().cloned())
+                .collect();
+            let group_roles: HashSet<String> = entries
+                .iter()
+                .filter(|entry| entry.ugids.iter().any(|id| groups.contains(id)))
+                .flat_map(|entry| entry.roles.iter().cloned())
+                .collect();
+
+            if !user_roles.is_empty() {
+                roles = user_roles;
+            } else if !group_roles.is_empty() {
+                roles = group_roles;
+            }
+        }
+
+        if roles.contains(NO_ACCESS_ROLE) {
+            Roles::Some(HashSet::new())
+        } else if roles.contains(ADMINISTRATOR_ROLE) {
+            Roles::All
+        } else {
+            Roles::Some(roles)
+        }
+    }
+}
+
+/// Privileges from `user.cfg` on a fixed ACL path
+///
+/// The file is read on every lookup, so ACL changes take effect without a
+/// daemon restart. A missing or unreadable file grants nothing beyond the
+/// implicit privileges of `root@pam`.
+#[derive(Debug, Clone)]
+pub struct AclPrivileges {
+    user_cfg: PathBuf,
+    path: String,
+}
+
+impl AclPrivileges {
+    /// Check privileges on `path` using the default `user.cfg`
+    pub fn new(path: &str) -> Self {
+        Self::with_user_cfg(DEFAULT_USER_CFG_PATH, path)
+    }
+
+    /// Check privileges on `path` using the given `user.cfg`
+    pub fn with_user_cfg<P: AsRef<Path>>(user_cfg: P, path: &str) -> Self {
+        Self {
+            user_cfg: user_cfg.as_ref().to_path_buf(),
+            path: normalize_path(path),
+        }
+    }
+
+    /// ACL path privileges are checked on
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl PrivilegeProvider for AclPrivileges {
+    fn has_privilege(&self, user: &str, privilege: &str) -> bool {
+        match std::fs::read_to_string(&self.user_cfg) {
+            Ok(content) => UserConfig::parse(&content).has_privilege(user, &self.path, privilege),
+            Err(e) => {
+                warn!("Failed to read {}: {}", self.user_cfg.display(), e);
+                user == ROOT_USER
+            }
+        }
+    }
+}
+
+/// Privileges of the built-in PVE roles, other than Administrator and NoAccess
+fn builtin_role(role: &str) -> Option<&'static [&'static str]> {
+    Some(match role {
+        "PVEAdmin" => &[
+            "Datastore.Allocate",
+            "Datastore.AllocateSpace",
+            "Datastore.AllocateTemplate",
+            "Datastore.Audit",
+            "Group.Allocate",
+            "Mapping.Audit",
+            "Mapping.Modify",
+            "Mapping.Use",
+            "Pool.Allocate",
+            "Pool.Audit",
+            "Realm.AllocateUser",
+            "SDN.Allocate",
+            "SDN.Audit",
+            "SDN.Use",
+            "Sys.Audit",
+            "Sys.Console",
+            "Sys.Syslog",
+            "User.Modify",
+            "VM.Allocate",
+            "VM.Audit",
+            "VM.Backup",
+            "VM.Clone",
+            "VM.Config.CDROM",
+            "VM.Config.CPU",
+            "VM.Config.Cloudinit",
+            "VM.Config.Disk",
+            "VM.Config.HWType",
+            "VM.Config.Memory",
+            "VM.Config.Network",
+            "VM.Config.Options",
+            "VM.Console",
+            "VM.Migrate",
+            "VM.Monitor",
+            "VM.PowerMgmt",
+            "VM.Snapshot",
+            "VM.Snapshot.Rollback",
+        ],
+        "PVEAuditor" => &[
+            "Datastore.Audit",
+            "Mapping.Audit",
+            "Pool.Audit",
+            "SDN.Audit",
+            "Sys.Audit",
+            "VM.Audit",
+        ],
+        "PVESysAdmin" => &["Sys.Audit", "Sys.Console", "Sys.Syslog"],
+        "PVEVMAdmin" => &[
+            "VM.Allocate",
+            "VM.Audit",
+            "VM.Backup",
+            "VM.Clone",
+            "VM.Config.CDROM",
+            "VM.Config.CPU",
+            "VM.Config.Cloudinit",
+            "VM.Config.Disk",
+            "VM.Config.HWType",
+            "VM.Config.Memory",
+            "VM.Config.Network",
+            "VM.Config.Options",
+            "VM.Console",
+            "VM.Migrate",
+            "VM.Monitor",
+            "VM.PowerMgmt",
+            "VM.Snapshot",
+            "VM.Snapshot.Rollback",
+        ],
+        "PVEVMUser" => &[
+            "VM.Audit",
+            "VM.Backup",
+            "VM.Config.CDROM",
+            "VM.Config.Cloudinit",
+            "VM.Console",
+            "VM.PowerMgmt",
+        ],
+        "PVETemplateUser" => &["VM.Audit", "VM.Clone"],
+        "PVEDatastoreAdmin" => &[
+            "Datastore.Allocate",
+            "Datastore.AllocateSpace",
+            "Datastore.AllocateTemplate",
+            "Datastore.Audit",
+        ],
+        "PVEDatastoreUser" => &["Datastore.AllocateSpace", "Datastore.Audit"],
+        "PVEPoolAdmin" => &["Pool.Allocate", "Pool.Audit"],
+        "PVEPoolUser" => &["Pool.Audit"],
+        "PVEUserAdmin" => &["Group.Allocate", "Realm.AllocateUser", "User.Modify"],
+        "PVESDNAdmin" => &["SDN.Allocate", "SDN.Audit", "SDN.Use"],
+        "PVESDNUser" => &["SDN.Audit", "SDN.Use"],
+        "PVEMappingAdmin" => &["Mapping.Audit", "Mapping.Modify", "Mapping.Use"],
+        "PVEMappingUser" => &["Mapping.Audit", "Mapping.Use"],
+        _ => return None,
+    })
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = String> + '_ {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+}
+
+fn normalize_path(path: &str) -> String {
+    let trimmed = path.trim_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// `/`, `/nodes`, `/nodes/node1` for `/nodes/node1`
+fn path_levels(path: &str) -> Vec<String> {
+    let mut levels = vec!["/".to_string()];
+    let mut current = String::new();
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        current.push('/');
+        current.push_str(component);
+        levels.push(current.clone());
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change_request::{APPROVE_PRIVILEGE, MODIFY_PRIVILEGE};
+
+    const USER_CFG: &str = "\
+user:root@pam:1:0:::root@example.com:::
+user:alice@pve:1:0:Alice::::
+user:bob@pve:1:0:Bob::::
+user:carol@pve:0:0:Carol::::
+user:dave@pve:1:0:Dave::::
+token:alice@pve!ci:0:1::
+token:bob@pve!ci:0:0::
+group:netops:bob@pve,carol@pve,dave@pve::
+role:NetworkApprover:Sys.Audit,Sys.Modify:
+acl:1:/nodes:@netops:NetworkApprover:
+acl:1:/nodes/node1:dave@pve:NoAccess:
+acl:1:/:alice@pve:Administrator:
+acl:1:/nodes/node1:alice@pve!ci:PVEAuditor:
+";
+
+    #[test]
+    fn test_acl_resolution() {
+        let config = UserConfig::parse(USER_CFG);
+
+        // Group role propagated from /nodes
+        assert!(config.has_privilege("bob@pve", "/nodes/node1", APPROVE_PRIVILEGE));
+        assert!(!config.has_privilege("bob@pve", "/", APPROVE_PRIVILEGE));
+
+        // Disabled users hold nothing
+        assert!(!config.has_privilege("carol@pve", "/nodes/node1", APPROVE_PRIVILEGE));
+
+        // Deeper user entries replace group roles
+        assert!(!config.has_privilege("dave@pve", "/nodes/node1", APPROVE_PRIVILEGE));
+        assert!(config.has_privilege("dave@pve", "/nodes/node2", APPROVE_PRIVILEGE));
+
+        // Administrator and root@pam hold every privilege
+        assert!(config.has_privilege("alice@pve", "/nodes/node1", "Sys.Anything"));
+        assert!(config.has_privilege("root@pam", "/nodes/node1", APPROVE_PRIVILEGE));
+        assert!(config.has_privilege("root@pam", "/", MODIFY_PRIVILEGE));
+        assert!(!config.has_privilege("mallory@pve", "/nodes/node1", APPROVE_PRIVILEGE));
+    }
+
+    #[test]
+    fn test_acl_tokens() {
+        let config = UserConfig::parse(USER_CFG);
+
+        // Privilege separated tokens are limited to their own ACL
+        assert!(!config.has_privilege("alice@pve!ci", "/nodes/node1", APPROVE_PRIVILEGE));
+
+        // Tokens without separation inherit the user's privileges
+        assert!(config.has_privilege("bob@pve!ci", "/nodes/node1", APPROVE_PRIVILEGE));
+
+        // Unknown tokens hold nothing
+        assert!(!config.has_privilege("bob@pve!other", "/nodes/node1", APPROVE_PRIVILEGE));
+    }
+
+    #[test]
+    fn test_acl_builtin_roles() {
+        let config = UserConfig::parse(
+            "\
+user:erin@pve:1:0:Erin::::
+user:frank@pve:1:0:Frank::::
+role:PVEAuditor:Sys.Modify:
+acl:1:/:erin@pve:PVEAdmin:
+acl:1:/:frank@pve:PVEAuditor:
+",
+        );
+
+        assert!(config.has_privilege("erin@pve", "/nodes/node1", "Sys.Audit"));
+        assert!(config.has_privilege("erin@pve", "/nodes/node1", "SDN.Allocate"));
+        assert!(!config.has_privilege("erin@pve", "/nodes/node1", MODIFY_PRIVILEGE));
+        assert!(config.has_privilege("frank@pve", "/nodes/node1", "Sys.Audit"));
+
+        // Built-in roles cannot be redefined in user.cfg
+        assert!(!config.has_privilege("frank@pve", "/nodes/node1", MODIFY_PRIVILEGE));
+    }
+
+    #[test]
+    fn test_acl_privileges_read_user_cfg() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let user_cfg = temp_dir.path().join("user.cfg");
+
+        let privileges = AclPrivileges::with_user_cfg(&user_cfg, "/nodes/node1/");
+        assert_eq!(privileges.path(), "/nodes/node1");
+        assert!(!privileges.has_privilege("bob@pve", APPROVE_PRIVILEGE));
+        assert!(privileges.has_privilege("root@pam", APPROVE_PRIVILEGE));
+
+        std::fs::write(&user_cfg, USER_CFG).unwrap();
+        assert!(privileges.has_privilege("bob@pve", APPROVE_PRIVILEGE));
+    }
+}
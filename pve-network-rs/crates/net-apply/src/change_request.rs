@@ -0,0 +1,672 @@
+//! Change requests with four-eyes approval before apply
+//!
+//! A change request wraps a prepared [`Transaction`] together with its
+//! validation results. It is proposed by one user and must be approved by a
+//! different user holding the approval privilege before it can be applied.
+//! PVE has no dedicated approval privilege and rejects unknown ones in
+//! custom roles, so approvers need `Sys.Modify` on `/nodes/<node>` like
+//! proposers; the four eyes come from requiring a different user.
+//! Every state transition is recorded with user and timestamp, and requests
+//! are persisted as JSON files.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use pve_network_core::error::ApiError;
+use pve_network_core::{NetworkConfiguration, NetworkError, Result};
+
+use crate::scheduler::ApplyBackend;
+use crate::transaction::{ApplyResult, Transaction};
+
+/// Default directory for persisted change requests
+pub const DEFAULT_CHANGE_REQUEST_DIR: &str = "/var/lib/pve-network/change-requests";
+
+/// Privilege required to approve or reject change requests
+///
+/// Granted to approvers through a custom role, e.g.
+/// `role:NetworkApprover:Sys.Audit,Sys.Modify:` in `user.cfg`.
+pub const APPROVE_PRIVILEGE: &str = "Sys.Modify";
+
+/// Privilege required to propose and apply change requests
+pub const MODIFY_PRIVILEGE: &str = "Sys.Modify";
+
+/// Lookup of user privileges
+pub trait PrivilegeProvider: Send + Sync {
+    /// Whether the user holds the given privilege
+    fn has_privilege(&self, user: &str, privilege: &str) -> bool;
+}
+
+/// Static privilege table
+#[derive(Debug, Clone, Default)]
+pub struct StaticPrivileges {
+    grants: HashMap<String, HashSet<String>>,
+}
+
+impl StaticPrivileges {
+    /// Create empty privilege table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant a privilege to a user
+    pub fn grant(mut self, user: &str, privilege: &str) -> Self {
+        self.grants
+            .entry(user.to_string())
+            .or_default()
+            .insert(privilege.to_string());
+        self
+    }
+}
+
+impl PrivilegeProvider for StaticPrivileges {
+    fn has_privilege(&self, user: &str, privilege: &str) -> bool {
+        self.grants
+            .get(user)
+            .is_some_and(|privileges| privileges.contains(privilege))
+    }
+}
+
+/// Validation results attached to a change request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Whether the proposed configuration passed validation
+    pub valid: bool,
+    /// Validation errors
+    pub errors: Vec<String>,
+}
+
+/// Change request states
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ChangeRequestState {
+    /// Waiting for approval
+    Proposed,
+    /// Approved, ready to be applied
+    Approved,
+    /// Rejected by an approver
+    Rejected,
+    /// Applied successfully
+    Applied,
+    /// Apply failed
+    Failed,
+}
+
+/// Recorded state transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeRequestTransition {
+    /// State entered
+    pub state: ChangeRequestState,
+    /// User causing the transition
+    pub user: String,
+    /// Time of the transition (unix epoch)
+    pub timestamp: u64,
+    /// Optional comment
+    pub comment: Option<String>,
+}
+
+/// Proposed network change awaiting approval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeRequest {
+    /// Unique change request ID
+    pub id: String,
+    /// Description of the change
+    pub description: String,
+    /// User who proposed the change
+    pub proposer: String,
+    /// Prepared transaction including the semantic diff
+    pub transaction: Transaction,
+    /// Validation results of the proposed configuration
+    pub validation: ValidationReport,
+    /// Current state
+    pub state: ChangeRequestState,
+    /// All state transitions
+    pub history: Vec<ChangeRequestTransition>,
+    /// Result of the apply, once applied
+    pub apply_result: Option<ApplyResult>,
+}
+
+impl ChangeRequest {
+    fn transition(&mut self, state: ChangeRequestState, user: &str, comment: Option<String>) {
+        self.state = state.clone();
+        self.history.push(ChangeRequestTransition {
+            state,
+            user: user.to_string(),
+            timestamp: now_secs(),
+            comment,
+        });
+    }
+
+    /// User who approved the request, if any
+    pub fn approver(&self) -> Option<&str> {
+        self.history
+            .iter()
+            .rev()
+            .find(|transition| transition.state == ChangeRequestState::Approved)
+            .map(|transition| transition.user.as_str())
+    }
+}
+
+/// Persistent store and workflow for change requests
+pub struct ChangeRequestManager {
+    /// Backend preparing and applying transactions
+    backend: Arc<dyn ApplyBackend>,
+    /// Privilege lookup for proposers and approvers
+    privileges: Arc<dyn PrivilegeProvider>,
+    /// Directory holding one JSON file per change request
+    store_dir: PathBuf,
+    /// Serializes state changes within this process
+    lock: Mutex<()>,
+}
+
+impl ChangeRequestManager {
+    /// Create manager using the default store directory
+    pub async fn new(
+        backend: Arc<dyn ApplyBackend>,
+        privileges: Arc<dyn PrivilegeProvider>,
+    ) -> Result<Self> {
+        Self::with_store_dir(backend, privileges, DEFAULT_CHANGE_REQUEST_DIR).await
+    }
+
+    /// Create manager with custom store directory
+    pub async fn with_store_dir<P: AsRef<Path>>(
+        backend: Arc<dyn ApplyBackend>,
+        privileges: Arc<dyn PrivilegeProvider>,
+        store_dir: P,
+    ) -> Result<Self> {
+        let store_dir = store_dir.as_ref().to_path_buf();
+        if !store_dir.exists() {
+            fs::create_dir_all(&store_dir).await?;
+        }
+
+        Ok(Self {
+            backend,
+            privileges,
+            store_dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Propose a configuration change
+    pub async fn propose(
+        &self,
+        user: &str,
+        description: &str,
+        config: NetworkConfiguration,
+    ) -> Result<ChangeRequest> {
+        self.ensure_privilege(user, MODIFY_PRIVILEGE, "propose change request")?;
+
+        let validation = match self.backend.validate_config(&config).await {
+            Ok(()) => ValidationReport {
+                valid: true,
+                errors: Vec::new(),
+            },
+            Err(e) => ValidationReport {
+                valid: false,
+                errors: vec![e.to_string()],
+            },
+        };
+
+        let mut transaction = self.backend.prepare_transaction(&config).await?;
+
+        let _guard = self.lock.lock().await;
+
+        let mut sequence = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        while self.request_path(&format!("cr_{}", sequence)).exists() {
+            sequence += 1;
+        }
+        let id = format!("cr_{}", sequence);
+
+        transaction
+            .metadata
+            .insert("change_request".to_string(), id.clone());
+        transaction
+            .metadata
+            .insert("proposed_by".to_string(), user.to_string());
+
+        let mut request = ChangeRequest {
+            id,
+            description: description.to_string(),
+            proposer: user.to_string(),
+            transaction,
+            validation,
+            state: ChangeRequestState::Proposed,
+            history: Vec::new(),
+            apply_result: None,
+        };
+        request.transition(ChangeRequestState::Proposed, user, None);
+
+        self.save(&request).await?;
+        info!(
+            "Change request {} proposed by {} ({} change(s))",
+            request.id,
+            user,
+            request.transaction.changes.len()
+        );
+
+        Ok(request)
+    }
+
+    /// List all change requests, newest first
+    pub async fn list(&self) -> Result<Vec<ChangeRequest>> {
+        let mut requests = Vec::new();
+        let mut entries = fs::read_dir(&self.store_dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            match fs::read_to_string(&path).await {
+                Ok(content) => match serde_json::from_str::<ChangeRequest>(&content) {
+                    Ok(request) => requests.push(request),
+                    Err(e) => warn!("Ignoring invalid change request {}: {}", path.display(), e),
+                },
+                Err(e) => warn!("Failed to read change request {}: {}", path.display(), e),
+            }
+        }
+
+        requests.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(requests)
+    }
+
+    /// Get a change request by ID
+    pub async fn get(&self, id: &str) -> Result<ChangeRequest> {
+        let path = self.request_path(id);
+        if !is_change_request_id(id) || !path.exists() {
+            return Err(NetworkError::Api(ApiError::NotFound {
+                resource: format!("change request {}", id),
+            }));
+        }
+
+        let content = fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Approve a proposed change request
+    pub async fn approve(
+        &self,
+        id: &str,
+        user: &str,
+        comment: Option<String>,
+    ) -> Result<ChangeRequest> {
+        let _guard = self.lock.lock().await;
+
+        let mut request = self.get(id).await?;
+        Self::ensure_state(&request, &[ChangeRequestState::Proposed], "approve")?;
+        self.ensure_privilege(user, APPROVE_PRIVILEGE, "approve change request")?;
+
+        // API tokens act on behalf of their owner
+        if token_owner(&request.proposer) == token_owner(user) {
+            return Err(NetworkError::Api(ApiError::PermissionDenied {
+                operation: format!("approve own change request {}", id),
+            }));
+        }
+
+        if !request.validation.valid {
+            return Err(NetworkError::Api(ApiError::Conflict {
+                resource: format!("change request {}", id),
+                message: "proposed configuration failed validation".to_string(),
+            }));
+        }
+
+        request.transition(ChangeRequestState::Approved, user, comment);
+        self.save(&request).await?;
+        info!("Change request {} approved by {}", id, user);
+
+        Ok(request)
+    }
+
+    /// Reject a proposed or approved change request
+    pub async fn reject(
+        &self,
+        id: &str,
+        user: &str,
+        comment: Option<String>,
+    ) -> Result<ChangeRequest> {
+        let _guard = self.lock.lock().await;
+
+        let mut request = self.get(id).await?;
+        Self::ensure_state(
+            &request,
+            &[ChangeRequestState::Proposed, ChangeRequestState::Approved],
+            "reject",
+        )?;
+        self.ensure_privilege(user, APPROVE_PRIVILEGE, "reject change request")?;
+
+        request.transition(ChangeRequestState::Rejected, user, comment);
+        self.save(&request).await?;
+        info!("Change request {} rejected by {}", id, user);
+
+        Ok(request)
+    }
+
+    /// Apply an approved change request
+    pub async fn apply(&self, id: &str, user: &str) -> Result<ChangeRequest> {
+        let _guard = self.lock.lock().await;
+
+        let mut request = self.get(id).await?;
+        Self::ensure_state(&request, &[ChangeRequestState::Approved], "apply")?;
+        self.ensure_privilege(user, MODIFY_PRIVILEGE, "apply change request")?;
+
+        // The approved diff is only meaningful against the configuration it was computed from
+        let current = self.backend.current_config().await?;
+        if serde_json::to_value(&current)?
+            != serde_json::to_value(&request.transaction.original_config)?
+        {
+            return Err(NetworkError::Api(ApiError::Conflict {
+                resource: format!("change request {}", id),
+                message: "network configuration changed since the request was proposed".to_string(),
+            }));
+        }
+
+        let mut transaction = request.transaction.clone();
+        if let Some(approver) = request.approver() {
+            transaction
+                .metadata
+                .insert("approved_by".to_string(), approver.to_string());
+        }
+
+        let result = self.backend.apply_transaction(transaction).await?;
+        let state = if result.success {
+            ChangeRequestState::Applied
+        } else {
+            ChangeRequestState::Failed
+        };
+        let comment = result.error.clone();
+
+        request.apply_result = Some(result);
+        request.transition(state, user, comment);
+        self.save(&request).await?;
+        info!(
+            "Change request {} applied by {}: {:?}",
+            id, user, request.state
+        );
+
+        Ok(request)
+    }
+
+    fn ensure_state(
+        request: &ChangeRequest,
+        allowed: &[ChangeRequestState],
+        operation: &str,
+    ) -> Result<()> {
+        if allowed.contains(&request.state) {
+            return Ok(());
+        }
+
+        Err(NetworkError::Api(ApiError::Conflict {
+            resource: format!("change request {}", request.id),
+            message: format!("cannot {} request in state {:?}", operation, request.state),
+        }))
+    }
+
+    fn ensure_privilege(&self, user: &str, privilege: &str, operation: &str) -> Result<()> {
+        if self.privileges.has_privilege(user, privilege) {
+            Ok(())
+        } else {
+            Err(NetworkError::Api(ApiError::PermissionDenied {
+                operation: operation.to_string(),
+            }))
+        }
+    }
+
+    fn request_path(&self, id: &str) -> PathBuf {
+        self.store_dir.join(format!("{}.json", id))
+    }
+
+    /// Persist a change request atomically
+    async fn save(&self, request: &ChangeRequest) -> Result<()> {
+        let path = self.request_path(&request.id);
+        let tmp_path = path.with_extension("json.tmp");
+
+        fs::write(&tmp_path, serde_json::to_string_pretty(request)?).await?;
+        fs::rename(&tmp_path, &path).await?;
+
+        Ok(())
+    }
+}
+
+/// Whether `id` has the format generated by [`ChangeRequestManager::propose`]
+fn is_change_request_id(id: &str) -> bool {
+    id.strip_prefix("cr_").is_some_and(|sequence| {
+        !sequence.is_empty() && sequence.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// User owning an API token (`user@realm!token`), or the user itself
+fn token_owner(userid: &str) -> &str {
+    userid.split('!').next().unwrap_or(userid)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use pve_network_core::error::ValidationError;
+    use std::sync::Mutex as StdMutex;
+    use tempfile::TempDir;
+
+    /// Backend keeping the active configuration in memory
+    struct FakeBackend {
+        active: StdMutex<NetworkConfiguration>,
+        applied: StdMutex<Vec<Transaction>>,
+    }
+
+    #[async_trait]
+    impl ApplyBackend for FakeBackend {
+        async fn current_config(&self) -> Result<NetworkConfiguration> {
+            Ok(self.active.lock().unwrap().clone())
+        }
+
+        async fn validate_config(&self, config: &NetworkConfiguration) -> Result<()> {
+            if config.auto_interfaces.iter().any(|name| name == "invalid") {
+                return Err(NetworkError::Validation(ValidationError::Schema {
+                    message: "invalid interface".to_string(),
+                }));
+            }
+            Ok(())
+        }
+
+        async fn apply_config(&self, config: &NetworkConfiguration) -> Result<ApplyResult> {
+            *self.active.lock().unwrap() = config.clone();
+            Ok(ApplyResult {
+                transaction_id: "txn_fake".to_string(),
+                success: true,
+                applied_changes: vec![],
+                warnings: vec![],
+                error: None,
+                duration_ms: 0,
+            })
+        }
+
+        async fn apply_transaction(&self, transaction: Transaction) -> Result<ApplyResult> {
+            self.applied.lock().unwrap().push(transaction.clone());
+            self.apply_config(&transaction.new_config).await
+        }
+    }
+
+    async fn create_manager(temp_dir: &TempDir) -> (ChangeRequestManager, Arc<FakeBackend>) {
+        let backend = Arc::new(FakeBackend {
+            active: StdMutex::new(NetworkConfiguration::default()),
+            applied: StdMutex::new(Vec::new()),
+        });
+        let privileges = Arc::new(
+            StaticPrivileges::new()
+                .grant("alice@pve", MODIFY_PRIVILEGE)
+                .grant("alice@pve!ci", APPROVE_PRIVILEGE)
+                .grant("approver@pve", APPROVE_PRIVILEGE)
+                .grant("carol@pve", APPROVE_PRIVILEGE)
+                .grant("bob@pve", "Sys.Audit"),
+        );
+        let manager =
+            ChangeRequestManager::with_store_dir(backend.clone(), privileges, temp_dir.path())
+                .await
+                .unwrap();
+        (manager, backend)
+    }
+
+    fn config_with(auto: &str) -> NetworkConfiguration {
+        let mut config = NetworkConfiguration::default();
+        config.auto_interfaces.push(auto.to_string());
+        config
+    }
+
+    #[tokio::test]
+    async fn test_four_eyes_approval_and_apply() {
+        let temp_dir = TempDir::new().unwrap();
+        let (manager, backend) = create_manager(&temp_dir).await;
+
+        let request = manager
+            .propose("alice@pve", "Add vmbr1", config_with("vmbr1"))
+            .await
+            .unwrap();
+        assert!(request.validation.valid);
+        assert_eq!(request.state, ChangeRequestState::Proposed);
+
+        // Only approved requests can be applied
+        assert!(manager.apply(&request.id, "alice@pve").await.is_err());
+
+        // Users without the privilege cannot approve
+        assert!(manager.approve(&request.id, "bob@pve", None).await.is_err());
+
+        let approved = manager
+            .approve(&request.id, "approver@pve", Some("looks good".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(approved.state, ChangeRequestState::Approved);
+
+        let applied = manager.apply(&request.id, "alice@pve").await.unwrap();
+        assert_eq!(applied.state, ChangeRequestState::Applied);
+        assert_eq!(applied.history.len(), 3);
+        assert_eq!(applied.history[1].user, "approver@pve");
+        assert_eq!(applied.history[1].comment.as_deref(), Some("looks good"));
+
+        let transactions = backend.applied.lock().unwrap();
+        assert_eq!(transactions[0].id, request.transaction.id);
+        assert_eq!(transactions[0].metadata["approved_by"], "approver@pve");
+        assert_eq!(transactions[0].metadata["change_request"], request.id);
+    }
+
+    #[tokio::test]
+    async fn test_proposer_cannot_approve_own_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let (manager, _backend) = create_manager(&temp_dir).await;
+
+        let request = manager
+            .propose("approver@pve", "Add vmbr1", config_with("vmbr1"))
+            .await
+            .unwrap();
+
+        assert!(manager
+            .approve(&request.id, "approver@pve", None)
+            .await
+            .is_err());
+
+        // root holds no implicit privileges
+        assert!(manager
+            .approve(&request.id, "root@pam", None)
+            .await
+            .is_err());
+        assert!(manager
+            .approve(&request.id, "carol@pve", None)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_token_cannot_approve_owner_request() {
+        let temp_dir = TempDir::new().unwrap();
+        let (manager, _backend) = create_manager(&temp_dir).await;
+
+        let request = manager
+            .propose("alice@pve", "Add vmbr1", config_with("vmbr1"))
+            .await
+            .unwrap();
+        assert!(manager
+            .approve(&request.id, "alice@pve!ci", None)
+            .await
+            .is_err());
+
+        // Proposing and applying need the modify privilege
+        assert!(manager
+            .propose("bob@pve", "Add vmbr2", config_with("vmbr2"))
+            .await
+            .is_err());
+        manager
+            .approve(&request.id, "carol@pve", None)
+            .await
+            .unwrap();
+        assert!(manager.apply(&request.id, "bob@pve").await.is_err());
+        assert!(manager.get("../cr_1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request_cannot_be_approved() {
+        let temp_dir = TempDir::new().unwrap();
+        let (manager, _backend) = create_manager(&temp_dir).await;
+
+        let request = manager
+            .propose("alice@pve", "Broken", config_with("invalid"))
+            .await
+            .unwrap();
+        assert!(!request.validation.valid);
+        assert_eq!(request.validation.errors.len(), 1);
+
+        assert!(manager
+            .approve(&request.id, "approver@pve", None)
+            .await
+            .is_err());
+
+        let rejected = manager
+            .reject(&request.id, "approver@pve", None)
+            .await
+            .unwrap();
+        assert_eq!(rejected.state, ChangeRequestState::Rejected);
+        assert!(manager
+            .approve(&request.id, "approver@pve", None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stale_request_is_not_applied() {
+        let temp_dir = TempDir::new().unwrap();
+        let (manager, backend) = create_manager(&temp_dir).await;
+
+        let request = manager
+            .propose("alice@pve", "Add vmbr1", config_with("vmbr1"))
+            .await
+            .unwrap();
+        manager
+            .approve(&request.id, "approver@pve", None)
+            .await
+            .unwrap();
+
+        // Configuration changed behind the request's back
+        *backend.active.lock().unwrap() = config_with("vmbr2");
+
+        assert!(manager.apply(&request.id, "alice@pve").await.is_err());
+        assert_eq!(
+            manager.get(&request.id).await.unwrap().state,
+            ChangeRequestState::Approved
+        );
+        assert_eq!(manager.list().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,587 @@
+//! User-defined hook scripts executed around network configuration apply
+//!
+//! Hooks live in per-stage directories below the hook root
+//! (`/etc/pve-network/hooks.d/<stage>/`). Every executable file in a stage
+//! directory is run in lexical order with the transaction serialized as JSON
+//! on stdin. A non-zero exit status from a pre-stage hook vetoes the
+//! transaction; failures of post-stage hooks are only recorded.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+use pve_network_core::error::SystemError;
+use pve_network_core::{NetworkError, Result};
+
+use crate::transaction::Transaction;
+
+/// Default directory containing the per-stage hook directories
+pub const DEFAULT_HOOK_DIR: &str = "/etc/pve-network/hooks.d";
+
+/// Default timeout for a single hook script
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long output of a timed out hook is still collected after killing it
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Point in the apply lifecycle at which hooks are executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookStage {
+    /// Before the new configuration is validated
+    PreValidate,
+    /// After validation and dry-run, before changes are applied
+    PreApply,
+    /// After changes were applied successfully
+    PostApply,
+    /// After a transaction was rolled back
+    PostRollback,
+}
+
+impl HookStage {
+    /// Name of the stage directory below the hook root
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            HookStage::PreValidate => "pre-validate",
+            HookStage::PreApply => "pre-apply",
+            HookStage::PostApply => "post-apply",
+            HookStage::PostRollback => "post-rollback",
+        }
+    }
+
+    /// Whether a failing hook in this stage aborts the transaction
+    pub fn is_pre(&self) -> bool {
+        matches!(self, HookStage::PreValidate | HookStage::PreApply)
+    }
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.dir_name())
+    }
+}
+
+/// Outcome of a single hook script execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookResult {
+    /// Stage the hook was executed in
+    pub stage: HookStage,
+    /// Hook script file name
+    pub hook: String,
+    /// Whether the hook exited successfully
+    pub success: bool,
+    /// Exit code (None if killed or timed out)
+    pub exit_code: Option<i32>,
+    /// Whether the hook was killed after exceeding the timeout
+    pub timed_out: bool,
+    /// Standard output
+    pub stdout: String,
+    /// Standard error
+    pub stderr: String,
+    /// Execution duration in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Runs hook scripts for a given stage
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    /// Root directory containing the stage directories
+    hook_dir: PathBuf,
+    /// Timeout for a single hook script
+    hook_timeout: Duration,
+}
+
+impl HookRunner {
+    /// Create hook runner using the default hook directory
+    pub fn new() -> Self {
+        Self::with_config(DEFAULT_HOOK_DIR, DEFAULT_HOOK_TIMEOUT)
+    }
+
+    /// Create hook runner with custom hook directory and timeout
+    pub fn with_config<P: AsRef<Path>>(hook_dir: P, hook_timeout: Duration) -> Self {
+        Self {
+            hook_dir: hook_dir.as_ref().to_path_buf(),
+            hook_timeout,
+        }
+    }
+
+    /// Root hook directory
+    pub fn hook_dir(&self) -> &Path {
+        &self.hook_dir
+    }
+
+    /// List executable hooks for a stage in execution order
+    pub async fn list_hooks(&self, stage: HookStage) -> Result<Vec<PathBuf>> {
+        let stage_dir = self.hook_dir.join(stage.dir_name());
+        if !stage_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut hooks = Vec::new();
+        let mut entries = fs::read_dir(&stage_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            // Skip hidden files and editor/package manager leftovers, like run-parts
+            if name.starts_with('.') || name.ends_with('~') || name.contains(".dpkg-") {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() && is_executable(&metadata) {
+                hooks.push(path);
+            } else {
+                debug!("Skipping non-executable hook {}", path.display());
+            }
+        }
+
+        hooks.sort();
+        Ok(hooks)
+    }
+
+    /// Run all hooks of a stage for the given transaction.
+    ///
+    /// For pre-stages execution stops at the first failing hook; callers use
+    /// [`HookRunner::check_results`] to turn such a failure into an error.
+    pub async fn run_stage(
+        &self,
+        stage: HookStage,
+        transaction: &Transaction,
+    ) -> Result<Vec<HookResult>> {
+        let hooks = self.list_hooks(stage).await?;
+        if hooks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        info!(
+            "Running {} {} hook(s) for transaction {}",
+            hooks.len(),
+            stage,
+            transaction.id
+        );
+
+        let input = serde_json::to_vec(transaction)?;
+        let mut results = Vec::new();
+
+        for hook in hooks {
+            let result = self.run_hook(stage, &hook, transaction, &input).await?;
+            let failed = !result.success;
+            results.push(result);
+
+            if failed && stage.is_pre() {
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Return an error if a pre-stage hook vetoed the transaction
+    pub fn check_results(stage: HookStage, results: &[HookResult]) -> Result<()> {
+        if !stage.is_pre() {
+            return Ok(());
+        }
+
+        match results.iter().find(|result| !result.success) {
+            Some(failed) => {
+                let mut reason = if failed.timed_out {
+                    "timed out".to_string()
+                } else {
+                    match failed.exit_code {
+                        Some(code) => format!("exit code {}", code),
+                        None => "terminated by signal".to_string(),
+                    }
+                };
+                // Hooks explain a veto on stderr
+                if let Some(message) = failed
+                    .stderr
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty() && !line.starts_with("hook timed out"))
+                {
+                    reason.push_str(&format!(" ({})", message));
+                }
+                Err(NetworkError::System(SystemError::HookFailed {
+                    stage: stage.to_string(),
+                    hook: failed.hook.clone(),
+                    reason,
+                }))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Execute a single hook script
+    async fn run_hook(
+        &self,
+        stage: HookStage,
+        hook: &Path,
+        transaction: &Transaction,
+        input: &[u8],
+    ) -> Result<HookResult> {
+        let hook_name = hook
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| hook.display().to_string());
+
+        debug!("Executing {} hook {}", stage, hook.display());
+        let start_time = Instant::now();
+
+        let mut child = Command::new(hook)
+            .env("PVE_NETWORK_HOOK_STAGE", stage.dir_name())
+            .env("PVE_NETWORK_TRANSACTION_ID", &transaction.id)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|_| {
+                NetworkError::System(SystemError::CommandFailed {
+                    command: hook.display().to_string(),
+                })
+            })?;
+
+        // Feed stdin from a separate task so a hook that never reads its
+        // input still runs into the timeout instead of blocking on a full
+        // pipe. The pipe is closed once the task finishes.
+        if let Some(mut stdin) = child.stdin.take() {
+            let input = input.to_vec();
+            let hook_name = hook_name.clone();
+            tokio::spawn(async move {
+                // Hooks are free to ignore their input, so a closed pipe is fine
+                if let Err(e) = stdin.write_all(&input).await {
+                    debug!("Hook {} did not consume stdin: {}", hook_name, e);
+                }
+            });
+        }
+
+        // Output is collected while the hook runs, so a hook killed on
+        // timeout still reports what it printed up to then
+        let mut stdout = OutputCapture::spawn(child.stdout.take());
+        let mut stderr = OutputCapture::spawn(child.stderr.take());
+
+        let status = timeout(self.hook_timeout, async {
+            let status = child.wait().await?;
+            stdout.finished().await;
+            stderr.finished().await;
+            Ok::<_, std::io::Error>(status)
+        })
+        .await;
+
+        let result = match status {
+            Ok(Ok(status)) => HookResult {
+                stage,
+                hook: hook_name,
+                success: status.success(),
+                exit_code: status.code(),
+                timed_out: false,
+                stdout: stdout.contents(),
+                stderr: stderr.contents(),
+                duration_ms: start_time.elapsed().as_millis() as u64,
+            },
+            Ok(Err(e)) => {
+                return Err(NetworkError::Io(e));
+            }
+            Err(_) => {
+                if let Err(e) = child.kill().await {
+                    debug!("Failed to kill hook {}: {}", hook_name, e);
+                }
+
+                // Processes the hook spawned may keep the pipes open
+                let _ = timeout(OUTPUT_GRACE_PERIOD, async {
+                    stdout.finished().await;
+                    stderr.finished().await;
+                })
+                .await;
+
+                let mut stderr = stderr.contents();
+                if !stderr.is_empty() && !stderr.ends_with('\n') {
+                    stderr.push('\n');
+                }
+                stderr.push_str(&format!("hook timed out after {:?}", self.hook_timeout));
+
+                HookResult {
+                    stage,
+                    hook: hook_name,
+                    success: false,
+                    exit_code: None,
+                    timed_out: true,
+                    stdout: stdout.contents(),
+                    stderr,
+                    duration_ms: start_time.elapsed().as_millis() as u64,
+                }
+            }
+        };
+
+        if result.success {
+            debug!(
+                "{} hook {} completed in {}ms",
+                stage, result.hook, result.duration_ms
+            );
+        } else {
+            warn!(
+                "{} hook {} failed (exit code {:?}, timed out: {}): {}",
+                stage,
+                result.hook,
+                result.exit_code,
+                result.timed_out,
+                result.stderr.trim()
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+/// Output of a hook pipe, collected by a background task
+struct OutputCapture {
+    buffer: Arc<StdMutex<Vec<u8>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl OutputCapture {
+    fn spawn<R: AsyncRead + Unpin + Send + 'static>(pipe: Option<R>) -> Self {
+        let buffer = Arc::new(StdMutex::new(Vec::new()));
+        let reader = pipe.map(|mut pipe| {
+            let buffer = buffer.clone();
+            tokio::spawn(async move {
+                let mut chunk = [0u8; 4096];
+                while let Ok(len) = pipe.read(&mut chunk).await {
+                    if len == 0 {
+                        break;
+                    }
+                    buffer.lock().unwrap().extend_from_slice(&chunk[..len]);
+                }
+            })
+        });
+
+        Self { buffer, reader }
+    }
+
+    /// Wait until the pipe was closed
+    async fn finished(&mut self) {
+        if let Some(reader) = &mut self.reader {
+            let _ = reader.await;
+            self.reader = None;
+        }
+    }
+
+    /// Output collected so far
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.lock().unwrap()).to_string()
+    }
+}
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        if let Some(reader) = &self.reader {
+            reader.abort();
+        }
+    }
+}
+
+impl Default for HookRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::TransactionState;
+    use pve_network_core::NetworkConfiguration;
+    use std::collections::HashMap;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn test_transaction() -> Transaction {
+        Transaction {
+            id: "txn_test".to_string(),
+            timestamp: 0,
+            original_config: NetworkConfiguration::default(),
+            new_config: NetworkConfiguration::default(),
+            state: TransactionState::Created,
+            changes: vec![],
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn write_hook(dir: &Path, stage: HookStage, name: &str, script: &str) -> PathBuf {
+        let stage_dir = dir.join(stage.dir_name());
+        std::fs::create_dir_all(&stage_dir).unwrap();
+        let path = stage_dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_missing_stage_dir_runs_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let runner = HookRunner::with_config(temp_dir.path(), Duration::from_secs(5));
+
+        let results = runner
+            .run_stage(HookStage::PreApply, &test_transaction())
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_in_order_and_receive_transaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let out = temp_dir.path().join("out.json");
+        write_hook(
+            temp_dir.path(),
+            HookStage::PostApply,
+            "20-second",
+            "echo second",
+        );
+        write_hook(
+            temp_dir.path(),
+            HookStage::PostApply,
+            "10-first",
+            &format!("cat > {}; echo $PVE_NETWORK_HOOK_STAGE", out.display()),
+        );
+        // Non-executable files are ignored
+        std::fs::write(
+            temp_dir.path().join("post-apply").join("30-disabled"),
+            "#!/bin/sh\nexit 1\n",
+        )
+        .unwrap();
+
+        let runner = HookRunner::with_config(temp_dir.path(), Duration::from_secs(5));
+        let results = runner
+            .run_stage(HookStage::PostApply, &test_transaction())
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].hook, "10-first");
+        assert_eq!(results[0].stdout.trim(), "post-apply");
+        assert_eq!(results[1].hook, "20-second");
+        assert!(results.iter().all(|r| r.success));
+
+        let input: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(input["id"], "txn_test");
+    }
+
+    #[tokio::test]
+    async fn test_failing_pre_hook_vetoes() {
+        let temp_dir = TempDir::new().unwrap();
+        write_hook(temp_dir.path(), HookStage::PreApply, "10-deny", "exit 3");
+        write_hook(temp_dir.path(), HookStage::PreApply, "20-never", "exit 0");
+
+        let runner = HookRunner::with_config(temp_dir.path(), Duration::from_secs(5));
+        let results = runner
+            .run_stage(HookStage::PreApply, &test_transaction())
+            .await
+            .unwrap();
+
+        // Execution stops at the first failing pre-hook
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].exit_code, Some(3));
+        assert!(HookRunner::check_results(HookStage::PreApply, &results).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failing_post_hook_is_not_fatal() {
+        let temp_dir = TempDir::new().unwrap();
+        write_hook(
+            temp_dir.path(),
+            HookStage::PostRollback,
+            "10-fail",
+            "exit 1",
+        );
+        write_hook(temp_dir.path(), HookStage::PostRollback, "20-ok", "exit 0");
+
+        let runner = HookRunner::with_config(temp_dir.path(), Duration::from_secs(5));
+        let results = runner
+            .run_stage(HookStage::PostRollback, &test_transaction())
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(HookRunner::check_results(HookStage::PostRollback, &results).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        write_hook(
+            temp_dir.path(),
+            HookStage::PreValidate,
+            "10-slow",
+            "echo checking; echo 'vmbr0 is busy' >&2; sleep 10",
+        );
+
+        let runner = HookRunner::with_config(temp_dir.path(), Duration::from_millis(500));
+        let results = runner
+            .run_stage(HookStage::PreValidate, &test_transaction())
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].timed_out);
+        assert!(!results[0].success);
+
+        // Output printed before the timeout is kept
+        assert_eq!(results[0].stdout, "checking\n");
+        assert!(results[0]
+            .stderr
+            .starts_with("vmbr0 is busy\nhook timed out"));
+        let err = HookRunner::check_results(HookStage::PreValidate, &results).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "System error: pre-validate hook 10-slow failed: timed out (vmbr0 is busy)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hook_ignoring_large_stdin_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        write_hook(temp_dir.path(), HookStage::PreApply, "10-deaf", "sleep 10");
+
+        // Larger than the pipe buffer, so writing it blocks until the hook reads
+        let mut transaction = test_transaction();
+        transaction
+            .metadata
+            .insert("payload".to_string(), "x".repeat(256 * 1024));
+
+        let runner = HookRunner::with_config(temp_dir.path(), Duration::from_millis(200));
+        let results = timeout(
+            Duration::from_secs(5),
+            runner.run_stage(HookStage::PreApply, &transaction),
+        )
+        .await
+        .expect("hook stdin write must be covered by the timeout")
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].timed_out);
+    }
+}
@@ -2,14 +2,30 @@
 //!
 //! Transactional configuration application with rollback support
 
+pub mod acl;
+pub mod change_request;
+pub mod hooks;
 pub mod ifupdown;
 pub mod rollback;
+pub mod rolling;
+pub mod scheduler;
 pub mod transaction;
 
 #[cfg(test)]
 mod tests;
 
+pub use acl::{AclPrivileges, UserConfig};
+pub use change_request::{
+    ChangeRequest, ChangeRequestManager, ChangeRequestState, ChangeRequestTransition,
+    PrivilegeProvider, StaticPrivileges, ValidationReport,
+};
+pub use hooks::{HookResult, HookRunner, HookStage};
 pub use ifupdown::{IfUpDownIntegration, IfUpDownResult, InterfaceChangeType, InterfaceState};
 pub use pve_shared_types::{ChangeType, ConfigChange};
 pub use rollback::{BackupFile, RollbackManager, RollbackPoint, RollbackStats};
+pub use rolling::{
+    NodeApplyState, NodeApplyStatus, NodeTransport, RollingApplyOptions, RollingApplyOrchestrator,
+    RollingApplyProgress, RollingApplyState,
+};
+pub use scheduler::{ApplyBackend, ApplyScheduler, ScheduledApply, ScheduledApplyState};
 pub use transaction::{ApplyResult, NetworkApplier, Transaction, TransactionState};
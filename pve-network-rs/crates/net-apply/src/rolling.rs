@@ -0,0 +1,668 @@
+//! Cluster-wide rolling application of network configuration
+//!
+//! The orchestrator applies a configuration to cluster nodes in batches. After
+//! each batch it waits for the health probes of the batch nodes to pass. The
+//! first failure stops the rollout and every node touched so far is restored
+//! to the configuration it had before the rollout started.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use pve_network_core::error::ConfigError;
+use pve_network_core::{NetworkConfiguration, NetworkError, Result};
+
+use crate::transaction::ApplyResult;
+
+/// Transport used to reach the nodes taking part in a rolling apply
+#[async_trait]
+pub trait NodeTransport: Send + Sync {
+    /// Read the currently active configuration of a node
+    async fn current_config(&self, node: &str) -> Result<NetworkConfiguration>;
+
+    /// Apply a configuration on a node
+    async fn apply_config(&self, node: &str, config: &NetworkConfiguration) -> Result<ApplyResult>;
+
+    /// Check whether a node is healthy after the apply of `transaction_id`
+    ///
+    /// Implementations reach the node over the network the transaction
+    /// configured and check that the node committed the transaction.
+    async fn health_probe(&self, node: &str, transaction_id: &str) -> Result<bool>;
+}
+
+/// Rolling apply options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingApplyOptions {
+    /// Number of nodes updated at the same time
+    pub batch_size: usize,
+    /// Maximum time to wait for a node to report healthy
+    pub probe_timeout: Duration,
+    /// Delay between two health probes of the same node
+    pub probe_interval: Duration,
+}
+
+impl Default for RollingApplyOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1,
+            probe_timeout: Duration::from_secs(60),
+            probe_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Overall state of a rolling apply
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RollingApplyState {
+    /// Rollout has not started yet
+    Pending,
+    /// Batches are being applied
+    Running,
+    /// All nodes were updated and passed their health probes
+    Completed,
+    /// A failure occurred and touched nodes are being restored
+    RollingBack,
+    /// All touched nodes were restored
+    RolledBack,
+    /// Rollout failed and at least one node could not be restored
+    Failed,
+}
+
+/// Per-node state of a rolling apply
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum NodeApplyState {
+    /// Node has not been processed yet
+    Pending,
+    /// Configuration is being applied
+    Applying,
+    /// Waiting for the node to report healthy
+    Probing,
+    /// Node was updated and is healthy
+    Applied,
+    /// Apply or health probe failed on this node
+    Failed,
+    /// Node was restored to its previous configuration
+    RolledBack,
+}
+
+/// Status of a single node within a rolling apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeApplyStatus {
+    /// Node name
+    pub node: String,
+    /// Batch the node belongs to (starting at 1)
+    pub batch: usize,
+    /// Current node state
+    pub state: NodeApplyState,
+    /// Transaction ID of the apply on the node
+    pub transaction_id: Option<String>,
+    /// Error message if the node failed
+    pub error: Option<String>,
+}
+
+/// Progress of a rolling apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingApplyProgress {
+    /// Rollout ID
+    pub id: String,
+    /// Overall rollout state
+    pub state: RollingApplyState,
+    /// Batch currently being processed (0 before the first batch)
+    pub current_batch: usize,
+    /// Total number of batches
+    pub total_batches: usize,
+    /// Per-node status in rollout order
+    pub nodes: Vec<NodeApplyStatus>,
+    /// First error that stopped the rollout
+    pub error: Option<String>,
+}
+
+impl RollingApplyProgress {
+    fn new(id: String) -> Self {
+        Self {
+            id,
+            state: RollingApplyState::Pending,
+            current_batch: 0,
+            total_batches: 0,
+            nodes: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Number of nodes that were updated and passed their health probes
+    pub fn applied_nodes(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|node| node.state == NodeApplyState::Applied)
+            .count()
+    }
+
+    fn node_mut(&mut self, node: &str) -> Option<&mut NodeApplyStatus> {
+        self.nodes.iter_mut().find(|status| status.node == node)
+    }
+}
+
+/// Applies a configuration to cluster nodes batch by batch
+pub struct RollingApplyOrchestrator {
+    /// Transport to the cluster nodes
+    transport: Arc<dyn NodeTransport>,
+    /// Rollout options
+    options: RollingApplyOptions,
+    /// Shared progress, readable while the rollout is running
+    progress: Arc<RwLock<RollingApplyProgress>>,
+}
+
+impl RollingApplyOrchestrator {
+    /// Create new orchestrator
+    pub fn new(transport: Arc<dyn NodeTransport>, options: RollingApplyOptions) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        Self {
+            transport,
+            options,
+            progress: Arc::new(RwLock::new(RollingApplyProgress::new(format!(
+                "rollout_{}",
+                timestamp
+            )))),
+        }
+    }
+
+    /// Snapshot of the current progress
+    pub async fn progress(&self) -> RollingApplyProgress {
+        self.progress.read().await.clone()
+    }
+
+    /// Shared progress handle for observers such as task trackers
+    pub fn progress_handle(&self) -> Arc<RwLock<RollingApplyProgress>> {
+        self.progress.clone()
+    }
+
+    /// Apply configuration to the given nodes in batches.
+    ///
+    /// Node failures do not return an error; they are reported through the
+    /// final progress state, like [`ApplyResult`] does for a single node.
+    pub async fn run(
+        &self,
+        nodes: &[String],
+        config: &NetworkConfiguration,
+    ) -> Result<RollingApplyProgress> {
+        if self.options.batch_size == 0 {
+            return Err(NetworkError::Configuration(ConfigError::InvalidValue {
+                field: "batch_size".to_string(),
+                value: "0".to_string(),
+            }));
+        }
+        if nodes.is_empty() {
+            return Err(NetworkError::Configuration(ConfigError::MissingField {
+                field: "nodes".to_string(),
+            }));
+        }
+
+        let batches: Vec<&[String]> = nodes.chunks(self.options.batch_size).collect();
+
+        {
+            let mut progress = self.progress.write().await;
+            progress.state = RollingApplyState::Running;
+            progress.total_batches = batches.len();
+            progress.nodes = batches
+                .iter()
+                .enumerate()
+                .flat_map(|(index, batch)| {
+                    batch.iter().map(move |node| NodeApplyStatus {
+                        node: node.clone(),
+                        batch: index + 1,
+                        state: NodeApplyState::Pending,
+                        transaction_id: None,
+                        error: None,
+                    })
+                })
+                .collect();
+        }
+
+        info!(
+            "Starting rolling apply to {} node(s) in {} batch(es)",
+            nodes.len(),
+            batches.len()
+        );
+
+        // Configurations to restore, in the order the nodes were touched
+        let mut previous_configs: Vec<(String, NetworkConfiguration)> = Vec::new();
+
+        for (index, batch) in batches.iter().enumerate() {
+            self.progress.write().await.current_batch = index + 1;
+            info!("Rolling apply batch {}/{}", index + 1, batches.len());
+
+            if let Err(failure) = self.apply_batch(batch, config, &mut previous_configs).await {
+                self.rollback(&previous_configs, failure).await;
+                return Ok(self.progress().await);
+            }
+
+            if let Err(failure) = self.probe_batch(batch).await {
+                self.rollback(&previous_configs, failure).await;
+                return Ok(self.progress().await);
+            }
+        }
+
+        self.progress.write().await.state = RollingApplyState::Completed;
+        info!("Rolling apply completed on {} node(s)", nodes.len());
+
+        Ok(self.progress().await)
+    }
+
+    /// Apply configuration to all nodes of a batch concurrently
+    async fn apply_batch(
+        &self,
+        batch: &[String],
+        config: &NetworkConfiguration,
+        previous_configs: &mut Vec<(String, NetworkConfiguration)>,
+    ) -> std::result::Result<(), String> {
+        // Capture the previous configuration before touching any node
+        let mut batch_previous = HashMap::new();
+        for node in batch {
+            match self.transport.current_config(node).await {
+                Ok(previous) => {
+                    batch_previous.insert(node.clone(), previous);
+                }
+                Err(e) => {
+                    let message = format!("failed to read configuration of {}: {}", node, e);
+                    self.set_node_failed(node, &message).await;
+                    return Err(message);
+                }
+            }
+        }
+
+        let mut handles = Vec::new();
+        for node in batch {
+            self.set_node_state(node, NodeApplyState::Applying).await;
+
+            let transport = self.transport.clone();
+            let config = config.clone();
+            let task_node = node.clone();
+            handles.push((
+                node.clone(),
+                tokio::spawn(async move { transport.apply_config(&task_node, &config).await }),
+            ));
+        }
+
+        // Every task is awaited, so nodes of the batch that applied
+        // successfully are restored even if another apply task died
+        let mut results = Vec::new();
+        for (node, handle) in handles {
+            results.push((node, handle.await));
+        }
+
+        let mut first_failure = None;
+        for (node, result) in results {
+            // Nodes whose apply failed were already rolled back by their own
+            // transaction, only successfully applied nodes need restoring
+            let failure = match result {
+                Ok(Ok(apply_result)) if apply_result.success => {
+                    if let Some(previous) = batch_previous.remove(&node) {
+                        previous_configs.push((node.clone(), previous));
+                    }
+                    if let Some(status) = self.progress.write().await.node_mut(&node) {
+                        status.transaction_id = Some(apply_result.transaction_id);
+                    }
+                    None
+                }
+                Ok(Ok(apply_result)) => Some(format!(
+                    "apply failed on {}: {}",
+                    node,
+                    apply_result
+                        .error
+                        .unwrap_or_else(|| "unknown error".to_string())
+                )),
+                Ok(Err(e)) => Some(format!("apply failed on {}: {}", node, e)),
+                Err(e) => Some(format!("apply task for {} failed: {}", node, e)),
+            };
+
+            if let Some(message) = failure {
+                self.set_node_failed(&node, &message).await;
+                first_failure.get_or_insert(message);
+            }
+        }
+
+        match first_failure {
+            Some(message) => Err(message),
+            None => Ok(()),
+        }
+    }
+
+    /// Wait for all nodes of a batch to report healthy
+    async fn probe_batch(&self, batch: &[String]) -> std::result::Result<(), String> {
+        for node in batch {
+            self.set_node_state(node, NodeApplyState::Probing).await;
+        }
+
+        for node in batch {
+            let transaction_id = self
+                .progress
+                .read()
+                .await
+                .nodes
+                .iter()
+                .find(|status| &status.node == node)
+                .and_then(|status| status.transaction_id.clone())
+                .unwrap_or_default();
+            if let Err(message) = self.wait_healthy(node, &transaction_id).await {
+                self.set_node_failed(node, &message).await;
+                return Err(message);
+            }
+            self.set_node_state(node, NodeApplyState::Applied).await;
+        }
+
+        Ok(())
+    }
+
+    /// Poll the health probe of a node until it passes or times out
+    async fn wait_healthy(
+        &self,
+        node: &str,
+        transaction_id: &str,
+    ) -> std::result::Result<(), String> {
+        let start_time = Instant::now();
+
+        loop {
+            match self.transport.health_probe(node, transaction_id).await {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => warn!("Health probe on {} failed: {}", node, e),
+            }
+
+            if start_time.elapsed() >= self.options.probe_timeout {
+                return Err(format!(
+                    "health probe on {} did not pass within {:?}",
+                    node, self.options.probe_timeout
+                ));
+            }
+
+            tokio::time::sleep(self.options.probe_interval).await;
+        }
+    }
+
+    /// Restore all touched nodes in reverse order
+    async fn rollback(&self, previous_configs: &[(String, NetworkConfiguration)], failure: String) {
+        error!("Rolling apply stopped: {}", failure);
+
+        {
+            let mut progress = self.progress.write().await;
+            progress.state = RollingApplyState::RollingBack;
+            progress.error = Some(failure);
+        }
+
+        let mut restore_failed = false;
+        for (node, previous) in previous_configs.iter().rev() {
+            info!("Restoring previous configuration on {}", node);
+
+            let error = match self.transport.apply_config(node, previous).await {
+                Ok(result) if result.success => None,
+                Ok(result) => Some(result.error.unwrap_or_else(|| "unknown error".to_string())),
+                Err(e) => Some(e.to_string()),
+            };
+
+            let mut progress = self.progress.write().await;
+            if let Some(status) = progress.node_mut(node) {
+                match error {
+                    None => status.state = NodeApplyState::RolledBack,
+                    Some(message) => {
+                        error!("Failed to restore configuration on {}: {}", node, message);
+                        restore_failed = true;
+                        status.state = NodeApplyState::Failed;
+                        status.error = Some(format!("rollback failed: {}", message));
+                    }
+                }
+            }
+        }
+
+        self.progress.write().await.state = if restore_failed {
+            RollingApplyState::Failed
+        } else {
+            RollingApplyState::RolledBack
+        };
+    }
+
+    async fn set_node_state(&self, node: &str, state: NodeApplyState) {
+        if let Some(status) = self.progress.write().await.node_mut(node) {
+            status.state = state;
+        }
+    }
+
+    async fn set_node_failed(&self, node: &str, message: &str) {
+        if let Some(status) = self.progress.write().await.node_mut(node) {
+            status.state = NodeApplyState::Failed;
+            status.error = Some(message.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_network_core::{AddressMethod, Interface, InterfaceType};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// In-process fake cluster; each node keeps its configuration in a tmpdir
+    struct FakeCluster {
+        root: TempDir,
+        failing_apply: HashSet<String>,
+        panicking_apply: HashSet<String>,
+        unhealthy: HashSet<String>,
+        apply_log: Mutex<Vec<String>>,
+    }
+
+    impl FakeCluster {
+        fn new(nodes: &[&str]) -> Self {
+            let root = TempDir::new().unwrap();
+            for node in nodes {
+                let path = root.path().join(node);
+                std::fs::create_dir_all(&path).unwrap();
+                std::fs::write(
+                    path.join("config.json"),
+                    serde_json::to_string(&NetworkConfiguration::default()).unwrap(),
+                )
+                .unwrap();
+            }
+
+            Self {
+                root,
+                failing_apply: HashSet::new(),
+                panicking_apply: HashSet::new(),
+                unhealthy: HashSet::new(),
+                apply_log: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn config_path(&self, node: &str) -> PathBuf {
+            self.root.path().join(node).join("config.json")
+        }
+
+        fn read(&self, node: &str) -> NetworkConfiguration {
+            serde_json::from_str(&std::fs::read_to_string(self.config_path(node)).unwrap()).unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl NodeTransport for FakeCluster {
+        async fn current_config(&self, node: &str) -> Result<NetworkConfiguration> {
+            Ok(self.read(node))
+        }
+
+        async fn apply_config(
+            &self,
+            node: &str,
+            config: &NetworkConfiguration,
+        ) -> Result<ApplyResult> {
+            self.apply_log.lock().unwrap().push(node.to_string());
+            if self.panicking_apply.contains(node) {
+                panic!("simulated crash applying on {}", node);
+            }
+
+            let success = !self.failing_apply.contains(node);
+            if success {
+                std::fs::write(self.config_path(node), serde_json::to_string(config)?)?;
+            }
+
+            Ok(ApplyResult {
+                transaction_id: format!("txn_{}", node),
+                success,
+                applied_changes: vec![],
+                warnings: vec![],
+                error: (!success).then(|| "simulated failure".to_string()),
+                duration_ms: 0,
+            })
+        }
+
+        async fn health_probe(&self, node: &str, transaction_id: &str) -> Result<bool> {
+            assert_eq!(transaction_id, format!("txn_{}", node));
+            Ok(!self.unhealthy.contains(node))
+        }
+    }
+
+    fn nodes(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn new_config() -> NetworkConfiguration {
+        let mut config = NetworkConfiguration::default();
+        config.interfaces.insert(
+            "vmbr0".to_string(),
+            Interface {
+                name: "vmbr0".to_string(),
+                iface_type: InterfaceType::Bridge {
+                    ports: vec![],
+                    vlan_aware: false,
+                },
+                method: AddressMethod::Manual,
+                addresses: vec![],
+                gateway: None,
+                mtu: None,
+                options: HashMap::new(),
+                enabled: true,
+                comments: vec![],
+            },
+        );
+        config
+    }
+
+    fn fast_options(batch_size: usize) -> RollingApplyOptions {
+        RollingApplyOptions {
+            batch_size,
+            probe_timeout: Duration::from_millis(50),
+            probe_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rolling_apply_all_batches() {
+        let names = ["node1", "node2", "node3", "node4", "node5"];
+        let cluster = Arc::new(FakeCluster::new(&names));
+        let orchestrator = RollingApplyOrchestrator::new(cluster.clone(), fast_options(2));
+
+        let progress = orchestrator
+            .run(&nodes(&names), &new_config())
+            .await
+            .unwrap();
+
+        assert_eq!(progress.state, RollingApplyState::Completed);
+        assert_eq!(progress.total_batches, 3);
+        assert_eq!(progress.applied_nodes(), 5);
+        assert_eq!(progress.nodes[4].batch, 3);
+        for name in names {
+            assert!(cluster.read(name).interfaces.contains_key("vmbr0"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_failure_stops_and_rolls_back() {
+        let names = ["node1", "node2", "node3", "node4"];
+        let mut cluster = FakeCluster::new(&names);
+        cluster.failing_apply.insert("node3".to_string());
+        let cluster = Arc::new(cluster);
+        let orchestrator = RollingApplyOrchestrator::new(cluster.clone(), fast_options(2));
+
+        let progress = orchestrator
+            .run(&nodes(&names), &new_config())
+            .await
+            .unwrap();
+
+        assert_eq!(progress.state, RollingApplyState::RolledBack);
+        assert!(progress.error.as_ref().unwrap().contains("node3"));
+        assert_eq!(progress.current_batch, 2);
+
+        // The first batch and the healthy part of the failing batch are restored
+        assert_eq!(progress.nodes[0].state, NodeApplyState::RolledBack);
+        assert_eq!(progress.nodes[2].state, NodeApplyState::Failed);
+        assert_eq!(progress.nodes[3].state, NodeApplyState::RolledBack);
+        for name in names {
+            assert!(cluster.read(name).interfaces.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crashed_apply_task_rolls_back_rest_of_batch() {
+        let names = ["node1", "node2", "node3"];
+        let mut cluster = FakeCluster::new(&names);
+        cluster.panicking_apply.insert("node1".to_string());
+        let cluster = Arc::new(cluster);
+        let orchestrator = RollingApplyOrchestrator::new(cluster.clone(), fast_options(2));
+
+        let progress = orchestrator
+            .run(&nodes(&names), &new_config())
+            .await
+            .unwrap();
+
+        // node2 applied next to the crashed task and is restored
+        assert_eq!(progress.state, RollingApplyState::RolledBack);
+        assert!(progress.error.as_ref().unwrap().contains("node1"));
+        assert_eq!(progress.nodes[0].state, NodeApplyState::Failed);
+        assert_eq!(progress.nodes[1].state, NodeApplyState::RolledBack);
+        assert_eq!(progress.nodes[2].state, NodeApplyState::Pending);
+        assert!(cluster.read("node2").interfaces.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_health_probe_failure_rolls_back() {
+        let names = ["node1", "node2", "node3"];
+        let mut cluster = FakeCluster::new(&names);
+        cluster.unhealthy.insert("node2".to_string());
+        let cluster = Arc::new(cluster);
+        let orchestrator = RollingApplyOrchestrator::new(cluster.clone(), fast_options(1));
+
+        let progress = orchestrator
+            .run(&nodes(&names), &new_config())
+            .await
+            .unwrap();
+
+        assert_eq!(progress.state, RollingApplyState::RolledBack);
+        assert!(progress.error.as_ref().unwrap().contains("health probe"));
+        assert_eq!(progress.nodes[2].state, NodeApplyState::Pending);
+
+        // node1 and node2 were applied, then restored in reverse order
+        let log = cluster.apply_log.lock().unwrap().clone();
+        assert_eq!(log, vec!["node1", "node2", "node2", "node1"]);
+        assert!(cluster.read("node1").interfaces.is_empty());
+        assert!(cluster.read("node2").interfaces.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_batch_size() {
+        let cluster = Arc::new(FakeCluster::new(&["node1"]));
+        let orchestrator = RollingApplyOrchestrator::new(cluster, fast_options(0));
+
+        assert!(orchestrator
+            .run(&nodes(&["node1"]), &new_config())
+            .await
+            .is_err());
+    }
+}
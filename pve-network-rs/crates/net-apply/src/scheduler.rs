@@ -0,0 +1,820 @@
+//! Scheduled (maintenance window) application of network configuration
+//!
+//! A validated configuration is queued with an execution time and an optional
+//! expiry. Jobs are persisted as JSON files so they survive daemon restarts.
+//! When a job runs it is applied through the backend with commit-confirm
+//! semantics: unless it is confirmed within the confirm timeout, the previous
+//! configuration is restored.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use pve_network_core::error::{ApiError, ConfigError};
+use pve_network_core::{NetworkConfiguration, NetworkError, Result};
+
+use crate::transaction::{ApplyResult, NetworkApplier, Transaction};
+
+/// Default directory for persisted scheduled applies
+pub const DEFAULT_SCHEDULE_DIR: &str = "/var/lib/pve-network/scheduled";
+
+/// Backend used by the scheduler to validate and apply configurations
+#[async_trait]
+pub trait ApplyBackend: Send + Sync {
+    /// Read the currently active configuration
+    async fn current_config(&self) -> Result<NetworkConfiguration>;
+
+    /// Validate a configuration without applying it
+    async fn validate_config(&self, config: &NetworkConfiguration) -> Result<()>;
+
+    /// Apply a configuration
+    async fn apply_config(&self, config: &NetworkConfiguration) -> Result<ApplyResult>;
+
+    /// Prepare a transaction against the current configuration without applying it
+    async fn prepare_transaction(&self, config: &NetworkConfiguration) -> Result<Transaction> {
+        let original_config = self.current_config().await?;
+        Transaction::new(original_config, config.clone())
+    }
+
+    /// Apply a previously prepared transaction
+    async fn apply_transaction(&self, transaction: Transaction) -> Result<ApplyResult> {
+        self.apply_config(&transaction.new_config).await
+    }
+}
+
+#[async_trait]
+impl ApplyBackend for NetworkApplier {
+    async fn current_config(&self) -> Result<NetworkConfiguration> {
+        self.current_configuration().await
+    }
+
+    async fn validate_config(&self, config: &NetworkConfiguration) -> Result<()> {
+        self.validate_configuration(config).await
+    }
+
+    async fn apply_config(&self, config: &NetworkConfiguration) -> Result<ApplyResult> {
+        self.apply_configuration(config).await
+    }
+
+    async fn apply_transaction(&self, transaction: Transaction) -> Result<ApplyResult> {
+        NetworkApplier::apply_transaction(self, transaction).await
+    }
+}
+
+/// State of a scheduled apply
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ScheduledApplyState {
+    /// Waiting for the execution time
+    Pending,
+    /// Configuration is being applied
+    Applying,
+    /// Applied, waiting for confirmation before the confirm deadline
+    AwaitingConfirm,
+    /// Not confirmed in time, previous configuration is being restored
+    RollingBack,
+    /// Applied and confirmed (or no confirmation required)
+    Committed,
+    /// Not confirmed in time, previous configuration restored
+    RolledBack,
+    /// Expiry passed before the job could run
+    Expired,
+    /// Cancelled by the user
+    Cancelled,
+    /// Apply or rollback failed
+    Failed,
+}
+
+/// A configuration queued for later application
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledApply {
+    /// Unique job ID
+    pub id: String,
+    /// Creation time (unix epoch)
+    pub created: u64,
+    /// Earliest execution time (unix epoch)
+    pub execute_at: u64,
+    /// Job is dropped if it could not run before this time (unix epoch)
+    pub expires_at: Option<u64>,
+    /// Seconds to wait for confirmation after apply (0 commits immediately)
+    pub confirm_timeout: u64,
+    /// Deadline for confirmation once applied (unix epoch)
+    pub confirm_deadline: Option<u64>,
+    /// Current job state
+    pub state: ScheduledApplyState,
+    /// Configuration to apply
+    pub config: NetworkConfiguration,
+    /// Configuration active before the apply, used for rollback
+    pub previous_config: Option<NetworkConfiguration>,
+    /// Transaction ID of the apply
+    pub transaction_id: Option<String>,
+    /// Error message if the job failed
+    pub error: Option<String>,
+    /// Optional user comment
+    pub comment: Option<String>,
+}
+
+/// Persistent scheduler for maintenance window applies
+pub struct ApplyScheduler {
+    /// Backend applying the configuration
+    backend: Arc<dyn ApplyBackend>,
+    /// Directory holding one JSON file per job
+    store_dir: PathBuf,
+    /// Serializes job state changes within this process
+    lock: Mutex<()>,
+}
+
+impl ApplyScheduler {
+    /// Create scheduler using the default store directory
+    pub async fn new(backend: Arc<dyn ApplyBackend>) -> Result<Self> {
+        Self::with_store_dir(backend, DEFAULT_SCHEDULE_DIR).await
+    }
+
+    /// Create scheduler with custom store directory
+    pub async fn with_store_dir<P: AsRef<Path>>(
+        backend: Arc<dyn ApplyBackend>,
+        store_dir: P,
+    ) -> Result<Self> {
+        let store_dir = store_dir.as_ref().to_path_buf();
+        if !store_dir.exists() {
+            fs::create_dir_all(&store_dir).await?;
+        }
+
+        let scheduler = Self {
+            backend,
+            store_dir,
+            lock: Mutex::new(()),
+        };
+
+        // Jobs still running belonged to a daemon that stopped mid-apply
+        for mut job in scheduler.list().await? {
+            if matches!(
+                job.state,
+                ScheduledApplyState::Applying | ScheduledApplyState::RollingBack
+            ) {
+                warn!("Scheduled apply {} was interrupted", job.id);
+                job.error = Some(format!("interrupted while in state {:?}", job.state));
+                job.state = ScheduledApplyState::Failed;
+                scheduler.save(&job).await?;
+            }
+        }
+
+        Ok(scheduler)
+    }
+
+    /// Validate and queue a configuration for later application
+    pub async fn schedule(
+        &self,
+        config: NetworkConfiguration,
+        execute_at: u64,
+        expires_at: Option<u64>,
+        confirm_timeout: u64,
+        comment: Option<String>,
+    ) -> Result<ScheduledApply> {
+        if let Some(expires_at) = expires_at {
+            if expires_at <= execute_at {
+                return Err(NetworkError::Configuration(ConfigError::InvalidValue {
+                    field: "expires_at".to_string(),
+                    value: expires_at.to_string(),
+                }));
+            }
+        }
+
+        self.backend.validate_config(&config).await?;
+
+        let _guard = self.lock.lock().await;
+
+        let created = now_secs();
+        let mut sequence = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        while self.job_path(&format!("sched_{}", sequence)).exists() {
+            sequence += 1;
+        }
+
+        let job = ScheduledApply {
+            id: format!("sched_{}", sequence),
+            created,
+            execute_at,
+            expires_at,
+            confirm_timeout,
+            confirm_deadline: None,
+            state: ScheduledApplyState::Pending,
+            config,
+            previous_config: None,
+            transaction_id: None,
+            error: None,
+            comment,
+        };
+
+        self.save(&job).await?;
+        info!("Scheduled apply {} for {}", job.id, job.execute_at);
+
+        Ok(job)
+    }
+
+    /// List all scheduled applies ordered by execution time
+    pub async fn list(&self) -> Result<Vec<ScheduledApply>> {
+        let mut jobs = Vec::new();
+        let mut entries = fs::read_dir(&self.store_dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            match fs::read_to_string(&path).await {
+                Ok(content) => match serde_json::from_str::<ScheduledApply>(&content) {
+                    Ok(job) => jobs.push(job),
+                    Err(e) => warn!("Ignoring invalid scheduled apply {}: {}", path.display(), e),
+                },
+                Err(e) => warn!("Failed to read scheduled apply {}: {}", path.display(), e),
+            }
+        }
+
+        jobs.sort_by(|a, b| a.execute_at.cmp(&b.execute_at).then(a.id.cmp(&b.id)));
+        Ok(jobs)
+    }
+
+    /// Get a scheduled apply by ID
+    pub async fn get(&self, id: &str) -> Result<ScheduledApply> {
+        let path = self.job_path(id);
+        if !is_scheduled_apply_id(id) || !path.exists() {
+            return Err(NetworkError::Api(ApiError::NotFound {
+                resource: format!("scheduled apply {}", id),
+            }));
+        }
+
+        let content = fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Cancel a pending scheduled apply
+    pub async fn cancel(&self, id: &str) -> Result<ScheduledApply> {
+        let _guard = self.lock.lock().await;
+
+        let mut job = self.get(id).await?;
+        if job.state != ScheduledApplyState::Pending {
+            return Err(NetworkError::Api(ApiError::Conflict {
+                resource: format!("scheduled apply {}", id),
+                message: format!("cannot cancel job in state {:?}", job.state),
+            }));
+        }
+
+        job.state = ScheduledApplyState::Cancelled;
+        self.save(&job).await?;
+        info!("Cancelled scheduled apply {}", id);
+
+        Ok(job)
+    }
+
+    /// Confirm an applied job so it is kept
+    pub async fn confirm(&self, id: &str) -> Result<ScheduledApply> {
+        let _guard = self.lock.lock().await;
+
+        let mut job = self.get(id).await?;
+        if job.state != ScheduledApplyState::AwaitingConfirm {
+            return Err(NetworkError::Api(ApiError::Conflict {
+                resource: format!("scheduled apply {}", id),
+                message: format!("cannot confirm job in state {:?}", job.state),
+            }));
+        }
+
+        job.state = ScheduledApplyState::Committed;
+        job.confirm_deadline = None;
+        job.previous_config = None;
+        self.save(&job).await?;
+        info!("Confirmed scheduled apply {}", id);
+
+        Ok(job)
+    }
+
+    /// Run due jobs, expire stale ones and roll back unconfirmed applies.
+    ///
+    /// Due jobs are claimed under the lock and applied without it, so the
+    /// API stays responsive while an apply runs. Returns the jobs whose
+    /// state changed.
+    pub async fn process_due(&self, now: u64) -> Result<Vec<ScheduledApply>> {
+        let mut changed = Vec::new();
+        let mut claimed = Vec::new();
+
+        {
+            let _guard = self.lock.lock().await;
+            for mut job in self.list().await? {
+                match job.state {
+                    ScheduledApplyState::Pending => {
+                        if job.expires_at.is_some_and(|expires_at| now >= expires_at) {
+                            warn!("Scheduled apply {} expired before execution", job.id);
+                            job.state = ScheduledApplyState::Expired;
+                            self.save(&job).await?;
+                            changed.push(job);
+                        } else if now >= job.execute_at {
+                            job.state = ScheduledApplyState::Applying;
+                            self.save(&job).await?;
+                            claimed.push(job);
+                        }
+                    }
+                    ScheduledApplyState::AwaitingConfirm
+                        if job.confirm_deadline.is_some_and(|deadline| now >= deadline) =>
+                    {
+                        job.state = ScheduledApplyState::RollingBack;
+                        self.save(&job).await?;
+                        claimed.push(job);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for mut job in claimed {
+            match job.state {
+                ScheduledApplyState::Applying => self.execute(&mut job, now).await,
+                _ => self.rollback(&mut job).await,
+            }
+
+            let _guard = self.lock.lock().await;
+            self.save(&job).await?;
+            changed.push(job);
+        }
+
+        Ok(changed)
+    }
+
+    /// Periodically process due jobs in the background
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_due(now_secs()).await {
+                    error!("Failed to process scheduled applies: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Apply a due job
+    ///
+    /// The confirm window starts once the apply finished, so a slow reload
+    /// does not eat into the time the user has to confirm.
+    async fn execute(&self, job: &mut ScheduledApply, now: u64) {
+        info!("Executing scheduled apply {}", job.id);
+
+        let previous = match self.backend.current_config().await {
+            Ok(previous) => previous,
+            Err(e) => {
+                job.state = ScheduledApplyState::Failed;
+                job.error = Some(format!("failed to read current configuration: {}", e));
+                return;
+            }
+        };
+
+        let started = Instant::now();
+        let applied = self.backend.apply_config(&job.config).await;
+        let finished = now + started.elapsed().as_secs();
+
+        match applied {
+            Ok(result) if result.success => {
+                job.transaction_id = Some(result.transaction_id);
+                if job.confirm_timeout == 0 {
+                    job.state = ScheduledApplyState::Committed;
+                } else {
+                    job.state = ScheduledApplyState::AwaitingConfirm;
+                    job.confirm_deadline = Some(finished + job.confirm_timeout);
+                    job.previous_config = Some(previous);
+                    info!(
+                        "Scheduled apply {} awaiting confirmation within {}s",
+                        job.id, job.confirm_timeout
+                    );
+                }
+            }
+            Ok(result) => {
+                job.transaction_id = Some(result.transaction_id);
+                job.state = ScheduledApplyState::Failed;
+                job.error = Some(result.error.unwrap_or_else(|| "apply failed".to_string()));
+            }
+            Err(e) => {
+                job.state = ScheduledApplyState::Failed;
+                job.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Restore the previous configuration of an unconfirmed job
+    async fn rollback(&self, job: &mut ScheduledApply) {
+        warn!(
+            "Scheduled apply {} was not confirmed in time, rolling back",
+            job.id
+        );
+
+        let Some(previous) = job.previous_config.take() else {
+            job.state = ScheduledApplyState::Failed;
+            job.error = Some("no previous configuration recorded".to_string());
+            return;
+        };
+
+        match self.backend.apply_config(&previous).await {
+            Ok(result) if result.success => {
+                job.state = ScheduledApplyState::RolledBack;
+            }
+            Ok(result) => {
+                job.state = ScheduledApplyState::Failed;
+                job.error = Some(format!(
+                    "rollback failed: {}",
+                    result.error.unwrap_or_default()
+                ));
+            }
+            Err(e) => {
+                job.state = ScheduledApplyState::Failed;
+                job.error = Some(format!("rollback failed: {}", e));
+            }
+        }
+        job.confirm_deadline = None;
+    }
+
+    fn job_path(&self, id: &str) -> PathBuf {
+        self.store_dir.join(format!("{}.json", id))
+    }
+
+    /// Persist a job atomically
+    async fn save(&self, job: &ScheduledApply) -> Result<()> {
+        let path = self.job_path(&job.id);
+        let tmp_path = path.with_extension("json.tmp");
+
+        fs::write(&tmp_path, serde_json::to_string_pretty(job)?).await?;
+        fs::rename(&tmp_path, &path).await?;
+
+        Ok(())
+    }
+}
+
+/// Whether `id` has the format generated by [`ApplyScheduler::schedule`]
+fn is_scheduled_apply_id(id: &str) -> bool {
+    id.strip_prefix("sched_").is_some_and(|sequence| {
+        !sequence.is_empty() && sequence.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex as StdMutex;
+    use tempfile::TempDir;
+
+    /// Backend keeping the "active" configuration in memory
+    struct FakeBackend {
+        active: StdMutex<NetworkConfiguration>,
+        applied: StdMutex<usize>,
+    }
+
+    impl FakeBackend {
+        fn new() -> Self {
+            Self {
+                active: StdMutex::new(NetworkConfiguration::default()),
+                applied: StdMutex::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ApplyBackend for FakeBackend {
+        async fn current_config(&self) -> Result<NetworkConfiguration> {
+            Ok(self.active.lock().unwrap().clone())
+        }
+
+        async fn validate_config(&self, _config: &NetworkConfiguration) -> Result<()> {
+            Ok(())
+        }
+
+        async fn apply_config(&self, config: &NetworkConfiguration) -> Result<ApplyResult> {
+            *self.active.lock().unwrap() = config.clone();
+            *self.applied.lock().unwrap() += 1;
+            Ok(ApplyResult {
+                transaction_id: "txn_fake".to_string(),
+                success: true,
+                applied_changes: vec![],
+                warnings: vec![],
+                error: None,
+                duration_ms: 0,
+            })
+        }
+    }
+
+    fn pending_config() -> NetworkConfiguration {
+        let mut config = NetworkConfiguration::default();
+        config.auto_interfaces.push("vmbr1".to_string());
+        config
+    }
+
+    #[tokio::test]
+    async fn test_schedule_and_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(FakeBackend::new());
+        let scheduler = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+
+        let job = scheduler
+            .schedule(pending_config(), 1000, Some(2000), 0, None)
+            .await
+            .unwrap();
+
+        // Nothing happens before the execution time
+        assert!(scheduler.process_due(999).await.unwrap().is_empty());
+
+        // A fresh scheduler (daemon restart) sees the persisted job
+        let restarted = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+        let jobs = restarted.list().await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, job.id);
+
+        let changed = restarted.process_due(1000).await.unwrap();
+        assert_eq!(changed[0].state, ScheduledApplyState::Committed);
+        assert_eq!(
+            backend.current_config().await.unwrap().auto_interfaces,
+            vec!["vmbr1".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_apply_rolls_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(FakeBackend::new());
+        let scheduler = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+
+        let job = scheduler
+            .schedule(pending_config(), 1000, None, 60, None)
+            .await
+            .unwrap();
+
+        let changed = scheduler.process_due(1000).await.unwrap();
+        assert_eq!(changed[0].state, ScheduledApplyState::AwaitingConfirm);
+        assert_eq!(changed[0].confirm_deadline, Some(1060));
+
+        assert!(scheduler.process_due(1059).await.unwrap().is_empty());
+        let changed = scheduler.process_due(1060).await.unwrap();
+        assert_eq!(changed[0].state, ScheduledApplyState::RolledBack);
+
+        assert!(backend
+            .current_config()
+            .await
+            .unwrap()
+            .auto_interfaces
+            .is_empty());
+        assert_eq!(*backend.applied.lock().unwrap(), 2);
+        assert!(scheduler.confirm(&job.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_apply_is_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(FakeBackend::new());
+        let scheduler = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+
+        let job = scheduler
+            .schedule(pending_config(), 1000, None, 60, None)
+            .await
+            .unwrap();
+        scheduler.process_due(1000).await.unwrap();
+
+        let confirmed = scheduler.confirm(&job.id).await.unwrap();
+        assert_eq!(confirmed.state, ScheduledApplyState::Committed);
+
+        assert!(scheduler.process_due(2000).await.unwrap().is_empty());
+        assert_eq!(
+            backend.current_config().await.unwrap().auto_interfaces,
+            vec!["vmbr1".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_expire() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(FakeBackend::new());
+        let scheduler = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+
+        let cancelled = scheduler
+            .schedule(pending_config(), 1000, None, 0, None)
+            .await
+            .unwrap();
+        let expiring = scheduler
+            .schedule(pending_config(), 1000, Some(1500), 0, None)
+            .await
+            .unwrap();
+
+        scheduler.cancel(&cancelled.id).await.unwrap();
+        assert!(scheduler.cancel(&cancelled.id).await.is_err());
+
+        let changed = scheduler.process_due(1500).await.unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].id, expiring.id);
+        assert_eq!(changed[0].state, ScheduledApplyState::Expired);
+        assert_eq!(*backend.applied.lock().unwrap(), 0);
+
+        assert!(scheduler
+            .schedule(pending_config(), 1000, Some(1000), 0, None)
+            .await
+            .is_err());
+    }
+
+    /// Backend whose applies block until released
+    struct BlockingBackend {
+        inner: FakeBackend,
+        started: tokio::sync::Notify,
+        release: tokio::sync::Notify,
+    }
+
+    #[async_trait]
+    impl ApplyBackend for BlockingBackend {
+        async fn current_config(&self) -> Result<NetworkConfiguration> {
+            self.inner.current_config().await
+        }
+
+        async fn validate_config(&self, config: &NetworkConfiguration) -> Result<()> {
+            self.inner.validate_config(config).await
+        }
+
+        async fn apply_config(&self, config: &NetworkConfiguration) -> Result<ApplyResult> {
+            self.started.notify_one();
+            self.release.notified().await;
+            self.inner.apply_config(config).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_runs_without_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(BlockingBackend {
+            inner: FakeBackend::new(),
+            started: tokio::sync::Notify::new(),
+            release: tokio::sync::Notify::new(),
+        });
+        let scheduler = Arc::new(
+            ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+                .await
+                .unwrap(),
+        );
+
+        let job = scheduler
+            .schedule(pending_config(), 1000, None, 0, None)
+            .await
+            .unwrap();
+
+        let processing = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.process_due(1000).await }
+        });
+        backend.started.notified().await;
+
+        // The running job is claimed and the scheduler still takes requests
+        let running = scheduler.get(&job.id).await.unwrap();
+        assert_eq!(running.state, ScheduledApplyState::Applying);
+        assert!(scheduler.cancel(&job.id).await.is_err());
+        let other = tokio::time::timeout(
+            Duration::from_secs(5),
+            scheduler.schedule(pending_config(), 5000, None, 0, None),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // A concurrent run does not apply the claimed job twice
+        assert!(scheduler.process_due(1000).await.unwrap().is_empty());
+
+        backend.release.notify_one();
+        let changed = processing.await.unwrap().unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].state, ScheduledApplyState::Committed);
+        assert_eq!(
+            scheduler.get(&other.id).await.unwrap().state,
+            ScheduledApplyState::Pending
+        );
+    }
+
+    /// Backend whose applies take a while, like a slow ifreload
+    struct SlowBackend {
+        inner: FakeBackend,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl ApplyBackend for SlowBackend {
+        async fn current_config(&self) -> Result<NetworkConfiguration> {
+            self.inner.current_config().await
+        }
+
+        async fn validate_config(&self, config: &NetworkConfiguration) -> Result<()> {
+            self.inner.validate_config(config).await
+        }
+
+        async fn apply_config(&self, config: &NetworkConfiguration) -> Result<ApplyResult> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.apply_config(config).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirm_window_starts_after_apply() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(SlowBackend {
+            inner: FakeBackend::new(),
+            delay: Duration::from_millis(2100),
+        });
+        let scheduler = ApplyScheduler::with_store_dir(backend, temp_dir.path())
+            .await
+            .unwrap();
+
+        scheduler
+            .schedule(pending_config(), 1000, None, 1, None)
+            .await
+            .unwrap();
+
+        let changed = scheduler.process_due(1000).await.unwrap();
+        assert_eq!(changed[0].state, ScheduledApplyState::AwaitingConfirm);
+        assert_eq!(changed[0].confirm_deadline, Some(1003));
+
+        // The apply took longer than the confirm timeout, still not rolled back
+        assert!(scheduler.process_due(1002).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_apply_fails_on_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = Arc::new(FakeBackend::new());
+        let scheduler = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+
+        let mut job = scheduler
+            .schedule(pending_config(), 1000, None, 0, None)
+            .await
+            .unwrap();
+        job.state = ScheduledApplyState::Applying;
+        scheduler.save(&job).await.unwrap();
+
+        let restarted = ApplyScheduler::with_store_dir(backend.clone(), temp_dir.path())
+            .await
+            .unwrap();
+        let job = restarted.get(&job.id).await.unwrap();
+        assert_eq!(job.state, ScheduledApplyState::Failed);
+        assert!(restarted.process_due(1000).await.unwrap().is_empty());
+        assert_eq!(*backend.applied.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_job_ids_are_validated() {
+        let temp_dir = TempDir::new().unwrap();
+        let store_dir = temp_dir.path().join("scheduled");
+        let scheduler = ApplyScheduler::with_store_dir(Arc::new(FakeBackend::new()), &store_dir)
+            .await
+            .unwrap();
+
+        // A valid job outside the store directory must not be reachable
+        let job = scheduler
+            .schedule(pending_config(), 1000, None, 0, None)
+            .await
+            .unwrap();
+        std::fs::copy(
+            store_dir.join(format!("{}.json", job.id)),
+            temp_dir.path().join("outside.json"),
+        )
+        .unwrap();
+
+        for id in ["../outside", "sched_", "sched_1/../../outside", "cr_1"] {
+            assert!(
+                matches!(
+                    scheduler.get(id).await,
+                    Err(NetworkError::Api(ApiError::NotFound { .. }))
+                ),
+                "{}",
+                id
+            );
+            assert!(scheduler.cancel(id).await.is_err());
+        }
+        assert!(scheduler.get(&job.id).await.is_ok());
+    }
+}
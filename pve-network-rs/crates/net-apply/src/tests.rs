@@ -2,21 +2,44 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::{IfUpDownIntegration, NetworkApplier, RollbackManager, TransactionState};
+    use crate::{
+        HookRunner, HookStage, IfUpDownIntegration, NetworkApplier, RollbackManager, Transaction,
+        TransactionState,
+    };
     use pve_network_config::{NetworkConfigManager, PmxcfsConfig};
     use pve_network_core::NetworkConfiguration;
     use pve_network_validate::NetworkValidator;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
     use std::sync::Arc;
+    use std::time::Duration;
     use tempfile::TempDir;
 
+    fn write_hook(dir: &Path, stage: HookStage, name: &str, script: &str) {
+        let stage_dir = dir.join(stage.dir_name());
+        std::fs::create_dir_all(&stage_dir).unwrap();
+        let path = stage_dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
     async fn create_test_applier() -> NetworkApplier {
         let temp_dir = TempDir::new().unwrap();
-        let rollback_dir = temp_dir.path().join("rollback");
-        let transaction_log_dir = temp_dir.path().join("transactions");
-        let pmxcfs = Arc::new(PmxcfsConfig::with_base_path(temp_dir.path()).unwrap());
+        create_test_applier_in(temp_dir.path()).await
+    }
+
+    async fn create_test_applier_in(dir: &Path) -> NetworkApplier {
+        create_test_applier_with(dir, IfUpDownIntegration::new()).await
+    }
+
+    async fn create_test_applier_with(dir: &Path, ifupdown: IfUpDownIntegration) -> NetworkApplier {
+        let temp_dir = dir;
+        let rollback_dir = temp_dir.join("rollback");
+        let transaction_log_dir = temp_dir.join("transactions");
+        let pmxcfs = Arc::new(PmxcfsConfig::with_base_path(temp_dir).unwrap());
         let config_manager = Arc::new(NetworkConfigManager::with_pmxcfs((*pmxcfs).clone()));
         let validator = Arc::new(NetworkValidator::new());
-        let ifupdown = Arc::new(IfUpDownIntegration::new());
+        let ifupdown = Arc::new(ifupdown);
         let rollback_manager = Arc::new(
             RollbackManager::new(Some(config_manager.clone()), Some(rollback_dir))
                 .await
@@ -53,6 +76,126 @@ mod tests {
         // assert!(!transaction.id.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_vetoed_transaction_is_not_rolled_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook_dir = temp_dir.path().join("hooks.d");
+        let rolled_back = temp_dir.path().join("rolled-back");
+        write_hook(
+            &hook_dir,
+            HookStage::PreValidate,
+            "10-deny",
+            "echo 'maintenance window closed' >&2; exit 1",
+        );
+        write_hook(
+            &hook_dir,
+            HookStage::PostRollback,
+            "10-record",
+            &format!("touch {}", rolled_back.display()),
+        );
+
+        let applier = create_test_applier_in(temp_dir.path())
+            .await
+            .with_hooks(HookRunner::with_config(&hook_dir, Duration::from_secs(5)));
+        let transaction = Transaction::new(
+            NetworkConfiguration::default(),
+            NetworkConfiguration::default(),
+        )
+        .unwrap();
+        let id = transaction.id.clone();
+
+        let result = applier.apply_transaction(transaction).await.unwrap();
+        assert!(!result.success);
+        assert!(result
+            .error
+            .unwrap()
+            .contains("pre-validate hook 10-deny failed: exit code 1 (maintenance window closed)"));
+
+        // The transaction ends as failed, without restoring or reloading
+        assert!(applier.get_active_transactions().await.is_empty());
+        let log = std::fs::read_to_string(
+            temp_dir
+                .path()
+                .join("transactions")
+                .join(format!("{}.log", id)),
+        )
+        .unwrap();
+        let log: serde_json::Value = serde_json::from_str(&log).unwrap();
+        assert_eq!(log["state"], serde_json::json!(TransactionState::Failed));
+        assert!(!rolled_back.exists());
+        assert_eq!(
+            applier.transaction_state(&id).await.unwrap(),
+            Some(TransactionState::Failed)
+        );
+        assert_eq!(applier.transaction_state("txn_0").await.unwrap(), None);
+        assert_eq!(applier.transaction_state("../x").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pre_apply_veto_reloads_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook_dir = temp_dir.path().join("hooks.d");
+        let rolled_back = temp_dir.path().join("rolled-back");
+        let reloaded = temp_dir.path().join("reloaded");
+        write_hook(
+            &hook_dir,
+            HookStage::PreApply,
+            "10-deny",
+            "echo 'change freeze' >&2; exit 1",
+        );
+        write_hook(
+            &hook_dir,
+            HookStage::PostRollback,
+            "10-record",
+            &format!("touch {}", rolled_back.display()),
+        );
+
+        // ifquery accepts every dry-run, ifup records any reload
+        let ifup = temp_dir.path().join("ifup");
+        std::fs::write(&ifup, format!("#!/bin/sh\ntouch {}\n", reloaded.display())).unwrap();
+        std::fs::set_permissions(&ifup, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let ifupdown = IfUpDownIntegration::with_config(
+            ifup.display().to_string(),
+            "/bin/true".to_string(),
+            "/bin/true".to_string(),
+            Duration::from_secs(5),
+            false,
+        );
+
+        let applier = create_test_applier_with(temp_dir.path(), ifupdown)
+            .await
+            .with_hooks(HookRunner::with_config(&hook_dir, Duration::from_secs(5)));
+        let transaction = Transaction::new(
+            NetworkConfiguration::default(),
+            NetworkConfiguration::default(),
+        )
+        .unwrap();
+        let id = transaction.id.clone();
+
+        let result = applier.apply_transaction(transaction).await.unwrap();
+        assert!(!result.success);
+        assert!(result
+            .error
+            .unwrap()
+            .contains("pre-apply hook 10-deny failed: exit code 1 (change freeze)"));
+
+        // The veto fails the transaction and drops its rollback point
+        assert!(!reloaded.exists());
+        assert!(!rolled_back.exists());
+        assert_eq!(
+            applier.transaction_state(&id).await.unwrap(),
+            Some(TransactionState::Failed)
+        );
+        let rollback_manager = RollbackManager::new(None, Some(temp_dir.path().join("rollback")))
+            .await
+            .unwrap();
+        assert!(rollback_manager
+            .list_rollback_points()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_rollback_manager_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -12,10 +12,11 @@ use tokio::sync::Mutex;
 
 use pve_event_bus::EventBus;
 use pve_network_config::{NetworkConfigManager, PmxcfsConfig};
-use pve_network_core::{NetworkConfiguration, Result};
+use pve_network_core::{NetworkConfiguration, NetworkError, Result};
 use pve_network_validate::NetworkValidator;
 use pve_shared_types::{ChangeType, ConfigChange, SystemEvent};
 
+use crate::hooks::{HookRunner, HookStage};
 use crate::ifupdown::IfUpDownIntegration;
 use crate::rollback::RollbackManager;
 
@@ -38,6 +39,29 @@ pub struct Transaction {
     pub metadata: HashMap<String, String>,
 }
 
+impl Transaction {
+    /// Create a new transaction with the semantic diff between both configurations
+    pub fn new(
+        original_config: NetworkConfiguration,
+        new_config: NetworkConfiguration,
+    ) -> Result<Self> {
+        let changes = calculate_changes(&original_config, &new_config)?;
+
+        Ok(Self {
+            id: generate_transaction_id(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            original_config,
+            new_config,
+            state: TransactionState::Created,
+            changes,
+            metadata: HashMap::new(),
+        })
+    }
+}
+
 /// Transaction states
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TransactionState {
@@ -98,6 +122,8 @@ pub struct NetworkApplier {
     transaction_log_dir: PathBuf,
     /// Optional event bus for broadcasting applied changes
     event_bus: Option<Arc<EventBus>>,
+    /// User-defined hook scripts run around the apply stages
+    hooks: Arc<HookRunner>,
 }
 
 impl NetworkApplier {
@@ -144,6 +170,7 @@ impl NetworkApplier {
             active_transactions: Arc::new(Mutex::new(HashMap::new())),
             transaction_log_dir,
             event_bus: None,
+            hooks: Arc::new(HookRunner::new()),
         })
     }
 
@@ -153,50 +180,90 @@ impl NetworkApplier {
         self
     }
 
+    /// Use a custom hook runner instead of the default hook directory.
+    pub fn with_hooks(mut self, hooks: HookRunner) -> Self {
+        self.hooks = Arc::new(hooks);
+        self
+    }
+
     /// Begin a new transaction for configuration changes
     pub async fn begin_transaction(&self, new_config: NetworkConfiguration) -> Result<Transaction> {
-        let transaction_id = self.generate_transaction_id();
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
         // Get current configuration
         let original_config = self.config_manager.get_current_config().await?;
 
-        // Calculate changes
-        let changes = self.calculate_changes(&original_config, &new_config)?;
+        let transaction = Transaction::new(original_config, new_config)?;
+        self.register_transaction(&transaction).await?;
 
-        let transaction = Transaction {
-            id: transaction_id.clone(),
-            timestamp,
-            original_config,
-            new_config,
-            state: TransactionState::Created,
-            changes,
-            metadata: HashMap::new(),
-        };
+        Ok(transaction)
+    }
 
-        // Store transaction
+    /// Store a transaction as active and log its creation
+    async fn register_transaction(&self, transaction: &Transaction) -> Result<()> {
         {
             let mut active = self.active_transactions.lock().await;
-            active.insert(transaction_id.clone(), transaction.clone());
+            active.insert(transaction.id.clone(), transaction.clone());
         }
 
         // Log transaction creation
-        self.log_transaction(&transaction, "Transaction created")
+        self.log_transaction(transaction, "Transaction created")
             .await?;
 
-        info!("Created transaction {}", transaction_id);
-        Ok(transaction)
+        info!("Created transaction {}", transaction.id);
+        Ok(())
     }
 
     /// Apply configuration changes transactionally
     pub async fn apply_configuration(&self, config: &NetworkConfiguration) -> Result<ApplyResult> {
         let start_time = SystemTime::now();
-        let mut transaction = self.begin_transaction(config.clone()).await?;
+        let transaction = self.begin_transaction(config.clone()).await?;
+
+        self.run_transaction(transaction, start_time).await
+    }
+
+    /// Apply a transaction prepared earlier, e.g. by an approved change request
+    pub async fn apply_transaction(&self, transaction: Transaction) -> Result<ApplyResult> {
+        let start_time = SystemTime::now();
+        self.register_transaction(&transaction).await?;
+
+        self.run_transaction(transaction, start_time).await
+    }
+
+    /// Run a registered transaction, committing on success and rolling back on failure
+    async fn run_transaction(
+        &self,
+        mut transaction: Transaction,
+        start_time: SystemTime,
+    ) -> Result<ApplyResult> {
+        // A veto comes before anything was changed, there is nothing to
+        // roll back or reload
+        if let Err(e) = self
+            .run_hooks(HookStage::PreValidate, &mut transaction)
+            .await
+        {
+            return self.fail_vetoed(transaction, e, start_time).await;
+        }
 
-        let result = self.apply_transaction_internal(&mut transaction).await;
+        let result = match self.prepare_transaction(&mut transaction).await {
+            Ok(()) => {
+                // Only the rollback point exists at this stage, a veto
+                // drops it instead of restoring it
+                if let Err(e) = self.run_hooks(HookStage::PreApply, &mut transaction).await {
+                    if let Err(cleanup_err) = self
+                        .rollback_manager
+                        .cleanup_rollback_point(&transaction.id)
+                        .await
+                    {
+                        warn!(
+                            "Failed to remove rollback point of vetoed transaction {}: {}",
+                            transaction.id, cleanup_err
+                        );
+                    }
+                    return self.fail_vetoed(transaction, e, start_time).await;
+                }
+                self.apply_transaction_internal(&mut transaction).await
+            }
+            Err(e) => Err(e),
+        };
 
         let duration_ms = start_time.elapsed().unwrap_or_default().as_millis() as u64;
 
@@ -254,11 +321,36 @@ impl NetworkApplier {
         }
     }
 
-    /// Apply transaction with staged approach
-    async fn apply_transaction_internal(
+    /// Fail a transaction vetoed by a hook before anything was changed
+    async fn fail_vetoed(
         &self,
-        transaction: &mut Transaction,
-    ) -> Result<Vec<ConfigChange>> {
+        mut transaction: Transaction,
+        error: NetworkError,
+        start_time: SystemTime,
+    ) -> Result<ApplyResult> {
+        error!("Transaction {} vetoed: {}", transaction.id, error);
+
+        transaction.state = TransactionState::Failed;
+        self.update_transaction(&transaction).await?;
+        {
+            let mut active = self.active_transactions.lock().await;
+            active.remove(&transaction.id);
+        }
+        self.log_transaction(&transaction, &format!("Transaction vetoed: {}", error))
+            .await?;
+
+        Ok(ApplyResult {
+            transaction_id: transaction.id,
+            success: false,
+            applied_changes: vec![],
+            warnings: vec![],
+            error: Some(error.to_string()),
+            duration_ms: start_time.elapsed().unwrap_or_default().as_millis() as u64,
+        })
+    }
+
+    /// Validate and dry-run a transaction and create its rollback point
+    async fn prepare_transaction(&self, transaction: &mut Transaction) -> Result<()> {
         info!("Applying transaction {}", transaction.id);
 
         // Stage 1: Validation
@@ -282,7 +374,14 @@ impl NetworkApplier {
             .await?;
 
         info!("Created rollback point for transaction {}", transaction.id);
+        Ok(())
+    }
 
+    /// Apply a prepared transaction with staged approach
+    async fn apply_transaction_internal(
+        &self,
+        transaction: &mut Transaction,
+    ) -> Result<Vec<ConfigChange>> {
         // Stage 4: Apply changes
         transaction.state = TransactionState::Applying;
         self.update_transaction(transaction).await?;
@@ -305,6 +404,13 @@ impl NetworkApplier {
             }
         }
 
+        if let Err(e) = self.run_hooks(HookStage::PostApply, transaction).await {
+            warn!(
+                "Post-apply hooks for transaction {} failed: {}",
+                transaction.id, e
+            );
+        }
+
         Ok(applied_changes)
     }
 
@@ -427,6 +533,13 @@ impl NetworkApplier {
         transaction.state = TransactionState::RolledBack;
         self.update_transaction(transaction).await?;
 
+        if let Err(e) = self.run_hooks(HookStage::PostRollback, transaction).await {
+            warn!(
+                "Post-rollback hooks for transaction {} failed: {}",
+                transaction.id, e
+            );
+        }
+
         // Remove from active transactions
         {
             let mut active = self.active_transactions.lock().await;
@@ -439,53 +552,37 @@ impl NetworkApplier {
         Ok(())
     }
 
-    /// Calculate changes between configurations
-    fn calculate_changes(
-        &self,
-        old_config: &NetworkConfiguration,
-        new_config: &NetworkConfiguration,
-    ) -> Result<Vec<ConfigChange>> {
-        let mut changes = Vec::new();
-
-        // Find deleted interfaces
-        for (name, old_iface) in &old_config.interfaces {
-            if !new_config.interfaces.contains_key(name) {
-                changes.push(ConfigChange {
-                    change_type: ChangeType::Delete,
-                    target: name.clone(),
-                    old_config: Some(serde_json::to_value(old_iface)?),
-                    new_config: None,
-                    description: format!("Delete interface {}", name),
-                });
-            }
+    /// Run hooks for a stage, record results in the transaction metadata and
+    /// fail if a pre-stage hook vetoed the transaction
+    async fn run_hooks(&self, stage: HookStage, transaction: &mut Transaction) -> Result<()> {
+        let results = self.hooks.run_stage(stage, transaction).await?;
+        if results.is_empty() {
+            return Ok(());
         }
 
-        // Find created and updated interfaces
-        for (name, new_iface) in &new_config.interfaces {
-            if let Some(old_iface) = old_config.interfaces.get(name) {
-                // Check if interface was modified
-                if serde_json::to_value(old_iface)? != serde_json::to_value(new_iface)? {
-                    changes.push(ConfigChange {
-                        change_type: ChangeType::Update,
-                        target: name.clone(),
-                        old_config: Some(serde_json::to_value(old_iface)?),
-                        new_config: Some(serde_json::to_value(new_iface)?),
-                        description: format!("Update interface {}", name),
-                    });
+        transaction
+            .metadata
+            .insert(format!("hooks.{}", stage), serde_json::to_string(&results)?);
+        self.update_transaction(transaction).await?;
+
+        if let Some(bus) = &self.event_bus {
+            for result in &results {
+                if let Err(err) = bus
+                    .publish(SystemEvent::NetworkHookExecuted {
+                        transaction_id: transaction.id.clone(),
+                        stage: stage.to_string(),
+                        hook: result.hook.clone(),
+                        success: result.success,
+                        exit_code: result.exit_code,
+                    })
+                    .await
+                {
+                    warn!("Failed to publish NetworkHookExecuted event: {}", err);
                 }
-            } else {
-                // New interface
-                changes.push(ConfigChange {
-                    change_type: ChangeType::Create,
-                    target: name.clone(),
-                    old_config: None,
-                    new_config: Some(serde_json::to_value(new_iface)?),
-                    description: format!("Create interface {}", name),
-                });
             }
         }
 
-        Ok(changes)
+        HookRunner::check_results(stage, &results)
     }
 
     /// Update transaction state
@@ -524,13 +621,14 @@ impl NetworkApplier {
         Ok(())
     }
 
-    /// Generate unique transaction ID
-    fn generate_transaction_id(&self) -> String {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
-        format!("txn_{}", timestamp)
+    /// Validate a configuration without applying it
+    pub async fn validate_configuration(&self, config: &NetworkConfiguration) -> Result<()> {
+        self.validator.validate(config).await
+    }
+
+    /// Get the currently active configuration
+    pub async fn current_configuration(&self) -> Result<NetworkConfiguration> {
+        self.config_manager.get_current_config().await
     }
 
     /// Get active transactions
@@ -544,6 +642,47 @@ impl NetworkApplier {
         let active = self.active_transactions.lock().await;
         active.get(transaction_id).cloned()
     }
+
+    /// State of an active or finished transaction
+    ///
+    /// Finished transactions are looked up in the transaction log, which
+    /// holds the last state each transaction reached.
+    pub async fn transaction_state(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Option<TransactionState>> {
+        if let Some(transaction) = self.get_transaction(transaction_id).await {
+            return Ok(Some(transaction.state));
+        }
+
+        if transaction_id.is_empty()
+            || !transaction_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Ok(None);
+        }
+
+        let log_file = self
+            .transaction_log_dir
+            .join(format!("{}.log", transaction_id));
+        let content = match fs::read_to_string(&log_file).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let state = content
+            .lines()
+            .last()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .transpose()?
+            .and_then(|entry| entry.get("state").cloned())
+            .map(serde_json::from_value)
+            .transpose()?;
+        Ok(state)
+    }
+
     /// Create a placeholder NetworkApplier for CLI testing
     /// This should not be used in production - use new() instead
     pub fn placeholder() -> Self {
@@ -562,6 +701,7 @@ impl NetworkApplier {
             active_transactions: Arc::new(Mutex::new(HashMap::new())),
             transaction_log_dir: PathBuf::from("/tmp/pve-network-transactions"),
             event_bus: None,
+            hooks: Arc::new(crate::HookRunner::new()),
         }
     }
 }
@@ -572,3 +712,60 @@ impl Default for NetworkApplier {
         panic!("NetworkApplier must be created with new() method")
     }
 }
+
+/// Calculate the semantic diff between two configurations
+pub fn calculate_changes(
+    old_config: &NetworkConfiguration,
+    new_config: &NetworkConfiguration,
+) -> Result<Vec<ConfigChange>> {
+    let mut changes = Vec::new();
+
+    // Find deleted interfaces
+    for (name, old_iface) in &old_config.interfaces {
+        if !new_config.interfaces.contains_key(name) {
+            changes.push(ConfigChange {
+                change_type: ChangeType::Delete,
+                target: name.clone(),
+                old_config: Some(serde_json::to_value(old_iface)?),
+                new_config: None,
+                description: format!("Delete interface {}", name),
+            });
+        }
+    }
+
+    // Find created and updated interfaces
+    for (name, new_iface) in &new_config.interfaces {
+        if let Some(old_iface) = old_config.interfaces.get(name) {
+            // Check if interface was modified
+            if serde_json::to_value(old_iface)? != serde_json::to_value(new_iface)? {
+                changes.push(ConfigChange {
+                    change_type: ChangeType::Update,
+                    target: name.clone(),
+                    old_config: Some(serde_json::to_value(old_iface)?),
+                    new_config: Some(serde_json::to_value(new_iface)?),
+                    description: format!("Update interface {}", name),
+                });
+            }
+        } else {
+            // New interface
+            changes.push(ConfigChange {
+                change_type: ChangeType::Create,
+                target: name.clone(),
+                old_config: None,
+                new_config: Some(serde_json::to_value(new_iface)?),
+                description: format!("Create interface {}", name),
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Generate unique transaction ID
+fn generate_transaction_id() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    format!("txn_{}", timestamp)
+}
@@ -3,11 +3,15 @@
 pub mod apply;
 pub mod compat;
 pub mod rollback;
+pub mod schedule;
 pub mod status;
+pub mod topology;
 pub mod validate;
 
 pub use apply::ApplyCommand;
 pub use compat::CompatCommand;
 pub use rollback::RollbackCommand;
+pub use schedule::ScheduleCommand;
 pub use status::StatusCommand;
+pub use topology::TopologyCommand;
 pub use validate::ValidateCommand;
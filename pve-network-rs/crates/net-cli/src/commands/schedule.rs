@@ -0,0 +1,173 @@
+//! Schedule command
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use pve_network_api::context::AppContext;
+use pve_network_apply::ScheduledApply;
+use pve_network_config::InterfacesParser;
+use std::fs;
+use std::sync::Arc;
+
+/// Schedule command implementation
+pub struct ScheduleCommand {
+    context: Arc<AppContext>,
+    parser: InterfacesParser,
+}
+
+impl ScheduleCommand {
+    /// Create new schedule command
+    pub fn new(context: Arc<AppContext>) -> Self {
+        Self {
+            context,
+            parser: InterfacesParser::new(),
+        }
+    }
+
+    /// Queue a configuration file for application at a later time
+    pub async fn add(
+        &self,
+        config_path: &str,
+        at: &str,
+        expires: Option<&str>,
+        confirm_timeout: u64,
+        comment: Option<String>,
+    ) -> Result<()> {
+        let execute_at = parse_time(at)?;
+        let expires_at = expires.map(parse_time).transpose()?;
+
+        let content = fs::read_to_string(config_path)
+            .with_context(|| format!("Failed to read configuration file: {}", config_path))?;
+
+        let config = self
+            .parser
+            .parse(&content)
+            .with_context(|| "Failed to parse network configuration")?;
+
+        let job = self
+            .context
+            .apply_scheduler
+            .schedule(config, execute_at, expires_at, confirm_timeout, comment)
+            .await
+            .with_context(|| "Failed to schedule network configuration apply")?;
+
+        println!("✓ Configuration validated and scheduled");
+        println!("  ID:         {}", job.id);
+        println!("  Execute at: {}", format_time(job.execute_at));
+        if let Some(expires_at) = job.expires_at {
+            println!("  Expires at: {}", format_time(expires_at));
+        }
+        if job.confirm_timeout > 0 {
+            println!(
+                "  Must be confirmed within {}s after apply (pvenet schedule --confirm {})",
+                job.confirm_timeout, job.id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// List scheduled applies
+    pub async fn list(&self, format: &str) -> Result<()> {
+        let jobs = self
+            .context
+            .apply_scheduler
+            .list()
+            .await
+            .with_context(|| "Failed to list scheduled applies")?;
+
+        if format == "json" {
+            println!("{}", serde_json::to_string_pretty(&jobs)?);
+            return Ok(());
+        }
+
+        if jobs.is_empty() {
+            println!("No scheduled applies.");
+            return Ok(());
+        }
+
+        println!(
+            "{:<20} {:<20} {:<20} {:<16} Comment",
+            "ID", "Execute at", "Expires at", "State"
+        );
+        println!("{}", "-".repeat(90));
+
+        for job in &jobs {
+            print_job(job);
+        }
+
+        Ok(())
+    }
+
+    /// Cancel a pending scheduled apply
+    pub async fn cancel(&self, id: &str) -> Result<()> {
+        self.context
+            .apply_scheduler
+            .cancel(id)
+            .await
+            .with_context(|| format!("Failed to cancel scheduled apply '{}'", id))?;
+
+        println!("✓ Scheduled apply '{}' cancelled", id);
+        Ok(())
+    }
+
+    /// Confirm an applied job so it is not rolled back
+    pub async fn confirm(&self, id: &str) -> Result<()> {
+        self.context
+            .apply_scheduler
+            .confirm(id)
+            .await
+            .with_context(|| format!("Failed to confirm scheduled apply '{}'", id))?;
+
+        println!("✓ Scheduled apply '{}' confirmed", id);
+        Ok(())
+    }
+}
+
+fn print_job(job: &ScheduledApply) {
+    println!(
+        "{:<20} {:<20} {:<20} {:<16} {}",
+        job.id,
+        format_time(job.execute_at),
+        job.expires_at
+            .map(format_time)
+            .unwrap_or_else(|| "-".to_string()),
+        format!("{:?}", job.state),
+        job.comment.as_deref().unwrap_or("")
+    );
+
+    if let Some(error) = &job.error {
+        println!("  Error: {}", error);
+    }
+}
+
+/// Parse a unix timestamp, RFC 3339 time or local "YYYY-MM-DD HH:MM" time
+fn parse_time(value: &str) -> Result<u64> {
+    if let Ok(epoch) = value.parse::<u64>() {
+        return Ok(epoch);
+    }
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp() as u64);
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .with_context(|| format!("Invalid time '{}'", value))?;
+
+    let local = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .with_context(|| format!("Invalid local time '{}'", value))?;
+
+    Ok(local.timestamp() as u64)
+}
+
+fn format_time(epoch: u64) -> String {
+    DateTime::<Utc>::from_timestamp(epoch as i64, 0)
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| epoch.to_string())
+}
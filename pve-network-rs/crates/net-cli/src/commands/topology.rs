@@ -0,0 +1,40 @@
+//! Topology command
+
+use anyhow::{bail, Context, Result};
+use pve_network_api::context::AppContext;
+use std::sync::Arc;
+
+/// Topology command implementation
+pub struct TopologyCommand {
+    context: Arc<AppContext>,
+}
+
+impl TopologyCommand {
+    /// Create new topology command
+    pub fn new(context: Arc<AppContext>) -> Self {
+        Self { context }
+    }
+
+    /// Print the network topology graph as JSON or Graphviz DOT
+    pub async fn execute(&self, format: &str) -> Result<()> {
+        print!("{}", self.render(format).await?);
+        Ok(())
+    }
+
+    /// Render the network topology graph as JSON or Graphviz DOT
+    ///
+    /// The SDN part shows the running configuration from pmxcfs.
+    pub async fn render(&self, format: &str) -> Result<String> {
+        let topology = self
+            .context
+            .running_network_topology()
+            .await
+            .context("Failed to build network topology")?;
+
+        match format {
+            "json" => Ok(format!("{}\n", serde_json::to_string_pretty(&topology)?)),
+            "dot" => Ok(topology.to_dot()),
+            _ => bail!("Unknown format '{}', expected json or dot", format),
+        }
+    }
+}
@@ -4,7 +4,8 @@ use anyhow::Result;
 use clap::{Parser, Subcommand};
 use pve_network_api::context::AppContext;
 use pvenet::commands::{
-    ApplyCommand, CompatCommand, RollbackCommand, StatusCommand, ValidateCommand,
+    ApplyCommand, CompatCommand, RollbackCommand, ScheduleCommand, StatusCommand, TopologyCommand,
+    ValidateCommand,
 };
 
 #[derive(Parser)]
@@ -28,9 +29,12 @@ Examples:
   pvenet rollback                          # Rollback to previous version
   pvenet rollback -v 20231201-120000       # Rollback to specific version
   pvenet rollback --list                   # List available versions
+  pvenet schedule --at \"2024-01-01 02:00\"  # Apply interfaces.new at 02:00
+  pvenet schedule --list                   # List scheduled applies
   pvenet status                            # Show basic status
   pvenet status -v                         # Show detailed status
   pvenet status --stats                    # Show interface statistics
+  pvenet topology --format dot | dot -Tsvg # Render the network topology
 ")]
 struct Cli {
     /// Enable verbose output
@@ -112,6 +116,45 @@ enum Commands {
         force: bool,
     },
 
+    /// Schedule network configuration apply for a maintenance window
+    Schedule {
+        /// Execution time (unix epoch, RFC 3339 or "YYYY-MM-DD HH:MM" local time)
+        #[arg(long)]
+        at: Option<String>,
+
+        /// Drop the job if it could not run before this time
+        #[arg(long)]
+        expires: Option<String>,
+
+        /// Seconds to wait for confirmation after apply before rolling back (0 = none)
+        #[arg(long, default_value_t = 0)]
+        confirm_timeout: u64,
+
+        /// Configuration file to schedule
+        #[arg(short, long, default_value = "/etc/network/interfaces.new")]
+        config: String,
+
+        /// Comment for the scheduled apply
+        #[arg(long)]
+        comment: Option<String>,
+
+        /// List scheduled applies
+        #[arg(short, long)]
+        list: bool,
+
+        /// Cancel a pending scheduled apply
+        #[arg(long, value_name = "ID")]
+        cancel: Option<String>,
+
+        /// Confirm an applied scheduled apply
+        #[arg(long, value_name = "ID")]
+        confirm: Option<String>,
+
+        /// Output format for --list (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
     /// Show network status
     Status {
         /// Show detailed status
@@ -135,6 +178,13 @@ enum Commands {
         filter: Option<String>,
     },
 
+    /// Show network topology graph
+    Topology {
+        /// Output format (json, dot)
+        #[arg(short, long, default_value = "json")]
+        format: String,
+    },
+
     /// List network interfaces (pvesh compatible)
     List {
         /// Node name
@@ -233,6 +283,34 @@ async fn main() -> Result<()> {
             }
         }
 
+        Commands::Schedule {
+            at,
+            expires,
+            confirm_timeout,
+            config,
+            comment,
+            list,
+            cancel,
+            confirm,
+            format,
+        } => {
+            let cmd = ScheduleCommand::new(context.clone());
+            if list {
+                cmd.list(&format).await
+            } else if let Some(id) = cancel {
+                cmd.cancel(&id).await
+            } else if let Some(id) = confirm {
+                cmd.confirm(&id).await
+            } else if let Some(at) = at {
+                cmd.add(&config, &at, expires.as_deref(), confirm_timeout, comment)
+                    .await
+            } else {
+                Err(anyhow::anyhow!(
+                    "One of --at, --list, --cancel or --confirm is required"
+                ))
+            }
+        }
+
         Commands::Status {
             verbose,
             stats,
@@ -248,6 +326,11 @@ async fn main() -> Result<()> {
             }
         }
 
+        Commands::Topology { format } => {
+            let cmd = TopologyCommand::new(context.clone());
+            cmd.execute(&format).await
+        }
+
         Commands::List { node, format } => {
             let cmd = CompatCommand::new(context.clone());
             cmd.list_nodes_network(&node, &format).await
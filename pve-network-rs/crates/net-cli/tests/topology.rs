@@ -0,0 +1,59 @@
+//! Integration tests for the topology command
+
+use pve_network_api::context::AppContext;
+use pve_network_core::topology::{NetworkTopology, TopologyNodeKind};
+use pve_shared_types::{VNetConfig, ZoneConfig, ZoneType};
+use pvenet::commands::TopologyCommand;
+
+async fn context() -> std::sync::Arc<AppContext> {
+    let context = AppContext::bootstrap().await.unwrap();
+    {
+        let mut config = context.sdn_state.config.write().await;
+        let mut zone = ZoneConfig::new(ZoneType::Vlan, "zone1".to_string());
+        zone.bridge = Some("vmbr0".to_string());
+        config.add_zone(zone).unwrap();
+        config
+            .add_vnet(VNetConfig::new("vnet1".to_string(), "zone1".to_string()))
+            .unwrap();
+    }
+    context
+}
+
+#[tokio::test]
+async fn test_topology_command_json() {
+    let cmd = TopologyCommand::new(context().await);
+    let output = cmd.render("json").await.unwrap();
+
+    let topology: NetworkTopology = serde_json::from_str(&output).unwrap();
+    let kind = |id: &str| {
+        topology
+            .nodes
+            .iter()
+            .find(|node| node.id == id)
+            .map(|node| node.kind)
+    };
+    assert_eq!(kind("iface:vmbr0"), Some(TopologyNodeKind::Bridge));
+    assert_eq!(kind("zone:zone1"), Some(TopologyNodeKind::Zone));
+    assert_eq!(kind("vnet:vnet1"), Some(TopologyNodeKind::Vnet));
+    assert!(topology
+        .edges
+        .iter()
+        .any(|edge| edge.from == "iface:vmbr0" && edge.to == "zone:zone1"));
+}
+
+#[tokio::test]
+async fn test_topology_command_dot() {
+    let cmd = TopologyCommand::new(context().await);
+    let output = cmd.render("dot").await.unwrap();
+
+    assert!(output.starts_with("digraph topology {"));
+    assert!(output.contains("\"iface:vmbr0\" -> \"zone:zone1\" [label=\"uplink\"];"));
+    assert!(output.contains("\"zone:zone1\" -> \"vnet:vnet1\" [label=\"vnet\"];"));
+}
+
+#[tokio::test]
+async fn test_topology_command_unknown_format() {
+    let cmd = TopologyCommand::new(context().await);
+    let error = cmd.render("xml").await.unwrap_err().to_string();
+    assert!(error.contains("Unknown format 'xml'"), "{}", error);
+}
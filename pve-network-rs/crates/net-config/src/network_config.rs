@@ -49,8 +49,6 @@ impl NetworkConfigManager {
 # This file describes the network interfaces available on your system
 # and how to activate them. For more information, see interfaces(5).
 
-source /etc/network/interfaces.d/*
-
 # The loopback network interface
 auto lo
 iface lo inet loopback
@@ -152,6 +150,11 @@ iface bond0 inet static
             .interfaces
             .insert(interface_name.to_string(), interface_config);
 
+        // The file is written in this order, new interfaces go last
+        if !config.ordering.iter().any(|name| name == interface_name) {
+            config.ordering.push(interface_name.to_string());
+        }
+
         self.write_node_config(node, &config).await
     }
 
@@ -168,6 +171,7 @@ iface bond0 inet static
         config.interfaces.remove(interface_name);
 
         // Also remove from auto and hotplug lists
+        config.ordering.retain(|name| name != interface_name);
         config.auto_interfaces.retain(|name| name != interface_name);
         config
             .hotplug_interfaces
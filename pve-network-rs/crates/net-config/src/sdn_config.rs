@@ -72,6 +72,7 @@ pub enum ControllerType {
     Evpn,
     Bgp,
     Faucet,
+    Isis,
 }
 
 /// Controller configuration
@@ -79,6 +79,13 @@ pub enum SystemError {
         path: String,
         source: Box<dyn std::error::Error + Send + Sync>,
     },
+
+    #[error("{stage} hook {hook} failed: {reason}")]
+    HookFailed {
+        stage: String,
+        hook: String,
+        reason: String,
+    },
 }
 
 /// API related errors
@@ -6,6 +6,7 @@ pub mod bond;
 pub mod bridge;
 pub mod error;
 pub mod interface;
+pub mod topology;
 pub mod types;
 pub mod vlan;
 
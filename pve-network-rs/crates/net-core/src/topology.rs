@@ -0,0 +1,545 @@
+//! Network topology graph
+//!
+//! Builds a directed graph of how traffic flows through the node: physical
+//! NICs feed bonds, bonds and NICs are bridge ports or VLAN parents, bridges
+//! are the uplinks of SDN zones, zones carry vnets and vnets are attached to
+//! containers. Storage VLANs hang off their VLAN interface. Edges point from
+//! the lower to the upper layer.
+//!
+//! The graph serializes to JSON as `nodes` and `edges` and renders to
+//! Graphviz DOT with [`NetworkTopology::to_dot`].
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bond::BondManager;
+use crate::types::{InterfaceType, NetworkConfiguration};
+use pve_shared_types::{SdnConfiguration, StorageVlanConfig, VNetBinding};
+
+/// Kind of a topology node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TopologyNodeKind {
+    Physical,
+    Bond,
+    Bridge,
+    Vlan,
+    Vxlan,
+    Loopback,
+    Zone,
+    Vnet,
+    Subnet,
+    Container,
+    Storage,
+}
+
+impl TopologyNodeKind {
+    fn dot_shape(self) -> &'static str {
+        match self {
+            TopologyNodeKind::Physical | TopologyNodeKind::Loopback => "box",
+            TopologyNodeKind::Bond => "box3d",
+            TopologyNodeKind::Bridge => "hexagon",
+            TopologyNodeKind::Vlan | TopologyNodeKind::Vxlan => "parallelogram",
+            TopologyNodeKind::Zone => "folder",
+            TopologyNodeKind::Vnet => "ellipse",
+            TopologyNodeKind::Subnet => "note",
+            TopologyNodeKind::Container => "component",
+            TopologyNodeKind::Storage => "cylinder",
+        }
+    }
+}
+
+/// Relation between two topology nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TopologyEdgeKind {
+    /// Interface enslaved to a bond
+    Slave,
+    /// Interface attached to a bridge
+    Port,
+    /// Parent of a VLAN interface
+    Parent,
+    /// Bridge a zone is built on
+    Uplink,
+    /// VNet of a zone
+    Vnet,
+    /// Subnet of a vnet
+    Subnet,
+    /// Container interface attached to a vnet
+    Attachment,
+    /// VLAN interface carrying storage traffic
+    Storage,
+}
+
+impl std::fmt::Display for TopologyEdgeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TopologyEdgeKind::Slave => "slave",
+            TopologyEdgeKind::Port => "port",
+            TopologyEdgeKind::Parent => "parent",
+            TopologyEdgeKind::Uplink => "uplink",
+            TopologyEdgeKind::Vnet => "vnet",
+            TopologyEdgeKind::Subnet => "subnet",
+            TopologyEdgeKind::Attachment => "attachment",
+            TopologyEdgeKind::Storage => "storage",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Node of the topology graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyNode {
+    /// Unique id, the name prefixed with its namespace (e.g. `iface:eth0`)
+    pub id: String,
+    pub kind: TopologyNodeKind,
+    /// Display name
+    pub label: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// Edge of the topology graph, from the lower to the upper layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: TopologyEdgeKind,
+    /// Additional detail, e.g. the container interface of an attachment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Network topology of a node
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkTopology {
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<TopologyEdge>,
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+}
+
+fn interface_id(name: &str) -> String {
+    format!("iface:{}", name)
+}
+
+impl NetworkTopology {
+    /// Create empty topology
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the interfaces of `config`
+    ///
+    /// Ports, slaves and parents that are not declared in the configuration
+    /// are added as physical interfaces.
+    pub fn with_network(mut self, config: &NetworkConfiguration) -> Self {
+        let mut names: Vec<&String> = config.interfaces.keys().collect();
+        names.sort();
+
+        for name in &names {
+            let iface = &config.interfaces[*name];
+            let kind = match &iface.iface_type {
+                InterfaceType::Physical => TopologyNodeKind::Physical,
+                InterfaceType::Bond { .. } => TopologyNodeKind::Bond,
+                InterfaceType::Bridge { .. } => TopologyNodeKind::Bridge,
+                InterfaceType::Vlan { .. } => TopologyNodeKind::Vlan,
+                InterfaceType::Vxlan { .. } => TopologyNodeKind::Vxlan,
+                InterfaceType::Loopback => TopologyNodeKind::Loopback,
+            };
+
+            let mut attributes = BTreeMap::new();
+            if !iface.addresses.is_empty() {
+                let addresses: Vec<String> =
+                    iface.addresses.iter().map(|a| a.to_string()).collect();
+                attributes.insert("address".to_string(), addresses.join(","));
+            }
+            if let Some(mtu) = iface.mtu {
+                attributes.insert("mtu".to_string(), mtu.to_string());
+            }
+            match &iface.iface_type {
+                InterfaceType::Bridge {
+                    vlan_aware: true, ..
+                } => {
+                    attributes.insert("vlan-aware".to_string(), "1".to_string());
+                }
+                InterfaceType::Bond { mode, .. } => {
+                    attributes.insert(
+                        "mode".to_string(),
+                        BondManager::mode_to_string(mode).to_string(),
+                    );
+                }
+                InterfaceType::Vlan { tag, .. } => {
+                    attributes.insert("tag".to_string(), tag.to_string());
+                }
+                InterfaceType::Vxlan { id, .. } => {
+                    attributes.insert("vni".to_string(), id.to_string());
+                }
+                _ => {}
+            }
+
+            let node = self.node(&interface_id(name), kind, name);
+            node.kind = kind;
+            node.attributes.extend(attributes);
+        }
+
+        for name in names {
+            let iface = &config.interfaces[name];
+            match &iface.iface_type {
+                InterfaceType::Bond { slaves, .. } => {
+                    for slave in slaves {
+                        self.interface_edge(slave, name, TopologyEdgeKind::Slave);
+                    }
+                }
+                InterfaceType::Bridge { ports, .. } => {
+                    for port in ports {
+                        self.interface_edge(port, name, TopologyEdgeKind::Port);
+                    }
+                }
+                InterfaceType::Vlan { parent, .. } => {
+                    self.interface_edge(parent, name, TopologyEdgeKind::Parent);
+                }
+                _ => {}
+            }
+        }
+
+        self
+    }
+
+    /// Add the zones, vnets and subnets of `config`
+    pub fn with_sdn(mut self, config: &SdnConfiguration) -> Self {
+        let mut zones: Vec<&String> = config.zones.keys().collect();
+        zones.sort();
+
+        for name in zones {
+            let zone = &config.zones[name];
+            let id = format!("zone:{}", name);
+            self.node(&id, TopologyNodeKind::Zone, name)
+                .attributes
+                .insert("type".to_string(), zone.zone_type.to_string());
+
+            if let Some(bridge) = &zone.bridge {
+                self.interface(bridge, TopologyNodeKind::Bridge);
+                self.edge(&interface_id(bridge), &id, TopologyEdgeKind::Uplink, None);
+            }
+        }
+
+        let mut vnets: Vec<&String> = config.vnets.keys().collect();
+        vnets.sort();
+
+        for name in vnets {
+            let vnet = &config.vnets[name];
+            let id = format!("vnet:{}", name);
+            let node = self.node(&id, TopologyNodeKind::Vnet, name);
+            if let Some(tag) = vnet.tag {
+                node.attributes.insert("tag".to_string(), tag.to_string());
+            }
+            if let Some(alias) = &vnet.alias {
+                node.attributes.insert("alias".to_string(), alias.clone());
+            }
+
+            let zone = format!("zone:{}", vnet.zone);
+            self.node(&zone, TopologyNodeKind::Zone, &vnet.zone);
+            self.edge(&zone, &id, TopologyEdgeKind::Vnet, None);
+        }
+
+        let mut subnets: Vec<&String> = config.subnets.keys().collect();
+        subnets.sort();
+
+        for name in subnets {
+            let subnet = &config.subnets[name];
+            let id = format!("subnet:{}", name);
+            let node = self.node(&id, TopologyNodeKind::Subnet, &subnet.cidr.to_string());
+            if let Some(gateway) = subnet.gateway {
+                node.attributes
+                    .insert("gateway".to_string(), gateway.to_string());
+            }
+
+            let vnet = format!("vnet:{}", subnet.vnet);
+            self.node(&vnet, TopologyNodeKind::Vnet, &subnet.vnet);
+            self.edge(&vnet, &id, TopologyEdgeKind::Subnet, None);
+        }
+
+        self
+    }
+
+    /// Add the containers attached to vnets
+    pub fn with_vnet_bindings(mut self, bindings: &[VNetBinding]) -> Self {
+        let mut bindings: Vec<&VNetBinding> = bindings.iter().collect();
+        bindings.sort_by(|a, b| {
+            (a.container_id, &a.interface_name).cmp(&(b.container_id, &b.interface_name))
+        });
+
+        for binding in bindings {
+            let id = format!("ct:{}", binding.container_id);
+            self.node(
+                &id,
+                TopologyNodeKind::Container,
+                &format!("CT {}", binding.container_id),
+            );
+
+            let vnet = format!("vnet:{}", binding.vnet);
+            self.node(&vnet, TopologyNodeKind::Vnet, &binding.vnet);
+            self.edge(
+                &vnet,
+                &id,
+                TopologyEdgeKind::Attachment,
+                Some(binding.interface_name.clone()),
+            );
+        }
+
+        self
+    }
+
+    /// Add storage networks on their VLAN interfaces
+    pub fn with_storage_vlans(mut self, vlans: &[(String, StorageVlanConfig)]) -> Self {
+        let mut vlans: Vec<&(String, StorageVlanConfig)> = vlans.iter().collect();
+        vlans.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (storage, vlan) in vlans {
+            let interface = format!("{}.{}", vlan.base_interface, vlan.vlan_tag);
+            let known = self.index.contains_key(&interface_id(&interface));
+            self.interface(&interface, TopologyNodeKind::Vlan)
+                .attributes
+                .insert("tag".to_string(), vlan.vlan_tag.to_string());
+            if !known {
+                self.interface_edge(&vlan.base_interface, &interface, TopologyEdgeKind::Parent);
+            }
+
+            let id = format!("storage:{}", storage);
+            let node = self.node(&id, TopologyNodeKind::Storage, storage);
+            if let Some(subnet) = &vlan.subnet {
+                node.attributes.insert("subnet".to_string(), subnet.clone());
+            }
+            self.edge(
+                &interface_id(&interface),
+                &id,
+                TopologyEdgeKind::Storage,
+                None,
+            );
+        }
+
+        self
+    }
+
+    /// Node with `id`
+    pub fn get(&self, id: &str) -> Option<&TopologyNode> {
+        self.index.get(id).map(|&pos| &self.nodes[pos])
+    }
+
+    /// Render as Graphviz DOT
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph topology {\n\trankdir=LR;\n");
+
+        for node in &self.nodes {
+            let mut label = node.label.clone();
+            for (key, value) in &node.attributes {
+                label.push_str(&format!("\\n{}={}", key, value));
+            }
+            dot.push_str(&format!(
+                "\t{} [label={}, shape={}];\n",
+                dot_quote(&node.id),
+                dot_quote(&label),
+                node.kind.dot_shape()
+            ));
+        }
+
+        for edge in &self.edges {
+            let label = match &edge.label {
+                Some(label) => format!("{} {}", edge.kind, label),
+                None => edge.kind.to_string(),
+            };
+            dot.push_str(&format!(
+                "\t{} -> {} [label={}];\n",
+                dot_quote(&edge.from),
+                dot_quote(&edge.to),
+                dot_quote(&label)
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Node with `id`, added as `kind` if it does not exist yet
+    fn node(&mut self, id: &str, kind: TopologyNodeKind, label: &str) -> &mut TopologyNode {
+        let pos = match self.index.get(id) {
+            Some(&pos) => pos,
+            None => {
+                self.nodes.push(TopologyNode {
+                    id: id.to_string(),
+                    kind,
+                    label: label.to_string(),
+                    attributes: BTreeMap::new(),
+                });
+                self.index.insert(id.to_string(), self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        &mut self.nodes[pos]
+    }
+
+    fn interface(&mut self, name: &str, kind: TopologyNodeKind) -> &mut TopologyNode {
+        self.node(&interface_id(name), kind, name)
+    }
+
+    fn interface_edge(&mut self, from: &str, to: &str, kind: TopologyEdgeKind) {
+        self.interface(from, TopologyNodeKind::Physical);
+        self.edge(&interface_id(from), &interface_id(to), kind, None);
+    }
+
+    fn edge(&mut self, from: &str, to: &str, kind: TopologyEdgeKind, label: Option<String>) {
+        let edge = TopologyEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+            label,
+        };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+}
+
+fn dot_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AddressMethod, BondMode, Interface};
+    use pve_shared_types::{SubnetConfig, VNetConfig, ZoneConfig, ZoneType};
+
+    fn interface(name: &str, iface_type: InterfaceType) -> Interface {
+        Interface {
+            name: name.to_string(),
+            iface_type,
+            method: AddressMethod::Manual,
+            addresses: Vec::new(),
+            gateway: None,
+            mtu: None,
+            options: Default::default(),
+            enabled: true,
+            comments: Vec::new(),
+        }
+    }
+
+    fn network() -> NetworkConfiguration {
+        let mut config = NetworkConfiguration::default();
+        for iface in [
+            interface("eno1", InterfaceType::Physical),
+            interface(
+                "bond0",
+                InterfaceType::Bond {
+                    slaves: vec!["eno1".to_string(), "eno2".to_string()],
+                    mode: BondMode::ActiveBackup,
+                    options: Default::default(),
+                },
+            ),
+            interface(
+                "vmbr0",
+                InterfaceType::Bridge {
+                    ports: vec!["bond0".to_string()],
+                    vlan_aware: true,
+                },
+            ),
+        ] {
+            config.interfaces.insert(iface.name.clone(), iface);
+        }
+        config
+    }
+
+    #[test]
+    fn test_topology_layers() {
+        let mut sdn = SdnConfiguration::new();
+        let mut zone = ZoneConfig::new(ZoneType::Vlan, "zone1".to_string());
+        zone.bridge = Some("vmbr0".to_string());
+        sdn.zones.insert("zone1".to_string(), zone);
+        let mut vnet = VNetConfig::new("vnet1".to_string(), "zone1".to_string());
+        vnet.tag = Some(100);
+        sdn.vnets.insert("vnet1".to_string(), vnet);
+        sdn.subnets.insert(
+            "zone1-10.0.0.0-24".to_string(),
+            SubnetConfig::new(
+                "zone1-10.0.0.0-24".to_string(),
+                "vnet1".to_string(),
+                "10.0.0.0/24".parse().unwrap(),
+            ),
+        );
+
+        let bindings = vec![VNetBinding::new(
+            "vnet1".to_string(),
+            100,
+            "net0".to_string(),
+        )];
+        let storage = vec![(
+            "ceph".to_string(),
+            StorageVlanConfig {
+                base_interface: "bond0".to_string(),
+                vlan_tag: 50,
+                subnet: Some("10.50.0.0/24".to_string()),
+                gateway: None,
+                mtu: None,
+                options: Default::default(),
+            },
+        )];
+
+        let topology = NetworkTopology::new()
+            .with_network(&network())
+            .with_sdn(&sdn)
+            .with_vnet_bindings(&bindings)
+            .with_storage_vlans(&storage);
+
+        // Undeclared bond slaves are physical NICs
+        assert_eq!(
+            topology.get("iface:eno2").unwrap().kind,
+            TopologyNodeKind::Physical
+        );
+        assert_eq!(
+            topology.get("iface:bond0.50").unwrap().attributes["tag"],
+            "50"
+        );
+
+        let edges: Vec<(&str, &str, TopologyEdgeKind)> = topology
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("iface:eno1", "iface:bond0", TopologyEdgeKind::Slave),
+                ("iface:eno2", "iface:bond0", TopologyEdgeKind::Slave),
+                ("iface:bond0", "iface:vmbr0", TopologyEdgeKind::Port),
+                ("iface:vmbr0", "zone:zone1", TopologyEdgeKind::Uplink),
+                ("zone:zone1", "vnet:vnet1", TopologyEdgeKind::Vnet),
+                (
+                    "vnet:vnet1",
+                    "subnet:zone1-10.0.0.0-24",
+                    TopologyEdgeKind::Subnet
+                ),
+                ("vnet:vnet1", "ct:100", TopologyEdgeKind::Attachment),
+                ("iface:bond0", "iface:bond0.50", TopologyEdgeKind::Parent),
+                ("iface:bond0.50", "storage:ceph", TopologyEdgeKind::Storage),
+            ]
+        );
+
+        let json = serde_json::to_value(&topology).unwrap();
+        assert_eq!(json["edges"][6]["label"], "net0");
+        assert_eq!(json["nodes"][0]["kind"], "bond");
+    }
+
+    #[test]
+    fn test_topology_dot() {
+        let dot = NetworkTopology::new().with_network(&network()).to_dot();
+
+        assert!(dot.starts_with("digraph topology {\n\trankdir=LR;\n"));
+        assert!(
+            dot.contains("\t\"iface:vmbr0\" [label=\"vmbr0\\nvlan-aware=1\", shape=hexagon];\n")
+        );
+        assert!(dot.contains("\t\"iface:eno1\" -> \"iface:bond0\" [label=\"slave\"];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,438 @@
+//! BGP peer, routing policy and runtime status types used by SDN controllers
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+/// Typed configuration of a single BGP neighbor
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BgpPeerConfig {
+    pub address: IpAddr,
+    /// Remote ASN, `internal` or `external`, defaults to `external`
+    #[serde(rename = "remote-as")]
+    pub remote_as: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "ebgp-multihop")]
+    pub ebgp_multihop: Option<u8>,
+    /// Source address or interface of the session, e.g. a fabric loopback
+    #[serde(rename = "update-source")]
+    pub update_source: Option<String>,
+    /// MD5 session password
+    pub password: Option<String>,
+    pub bfd: Option<bool>,
+    /// BFD profile, implies `bfd`
+    #[serde(rename = "bfd-profile")]
+    pub bfd_profile: Option<String>,
+    #[serde(rename = "prefix-list-in")]
+    pub prefix_list_in: Option<String>,
+    #[serde(rename = "prefix-list-out")]
+    pub prefix_list_out: Option<String>,
+    #[serde(rename = "route-map-in")]
+    pub route_map_in: Option<String>,
+    #[serde(rename = "route-map-out")]
+    pub route_map_out: Option<String>,
+}
+
+impl BgpPeerConfig {
+    pub fn new(address: IpAddr) -> Self {
+        Self {
+            address,
+            remote_as: None,
+            description: None,
+            ebgp_multihop: None,
+            update_source: None,
+            password: None,
+            bfd: None,
+            bfd_profile: None,
+            prefix_list_in: None,
+            prefix_list_out: None,
+            route_map_in: None,
+            route_map_out: None,
+        }
+    }
+
+    /// Remote AS as rendered in `neighbor ... remote-as`
+    pub fn remote_as(&self) -> &str {
+        self.remote_as.as_deref().unwrap_or("external")
+    }
+
+    /// Whether BFD is enabled for the session
+    pub fn bfd_enabled(&self) -> bool {
+        self.bfd.unwrap_or(false) || self.bfd_profile.is_some()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let remote_as = self.remote_as();
+        if remote_as != "internal"
+            && remote_as != "external"
+            && !matches!(remote_as.parse::<u32>(), Ok(asn) if asn > 0)
+        {
+            bail!(
+                "Invalid remote-as '{}' for peer {}, expected an ASN, internal or external",
+                remote_as,
+                self.address
+            );
+        }
+
+        if let Some(0) = self.ebgp_multihop {
+            bail!("ebgp-multihop of peer {} cannot be 0", self.address);
+        }
+
+        if let Some(source) = &self.update_source {
+            if source.parse::<IpAddr>().is_err() && !is_valid_ifname(source) {
+                bail!(
+                    "Invalid update-source '{}' for peer {}",
+                    source,
+                    self.address
+                );
+            }
+        }
+
+        // Rendered verbatim into frr.conf, a line break would inject commands
+        if let Some(description) = &self.description {
+            if description.trim().is_empty()
+                || description.len() > 80
+                || !description
+                    .chars()
+                    .all(|c| c == ' ' || c.is_ascii_graphic())
+            {
+                bail!(
+                    "Description of peer {} must be 1-80 printable ASCII characters",
+                    self.address
+                );
+            }
+        }
+
+        if let Some(password) = &self.password {
+            if password.is_empty() || password.len() > 80 || password.contains(char::is_whitespace)
+            {
+                bail!(
+                    "Password of peer {} must be 1-80 characters without whitespace",
+                    self.address
+                );
+            }
+        }
+
+        let names = [
+            &self.bfd_profile,
+            &self.prefix_list_in,
+            &self.prefix_list_out,
+            &self.route_map_in,
+            &self.route_map_out,
+        ];
+        for name in names.into_iter().flatten() {
+            if !is_valid_policy_name(name) {
+                bail!("Invalid policy name '{}' for peer {}", name, self.address);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Named BFD timer profile
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BfdProfile {
+    pub name: String,
+    #[serde(rename = "detect-multiplier")]
+    pub detect_multiplier: Option<u8>,
+    /// Minimum receive interval in milliseconds
+    #[serde(rename = "receive-interval")]
+    pub receive_interval: Option<u32>,
+    /// Minimum transmit interval in milliseconds
+    #[serde(rename = "transmit-interval")]
+    pub transmit_interval: Option<u32>,
+}
+
+impl BfdProfile {
+    pub fn validate(&self) -> Result<()> {
+        if !is_valid_policy_name(&self.name) {
+            bail!("Invalid BFD profile name '{}'", self.name);
+        }
+
+        if let Some(multiplier) = self.detect_multiplier {
+            if !(2..=255).contains(&multiplier) {
+                bail!(
+                    "BFD profile '{}' detect-multiplier must be between 2 and 255",
+                    self.name
+                );
+            }
+        }
+
+        for interval in [self.receive_interval, self.transmit_interval]
+            .into_iter()
+            .flatten()
+        {
+            if !(10..=60000).contains(&interval) {
+                bail!(
+                    "BFD profile '{}' intervals must be between 10 and 60000 ms",
+                    self.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Action of a prefix-list or route-map entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Permit,
+    Deny,
+}
+
+impl std::fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyAction::Permit => write!(f, "permit"),
+            PolicyAction::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrefixListEntry {
+    pub seq: u32,
+    pub action: PolicyAction,
+    pub prefix: IpNet,
+    pub ge: Option<u8>,
+    pub le: Option<u8>,
+}
+
+/// Named prefix-list, all entries share one address family
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrefixList {
+    pub name: String,
+    #[serde(default)]
+    pub entries: Vec<PrefixListEntry>,
+}
+
+impl PrefixList {
+    /// Whether the list matches IPv6 prefixes
+    pub fn is_ipv6(&self) -> bool {
+        self.entries
+            .first()
+            .map(|entry| entry.prefix.addr().is_ipv6())
+            .unwrap_or(false)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !is_valid_policy_name(&self.name) {
+            bail!("Invalid prefix-list name '{}'", self.name);
+        }
+
+        if self.entries.is_empty() {
+            bail!("Prefix-list '{}' has no entries", self.name);
+        }
+
+        let ipv6 = self.is_ipv6();
+        let mut seqs = HashSet::new();
+
+        for entry in &self.entries {
+            if !seqs.insert(entry.seq) {
+                bail!(
+                    "Prefix-list '{}' uses sequence {} twice",
+                    self.name,
+                    entry.seq
+                );
+            }
+
+            if entry.prefix.addr().is_ipv6() != ipv6 {
+                bail!("Prefix-list '{}' mixes address families", self.name);
+            }
+
+            let len = entry.prefix.prefix_len();
+            let max = entry.prefix.max_prefix_len();
+            let ge = entry.ge.unwrap_or(len);
+            let le = entry.le.unwrap_or(max);
+            if ge < len || le > max || ge > le {
+                bail!(
+                    "Prefix-list '{}' entry {} requires {} <= ge <= le <= {}",
+                    self.name,
+                    entry.seq,
+                    len,
+                    max
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RouteMapEntry {
+    pub seq: u32,
+    pub action: PolicyAction,
+    #[serde(rename = "match-prefix-list")]
+    pub match_prefix_list: Option<String>,
+    #[serde(rename = "set-local-preference")]
+    pub set_local_preference: Option<u32>,
+    #[serde(rename = "set-metric")]
+    pub set_metric: Option<u32>,
+    #[serde(rename = "set-community")]
+    pub set_community: Option<String>,
+}
+
+/// Suffix of the IPv6 variant of route-maps matching prefix-lists
+pub const IPV6_ROUTE_MAP_SUFFIX: &str = "_v6";
+
+/// Named route-map
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RouteMap {
+    pub name: String,
+    #[serde(default)]
+    pub entries: Vec<RouteMapEntry>,
+}
+
+impl RouteMap {
+    /// Whether entries match prefix-lists, which only apply to a single
+    /// address family
+    pub fn is_family_specific(&self) -> bool {
+        self.entries.iter().any(|e| e.match_prefix_list.is_some())
+    }
+
+    /// Name of the route-map in the IPv6 address family
+    ///
+    /// Family specific route-maps are rendered once per family, the IPv6
+    /// variant carries [`IPV6_ROUTE_MAP_SUFFIX`].
+    pub fn ipv6_name(&self) -> String {
+        if self.is_family_specific() {
+            format!("{}{}", self.name, IPV6_ROUTE_MAP_SUFFIX)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !is_valid_policy_name(&self.name) {
+            bail!("Invalid route-map name '{}'", self.name);
+        }
+
+        if self.entries.is_empty() {
+            bail!("Route-map '{}' has no entries", self.name);
+        }
+
+        let mut seqs = HashSet::new();
+        for entry in &self.entries {
+            if !seqs.insert(entry.seq) {
+                bail!(
+                    "Route-map '{}' uses sequence {} twice",
+                    self.name,
+                    entry.seq
+                );
+            }
+
+            if let Some(community) = &entry.set_community {
+                let valid = community.split_whitespace().all(|c| {
+                    matches!(c, "no-export" | "no-advertise" | "local-AS" | "additive")
+                        || matches!(
+                            c.split_once(':'),
+                            Some((asn, value)) if asn.parse::<u16>().is_ok() && value.parse::<u16>().is_ok()
+                        )
+                });
+                if community.trim().is_empty() || !valid {
+                    bail!(
+                        "Route-map '{}' entry {} has an invalid community '{}'",
+                        self.name,
+                        entry.seq,
+                        community
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// BGP session as reported by FRR
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BgpPeerStatus {
+    /// Neighbor address, or interface name for unnumbered sessions
+    pub peer: String,
+    /// Address family of the session, e.g. `ipv4Unicast` or `l2VpnEvpn`
+    pub afi: String,
+    pub vrf: Option<String>,
+    #[serde(rename = "remote-as")]
+    pub remote_as: Option<u32>,
+    /// Session state, e.g. `Established` or `Active`
+    pub state: String,
+    /// Time since the session was established, in seconds
+    pub uptime: Option<u64>,
+    #[serde(rename = "prefixes-received")]
+    pub prefixes_received: Option<u64>,
+    #[serde(rename = "prefixes-sent")]
+    pub prefixes_sent: Option<u64>,
+    pub description: Option<String>,
+}
+
+impl BgpPeerStatus {
+    /// Whether the session is established
+    pub fn is_established(&self) -> bool {
+        self.state == "Established"
+    }
+}
+
+/// EVPN VNI known to zebra
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EvpnVniStatus {
+    pub vni: u32,
+    /// `L2` or `L3`
+    #[serde(rename = "type")]
+    pub vni_type: String,
+    #[serde(rename = "vxlan-interface")]
+    pub vxlan_interface: Option<String>,
+    pub vrf: Option<String>,
+    pub macs: u64,
+    #[serde(rename = "arp-nd")]
+    pub arp_nd: u64,
+    #[serde(rename = "remote-vteps")]
+    pub remote_vteps: Vec<IpAddr>,
+}
+
+/// Entry of the EVPN MAC table of a VNI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EvpnMacEntry {
+    pub vni: u32,
+    pub mac: String,
+    /// `local` or `remote`
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    /// Local port the MAC was learned on
+    pub interface: Option<String>,
+    pub vlan: Option<u16>,
+    /// VTEP behind which a remote MAC lives
+    #[serde(rename = "remote-vtep")]
+    pub remote_vtep: Option<IpAddr>,
+    /// IP addresses bound to the MAC
+    #[serde(default)]
+    pub ips: Vec<IpAddr>,
+}
+
+impl EvpnMacEntry {
+    pub fn is_local(&self) -> bool {
+        self.entry_type == "local"
+    }
+}
+
+fn is_valid_ifname(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 15
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn is_valid_policy_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
@@ -176,6 +176,9 @@ pub struct VNetBinding {
     pub container_id: ContainerId,
     pub interface_name: String,
     pub bound_at: DateTime<Utc>,
+    /// Bridge port isolation of the vnet applies to the interface
+    #[serde(default)]
+    pub isolated: bool,
     #[serde(default)]
     pub metadata: HashMap<String, String>,
 }
@@ -187,6 +190,7 @@ impl VNetBinding {
             container_id,
             interface_name,
             bound_at: Utc::now(),
+            isolated: false,
             metadata: HashMap::new(),
         }
     }
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Default TTL of records registered by SDN DNS plugins
+pub const DEFAULT_DNS_TTL: u32 = 3600;
+
+/// Default IPv6 reverse zone prefix length
+pub const DEFAULT_REVERSE_MASK_V6: u8 = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum DnsType {
+    #[serde(rename = "powerdns")]
+    PowerDns,
+}
+
+impl std::fmt::Display for DnsType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DnsType::PowerDns => write!(f, "powerdns"),
+        }
+    }
+}
+
+/// DNS plugin configuration, one entry of `dns.cfg`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsConfig {
+    #[serde(rename = "type")]
+    pub dns_type: DnsType,
+    pub name: String,
+    pub url: Option<String>,
+    pub key: Option<String>,
+    pub ttl: Option<u32>,
+    /// Prefix length of the IPv6 reverse zones
+    pub reversemaskv6: Option<u8>,
+    #[serde(flatten)]
+    pub options: HashMap<String, serde_json::Value>,
+}
+
+impl DnsConfig {
+    pub fn new(name: String, dns_type: DnsType) -> Self {
+        Self {
+            name,
+            dns_type,
+            url: None,
+            key: None,
+            ttl: None,
+            reversemaskv6: None,
+            options: HashMap::new(),
+        }
+    }
+
+    /// Copy without the API key, for API responses
+    pub fn redacted(&self) -> Self {
+        Self {
+            key: None,
+            ..self.clone()
+        }
+    }
+
+    /// Take the API key left out of `self` from `current`
+    pub fn keep_key(&mut self, current: &DnsConfig) {
+        if self.key.is_none() {
+            self.key = current.key.clone();
+        }
+    }
+
+    pub fn ttl(&self) -> u32 {
+        self.ttl.unwrap_or(DEFAULT_DNS_TTL)
+    }
+
+    pub fn reversemaskv6(&self) -> u8 {
+        self.reversemaskv6.unwrap_or(DEFAULT_REVERSE_MASK_V6)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            bail!("DNS name cannot be empty");
+        }
+
+        match self.dns_type {
+            DnsType::PowerDns => {
+                if self.url.is_none() {
+                    bail!("PowerDNS requires URL configuration");
+                }
+                if self.key.is_none() {
+                    bail!("PowerDNS requires API key configuration");
+                }
+            }
+        }
+
+        if let Some(mask) = self.reversemaskv6 {
+            if mask == 0 || mask > 124 || mask % 4 != 0 {
+                bail!("reversemaskv6 must be a multiple of 4 between 4 and 124");
+            }
+        }
+
+        Ok(())
+    }
+}
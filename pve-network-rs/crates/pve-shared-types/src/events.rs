@@ -9,6 +9,14 @@ use crate::migration::MigrationPhase;
 pub enum SystemEvent {
     /// Network configuration was applied through the transactional applier
     NetworkApplied { changes: Vec<ConfigChange> },
+    /// User-defined hook script was executed during a network apply
+    NetworkHookExecuted {
+        transaction_id: String,
+        stage: String,
+        hook: String,
+        success: bool,
+        exit_code: Option<i32>,
+    },
     /// Container finished its start sequence
     ContainerStarted { id: ContainerId },
     /// Storage VLAN was (re)created for a given storage backend identifier
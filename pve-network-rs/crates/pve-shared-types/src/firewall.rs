@@ -0,0 +1,336 @@
+//! VNet firewall rules
+//!
+//! A vnet firewall filters traffic routed into (`in`) and out of (`out`) the
+//! vnet bridge. Rule addresses are IPs, CIDRs, aliases or `+<ipset>`
+//! references to the ipsets and aliases defined next to the rules.
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+/// Protocols accepted in the `proto` field of a rule, besides numbers
+const KNOWN_PROTOCOLS: &[&str] = &[
+    "tcp",
+    "udp",
+    "icmp",
+    "icmpv6",
+    "ipv6-icmp",
+    "sctp",
+    "udplite",
+    "gre",
+    "esp",
+    "ah",
+    "ospf",
+    "vrrp",
+    "igmp",
+    "pim",
+];
+
+/// Protocols with source and destination ports
+const PORT_PROTOCOLS: &[&str] = &["tcp", "udp", "sctp", "udplite"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallDirection {
+    /// Traffic routed into the vnet
+    In,
+    /// Traffic routed out of the vnet
+    Out,
+}
+
+impl std::fmt::Display for FirewallDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FirewallDirection::In => write!(f, "in"),
+            FirewallDirection::Out => write!(f, "out"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum FirewallAction {
+    Accept,
+    Drop,
+    Reject,
+}
+
+impl std::fmt::Display for FirewallAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FirewallAction::Accept => write!(f, "ACCEPT"),
+            FirewallAction::Drop => write!(f, "DROP"),
+            FirewallAction::Reject => write!(f, "REJECT"),
+        }
+    }
+}
+
+/// Single firewall rule, evaluated in order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FirewallRule {
+    #[serde(rename = "type")]
+    pub direction: FirewallDirection,
+    pub action: FirewallAction,
+    /// Rules are enabled unless set to false
+    pub enable: Option<bool>,
+    /// Protocol name or number
+    pub proto: Option<String>,
+    /// Source ports, e.g. `80,443` or `8000:8080`
+    pub sport: Option<String>,
+    /// Destination ports, e.g. `80,443` or `8000:8080`
+    pub dport: Option<String>,
+    /// Source addresses, aliases or `+<ipset>`
+    pub source: Option<String>,
+    /// Destination addresses, aliases or `+<ipset>`
+    pub dest: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl FirewallRule {
+    pub fn new(direction: FirewallDirection, action: FirewallAction) -> Self {
+        Self {
+            direction,
+            action,
+            enable: None,
+            proto: None,
+            sport: None,
+            dport: None,
+            source: None,
+            dest: None,
+            comment: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enable.unwrap_or(true)
+    }
+
+    /// Protocol in its canonical lowercase form
+    pub fn protocol(&self) -> Option<String> {
+        self.proto.as_ref().map(|proto| match proto.to_lowercase() {
+            proto if proto == "icmpv6" => "ipv6-icmp".to_string(),
+            proto => proto,
+        })
+    }
+}
+
+/// Named set of networks, referenced as `+<name>`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FirewallIpSet {
+    pub name: String,
+    #[serde(default)]
+    pub cidrs: Vec<IpNet>,
+    pub comment: Option<String>,
+}
+
+/// Named network, referenced by its name
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FirewallAlias {
+    pub name: String,
+    pub cidr: IpNet,
+    pub comment: Option<String>,
+}
+
+/// Firewall of a vnet
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VNetFirewall {
+    /// The firewall is disabled unless set to true
+    pub enable: Option<bool>,
+    /// Verdict for traffic into the vnet no rule matched, defaults to ACCEPT
+    pub policy_in: Option<FirewallAction>,
+    /// Verdict for traffic out of the vnet no rule matched, defaults to ACCEPT
+    pub policy_out: Option<FirewallAction>,
+    #[serde(default)]
+    pub rules: Vec<FirewallRule>,
+    #[serde(default)]
+    pub ipsets: Vec<FirewallIpSet>,
+    #[serde(default)]
+    pub aliases: Vec<FirewallAlias>,
+}
+
+impl VNetFirewall {
+    pub fn enabled(&self) -> bool {
+        self.enable.unwrap_or(false)
+    }
+
+    pub fn policy(&self, direction: FirewallDirection) -> FirewallAction {
+        match direction {
+            FirewallDirection::In => self.policy_in,
+            FirewallDirection::Out => self.policy_out,
+        }
+        .unwrap_or(FirewallAction::Accept)
+    }
+
+    pub fn ipset(&self, name: &str) -> Option<&FirewallIpSet> {
+        self.ipsets.iter().find(|ipset| ipset.name == name)
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&FirewallAlias> {
+        self.aliases.iter().find(|alias| alias.name == name)
+    }
+
+    /// Networks matched by the `source` or `dest` field of a rule
+    pub fn resolve_addresses(&self, spec: &str) -> Result<Vec<IpNet>> {
+        let spec = spec.trim();
+
+        if let Some(name) = spec.strip_prefix('+') {
+            return match self.ipset(name) {
+                Some(ipset) if ipset.cidrs.is_empty() => bail!("IP set '{}' is empty", name),
+                Some(ipset) => Ok(ipset.cidrs.clone()),
+                None => bail!("IP set '{}' does not exist", name),
+            };
+        }
+
+        let mut networks = Vec::new();
+        for entry in spec.split(',').map(str::trim) {
+            if entry.is_empty() {
+                bail!("Empty entry in address list '{}'", spec);
+            }
+
+            if let Ok(cidr) = entry.parse::<IpNet>() {
+                networks.push(cidr);
+            } else if let Ok(ip) = entry.parse::<IpAddr>() {
+                networks.push(IpNet::from(ip));
+            } else if let Some(alias) = self.alias(entry) {
+                networks.push(alias.cidr);
+            } else {
+                bail!("'{}' is neither an address nor a known alias", entry);
+            }
+        }
+
+        Ok(networks)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for ipset in &self.ipsets {
+            validate_name(&ipset.name, "IP set")?;
+            if !names.insert(ipset.name.as_str()) {
+                bail!("Duplicate IP set '{}'", ipset.name);
+            }
+        }
+
+        let mut names = HashSet::new();
+        for alias in &self.aliases {
+            validate_name(&alias.name, "Alias")?;
+            if !names.insert(alias.name.as_str()) {
+                bail!("Duplicate alias '{}'", alias.name);
+            }
+        }
+
+        for (pos, rule) in self.rules.iter().enumerate() {
+            self.validate_rule(rule)
+                .map_err(|e| anyhow::anyhow!("Firewall rule {}: {}", pos, e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Validate `rule` against the ipsets and aliases of this firewall
+    pub fn validate_rule(&self, rule: &FirewallRule) -> Result<()> {
+        let proto = rule.protocol();
+
+        if let Some(proto) = &proto {
+            if !KNOWN_PROTOCOLS.contains(&proto.as_str()) && proto.parse::<u8>().is_err() {
+                bail!("Unknown protocol '{}'", proto);
+            }
+        }
+
+        for ports in [&rule.sport, &rule.dport].into_iter().flatten() {
+            match &proto {
+                Some(proto) if PORT_PROTOCOLS.contains(&proto.as_str()) => {
+                    parse_ports(ports)?;
+                }
+                _ => bail!(
+                    "Ports require one of the protocols {}",
+                    PORT_PROTOCOLS.join(", ")
+                ),
+            }
+        }
+
+        let source = rule
+            .source
+            .as_deref()
+            .map(|spec| self.resolve_addresses(spec))
+            .transpose()?;
+        let dest = rule
+            .dest
+            .as_deref()
+            .map(|spec| self.resolve_addresses(spec))
+            .transpose()?;
+
+        if let (Some(source), Some(dest)) = (&source, &dest) {
+            let compatible = source.iter().any(|s| {
+                dest.iter()
+                    .any(|d| s.addr().is_ipv4() == d.addr().is_ipv4())
+            });
+            if !compatible {
+                bail!("Source and destination have no address family in common");
+            }
+        }
+
+        let family_only = match proto.as_deref() {
+            Some("icmp") => Some(true),
+            Some("ipv6-icmp") => Some(false),
+            _ => None,
+        };
+        if let Some(ipv4) = family_only {
+            for networks in [&source, &dest].into_iter().flatten() {
+                if !networks.iter().any(|n| n.addr().is_ipv4() == ipv4) {
+                    bail!(
+                        "Protocol '{}' does not match the address family of the rule",
+                        rule.proto.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+        }
+
+        if let Some(comment) = &rule.comment {
+            if comment.contains('\n') {
+                bail!("Rule comment must be a single line");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a port list such as `22,80,8000:8080` into inclusive ranges
+pub fn parse_ports(spec: &str) -> Result<Vec<(u16, u16)>> {
+    let parse = |port: &str| -> Result<u16> {
+        match port.trim().parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => bail!("Invalid port '{}'", port.trim()),
+        }
+    };
+
+    spec.split(',')
+        .map(|entry| match entry.split_once(':') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    bail!("Invalid port range '{}'", entry.trim());
+                }
+                Ok((first, last))
+            }
+            None => {
+                let port = parse(entry)?;
+                Ok((port, port))
+            }
+        })
+        .collect()
+}
+
+fn validate_name(name: &str, kind: &str) -> Result<()> {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("{} name '{}' is invalid", kind, name);
+    }
+    Ok(())
+}
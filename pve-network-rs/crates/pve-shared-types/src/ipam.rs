@@ -13,6 +13,9 @@ pub enum IpamType {
     PhpIpam,
     #[serde(rename = "netbox")]
     NetBox,
+    /// IPAM type provided by an external plugin
+    #[serde(untagged)]
+    External(String),
 }
 
 impl std::fmt::Display for IpamType {
@@ -21,6 +24,7 @@ impl std::fmt::Display for IpamType {
             IpamType::Pve => write!(f, "pve"),
             IpamType::PhpIpam => write!(f, "phpipam"),
             IpamType::NetBox => write!(f, "netbox"),
+            IpamType::External(name) => write!(f, "{}", name),
         }
     }
 }
@@ -78,6 +82,7 @@ impl IpamConfig {
                     bail!("NetBox requires token configuration");
                 }
             }
+            IpamType::External(_) => {}
         }
 
         Ok(())
@@ -1,26 +1,38 @@
+pub mod bgp;
 pub mod container;
+pub mod dns;
 pub mod error;
 pub mod events;
+pub mod firewall;
 pub mod ipam;
 pub mod migration;
 pub mod network;
 pub mod sdn;
 pub mod storage;
 
+pub use bgp::{
+    BfdProfile, BgpPeerConfig, BgpPeerStatus, EvpnMacEntry, EvpnVniStatus, PolicyAction,
+    PrefixList, PrefixListEntry, RouteMap, RouteMapEntry,
+};
 pub use container::{
     ContainerId, ContainerNetworkConfig, ContainerNetworkEvent, ContainerNetworkEventType,
     ContainerNetworkInterface, ContainerNetworkState, ContainerNetworkStatus, VNetBinding,
 };
+pub use dns::{DnsConfig, DnsType};
 pub use error::{SharedResult, SharedTypeError};
 pub use events::{ChangeType, ConfigChange, SystemEvent};
+pub use firewall::{
+    FirewallAction, FirewallAlias, FirewallDirection, FirewallIpSet, FirewallRule, VNetFirewall,
+};
 pub use ipam::{IpAllocation, IpAllocationRequest, IpamConfig, IpamType};
 pub use migration::{EndpointConfig, MigrationConfig, MigrationPhase};
 pub use network::{
     AddressMethod, BondMode, Interface, InterfaceType, IpAddress, MacAddr, NetworkConfiguration,
 };
 pub use sdn::{
-    ControllerConfig, ControllerStatus, ControllerType, DhcpConfig, SdnConfiguration, SubnetConfig,
-    SubnetType, VNetConfig, ZoneConfig, ZoneType,
+    BuiltinDrivers, ControllerConfig, ControllerStatus, ControllerType, DhcpConfig, DriverRegistry,
+    FabricConfig, FabricInterface, FabricNodeConfig, FabricProtocol, SdnConfiguration,
+    SubnetConfig, SubnetType, VNetConfig, ZoneConfig, ZoneType,
 };
 pub use storage::{
     QosSettings, StorageBackendType, StorageNetworkConfig, StorageNetworkInfo,
@@ -5,7 +5,12 @@ use anyhow::{bail, Result};
 use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 
-use crate::ipam::IpamConfig;
+use crate::bgp::{
+    BfdProfile, BgpPeerConfig, BgpPeerStatus, EvpnMacEntry, EvpnVniStatus, PrefixList, RouteMap,
+};
+use crate::dns::DnsConfig;
+use crate::firewall::VNetFirewall;
+use crate::ipam::{IpamConfig, IpamType};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
@@ -15,6 +20,11 @@ pub enum ZoneType {
     QinQ,
     Vxlan,
     Evpn,
+    Geneve,
+    Wireguard,
+    /// Zone type provided by an external plugin
+    #[serde(untagged)]
+    External(String),
 }
 
 impl std::fmt::Display for ZoneType {
@@ -25,6 +35,9 @@ impl std::fmt::Display for ZoneType {
             ZoneType::QinQ => write!(f, "qinq"),
             ZoneType::Vxlan => write!(f, "vxlan"),
             ZoneType::Evpn => write!(f, "evpn"),
+            ZoneType::Geneve => write!(f, "geneve"),
+            ZoneType::Wireguard => write!(f, "wireguard"),
+            ZoneType::External(name) => write!(f, "{}", name),
         }
     }
 }
@@ -80,6 +93,16 @@ impl ZoneConfig {
             }
         }
 
+        if let Some(dhcp) = self.options.get("dhcp") {
+            match dhcp.as_str() {
+                Some("dnsmasq") => {}
+                _ => bail!(
+                    "Unsupported DHCP plugin {}, only 'dnsmasq' is available",
+                    dhcp
+                ),
+            }
+        }
+
         Ok(())
     }
 }
@@ -92,6 +115,15 @@ pub struct VNetConfig {
     pub alias: Option<String>,
     pub vlanaware: Option<bool>,
     pub mac: Option<String>,
+    /// Isolate the guest ports of the vnet bridge from each other, leaving
+    /// them only the uplink and the gateway
+    #[serde(rename = "isolate-ports")]
+    pub isolate_ports: Option<bool>,
+    /// ARP/ND suppression on the VXLAN port of EVPN vnets, defaults to on
+    #[serde(rename = "neigh-suppress")]
+    pub neigh_suppress: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firewall: Option<VNetFirewall>,
     #[serde(flatten)]
     pub options: HashMap<String, serde_json::Value>,
 }
@@ -105,6 +137,9 @@ impl VNetConfig {
             alias: None,
             vlanaware: None,
             mac: None,
+            isolate_ports: None,
+            neigh_suppress: None,
+            firewall: None,
             options: HashMap::new(),
         }
     }
@@ -130,8 +165,63 @@ impl VNetConfig {
             }
         }
 
+        if let Some(firewall) = &self.firewall {
+            firewall.validate()?;
+        }
+
         Ok(())
     }
+
+    pub fn isolate_ports(&self) -> bool {
+        self.isolate_ports.unwrap_or(false)
+    }
+
+    pub fn neigh_suppress(&self) -> bool {
+        self.neigh_suppress.unwrap_or(true)
+    }
+
+    /// Validate the options that depend on the type of `zone`
+    ///
+    /// As in PVE, isolate-ports only isolates guest ports, so guests keep
+    /// reaching the uplink or tunnel port and the gateway behind it. Geneve
+    /// zones already isolate their tunnel ports for split horizon, isolated
+    /// guests could not reach them.
+    pub fn validate_zone(&self, zone: &ZoneConfig) -> Result<()> {
+        if self.neigh_suppress.is_some() && zone.zone_type != ZoneType::Evpn {
+            bail!(
+                "VNet '{}': neigh-suppress is only supported in EVPN zones",
+                self.vnet
+            );
+        }
+
+        if self.isolate_ports()
+            && !matches!(
+                zone.zone_type,
+                ZoneType::Simple
+                    | ZoneType::Vlan
+                    | ZoneType::QinQ
+                    | ZoneType::Vxlan
+                    | ZoneType::Evpn
+            )
+        {
+            bail!(
+                "VNet '{}': isolate-ports is not supported in {} zones",
+                self.vnet,
+                zone.zone_type
+            );
+        }
+
+        Ok(())
+    }
+
+    /// VXLAN network identifier, the `vni` option or else the tag
+    pub fn vni(&self) -> Option<u32> {
+        self.options
+            .get("vni")
+            .and_then(|v| v.as_u64())
+            .map(|vni| vni as u32)
+            .or(self.tag.map(u32::from))
+    }
 }
 
 fn is_valid_mac(mac: &str) -> bool {
@@ -243,6 +333,10 @@ pub enum ControllerType {
     Evpn,
     Bgp,
     Faucet,
+    Isis,
+    /// Controller type provided by an external plugin
+    #[serde(untagged)]
+    External(String),
 }
 
 impl std::fmt::Display for ControllerType {
@@ -251,6 +345,8 @@ impl std::fmt::Display for ControllerType {
             ControllerType::Evpn => write!(f, "evpn"),
             ControllerType::Bgp => write!(f, "bgp"),
             ControllerType::Faucet => write!(f, "faucet"),
+            ControllerType::Isis => write!(f, "isis"),
+            ControllerType::External(name) => write!(f, "{}", name),
         }
     }
 }
@@ -265,6 +361,23 @@ pub struct ControllerConfig {
     pub bgp_multipath_relax: Option<bool>,
     pub ebgp_requires_policy: Option<bool>,
     pub node: Option<String>,
+    /// Typed neighbors, used alongside the plain `peers` addresses
+    #[serde(default, rename = "bgp-peers", skip_serializing_if = "Vec::is_empty")]
+    pub bgp_peers: Vec<BgpPeerConfig>,
+    #[serde(
+        default,
+        rename = "bfd-profiles",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub bfd_profiles: Vec<BfdProfile>,
+    #[serde(
+        default,
+        rename = "prefix-lists",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub prefix_lists: Vec<PrefixList>,
+    #[serde(default, rename = "route-maps", skip_serializing_if = "Vec::is_empty")]
+    pub route_maps: Vec<RouteMap>,
     #[serde(flatten)]
     pub options: HashMap<String, serde_json::Value>,
 }
@@ -279,10 +392,70 @@ impl ControllerConfig {
             bgp_multipath_relax: None,
             ebgp_requires_policy: None,
             node: None,
+            bgp_peers: Vec::new(),
+            bfd_profiles: Vec::new(),
+            prefix_lists: Vec::new(),
+            route_maps: Vec::new(),
             options: HashMap::new(),
         }
     }
 
+    /// Copy without the BGP session passwords, for API responses
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+
+        for peer in &mut config.bgp_peers {
+            peer.password = None;
+        }
+
+        for (key, value) in &mut config.options {
+            if let Some(options) = value.as_object_mut().filter(|_| key.starts_with("peer-")) {
+                options.remove("password");
+            }
+        }
+
+        config
+    }
+
+    /// Take the BGP session passwords left out of `self` from `current`
+    ///
+    /// Passwords are never returned by the API, an update sending back a
+    /// redacted controller keeps the configured ones. An empty password
+    /// clears the configured one.
+    pub fn keep_passwords(&mut self, current: &ControllerConfig) {
+        for peer in &mut self.bgp_peers {
+            match peer.password.as_deref() {
+                Some("") => peer.password = None,
+                Some(_) => {}
+                None => {
+                    peer.password = current
+                        .bgp_peers
+                        .iter()
+                        .find(|p| p.address == peer.address)
+                        .and_then(|p| p.password.clone());
+                }
+            }
+        }
+
+        for (key, value) in &mut self.options {
+            let Some(options) = value.as_object_mut().filter(|_| key.starts_with("peer-")) else {
+                continue;
+            };
+            match options.get("password") {
+                Some(password) if password.as_str() == Some("") => {
+                    options.remove("password");
+                }
+                Some(_) => {}
+                None => {
+                    if let Some(password) = current.options.get(key).and_then(|v| v.get("password"))
+                    {
+                        options.insert("password".to_string(), password.clone());
+                    }
+                }
+            }
+        }
+    }
+
     pub fn validate(&self) -> Result<()> {
         if self.controller.is_empty() {
             bail!("Controller name cannot be empty");
@@ -294,17 +467,290 @@ impl ControllerConfig {
             }
         }
 
+        let mut profiles = HashSet::new();
+        for profile in &self.bfd_profiles {
+            profile.validate()?;
+            if !profiles.insert(profile.name.as_str()) {
+                bail!("BFD profile '{}' is defined twice", profile.name);
+            }
+        }
+
+        let mut prefix_lists = HashSet::new();
+        for list in &self.prefix_lists {
+            list.validate()?;
+            if !prefix_lists.insert(list.name.as_str()) {
+                bail!("Prefix-list '{}' is defined twice", list.name);
+            }
+        }
+
+        let mut route_maps = HashSet::new();
+        for map in &self.route_maps {
+            map.validate()?;
+            if !route_maps.insert(map.name.as_str()) {
+                bail!("Route-map '{}' is defined twice", map.name);
+            }
+
+            let ipv6_name = map.ipv6_name();
+            if ipv6_name != map.name && self.route_maps.iter().any(|m| m.name == ipv6_name) {
+                bail!(
+                    "Route-map '{}' clashes with the IPv6 variant of route-map '{}'",
+                    ipv6_name,
+                    map.name
+                );
+            }
+
+            for entry in &map.entries {
+                if let Some(list) = &entry.match_prefix_list {
+                    if !prefix_lists.contains(list.as_str()) {
+                        bail!(
+                            "Route-map '{}' references unknown prefix-list '{}'",
+                            map.name,
+                            list
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut addresses = HashSet::new();
+        for peer in &self.bgp_peers {
+            peer.validate()?;
+            if !addresses.insert(peer.address) {
+                bail!("BGP peer {} is defined twice", peer.address);
+            }
+
+            if let Some(profile) = &peer.bfd_profile {
+                if !profiles.contains(profile.as_str()) {
+                    bail!(
+                        "Peer {} references unknown BFD profile '{}'",
+                        peer.address,
+                        profile
+                    );
+                }
+            }
+
+            for list in [&peer.prefix_list_in, &peer.prefix_list_out]
+                .into_iter()
+                .flatten()
+            {
+                if !prefix_lists.contains(list.as_str()) {
+                    bail!(
+                        "Peer {} references unknown prefix-list '{}'",
+                        peer.address,
+                        list
+                    );
+                }
+            }
+
+            for map in [&peer.route_map_in, &peer.route_map_out]
+                .into_iter()
+                .flatten()
+            {
+                if !route_maps.contains(map.as_str()) {
+                    bail!(
+                        "Peer {} references unknown route-map '{}'",
+                        peer.address,
+                        map
+                    );
+                }
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Routing protocol of an underlay fabric
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FabricProtocol {
+    Openfabric,
+    Ospf,
+}
+
+impl std::fmt::Display for FabricProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FabricProtocol::Openfabric => write!(f, "openfabric"),
+            FabricProtocol::Ospf => write!(f, "ospf"),
+        }
+    }
+}
+
+/// Point-to-point interface of a fabric node
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FabricInterface {
+    pub name: String,
+    /// Address of the link, `None` for unnumbered links
+    pub ip: Option<IpNet>,
+}
+
+/// Node taking part in a fabric
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FabricNodeConfig {
+    pub node: String,
+    /// Loopback address, used as the node's VTEP address
+    pub loopback: IpAddr,
+    #[serde(default)]
+    pub interfaces: Vec<FabricInterface>,
+}
+
+/// Underlay fabric connecting the VTEPs of the cluster
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FabricConfig {
+    pub fabric: String,
+    pub protocol: FabricProtocol,
+    /// OSPF area, defaults to 0
+    pub area: Option<String>,
+    #[serde(rename = "hello-interval")]
+    pub hello_interval: Option<u16>,
+    #[serde(default)]
+    pub nodes: Vec<FabricNodeConfig>,
+    #[serde(flatten)]
+    pub options: HashMap<String, serde_json::Value>,
+}
+
+impl FabricConfig {
+    pub fn new(fabric: String, protocol: FabricProtocol) -> Self {
+        Self {
+            fabric,
+            protocol,
+            area: None,
+            hello_interval: None,
+            nodes: Vec::new(),
+            options: HashMap::new(),
+        }
+    }
+
+    pub fn node(&self, node: &str) -> Option<&FabricNodeConfig> {
+        self.nodes.iter().find(|n| n.node == node)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        // The loopback device is named dummy_<fabric>
+        if self.fabric.is_empty()
+            || self.fabric.len() > 8
+            || !self.fabric.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            bail!("Fabric name must be 1-8 alphanumeric characters");
+        }
+
+        if let Some(0) = self.hello_interval {
+            bail!("Fabric hello interval cannot be 0");
+        }
+
+        let mut nodes = HashSet::new();
+        let mut loopbacks = HashSet::new();
+
+        for node in &self.nodes {
+            if !nodes.insert(&node.node) {
+                bail!(
+                    "Node '{}' is listed twice in fabric '{}'",
+                    node.node,
+                    self.fabric
+                );
+            }
+
+            if !loopbacks.insert(node.loopback) {
+                bail!(
+                    "Loopback {} is used twice in fabric '{}'",
+                    node.loopback,
+                    self.fabric
+                );
+            }
+
+            if self.protocol == FabricProtocol::Ospf && !node.loopback.is_ipv4() {
+                bail!(
+                    "OSPF fabric '{}' requires an IPv4 loopback on node '{}'",
+                    self.fabric,
+                    node.node
+                );
+            }
+
+            let mut interfaces = HashSet::new();
+            for interface in &node.interfaces {
+                if interface.name.is_empty() || interface.name.len() > 15 {
+                    bail!("Invalid fabric interface name '{}'", interface.name);
+                }
+
+                if !interfaces.insert(&interface.name) {
+                    bail!(
+                        "Interface '{}' is listed twice for node '{}'",
+                        interface.name,
+                        node.node
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ControllerStatus {
     pub running: bool,
     pub pid: Option<u32>,
     pub uptime: Option<u64>,
     pub last_error: Option<String>,
     pub config_version: Option<String>,
+    /// BGP sessions of the controller
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bgp_peers: Vec<BgpPeerStatus>,
+    /// EVPN VNIs of the node
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evpn_vnis: Vec<EvpnVniStatus>,
+    /// EVPN MAC/IP table of the node
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evpn_macs: Vec<EvpnMacEntry>,
+}
+
+/// Zone, controller and IPAM types that have a driver
+///
+/// Any unknown `type` deserializes as an external type, so configurations
+/// are checked against the drivers actually registered.
+pub trait DriverRegistry {
+    fn has_zone_driver(&self, zone_type: &ZoneType) -> bool;
+    fn has_controller_driver(&self, controller_type: &ControllerType) -> bool;
+    fn has_ipam_driver(&self, ipam_type: &IpamType) -> bool;
+
+    fn check_zone_type(&self, zone_type: &ZoneType) -> Result<()> {
+        if !self.has_zone_driver(zone_type) {
+            bail!("Unknown zone type '{}'", zone_type);
+        }
+        Ok(())
+    }
+
+    fn check_controller_type(&self, controller_type: &ControllerType) -> Result<()> {
+        if !self.has_controller_driver(controller_type) {
+            bail!("Unknown controller type '{}'", controller_type);
+        }
+        Ok(())
+    }
+
+    fn check_ipam_type(&self, ipam_type: &IpamType) -> Result<()> {
+        if !self.has_ipam_driver(ipam_type) {
+            bail!("Unknown IPAM type '{}'", ipam_type);
+        }
+        Ok(())
+    }
+}
+
+/// Only the built-in driver types, no external plugins loaded
+pub struct BuiltinDrivers;
+
+impl DriverRegistry for BuiltinDrivers {
+    fn has_zone_driver(&self, zone_type: &ZoneType) -> bool {
+        !matches!(zone_type, ZoneType::External(_))
+    }
+
+    fn has_controller_driver(&self, controller_type: &ControllerType) -> bool {
+        !matches!(controller_type, ControllerType::External(_))
+    }
+
+    fn has_ipam_driver(&self, ipam_type: &IpamType) -> bool {
+        !matches!(ipam_type, IpamType::External(_))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -319,6 +765,13 @@ pub struct SdnConfiguration {
     pub controllers: HashMap<String, ControllerConfig>,
     #[serde(default)]
     pub ipams: HashMap<String, IpamConfig>,
+    #[serde(default)]
+    pub dns: HashMap<String, DnsConfig>,
+    #[serde(default)]
+    pub fabrics: HashMap<String, FabricConfig>,
+    /// Version of the running configuration, bumped on every apply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
 }
 
 impl SdnConfiguration {
@@ -326,6 +779,40 @@ impl SdnConfiguration {
         Self::default()
     }
 
+    /// Version of the running configuration, 1 if it was never bumped
+    pub fn running_version(&self) -> u64 {
+        self.version.unwrap_or(1)
+    }
+
+    /// Copy without secrets, for API responses
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        for controller in config.controllers.values_mut() {
+            *controller = controller.redacted();
+        }
+        for dns in config.dns.values_mut() {
+            *dns = dns.redacted();
+        }
+        config
+    }
+
+    /// Take the secrets left out of `self` from `current`
+    ///
+    /// Secrets are never returned by the API, an update sending back a
+    /// redacted configuration keeps the configured ones.
+    pub fn keep_secrets(&mut self, current: &SdnConfiguration) {
+        for (name, controller) in &mut self.controllers {
+            if let Some(current) = current.controllers.get(name) {
+                controller.keep_passwords(current);
+            }
+        }
+        for (name, dns) in &mut self.dns {
+            if let Some(current) = current.dns.get(name) {
+                dns.keep_key(current);
+            }
+        }
+    }
+
     pub fn add_zone(&mut self, config: ZoneConfig) -> Result<()> {
         config.validate()?;
         self.zones.insert(config.zone.clone(), config);
@@ -335,9 +822,10 @@ impl SdnConfiguration {
     pub fn add_vnet(&mut self, config: VNetConfig) -> Result<()> {
         config.validate()?;
 
-        if !self.zones.contains_key(&config.zone) {
+        let Some(zone) = self.zones.get(&config.zone) else {
             bail!("Zone '{}' does not exist", config.zone);
-        }
+        };
+        config.validate_zone(zone)?;
 
         self.vnets.insert(config.vnet.clone(), config);
         Ok(())
@@ -360,6 +848,30 @@ impl SdnConfiguration {
         Ok(())
     }
 
+    pub fn add_dns(&mut self, config: DnsConfig) -> Result<()> {
+        config.validate()?;
+        self.dns.insert(config.name.clone(), config);
+        Ok(())
+    }
+
+    pub fn add_controller(&mut self, config: ControllerConfig) -> Result<()> {
+        config.validate()?;
+        self.controllers.insert(config.controller.clone(), config);
+        Ok(())
+    }
+
+    pub fn add_fabric(&mut self, config: FabricConfig) -> Result<()> {
+        config.validate()?;
+        Self::validate_ospf_fabrics(
+            self.fabrics
+                .values()
+                .filter(|fabric| fabric.fabric != config.fabric)
+                .chain(std::iter::once(&config)),
+        )?;
+        self.fabrics.insert(config.fabric.clone(), config);
+        Ok(())
+    }
+
     pub fn remove_zone(&mut self, zone_name: &str) -> Result<()> {
         let dependent_vnets: Vec<_> = self
             .vnets
@@ -410,20 +922,115 @@ impl SdnConfiguration {
         Ok(())
     }
 
+    pub fn remove_dns(&mut self, dns_name: &str) -> Result<()> {
+        let users: Vec<_> = self
+            .subnets
+            .values()
+            .filter(|subnet| {
+                ["dns", "reversedns"]
+                    .iter()
+                    .any(|key| subnet.options.get(*key).and_then(|v| v.as_str()) == Some(dns_name))
+            })
+            .map(|subnet| subnet.subnet.clone())
+            .collect();
+
+        if !users.is_empty() {
+            bail!(
+                "Cannot remove DNS '{}': Subnets {:?} depend on it",
+                dns_name,
+                users
+            );
+        }
+
+        self.dns.remove(dns_name);
+        Ok(())
+    }
+
+    pub fn remove_controller(&mut self, controller_name: &str) -> Result<()> {
+        let dependent_zones: Vec<_> = self
+            .zones
+            .values()
+            .filter(|zone| {
+                zone.options.get("controller").and_then(|c| c.as_str()) == Some(controller_name)
+            })
+            .map(|zone| zone.zone.clone())
+            .collect();
+
+        if !dependent_zones.is_empty() {
+            bail!(
+                "Cannot remove controller '{}': Zones {:?} depend on it",
+                controller_name,
+                dependent_zones
+            );
+        }
+
+        self.controllers.remove(controller_name);
+        Ok(())
+    }
+
+    pub fn remove_fabric(&mut self, fabric_name: &str) -> Result<()> {
+        let users = self.fabric_users(fabric_name);
+        if !users.is_empty() {
+            bail!(
+                "Cannot remove fabric '{}': {:?} depend on it",
+                fabric_name,
+                users
+            );
+        }
+
+        self.fabrics.remove(fabric_name);
+        Ok(())
+    }
+
+    /// Zones and controllers referencing a fabric through their `fabric` option
+    pub fn fabric_users(&self, fabric_name: &str) -> Vec<String> {
+        let references = |options: &HashMap<String, serde_json::Value>| {
+            options.get("fabric").and_then(|f| f.as_str()) == Some(fabric_name)
+        };
+
+        let mut users: Vec<String> = self
+            .zones
+            .values()
+            .filter(|zone| references(&zone.options))
+            .map(|zone| format!("zone {}", zone.zone))
+            .chain(
+                self.controllers
+                    .values()
+                    .filter(|controller| references(&controller.options))
+                    .map(|controller| format!("controller {}", controller.controller)),
+            )
+            .collect();
+        users.sort();
+        users
+    }
+
+    /// Validate the configuration, allowing only built-in driver types
     pub fn validate(&self) -> Result<()> {
+        self.validate_with(&BuiltinDrivers)
+    }
+
+    /// Validate the configuration, allowing the driver types of `drivers`
+    pub fn validate_with(&self, drivers: &dyn DriverRegistry) -> Result<()> {
         for zone in self.zones.values() {
+            drivers.check_zone_type(&zone.zone_type)?;
             zone.validate()?;
         }
 
+        for controller in self.controllers.values() {
+            drivers.check_controller_type(&controller.controller_type)?;
+        }
+
         for vnet in self.vnets.values() {
             vnet.validate()?;
-            if !self.zones.contains_key(&vnet.zone) {
+            let Some(zone) = self.zones.get(&vnet.zone) else {
                 bail!(
                     "VNet '{}' references non-existent zone '{}'",
                     vnet.vnet,
                     vnet.zone
                 );
-            }
+            };
+
+            vnet.validate_zone(zone)?;
         }
 
         for subnet in self.subnets.values() {
@@ -438,9 +1045,116 @@ impl SdnConfiguration {
         }
 
         for ipam in self.ipams.values() {
+            drivers.check_ipam_type(&ipam.ipam_type)?;
             ipam.validate()?;
         }
 
+        for dns in self.dns.values() {
+            dns.validate()?;
+        }
+
+        for subnet in self.subnets.values() {
+            for key in ["dns", "reversedns"] {
+                if let Some(dns) = subnet.options.get(key).and_then(|v| v.as_str()) {
+                    if !self.dns.contains_key(dns) {
+                        bail!(
+                            "Subnet '{}' references non-existent DNS '{}'",
+                            subnet.subnet,
+                            dns
+                        );
+                    }
+                }
+            }
+        }
+
+        for fabric in self.fabrics.values() {
+            fabric.validate()?;
+        }
+        Self::validate_ospf_fabrics(self.fabrics.values())?;
+
+        let fabric_refs = self
+            .zones
+            .values()
+            .map(|zone| (&zone.zone, &zone.options))
+            .chain(
+                self.controllers
+                    .values()
+                    .map(|controller| (&controller.controller, &controller.options)),
+            );
+        for (name, options) in fabric_refs {
+            if let Some(fabric) = options.get("fabric").and_then(|f| f.as_str()) {
+                if !self.fabrics.contains_key(fabric) {
+                    bail!("'{}' references non-existent fabric '{}'", name, fabric);
+                }
+            }
+        }
+
+        self.validate_vni_uniqueness()
+    }
+
+    /// Ensure no node takes part in more than one OSPF fabric
+    ///
+    /// Every OSPF fabric renders a `router ospf` instance with the node's
+    /// loopback as router-id, FRR would merge them into a single instance.
+    fn validate_ospf_fabrics<'a>(fabrics: impl Iterator<Item = &'a FabricConfig>) -> Result<()> {
+        let mut ospf_nodes: HashMap<&str, &str> = HashMap::new();
+        for fabric in fabrics.filter(|fabric| fabric.protocol == FabricProtocol::Ospf) {
+            for node in &fabric.nodes {
+                if let Some(other) = ospf_nodes.insert(&node.node, &fabric.fabric) {
+                    bail!(
+                        "Node '{}' is part of OSPF fabrics '{}' and '{}', only one is supported",
+                        node.node,
+                        other,
+                        fabric.fabric
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensure every VXLAN/EVPN VNI and every EVPN VRF is used only once
+    ///
+    /// Covers zone VNIs, EVPN L3VNIs (`vrf-vxlan`) and the L2 VNIs of vnets in
+    /// VXLAN, EVPN and WireGuard zones.
+    pub fn validate_vni_uniqueness(&self) -> Result<()> {
+        let is_overlay = |zone: &ZoneConfig| {
+            matches!(
+                zone.zone_type,
+                ZoneType::Vxlan | ZoneType::Evpn | ZoneType::Wireguard
+            )
+        };
+
+        let mut owners: HashMap<u32, String> = HashMap::new();
+        let mut claim = |vni: u32, owner: String| -> Result<()> {
+            if let Some(existing) = owners.insert(vni, owner.clone()) {
+                bail!("VNI {} is used by both {} and {}", vni, existing, owner);
+            }
+            Ok(())
+        };
+
+        let mut zones: Vec<_> = self.zones.values().filter(|z| is_overlay(z)).collect();
+        zones.sort_by(|a, b| a.zone.cmp(&b.zone));
+
+        for zone in zones {
+            if let Some(vni) = zone.options.get("vni").and_then(|v| v.as_u64()) {
+                claim(vni as u32, format!("zone '{}'", zone.zone))?;
+            }
+            if let Some(l3vni) = zone.options.get("vrf-vxlan").and_then(|v| v.as_u64()) {
+                claim(l3vni as u32, format!("VRF of zone '{}'", zone.zone))?;
+            }
+        }
+
+        let mut vnets: Vec<_> = self.vnets.values().collect();
+        vnets.sort_by(|a, b| a.vnet.cmp(&b.vnet));
+
+        for vnet in vnets {
+            let overlay = self.zones.get(&vnet.zone).map(is_overlay).unwrap_or(false);
+            if let (true, Some(vni)) = (overlay, vnet.vni()) {
+                claim(vni, format!("vnet '{}'", vnet.vnet))?;
+            }
+        }
+
         Ok(())
     }
 
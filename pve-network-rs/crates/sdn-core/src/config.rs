@@ -1,9 +1,11 @@
-pub use pve_shared_types::SdnConfiguration;
+pub use pve_shared_types::{BuiltinDrivers, DriverRegistry, SdnConfiguration};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{SubnetConfig, SubnetType, VNetConfig, ZoneConfig, ZoneType};
+    use crate::{
+        ControllerType, IpamConfig, IpamType, SubnetConfig, VNetConfig, ZoneConfig, ZoneType,
+    };
     use ipnet::IpNet;
 
     #[test]
@@ -23,6 +25,39 @@ mod tests {
         config.validate().unwrap();
     }
 
+    #[test]
+    fn test_external_driver_types() {
+        let mut config = SdnConfiguration::new();
+        let zone_type = ZoneType::External("vxaln".to_string());
+        let zone_config = ZoneConfig::new(zone_type, "zone1".to_string());
+        config.add_zone(zone_config).unwrap();
+
+        let error = config.validate().unwrap_err().to_string();
+        assert_eq!(error, "Unknown zone type 'vxaln'");
+
+        struct Plugins;
+        impl DriverRegistry for Plugins {
+            fn has_zone_driver(&self, zone_type: &ZoneType) -> bool {
+                BuiltinDrivers.has_zone_driver(zone_type)
+                    || *zone_type == ZoneType::External("vxaln".to_string())
+            }
+
+            fn has_controller_driver(&self, controller_type: &ControllerType) -> bool {
+                BuiltinDrivers.has_controller_driver(controller_type)
+            }
+
+            fn has_ipam_driver(&self, ipam_type: &IpamType) -> bool {
+                BuiltinDrivers.has_ipam_driver(ipam_type)
+            }
+        }
+        config.validate_with(&Plugins).unwrap();
+
+        let ipam = IpamConfig::new("ipam1".to_string(), IpamType::External("x".into()));
+        config.ipams.insert("ipam1".to_string(), ipam);
+        let error = config.validate_with(&Plugins).unwrap_err().to_string();
+        assert_eq!(error, "Unknown IPAM type 'x'");
+    }
+
     #[test]
     fn test_dependency_validation() {
         let mut config = SdnConfiguration::new();
@@ -41,4 +76,84 @@ mod tests {
         config.remove_vnet("vnet1").unwrap();
         config.remove_zone("zone1").unwrap();
     }
-}
+
+    #[test]
+    fn test_vni_uniqueness_validation() {
+        let mut config = SdnConfiguration::new();
+
+        let mut zone_config = ZoneConfig::new(ZoneType::Vxlan, "zone1".to_string());
+        zone_config.peers = Some(vec!["192.0.2.1".to_string()]);
+        zone_config
+            .options
+            .insert("vni".to_string(), serde_json::json!(100));
+        config.add_zone(zone_config).unwrap();
+
+        let mut vnet_config = VNetConfig::new("vnet1".to_string(), "zone1".to_string());
+        vnet_config.tag = Some(200);
+        config.add_vnet(vnet_config).unwrap();
+        config.validate().unwrap();
+
+        // A vnet reusing the zone's VNI
+        let mut vnet_config = VNetConfig::new("vnet2".to_string(), "zone1".to_string());
+        vnet_config.tag = Some(100);
+        config.add_vnet(vnet_config).unwrap();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("VNI 100"), "{}", error);
+    }
+
+    #[test]
+    fn test_vnet_zone_specific_options() {
+        let mut config = SdnConfiguration::new();
+        config
+            .add_zone(ZoneConfig::new(ZoneType::Simple, "simple".to_string()))
+            .unwrap();
+        let mut vlan = ZoneConfig::new(ZoneType::Vlan, "vlan".to_string());
+        vlan.bridge = Some("vmbr0".to_string());
+        config.add_zone(vlan).unwrap();
+
+        let mut isolated = VNetConfig::new("vnet1".to_string(), "simple".to_string());
+        isolated.isolate_ports = Some(true);
+        config.add_vnet(isolated.clone()).unwrap();
+
+        // Only guest ports are isolated, uplinks and tunnels stay reachable
+        let mut vxlan = ZoneConfig::new(ZoneType::Vxlan, "vxlan".to_string());
+        vxlan
+            .options
+            .insert("vni".to_string(), serde_json::json!(100));
+        vxlan.peers = Some(vec!["192.0.2.1".to_string()]);
+        config.add_zone(vxlan).unwrap();
+        isolated.vnet = "vnet4".to_string();
+        isolated.zone = "vxlan".to_string();
+        config.add_vnet(isolated.clone()).unwrap();
+
+        isolated.vnet = "vnet2".to_string();
+        isolated.zone = "vlan".to_string();
+        isolated.tag = Some(100);
+        config.add_vnet(isolated.clone()).unwrap();
+        isolated.tag = None;
+
+        // Geneve tunnel ports are isolated already, guests could not reach them
+        let mut geneve = ZoneConfig::new(ZoneType::Geneve, "geneve".to_string());
+        geneve.bridge = Some("gnvbr1".to_string());
+        geneve
+            .options
+            .insert("vni".to_string(), serde_json::json!(300));
+        geneve.peers = Some(vec!["192.0.2.1".to_string()]);
+        config.add_zone(geneve).unwrap();
+        isolated.vnet = "vnet5".to_string();
+        isolated.zone = "geneve".to_string();
+        let error = config.add_vnet(isolated.clone()).unwrap_err().to_string();
+        assert!(error.contains("isolate-ports"), "{}", error);
+
+        config.vnets.insert("vnet5".to_string(), isolated);
+        assert!(config.validate().is_err());
+        config.vnets.remove("vnet5");
+
+        let mut suppressed = VNetConfig::new("vnet3".to_string(), "simple".to_string());
+        suppressed.neigh_suppress = Some(false);
+        let error = config.add_vnet(suppressed).unwrap_err().to_string();
+        assert!(error.contains("neigh-suppress"), "{}", error);
+
+        config.validate().unwrap();
+    }
+}
@@ -6,7 +6,11 @@ use std::collections::HashMap;
 
 use crate::{VNet, Zone};
 
-pub use pve_shared_types::{ControllerConfig, ControllerStatus, ControllerType};
+pub use pve_shared_types::{
+    BfdProfile, BgpPeerConfig, BgpPeerStatus, ControllerConfig, ControllerStatus, ControllerType,
+    EvpnMacEntry, EvpnVniStatus, PolicyAction, PrefixList, PrefixListEntry, RouteMap,
+    RouteMapEntry,
+};
 
 #[async_trait]
 pub trait Controller: Send + Sync {
@@ -19,4 +23,4 @@ pub trait Controller: Send + Sync {
     async fn stop(&self) -> Result<()>;
     async fn status(&self) -> Result<ControllerStatus>;
     async fn reload(&self) -> Result<()>;
-}
+}
@@ -0,0 +1,111 @@
+//! SDN DHCP abstractions
+//!
+//! Zones with a `dhcp` option run one DHCP server instance per node serving
+//! the `dhcp-range`s of their subnets. Static leases come from IPAM: every
+//! allocation with a MAC address in a DHCP-enabled subnet is handed out to
+//! that MAC only.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::net::IpAddr;
+
+use crate::{IpAllocation, IpamManager, SdnConfiguration, SubnetConfig, ZoneConfig};
+
+/// Zone option selecting the DHCP plugin
+pub const DHCP_OPTION: &str = "dhcp";
+
+/// DHCP server backing SDN zones
+#[async_trait]
+pub trait DhcpPlugin: Send + Sync {
+    /// Plugin name, as used in the zone `dhcp` option
+    fn name(&self) -> &str;
+
+    /// Configure the instance of `zone` and (re)start it if needed
+    ///
+    /// Returns whether the configuration changed.
+    async fn apply_zone(
+        &self,
+        sdn: &SdnConfiguration,
+        zone: &str,
+        leases: &[IpAllocation],
+    ) -> Result<bool>;
+
+    /// Replace the static leases of `zone`
+    ///
+    /// Returns whether the leases changed.
+    async fn sync_leases(
+        &self,
+        sdn: &SdnConfiguration,
+        zone: &str,
+        leases: &[IpAllocation],
+    ) -> Result<bool>;
+
+    /// Stop the instance of `zone` and remove its configuration
+    async fn remove_zone(&self, zone: &str) -> Result<()>;
+
+    /// Zones with an instance configured on this node
+    async fn configured_zones(&self) -> Result<Vec<String>>;
+}
+
+/// DHCP plugin of `zone`, if DHCP is enabled for it
+pub fn zone_dhcp_plugin(zone: &ZoneConfig) -> Option<&str> {
+    zone.options.get(DHCP_OPTION).and_then(|v| v.as_str())
+}
+
+/// Parse a `start,end` DHCP range
+pub fn parse_dhcp_range(range: &str) -> Option<(IpAddr, IpAddr)> {
+    let (start, end) = range.split_once(',')?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+/// Subnets of `zone` with at least one DHCP range, sorted by name
+pub fn zone_dhcp_subnets<'a>(sdn: &'a SdnConfiguration, zone: &str) -> Vec<&'a SubnetConfig> {
+    let mut subnets: Vec<&SubnetConfig> = sdn
+        .subnets
+        .values()
+        .filter(|subnet| {
+            sdn.vnets
+                .get(&subnet.vnet)
+                .map(|vnet| vnet.zone == zone)
+                .unwrap_or(false)
+        })
+        .filter(|subnet| {
+            subnet
+                .dhcp
+                .as_ref()
+                .and_then(|dhcp| dhcp.dhcp_range.as_ref())
+                .map(|ranges| !ranges.is_empty())
+                .unwrap_or(false)
+        })
+        .collect();
+
+    subnets.sort_by(|a, b| a.subnet.cmp(&b.subnet));
+    subnets
+}
+
+/// Zone serving DHCP for `subnet`, if any
+pub fn subnet_dhcp_zone<'a>(sdn: &'a SdnConfiguration, subnet: &str) -> Option<&'a ZoneConfig> {
+    let subnet = sdn.subnets.get(subnet)?;
+    let vnet = sdn.vnets.get(&subnet.vnet)?;
+    let zone = sdn.zones.get(&vnet.zone)?;
+
+    zone_dhcp_plugin(zone).map(|_| zone)
+}
+
+/// Static leases of `zone`: IPAM allocations with a MAC in its DHCP subnets
+pub async fn collect_static_leases(
+    ipam: &IpamManager,
+    sdn: &SdnConfiguration,
+    zone: &str,
+) -> Result<Vec<IpAllocation>> {
+    let mut leases = Vec::new();
+
+    for subnet in zone_dhcp_subnets(sdn, zone) {
+        let plugin = subnet.options.get("ipam").and_then(|v| v.as_str());
+        let allocations = ipam.list_subnet_ips(plugin, &subnet.subnet).await?;
+        leases.extend(allocations.into_iter().filter(|a| a.mac.is_some()));
+    }
+
+    leases.sort_by(|a, b| (&a.subnet, a.ip).cmp(&(&b.subnet, b.ip)));
+    Ok(leases)
+}
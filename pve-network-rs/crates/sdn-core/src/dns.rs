@@ -0,0 +1,243 @@
+//! SDN DNS abstractions
+//!
+//! DNS plugins register forward (A/AAAA) and reverse (PTR) records for IPAM
+//! allocations. A subnet opts in through its options:
+//!
+//! - `dns` + `dnszone`: plugin and zone of the forward records
+//! - `reversedns`: plugin of the PTR records, the reverse zone is derived
+//!   from the subnet
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+use crate::SubnetConfig;
+
+pub use pve_shared_types::dns::DEFAULT_REVERSE_MASK_V6;
+pub use pve_shared_types::{DnsConfig, DnsType};
+
+#[async_trait]
+pub trait DnsPlugin: Send + Sync {
+    fn dns_type(&self) -> DnsType;
+    fn name(&self) -> &str;
+    async fn validate_config(&self, config: &DnsConfig) -> Result<()>;
+
+    /// Check that `zone` exists on the server
+    async fn verify_zone(&self, zone: &str) -> Result<()>;
+
+    /// Add an A or AAAA record, depending on the address family of `ip`
+    async fn add_a_record(&self, zone: &str, hostname: &str, ip: &IpAddr) -> Result<()>;
+
+    /// Remove `ip` from the A or AAAA records of `hostname`
+    async fn del_a_record(&self, zone: &str, hostname: &str, ip: &IpAddr) -> Result<()>;
+
+    /// Move `hostname` from `old_ip` to `ip`
+    async fn update_a_record(
+        &self,
+        zone: &str,
+        hostname: &str,
+        old_ip: &IpAddr,
+        ip: &IpAddr,
+    ) -> Result<()> {
+        self.del_a_record(zone, hostname, old_ip).await?;
+        self.add_a_record(zone, hostname, ip).await
+    }
+
+    /// Point the PTR record of `ip` to `fqdn`, replacing an existing one
+    async fn add_ptr_record(&self, reverse_zone: &str, fqdn: &str, ip: &IpAddr) -> Result<()>;
+
+    /// Remove the PTR record of `ip`
+    async fn del_ptr_record(&self, reverse_zone: &str, ip: &IpAddr) -> Result<()>;
+
+    /// Reverse zone holding the PTR records of `cidr`
+    fn reverse_zone(&self, cidr: &IpNet) -> String {
+        reverse_zone(cidr, DEFAULT_REVERSE_MASK_V6)
+    }
+}
+
+/// DNS settings of a subnet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubnetDns {
+    pub cidr: IpNet,
+    /// Plugin registering forward records
+    pub dns: Option<String>,
+    /// Zone of the forward records
+    pub dnszone: Option<String>,
+    /// Plugin registering PTR records
+    pub reversedns: Option<String>,
+}
+
+impl SubnetDns {
+    /// DNS settings of `subnet`, if it uses DNS at all
+    pub fn from_subnet(subnet: &SubnetConfig) -> Option<Self> {
+        let option = |key: &str| {
+            subnet
+                .options
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+
+        let dns = Self {
+            cidr: subnet.cidr,
+            dns: option("dns"),
+            dnszone: option("dnszone"),
+            reversedns: option("reversedns"),
+        };
+
+        if dns.forward().is_none() && dns.reversedns.is_none() {
+            return None;
+        }
+
+        Some(dns)
+    }
+
+    /// Plugin and zone of the forward records
+    pub fn forward(&self) -> Option<(&str, &str)> {
+        Some((self.dns.as_deref()?, self.dnszone.as_deref()?))
+    }
+
+    /// Fully qualified name of `hostname` in the forward zone
+    ///
+    /// The same name the forward records are registered under, see
+    /// [`record_name`].
+    pub fn fqdn(&self, hostname: &str) -> String {
+        match &self.dnszone {
+            Some(zone) => record_name(zone, hostname),
+            None => hostname.trim_end_matches('.').to_string(),
+        }
+    }
+}
+
+/// Name of the forward records of `hostname` in `zone`
+///
+/// Only names with a trailing dot are absolute, everything else is
+/// relative to the zone (`vm.other` -> `vm.other.example.com`).
+pub fn record_name(zone: &str, hostname: &str) -> String {
+    if hostname.ends_with('.') {
+        hostname.trim_end_matches('.').to_string()
+    } else {
+        format!("{}.{}", hostname, zone.trim_end_matches('.'))
+    }
+}
+
+/// Reverse zone of `cidr`
+///
+/// IPv4 zones are cut at the octet boundary at or above the prefix
+/// (`10.1.2.0/24` -> `2.1.10.in-addr.arpa`), IPv6 zones at `mask_v6`.
+pub fn reverse_zone(cidr: &IpNet, mask_v6: u8) -> String {
+    match cidr {
+        IpNet::V4(net) => {
+            let octets = (net.prefix_len() / 8).clamp(1, 3) as usize;
+            let labels: Vec<String> = net.network().octets()[..octets]
+                .iter()
+                .rev()
+                .map(|octet| octet.to_string())
+                .collect();
+            format!("{}.in-addr.arpa", labels.join("."))
+        }
+        IpNet::V6(net) => {
+            let nibbles = (mask_v6 / 4) as usize;
+            let labels: Vec<String> = ipv6_nibbles(&IpAddr::V6(net.network()))[..nibbles]
+                .iter()
+                .rev()
+                .map(|nibble| format!("{:x}", nibble))
+                .collect();
+            format!("{}.ip6.arpa", labels.join("."))
+        }
+    }
+}
+
+/// Name of the PTR record of `ip`
+pub fn ptr_name(ip: &IpAddr) -> String {
+    match ip {
+        IpAddr::V4(addr) => {
+            let labels: Vec<String> = addr.octets().iter().rev().map(u8::to_string).collect();
+            format!("{}.in-addr.arpa", labels.join("."))
+        }
+        IpAddr::V6(_) => {
+            let labels: Vec<String> = ipv6_nibbles(ip)
+                .iter()
+                .rev()
+                .map(|nibble| format!("{:x}", nibble))
+                .collect();
+            format!("{}.ip6.arpa", labels.join("."))
+        }
+    }
+}
+
+fn ipv6_nibbles(ip: &IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V6(addr) => addr
+            .octets()
+            .iter()
+            .flat_map(|octet| [octet >> 4, octet & 0xf])
+            .collect(),
+        IpAddr::V4(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reverse_names() {
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+        assert_eq!(ptr_name(&ip), "3.2.1.10.in-addr.arpa");
+        assert_eq!(
+            reverse_zone(&"10.1.2.0/24".parse().unwrap(), 64),
+            "2.1.10.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_zone(&"10.1.0.0/20".parse().unwrap(), 64),
+            "1.10.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_zone(&"10.1.2.128/25".parse().unwrap(), 64),
+            "2.1.10.in-addr.arpa"
+        );
+
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(
+            ptr_name(&ip),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        let cidr = "2001:db8:0:1::/64".parse().unwrap();
+        assert_eq!(
+            reverse_zone(&cidr, 64),
+            "1.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        assert_eq!(reverse_zone(&cidr, 48), "0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa");
+    }
+
+    #[test]
+    fn test_subnet_dns() {
+        let mut subnet = SubnetConfig::new(
+            "zone1-10.1.2.0-24".to_string(),
+            "vnet1".to_string(),
+            "10.1.2.0/24".parse().unwrap(),
+        );
+        assert_eq!(SubnetDns::from_subnet(&subnet), None);
+
+        // A zone without plugin registers nothing
+        subnet
+            .options
+            .insert("dnszone".to_string(), json!("example.com"));
+        assert_eq!(SubnetDns::from_subnet(&subnet), None);
+
+        subnet.options.insert("dns".to_string(), json!("pdns"));
+        let dns = SubnetDns::from_subnet(&subnet).unwrap();
+        assert_eq!(dns.forward(), Some(("pdns", "example.com")));
+        assert_eq!(dns.reversedns, None);
+        assert_eq!(dns.fqdn("vm100"), "vm100.example.com");
+        assert_eq!(dns.fqdn("vm100.other.org."), "vm100.other.org");
+        assert_eq!(dns.fqdn("vm100.other.org"), "vm100.other.org.example.com");
+        assert_eq!(
+            dns.fqdn("vm100.other.org"),
+            record_name("example.com.", "vm100.other.org")
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! System command execution for SDN drivers
+//!
+//! Drivers never spawn processes directly. They go through a
+//! [`SystemExecutor`], which allows running them against the real system,
+//! a recording mock in tests, or a dry-run executor that only collects the
+//! commands that would have been executed.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// A command line executed by a driver
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemCommand {
+    /// Program to run
+    pub program: String,
+    /// Program arguments
+    pub args: Vec<String>,
+}
+
+impl SystemCommand {
+    /// Create a new command
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for SystemCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Output of an executed command
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandOutput {
+    /// Exit code, `None` when the process was terminated by a signal
+    pub exit_code: Option<i32>,
+    /// Captured standard output
+    pub stdout: String,
+    /// Captured standard error
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Successful output with the given stdout
+    pub fn success(stdout: &str) -> Self {
+        Self {
+            exit_code: Some(0),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Failed output with the given exit code and stderr
+    pub fn failure(exit_code: i32, stderr: &str) -> Self {
+        Self {
+            exit_code: Some(exit_code),
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    /// Whether the command exited successfully
+    pub fn is_success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Executes system commands on behalf of SDN drivers
+#[async_trait]
+pub trait SystemExecutor: Send + Sync {
+    /// Run a command that changes system state
+    async fn execute(&self, program: &str, args: &[&str]) -> Result<CommandOutput>;
+
+    /// Run a read-only command, e.g. `ip link show`
+    ///
+    /// Dry-run executors use this distinction to keep inspecting the system
+    /// while suppressing changes.
+    async fn query(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        self.execute(program, args).await
+    }
+}
+
+/// Executor spawning real processes
+#[derive(Debug, Default, Clone)]
+pub struct RealExecutor;
+
+impl RealExecutor {
+    /// Create new real executor
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl SystemExecutor for RealExecutor {
+    async fn execute(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        log::debug!("Executing: {}", SystemCommand::new(program, args));
+
+        let output = tokio::process::Command::new(program)
+            .args(args)
+            .output()
+            .await
+            .with_context(|| format!("Failed to execute '{}'", program))?;
+
+        Ok(CommandOutput {
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+/// Executor recording every command, for tests
+///
+/// Commands succeed with empty output unless a canned response was
+/// registered for a matching command line prefix.
+#[derive(Debug, Default)]
+pub struct RecordingExecutor {
+    commands: Mutex<Vec<SystemCommand>>,
+    responses: Mutex<Vec<(String, CommandOutput)>>,
+}
+
+impl RecordingExecutor {
+    /// Create new recording executor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return `output` for commands whose command line starts with `prefix`
+    ///
+    /// Later registrations take precedence over earlier ones.
+    pub fn respond(&self, prefix: &str, output: CommandOutput) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .push((prefix.to_string(), output));
+        self
+    }
+
+    /// All recorded commands
+    pub fn commands(&self) -> Vec<SystemCommand> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    /// All recorded command lines
+    pub fn command_lines(&self) -> Vec<String> {
+        self.commands().iter().map(|c| c.to_string()).collect()
+    }
+
+    /// Forget recorded commands
+    pub fn clear(&self) {
+        self.commands.lock().unwrap().clear();
+    }
+
+    /// Assert that exactly the expected command lines were recorded, in order
+    pub fn assert_commands(&self, expected: &[&str]) {
+        let actual = self.command_lines();
+        assert_eq!(
+            actual, expected,
+            "unexpected command sequence\nactual:   {:#?}\nexpected: {:#?}",
+            actual, expected
+        );
+    }
+
+    fn record(&self, program: &str, args: &[&str]) -> CommandOutput {
+        let command = SystemCommand::new(program, args);
+        let line = command.to_string();
+        self.commands.lock().unwrap().push(command);
+
+        self.responses
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(prefix, _)| line.starts_with(prefix.as_str()))
+            .map(|(_, output)| output.clone())
+            .unwrap_or_else(|| CommandOutput::success(""))
+    }
+}
+
+#[async_trait]
+impl SystemExecutor for RecordingExecutor {
+    async fn execute(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        Ok(self.record(program, args))
+    }
+}
+
+/// Executor collecting changes as a plan instead of running them
+///
+/// Read-only queries are forwarded to an optional inspector so the plan
+/// reflects the current system state. Without an inspector queries fail,
+/// meaning nothing is assumed to exist yet.
+#[derive(Default)]
+pub struct DryRunExecutor {
+    inspector: Option<Arc<dyn SystemExecutor>>,
+    plan: Mutex<Vec<SystemCommand>>,
+}
+
+impl DryRunExecutor {
+    /// Create new dry-run executor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward read-only queries to `inspector`
+    pub fn with_inspector(inspector: Arc<dyn SystemExecutor>) -> Self {
+        Self {
+            inspector: Some(inspector),
+            plan: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Commands that would have been executed
+    pub fn plan(&self) -> Vec<SystemCommand> {
+        self.plan.lock().unwrap().clone()
+    }
+
+    /// Take the plan, leaving it empty
+    pub fn take_plan(&self) -> Vec<SystemCommand> {
+        std::mem::take(&mut *self.plan.lock().unwrap())
+    }
+}
+
+#[async_trait]
+impl SystemExecutor for DryRunExecutor {
+    async fn execute(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        let command = SystemCommand::new(program, args);
+        log::info!("Dry run: {}", command);
+        self.plan.lock().unwrap().push(command);
+        Ok(CommandOutput::success(""))
+    }
+
+    async fn query(&self, program: &str, args: &[&str]) -> Result<CommandOutput> {
+        match &self.inspector {
+            Some(inspector) => inspector.query(program, args).await,
+            None => Ok(CommandOutput::failure(1, "dry run")),
+        }
+    }
+}
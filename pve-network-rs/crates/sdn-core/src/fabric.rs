@@ -0,0 +1,303 @@
+//! SDN underlay fabrics
+//!
+//! A fabric is a set of nodes, each with a loopback address and a list of
+//! point-to-point interfaces, routed with OpenFabric or OSPF. The loopbacks
+//! are announced through the fabric and serve as VTEP addresses: zones and
+//! controllers referencing a fabric through their `fabric` option get their
+//! `vtep-ip`, `local-ip` and peers filled in by [`resolve_fabric_vteps`].
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::{ControllerType, SdnConfiguration, ZoneType};
+
+pub use pve_shared_types::{FabricConfig, FabricInterface, FabricNodeConfig, FabricProtocol};
+
+/// Name of the dummy device carrying the loopback address of `fabric`
+pub fn loopback_device(fabric: &FabricConfig) -> String {
+    format!("dummy_{}", fabric.fabric)
+}
+
+/// IS-IS system ID derived from a loopback address
+///
+/// IPv4 addresses are written with three digits per octet and regrouped,
+/// e.g. `10.10.10.1` becomes `0100.1001.0001`. IPv6 addresses use their
+/// last six bytes.
+pub fn system_id(loopback: IpAddr) -> String {
+    let digits = match loopback {
+        IpAddr::V4(addr) => addr
+            .octets()
+            .iter()
+            .map(|octet| format!("{:03}", octet))
+            .collect::<String>(),
+        IpAddr::V6(addr) => addr.octets()[10..]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>(),
+    };
+
+    format!("{}.{}.{}", &digits[0..4], &digits[4..8], &digits[8..12])
+}
+
+fn fabric_node<'a>(fabric: &'a FabricConfig, node: &str) -> Result<&'a FabricNodeConfig> {
+    fabric
+        .node(node)
+        .with_context(|| format!("Node '{}' is not part of fabric '{}'", node, fabric.fabric))
+}
+
+/// Loopback address of `node` in `fabric`, used as its VTEP address
+pub fn fabric_vtep_ip(sdn: &SdnConfiguration, fabric: &str, node: &str) -> Result<IpAddr> {
+    let fabric = sdn
+        .fabrics
+        .get(fabric)
+        .with_context(|| format!("Fabric '{}' does not exist", fabric))?;
+
+    Ok(fabric_node(fabric, node)?.loopback)
+}
+
+/// Generate the interfaces and FRR configuration of `fabric` for `node`
+///
+/// Returns the `interfaces` and `frr` sections, in the same shape as the
+/// output of zone and controller drivers.
+pub fn generate_fabric_config(
+    fabric: &FabricConfig,
+    node: &str,
+) -> Result<HashMap<String, String>> {
+    fabric
+        .validate()
+        .with_context(|| format!("Invalid fabric '{}'", fabric.fabric))?;
+    let local = fabric_node(fabric, node)?;
+
+    let mut configs = HashMap::new();
+    configs.insert(
+        "interfaces".to_string(),
+        generate_fabric_interfaces(fabric, local),
+    );
+    configs.insert("frr".to_string(), generate_fabric_frr(fabric, local));
+    Ok(configs)
+}
+
+fn host_prefix(addr: IpAddr) -> u8 {
+    if addr.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+fn generate_fabric_interfaces(fabric: &FabricConfig, local: &FabricNodeConfig) -> String {
+    let loopback = loopback_device(fabric);
+    let mut config = format!(
+        "auto {loopback}\n\
+         iface {loopback} inet static\n\
+         \taddress {}/{}\n\
+         \tlink-type dummy\n\
+         \tip-forward on\n",
+        local.loopback,
+        host_prefix(local.loopback),
+    );
+
+    for interface in &local.interfaces {
+        let address = match (&interface.ip, &fabric.protocol) {
+            (Some(ip), _) => Some(ip.to_string()),
+            // OSPF needs an address on the link, borrow the loopback
+            (None, FabricProtocol::Ospf) => Some(format!("{}/32", local.loopback)),
+            (None, FabricProtocol::Openfabric) => None,
+        };
+
+        config.push_str(&format!("\nauto {}\n", interface.name));
+        match address {
+            Some(address) => config.push_str(&format!(
+                "iface {} inet static\n\
+                 \taddress {}\n",
+                interface.name, address
+            )),
+            None => config.push_str(&format!("iface {} inet manual\n", interface.name)),
+        }
+        config.push_str("\tip-forward on\n");
+    }
+
+    config
+}
+
+fn generate_fabric_frr(fabric: &FabricConfig, local: &FabricNodeConfig) -> String {
+    let name = &fabric.fabric;
+    let loopback = loopback_device(fabric);
+    let ipv6 = !local.loopback.is_ipv4();
+
+    let mut config = format!(
+        "!\n\
+         ! {} fabric {}\n\
+         !\n",
+        fabric.protocol, name
+    );
+
+    match fabric.protocol {
+        FabricProtocol::Openfabric => {
+            let ip = if ipv6 { "ipv6" } else { "ip" };
+
+            config.push_str(&format!(
+                "router openfabric {name}\n\
+                 \x20net 49.0001.{}.00\n\
+                 exit\n\
+                 !\n\
+                 interface {loopback}\n\
+                 \x20{ip} router openfabric {name}\n\
+                 \x20openfabric passive\n\
+                 exit\n\
+                 !\n",
+                system_id(local.loopback),
+            ));
+
+            for interface in &local.interfaces {
+                config.push_str(&format!(
+                    "interface {}\n\
+                     \x20{ip} router openfabric {name}\n",
+                    interface.name
+                ));
+                if let Some(hello) = fabric.hello_interval {
+                    config.push_str(&format!(" openfabric hello-interval {}\n", hello));
+                }
+                config.push_str("exit\n!\n");
+            }
+        }
+        FabricProtocol::Ospf => {
+            let area = fabric.area.as_deref().unwrap_or("0");
+
+            config.push_str(&format!(
+                "router ospf\n\
+                 \x20ospf router-id {}\n\
+                 exit\n\
+                 !\n\
+                 interface {loopback}\n\
+                 \x20ip ospf area {area}\n\
+                 \x20ip ospf passive\n\
+                 exit\n\
+                 !\n",
+                local.loopback,
+            ));
+
+            for interface in &local.interfaces {
+                config.push_str(&format!(
+                    "interface {}\n\
+                     \x20ip ospf area {area}\n\
+                     \x20ip ospf network point-to-point\n",
+                    interface.name
+                ));
+                if let Some(hello) = fabric.hello_interval {
+                    config.push_str(&format!(" ip ospf hello-interval {}\n", hello));
+                }
+                config.push_str("exit\n!\n");
+            }
+        }
+    }
+
+    config
+}
+
+/// Fill in VTEP addresses of zones and controllers referencing a fabric
+///
+/// For every zone active on `node` and every controller of `node` with a
+/// `fabric` option, the local loopback of that fabric becomes `vtep-ip`
+/// (EVPN) or `local-ip` (VXLAN, Geneve). VXLAN and Geneve zones and EVPN
+/// controllers without explicit peers peer with the loopbacks of the other
+/// fabric nodes.
+pub fn resolve_fabric_vteps(sdn: &SdnConfiguration, node: &str) -> Result<SdnConfiguration> {
+    let mut resolved = sdn.clone();
+
+    for zone in resolved.zones.values_mut() {
+        let fabric_name = match zone.options.get("fabric").and_then(|f| f.as_str()) {
+            Some(fabric) => fabric.to_string(),
+            None => continue,
+        };
+        if let Some(nodes) = &zone.nodes {
+            if !nodes.iter().any(|n| n == node) {
+                continue;
+            }
+        }
+
+        let fabric = sdn.fabrics.get(&fabric_name).with_context(|| {
+            format!(
+                "Zone '{}' references non-existent fabric '{}'",
+                zone.zone, fabric_name
+            )
+        })?;
+        let vtep = fabric_node(fabric, node)?.loopback.to_string();
+
+        match zone.zone_type {
+            ZoneType::Evpn => {
+                zone.options
+                    .insert("vtep-ip".to_string(), serde_json::json!(vtep));
+            }
+            ZoneType::Vxlan | ZoneType::Geneve => {
+                zone.options
+                    .insert("local-ip".to_string(), serde_json::json!(vtep));
+
+                if zone.peers.as_ref().map(|p| p.is_empty()).unwrap_or(true) {
+                    let peers: Vec<String> = fabric
+                        .nodes
+                        .iter()
+                        .filter(|n| n.node != node)
+                        .filter(|n| {
+                            zone.nodes
+                                .as_ref()
+                                .map(|nodes| nodes.contains(&n.node))
+                                .unwrap_or(true)
+                        })
+                        .map(|n| n.loopback.to_string())
+                        .collect();
+                    zone.peers = Some(peers);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for controller in resolved.controllers.values_mut() {
+        let fabric_name = match controller.options.get("fabric").and_then(|f| f.as_str()) {
+            Some(fabric) => fabric.to_string(),
+            None => continue,
+        };
+        if controller
+            .node
+            .as_deref()
+            .map(|n| n != node)
+            .unwrap_or(false)
+        {
+            continue;
+        }
+
+        let fabric = sdn.fabrics.get(&fabric_name).with_context(|| {
+            format!(
+                "Controller '{}' references non-existent fabric '{}'",
+                controller.controller, fabric_name
+            )
+        })?;
+        let vtep = fabric_node(fabric, node)?.loopback;
+
+        if controller.controller_type == ControllerType::Evpn {
+            controller
+                .options
+                .insert("vtep-ip".to_string(), serde_json::json!(vtep.to_string()));
+
+            if controller
+                .peers
+                .as_ref()
+                .map(|p| p.is_empty())
+                .unwrap_or(true)
+            {
+                controller.peers = Some(
+                    fabric
+                        .nodes
+                        .iter()
+                        .filter(|n| n.node != node)
+                        .map(|n| n.loopback.to_string())
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    Ok(resolved)
+}
@@ -1,14 +1,18 @@
 //! IPAM Manager
 //!
 //! Manages multiple IPAM plugins and provides a unified interface
+//!
+//! Allocations in subnets with DNS settings (see [`SubnetDns`]) also
+//! register their forward and reverse records through the DNS plugins.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::net::IpAddr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use crate::{
-    IpAllocation, IpAllocationRequest, IpamConfig, IpamError, IpamPlugin, IpamType, Subnet,
+    DnsPlugin, IpAllocation, IpAllocationRequest, IpamConfig, IpamError, IpamPlugin, IpamType,
+    SdnConfiguration, Subnet, SubnetDns,
 };
 
 /// IPAM Manager
@@ -17,6 +21,8 @@ use crate::{
 pub struct IpamManager {
     plugins: HashMap<String, Arc<dyn IpamPlugin>>,
     default_plugin: Option<String>,
+    dns_plugins: HashMap<String, Arc<dyn DnsPlugin>>,
+    subnet_dns: RwLock<HashMap<String, SubnetDns>>,
 }
 
 impl IpamManager {
@@ -25,6 +31,8 @@ impl IpamManager {
         Self {
             plugins: HashMap::new(),
             default_plugin: None,
+            dns_plugins: HashMap::new(),
+            subnet_dns: RwLock::new(HashMap::new()),
         }
     }
 
@@ -78,6 +86,176 @@ impl IpamManager {
             .collect()
     }
 
+    /// Register a DNS plugin
+    pub fn register_dns_plugin(&mut self, plugin: Arc<dyn DnsPlugin>) {
+        let name = plugin.name().to_string();
+        log::info!(
+            "Registering DNS plugin: {} (type: {:?})",
+            name,
+            plugin.dns_type()
+        );
+        self.dns_plugins.insert(name, plugin);
+    }
+
+    /// Get DNS plugin by name
+    pub fn get_dns_plugin(&self, name: &str) -> Result<Arc<dyn DnsPlugin>> {
+        self.dns_plugins.get(name).cloned().ok_or_else(|| {
+            IpamError::Configuration {
+                message: format!("DNS plugin '{}' not found", name),
+            }
+            .into()
+        })
+    }
+
+    /// Replace the registered DNS plugins
+    ///
+    /// Used when the DNS entries of the SDN configuration change, plugins
+    /// that are no longer configured are dropped.
+    pub fn set_dns_plugins(&mut self, plugins: Vec<Arc<dyn DnsPlugin>>) {
+        self.dns_plugins.clear();
+        for plugin in plugins {
+            self.register_dns_plugin(plugin);
+        }
+    }
+
+    /// Take the DNS settings of all subnets from `config`
+    ///
+    /// The subnet options are the source of truth, this drops settings of
+    /// subnets that were removed or no longer use DNS.
+    pub fn load_subnet_dns(&self, config: &SdnConfiguration) {
+        let mut subnet_dns = self.subnet_dns.write().unwrap();
+        subnet_dns.clear();
+        for (name, subnet) in &config.subnets {
+            if let Some(dns) = SubnetDns::from_subnet(subnet) {
+                subnet_dns.insert(name.clone(), dns);
+            }
+        }
+    }
+
+    /// Set the DNS settings of a subnet
+    ///
+    /// [`add_subnet`](Self::add_subnet) does this from the subnet options,
+    /// this is for subnets that already exist in IPAM.
+    pub fn set_subnet_dns(&self, subnet: &str, dns: Option<SubnetDns>) {
+        let mut subnet_dns = self.subnet_dns.write().unwrap();
+        match dns {
+            Some(dns) => subnet_dns.insert(subnet.to_string(), dns),
+            None => subnet_dns.remove(subnet),
+        };
+    }
+
+    /// DNS settings of a subnet
+    pub fn subnet_dns(&self, subnet: &str) -> Option<SubnetDns> {
+        self.subnet_dns.read().unwrap().get(subnet).cloned()
+    }
+
+    /// Check that the DNS zones used by `dns` exist
+    pub async fn verify_subnet_dns(&self, dns: &SubnetDns) -> Result<()> {
+        if let Some((plugin, zone)) = dns.forward() {
+            self.get_dns_plugin(plugin)?
+                .verify_zone(zone)
+                .await
+                .with_context(|| format!("DNS zone '{}' not available", zone))?;
+        }
+
+        if let Some(plugin) = &dns.reversedns {
+            let plugin = self.get_dns_plugin(plugin)?;
+            let zone = plugin.reverse_zone(&dns.cidr);
+            plugin
+                .verify_zone(&zone)
+                .await
+                .with_context(|| format!("Reverse DNS zone '{}' not available", zone))?;
+        }
+
+        Ok(())
+    }
+
+    /// Register the DNS records of an allocation
+    async fn add_dns_records(&self, allocation: &IpAllocation) -> Result<()> {
+        let (dns, hostname) = match (self.subnet_dns(&allocation.subnet), &allocation.hostname) {
+            (Some(dns), Some(hostname)) => (dns, hostname),
+            _ => return Ok(()),
+        };
+
+        if let Some((plugin, zone)) = dns.forward() {
+            self.get_dns_plugin(plugin)?
+                .add_a_record(zone, hostname, &allocation.ip)
+                .await?;
+        }
+
+        if let Some(plugin) = &dns.reversedns {
+            let plugin = self.get_dns_plugin(plugin)?;
+            plugin
+                .add_ptr_record(
+                    &plugin.reverse_zone(&dns.cidr),
+                    &dns.fqdn(hostname),
+                    &allocation.ip,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the DNS records of an allocation
+    async fn del_dns_records(&self, allocation: &IpAllocation) -> Result<()> {
+        let (dns, hostname) = match (self.subnet_dns(&allocation.subnet), &allocation.hostname) {
+            (Some(dns), Some(hostname)) => (dns, hostname),
+            _ => return Ok(()),
+        };
+
+        if let Some((plugin, zone)) = dns.forward() {
+            self.get_dns_plugin(plugin)?
+                .del_a_record(zone, hostname, &allocation.ip)
+                .await?;
+        }
+
+        if let Some(plugin) = &dns.reversedns {
+            let plugin = self.get_dns_plugin(plugin)?;
+            plugin
+                .del_ptr_record(&plugin.reverse_zone(&dns.cidr), &allocation.ip)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Move the DNS records of an allocation from `old` to `new`
+    async fn update_dns_records(&self, old: &IpAllocation, new: &IpAllocation) -> Result<()> {
+        let dns = match self.subnet_dns(&new.subnet) {
+            Some(dns) => dns,
+            None => return Ok(()),
+        };
+
+        if old.hostname == new.hostname {
+            if old.ip == new.ip {
+                return Ok(());
+            }
+
+            if let (Some((plugin, zone)), Some(hostname)) = (dns.forward(), &new.hostname) {
+                self.get_dns_plugin(plugin)?
+                    .update_a_record(zone, hostname, &old.ip, &new.ip)
+                    .await?;
+            }
+
+            if let Some(plugin) = &dns.reversedns {
+                let plugin = self.get_dns_plugin(plugin)?;
+                let reverse_zone = plugin.reverse_zone(&dns.cidr);
+                plugin.del_ptr_record(&reverse_zone, &old.ip).await?;
+                if let Some(hostname) = &new.hostname {
+                    plugin
+                        .add_ptr_record(&reverse_zone, &dns.fqdn(hostname), &new.ip)
+                        .await?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        self.del_dns_records(old).await?;
+        self.add_dns_records(new).await
+    }
+
     /// Allocate IP using specified or default plugin
     pub async fn allocate_ip(
         &self,
@@ -90,7 +268,24 @@ impl IpamManager {
             self.get_default_plugin()?
         };
 
-        plugin.allocate_ip(request).await
+        let allocation = plugin.allocate_ip(request).await?;
+
+        // Without its records the allocation is of no use, undo it
+        if let Err(e) = self.add_dns_records(&allocation).await {
+            if let Err(release) = plugin.release_ip(&allocation.subnet, &allocation.ip).await {
+                log::warn!(
+                    "Failed to release {} after DNS error: {}",
+                    allocation.ip,
+                    release
+                );
+            }
+            return Err(e.context(format!(
+                "Failed to register DNS records of {}",
+                allocation.ip
+            )));
+        }
+
+        Ok(allocation)
     }
 
     /// Release IP using specified or default plugin
@@ -106,6 +301,15 @@ impl IpamManager {
             self.get_default_plugin()?
         };
 
+        // Records first, so a DNS failure leaves the allocation to retry with
+        if self.subnet_dns(subnet).is_some() {
+            if let Some(allocation) = plugin.get_ip(subnet, ip).await? {
+                self.del_dns_records(&allocation)
+                    .await
+                    .with_context(|| format!("Failed to remove DNS records of {}", ip))?;
+            }
+        }
+
         plugin.release_ip(subnet, ip).await
     }
 
@@ -123,7 +327,33 @@ impl IpamManager {
             self.get_default_plugin()?
         };
 
-        plugin.update_ip(subnet, ip, allocation).await
+        let old = match self.subnet_dns(subnet) {
+            Some(_) => plugin.get_ip(subnet, ip).await?,
+            None => None,
+        };
+
+        // Records first, so a DNS failure leaves the allocation untouched
+        if let Some(old) = &old {
+            self.update_dns_records(old, allocation)
+                .await
+                .with_context(|| format!("Failed to update DNS records of {}", ip))?;
+        }
+
+        if let Err(e) = plugin.update_ip(subnet, ip, allocation).await {
+            // The allocation did not change, move the records back
+            if let Some(old) = &old {
+                if let Err(restore) = self.update_dns_records(allocation, old).await {
+                    log::warn!(
+                        "Failed to restore DNS records of {} after IPAM error: {}",
+                        ip,
+                        restore
+                    );
+                }
+            }
+            return Err(e);
+        }
+
+        Ok(())
     }
 
     /// Get IP allocation using specified or default plugin
@@ -165,7 +395,15 @@ impl IpamManager {
             self.get_default_plugin()?
         };
 
-        plugin.add_subnet(subnet).await
+        let dns = SubnetDns::from_subnet(&subnet.config);
+        if let Some(dns) = &dns {
+            self.verify_subnet_dns(dns).await?;
+        }
+
+        plugin.add_subnet(subnet).await?;
+        self.set_subnet_dns(subnet.name(), dns);
+
+        Ok(())
     }
 
     /// Remove subnet from specified or default plugin
@@ -176,7 +414,10 @@ impl IpamManager {
             self.get_default_plugin()?
         };
 
-        plugin.remove_subnet(subnet_name).await
+        plugin.remove_subnet(subnet_name).await?;
+        self.set_subnet_dns(subnet_name, None);
+
+        Ok(())
     }
 
     /// Get next free IP using specified or default plugin
@@ -4,8 +4,14 @@
 
 pub mod config;
 pub mod controller;
+pub mod dhcp;
+pub mod dns;
+pub mod executor;
+pub mod fabric;
 pub mod ipam;
 pub mod ipam_manager;
+pub mod reconciler;
+pub mod status;
 pub mod subnet;
 pub mod vnet;
 pub mod zone;
@@ -13,10 +19,29 @@ pub mod zone;
 #[cfg(test)]
 mod tests;
 
-pub use config::SdnConfiguration;
+pub use config::{BuiltinDrivers, DriverRegistry, SdnConfiguration};
 pub use controller::{Controller, ControllerType};
+pub use dhcp::DhcpPlugin;
+pub use dns::{DnsConfig, DnsPlugin, DnsType, SubnetDns};
+pub use executor::{
+    CommandOutput, DryRunExecutor, RealExecutor, RecordingExecutor, SystemCommand, SystemExecutor,
+};
+pub use fabric::{
+    generate_fabric_config, resolve_fabric_vteps, FabricConfig, FabricInterface, FabricNodeConfig,
+    FabricProtocol,
+};
 pub use ipam::{IpAllocation, IpAllocationRequest, IpamConfig, IpamError, IpamPlugin, IpamType};
 pub use ipam_manager::IpamManager;
+pub use reconciler::{ReconcileAction, ReconcileReport, SdnReconciler, ZoneResolver};
+pub use status::{
+    aggregate_status, ClusterSdnStatus, ClusterZoneStatus, DeploymentStatus, NodeSdnStatus,
+    SdnStatusCollector, SdnStatusStore, VNetNodeStatus, ZoneNodeStatus,
+};
 pub use subnet::{DhcpConfig, Subnet, SubnetConfig, SubnetStatus, SubnetType};
-pub use vnet::{VNet, VNetConfig, VNetStatus};
-pub use zone::{Zone, ZoneConfig, ZoneType};
+pub use vnet::{
+    FirewallAction, FirewallAlias, FirewallDirection, FirewallIpSet, FirewallRule, VNet,
+    VNetConfig, VNetFirewall, VNetStatus,
+};
+pub use zone::{
+    ObservedDevice, Zone, ZoneConfig, ZoneObservedState, ZoneRenderContext, ZoneStatus, ZoneType,
+};
@@ -0,0 +1,414 @@
+//! SDN device reconciliation
+//!
+//! Devices created by SDN zones are marked with an interface alias of the
+//! form `pve-sdn:<zone>:<fingerprint>`, set by `apply_config` or through the
+//! `alias` attribute of the generated interfaces stanzas. ifupdown2 creates
+//! the devices of the generated stanzas; the reconciler compares the desired
+//! [`SdnConfiguration`] against the links present on the node and updates or
+//! garbage-collects devices accordingly. Devices of a changed zone are
+//! updated in place, so guest ports enslaved to them stay attached. Links
+//! without an SDN alias are never modified or removed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::executor::SystemExecutor;
+use crate::fabric::resolve_fabric_vteps;
+use crate::zone::{ObservedDevice, Zone, ZoneConfig, ZoneObservedState};
+use crate::SdnConfiguration;
+
+/// Alias prefix marking SDN-owned devices
+pub const OWNER_ALIAS_PREFIX: &str = "pve-sdn:";
+
+/// FNV-1a hash of `data`
+///
+/// Unlike `DefaultHasher` the result is the same across releases, so it can
+/// end up in device names and aliases.
+pub fn stable_hash(data: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Stable fingerprint of a zone configuration
+pub fn config_fingerprint(config: &ZoneConfig) -> String {
+    // serde_json::Value objects are sorted maps, so the rendering is stable
+    // regardless of HashMap iteration order
+    let rendered = serde_json::to_value(config)
+        .map(|value| value.to_string())
+        .unwrap_or_default();
+
+    format!("{:016x}", stable_hash(&rendered))
+}
+
+/// Ownership alias for devices created for `config`
+pub fn owner_alias(config: &ZoneConfig) -> String {
+    format!(
+        "{}{}:{}",
+        OWNER_ALIAS_PREFIX,
+        config.zone,
+        config_fingerprint(config)
+    )
+}
+
+/// Interfaces stanza attribute setting the ownership alias for `config`
+///
+/// Zones add it to the stanzas of the devices they own, so devices created
+/// by ifupdown2 carry the same alias as those created by `apply_config`.
+pub fn owner_alias_attribute(config: &ZoneConfig) -> String {
+    format!("\talias {}\n", owner_alias(config))
+}
+
+/// Parse an ownership alias into zone name and fingerprint
+pub fn alias_owner(alias: &str) -> Option<(&str, &str)> {
+    alias.strip_prefix(OWNER_ALIAS_PREFIX)?.rsplit_once(':')
+}
+
+/// Mark `device` as owned by the zone described by `config`
+pub async fn mark_owned(
+    executor: &dyn SystemExecutor,
+    device: &str,
+    config: &ZoneConfig,
+) -> Result<()> {
+    let alias = owner_alias(config);
+    let output = executor
+        .execute("ip", &["link", "set", "dev", device, "alias", &alias])
+        .await?;
+
+    if !output.is_success() {
+        anyhow::bail!(
+            "Failed to mark '{}' as owned by zone '{}': {}",
+            device,
+            config.zone,
+            output.stderr.trim()
+        );
+    }
+
+    Ok(())
+}
+
+fn parse_link(link: &serde_json::Value) -> Option<ObservedDevice> {
+    let name = link.get("ifname")?.as_str()?;
+    let kind = link
+        .get("linkinfo")
+        .and_then(|info| info.get("info_kind"))
+        .and_then(|kind| kind.as_str())
+        .unwrap_or("");
+    let up = link
+        .get("flags")
+        .and_then(|flags| flags.as_array())
+        .map(|flags| flags.iter().any(|flag| flag.as_str() == Some("UP")))
+        .unwrap_or(false);
+
+    Some(ObservedDevice {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        present: true,
+        up,
+        master: link
+            .get("master")
+            .and_then(|m| m.as_str())
+            .map(str::to_string),
+        mtu: link.get("mtu").and_then(|m| m.as_u64()).map(|m| m as u32),
+        alias: link
+            .get("ifalias")
+            .and_then(|a| a.as_str())
+            .map(str::to_string),
+    })
+}
+
+fn parse_links(stdout: &str) -> Result<Vec<ObservedDevice>> {
+    let links: Vec<serde_json::Value> =
+        serde_json::from_str(stdout).context("Failed to parse 'ip -j link' output")?;
+    Ok(links.iter().filter_map(parse_link).collect())
+}
+
+/// All links present on the node
+pub async fn list_links(executor: &dyn SystemExecutor) -> Result<Vec<ObservedDevice>> {
+    let output = executor.query("ip", &["-j", "-d", "link", "show"]).await?;
+
+    if !output.is_success() {
+        anyhow::bail!("Failed to list links: {}", output.stderr.trim());
+    }
+
+    parse_links(&output.stdout)
+}
+
+/// Current state of a single device expected to be of `kind`
+pub async fn observe_device(
+    executor: &dyn SystemExecutor,
+    name: &str,
+    kind: &str,
+) -> Result<ObservedDevice> {
+    let output = executor
+        .query("ip", &["-j", "-d", "link", "show", "dev", name])
+        .await?;
+
+    if !output.is_success() {
+        return Ok(ObservedDevice::absent(name, kind));
+    }
+
+    let mut device = parse_links(&output.stdout)?
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| ObservedDevice::absent(name, kind));
+    device.kind = kind.to_string();
+    Ok(device)
+}
+
+/// Delete a device
+pub async fn delete_device(executor: &dyn SystemExecutor, device: &str) -> Result<()> {
+    let output = executor
+        .execute("ip", &["link", "delete", "dev", device])
+        .await?;
+
+    if !output.is_success() {
+        anyhow::bail!("Failed to delete '{}': {}", device, output.stderr.trim());
+    }
+
+    Ok(())
+}
+
+/// Delete the present devices of `state` that are owned by its zone
+///
+/// Devices are removed in reverse order, so bridges go before the ports
+/// enslaved to them. Devices without the zone's ownership alias are skipped.
+pub async fn remove_owned_devices(
+    executor: &dyn SystemExecutor,
+    state: &ZoneObservedState,
+) -> Result<Vec<String>> {
+    let mut removed = Vec::new();
+
+    for device in state.devices.iter().rev().filter(|d| d.present) {
+        let owned = device
+            .alias
+            .as_deref()
+            .and_then(alias_owner)
+            .map(|(zone, _)| zone == state.zone)
+            .unwrap_or(false);
+
+        if !owned {
+            log::warn!(
+                "Not removing '{}' for zone '{}', it is not SDN-owned",
+                device.name,
+                state.zone
+            );
+            continue;
+        }
+
+        delete_device(executor, &device.name).await?;
+        removed.push(device.name.clone());
+    }
+
+    Ok(removed)
+}
+
+/// Creates zone drivers for the reconciler
+pub type ZoneResolver = Arc<dyn Fn(&ZoneConfig) -> Result<Box<dyn Zone>> + Send + Sync>;
+
+/// Single reconciliation step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ReconcileAction {
+    /// Zone configuration changed, update its devices in place
+    Update { zone: String, devices: Vec<String> },
+    /// Device belongs to a zone that no longer exists on this node
+    Remove { zone: String, device: String },
+}
+
+/// Failed reconciliation step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconcileFailure {
+    /// Step that failed
+    pub action: ReconcileAction,
+    /// Error message
+    pub error: String,
+}
+
+/// Outcome of a reconciliation run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    /// Steps executed successfully
+    pub applied: Vec<ReconcileAction>,
+    /// Steps that failed
+    pub failed: Vec<ReconcileFailure>,
+}
+
+/// Reconciles desired SDN zones with the devices on the local node
+pub struct SdnReconciler {
+    executor: Arc<dyn SystemExecutor>,
+    resolver: ZoneResolver,
+    node: Option<String>,
+}
+
+impl SdnReconciler {
+    /// Create reconciler using `resolver` to instantiate zone drivers
+    pub fn new(executor: Arc<dyn SystemExecutor>, resolver: ZoneResolver) -> Self {
+        Self {
+            executor,
+            resolver,
+            node: None,
+        }
+    }
+
+    /// Only reconcile zones configured for `node`
+    pub fn with_node(mut self, node: &str) -> Self {
+        self.node = Some(node.to_string());
+        self
+    }
+
+    fn is_local(&self, config: &ZoneConfig) -> bool {
+        match (&self.node, &config.nodes) {
+            (Some(node), Some(nodes)) => nodes.iter().any(|n| n == node),
+            _ => true,
+        }
+    }
+
+    /// Fill in fabric VTEP addresses for the local node
+    fn resolve(&self, desired: &SdnConfiguration) -> Result<SdnConfiguration> {
+        match &self.node {
+            Some(node) => resolve_fabric_vteps(desired, node),
+            None => Ok(desired.clone()),
+        }
+    }
+
+    /// Compute the steps needed to reach `desired`
+    pub async fn plan(&self, desired: &SdnConfiguration) -> Result<Vec<ReconcileAction>> {
+        self.plan_resolved(&self.resolve(desired)?).await
+    }
+
+    async fn plan_resolved(&self, desired: &SdnConfiguration) -> Result<Vec<ReconcileAction>> {
+        let mut owned: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for link in list_links(self.executor.as_ref()).await? {
+            if let Some((zone, fingerprint)) = link.alias.as_deref().and_then(alias_owner) {
+                owned
+                    .entry(zone.to_string())
+                    .or_default()
+                    .push((link.name.clone(), fingerprint.to_string()));
+            }
+        }
+
+        let mut actions = Vec::new();
+        let mut local_zones = BTreeSet::new();
+
+        let mut names: Vec<&String> = desired.zones.keys().collect();
+        names.sort();
+
+        for name in names {
+            let config = &desired.zones[name];
+            if !self.is_local(config) {
+                continue;
+            }
+            local_zones.insert(name.clone());
+
+            let fingerprint = config_fingerprint(config);
+            let stale: Vec<String> = owned
+                .get(name)
+                .map(|devices| {
+                    devices
+                        .iter()
+                        .filter(|(_, fp)| fp != &fingerprint)
+                        .map(|(device, _)| device.clone())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if !stale.is_empty() {
+                actions.push(ReconcileAction::Update {
+                    zone: name.clone(),
+                    devices: stale,
+                });
+            }
+        }
+
+        for (zone, devices) in owned {
+            if local_zones.contains(&zone) {
+                continue;
+            }
+            for (device, _) in devices {
+                actions.push(ReconcileAction::Remove {
+                    zone: zone.clone(),
+                    device,
+                });
+            }
+        }
+
+        Ok(actions)
+    }
+
+    /// Bring the node in line with `desired`
+    ///
+    /// Failing steps are reported and do not stop the remaining ones.
+    pub async fn reconcile(&self, desired: &SdnConfiguration) -> Result<ReconcileReport> {
+        let mut report = ReconcileReport::default();
+        let desired = &self.resolve(desired)?;
+
+        for action in self.plan_resolved(desired).await? {
+            match self.execute(desired, &action).await {
+                Ok(()) => report.applied.push(action),
+                Err(e) => {
+                    log::error!("SDN reconcile step {:?} failed: {:#}", action, e);
+                    report.failed.push(ReconcileFailure {
+                        action,
+                        error: format!("{:#}", e),
+                    });
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn execute(&self, desired: &SdnConfiguration, action: &ReconcileAction) -> Result<()> {
+        match action {
+            ReconcileAction::Update { zone, devices } => {
+                self.update_zone(desired, zone, devices).await
+            }
+            ReconcileAction::Remove { device, .. } => {
+                delete_device(self.executor.as_ref(), device).await
+            }
+        }
+    }
+
+    /// Update the devices of a changed zone in place
+    ///
+    /// Devices the zone still expects are kept and marked with the new
+    /// configuration; only devices it no longer expects, e.g. a VXLAN device
+    /// named after a changed VNI, are removed.
+    async fn update_zone(
+        &self,
+        desired: &SdnConfiguration,
+        name: &str,
+        devices: &[String],
+    ) -> Result<()> {
+        let config = desired
+            .zones
+            .get(name)
+            .with_context(|| format!("Zone '{}' not found", name))?;
+        let zone = (self.resolver)(config)?;
+        zone.update_config(config).await?;
+
+        let expected: BTreeSet<String> = zone
+            .observed_state(config)
+            .await?
+            .devices
+            .into_iter()
+            .map(|device| device.name)
+            .collect();
+
+        for device in devices {
+            if expected.contains(device) {
+                mark_owned(self.executor.as_ref(), device, config).await?;
+            } else {
+                delete_device(self.executor.as_ref(), device).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+//! Per-node SDN deployment status
+//!
+//! [`SdnStatusCollector`] checks whether the zones and vnets of the running
+//! configuration are instantiated on the local node: the applied version
+//! matches the running one, the devices of every zone and vnet exist and
+//! FRR knows the EVPN VNIs. Each node publishes its [`NodeSdnStatus`] to
+//! pmxcfs, where [`SdnStatusStore`] reads the files of all nodes back for
+//! [`aggregate_status`]. The store also keeps the running configuration,
+//! whose version all nodes compare their applied version against.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::executor::SystemExecutor;
+use crate::fabric::resolve_fabric_vteps;
+use crate::reconciler::ZoneResolver;
+use crate::zone::{ZoneConfig, ZoneObservedState, ZoneType};
+use crate::{SdnConfiguration, VNetConfig};
+
+/// Default pmxcfs mount point
+pub const DEFAULT_PMXCFS_PATH: &str = "/etc/pve";
+
+/// Name of the status file in the pmxcfs directory of each node
+pub const STATUS_FILE_NAME: &str = "sdn-status.json";
+
+/// Running configuration of the cluster, relative to the pmxcfs mount point
+pub const RUNNING_CONFIG_FILE: &str = "sdn/.running-config";
+
+/// Deployment status of a zone or vnet on one node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeploymentStatus {
+    /// Instantiated as configured
+    Available,
+    /// The running configuration is not applied on the node yet
+    Pending,
+    /// Applied, but devices or FRR state are missing
+    Error,
+    /// Not configured for the node
+    NotDeployed,
+}
+
+impl DeploymentStatus {
+    /// Rank used to combine the status of several nodes, the worst wins
+    fn severity(self) -> u8 {
+        match self {
+            DeploymentStatus::NotDeployed => 0,
+            DeploymentStatus::Available => 1,
+            DeploymentStatus::Pending => 2,
+            DeploymentStatus::Error => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for DeploymentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeploymentStatus::Available => write!(f, "available"),
+            DeploymentStatus::Pending => write!(f, "pending"),
+            DeploymentStatus::Error => write!(f, "error"),
+            DeploymentStatus::NotDeployed => write!(f, "not-deployed"),
+        }
+    }
+}
+
+/// Status of a zone on one node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneNodeStatus {
+    pub zone: String,
+    pub status: DeploymentStatus,
+    /// Reason for a status other than available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Status of a vnet on one node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VNetNodeStatus {
+    pub vnet: String,
+    pub zone: String,
+    pub status: DeploymentStatus,
+    /// Reason for a status other than available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// SDN status of a node, as published to pmxcfs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeSdnStatus {
+    pub node: String,
+    /// Running configuration version the status was computed against
+    pub version: u64,
+    /// Configuration version applied on the node, `None` if never applied
+    pub applied_version: Option<u64>,
+    /// Unix time the status was computed at
+    pub timestamp: i64,
+    pub zones: Vec<ZoneNodeStatus>,
+    pub vnets: Vec<VNetNodeStatus>,
+}
+
+/// Status of a zone across the cluster
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterZoneStatus {
+    pub zone: String,
+    /// Worst status of all nodes, not deployed if no node runs the zone
+    pub status: DeploymentStatus,
+    /// Status on each node
+    pub nodes: BTreeMap<String, DeploymentStatus>,
+}
+
+/// SDN status of the cluster
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterSdnStatus {
+    /// Running configuration version
+    pub version: u64,
+    pub zones: Vec<ClusterZoneStatus>,
+    /// Status files published by the nodes
+    pub nodes: Vec<NodeSdnStatus>,
+}
+
+/// Computes the SDN status of the local node
+pub struct SdnStatusCollector {
+    executor: Arc<dyn SystemExecutor>,
+    resolver: ZoneResolver,
+    node: String,
+}
+
+impl SdnStatusCollector {
+    /// Create collector for `node` using `resolver` to instantiate zone
+    /// drivers
+    pub fn new(executor: Arc<dyn SystemExecutor>, resolver: ZoneResolver, node: &str) -> Self {
+        Self {
+            executor,
+            resolver,
+            node: node.to_string(),
+        }
+    }
+
+    /// Status of all zones and vnets of `config` on the local node
+    ///
+    /// `applied_version` is the configuration version last applied on the
+    /// node. Zones are only checked against the system once it matches the
+    /// running version, before that they are pending.
+    pub async fn collect(
+        &self,
+        config: &SdnConfiguration,
+        applied_version: Option<u64>,
+    ) -> Result<NodeSdnStatus> {
+        let config = &resolve_fabric_vteps(config, &self.node)?;
+        let version = config.running_version();
+
+        let pending = match applied_version {
+            Some(applied) if applied == version => None,
+            Some(applied) => Some(format!(
+                "Applied version {} differs from running version {}",
+                applied, version
+            )),
+            None => Some("Configuration has not been applied".to_string()),
+        };
+
+        let mut names: Vec<&String> = config.zones.keys().collect();
+        names.sort();
+
+        // FRR is queried once for all EVPN zones of the node
+        let evpn = config
+            .zones
+            .values()
+            .any(|zone| zone.zone_type == ZoneType::Evpn && self.is_local(zone));
+        let frr_vnis = match pending.is_none() && evpn {
+            true => Some(self.frr_vnis().await.map_err(|e| format!("{:#}", e))),
+            false => None,
+        };
+
+        let mut vnets: Vec<&VNetConfig> = config.vnets.values().collect();
+        vnets.sort_by(|a, b| a.vnet.cmp(&b.vnet));
+
+        let mut status = NodeSdnStatus {
+            node: self.node.clone(),
+            version,
+            applied_version,
+            timestamp: chrono::Utc::now().timestamp(),
+            zones: Vec::new(),
+            vnets: Vec::new(),
+        };
+
+        for name in names {
+            let zone_config = &config.zones[name];
+            let zone_vnets = vnets.iter().filter(|vnet| &vnet.zone == name);
+
+            let (zone_status, message) = if !self.is_local(zone_config) {
+                (DeploymentStatus::NotDeployed, None)
+            } else if let Some(reason) = &pending {
+                (DeploymentStatus::Pending, Some(reason.clone()))
+            } else {
+                checked(self.check_zone(zone_config, frr_vnis.as_ref()).await)
+            };
+
+            status.zones.push(ZoneNodeStatus {
+                zone: name.clone(),
+                status: zone_status,
+                message: message.clone(),
+            });
+
+            for vnet in zone_vnets {
+                // A vnet cannot be available in a zone that is not
+                let (vnet_status, message) = match zone_status {
+                    DeploymentStatus::Available => {
+                        checked(self.check_vnet(zone_config, vnet, frr_vnis.as_ref()).await)
+                    }
+                    _ => (zone_status, message.clone()),
+                };
+
+                status.vnets.push(VNetNodeStatus {
+                    vnet: vnet.vnet.clone(),
+                    zone: name.clone(),
+                    status: vnet_status,
+                    message,
+                });
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn is_local(&self, config: &ZoneConfig) -> bool {
+        match &config.nodes {
+            Some(nodes) => nodes.iter().any(|n| n == &self.node),
+            None => true,
+        }
+    }
+
+    async fn check_zone(
+        &self,
+        config: &ZoneConfig,
+        frr_vnis: Option<&Result<BTreeSet<u32>, String>>,
+    ) -> Result<()> {
+        let zone = (self.resolver)(config)?;
+        zone.validate_config(config).await?;
+        check_devices(&zone.observed_state(config).await?)?;
+
+        if config.zone_type == ZoneType::Evpn {
+            if let Some(l3vni) = config.options.get("vrf-vxlan").and_then(|v| v.as_u64()) {
+                check_vni(frr_vnis, l3vni as u32)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_vnet(
+        &self,
+        config: &ZoneConfig,
+        vnet: &VNetConfig,
+        frr_vnis: Option<&Result<BTreeSet<u32>, String>>,
+    ) -> Result<()> {
+        let zone = (self.resolver)(config)?;
+        check_devices(&zone.observed_vnet_state(config, vnet).await?)?;
+
+        if config.zone_type == ZoneType::Evpn {
+            if let Some(vni) = vnet.vni() {
+                check_vni(frr_vnis, vni)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// VNIs known to zebra
+    async fn frr_vnis(&self) -> Result<BTreeSet<u32>> {
+        let output = self
+            .executor
+            .query("vtysh", &["-c", "show evpn vni json"])
+            .await?;
+
+        if !output.is_success() {
+            anyhow::bail!("Failed to query FRR VNIs: {}", output.stderr.trim());
+        }
+
+        let value: serde_json::Value = serde_json::from_str(&output.stdout)
+            .context("Failed to parse 'show evpn vni json' output")?;
+
+        Ok(value
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, entry)| {
+                entry
+                    .get("vni")
+                    .and_then(|vni| vni.as_u64())
+                    .or_else(|| key.parse().ok())
+            })
+            .map(|vni| vni as u32)
+            .collect())
+    }
+}
+
+fn checked(result: Result<()>) -> (DeploymentStatus, Option<String>) {
+    match result {
+        Ok(()) => (DeploymentStatus::Available, None),
+        Err(e) => (DeploymentStatus::Error, Some(format!("{:#}", e))),
+    }
+}
+
+fn check_devices(state: &ZoneObservedState) -> Result<()> {
+    let problems: Vec<String> = state
+        .devices
+        .iter()
+        .filter_map(|device| match (device.present, device.up) {
+            (false, _) => Some(format!("{} '{}' is missing", device.kind, device.name)),
+            (true, false) => Some(format!("{} '{}' is down", device.kind, device.name)),
+            (true, true) => None,
+        })
+        .collect();
+
+    if !problems.is_empty() {
+        anyhow::bail!("{}", problems.join(", "));
+    }
+
+    Ok(())
+}
+
+fn check_vni(frr_vnis: Option<&Result<BTreeSet<u32>, String>>, vni: u32) -> Result<()> {
+    match frr_vnis {
+        Some(Ok(vnis)) if !vnis.contains(&vni) => anyhow::bail!("VNI {} is not known to FRR", vni),
+        Some(Err(e)) => anyhow::bail!("{}", e),
+        _ => Ok(()),
+    }
+}
+
+/// Node status files in pmxcfs
+pub struct SdnStatusStore {
+    base_path: PathBuf,
+}
+
+impl SdnStatusStore {
+    /// Create store using the default pmxcfs mount point
+    pub fn new() -> Self {
+        Self::with_base_path(DEFAULT_PMXCFS_PATH)
+    }
+
+    /// Create store rooted at `base_path` instead of `/etc/pve`
+    pub fn with_base_path(base_path: impl Into<PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+        }
+    }
+
+    fn nodes_path(&self) -> PathBuf {
+        self.base_path.join("nodes")
+    }
+
+    /// Path of the status file of `node`
+    pub fn status_path(&self, node: &str) -> PathBuf {
+        self.nodes_path().join(node).join(STATUS_FILE_NAME)
+    }
+
+    /// Path of the running configuration
+    pub fn running_config_path(&self) -> PathBuf {
+        self.base_path.join(RUNNING_CONFIG_FILE)
+    }
+
+    /// Publish the status of its node
+    pub async fn publish(&self, status: &NodeSdnStatus) -> Result<()> {
+        write_atomic(
+            &self.status_path(&status.node),
+            &serde_json::to_vec_pretty(status)?,
+        )
+        .await
+    }
+
+    /// Publish `config` as the running configuration of the cluster
+    pub async fn write_running_config(&self, config: &SdnConfiguration) -> Result<()> {
+        write_atomic(
+            &self.running_config_path(),
+            &serde_json::to_vec_pretty(config)?,
+        )
+        .await
+    }
+
+    /// Running configuration of the cluster, `None` before the first reload
+    pub async fn read_running_config(&self) -> Result<Option<SdnConfiguration>> {
+        let path = self.running_config_path();
+        let content = match tokio::fs::read(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+            .map(Some)
+    }
+
+    /// Published status of `node`, `None` if it has not published one
+    pub async fn read(&self, node: &str) -> Result<Option<NodeSdnStatus>> {
+        let path = self.status_path(node);
+        let content = match tokio::fs::read(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+            .map(Some)
+    }
+
+    /// Published status of all nodes, sorted by node name
+    ///
+    /// Unreadable status files are skipped, a broken node must not hide the
+    /// status of the others.
+    pub async fn read_all(&self) -> Result<Vec<NodeSdnStatus>> {
+        let mut entries = match tokio::fs::read_dir(self.nodes_path()).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to read the pmxcfs nodes directory"),
+        };
+
+        let mut nodes = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(node) = entry.file_name().to_str() {
+                nodes.push(node.to_string());
+            }
+        }
+        nodes.sort();
+
+        let mut statuses = Vec::new();
+        for node in nodes {
+            match self.read(&node).await {
+                Ok(Some(status)) => statuses.push(status),
+                Ok(None) => {}
+                Err(e) => log::warn!("Ignoring SDN status of node '{}': {:#}", node, e),
+            }
+        }
+
+        Ok(statuses)
+    }
+}
+
+/// Replace `path` with `content`
+///
+/// Readers on other nodes must never see a partially written file.
+async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    tokio::fs::write(&tmp, content)
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+impl Default for SdnStatusStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Combine the published node status into the status of each zone
+///
+/// Nodes that reported an older configuration version count as pending for
+/// the zones configured on them, so do nodes a zone is pinned to that have
+/// not published a status at all.
+pub fn aggregate_status(config: &SdnConfiguration, nodes: Vec<NodeSdnStatus>) -> ClusterSdnStatus {
+    let version = config.running_version();
+
+    let mut names: Vec<&String> = config.zones.keys().collect();
+    names.sort();
+
+    let zones = names
+        .into_iter()
+        .map(|name| {
+            let zone_config = &config.zones[name];
+            let mut per_node = BTreeMap::new();
+
+            for node in &nodes {
+                let local = match &zone_config.nodes {
+                    Some(zone_nodes) => zone_nodes.contains(&node.node),
+                    None => true,
+                };
+                let reported = node
+                    .zones
+                    .iter()
+                    .find(|zone| &zone.zone == name && node.version == version);
+
+                let status = match (local, reported) {
+                    (false, _) => DeploymentStatus::NotDeployed,
+                    (true, Some(zone)) => zone.status,
+                    (true, None) => DeploymentStatus::Pending,
+                };
+                per_node.insert(node.node.clone(), status);
+            }
+
+            // Nodes the zone is pinned to, but without a status file
+            for node in zone_config.nodes.iter().flatten() {
+                per_node
+                    .entry(node.clone())
+                    .or_insert(DeploymentStatus::Pending);
+            }
+
+            let status = per_node
+                .values()
+                .copied()
+                .max_by_key(|status| status.severity())
+                .unwrap_or(DeploymentStatus::NotDeployed);
+
+            ClusterZoneStatus {
+                zone: name.clone(),
+                status,
+                nodes: per_node,
+            }
+        })
+        .collect();
+
+    ClusterSdnStatus {
+        version,
+        zones,
+        nodes,
+    }
+}
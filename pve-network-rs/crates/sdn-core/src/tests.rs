@@ -1,7 +1,9 @@
 //! SDN Core tests
 
+use crate::controller::ControllerConfig;
 use crate::*;
 use ipnet::IpNet;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_zone_configuration() {
@@ -122,3 +124,538 @@ async fn test_sdn_configuration_serialization() {
     assert_eq!(config.vnets.len(), parsed_config.vnets.len());
     assert_eq!(config.subnets.len(), parsed_config.subnets.len());
 }
+
+#[tokio::test]
+async fn test_recording_executor() {
+    let executor = RecordingExecutor::new();
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+
+    let output = executor
+        .query("ip", &["link", "show", "vxlan100"])
+        .await
+        .unwrap();
+    assert!(!output.is_success());
+
+    let output = executor
+        .execute("ip", &["link", "set", "vxlan100", "up"])
+        .await
+        .unwrap();
+    assert!(output.is_success());
+
+    executor.assert_commands(&["ip link show vxlan100", "ip link set vxlan100 up"]);
+}
+
+#[tokio::test]
+async fn test_dry_run_executor() {
+    let inspector = Arc::new(RecordingExecutor::new());
+    let executor = DryRunExecutor::with_inspector(inspector.clone());
+
+    // Queries are forwarded, changes only end up in the plan
+    assert!(executor
+        .query("ip", &["link", "show", "vmbr0"])
+        .await
+        .unwrap()
+        .is_success());
+    executor
+        .execute("ip", &["link", "add", "name", "vmbr1", "type", "bridge"])
+        .await
+        .unwrap();
+
+    inspector.assert_commands(&["ip link show vmbr0"]);
+    assert_eq!(
+        executor.take_plan(),
+        vec![SystemCommand::new(
+            "ip",
+            &["link", "add", "name", "vmbr1", "type", "bridge"]
+        )]
+    );
+    assert!(executor.plan().is_empty());
+
+    // Without inspector nothing is assumed to exist
+    let executor = DryRunExecutor::new();
+    assert!(!executor
+        .query("ip", &["link", "show", "vmbr0"])
+        .await
+        .unwrap()
+        .is_success());
+}
+
+/// Zone owning a single bridge named after the zone
+struct BridgeZone {
+    name: String,
+    executor: Arc<RecordingExecutor>,
+}
+
+#[async_trait::async_trait]
+impl Zone for BridgeZone {
+    fn zone_type(&self) -> ZoneType {
+        ZoneType::Simple
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self, _config: &ZoneConfig) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn apply_config(&self, config: &ZoneConfig) -> anyhow::Result<()> {
+        let bridge = format!("br{}", self.name);
+        self.executor
+            .execute("ip", &["link", "add", "name", &bridge, "type", "bridge"])
+            .await?;
+        reconciler::mark_owned(self.executor.as_ref(), &bridge, config).await
+    }
+
+    async fn generate_config(
+        &self,
+        _config: &ZoneConfig,
+    ) -> anyhow::Result<std::collections::HashMap<String, String>> {
+        Ok(Default::default())
+    }
+
+    async fn observed_state(&self, _config: &ZoneConfig) -> anyhow::Result<ZoneObservedState> {
+        let bridge = format!("br{}", self.name);
+        let mut state = ZoneObservedState::new(&self.name);
+        state
+            .devices
+            .push(reconciler::observe_device(self.executor.as_ref(), &bridge, "bridge").await?);
+        Ok(state)
+    }
+}
+
+#[tokio::test]
+async fn test_reconciler_updates_changed_zone_in_place() {
+    let executor = Arc::new(RecordingExecutor::new());
+
+    let mut old = ZoneConfig::new(ZoneType::Simple, "z1".to_string());
+    old.mtu = Some(1500);
+    let mut new = old.clone();
+    new.mtu = Some(9000);
+
+    let alias = reconciler::owner_alias(&old);
+    assert_eq!(
+        reconciler::alias_owner(&alias),
+        Some(("z1", reconciler::config_fingerprint(&old).as_str()))
+    );
+    assert_ne!(alias, reconciler::owner_alias(&new));
+    assert_eq!(reconciler::alias_owner("uplink"), None);
+
+    // vxlan_z1 was created for the old configuration and is no longer
+    // expected by the zone
+    let links = serde_json::json!([
+        { "ifname": "brz1", "flags": ["UP"], "ifalias": alias },
+        { "ifname": "vxlan_z1", "flags": ["UP"], "ifalias": alias },
+        { "ifname": "eno1", "flags": ["UP"], "ifalias": "uplink" },
+    ]);
+    executor.respond(
+        "ip -j -d link show",
+        CommandOutput::success(&links.to_string()),
+    );
+
+    let zone_executor = executor.clone();
+    let resolver: ZoneResolver = Arc::new(move |config: &ZoneConfig| {
+        Ok(Box::new(BridgeZone {
+            name: config.zone.clone(),
+            executor: zone_executor.clone(),
+        }) as Box<dyn Zone>)
+    });
+    let reconciler = SdnReconciler::new(executor.clone(), resolver).with_node("node1");
+
+    let mut desired = SdnConfiguration::new();
+    desired.zones.insert("z1".to_string(), new.clone());
+
+    // Zones of other nodes are ignored and their devices collected
+    let mut remote = ZoneConfig::new(ZoneType::Simple, "z2".to_string());
+    remote.nodes = Some(vec!["node2".to_string()]);
+    desired.zones.insert("z2".to_string(), remote);
+
+    let report = reconciler.reconcile(&desired).await.unwrap();
+    assert!(report.failed.is_empty());
+    assert_eq!(
+        report.applied,
+        vec![ReconcileAction::Update {
+            zone: "z1".to_string(),
+            devices: vec!["brz1".to_string(), "vxlan_z1".to_string()],
+        }]
+    );
+
+    // The bridge is kept, so enslaved guest ports stay attached
+    let commands = executor.command_lines();
+    assert!(!commands.contains(&"ip link delete dev brz1".to_string()));
+    assert!(commands.contains(&"ip link delete dev vxlan_z1".to_string()));
+    assert!(commands.contains(&format!(
+        "ip link set dev brz1 alias {}",
+        reconciler::owner_alias(&new)
+    )));
+    assert!(!commands.iter().any(|c| c.contains("eno1")));
+}
+
+fn three_node_fabric(protocol: FabricProtocol) -> FabricConfig {
+    let mut fabric = FabricConfig::new("f1".to_string(), protocol);
+
+    for (node, loopback, link) in [
+        ("node1", "10.10.10.1", "10.0.12.0/31"),
+        ("node2", "10.10.10.2", "10.0.12.1/31"),
+        ("node3", "10.10.10.3", "10.0.13.1/31"),
+    ] {
+        fabric.nodes.push(FabricNodeConfig {
+            node: node.to_string(),
+            loopback: loopback.parse().unwrap(),
+            interfaces: vec![
+                FabricInterface {
+                    name: "ens19".to_string(),
+                    ip: Some(link.parse().unwrap()),
+                },
+                FabricInterface {
+                    name: "ens20".to_string(),
+                    ip: None,
+                },
+            ],
+        });
+    }
+
+    fabric
+}
+
+#[test]
+fn test_fabric_validation() {
+    let fabric = three_node_fabric(FabricProtocol::Openfabric);
+    assert!(fabric.validate().is_ok());
+
+    let mut invalid = fabric.clone();
+    invalid.fabric = "toolongname".to_string();
+    assert!(invalid.validate().is_err());
+
+    let mut invalid = fabric.clone();
+    invalid.nodes[1].loopback = invalid.nodes[0].loopback;
+    assert!(invalid.validate().is_err());
+
+    let mut invalid = fabric.clone();
+    invalid.protocol = FabricProtocol::Ospf;
+    invalid.nodes[0].loopback = "fd00::1".parse().unwrap();
+    assert!(invalid.validate().is_err());
+
+    let mut invalid = fabric;
+    invalid.nodes[0].interfaces[1].name = "ens19".to_string();
+    assert!(invalid.validate().is_err());
+}
+
+#[test]
+fn test_single_ospf_fabric_per_node() {
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_fabric(three_node_fabric(FabricProtocol::Ospf))
+        .unwrap();
+
+    // OpenFabric fabrics can share nodes with the OSPF fabric
+    let mut openfabric = three_node_fabric(FabricProtocol::Openfabric);
+    openfabric.fabric = "f2".to_string();
+    sdn.add_fabric(openfabric).unwrap();
+
+    // A second OSPF instance on the same nodes is rejected
+    let mut ospf = three_node_fabric(FabricProtocol::Ospf);
+    ospf.fabric = "f3".to_string();
+    let err = sdn.add_fabric(ospf.clone()).unwrap_err().to_string();
+    assert!(err.contains("OSPF fabrics"));
+
+    sdn.fabrics.insert("f3".to_string(), ospf);
+    assert!(sdn.validate().is_err());
+
+    // Replacing the existing OSPF fabric is fine
+    sdn.fabrics.remove("f3");
+    sdn.add_fabric(three_node_fabric(FabricProtocol::Ospf))
+        .unwrap();
+}
+
+#[test]
+fn test_openfabric_generation() {
+    let mut fabric = three_node_fabric(FabricProtocol::Openfabric);
+    fabric.hello_interval = Some(1);
+
+    assert_eq!(
+        fabric::system_id("10.10.10.2".parse().unwrap()),
+        "0100.1001.0002"
+    );
+
+    let configs = generate_fabric_config(&fabric, "node2").unwrap();
+
+    let interfaces = &configs["interfaces"];
+    assert!(interfaces.starts_with(
+        "auto dummy_f1\niface dummy_f1 inet static\n\taddress 10.10.10.2/32\n\tlink-type dummy\n"
+    ));
+    assert!(interfaces.contains("iface ens19 inet static\n\taddress 10.0.12.1/31\n"));
+    assert!(interfaces.contains("iface ens20 inet manual\n"));
+
+    let frr = &configs["frr"];
+    assert!(frr.contains("router openfabric f1\n net 49.0001.0100.1001.0002.00\nexit\n"));
+    assert!(frr.contains("interface dummy_f1\n ip router openfabric f1\n openfabric passive\n"));
+    assert!(frr.contains(
+        "interface ens20\n ip router openfabric f1\n openfabric hello-interval 1\nexit\n"
+    ));
+
+    assert!(generate_fabric_config(&fabric, "node4").is_err());
+}
+
+#[test]
+fn test_ospf_generation() {
+    let mut fabric = three_node_fabric(FabricProtocol::Ospf);
+    fabric.area = Some("0.0.0.1".to_string());
+
+    let configs = generate_fabric_config(&fabric, "node3").unwrap();
+
+    // Unnumbered links borrow the loopback address
+    assert!(configs["interfaces"].contains("iface ens20 inet static\n\taddress 10.10.10.3/32\n"));
+
+    let frr = &configs["frr"];
+    assert!(frr.contains("router ospf\n ospf router-id 10.10.10.3\nexit\n"));
+    assert!(frr.contains("interface dummy_f1\n ip ospf area 0.0.0.1\n ip ospf passive\n"));
+    assert!(frr.contains(
+        "interface ens19\n ip ospf area 0.0.0.1\n ip ospf network point-to-point\nexit\n"
+    ));
+}
+
+#[test]
+fn test_resolve_fabric_vteps() {
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_fabric(three_node_fabric(FabricProtocol::Openfabric))
+        .unwrap();
+
+    let mut evpn = ZoneConfig::new(ZoneType::Evpn, "evpn1".to_string());
+    evpn.options
+        .insert("fabric".to_string(), serde_json::json!("f1"));
+    sdn.add_zone(evpn).unwrap();
+
+    let mut vxlan = ZoneConfig::new(ZoneType::Vxlan, "vxlan1".to_string());
+    vxlan
+        .options
+        .insert("fabric".to_string(), serde_json::json!("f1"));
+    vxlan.nodes = Some(vec!["node1".to_string(), "node2".to_string()]);
+    sdn.add_zone(vxlan).unwrap();
+
+    let mut controller = ControllerConfig::new(ControllerType::Evpn, "ctl1".to_string());
+    controller
+        .options
+        .insert("fabric".to_string(), serde_json::json!("f1"));
+    sdn.controllers.insert("ctl1".to_string(), controller);
+    sdn.validate().unwrap();
+
+    let resolved = resolve_fabric_vteps(&sdn, "node2").unwrap();
+
+    assert_eq!(
+        resolved.zones["evpn1"].options["vtep-ip"],
+        serde_json::json!("10.10.10.2")
+    );
+    assert_eq!(
+        resolved.zones["vxlan1"].options["local-ip"],
+        serde_json::json!("10.10.10.2")
+    );
+    assert_eq!(
+        resolved.zones["vxlan1"].peers,
+        Some(vec!["10.10.10.1".to_string()])
+    );
+    assert_eq!(
+        resolved.controllers["ctl1"].peers,
+        Some(vec!["10.10.10.1".to_string(), "10.10.10.3".to_string()])
+    );
+
+    // Zones not active on the node are left alone
+    let resolved = resolve_fabric_vteps(&sdn, "node3").unwrap();
+    assert!(resolved.zones["vxlan1"].peers.is_none());
+
+    // A node outside the fabric cannot resolve its VTEP
+    assert!(resolve_fabric_vteps(&sdn, "node4").is_err());
+
+    // Fabrics in use cannot be removed
+    assert!(sdn.remove_fabric("f1").is_err());
+    assert_eq!(
+        sdn.fabric_users("f1"),
+        vec!["controller ctl1", "zone evpn1", "zone vxlan1"]
+    );
+}
+
+fn bridge_resolver(executor: Arc<RecordingExecutor>) -> ZoneResolver {
+    Arc::new(move |config: &ZoneConfig| {
+        Ok(Box::new(BridgeZone {
+            name: config.zone.clone(),
+            executor: executor.clone(),
+        }) as Box<dyn Zone>)
+    })
+}
+
+#[tokio::test]
+async fn test_status_collector() {
+    let executor = Arc::new(RecordingExecutor::new());
+    let link = serde_json::json!([{ "ifname": "brz1", "flags": ["UP"] }]);
+    executor.respond(
+        "ip -j -d link show dev brz1",
+        CommandOutput::success(&link.to_string()),
+    );
+    executor.respond(
+        "ip -j -d link show dev brz2",
+        CommandOutput::failure(1, "Device \"brz2\" does not exist."),
+    );
+
+    let mut config = SdnConfiguration::new();
+    for zone in ["z1", "z2"] {
+        config
+            .add_zone(ZoneConfig::new(ZoneType::Simple, zone.to_string()))
+            .unwrap();
+    }
+    let mut remote = ZoneConfig::new(ZoneType::Simple, "z3".to_string());
+    remote.nodes = Some(vec!["node2".to_string()]);
+    config.add_zone(remote).unwrap();
+    config
+        .add_vnet(VNetConfig::new("v1".to_string(), "z1".to_string()))
+        .unwrap();
+    config
+        .add_vnet(VNetConfig::new("v2".to_string(), "z2".to_string()))
+        .unwrap();
+    config.version = Some(3);
+
+    let collector =
+        SdnStatusCollector::new(executor.clone(), bridge_resolver(executor.clone()), "node1");
+    let statuses = |status: &NodeSdnStatus| -> Vec<DeploymentStatus> {
+        status
+            .zones
+            .iter()
+            .map(|z| z.status)
+            .chain(status.vnets.iter().map(|v| v.status))
+            .collect()
+    };
+
+    // Nothing is checked before the running version is applied
+    let status = collector.collect(&config, Some(2)).await.unwrap();
+    assert_eq!(
+        statuses(&status),
+        vec![
+            DeploymentStatus::Pending,
+            DeploymentStatus::Pending,
+            DeploymentStatus::NotDeployed,
+            DeploymentStatus::Pending,
+            DeploymentStatus::Pending,
+        ]
+    );
+    assert!(status.zones[0]
+        .message
+        .as_deref()
+        .unwrap()
+        .contains("differs from running version 3"));
+    assert!(executor.command_lines().is_empty());
+
+    let status = collector.collect(&config, Some(3)).await.unwrap();
+    assert_eq!(status.node, "node1");
+    assert_eq!(
+        statuses(&status),
+        vec![
+            DeploymentStatus::Available,
+            DeploymentStatus::Error,
+            DeploymentStatus::NotDeployed,
+            DeploymentStatus::Available,
+            DeploymentStatus::Error,
+        ]
+    );
+    assert_eq!(
+        status.zones[1].message.as_deref(),
+        Some("bridge 'brz2' is missing")
+    );
+    assert_eq!(status.vnets[1].vnet, "v2");
+}
+
+#[tokio::test]
+async fn test_status_store_aggregation() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SdnStatusStore::with_base_path(dir.path());
+
+    let mut config = SdnConfiguration::new();
+    config
+        .add_zone(ZoneConfig::new(ZoneType::Simple, "z1".to_string()))
+        .unwrap();
+    let mut pinned = ZoneConfig::new(ZoneType::Simple, "z2".to_string());
+    pinned.nodes = Some(vec!["node1".to_string(), "node3".to_string()]);
+    config.add_zone(pinned).unwrap();
+    config.version = Some(2);
+
+    let node_status =
+        |node: &str, version: u64, zones: &[(&str, DeploymentStatus)]| NodeSdnStatus {
+            node: node.to_string(),
+            version,
+            applied_version: Some(version),
+            timestamp: 0,
+            zones: zones
+                .iter()
+                .map(|(zone, status)| ZoneNodeStatus {
+                    zone: zone.to_string(),
+                    status: *status,
+                    message: None,
+                })
+                .collect(),
+            vnets: Vec::new(),
+        };
+
+    let node1 = node_status(
+        "node1",
+        2,
+        &[
+            ("z1", DeploymentStatus::Available),
+            ("z2", DeploymentStatus::Available),
+        ],
+    );
+    // Reported before the last apply
+    let node2 = node_status(
+        "node2",
+        1,
+        &[
+            ("z1", DeploymentStatus::Available),
+            ("z2", DeploymentStatus::NotDeployed),
+        ],
+    );
+    store.publish(&node2).await.unwrap();
+    store.publish(&node1).await.unwrap();
+    assert_eq!(store.read("node1").await.unwrap(), Some(node1.clone()));
+    assert_eq!(store.read("node3").await.unwrap(), None);
+
+    // Nodes without or with a broken status file are skipped
+    std::fs::create_dir_all(dir.path().join("nodes/node3")).unwrap();
+    std::fs::create_dir_all(dir.path().join("nodes/node4")).unwrap();
+    std::fs::write(store.status_path("node4"), "{").unwrap();
+
+    let nodes = store.read_all().await.unwrap();
+    assert_eq!(nodes, vec![node1, node2]);
+
+    let cluster = aggregate_status(&config, nodes);
+    assert_eq!(cluster.version, 2);
+    assert_eq!(cluster.zones.len(), 2);
+
+    assert_eq!(cluster.zones[0].status, DeploymentStatus::Pending);
+    assert_eq!(
+        cluster.zones[0].nodes.values().copied().collect::<Vec<_>>(),
+        vec![DeploymentStatus::Available, DeploymentStatus::Pending]
+    );
+
+    assert_eq!(cluster.zones[1].status, DeploymentStatus::Pending);
+    assert_eq!(
+        cluster.zones[1].nodes["node2"],
+        DeploymentStatus::NotDeployed
+    );
+    assert_eq!(cluster.zones[1].nodes["node3"], DeploymentStatus::Pending);
+}
+
+#[tokio::test]
+async fn test_status_store_running_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = SdnStatusStore::with_base_path(dir.path());
+    assert!(store.read_running_config().await.unwrap().is_none());
+
+    let mut config = SdnConfiguration::new();
+    config
+        .add_zone(ZoneConfig::new(ZoneType::Simple, "z1".to_string()))
+        .unwrap();
+    config.version = Some(3);
+    store.write_running_config(&config).await.unwrap();
+
+    assert!(dir.path().join("sdn/.running-config").exists());
+    let running = store.read_running_config().await.unwrap().unwrap();
+    assert_eq!(running.running_version(), 3);
+    assert!(running.zones.contains_key("z1"));
+}
@@ -3,7 +3,11 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-pub use pve_shared_types::VNetConfig;
+pub use pve_shared_types::firewall::parse_ports;
+pub use pve_shared_types::{
+    FirewallAction, FirewallAlias, FirewallDirection, FirewallIpSet, FirewallRule, VNetConfig,
+    VNetFirewall,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VNet {
@@ -33,4 +37,4 @@ impl VNet {
     pub fn zone(&self) -> &str {
         &self.config.zone
     }
-}
+}
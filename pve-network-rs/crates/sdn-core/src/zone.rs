@@ -2,9 +2,122 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{SdnConfiguration, SubnetConfig, VNetConfig};
 
 pub use pve_shared_types::{ZoneConfig, ZoneType};
 
+/// Zone deployment status on the local node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ZoneStatus {
+    /// All devices of the zone exist and are up
+    Available,
+    /// Devices are missing or down, the zone needs to be (re)applied
+    Pending,
+    /// The zone configuration is invalid
+    Error(String),
+}
+
+/// State of a device a zone expects on the local node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObservedDevice {
+    /// Interface name
+    pub name: String,
+    /// Expected link kind (e.g. "vxlan", "bridge")
+    pub kind: String,
+    /// Whether the device exists
+    pub present: bool,
+    /// Whether the device is administratively up
+    pub up: bool,
+    /// Bridge the device is enslaved to
+    pub master: Option<String>,
+    /// Current MTU
+    pub mtu: Option<u32>,
+    /// Interface alias, used to mark SDN ownership
+    pub alias: Option<String>,
+}
+
+impl ObservedDevice {
+    /// Device that does not exist
+    pub fn absent(name: &str, kind: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            present: false,
+            up: false,
+            master: None,
+            mtu: None,
+            alias: None,
+        }
+    }
+}
+
+/// Devices a zone expects, as found on the local node
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZoneObservedState {
+    /// Zone name
+    pub zone: String,
+    /// Expected devices and their current state
+    pub devices: Vec<ObservedDevice>,
+}
+
+impl ZoneObservedState {
+    /// Create empty observed state for a zone
+    pub fn new(zone: &str) -> Self {
+        Self {
+            zone: zone.to_string(),
+            devices: Vec::new(),
+        }
+    }
+
+    /// Devices that do not exist yet
+    pub fn missing(&self) -> Vec<&ObservedDevice> {
+        self.devices.iter().filter(|d| !d.present).collect()
+    }
+
+    /// Whether all expected devices exist and are up
+    pub fn is_complete(&self) -> bool {
+        self.devices.iter().all(|d| d.present && d.up)
+    }
+}
+
+/// Context for rendering a zone's configuration on one node
+#[derive(Debug, Clone, Copy)]
+pub struct ZoneRenderContext<'a> {
+    /// Node the configuration is rendered for
+    pub node: &'a str,
+    /// Complete SDN configuration
+    pub sdn: &'a SdnConfiguration,
+}
+
+impl<'a> ZoneRenderContext<'a> {
+    /// Create render context
+    pub fn new(node: &'a str, sdn: &'a SdnConfiguration) -> Self {
+        Self { node, sdn }
+    }
+
+    /// VNets of `zone`, sorted by name
+    pub fn vnets(&self, zone: &str) -> Vec<&'a VNetConfig> {
+        let mut vnets: Vec<&VNetConfig> =
+            self.sdn.vnets.values().filter(|v| v.zone == zone).collect();
+        vnets.sort_by(|a, b| a.vnet.cmp(&b.vnet));
+        vnets
+    }
+
+    /// Subnets of `vnet`, sorted by name
+    pub fn subnets(&self, vnet: &str) -> Vec<&'a SubnetConfig> {
+        let mut subnets: Vec<&SubnetConfig> = self
+            .sdn
+            .subnets
+            .values()
+            .filter(|s| s.vnet == vnet)
+            .collect();
+        subnets.sort_by(|a, b| a.subnet.cmp(&b.subnet));
+        subnets
+    }
+}
+
 #[async_trait]
 pub trait Zone: Send + Sync {
     fn zone_type(&self) -> ZoneType;
@@ -15,4 +128,74 @@ pub trait Zone: Send + Sync {
         &self,
         config: &ZoneConfig,
     ) -> Result<std::collections::HashMap<String, String>>;
+
+    /// Generate configuration for the node in `ctx`
+    ///
+    /// Zones whose output depends on their vnets, subnets, controllers or
+    /// the local node override this; the default is `generate_config`.
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        _ctx: &ZoneRenderContext<'_>,
+    ) -> Result<std::collections::HashMap<String, String>> {
+        self.generate_config(config).await
+    }
+
+    /// Set up node-local state the configuration generated for the node in
+    /// `ctx` relies on, such as key material
+    ///
+    /// The apply pipeline calls this before generating, so generating stays
+    /// free of side effects. Most zones keep no such state.
+    async fn prepare_node_config(
+        &self,
+        _config: &ZoneConfig,
+        _ctx: &ZoneRenderContext<'_>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Bring the existing devices of the zone in line with a changed
+    /// `config` without recreating them, so attached guest ports stay
+    ///
+    /// The default re-runs `apply_config`, which creates missing devices and
+    /// leaves existing ones alone.
+    async fn update_config(&self, config: &ZoneConfig) -> Result<()> {
+        self.apply_config(config).await
+    }
+
+    /// Remove the SDN-owned devices created by `apply_config`
+    ///
+    /// Zones that only attach to pre-existing bridges own no devices.
+    async fn remove_config(&self, _config: &ZoneConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// Devices this zone expects on the local node and their current state
+    async fn observed_state(&self, _config: &ZoneConfig) -> Result<ZoneObservedState> {
+        Ok(ZoneObservedState::new(self.name()))
+    }
+
+    /// Devices `vnet` expects on the local node and their current state
+    ///
+    /// Zones that create no per-vnet devices report none.
+    async fn observed_vnet_state(
+        &self,
+        _config: &ZoneConfig,
+        _vnet: &VNetConfig,
+    ) -> Result<ZoneObservedState> {
+        Ok(ZoneObservedState::new(self.name()))
+    }
+
+    /// Deployment status of the zone on the local node
+    async fn status(&self, config: &ZoneConfig) -> Result<ZoneStatus> {
+        if let Err(e) = self.validate_config(config).await {
+            return Ok(ZoneStatus::Error(e.to_string()));
+        }
+
+        if self.observed_state(config).await?.is_complete() {
+            Ok(ZoneStatus::Available)
+        } else {
+            Ok(ZoneStatus::Pending)
+        }
+    }
 }
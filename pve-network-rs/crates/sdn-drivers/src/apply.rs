@@ -0,0 +1,1230 @@
+//! SDN apply pipeline
+//!
+//! Collects the output of `generate_config` from every fabric, zone and
+//! controller that is active on the local node, renders it into
+//! `/etc/network/interfaces.d/sdn`, `/etc/frr/frr.conf`, the SDN
+//! nftables ruleset and the WireGuard configs, and reloads ifupdown2, FRR,
+//! nftables and WireGuard only when the rendered content differs from what
+//! is currently on disk. This mirrors the behaviour of PVE's Perl SDN stack.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use pve_sdn_core::{
+    generate_fabric_config, resolve_fabric_vteps, Controller, RealExecutor, ReconcileReport,
+    SdnConfiguration, SystemCommand, SystemExecutor, Zone, ZoneConfig, ZoneRenderContext,
+};
+
+use crate::firewall::generate_firewall_nftables;
+use crate::plugin_factory::{get_plugin_factory, PluginFactory};
+use crate::zones::wireguard::{DEFAULT_SDN_WIREGUARD_PATH, WIREGUARD_CONFIG_KEY_PREFIX};
+
+/// Default location of the generated SDN interfaces file
+pub const DEFAULT_SDN_INTERFACES_PATH: &str = "/etc/network/interfaces.d/sdn";
+
+/// Default location of the FRR configuration
+pub const DEFAULT_FRR_CONFIG_PATH: &str = "/etc/frr/frr.conf";
+
+/// Default location of the local FRR overrides merged into `frr.conf`
+pub const DEFAULT_FRR_LOCAL_CONFIG_PATH: &str = "/etc/frr/frr.conf.local";
+
+/// Default location of the SDN nftables ruleset
+pub const DEFAULT_SDN_NFTABLES_PATH: &str = "/etc/nftables.d/pve-sdn.nft";
+
+/// Generated config keys that are not part of the rendered files
+const IGNORED_KEYS: &[&str] = &["systemd", "metadata"];
+
+/// Generated config key holding FRR configuration
+const FRR_KEY: &str = "frr";
+
+/// Generated config key holding nftables rules
+const NFTABLES_KEY: &str = "nftables";
+
+/// nftables table owned by the SDN stack
+const NFTABLES_TABLE: &str = "inet pve-sdn";
+
+/// First line of the SDN interfaces file, followed by the config version
+const VERSION_HEADER: &str = "#version:";
+
+/// Reloads the services consuming the generated files
+#[async_trait]
+pub trait SdnReloader: Send + Sync {
+    /// Reload network interfaces (ifupdown2)
+    async fn reload_interfaces(&self) -> Result<()>;
+
+    /// Reload FRR from the given configuration file
+    async fn reload_frr(&self, frr_config: &Path) -> Result<()>;
+
+    /// Load the given nftables ruleset
+    async fn reload_nftables(&self, ruleset: &Path) -> Result<()>;
+
+    /// Sync the running WireGuard interface with the given config
+    async fn reload_wireguard(&self, interface: &str, wg_config: &Path) -> Result<()>;
+}
+
+/// Reloader invoking `ifreload` and `frr-reload.py` on the local system
+pub struct SystemReloader {
+    executor: Arc<dyn SystemExecutor>,
+}
+
+impl SystemReloader {
+    /// Create reloader running commands through `executor`
+    pub fn new(executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { executor }
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> Result<()> {
+        let output = self.executor.execute(program, args).await?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "{} failed: {}",
+                SystemCommand::new(program, args),
+                output.stderr.trim()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SdnReloader for SystemReloader {
+    async fn reload_interfaces(&self) -> Result<()> {
+        self.run("ifreload", &["-a"]).await
+    }
+
+    async fn reload_frr(&self, frr_config: &Path) -> Result<()> {
+        let frr_config = frr_config.to_string_lossy();
+        if self
+            .run(
+                "/usr/lib/frr/frr-reload.py",
+                &["--stdout", "--reload", &frr_config],
+            )
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        // frr-reload.py can fail on configurations it cannot diff, fall back
+        // to a full service restart like pve-network does
+        self.run("systemctl", &["restart", "frr.service"]).await
+    }
+
+    async fn reload_nftables(&self, ruleset: &Path) -> Result<()> {
+        self.run("nft", &["-f", &ruleset.to_string_lossy()]).await
+    }
+
+    async fn reload_wireguard(&self, interface: &str, wg_config: &Path) -> Result<()> {
+        // Interfaces created by ifupdown2 already loaded the config
+        let exists = self
+            .executor
+            .query("ip", &["link", "show", interface])
+            .await
+            .map(|output| output.is_success())
+            .unwrap_or(false);
+        if !exists {
+            return Ok(());
+        }
+
+        self.run("wg", &["syncconf", interface, &wg_config.to_string_lossy()])
+            .await
+    }
+}
+
+/// Rendered SDN configuration for the local node
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedSdnConfig {
+    /// Content of the SDN interfaces file
+    pub interfaces: String,
+    /// Content of `frr.conf`, `None` when no zone or controller needs FRR
+    pub frr: Option<String>,
+    /// Content of the SDN nftables ruleset, `None` when no zone needs one
+    pub nftables: Option<String>,
+    /// Content of the WireGuard configs, by interface name
+    #[serde(default)]
+    pub wireguard: BTreeMap<String, String>,
+    /// Zones skipped because they are not configured on the local node
+    pub skipped_zones: Vec<String>,
+    /// Controllers skipped because they belong to another node
+    pub skipped_controllers: Vec<String>,
+}
+
+/// Change to a single rendered file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SdnFileChange {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Whether the content differs from the file on disk
+    pub changed: bool,
+    /// Line diff against the file on disk ("-" removed, "+" added)
+    pub diff: Vec<String>,
+}
+
+/// Result of an SDN apply run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SdnApplyResult {
+    /// Change to the SDN interfaces file
+    pub interfaces: SdnFileChange,
+    /// Change to `frr.conf`, `None` when FRR is not managed
+    pub frr: Option<SdnFileChange>,
+    /// Change to the nftables ruleset, `None` when nftables is not managed
+    #[serde(default)]
+    pub nftables: Option<SdnFileChange>,
+    /// Changes to the WireGuard configs
+    #[serde(default)]
+    pub wireguard: Vec<SdnFileChange>,
+    /// Whether ifupdown2 was reloaded
+    pub interfaces_reloaded: bool,
+    /// Whether FRR was reloaded
+    pub frr_reloaded: bool,
+    /// Whether the nftables ruleset was loaded
+    #[serde(default)]
+    pub nftables_reloaded: bool,
+    /// Whether a WireGuard interface was synced with its config
+    #[serde(default)]
+    pub wireguard_reloaded: bool,
+    /// Whether files were left untouched (dry run)
+    pub dry_run: bool,
+    /// Device reconciliation run after the reload, `None` if it did not run
+    #[serde(default)]
+    pub reconcile: Option<ReconcileReport>,
+}
+
+impl SdnApplyResult {
+    /// Whether any rendered file changed
+    pub fn changed(&self) -> bool {
+        self.interfaces.changed
+            || self.frr.as_ref().map(|f| f.changed).unwrap_or(false)
+            || self.nftables.as_ref().map(|f| f.changed).unwrap_or(false)
+            || self.wireguard.iter().any(|f| f.changed)
+    }
+}
+
+/// SDN apply pipeline for the local node
+#[derive(Clone)]
+pub struct SdnApplyPipeline {
+    node: String,
+    factory: Option<Arc<PluginFactory>>,
+    interfaces_path: PathBuf,
+    frr_path: PathBuf,
+    frr_local_path: PathBuf,
+    nftables_path: PathBuf,
+    wireguard_path: PathBuf,
+    reloader: Arc<dyn SdnReloader>,
+}
+
+impl SdnApplyPipeline {
+    /// Create pipeline for the given node using the default paths
+    pub fn new(node: &str) -> Self {
+        Self {
+            node: node.to_string(),
+            factory: None,
+            interfaces_path: PathBuf::from(DEFAULT_SDN_INTERFACES_PATH),
+            frr_path: PathBuf::from(DEFAULT_FRR_CONFIG_PATH),
+            frr_local_path: PathBuf::from(DEFAULT_FRR_LOCAL_CONFIG_PATH),
+            nftables_path: PathBuf::from(DEFAULT_SDN_NFTABLES_PATH),
+            wireguard_path: PathBuf::from(DEFAULT_SDN_WIREGUARD_PATH),
+            reloader: Arc::new(SystemReloader::new(Arc::new(RealExecutor::new()))),
+        }
+    }
+
+    /// Use a specific plugin factory instead of the global one
+    ///
+    /// Services are reloaded through the factory's executor, so a dry-run
+    /// factory also suppresses the reloads.
+    pub fn with_factory(mut self, factory: Arc<PluginFactory>) -> Self {
+        self.reloader = Arc::new(SystemReloader::new(factory.executor()));
+        self.factory = Some(factory);
+        self
+    }
+
+    /// Override the rendered file locations
+    pub fn with_paths(
+        mut self,
+        interfaces_path: impl Into<PathBuf>,
+        frr_path: impl Into<PathBuf>,
+        frr_local_path: impl Into<PathBuf>,
+    ) -> Self {
+        self.interfaces_path = interfaces_path.into();
+        self.frr_path = frr_path.into();
+        self.frr_local_path = frr_local_path.into();
+        self
+    }
+
+    /// Override the nftables ruleset location
+    pub fn with_nftables_path(mut self, nftables_path: impl Into<PathBuf>) -> Self {
+        self.nftables_path = nftables_path.into();
+        self
+    }
+
+    /// Override the directory of the WireGuard configs
+    pub fn with_wireguard_path(mut self, wireguard_path: impl Into<PathBuf>) -> Self {
+        self.wireguard_path = wireguard_path.into();
+        self
+    }
+
+    /// Use a custom reloader
+    pub fn with_reloader(mut self, reloader: Arc<dyn SdnReloader>) -> Self {
+        self.reloader = reloader;
+        self
+    }
+
+    /// Node this pipeline renders configuration for
+    pub fn node(&self) -> &str {
+        &self.node
+    }
+
+    /// Plugin factory set with [`Self::with_factory`]
+    pub fn plugin_factory(&self) -> Option<&Arc<PluginFactory>> {
+        self.factory.as_ref()
+    }
+
+    /// Configuration version of the SDN interfaces file on disk, `None`
+    /// if it was never written
+    pub async fn applied_version(&self) -> Result<Option<u64>> {
+        let content = match tokio::fs::read_to_string(&self.interfaces_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read {}", self.interfaces_path.display()))
+            }
+        };
+
+        Ok(content
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix(VERSION_HEADER))
+            .and_then(|version| version.trim().parse().ok()))
+    }
+
+    fn factory(&self) -> &PluginFactory {
+        match &self.factory {
+            Some(factory) => factory,
+            None => get_plugin_factory(),
+        }
+    }
+
+    /// Whether the zone is active on this node
+    fn zone_on_node(&self, zone_config: &ZoneConfig) -> bool {
+        match &zone_config.nodes {
+            Some(nodes) => nodes.iter().any(|n| n == &self.node),
+            None => true,
+        }
+    }
+
+    /// Set up the node-local state of the zones on this node, like
+    /// WireGuard keys, ahead of rendering their configuration
+    pub async fn prepare(&self, config: &SdnConfiguration) -> Result<()> {
+        let config = &resolve_fabric_vteps(config, &self.node)?;
+
+        let mut zone_names: Vec<&String> = config.zones.keys().collect();
+        zone_names.sort();
+
+        for name in zone_names {
+            let zone_config = &config.zones[name];
+            if !self.zone_on_node(zone_config) {
+                continue;
+            }
+
+            let ctx = ZoneRenderContext::new(&self.node, config);
+            let zone: Box<dyn Zone> = self
+                .factory()
+                .create_zone(&zone_config.zone_type, name.clone())?;
+            zone.validate_config(zone_config)
+                .await
+                .with_context(|| format!("Invalid configuration for zone '{}'", name))?;
+            zone.prepare_node_config(zone_config, &ctx)
+                .await
+                .with_context(|| format!("Failed to prepare zone '{}'", name))?;
+        }
+
+        Ok(())
+    }
+
+    /// Render the configuration of all fabrics, zones and controllers on
+    /// this node
+    ///
+    /// Rendering has no side effects, `apply` runs `prepare` first.
+    pub async fn generate(&self, config: &SdnConfiguration) -> Result<GeneratedSdnConfig> {
+        let mut generated = GeneratedSdnConfig::default();
+        let mut interface_sections = Vec::new();
+        let mut frr_sections = Vec::new();
+        let mut nftables_sections = Vec::new();
+
+        // Zones and controllers may take their VTEP addresses from a fabric
+        let config = &resolve_fabric_vteps(config, &self.node)?;
+
+        let mut fabric_names: Vec<&String> = config.fabrics.keys().collect();
+        fabric_names.sort();
+
+        for name in fabric_names {
+            let fabric = &config.fabrics[name];
+            if fabric.node(&self.node).is_none() {
+                debug!(
+                    "Skipping fabric '{}', {} is not part of it",
+                    name, self.node
+                );
+                continue;
+            }
+
+            let files = generate_fabric_config(fabric, &self.node)
+                .with_context(|| format!("Failed to generate config for fabric '{}'", name))?;
+
+            collect_sections(
+                files,
+                &mut interface_sections,
+                &mut frr_sections,
+                &mut nftables_sections,
+                &mut generated.wireguard,
+            );
+        }
+
+        let mut zone_names: Vec<&String> = config.zones.keys().collect();
+        zone_names.sort();
+
+        // VNets of the zones active on this node, in zone order
+        let mut vnets = Vec::new();
+
+        for name in zone_names {
+            let zone_config = &config.zones[name];
+            if !self.zone_on_node(zone_config) {
+                debug!("Skipping zone '{}', not configured on {}", name, self.node);
+                generated.skipped_zones.push(name.clone());
+                continue;
+            }
+
+            let ctx = ZoneRenderContext::new(&self.node, config);
+            let zone: Box<dyn Zone> = self
+                .factory()
+                .create_zone(&zone_config.zone_type, name.clone())?;
+            let files = zone
+                .generate_node_config(zone_config, &ctx)
+                .await
+                .with_context(|| format!("Failed to generate config for zone '{}'", name))?;
+            vnets.extend(ctx.vnets(name));
+
+            collect_sections(
+                files,
+                &mut interface_sections,
+                &mut frr_sections,
+                &mut nftables_sections,
+                &mut generated.wireguard,
+            );
+        }
+
+        if let Some(ruleset) = generate_firewall_nftables(&vnets)? {
+            nftables_sections.push(ruleset);
+        }
+
+        let mut controller_names: Vec<&String> = config.controllers.keys().collect();
+        controller_names.sort();
+
+        for name in controller_names {
+            let controller_config = &config.controllers[name];
+            if let Some(node) = &controller_config.node {
+                if node != &self.node {
+                    debug!(
+                        "Skipping controller '{}', it belongs to node {}",
+                        name, node
+                    );
+                    generated.skipped_controllers.push(name.clone());
+                    continue;
+                }
+            }
+
+            let controller: Box<dyn Controller> = self
+                .factory()
+                .create_controller(&controller_config.controller_type, name.clone())?;
+            let files = controller
+                .generate_config(controller_config)
+                .await
+                .with_context(|| format!("Failed to generate config for controller '{}'", name))?;
+
+            collect_sections(
+                files,
+                &mut interface_sections,
+                &mut frr_sections,
+                &mut nftables_sections,
+                &mut generated.wireguard,
+            );
+        }
+
+        generated.interfaces = render_interfaces(config.running_version(), &interface_sections);
+
+        // Once no zone or controller needs FRR anymore, a frr.conf rendered by
+        // a previous run is reset so stale routing configuration goes away
+        if !frr_sections.is_empty() || self.frr_rendered_before().await? {
+            let local = match tokio::fs::read_to_string(&self.frr_local_path).await {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!("Failed to read {}", self.frr_local_path.display())
+                    })
+                }
+            };
+            generated.frr = Some(render_frr(&self.node, &frr_sections, local.as_deref()));
+        }
+
+        // Keep rendering an empty ruleset while a previous one is on disk, so
+        // loading it flushes rules of zones that no longer need them
+        if !nftables_sections.is_empty()
+            || tokio::fs::try_exists(&self.nftables_path)
+                .await
+                .unwrap_or(false)
+        {
+            generated.nftables = Some(render_nftables(&nftables_sections));
+        }
+
+        Ok(generated)
+    }
+
+    /// Compare the rendered configuration against the files on disk
+    pub async fn diff(&self, generated: &GeneratedSdnConfig) -> Result<SdnApplyResult> {
+        let interfaces = file_change(&self.interfaces_path, &generated.interfaces).await?;
+        let frr = match &generated.frr {
+            Some(content) => Some(file_change(&self.frr_path, content).await?),
+            None => None,
+        };
+        let nftables = match &generated.nftables {
+            Some(content) => Some(file_change(&self.nftables_path, content).await?),
+            None => None,
+        };
+        let mut wireguard = Vec::new();
+        for (interface, content) in &generated.wireguard {
+            wireguard.push(file_change(&self.wireguard_config_path(interface), content).await?);
+        }
+
+        Ok(SdnApplyResult {
+            interfaces,
+            frr,
+            nftables,
+            wireguard,
+            interfaces_reloaded: false,
+            frr_reloaded: false,
+            nftables_reloaded: false,
+            wireguard_reloaded: false,
+            dry_run: true,
+            reconcile: None,
+        })
+    }
+
+    /// Render, write and reload the SDN configuration
+    pub async fn apply(&self, config: &SdnConfiguration) -> Result<SdnApplyResult> {
+        self.prepare(config).await?;
+        let generated = self.generate(config).await?;
+        let mut result = self.diff(&generated).await?;
+        result.dry_run = false;
+
+        // ifupdown2 loads the WireGuard configs when creating the interfaces
+        let mut changed_wireguard = Vec::new();
+        for (interface, content) in &generated.wireguard {
+            let path = self.wireguard_config_path(interface);
+            if result.wireguard.iter().any(|f| f.path == path && f.changed) {
+                write_file(&path, content).await?;
+                changed_wireguard.push((interface, path));
+            }
+        }
+
+        if result.interfaces.changed {
+            write_file(&self.interfaces_path, &generated.interfaces).await?;
+            self.reloader
+                .reload_interfaces()
+                .await
+                .context("Failed to reload network interfaces")?;
+            result.interfaces_reloaded = true;
+        }
+
+        for (interface, path) in changed_wireguard {
+            self.reloader
+                .reload_wireguard(interface, &path)
+                .await
+                .with_context(|| format!("Failed to reload WireGuard interface {}", interface))?;
+            result.wireguard_reloaded = true;
+        }
+
+        if let (Some(change), Some(content)) = (&result.frr, &generated.frr) {
+            if change.changed {
+                write_file(&self.frr_path, content).await?;
+                self.reloader
+                    .reload_frr(&self.frr_path)
+                    .await
+                    .context("Failed to reload FRR")?;
+                result.frr_reloaded = true;
+            }
+        }
+
+        if let (Some(change), Some(content)) = (&result.nftables, &generated.nftables) {
+            if change.changed {
+                write_file(&self.nftables_path, content).await?;
+                self.reloader
+                    .reload_nftables(&self.nftables_path)
+                    .await
+                    .context("Failed to load nftables ruleset")?;
+                result.nftables_reloaded = true;
+            }
+        }
+
+        if result.changed() {
+            info!("SDN configuration applied on node {}", self.node);
+        } else {
+            info!("SDN configuration on node {} is up to date", self.node);
+        }
+
+        Ok(result)
+    }
+
+    fn wireguard_config_path(&self, interface: &str) -> PathBuf {
+        self.wireguard_path.join(format!("{}.conf", interface))
+    }
+
+    /// Whether `frr.conf` on disk was rendered by this pipeline, as opposed
+    /// to a configuration managed by hand
+    async fn frr_rendered_before(&self) -> Result<bool> {
+        match tokio::fs::read_to_string(&self.frr_path).await {
+            Ok(content) => Ok(content.starts_with(&frr_header(&self.node))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", self.frr_path.display())),
+        }
+    }
+}
+
+/// Sort generated files into interfaces, FRR, nftables sections and
+/// WireGuard configs
+fn collect_sections(
+    files: std::collections::HashMap<String, String>,
+    interface_sections: &mut Vec<String>,
+    frr_sections: &mut Vec<String>,
+    nftables_sections: &mut Vec<String>,
+    wireguard_configs: &mut BTreeMap<String, String>,
+) {
+    let mut files: Vec<(String, String)> = files.into_iter().collect();
+    files.sort();
+
+    for (key, content) in files {
+        if IGNORED_KEYS.contains(&key.as_str()) || content.trim().is_empty() {
+            continue;
+        }
+
+        if key == FRR_KEY {
+            frr_sections.push(content);
+        } else if key == NFTABLES_KEY {
+            nftables_sections.push(content);
+        } else if let Some(interface) = key.strip_prefix(WIREGUARD_CONFIG_KEY_PREFIX) {
+            wireguard_configs.insert(interface.to_string(), content);
+        } else {
+            interface_sections.push(content);
+        }
+    }
+}
+
+fn render_interfaces(version: u64, sections: &[String]) -> String {
+    let mut content = format!("{}{}\n", VERSION_HEADER, version);
+    for section in sections {
+        content.push('\n');
+        content.push_str(section.trim_end());
+        content.push('\n');
+    }
+    content
+}
+
+fn frr_header(node: &str) -> String {
+    format!(
+        "frr defaults datacenter\n\
+         hostname {}\n\
+         log syslog informational\n\
+         service integrated-vtysh-config\n\
+         !\n",
+        node
+    )
+}
+
+fn render_frr(node: &str, sections: &[String], local: Option<&str>) -> String {
+    let mut lines: Vec<String> = sections
+        .iter()
+        .flat_map(|section| section.trim_end().lines())
+        .map(str::to_string)
+        .collect();
+
+    if let Some(local) = local.filter(|l| !l.trim().is_empty()) {
+        merge_frr_local(&mut lines, local);
+    }
+
+    let mut content = frr_header(node);
+    for line in lines {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    content.push_str("!\nline vty\n!\n");
+    content
+}
+
+/// Merge `frr.conf.local` into the generated FRR configuration
+///
+/// Like the Perl SDN stack, local blocks with the same header as a generated
+/// block (e.g. `router bgp 65000`) are merged into it, sub-blocks such as
+/// `address-family ipv4 unicast` into the matching sub-block. Lines already
+/// present are not repeated, all other blocks are appended. Lines are
+/// compared without their indentation, as renderers indent with tabs or
+/// spaces.
+fn merge_frr_local(lines: &mut Vec<String>, local: &str) {
+    let mut appended: Vec<Vec<String>> = Vec::new();
+
+    for block in frr_blocks(local) {
+        let header = &block[0];
+        let start = match lines.iter().position(|line| same_frr_line(line, header)) {
+            Some(start) if block.len() > 1 => start,
+            Some(_) => continue,
+            None => {
+                appended.push(block);
+                continue;
+            }
+        };
+
+        let body: Vec<&String> = block[1..]
+            .iter()
+            .filter(|line| line.as_str() != "exit")
+            .collect();
+        let mut index = 0;
+        while index < body.len() {
+            let line = body[index];
+            let indent = frr_indent(line);
+
+            // Sub-block: header followed by deeper indented lines
+            if body
+                .get(index + 1)
+                .is_some_and(|next| frr_indent(next) > indent)
+            {
+                let mut end = index + 1;
+                while end < body.len() && frr_indent(body[end]) > indent {
+                    end += 1;
+                }
+                let closes = body
+                    .get(end)
+                    .is_some_and(|l| frr_indent(l) == indent && l.trim().starts_with("exit"));
+                if closes {
+                    end += 1;
+                }
+
+                let block_end = frr_block_end(lines, start);
+                match lines[start + 1..block_end]
+                    .iter()
+                    .position(|l| same_frr_line(l, line))
+                {
+                    Some(offset) => {
+                        let sub_start = start + 1 + offset;
+                        let mut sub_end = sub_start + 1;
+                        while sub_end < block_end && frr_indent(&lines[sub_end]) > indent {
+                            sub_end += 1;
+                        }
+                        let inner = if closes { end - 1 } else { end };
+                        for new_line in &body[index + 1..inner] {
+                            if !lines[sub_start + 1..sub_end]
+                                .iter()
+                                .any(|l| same_frr_line(l, new_line))
+                            {
+                                lines.insert(sub_end, new_line.to_string());
+                                sub_end += 1;
+                            }
+                        }
+                    }
+                    None => {
+                        let mut insert_at = block_end;
+                        lines.insert(insert_at, format!("{}!", " ".repeat(indent)));
+                        insert_at += 1;
+                        for new_line in &body[index..end] {
+                            lines.insert(insert_at, new_line.to_string());
+                            insert_at += 1;
+                        }
+                    }
+                }
+
+                index = end;
+                continue;
+            }
+
+            // Plain line: goes before the first sub-block of the generated block
+            let block_end = frr_block_end(lines, start);
+            if !lines[start + 1..block_end]
+                .iter()
+                .any(|l| same_frr_line(l, line))
+            {
+                let insert_at = (start + 1..block_end)
+                    .find(|&i| {
+                        lines[i].trim() == "!"
+                            || lines
+                                .get(i + 1)
+                                .is_some_and(|next| frr_indent(next) > frr_indent(&lines[i]))
+                    })
+                    .unwrap_or(block_end);
+                lines.insert(insert_at, line.to_string());
+            }
+            index += 1;
+        }
+    }
+
+    for block in appended {
+        if lines.last().is_some_and(|line| line != "!") {
+            lines.push("!".to_string());
+        }
+        lines.extend(block);
+    }
+}
+
+/// Split FRR configuration into top-level blocks
+///
+/// A block starts at an unindented line and holds the indented lines below
+/// it, including a closing `exit`. `!` lines and empty lines separate blocks.
+fn frr_blocks(content: &str) -> Vec<Vec<String>> {
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+    let mut current: Option<Vec<String>> = None;
+
+    for line in content.lines().map(str::trim_end) {
+        if line.is_empty() || line == "!" {
+            blocks.extend(current.take());
+        } else if frr_indent(line) > 0 || line == "exit" {
+            match current.as_mut() {
+                Some(block) => block.push(line.to_string()),
+                None => warn!(
+                    "Ignoring indented frr.conf.local line outside a block: {}",
+                    line
+                ),
+            }
+            if line == "exit" {
+                blocks.extend(current.take());
+            }
+        } else {
+            blocks.extend(current.take());
+            current = Some(vec![line.to_string()]);
+        }
+    }
+    blocks.extend(current);
+
+    blocks
+}
+
+/// End (exclusive) of the generated block starting at `start`, before its
+/// closing `exit` if it has one
+fn frr_block_end(lines: &[String], start: usize) -> usize {
+    let mut end = start + 1;
+    while end < lines.len() && frr_indent(&lines[end]) > 0 {
+        end += 1;
+    }
+    // Trailing " !" separators belong between blocks, not inside them
+    while end > start + 1 && lines[end - 1].trim() == "!" {
+        end -= 1;
+    }
+    end
+}
+
+fn same_frr_line(a: &str, b: &str) -> bool {
+    a.trim() == b.trim()
+}
+
+fn frr_indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn render_nftables(sections: &[String]) -> String {
+    // Declaring the table before deleting it makes the delete succeed on the
+    // first load, the whole file is applied atomically by nft
+    let mut content = format!(
+        "#!/usr/sbin/nft -f\n\
+         \n\
+         table {table}\n\
+         delete table {table}\n",
+        table = NFTABLES_TABLE
+    );
+
+    for section in sections {
+        content.push('\n');
+        content.push_str(section.trim_end());
+        content.push('\n');
+    }
+    content
+}
+
+async fn file_change(path: &Path, content: &str) -> Result<SdnFileChange> {
+    let current = match tokio::fs::read_to_string(path).await {
+        Ok(current) => current,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    Ok(SdnFileChange {
+        path: path.to_path_buf(),
+        changed: current != content,
+        diff: line_diff(&current, content),
+    })
+}
+
+async fn write_file(path: &Path, content: &str) -> Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    tokio::fs::create_dir_all(parent)
+        .await
+        .with_context(|| format!("Failed to create {}", parent.display()))?;
+
+    let tmp = tmp_path(path);
+    tokio::fs::write(&tmp, content)
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Temporary file to write `path` through
+///
+/// Files in `*.d` directories are sourced as a whole (ifupdown2 sources
+/// everything in `interfaces.d`), so their temporary file is placed next to
+/// the directory instead of inside it.
+fn tmp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+
+    match (parent.file_name(), parent.parent()) {
+        (Some(dir), Some(grandparent))
+            if dir.to_string_lossy().ends_with(".d") && !grandparent.as_os_str().is_empty() =>
+        {
+            grandparent.join(format!(".{}-{}.tmp", dir.to_string_lossy(), name))
+        }
+        _ => parent.join(format!(".{}.tmp", name)),
+    }
+}
+
+/// Line diff based on a longest common subsequence
+///
+/// The LCS is found with Hirschberg's algorithm, so memory stays linear in
+/// the size of the files.
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let mut diff = Vec::new();
+    diff_lines(&old, &new, &mut diff);
+    diff
+}
+
+fn diff_lines(old: &[&str], new: &[&str], diff: &mut Vec<String>) {
+    // Common prefix and suffix are not part of the diff
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() || new.is_empty() {
+        diff.extend(old.iter().map(|line| format!("-{}", line)));
+        diff.extend(new.iter().map(|line| format!("+{}", line)));
+        return;
+    }
+
+    if old.len() == 1 {
+        match new.iter().position(|line| *line == old[0]) {
+            Some(position) => {
+                diff.extend(new[..position].iter().map(|line| format!("+{}", line)));
+                diff.extend(new[position + 1..].iter().map(|line| format!("+{}", line)));
+            }
+            None => {
+                diff.push(format!("-{}", old[0]));
+                diff.extend(new.iter().map(|line| format!("+{}", line)));
+            }
+        }
+        return;
+    }
+
+    // Split the old lines in half and find where the LCS crosses into the
+    // second half of the new lines
+    let middle = old.len() / 2;
+    let forward = lcs_lengths(old[..middle].iter(), new.iter());
+    let backward = lcs_lengths(old[middle..].iter().rev(), new.iter().rev());
+    let split = (0..=new.len())
+        .max_by_key(|&k| (forward[k] + backward[new.len() - k], std::cmp::Reverse(k)))
+        .unwrap_or(0);
+
+    diff_lines(&old[..middle], &new[..split], diff);
+    diff_lines(&old[middle..], &new[split..], diff);
+}
+
+/// LCS length of `old` and every prefix of `new`, using a single row
+fn lcs_lengths<'a>(
+    old: impl Iterator<Item = &'a &'a str>,
+    new: impl Iterator<Item = &'a &'a str> + Clone,
+) -> Vec<usize> {
+    let len = new.clone().count();
+    let mut row = vec![0usize; len + 1];
+
+    for old_line in old {
+        let mut diagonal = 0;
+        for (j, new_line) in new.clone().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if old_line == new_line {
+                diagonal + 1
+            } else {
+                above.max(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_sdn_core::controller::ControllerConfig;
+    use pve_sdn_core::{ControllerType, ZoneConfig, ZoneType};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingReloader {
+        interfaces: AtomicUsize,
+        frr: AtomicUsize,
+        nftables: AtomicUsize,
+        wireguard: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SdnReloader for CountingReloader {
+        async fn reload_interfaces(&self) -> Result<()> {
+            self.interfaces.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn reload_frr(&self, _frr_config: &Path) -> Result<()> {
+            self.frr.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn reload_nftables(&self, _ruleset: &Path) -> Result<()> {
+            self.nftables.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn reload_wireguard(&self, _interface: &str, _wg_config: &Path) -> Result<()> {
+            self.wireguard.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn pipeline(dir: &Path, reloader: Arc<CountingReloader>) -> SdnApplyPipeline {
+        SdnApplyPipeline::new("node1")
+            .with_factory(Arc::new(PluginFactory::new()))
+            .with_paths(
+                dir.join("interfaces.d/sdn"),
+                dir.join("frr/frr.conf"),
+                dir.join("frr/frr.conf.local"),
+            )
+            .with_nftables_path(dir.join("nftables.d/pve-sdn.nft"))
+            .with_reloader(reloader)
+    }
+
+    fn sdn_config() -> SdnConfiguration {
+        let mut config = SdnConfiguration::new();
+
+        let mut simple = ZoneConfig::new(ZoneType::Simple, "simple1".to_string());
+        simple.bridge = Some("sdnbr1".to_string());
+        config.zones.insert("simple1".to_string(), simple);
+
+        let mut remote = ZoneConfig::new(ZoneType::Simple, "remote1".to_string());
+        remote.bridge = Some("sdnbr2".to_string());
+        remote.nodes = Some(vec!["node2".to_string()]);
+        config.zones.insert("remote1".to_string(), remote);
+
+        let mut bgp = ControllerConfig::new(ControllerType::Bgp, "bgp1".to_string());
+        bgp.asn = Some(65000);
+        bgp.node = Some("node1".to_string());
+        bgp.peers = Some(vec!["192.168.1.1".to_string()]);
+        bgp.options
+            .insert("router-id".to_string(), json!("192.168.1.10"));
+        config.controllers.insert("bgp1".to_string(), bgp);
+
+        config
+    }
+
+    #[tokio::test]
+    async fn test_generate_filters_by_node() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipeline = pipeline(dir.path(), Arc::new(CountingReloader::default()));
+
+        let generated = pipeline.generate(&sdn_config()).await.unwrap();
+
+        assert!(generated.interfaces.contains("iface sdnbr1 inet manual"));
+        assert!(!generated.interfaces.contains("sdnbr2"));
+        assert_eq!(generated.skipped_zones, vec!["remote1".to_string()]);
+
+        let frr = generated.frr.unwrap();
+        assert!(frr.contains("hostname node1"));
+        assert!(frr.contains("router bgp 65000"));
+        assert!(frr.ends_with("line vty\n!\n"));
+    }
+
+    #[tokio::test]
+    async fn test_frr_local_is_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("frr")).unwrap();
+        std::fs::write(
+            dir.path().join("frr/frr.conf.local"),
+            "ip prefix-list local seq 10 permit 10.0.0.0/8\n",
+        )
+        .unwrap();
+
+        let pipeline = pipeline(dir.path(), Arc::new(CountingReloader::default()));
+        let frr = pipeline.generate(&sdn_config()).await.unwrap().frr.unwrap();
+
+        let bgp = frr.find("router bgp 65000").unwrap();
+        let local = frr.find("ip prefix-list local").unwrap();
+        assert!(bgp < local);
+    }
+
+    #[tokio::test]
+    async fn test_frr_local_merged_by_section() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("frr")).unwrap();
+        std::fs::write(
+            dir.path().join("frr/frr.conf.local"),
+            "router bgp 65000\n\
+             \x20neighbor 10.9.9.9 remote-as 65010\n\
+             \x20address-family ipv4 unicast\n\
+             \x20\x20network 10.9.0.0/16\n\
+             \x20exit-address-family\n\
+             exit\n\
+             !\n\
+             ip prefix-list local seq 10 permit 10.0.0.0/8\n",
+        )
+        .unwrap();
+
+        let pipeline = pipeline(dir.path(), Arc::new(CountingReloader::default()));
+        let frr = pipeline.generate(&sdn_config()).await.unwrap().frr.unwrap();
+
+        assert_eq!(frr.matches("router bgp 65000").count(), 1);
+        let router = frr.find("router bgp 65000").unwrap();
+        let neighbor = frr.find(" neighbor 10.9.9.9 remote-as 65010").unwrap();
+        let family = frr.find(" address-family ipv4 unicast").unwrap();
+        let network = frr.find("  network 10.9.0.0/16").unwrap();
+        let family_end = family + frr[family..].find(" exit-address-family").unwrap();
+        assert!(router < neighbor && neighbor < family);
+        assert!(family < network && network < family_end);
+        assert!(frr.find("ip prefix-list local").unwrap() > family_end);
+    }
+
+    #[tokio::test]
+    async fn test_frr_reset_without_frr_zones() {
+        let dir = tempfile::tempdir().unwrap();
+        let reloader = Arc::new(CountingReloader::default());
+        let pipeline = pipeline(dir.path(), reloader.clone());
+        let mut config = sdn_config();
+
+        pipeline.apply(&config).await.unwrap();
+        assert!(std::fs::read_to_string(dir.path().join("frr/frr.conf"))
+            .unwrap()
+            .contains("router bgp 65000"));
+
+        config.controllers.clear();
+        let result = pipeline.apply(&config).await.unwrap();
+        assert!(result.frr_reloaded);
+        let frr = std::fs::read_to_string(dir.path().join("frr/frr.conf")).unwrap();
+        assert!(!frr.contains("router bgp"));
+        assert!(frr.starts_with(&frr_header("node1")));
+
+        // A frr.conf not rendered by the pipeline is left alone
+        std::fs::write(dir.path().join("frr/frr.conf"), "router ospf\n").unwrap();
+        let result = pipeline.apply(&config).await.unwrap();
+        assert!(result.frr.is_none());
+        assert_eq!(reloader.frr.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_reloads_only_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let reloader = Arc::new(CountingReloader::default());
+        let pipeline = pipeline(dir.path(), reloader.clone());
+        let mut config = sdn_config();
+
+        let result = pipeline.apply(&config).await.unwrap();
+        assert!(result.changed());
+        assert!(result.interfaces_reloaded);
+        assert!(result.frr_reloaded);
+        assert!(dir.path().join("interfaces.d/sdn").exists());
+
+        let result = pipeline.apply(&config).await.unwrap();
+        assert!(!result.changed());
+        assert_eq!(reloader.interfaces.load(Ordering::SeqCst), 1);
+        assert_eq!(reloader.frr.load(Ordering::SeqCst), 1);
+
+        config.zones.get_mut("simple1").unwrap().mtu = Some(1450);
+        let result = pipeline.apply(&config).await.unwrap();
+        assert!(result.interfaces_reloaded);
+        assert!(!result.frr_reloaded);
+        assert!(result
+            .interfaces
+            .diff
+            .contains(&"+    mtu 1450".to_string()));
+        assert_eq!(reloader.interfaces.load(Ordering::SeqCst), 2);
+        assert_eq!(reloader.frr.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_applied_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let pipeline = pipeline(dir.path(), Arc::new(CountingReloader::default()));
+        let mut config = sdn_config();
+
+        assert_eq!(pipeline.applied_version().await.unwrap(), None);
+
+        pipeline.apply(&config).await.unwrap();
+        assert_eq!(pipeline.applied_version().await.unwrap(), Some(1));
+
+        config.version = Some(7);
+        let result = pipeline.apply(&config).await.unwrap();
+        assert!(result.interfaces_reloaded);
+        assert_eq!(pipeline.applied_version().await.unwrap(), Some(7));
+    }
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!(diff, vec!["-b".to_string(), "+d".to_string()]);
+        assert!(line_diff("a\n", "a\n").is_empty());
+        assert_eq!(line_diff("", "a\n"), vec!["+a".to_string()]);
+        assert_eq!(
+            line_diff("a\nb\nc\nd\ne\n", "x\nb\nd\ny\ne\nz\n"),
+            vec!["-a", "+x", "-c", "+y", "+z"]
+        );
+
+        // Large inputs only need linear memory
+        let old: String = (0..20000).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 10000\n", "changed\n");
+        assert_eq!(line_diff(&old, &new), vec!["-line 10000", "+changed"]);
+    }
+
+    #[test]
+    fn test_tmp_path_outside_sourced_dir() {
+        assert_eq!(
+            tmp_path(Path::new("/etc/network/interfaces.d/sdn")),
+            PathBuf::from("/etc/network/.interfaces.d-sdn.tmp")
+        );
+        assert_eq!(
+            tmp_path(Path::new("/etc/frr/frr.conf")),
+            PathBuf::from("/etc/frr/.frr.conf.tmp")
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Sample out-of-process SDN plugin
+//!
+//! Provides a `sample` zone, controller and IPAM over the external plugin
+//! protocol. The zone gives every vnet an isolated bridge without uplink,
+//! the controller only tracks whether it was started and the IPAM keeps its
+//! allocations in memory. Load it with
+//! `PluginFactory::load_external_plugin("/path/to/pve-sdn-sample-plugin")`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use ipnet::IpNet;
+
+use pve_sdn_core::controller::{ControllerConfig, ControllerStatus};
+use pve_sdn_core::{
+    Controller, ControllerType, IpAllocation, IpAllocationRequest, IpamConfig, IpamPlugin,
+    IpamType, Subnet, VNet, Zone, ZoneConfig, ZoneRenderContext, ZoneType,
+};
+use pve_sdn_drivers::PluginServer;
+
+const DRIVER: &str = "sample";
+
+/// Zone with one isolated bridge per vnet
+struct SampleZone {
+    name: String,
+}
+
+#[async_trait]
+impl Zone for SampleZone {
+    fn zone_type(&self) -> ZoneType {
+        ZoneType::External(DRIVER.to_string())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self, config: &ZoneConfig) -> Result<()> {
+        if let Some(mtu) = config.mtu {
+            if !(576..=9000).contains(&mtu) {
+                bail!("Zone '{}': MTU {} is out of range", config.zone, mtu);
+            }
+        }
+        if config.bridge.is_some() {
+            bail!("Zone '{}': sample zones have no uplink bridge", config.zone);
+        }
+        Ok(())
+    }
+
+    async fn apply_config(&self, config: &ZoneConfig) -> Result<()> {
+        self.validate_config(config).await
+    }
+
+    async fn generate_config(&self, config: &ZoneConfig) -> Result<HashMap<String, String>> {
+        self.validate_config(config).await?;
+        Ok(HashMap::new())
+    }
+
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        self.validate_config(config).await?;
+
+        let mut configs = HashMap::new();
+        for vnet in ctx.vnets(&config.zone) {
+            let mut bridge = format!("auto {}\niface {} inet manual\n", vnet.vnet, vnet.vnet);
+            bridge.push_str("    bridge_ports none\n");
+            bridge.push_str("    bridge_stp off\n");
+            bridge.push_str("    bridge_fd 0\n");
+            if let Some(mtu) = config.mtu {
+                bridge.push_str(&format!("    mtu {}\n", mtu));
+            }
+            configs.insert(format!("vnet_{}", vnet.vnet), bridge);
+        }
+
+        Ok(configs)
+    }
+}
+
+/// Controller that only remembers whether it runs
+struct SampleController {
+    name: String,
+    running: Mutex<bool>,
+}
+
+#[async_trait]
+impl Controller for SampleController {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::External(DRIVER.to_string())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_configuration(&self, config: &ControllerConfig) -> Result<()> {
+        if config.asn.is_some() {
+            bail!(
+                "Controller '{}': sample controllers do not speak BGP",
+                config.controller
+            );
+        }
+        Ok(())
+    }
+
+    async fn apply_configuration(&self, zones: &[Box<dyn Zone>], _vnets: &[VNet]) -> Result<()> {
+        for zone in zones {
+            if zone.zone_type() != ZoneType::External(DRIVER.to_string()) {
+                bail!("Zone '{}' is not a sample zone", zone.name());
+            }
+        }
+        Ok(())
+    }
+
+    async fn generate_config(&self, _config: &ControllerConfig) -> Result<HashMap<String, String>> {
+        Ok(HashMap::new())
+    }
+
+    async fn start(&self) -> Result<()> {
+        *self.running.lock().unwrap() = true;
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<()> {
+        *self.running.lock().unwrap() = false;
+        Ok(())
+    }
+
+    async fn status(&self) -> Result<ControllerStatus> {
+        Ok(ControllerStatus {
+            running: *self.running.lock().unwrap(),
+            ..Default::default()
+        })
+    }
+
+    async fn reload(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct SampleSubnet {
+    cidr: IpNet,
+    gateway: Option<IpAddr>,
+    allocations: BTreeMap<IpAddr, IpAllocation>,
+}
+
+/// IPAM keeping its allocations in memory
+struct SampleIpam {
+    name: String,
+    subnets: Mutex<HashMap<String, SampleSubnet>>,
+}
+
+impl SampleIpam {
+    fn with_subnet<T>(
+        &self,
+        subnet: &str,
+        f: impl FnOnce(&mut SampleSubnet) -> Result<T>,
+    ) -> Result<T> {
+        let mut subnets = self.subnets.lock().unwrap();
+        match subnets.get_mut(subnet) {
+            Some(entry) => f(entry),
+            None => bail!("Subnet {} not found in IPAM", subnet),
+        }
+    }
+}
+
+impl SampleSubnet {
+    fn is_free(&self, ip: &IpAddr) -> bool {
+        Some(*ip) != self.gateway && !self.allocations.contains_key(ip)
+    }
+
+    fn next_free(&self) -> Option<IpAddr> {
+        self.cidr.hosts().find(|ip| self.is_free(ip))
+    }
+}
+
+#[async_trait]
+impl IpamPlugin for SampleIpam {
+    fn plugin_type(&self) -> IpamType {
+        IpamType::External(DRIVER.to_string())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self, _config: &IpamConfig) -> Result<()> {
+        Ok(())
+    }
+
+    async fn allocate_ip(&self, request: &IpAllocationRequest) -> Result<IpAllocation> {
+        self.with_subnet(&request.subnet, |subnet| {
+            let ip = match request.requested_ip {
+                Some(ip) if !subnet.cidr.contains(&ip) => {
+                    bail!("IP address {} is not in subnet {}", ip, request.subnet)
+                }
+                Some(ip) if !subnet.is_free(&ip) => {
+                    bail!(
+                        "IP address {} already allocated in subnet {}",
+                        ip,
+                        request.subnet
+                    )
+                }
+                Some(ip) => ip,
+                None => match subnet.next_free() {
+                    Some(ip) => ip,
+                    None => bail!(
+                        "No free IP addresses available in subnet {}",
+                        request.subnet
+                    ),
+                },
+            };
+
+            let allocation = IpAllocation {
+                ip,
+                subnet: request.subnet.clone(),
+                vmid: request.vmid,
+                hostname: request.hostname.clone(),
+                mac: request.mac.clone(),
+                description: request.description.clone(),
+                allocated_at: chrono::Utc::now(),
+            };
+            subnet.allocations.insert(ip, allocation.clone());
+            Ok(allocation)
+        })
+    }
+
+    async fn release_ip(&self, subnet: &str, ip: &IpAddr) -> Result<()> {
+        self.with_subnet(subnet, |entry| match entry.allocations.remove(ip) {
+            Some(_) => Ok(()),
+            None => bail!("IP address {} not found in subnet {}", ip, subnet),
+        })
+    }
+
+    async fn update_ip(&self, subnet: &str, ip: &IpAddr, allocation: &IpAllocation) -> Result<()> {
+        self.with_subnet(subnet, |entry| match entry.allocations.get_mut(ip) {
+            Some(existing) => {
+                *existing = allocation.clone();
+                Ok(())
+            }
+            None => bail!("IP address {} not found in subnet {}", ip, subnet),
+        })
+    }
+
+    async fn get_ip(&self, subnet: &str, ip: &IpAddr) -> Result<Option<IpAllocation>> {
+        self.with_subnet(subnet, |entry| Ok(entry.allocations.get(ip).cloned()))
+    }
+
+    async fn list_subnet_ips(&self, subnet: &str) -> Result<Vec<IpAllocation>> {
+        self.with_subnet(subnet, |entry| {
+            Ok(entry.allocations.values().cloned().collect())
+        })
+    }
+
+    async fn validate_subnet(&self, subnet: &Subnet) -> Result<()> {
+        if let Some(gateway) = subnet.config.gateway {
+            if !subnet.config.cidr.contains(&gateway) {
+                bail!(
+                    "Gateway {} is not in subnet {}",
+                    gateway,
+                    subnet.config.subnet
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn add_subnet(&self, subnet: &Subnet) -> Result<()> {
+        self.validate_subnet(subnet).await?;
+
+        let mut subnets = self.subnets.lock().unwrap();
+        subnets
+            .entry(subnet.config.subnet.clone())
+            .or_insert_with(|| SampleSubnet {
+                cidr: subnet.config.cidr,
+                gateway: subnet.config.gateway,
+                allocations: BTreeMap::new(),
+            });
+        Ok(())
+    }
+
+    async fn remove_subnet(&self, subnet_name: &str) -> Result<()> {
+        self.subnets.lock().unwrap().remove(subnet_name);
+        Ok(())
+    }
+
+    async fn get_next_free_ip(&self, subnet: &str) -> Result<Option<IpAddr>> {
+        self.with_subnet(subnet, |entry| Ok(entry.next_free()))
+    }
+
+    async fn is_ip_available(&self, subnet: &str, ip: &IpAddr) -> Result<bool> {
+        self.with_subnet(subnet, |entry| {
+            Ok(entry.cidr.contains(ip) && entry.is_free(ip))
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    PluginServer::new("sample", env!("CARGO_PKG_VERSION"))
+        .with_zone(
+            DRIVER,
+            Box::new(|name| Box::new(SampleZone { name }) as Box<dyn Zone>),
+        )
+        .with_controller(
+            DRIVER,
+            Box::new(|name| {
+                Box::new(SampleController {
+                    name,
+                    running: Mutex::new(false),
+                }) as Box<dyn Controller>
+            }),
+        )
+        .with_ipam(
+            DRIVER,
+            Box::new(|name| {
+                Box::new(SampleIpam {
+                    name,
+                    subnets: Mutex::new(HashMap::new()),
+                }) as Box<dyn IpamPlugin>
+            }),
+        )
+        .serve_stdio()
+        .await
+}
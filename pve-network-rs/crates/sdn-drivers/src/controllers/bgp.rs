@@ -3,15 +3,37 @@
 //! BGP controller provides basic BGP routing functionality for SDN zones.
 //! This controller manages FRR BGP daemon configuration and routing policies.
 
+use super::frr_status::{parse_bgp_summary, vtysh_json};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
-use pve_sdn_core::controller::{ControllerConfig, ControllerStatus};
-use pve_sdn_core::{Controller, ControllerType, VNet, Zone};
+use pve_sdn_core::controller::{
+    BgpPeerConfig, BgpPeerStatus, ControllerConfig, ControllerStatus, RouteMapEntry,
+};
+use pve_sdn_core::{Controller, ControllerType, RealExecutor, SystemExecutor, VNet, Zone};
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::path::Path;
-use tokio::process::Command;
+use std::sync::Arc;
+
+/// Whether the controller enables the IPv6 unicast address family
+fn ipv6_enabled(config: &ControllerConfig) -> bool {
+    config
+        .options
+        .get("ipv6")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Whether prefix-list `name` holds IPv6 prefixes
+fn prefix_list_is_ipv6(config: &ControllerConfig, name: &str) -> bool {
+    config
+        .prefix_lists
+        .iter()
+        .find(|l| l.name == name)
+        .map(|l| l.is_ipv6())
+        .unwrap_or(false)
+}
 
 /// BGP controller implementation
 ///
@@ -23,12 +45,18 @@ use tokio::process::Command;
 /// - Integration with Linux routing table
 pub struct BgpController {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl BgpController {
     /// Create new BGP controller
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new BGP controller running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
     }
 
     /// Validate BGP-specific configuration
@@ -58,6 +86,25 @@ impl BgpController {
             }
         }
 
+        // Typed peers are checked by ControllerConfig::validate, plain peers
+        // take description and password from their option object
+        for peer in self.effective_peers(config) {
+            peer.validate()
+                .with_context(|| format!("Invalid options for peer {}", peer.address))?;
+        }
+
+        // Session settings depending on the local ASN
+        for peer in &config.bgp_peers {
+            let internal = peer.remote_as() == "internal" || peer.remote_as() == asn.to_string();
+            if internal && peer.ebgp_multihop.is_some() {
+                anyhow::bail!(
+                    "Peer {} of BGP controller '{}' is internal, ebgp-multihop does not apply",
+                    peer.address,
+                    self.name
+                );
+            }
+        }
+
         // Validate router-id if specified
         if let Some(router_id) = config.options.get("router-id") {
             if let Some(router_id_str) = router_id.as_str() {
@@ -73,19 +120,221 @@ impl BgpController {
         Ok(())
     }
 
+    /// Neighbors of the controller
+    ///
+    /// Typed `bgp-peers` come first, followed by plain `peers` addresses
+    /// not covered by them. Plain peers take remote-as, description and
+    /// password from an optional `peer-<address>` option object.
+    fn effective_peers(&self, config: &ControllerConfig) -> Vec<BgpPeerConfig> {
+        let mut peers = config.bgp_peers.clone();
+
+        for peer in config.peers.iter().flatten() {
+            let address: IpAddr = match peer.parse() {
+                Ok(address) => address,
+                Err(_) => continue,
+            };
+            if peers.iter().any(|p| p.address == address) {
+                continue;
+            }
+
+            let mut typed = BgpPeerConfig::new(address);
+            if let Some(options) = config
+                .options
+                .get(&format!("peer-{}", peer))
+                .and_then(|v| v.as_object())
+            {
+                let text = |key: &str| {
+                    options
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string)
+                };
+                typed.remote_as = options
+                    .get("remote-as")
+                    .and_then(|v| v.as_u64())
+                    .map(|asn| asn.to_string());
+                typed.description = text("description");
+                typed.password = text("password");
+            }
+            peers.push(typed);
+        }
+
+        peers
+    }
+
+    /// Generate BFD profiles, prefix-lists and route-maps
+    fn generate_frr_policy_config(&self, config: &ControllerConfig) -> String {
+        let mut policy = String::new();
+
+        if !config.bfd_profiles.is_empty() {
+            policy.push_str("bfd\n");
+            for profile in &config.bfd_profiles {
+                policy.push_str(&format!(" profile {}\n", profile.name));
+                if let Some(multiplier) = profile.detect_multiplier {
+                    policy.push_str(&format!("  detect-multiplier {}\n", multiplier));
+                }
+                if let Some(interval) = profile.receive_interval {
+                    policy.push_str(&format!("  receive-interval {}\n", interval));
+                }
+                if let Some(interval) = profile.transmit_interval {
+                    policy.push_str(&format!("  transmit-interval {}\n", interval));
+                }
+                policy.push_str(" exit\n");
+            }
+            policy.push_str("exit\n!\n");
+        }
+
+        for list in &config.prefix_lists {
+            let family = if list.is_ipv6() { "ipv6" } else { "ip" };
+            for entry in &list.entries {
+                policy.push_str(&format!(
+                    "{} prefix-list {} seq {} {} {}",
+                    family, list.name, entry.seq, entry.action, entry.prefix
+                ));
+                if let Some(ge) = entry.ge {
+                    policy.push_str(&format!(" ge {}", ge));
+                }
+                if let Some(le) = entry.le {
+                    policy.push_str(&format!(" le {}", le));
+                }
+                policy.push('\n');
+            }
+        }
+        if !config.prefix_lists.is_empty() {
+            policy.push_str("!\n");
+        }
+
+        // Prefix-lists only match routes of their own family, so route-maps
+        // matching them are rendered once per address family
+        for map in &config.route_maps {
+            if !map.is_family_specific() {
+                self.push_route_map(&mut policy, config, &map.name, &map.entries);
+                continue;
+            }
+
+            let mut families = vec![(map.name.clone(), false)];
+            if ipv6_enabled(config) {
+                families.push((map.ipv6_name(), true));
+            }
+            for (name, ipv6) in families {
+                let entries: Vec<RouteMapEntry> = map
+                    .entries
+                    .iter()
+                    .filter(|entry| match &entry.match_prefix_list {
+                        Some(list) => prefix_list_is_ipv6(config, list) == ipv6,
+                        None => true,
+                    })
+                    .cloned()
+                    .collect();
+
+                if entries.is_empty() {
+                    // Keep the map defined, none of its entries can match
+                    let seq = map.entries.iter().map(|e| e.seq).min().unwrap_or(10);
+                    policy.push_str(&format!("route-map {} deny {}\nexit\n!\n", name, seq));
+                } else {
+                    self.push_route_map(&mut policy, config, &name, &entries);
+                }
+            }
+        }
+
+        policy
+    }
+
+    /// Render the entries of route-map `name`
+    fn push_route_map(
+        &self,
+        policy: &mut String,
+        config: &ControllerConfig,
+        name: &str,
+        entries: &[RouteMapEntry],
+    ) {
+        for entry in entries {
+            policy.push_str(&format!(
+                "route-map {} {} {}\n",
+                name, entry.action, entry.seq
+            ));
+            if let Some(list) = &entry.match_prefix_list {
+                let family = if prefix_list_is_ipv6(config, list) {
+                    "ipv6"
+                } else {
+                    "ip"
+                };
+                policy.push_str(&format!(" match {} address prefix-list {}\n", family, list));
+            }
+            if let Some(local_pref) = entry.set_local_preference {
+                policy.push_str(&format!(" set local-preference {}\n", local_pref));
+            }
+            if let Some(metric) = entry.set_metric {
+                policy.push_str(&format!(" set metric {}\n", metric));
+            }
+            if let Some(community) = &entry.set_community {
+                policy.push_str(&format!(" set community {}\n", community));
+            }
+            policy.push_str("exit\n!\n");
+        }
+    }
+
+    /// Generate address family activation and filters for `peers`
+    fn generate_frr_address_family(
+        &self,
+        config: &ControllerConfig,
+        peers: &[BgpPeerConfig],
+        ipv6: bool,
+    ) -> String {
+        let mut af = String::new();
+
+        for peer in peers {
+            let address = peer.address;
+            af.push_str(&format!("  neighbor {} activate\n", address));
+
+            for (list, direction) in [(&peer.prefix_list_in, "in"), (&peer.prefix_list_out, "out")]
+            {
+                // Prefix-lists only apply to their own address family
+                let matches_af = list
+                    .as_ref()
+                    .and_then(|name| config.prefix_lists.iter().find(|l| &l.name == name))
+                    .map(|l| l.is_ipv6() == ipv6)
+                    .unwrap_or(false);
+                if let (Some(list), true) = (list, matches_af) {
+                    af.push_str(&format!(
+                        "  neighbor {} prefix-list {} {}\n",
+                        address, list, direction
+                    ));
+                }
+            }
+
+            for (map, direction) in [(&peer.route_map_in, "in"), (&peer.route_map_out, "out")] {
+                if let Some(map) = map {
+                    let name = match config.route_maps.iter().find(|m| &m.name == map) {
+                        Some(route_map) if ipv6 => route_map.ipv6_name(),
+                        _ => map.clone(),
+                    };
+                    af.push_str(&format!(
+                        "  neighbor {} route-map {} {}\n",
+                        address, name, direction
+                    ));
+                }
+            }
+        }
+
+        af
+    }
+
     /// Generate FRR BGP configuration
     fn generate_frr_bgp_config(&self, config: &ControllerConfig) -> Result<String> {
         let asn = config.asn.unwrap();
+        let peers = self.effective_peers(config);
 
         let mut bgp_config = format!(
             "!\n\
              ! BGP configuration for controller {}\n\
-             !\n\
-             router bgp {asn}\n",
+             !\n",
             self.name,
-            asn = asn
         );
 
+        bgp_config.push_str(&self.generate_frr_policy_config(config));
+        bgp_config.push_str(&format!("router bgp {}\n", asn));
+
         // Add router-id if specified
         if let Some(router_id) = config.options.get("router-id") {
             if let Some(router_id_str) = router_id.as_str() {
@@ -103,53 +352,47 @@ impl BgpController {
         }
 
         // Add BGP neighbors
-        if let Some(peers) = &config.peers {
-            for peer in peers {
-                bgp_config.push_str(&format!(" neighbor {} remote-as external\n", peer));
-                bgp_config.push_str(&format!(" neighbor {} capability extended-nexthop\n", peer));
-
-                // Add peer-specific options
-                if let Some(peer_options) = config.options.get(&format!("peer-{}", peer)) {
-                    if let Some(peer_obj) = peer_options.as_object() {
-                        if let Some(remote_asn) = peer_obj.get("remote-as") {
-                            if let Some(remote_asn_num) = remote_asn.as_u64() {
-                                bgp_config = bgp_config.replace(
-                                    &format!(" neighbor {} remote-as external\n", peer),
-                                    &format!(" neighbor {} remote-as {}\n", peer, remote_asn_num),
-                                );
-                            }
-                        }
-
-                        if let Some(description) = peer_obj.get("description") {
-                            if let Some(desc_str) = description.as_str() {
-                                bgp_config.push_str(&format!(
-                                    " neighbor {} description {}\n",
-                                    peer, desc_str
-                                ));
-                            }
-                        }
-
-                        if let Some(password) = peer_obj.get("password") {
-                            if let Some(pass_str) = password.as_str() {
-                                bgp_config.push_str(&format!(
-                                    " neighbor {} password {}\n",
-                                    peer, pass_str
-                                ));
-                            }
-                        }
-                    }
+        for peer in &peers {
+            let address = peer.address;
+            bgp_config.push_str(&format!(
+                " neighbor {} remote-as {}\n",
+                address,
+                peer.remote_as()
+            ));
+            bgp_config.push_str(&format!(
+                " neighbor {} capability extended-nexthop\n",
+                address
+            ));
+
+            if let Some(description) = &peer.description {
+                bgp_config.push_str(&format!(
+                    " neighbor {} description {}\n",
+                    address, description
+                ));
+            }
+            if let Some(hops) = peer.ebgp_multihop {
+                bgp_config.push_str(&format!(" neighbor {} ebgp-multihop {}\n", address, hops));
+            }
+            if let Some(source) = &peer.update_source {
+                bgp_config.push_str(&format!(" neighbor {} update-source {}\n", address, source));
+            }
+            if let Some(password) = &peer.password {
+                bgp_config.push_str(&format!(" neighbor {} password {}\n", address, password));
+            }
+            match &peer.bfd_profile {
+                Some(profile) => {
+                    bgp_config.push_str(&format!(" neighbor {} bfd profile {}\n", address, profile))
+                }
+                None if peer.bfd_enabled() => {
+                    bgp_config.push_str(&format!(" neighbor {} bfd\n", address))
                 }
+                None => {}
             }
         }
 
         // Address family configuration
         bgp_config.push_str(" !\n address-family ipv4 unicast\n");
-
-        if let Some(peers) = &config.peers {
-            for peer in peers {
-                bgp_config.push_str(&format!("  neighbor {} activate\n", peer));
-            }
-        }
+        bgp_config.push_str(&self.generate_frr_address_family(config, &peers, false));
 
         // Network advertisements
         if let Some(networks) = config.options.get("networks") {
@@ -176,15 +419,9 @@ impl BgpController {
         bgp_config.push_str(" exit-address-family\n");
 
         // IPv6 address family if enabled
-        if let Some(true) = config.options.get("ipv6").and_then(|v| v.as_bool()) {
+        if ipv6_enabled(config) {
             bgp_config.push_str(" !\n address-family ipv6 unicast\n");
-
-            if let Some(peers) = &config.peers {
-                for peer in peers {
-                    bgp_config.push_str(&format!("  neighbor {} activate\n", peer));
-                }
-            }
-
+            bgp_config.push_str(&self.generate_frr_address_family(config, &peers, true));
             bgp_config.push_str(" exit-address-family\n");
         }
 
@@ -216,11 +453,19 @@ impl BgpController {
         Ok(systemd_config)
     }
 
+    /// BGP sessions of all address families
+    pub async fn peer_status(&self) -> Result<Vec<BgpPeerStatus>> {
+        let json = vtysh_json(self.executor.as_ref(), "show bgp summary json")
+            .await
+            .with_context(|| format!("Failed to query BGP controller '{}'", self.name))?;
+        parse_bgp_summary(&json)
+    }
+
     /// Check if FRR is installed
     async fn check_frr_installed(&self) -> Result<bool> {
-        let output = Command::new("which").arg("bgpd").output().await?;
+        let output = self.executor.query("which", &["bgpd"]).await?;
 
-        Ok(output.status.success())
+        Ok(output.is_success())
     }
 
     /// Get BGP daemon PID
@@ -238,12 +483,12 @@ impl BgpController {
             .with_context(|| format!("Invalid PID in file {}", pid_file))?;
 
         // Check if process is actually running
-        let output = Command::new("kill")
-            .args(&["-0", &pid.to_string()])
-            .output()
+        let output = self
+            .executor
+            .query("kill", &["-0", &pid.to_string()])
             .await?;
 
-        if output.status.success() {
+        if output.is_success() {
             Ok(Some(pid))
         } else {
             Ok(None)
@@ -377,16 +622,19 @@ impl Controller for BgpController {
         let config_file = format!("/etc/frr/bgpd-{}.conf", self.name);
         let pid_file = format!("/var/run/frr/bgpd-{}.pid", self.name);
 
-        let output = Command::new("/usr/lib/frr/bgpd")
-            .args(&["-d", "-f", &config_file, "--pid_file", &pid_file])
-            .output()
+        let output = self
+            .executor
+            .execute(
+                "/usr/lib/frr/bgpd",
+                &["-d", "-f", &config_file, "--pid_file", &pid_file],
+            )
             .await
             .with_context(|| {
                 format!("Failed to start BGP daemon for controller '{}'", self.name)
             })?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
             anyhow::bail!("Failed to start BGP controller '{}': {}", self.name, stderr);
         }
 
@@ -398,25 +646,25 @@ impl Controller for BgpController {
         debug!("Stopping BGP controller '{}'", self.name);
 
         if let Some(pid) = self.get_bgp_pid().await? {
-            let output = Command::new("kill")
-                .args(&["-TERM", &pid.to_string()])
-                .output()
+            let output = self
+                .executor
+                .execute("kill", &["-TERM", &pid.to_string()])
                 .await
                 .with_context(|| {
                     format!("Failed to stop BGP daemon for controller '{}'", self.name)
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 error!("Failed to stop BGP controller '{}': {}", self.name, stderr);
 
                 // Try force kill
-                let output = Command::new("kill")
-                    .args(&["-KILL", &pid.to_string()])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("kill", &["-KILL", &pid.to_string()])
                     .await?;
 
-                if !output.status.success() {
+                if !output.is_success() {
                     anyhow::bail!("Failed to force stop BGP controller '{}'", self.name);
                 }
             }
@@ -469,12 +717,19 @@ impl Controller for BgpController {
             None
         };
 
+        let (bgp_peers, last_error) = match self.peer_status().await {
+            Ok(peers) => (peers, None),
+            Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+        };
+
         Ok(ControllerStatus {
             running,
             pid,
             uptime,
-            last_error: None,
+            last_error,
             config_version: None,
+            bgp_peers,
+            ..Default::default()
         })
     }
 
@@ -482,16 +737,16 @@ impl Controller for BgpController {
         debug!("Reloading BGP controller '{}'", self.name);
 
         if let Some(pid) = self.get_bgp_pid().await? {
-            let output = Command::new("kill")
-                .args(&["-HUP", &pid.to_string()])
-                .output()
+            let output = self
+                .executor
+                .execute("kill", &["-HUP", &pid.to_string()])
                 .await
                 .with_context(|| {
                     format!("Failed to reload BGP daemon for controller '{}'", self.name)
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to reload BGP controller '{}': {}",
                     self.name,
@@ -582,4 +837,191 @@ mod tests {
         assert!(frr_config.contains("network 10.0.0.0/24"));
         assert!(frr_config.contains("network 10.1.0.0/24"));
     }
+
+    /// Compare `actual` with `testdata/bgp/<name>`, rewriting the file
+    /// instead when `UPDATE_GOLDEN` is set
+    fn assert_golden(name: &str, actual: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/bgp")
+            .join(name);
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+        assert_eq!(
+            actual,
+            expected,
+            "FRR output differs from {}, rerun with UPDATE_GOLDEN=1 to update",
+            path.display()
+        );
+    }
+
+    fn policy_config() -> ControllerConfig {
+        let mut config = ControllerConfig::new(ControllerType::Bgp, "edge".to_string());
+        config.asn = Some(65000);
+        config.ebgp_requires_policy = Some(false);
+        config
+            .options
+            .insert("router-id".to_string(), json!("10.255.0.1"));
+        config.options.insert("ipv6".to_string(), json!(true));
+        config
+            .options
+            .insert("redistribute".to_string(), json!(["connected"]));
+
+        config.bfd_profiles = serde_json::from_value(json!([
+            {"name": "fast", "detect-multiplier": 3, "receive-interval": 100, "transmit-interval": 100}
+        ]))
+        .unwrap();
+        config.prefix_lists = serde_json::from_value(json!([
+            {"name": "TENANTS", "entries": [
+                {"seq": 10, "action": "permit", "prefix": "10.0.0.0/8", "le": 24},
+                {"seq": 20, "action": "deny", "prefix": "0.0.0.0/0"}
+            ]},
+            {"name": "TENANTS6", "entries": [
+                {"seq": 10, "action": "permit", "prefix": "fd00::/8", "le": 64}
+            ]}
+        ]))
+        .unwrap();
+        config.route_maps = serde_json::from_value(json!([
+            {"name": "EXPORT", "entries": [
+                {"seq": 10, "action": "permit", "match-prefix-list": "TENANTS", "set-community": "65000:100"},
+                {"seq": 15, "action": "permit", "match-prefix-list": "TENANTS6", "set-community": "65000:100"},
+                {"seq": 20, "action": "deny"}
+            ]},
+            {"name": "IMPORT", "entries": [
+                {"seq": 10, "action": "permit", "set-local-preference": 200}
+            ]}
+        ]))
+        .unwrap();
+        config.bgp_peers = serde_json::from_value(json!([
+            {
+                "address": "192.0.2.1",
+                "remote-as": "64512",
+                "description": "upstream",
+                "ebgp-multihop": 2,
+                "update-source": "dummy_uplink",
+                "password": "s3cret",
+                "bfd-profile": "fast",
+                "prefix-list-out": "TENANTS",
+                "route-map-in": "IMPORT",
+                "route-map-out": "EXPORT"
+            },
+            {
+                "address": "2001:db8::1",
+                "remote-as": "64513",
+                "bfd": true,
+                "prefix-list-out": "TENANTS6"
+            }
+        ]))
+        .unwrap();
+        config.peers = Some(vec!["192.0.2.1".to_string(), "10.0.0.2".to_string()]);
+        config.options.insert(
+            "peer-10.0.0.2".to_string(),
+            json!({"remote-as": 65001, "description": "legacy"}),
+        );
+
+        config
+    }
+
+    #[tokio::test]
+    async fn test_bgp_golden_basic() {
+        let controller = BgpController::new("test-bgp".to_string());
+
+        let mut config = ControllerConfig::new(ControllerType::Bgp, "test-bgp".to_string());
+        config.asn = Some(65000);
+        config.peers = Some(vec!["192.168.1.1".to_string(), "192.168.1.2".to_string()]);
+        config
+            .options
+            .insert("router-id".to_string(), json!("192.168.1.10"));
+        config
+            .options
+            .insert("networks".to_string(), json!(["10.0.0.0/24"]));
+        config.bgp_multipath_relax = Some(true);
+
+        let configs = controller.generate_config(&config).await.unwrap();
+        assert_golden("basic.frr", &configs["frr"]);
+    }
+
+    #[tokio::test]
+    async fn test_bgp_golden_peer_policy() {
+        let controller = BgpController::new("edge".to_string());
+        let config = policy_config();
+
+        controller.validate_configuration(&config).await.unwrap();
+        let configs = controller.generate_config(&config).await.unwrap();
+        assert_golden("peer_policy.frr", &configs["frr"]);
+    }
+
+    #[tokio::test]
+    async fn test_bgp_peer_validation() {
+        let controller = BgpController::new("edge".to_string());
+
+        // Unknown BFD profile
+        let mut config = policy_config();
+        config.bgp_peers[0].bfd_profile = Some("slow".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Unknown route-map
+        let mut config = policy_config();
+        config.bgp_peers[1].route_map_in = Some("MISSING".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Route-map matching an unknown prefix-list
+        let mut config = policy_config();
+        config.route_maps[1].entries[0].match_prefix_list = Some("MISSING".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Invalid remote-as
+        let mut config = policy_config();
+        config.bgp_peers[0].remote_as = Some("upstream".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // ebgp-multihop on an iBGP session
+        let mut config = policy_config();
+        config.bgp_peers[0].remote_as = Some("65000".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Description breaking out of its line
+        let mut config = policy_config();
+        config.bgp_peers[0].description = Some("up\n neighbor 192.0.2.9 remote-as 1".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+        let mut config = policy_config();
+        config.options.insert(
+            "peer-10.0.0.2".to_string(),
+            json!({"remote-as": 65001, "description": "legacy\nexit"}),
+        );
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Route-map clashing with the IPv6 variant of EXPORT
+        let mut config = policy_config();
+        let mut clash = config.route_maps[1].clone();
+        clash.name = "EXPORT_v6".to_string();
+        config.route_maps.push(clash);
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Password with whitespace
+        let mut config = policy_config();
+        config.bgp_peers[0].password = Some("two words".to_string());
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Duplicate peer
+        let mut config = policy_config();
+        let duplicate = config.bgp_peers[0].clone();
+        config.bgp_peers.push(duplicate);
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Prefix-list with le below the prefix length
+        let mut config = policy_config();
+        config.prefix_lists[0].entries[0].le = Some(4);
+        assert!(controller.validate_configuration(&config).await.is_err());
+
+        // Mixed address families in one prefix-list
+        let mut config = policy_config();
+        config.prefix_lists[0].entries[1].prefix = "::/0".parse().unwrap();
+        assert!(controller.validate_configuration(&config).await.is_err());
+    }
 }
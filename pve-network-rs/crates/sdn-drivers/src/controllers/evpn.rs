@@ -3,15 +3,18 @@
 //! EVPN controller provides BGP EVPN control plane functionality for SDN zones.
 //! This controller manages FRR BGP EVPN configuration and VXLAN integration.
 
+use super::frr_status::{parse_bgp_evpn_summary, parse_evpn_macs, parse_evpn_vnis, vtysh_json};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
-use pve_sdn_core::controller::{ControllerConfig, ControllerStatus};
-use pve_sdn_core::{Controller, ControllerType, VNet, Zone};
+use pve_sdn_core::controller::{
+    BgpPeerStatus, ControllerConfig, ControllerStatus, EvpnMacEntry, EvpnVniStatus,
+};
+use pve_sdn_core::{Controller, ControllerType, RealExecutor, SystemExecutor, VNet, Zone};
 use std::collections::HashMap;
 use std::net::IpAddr;
 use std::path::Path;
-use tokio::process::Command;
+use std::sync::Arc;
 
 /// EVPN controller implementation
 ///
@@ -25,12 +28,18 @@ use tokio::process::Command;
 /// - Integrated routing and bridging (IRB)
 pub struct EvpnController {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl EvpnController {
     /// Create new EVPN controller
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new EVPN controller running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
     }
 
     /// Validate EVPN-specific configuration
@@ -82,8 +91,11 @@ impl EvpnController {
                     )
                 })?;
             }
-        } else {
-            anyhow::bail!("EVPN controller '{}' requires a VTEP IP address", self.name);
+        } else if !config.options.contains_key("fabric") {
+            anyhow::bail!(
+                "EVPN controller '{}' requires a VTEP IP address or a fabric",
+                self.name
+            );
         }
 
         Ok(())
@@ -291,16 +303,37 @@ impl EvpnController {
 
     /// Check if FRR is installed with EVPN support
     async fn check_frr_evpn_support(&self) -> Result<bool> {
-        let output = Command::new("bgpd").args(&["--help"]).output().await?;
+        let output = self.executor.query("bgpd", &["--help"]).await?;
 
-        if !output.status.success() {
+        if !output.is_success() {
             return Ok(false);
         }
 
-        let help_text = String::from_utf8_lossy(&output.stdout);
+        let help_text = output.stdout.as_str();
         Ok(help_text.contains("evpn") || help_text.contains("l2vpn"))
     }
 
+    async fn query(&self, command: &str) -> Result<String> {
+        vtysh_json(self.executor.as_ref(), command)
+            .await
+            .with_context(|| format!("Failed to query EVPN controller '{}'", self.name))
+    }
+
+    /// BGP sessions of the L2VPN EVPN address family
+    pub async fn peer_status(&self) -> Result<Vec<BgpPeerStatus>> {
+        parse_bgp_evpn_summary(&self.query("show bgp l2vpn evpn summary json").await?)
+    }
+
+    /// L2 and L3 VNIs known to zebra
+    pub async fn vni_status(&self) -> Result<Vec<EvpnVniStatus>> {
+        parse_evpn_vnis(&self.query("show evpn vni json").await?)
+    }
+
+    /// MAC/IP table of all VNIs
+    pub async fn mac_table(&self) -> Result<Vec<EvpnMacEntry>> {
+        parse_evpn_macs(&self.query("show evpn mac vni all json").await?)
+    }
+
     /// Get EVPN BGP daemon PID
     async fn get_evpn_bgp_pid(&self) -> Result<Option<u32>> {
         let pid_file = format!("/var/run/frr/bgpd-evpn-{}.pid", self.name);
@@ -316,12 +349,12 @@ impl EvpnController {
             .with_context(|| format!("Invalid PID in file {}", pid_file))?;
 
         // Check if process is actually running
-        let output = Command::new("kill")
-            .args(&["-0", &pid.to_string()])
-            .output()
+        let output = self
+            .executor
+            .query("kill", &["-0", &pid.to_string()])
             .await?;
 
-        if output.status.success() {
+        if output.is_success() {
             Ok(Some(pid))
         } else {
             Ok(None)
@@ -482,9 +515,12 @@ impl Controller for EvpnController {
         let config_file = format!("/etc/frr/bgpd-evpn-{}.conf", self.name);
         let pid_file = format!("/var/run/frr/bgpd-evpn-{}.pid", self.name);
 
-        let output = Command::new("/usr/lib/frr/bgpd")
-            .args(&["-d", "-f", &config_file, "--pid_file", &pid_file])
-            .output()
+        let output = self
+            .executor
+            .execute(
+                "/usr/lib/frr/bgpd",
+                &["-d", "-f", &config_file, "--pid_file", &pid_file],
+            )
             .await
             .with_context(|| {
                 format!(
@@ -493,8 +529,8 @@ impl Controller for EvpnController {
                 )
             })?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
             anyhow::bail!(
                 "Failed to start EVPN controller '{}': {}",
                 self.name,
@@ -510,9 +546,9 @@ impl Controller for EvpnController {
         debug!("Stopping EVPN controller '{}'", self.name);
 
         if let Some(pid) = self.get_evpn_bgp_pid().await? {
-            let output = Command::new("kill")
-                .args(&["-TERM", &pid.to_string()])
-                .output()
+            let output = self
+                .executor
+                .execute("kill", &["-TERM", &pid.to_string()])
                 .await
                 .with_context(|| {
                     format!(
@@ -521,17 +557,17 @@ impl Controller for EvpnController {
                     )
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 error!("Failed to stop EVPN controller '{}': {}", self.name, stderr);
 
                 // Try force kill
-                let output = Command::new("kill")
-                    .args(&["-KILL", &pid.to_string()])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("kill", &["-KILL", &pid.to_string()])
                     .await?;
 
-                if !output.status.success() {
+                if !output.is_success() {
                     anyhow::bail!("Failed to force stop EVPN controller '{}'", self.name);
                 }
             }
@@ -584,12 +620,30 @@ impl Controller for EvpnController {
             None
         };
 
+        // Collect as much as possible, the first failing query is reported
+        let mut errors = Vec::new();
+        let bgp_peers = self.peer_status().await.unwrap_or_else(|e| {
+            errors.push(format!("{:#}", e));
+            Vec::new()
+        });
+        let evpn_vnis = self.vni_status().await.unwrap_or_else(|e| {
+            errors.push(format!("{:#}", e));
+            Vec::new()
+        });
+        let evpn_macs = self.mac_table().await.unwrap_or_else(|e| {
+            errors.push(format!("{:#}", e));
+            Vec::new()
+        });
+
         Ok(ControllerStatus {
             running,
             pid,
             uptime,
-            last_error: None,
+            last_error: errors.into_iter().next(),
             config_version: None,
+            bgp_peers,
+            evpn_vnis,
+            evpn_macs,
         })
     }
 
@@ -597,9 +651,9 @@ impl Controller for EvpnController {
         debug!("Reloading EVPN controller '{}'", self.name);
 
         if let Some(pid) = self.get_evpn_bgp_pid().await? {
-            let output = Command::new("kill")
-                .args(&["-HUP", &pid.to_string()])
-                .output()
+            let output = self
+                .executor
+                .execute("kill", &["-HUP", &pid.to_string()])
                 .await
                 .with_context(|| {
                     format!(
@@ -608,8 +662,8 @@ impl Controller for EvpnController {
                     )
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to reload EVPN controller '{}': {}",
                     self.name,
@@ -632,6 +686,7 @@ impl Controller for EvpnController {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pve_sdn_core::{CommandOutput, RecordingExecutor};
     use serde_json::json;
 
     #[tokio::test]
@@ -697,4 +752,40 @@ mod tests {
         assert!(frr_config.contains("redistribute connected"));
         assert!(frr_config.contains("ip address 192.168.1.10/32"));
     }
+
+    #[tokio::test]
+    async fn test_evpn_status_from_vtysh() {
+        let executor = Arc::new(RecordingExecutor::new());
+        let controller = EvpnController::with_executor("evpn1".to_string(), executor.clone());
+
+        executor
+            .respond(
+                "vtysh -c show bgp l2vpn evpn summary json",
+                CommandOutput::success(include_str!("../../testdata/frr/bgp_evpn_summary.json")),
+            )
+            .respond(
+                "vtysh -c show evpn vni json",
+                CommandOutput::success(include_str!("../../testdata/frr/evpn_vni.json")),
+            )
+            .respond(
+                "vtysh -c show evpn mac vni all json",
+                CommandOutput::success(include_str!("../../testdata/frr/evpn_mac.json")),
+            );
+
+        let status = controller.status().await.unwrap();
+        assert!(status.last_error.is_none());
+        assert_eq!(status.bgp_peers.len(), 2);
+        assert_eq!(status.evpn_vnis.len(), 3);
+        assert_eq!(status.evpn_macs.len(), 3);
+
+        // A failing query leaves the other tables intact
+        executor.respond(
+            "vtysh -c show evpn mac vni all json",
+            CommandOutput::failure(1, "zebra is not running"),
+        );
+        let status = controller.status().await.unwrap();
+        assert_eq!(status.evpn_vnis.len(), 3);
+        assert!(status.evpn_macs.is_empty());
+        assert!(status.last_error.unwrap().contains("zebra is not running"));
+    }
 }
@@ -7,11 +7,11 @@ use anyhow::{Context, Result};
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
 use pve_sdn_core::controller::{ControllerConfig, ControllerStatus};
-use pve_sdn_core::{Controller, ControllerType, VNet, Zone};
+use pve_sdn_core::{Controller, ControllerType, RealExecutor, SystemExecutor, VNet, Zone};
 use serde_yaml;
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use tokio::process::Command;
+use std::sync::Arc;
 
 /// Faucet controller implementation
 ///
@@ -24,12 +24,18 @@ use tokio::process::Command;
 /// - Integration with Gauge for monitoring
 pub struct FaucetController {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl FaucetController {
     /// Create new Faucet controller
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new Faucet controller running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
     }
 
     /// Validate Faucet-specific configuration
@@ -353,20 +359,20 @@ impl FaucetController {
 
     /// Check if Faucet is installed
     async fn check_faucet_installed(&self) -> Result<bool> {
-        let output = Command::new("which").arg("faucet").output().await?;
+        let output = self.executor.query("which", &["faucet"]).await?;
 
-        Ok(output.status.success())
+        Ok(output.is_success())
     }
 
     /// Get Faucet process PID
     async fn get_faucet_pid(&self) -> Result<Option<u32>> {
-        let output = Command::new("pgrep")
-            .args(&["-f", &format!("faucet-{}.yaml", self.name)])
-            .output()
+        let output = self
+            .executor
+            .query("pgrep", &["-f", &format!("faucet-{}.yaml", self.name)])
             .await?;
 
-        if output.status.success() {
-            let pid_str = String::from_utf8_lossy(&output.stdout);
+        if output.is_success() {
+            let pid_str = output.stdout.as_str();
             if let Ok(pid) = pid_str.trim().parse::<u32>() {
                 Ok(Some(pid))
             } else {
@@ -530,51 +536,61 @@ impl Controller for FaucetController {
         let config_file = format!("/etc/faucet/faucet-{}.yaml", self.name);
         let controller_addr = "tcp:0.0.0.0:6653"; // Default OpenFlow port
 
-        let output = Command::new("faucet")
-            .args(&[
-                "--config-file",
-                &config_file,
-                "--ofp-listen-host",
-                controller_addr,
-                "--verbose",
-            ])
-            .spawn();
-
-        match output {
-            Ok(_child) => {
-                info!("Faucet controller '{}' started successfully", self.name);
-                Ok(())
-            }
-            Err(e) => {
-                anyhow::bail!("Failed to start Faucet controller '{}': {}", self.name, e);
-            }
+        // Run the daemon as a transient unit so it outlives this process
+        let unit = format!("--unit=faucet-{}", self.name);
+        let output = self
+            .executor
+            .execute(
+                "systemd-run",
+                &[
+                    &unit,
+                    "faucet",
+                    "--config-file",
+                    &config_file,
+                    "--ofp-listen-host",
+                    controller_addr,
+                    "--verbose",
+                ],
+            )
+            .await
+            .with_context(|| format!("Failed to start Faucet controller '{}'", self.name))?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "Failed to start Faucet controller '{}': {}",
+                self.name,
+                output.stderr.trim()
+            );
         }
+
+        info!("Faucet controller '{}' started successfully", self.name);
+        Ok(())
     }
 
     async fn stop(&self) -> Result<()> {
         debug!("Stopping Faucet controller '{}'", self.name);
 
         if let Some(pid) = self.get_faucet_pid().await? {
-            let output = Command::new("kill")
-                .args(&["-TERM", &pid.to_string()])
-                .output()
+            let output = self
+                .executor
+                .execute("kill", &["-TERM", &pid.to_string()])
                 .await
                 .with_context(|| format!("Failed to stop Faucet controller '{}'", self.name))?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 error!(
                     "Failed to stop Faucet controller '{}': {}",
                     self.name, stderr
                 );
 
                 // Try force kill
-                let output = Command::new("kill")
-                    .args(&["-KILL", &pid.to_string()])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("kill", &["-KILL", &pid.to_string()])
                     .await?;
 
-                if !output.status.success() {
+                if !output.is_success() {
                     anyhow::bail!("Failed to force stop Faucet controller '{}'", self.name);
                 }
             }
@@ -633,6 +649,7 @@ impl Controller for FaucetController {
             uptime,
             last_error: None,
             config_version: None,
+            ..Default::default()
         })
     }
 
@@ -640,14 +657,14 @@ impl Controller for FaucetController {
         debug!("Reloading Faucet controller '{}'", self.name);
 
         if let Some(pid) = self.get_faucet_pid().await? {
-            let output = Command::new("kill")
-                .args(&["-HUP", &pid.to_string()])
-                .output()
+            let output = self
+                .executor
+                .execute("kill", &["-HUP", &pid.to_string()])
                 .await
                 .with_context(|| format!("Failed to reload Faucet controller '{}'", self.name))?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to reload Faucet controller '{}': {}",
                     self.name,
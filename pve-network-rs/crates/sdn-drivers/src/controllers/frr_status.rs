@@ -0,0 +1,296 @@
+//! FRR runtime status
+//!
+//! Parsers for the JSON output of the vtysh `show` commands used by the BGP
+//! and EVPN controllers to report sessions, VNIs and MAC/IP tables. The
+//! parsers are lenient towards fields missing in older FRR releases and to
+//! the `"n/a"` placeholders zebra prints for L3 VNIs.
+
+use anyhow::{Context, Result};
+use pve_sdn_core::controller::{BgpPeerStatus, EvpnMacEntry, EvpnVniStatus};
+use pve_sdn_core::SystemExecutor;
+use serde_json::Value;
+use std::net::IpAddr;
+
+/// Address family name FRR uses for the EVPN summary
+pub const L2VPN_EVPN_AFI: &str = "l2VpnEvpn";
+
+/// Run `vtysh -c <command>` and return its output
+pub async fn vtysh_json(executor: &dyn SystemExecutor, command: &str) -> Result<String> {
+    let output = executor.query("vtysh", &["-c", command]).await?;
+
+    if !output.is_success() {
+        anyhow::bail!("'{}' failed: {}", command, output.stderr.trim());
+    }
+
+    Ok(output.stdout)
+}
+
+fn parse_json(json: &str, what: &str) -> Result<Value> {
+    serde_json::from_str(json).with_context(|| format!("Failed to parse {} JSON", what))
+}
+
+fn u64_field(value: &Value, key: &str) -> Option<u64> {
+    value.get(key).and_then(Value::as_u64)
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn parse_peer_table(table: &Value, afi: &str, peers: &mut Vec<BgpPeerStatus>) {
+    let vrf = str_field(table, "vrfName");
+    let entries = match table.get("peers").and_then(Value::as_object) {
+        Some(entries) => entries,
+        None => return,
+    };
+
+    for (peer, entry) in entries {
+        let state = str_field(entry, "state").unwrap_or_else(|| "Unknown".to_string());
+        let uptime = match state.as_str() {
+            "Established" => u64_field(entry, "peerUptimeMsec").map(|msec| msec / 1000),
+            _ => None,
+        };
+
+        peers.push(BgpPeerStatus {
+            peer: peer.clone(),
+            afi: afi.to_string(),
+            vrf: vrf.clone(),
+            remote_as: u64_field(entry, "remoteAs").map(|asn| asn as u32),
+            state,
+            uptime,
+            prefixes_received: u64_field(entry, "pfxRcd"),
+            prefixes_sent: u64_field(entry, "pfxSnt"),
+            description: str_field(entry, "desc"),
+        });
+    }
+}
+
+/// Parse the output of `show bgp summary json`
+///
+/// The output holds one peer table per address family, keyed by names
+/// such as `ipv4Unicast`.
+pub fn parse_bgp_summary(json: &str) -> Result<Vec<BgpPeerStatus>> {
+    let value = parse_json(json, "BGP summary")?;
+
+    let mut peers = Vec::new();
+    if let Some(families) = value.as_object() {
+        for (afi, table) in families {
+            parse_peer_table(table, afi, &mut peers);
+        }
+    }
+
+    peers.sort_by(|a, b| (&a.afi, &a.peer).cmp(&(&b.afi, &b.peer)));
+    Ok(peers)
+}
+
+/// Parse the output of `show bgp l2vpn evpn summary json`
+///
+/// Depending on the FRR release the peer table is printed directly or
+/// wrapped in an `l2VpnEvpn` object.
+pub fn parse_bgp_evpn_summary(json: &str) -> Result<Vec<BgpPeerStatus>> {
+    let value = parse_json(json, "BGP EVPN summary")?;
+    let table = value.get(L2VPN_EVPN_AFI).unwrap_or(&value);
+
+    let mut peers = Vec::new();
+    parse_peer_table(table, L2VPN_EVPN_AFI, &mut peers);
+
+    peers.sort_by(|a, b| a.peer.cmp(&b.peer));
+    Ok(peers)
+}
+
+/// Parse the output of `show evpn vni json`
+pub fn parse_evpn_vnis(json: &str) -> Result<Vec<EvpnVniStatus>> {
+    let value = parse_json(json, "EVPN VNI")?;
+
+    let mut vnis = Vec::new();
+    for (key, entry) in value.as_object().into_iter().flatten() {
+        let vni = match u64_field(entry, "vni").or_else(|| key.parse().ok()) {
+            Some(vni) => vni as u32,
+            None => continue,
+        };
+
+        vnis.push(EvpnVniStatus {
+            vni,
+            vni_type: str_field(entry, "type").unwrap_or_else(|| "L2".to_string()),
+            vxlan_interface: str_field(entry, "vxlanIf"),
+            vrf: str_field(entry, "tenantVrf"),
+            // L3 VNIs report "n/a" for the counters
+            macs: u64_field(entry, "numMacs").unwrap_or(0),
+            arp_nd: u64_field(entry, "numArpNd").unwrap_or(0),
+            remote_vteps: entry
+                .get("remoteVteps")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|vtep| vtep.as_str()?.parse().ok())
+                .collect(),
+        });
+    }
+
+    vnis.sort_by_key(|vni| vni.vni);
+    Ok(vnis)
+}
+
+/// Parse the output of `show evpn mac vni all json`
+///
+/// IP addresses are taken from the `neighbors` of an entry, which FRR
+/// includes for MACs with known ARP/ND bindings.
+pub fn parse_evpn_macs(json: &str) -> Result<Vec<EvpnMacEntry>> {
+    let value = parse_json(json, "EVPN MAC")?;
+
+    let mut macs = Vec::new();
+    for (key, table) in value.as_object().into_iter().flatten() {
+        let vni: u32 = match key.parse() {
+            Ok(vni) => vni,
+            Err(_) => continue,
+        };
+
+        for (mac, entry) in table
+            .get("macs")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            let ips: Vec<IpAddr> = entry
+                .get("neighbors")
+                .and_then(|n| n.get("active"))
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|ip| ip.as_str()?.parse().ok())
+                .collect();
+
+            macs.push(EvpnMacEntry {
+                vni,
+                mac: mac.clone(),
+                entry_type: str_field(entry, "type").unwrap_or_else(|| "unknown".to_string()),
+                interface: str_field(entry, "intf"),
+                vlan: u64_field(entry, "vlan").map(|vlan| vlan as u16),
+                remote_vtep: entry
+                    .get("remoteVtep")
+                    .and_then(Value::as_str)
+                    .and_then(|vtep| vtep.parse().ok()),
+                ips,
+            });
+        }
+    }
+
+    macs.sort_by(|a, b| (a.vni, &a.mac).cmp(&(b.vni, &b.mac)));
+    Ok(macs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BGP_SUMMARY: &str = include_str!("../../testdata/frr/bgp_summary.json");
+    const BGP_EVPN_SUMMARY: &str = include_str!("../../testdata/frr/bgp_evpn_summary.json");
+    const EVPN_VNI: &str = include_str!("../../testdata/frr/evpn_vni.json");
+    const EVPN_MAC: &str = include_str!("../../testdata/frr/evpn_mac.json");
+    const EMPTY: &str = include_str!("../../testdata/frr/empty.json");
+
+    #[test]
+    fn test_parse_bgp_summary() {
+        let peers = parse_bgp_summary(BGP_SUMMARY).unwrap();
+        assert_eq!(peers.len(), 3);
+
+        let upstream = &peers[0];
+        assert_eq!(upstream.peer, "192.0.2.1");
+        assert_eq!(upstream.afi, "ipv4Unicast");
+        assert_eq!(upstream.vrf.as_deref(), Some("default"));
+        assert_eq!(upstream.remote_as, Some(64512));
+        assert!(upstream.is_established());
+        assert_eq!(upstream.uptime, Some(70931));
+        assert_eq!(upstream.prefixes_received, Some(4));
+        assert_eq!(upstream.prefixes_sent, Some(3));
+        assert_eq!(upstream.description.as_deref(), Some("upstream"));
+
+        let idle = &peers[1];
+        assert_eq!(idle.peer, "192.0.2.5");
+        assert_eq!(idle.state, "Active");
+        assert!(!idle.is_established());
+        assert_eq!(idle.uptime, None);
+
+        // Unnumbered session keyed by interface
+        let unnumbered = &peers[2];
+        assert_eq!(unnumbered.peer, "ens19");
+        assert_eq!(unnumbered.afi, "ipv6Unicast");
+        assert_eq!(unnumbered.remote_as, Some(65100));
+    }
+
+    #[test]
+    fn test_parse_bgp_evpn_summary() {
+        let peers = parse_bgp_evpn_summary(BGP_EVPN_SUMMARY).unwrap();
+        assert_eq!(peers.len(), 2);
+        assert!(peers.iter().all(|p| p.afi == L2VPN_EVPN_AFI));
+        assert!(peers[0].is_established());
+        assert_eq!(peers[0].prefixes_received, Some(5));
+        assert_eq!(peers[1].state, "Connect");
+
+        // Newer releases wrap the table in the address family
+        let wrapped = format!("{{\"{}\": {}}}", L2VPN_EVPN_AFI, BGP_EVPN_SUMMARY);
+        assert_eq!(parse_bgp_evpn_summary(&wrapped).unwrap(), peers);
+    }
+
+    #[test]
+    fn test_parse_evpn_vnis() {
+        let vnis = parse_evpn_vnis(EVPN_VNI).unwrap();
+        assert_eq!(
+            vnis.iter().map(|v| v.vni).collect::<Vec<_>>(),
+            vec![4000, 10100, 10200]
+        );
+
+        let l3 = &vnis[0];
+        assert_eq!(l3.vni_type, "L3");
+        assert_eq!(l3.vxlan_interface.as_deref(), Some("vrfvx_tenant1"));
+        assert_eq!(l3.macs, 0);
+
+        let l2 = &vnis[1];
+        assert_eq!(l2.vni_type, "L2");
+        assert_eq!(l2.vrf.as_deref(), Some("vrf_tenant1"));
+        assert_eq!(l2.macs, 3);
+        assert_eq!(l2.arp_nd, 2);
+        assert_eq!(
+            l2.remote_vteps,
+            vec![
+                "10.255.0.2".parse::<IpAddr>().unwrap(),
+                "10.255.0.3".parse().unwrap()
+            ]
+        );
+        assert!(vnis[2].remote_vteps.is_empty());
+    }
+
+    #[test]
+    fn test_parse_evpn_macs() {
+        let macs = parse_evpn_macs(EVPN_MAC).unwrap();
+        assert_eq!(macs.len(), 3);
+        assert!(macs.iter().all(|m| m.vni == 10100));
+
+        let gateway = &macs[0];
+        assert_eq!(gateway.mac, "02:00:5e:10:00:01");
+        assert_eq!(gateway.interface.as_deref(), Some("vnet1"));
+        assert_eq!(gateway.vlan, Some(10));
+
+        let vm = &macs[1];
+        assert_eq!(vm.mac, "bc:24:11:4e:02:a1");
+        assert!(vm.is_local());
+        assert_eq!(vm.interface.as_deref(), Some("tap100i0"));
+        assert_eq!(vm.ips.len(), 2);
+
+        let remote = &macs[2];
+        assert!(!remote.is_local());
+        assert_eq!(remote.remote_vtep, Some("10.255.0.2".parse().unwrap()));
+        assert!(remote.ips.is_empty());
+    }
+
+    #[test]
+    fn test_parse_empty_and_invalid() {
+        assert!(parse_bgp_summary(EMPTY).unwrap().is_empty());
+        assert!(parse_bgp_evpn_summary(EMPTY).unwrap().is_empty());
+        assert!(parse_evpn_vnis(EMPTY).unwrap().is_empty());
+        assert!(parse_evpn_macs(EMPTY).unwrap().is_empty());
+
+        assert!(parse_bgp_summary("% BGP instance not found").is_err());
+        assert!(parse_evpn_vnis("").is_err());
+    }
+}
@@ -0,0 +1,622 @@
+//! IS-IS controller driver
+//!
+//! IS-IS controller provides underlay routing between the VTEPs of a
+//! cluster. Each node runs its own controller instance configuring FRR's
+//! isisd on the listed interfaces, so EVPN/VXLAN peers are reachable without
+//! static routes.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use pve_sdn_core::controller::{ControllerConfig, ControllerStatus};
+use pve_sdn_core::{Controller, ControllerType, RealExecutor, SystemExecutor, VNet, Zone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Path of the FRR IS-IS daemon
+const ISISD_PATH: &str = "/usr/lib/frr/isisd";
+
+/// Maximum length of a Linux interface name
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// IS-IS adjacency reported by FRR
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IsisNeighbor {
+    /// IS-IS area (domain) of the adjacency
+    pub area: String,
+    /// Hostname or system ID of the neighbor
+    pub system_id: String,
+    /// Local interface the adjacency is formed on
+    pub interface: String,
+    /// IS-IS level
+    pub level: Option<u8>,
+    /// Adjacency state, e.g. "Up" or "Initializing"
+    pub state: String,
+    /// Remaining hold time
+    pub expires_in: Option<String>,
+    /// Subnetwork point of attachment (MAC) of the neighbor
+    pub snpa: Option<String>,
+}
+
+impl IsisNeighbor {
+    /// Whether the adjacency is established
+    pub fn is_up(&self) -> bool {
+        self.state.eq_ignore_ascii_case("up")
+    }
+}
+
+/// Parse the output of `vtysh -c 'show isis neighbor json'`
+///
+/// Handles both the flat format of FRR 8 and the newer format where
+/// `interface` is an object carrying name and state.
+pub fn parse_isis_neighbors(json: &str) -> Result<Vec<IsisNeighbor>> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).context("Failed to parse IS-IS neighbor JSON")?;
+
+    let mut neighbors = Vec::new();
+    let areas = value
+        .get("areas")
+        .and_then(|a| a.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    for area in &areas {
+        let area_name = area.get("area").and_then(|a| a.as_str()).unwrap_or("");
+        let circuits = area
+            .get("circuits")
+            .and_then(|c| c.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        for circuit in &circuits {
+            // Circuits without adjacency only carry their index
+            let system_id = match circuit.get("adj").and_then(|a| a.as_str()) {
+                Some(adj) => adj,
+                None => continue,
+            };
+
+            let interface = circuit.get("interface");
+            let field = |key: &str| {
+                circuit
+                    .get(key)
+                    .or_else(|| interface.and_then(|i| i.get(key)))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            };
+
+            let interface_name = match interface {
+                Some(serde_json::Value::String(name)) => name.clone(),
+                Some(interface) => interface
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                None => String::new(),
+            };
+
+            neighbors.push(IsisNeighbor {
+                area: area_name.to_string(),
+                system_id: system_id.to_string(),
+                interface: interface_name,
+                level: circuit
+                    .get("level")
+                    .and_then(|l| l.as_u64())
+                    .map(|l| l as u8),
+                state: field("state").unwrap_or_else(|| "Unknown".to_string()),
+                expires_in: field("expires-in"),
+                snpa: field("snpa"),
+            });
+        }
+    }
+
+    Ok(neighbors)
+}
+
+/// IS-IS controller implementation
+///
+/// Options:
+/// - `isis-domain`: name of the IS-IS instance
+/// - `isis-net`: network entity title of the node
+/// - `isis-ifaces`: interfaces IS-IS runs on
+/// - `loopback`: optional interface announced passively, e.g. the VTEP
+///   address
+pub struct IsisController {
+    name: String,
+    executor: Arc<dyn SystemExecutor>,
+}
+
+impl IsisController {
+    /// Create new IS-IS controller
+    pub fn new(name: String) -> Self {
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new IS-IS controller running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
+    }
+
+    fn domain<'a>(&self, config: &'a ControllerConfig) -> Result<&'a str> {
+        config
+            .options
+            .get("isis-domain")
+            .and_then(|v| v.as_str())
+            .with_context(|| format!("IS-IS controller '{}' requires isis-domain", self.name))
+    }
+
+    fn net<'a>(&self, config: &'a ControllerConfig) -> Result<&'a str> {
+        config
+            .options
+            .get("isis-net")
+            .and_then(|v| v.as_str())
+            .with_context(|| format!("IS-IS controller '{}' requires isis-net", self.name))
+    }
+
+    /// Interfaces from `isis-ifaces`, given as array or comma separated list
+    fn interfaces(&self, config: &ControllerConfig) -> Vec<String> {
+        match config.options.get("isis-ifaces") {
+            Some(serde_json::Value::String(list)) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|iface| !iface.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Some(serde_json::Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| item.as_str())
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Validate a network entity title, e.g. `49.0001.1921.6800.1001.00`
+    ///
+    /// The NET consists of a one byte AFI, an optional area of 2 byte
+    /// groups, a 6 byte system ID and a zero NSEL.
+    fn validate_net(&self, net: &str) -> Result<()> {
+        let parts: Vec<&str> = net.split('.').collect();
+        let is_hex = |part: &str, len: usize| {
+            part.len() == len && part.chars().all(|c| c.is_ascii_hexdigit())
+        };
+
+        let valid = parts.len() >= 5
+            && is_hex(parts[0], 2)
+            && parts[1..parts.len() - 1].iter().all(|p| is_hex(p, 4))
+            && parts[parts.len() - 1] == "00";
+
+        if !valid {
+            anyhow::bail!(
+                "Invalid isis-net '{}' for IS-IS controller '{}'",
+                net,
+                self.name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Validate IS-IS-specific configuration
+    fn validate_isis_config(&self, config: &ControllerConfig) -> Result<()> {
+        if config.node.is_none() {
+            anyhow::bail!("IS-IS controller '{}' requires a node", self.name);
+        }
+
+        let domain = self.domain(config)?;
+        if domain.is_empty()
+            || !domain
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            anyhow::bail!(
+                "Invalid isis-domain '{}' for IS-IS controller '{}'",
+                domain,
+                self.name
+            );
+        }
+
+        self.validate_net(self.net(config)?)?;
+
+        let interfaces = self.interfaces(config);
+        if interfaces.is_empty() {
+            anyhow::bail!("IS-IS controller '{}' requires isis-ifaces", self.name);
+        }
+
+        let loopback = config.options.get("loopback").and_then(|v| v.as_str());
+        for iface in interfaces.iter().map(String::as_str).chain(loopback) {
+            if iface.is_empty() || iface.len() > MAX_INTERFACE_NAME_LEN {
+                anyhow::bail!(
+                    "Invalid interface '{}' for IS-IS controller '{}'",
+                    iface,
+                    self.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate FRR IS-IS configuration
+    fn generate_frr_isis_config(&self, config: &ControllerConfig) -> Result<String> {
+        let domain = self.domain(config)?;
+        let net = self.net(config)?;
+
+        let mut isis_config = format!(
+            "!\n\
+             ! IS-IS configuration for controller {}\n\
+             !\n",
+            self.name
+        );
+
+        for iface in self.interfaces(config) {
+            isis_config.push_str(&format!(
+                "interface {iface}\n\
+                 \x20ip router isis {domain}\n\
+                 \x20ipv6 router isis {domain}\n\
+                 \x20isis circuit-type level-2-only\n\
+                 \x20isis network point-to-point\n\
+                 !\n",
+            ));
+        }
+
+        if let Some(loopback) = config.options.get("loopback").and_then(|v| v.as_str()) {
+            isis_config.push_str(&format!(
+                "interface {loopback}\n\
+                 \x20ip router isis {domain}\n\
+                 \x20ipv6 router isis {domain}\n\
+                 \x20isis passive\n\
+                 !\n",
+            ));
+        }
+
+        isis_config.push_str(&format!(
+            "router isis {domain}\n\
+             \x20net {net}\n\
+             \x20is-type level-2-only\n\
+             \x20redistribute ipv4 connected level-2\n\
+             \x20redistribute ipv6 connected level-2\n\
+             \x20log-adjacency-changes\n\
+             exit\n\
+             !\n",
+        ));
+
+        Ok(isis_config)
+    }
+
+    /// Current IS-IS adjacencies of the node
+    pub async fn neighbors(&self) -> Result<Vec<IsisNeighbor>> {
+        let output = self
+            .executor
+            .query("vtysh", &["-c", "show isis neighbor json"])
+            .await?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "Failed to query IS-IS neighbors for controller '{}': {}",
+                self.name,
+                output.stderr.trim()
+            );
+        }
+
+        parse_isis_neighbors(&output.stdout)
+    }
+}
+
+#[async_trait]
+impl Controller for IsisController {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::Isis
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_configuration(&self, config: &ControllerConfig) -> Result<()> {
+        debug!("Validating IS-IS controller '{}' configuration", self.name);
+
+        config.validate().with_context(|| {
+            format!(
+                "Basic validation failed for IS-IS controller '{}'",
+                self.name
+            )
+        })?;
+
+        self.validate_isis_config(config).with_context(|| {
+            format!(
+                "IS-IS-specific validation failed for controller '{}'",
+                self.name
+            )
+        })?;
+
+        info!(
+            "IS-IS controller '{}' configuration validation successful",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn apply_configuration(&self, _zones: &[Box<dyn Zone>], _vnets: &[VNet]) -> Result<()> {
+        debug!(
+            "Applying configuration for IS-IS controller '{}'",
+            self.name
+        );
+
+        // IS-IS only provides the underlay, zones and vnets are not touched
+
+        info!(
+            "IS-IS controller '{}' configuration applied successfully",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn generate_config(&self, config: &ControllerConfig) -> Result<HashMap<String, String>> {
+        debug!(
+            "Generating configuration files for IS-IS controller '{}'",
+            self.name
+        );
+
+        let mut configs = HashMap::new();
+
+        let frr_config = self.generate_frr_isis_config(config).with_context(|| {
+            format!(
+                "Failed to generate FRR config for IS-IS controller '{}'",
+                self.name
+            )
+        })?;
+        configs.insert("frr".to_string(), frr_config);
+
+        let metadata = format!(
+            "# IS-IS Controller Configuration\n\
+             # Controller: {}\n\
+             # Type: IS-IS\n\
+             # Domain: {}\n\
+             # NET: {}\n\
+             # Interfaces: {}\n",
+            self.name,
+            self.domain(config)?,
+            self.net(config)?,
+            self.interfaces(config).join(", ")
+        );
+        configs.insert("metadata".to_string(), metadata);
+
+        info!(
+            "Generated configuration files for IS-IS controller '{}'",
+            self.name
+        );
+        Ok(configs)
+    }
+
+    async fn start(&self) -> Result<()> {
+        debug!("Starting IS-IS controller '{}'", self.name);
+
+        let installed = self.executor.query("test", &["-x", ISISD_PATH]).await?;
+        if !installed.is_success() {
+            anyhow::bail!(
+                "FRR isisd is not installed, cannot start IS-IS controller '{}'",
+                self.name
+            );
+        }
+
+        // isisd runs as part of the FRR service using the integrated config
+        let output = self
+            .executor
+            .execute("systemctl", &["start", "frr.service"])
+            .await
+            .with_context(|| format!("Failed to start FRR for controller '{}'", self.name))?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "Failed to start IS-IS controller '{}': {}",
+                self.name,
+                output.stderr.trim()
+            );
+        }
+
+        info!("IS-IS controller '{}' started successfully", self.name);
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<()> {
+        // FRR is shared with the other controllers, the IS-IS instance goes
+        // away when the configuration without it is applied
+        warn!(
+            "IS-IS controller '{}' is stopped by removing it from the SDN configuration",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn status(&self) -> Result<ControllerStatus> {
+        debug!("Getting status for IS-IS controller '{}'", self.name);
+
+        let (running, last_error) = match self.neighbors().await {
+            Ok(neighbors) if neighbors.iter().any(IsisNeighbor::is_up) => (true, None),
+            Ok(_) => (true, Some("No IS-IS adjacency is up".to_string())),
+            Err(e) => (false, Some(format!("{:#}", e))),
+        };
+
+        Ok(ControllerStatus {
+            running,
+            pid: None,
+            uptime: None,
+            last_error,
+            config_version: None,
+            ..Default::default()
+        })
+    }
+
+    async fn reload(&self) -> Result<()> {
+        debug!("Reloading IS-IS controller '{}'", self.name);
+
+        let output = self
+            .executor
+            .execute("systemctl", &["reload", "frr.service"])
+            .await
+            .with_context(|| format!("Failed to reload FRR for controller '{}'", self.name))?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "Failed to reload IS-IS controller '{}': {}",
+                self.name,
+                output.stderr.trim()
+            );
+        }
+
+        info!("IS-IS controller '{}' reloaded successfully", self.name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_sdn_core::{CommandOutput, RecordingExecutor};
+    use serde_json::json;
+
+    const NEIGHBOR_FRR8: &str = include_str!("../../testdata/isis/neighbor_frr8.json");
+    const NEIGHBOR_FRR10: &str = include_str!("../../testdata/isis/neighbor_frr10.json");
+    const NEIGHBOR_EMPTY: &str = include_str!("../../testdata/isis/neighbor_empty.json");
+
+    fn isis_config() -> ControllerConfig {
+        let mut config = ControllerConfig::new(ControllerType::Isis, "isis1".to_string());
+        config.node = Some("pve1".to_string());
+        config
+            .options
+            .insert("isis-domain".to_string(), json!("pve"));
+        config
+            .options
+            .insert("isis-net".to_string(), json!("49.0001.1921.6800.1001.00"));
+        config
+            .options
+            .insert("isis-ifaces".to_string(), json!("ens19,ens20"));
+        config
+    }
+
+    #[tokio::test]
+    async fn test_isis_controller_validation() {
+        let controller = IsisController::new("isis1".to_string());
+        let config = isis_config();
+        assert!(controller.validate_configuration(&config).await.is_ok());
+
+        let mut array = config.clone();
+        array
+            .options
+            .insert("isis-ifaces".to_string(), json!(["ens19", "ens20"]));
+        assert!(controller.validate_configuration(&array).await.is_ok());
+
+        // Missing node
+        let mut invalid = config.clone();
+        invalid.node = None;
+        assert!(controller.validate_configuration(&invalid).await.is_err());
+
+        // Missing domain
+        let mut invalid = config.clone();
+        invalid.options.remove("isis-domain");
+        assert!(controller.validate_configuration(&invalid).await.is_err());
+
+        // Invalid NETs
+        for net in [
+            "49.0001.1921.6800.1001",
+            "49.0001.1921.6800.1001.01",
+            "4.0001.1921.6800.1001.00",
+            "49.1921.6800.100x.00",
+        ] {
+            let mut invalid = config.clone();
+            invalid.options.insert("isis-net".to_string(), json!(net));
+            assert!(controller.validate_configuration(&invalid).await.is_err());
+        }
+
+        // No interfaces
+        let mut invalid = config.clone();
+        invalid.options.insert("isis-ifaces".to_string(), json!(""));
+        assert!(controller.validate_configuration(&invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_isis_config_generation() {
+        let controller = IsisController::new("isis1".to_string());
+        let mut config = isis_config();
+        config
+            .options
+            .insert("loopback".to_string(), json!("dummy1"));
+
+        let configs = controller.generate_config(&config).await.unwrap();
+        assert!(configs.contains_key("metadata"));
+
+        let frr_config = &configs["frr"];
+        assert!(frr_config.contains(
+            "interface ens19\n ip router isis pve\n ipv6 router isis pve\n \
+             isis circuit-type level-2-only\n isis network point-to-point\n!\n"
+        ));
+        assert!(frr_config.contains("interface ens20\n"));
+        assert!(frr_config.contains("interface dummy1\n ip router isis pve\n"));
+        assert!(frr_config.contains(" isis passive\n"));
+        assert!(frr_config.contains("router isis pve\n net 49.0001.1921.6800.1001.00\n"));
+        assert!(frr_config.contains(" redistribute ipv4 connected level-2\n"));
+        assert!(frr_config.ends_with("exit\n!\n"));
+    }
+
+    #[test]
+    fn test_parse_isis_neighbors_frr8() {
+        let neighbors = parse_isis_neighbors(NEIGHBOR_FRR8).unwrap();
+
+        assert_eq!(
+            neighbors[0],
+            IsisNeighbor {
+                area: "pve".to_string(),
+                system_id: "pve2".to_string(),
+                interface: "ens19".to_string(),
+                level: Some(2),
+                state: "Up".to_string(),
+                expires_in: Some("28s".to_string()),
+                snpa: Some("bc24.1122.3344".to_string()),
+            }
+        );
+        assert_eq!(neighbors[1].system_id, "pve3");
+        assert!(!neighbors[1].is_up());
+    }
+
+    #[test]
+    fn test_parse_isis_neighbors_frr10() {
+        let neighbors = parse_isis_neighbors(NEIGHBOR_FRR10).unwrap();
+
+        assert_eq!(neighbors.len(), 2);
+        assert!(neighbors.iter().all(IsisNeighbor::is_up));
+        assert_eq!(neighbors[0].interface, "ens19");
+        assert_eq!(neighbors[0].expires_in.as_deref(), Some("27s"));
+        assert_eq!(neighbors[1].snpa.as_deref(), Some("bc24.1122.5566"));
+
+        assert!(parse_isis_neighbors(NEIGHBOR_EMPTY).unwrap().is_empty());
+        assert!(parse_isis_neighbors("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_isis_status_from_vtysh() {
+        let executor = Arc::new(RecordingExecutor::new());
+        let controller = IsisController::with_executor("isis1".to_string(), executor.clone());
+
+        executor.respond(
+            "vtysh -c show isis neighbor json",
+            CommandOutput::success(NEIGHBOR_FRR8),
+        );
+        let status = controller.status().await.unwrap();
+        assert!(status.running);
+        assert!(status.last_error.is_none());
+
+        executor.respond(
+            "vtysh -c show isis neighbor json",
+            CommandOutput::success(NEIGHBOR_EMPTY),
+        );
+        let status = controller.status().await.unwrap();
+        assert!(status.running);
+        assert!(status.last_error.is_some());
+
+        executor.respond("vtysh", CommandOutput::failure(1, "isisd is not running"));
+        let status = controller.status().await.unwrap();
+        assert!(!status.running);
+        assert!(status.last_error.unwrap().contains("isisd is not running"));
+    }
+}
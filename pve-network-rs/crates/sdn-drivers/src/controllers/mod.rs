@@ -3,7 +3,10 @@
 pub mod bgp;
 pub mod evpn;
 pub mod faucet;
+pub mod frr_status;
+pub mod isis;
 
 pub use bgp::BgpController;
 pub use evpn::EvpnController;
 pub use faucet::FaucetController;
+pub use isis::IsisController;
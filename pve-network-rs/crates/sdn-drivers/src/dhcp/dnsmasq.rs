@@ -0,0 +1,683 @@
+//! dnsmasq DHCP plugin
+//!
+//! Each DHCP zone gets its own dnsmasq instance (`dnsmasq@<zone>.service`)
+//! reading `/etc/dnsmasq.d/<zone>/`:
+//!
+//! - `00-default.conf`: instance settings, lease file and static lease files
+//! - `10-<subnet>.conf`: interface, ranges and options of one subnet
+//! - `ethers`: static leases (`dhcp-hostsfile`)
+//! - `hosts`: names of the leased addresses (`addn-hosts`)
+//!
+//! Only clients with a static lease are served, the leases are the IPAM
+//! allocations of the zone.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ipnet::IpNet;
+use log::{debug, info, warn};
+use pve_sdn_core::dhcp::{parse_dhcp_range, zone_dhcp_subnets};
+use pve_sdn_core::{
+    DhcpPlugin, IpAllocation, RealExecutor, SdnConfiguration, SubnetConfig, SystemExecutor,
+};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Default directory holding the per-zone configuration directories
+pub const DEFAULT_DNSMASQ_CONFIG_DIR: &str = "/etc/dnsmasq.d";
+
+/// Default directory of the lease databases
+pub const DEFAULT_DNSMASQ_LEASE_DIR: &str = "/var/lib/misc";
+
+/// First line of every generated file
+const HEADER: &str = "# Generated by pve-sdn, do not edit";
+
+const DEFAULT_CONF: &str = "00-default.conf";
+const ETHERS_FILE: &str = "ethers";
+const HOSTS_FILE: &str = "hosts";
+
+/// Whether `name` is a valid (RFC 1123) host or domain name
+fn is_valid_hostname(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+fn is_valid_mac(mac: &str) -> bool {
+    let parts: Vec<&str> = mac.split(':').collect();
+    parts.len() == 6
+        && parts
+            .iter()
+            .all(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// dnsmasq backed DHCP for SDN zones
+pub struct DnsmasqDhcp {
+    config_dir: PathBuf,
+    lease_dir: PathBuf,
+    executor: Arc<dyn SystemExecutor>,
+}
+
+impl DnsmasqDhcp {
+    /// Create new dnsmasq plugin using the default paths
+    pub fn new() -> Self {
+        Self {
+            config_dir: PathBuf::from(DEFAULT_DNSMASQ_CONFIG_DIR),
+            lease_dir: PathBuf::from(DEFAULT_DNSMASQ_LEASE_DIR),
+            executor: Arc::new(RealExecutor::new()),
+        }
+    }
+
+    /// Use different configuration and lease directories
+    pub fn with_paths(
+        mut self,
+        config_dir: impl Into<PathBuf>,
+        lease_dir: impl Into<PathBuf>,
+    ) -> Self {
+        self.config_dir = config_dir.into();
+        self.lease_dir = lease_dir.into();
+        self
+    }
+
+    /// Manage the instances through `executor`
+    pub fn with_executor(mut self, executor: Arc<dyn SystemExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Configuration directory of `zone`
+    pub fn zone_dir(&self, zone: &str) -> PathBuf {
+        self.config_dir.join(zone)
+    }
+
+    fn service(zone: &str) -> String {
+        format!("dnsmasq@{}", zone)
+    }
+
+    /// dnsmasq tag of a subnet
+    fn subnet_tag(subnet: &SubnetConfig) -> String {
+        subnet
+            .subnet
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect()
+    }
+
+    /// Render all files of `zone`, keyed by file name
+    pub fn render_zone(
+        &self,
+        sdn: &SdnConfiguration,
+        zone: &str,
+        leases: &[IpAllocation],
+    ) -> Result<BTreeMap<String, String>> {
+        let zone_dir = self.zone_dir(zone);
+        let mut files = BTreeMap::new();
+
+        files.insert(
+            DEFAULT_CONF.to_string(),
+            format!(
+                "{HEADER}\n\
+                 except-interface=lo\n\
+                 bind-dynamic\n\
+                 no-hosts\n\
+                 dhcp-authoritative\n\
+                 dhcp-leasefile={}\n\
+                 dhcp-hostsfile={}\n\
+                 addn-hosts={}\n\
+                 dhcp-ignore=tag:!known\n\
+                 enable-ra\n\
+                 quiet-ra\n",
+                self.lease_dir
+                    .join(format!("dnsmasq.{}.leases", zone))
+                    .display(),
+                zone_dir.join(ETHERS_FILE).display(),
+                zone_dir.join(HOSTS_FILE).display(),
+            ),
+        );
+
+        for subnet in zone_dhcp_subnets(sdn, zone) {
+            files.insert(
+                format!("10-{}.conf", Self::subnet_tag(subnet)),
+                Self::render_subnet(subnet)?,
+            );
+        }
+
+        let (ethers, hosts) = Self::render_leases(sdn, zone, leases);
+        files.insert(ETHERS_FILE.to_string(), ethers);
+        files.insert(HOSTS_FILE.to_string(), hosts);
+
+        Ok(files)
+    }
+
+    fn render_subnet(subnet: &SubnetConfig) -> Result<String> {
+        let tag = Self::subnet_tag(subnet);
+        let gateway = subnet.gateway.with_context(|| {
+            format!(
+                "Subnet '{}' needs a gateway for DHCP, dnsmasq serves it from that address",
+                subnet.subnet
+            )
+        })?;
+        let ipv6 = gateway.is_ipv6();
+
+        let mut config = format!("{HEADER}\ninterface={}\n", subnet.vnet);
+
+        let dhcp = subnet.dhcp.as_ref();
+        for range in dhcp
+            .and_then(|d| d.dhcp_range.as_ref())
+            .into_iter()
+            .flatten()
+        {
+            let (start, end) = parse_dhcp_range(range).with_context(|| {
+                format!(
+                    "Invalid DHCP range '{}' in subnet '{}'",
+                    range, subnet.subnet
+                )
+            })?;
+
+            let mask = match subnet.cidr {
+                IpNet::V4(net) => net.netmask().to_string(),
+                IpNet::V6(net) => net.prefix_len().to_string(),
+            };
+            config.push_str(&format!(
+                "dhcp-range=set:{},{},{},{},infinite\n",
+                tag, start, end, mask
+            ));
+        }
+
+        if !ipv6 {
+            config.push_str(&format!(
+                "dhcp-option=tag:{},option:router,{}\n",
+                tag, gateway
+            ));
+        }
+
+        let dns: Vec<String> = dhcp
+            .and_then(|d| d.dns_server.as_ref())
+            .into_iter()
+            .flatten()
+            .filter(|server| server.is_ipv6() == ipv6)
+            .map(|server| match server {
+                IpAddr::V4(addr) => addr.to_string(),
+                IpAddr::V6(addr) => format!("[{}]", addr),
+            })
+            .collect();
+        if !dns.is_empty() {
+            let option = if ipv6 { "option6" } else { "option" };
+            config.push_str(&format!(
+                "dhcp-option=tag:{},{}:dns-server,{}\n",
+                tag,
+                option,
+                dns.join(",")
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// Render the `ethers` and `hosts` files from the leases of `zone`
+    fn render_leases(
+        sdn: &SdnConfiguration,
+        zone: &str,
+        leases: &[IpAllocation],
+    ) -> (String, String) {
+        let subnets: Vec<&str> = zone_dhcp_subnets(sdn, zone)
+            .iter()
+            .map(|subnet| subnet.subnet.as_str())
+            .collect();
+
+        let mut leases: Vec<&IpAllocation> = leases
+            .iter()
+            .filter(|lease| subnets.contains(&lease.subnet.as_str()))
+            .collect();
+        leases.sort_by_key(|lease| lease.ip);
+
+        let mut ethers = format!("{HEADER}\n");
+        let mut hosts = format!("{HEADER}\n");
+
+        for lease in leases {
+            let mac = match &lease.mac {
+                Some(mac) if is_valid_mac(mac) => mac.to_lowercase(),
+                Some(mac) => {
+                    warn!("Skipping lease of {} with invalid MAC {:?}", lease.ip, mac);
+                    continue;
+                }
+                None => continue,
+            };
+
+            match lease.ip {
+                IpAddr::V4(ip) => ethers.push_str(&format!("{},{}\n", mac, ip)),
+                IpAddr::V6(ip) => ethers.push_str(&format!("{},[{}]\n", mac, ip)),
+            }
+
+            // Names come from guest configs, anything else could add
+            // entries or break the file
+            match &lease.hostname {
+                Some(hostname) if is_valid_hostname(hostname) => {
+                    hosts.push_str(&format!("{} {}\n", lease.ip, hostname));
+                }
+                Some(hostname) => {
+                    warn!("Not adding invalid hostname {:?} of {}", hostname, lease.ip);
+                }
+                None => {}
+            }
+        }
+
+        (ethers, hosts)
+    }
+
+    async fn systemctl(&self, args: &[&str]) -> Result<()> {
+        let output = self.executor.execute("systemctl", args).await?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "'systemctl {}' failed: {}",
+                args.join(" "),
+                output.stderr.trim()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Write `files` below `dir`, returning whether anything changed
+    ///
+    /// Generated subnet files not part of `files` are removed.
+    async fn write_files(
+        dir: &Path,
+        files: &BTreeMap<String, String>,
+        prune: bool,
+    ) -> Result<bool> {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        let mut changed = false;
+        for (name, content) in files {
+            let path = dir.join(name);
+            let current = tokio::fs::read_to_string(&path).await.ok();
+            if current.as_deref() != Some(content.as_str()) {
+                tokio::fs::write(&path, content)
+                    .await
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                changed = true;
+            }
+        }
+
+        if prune {
+            let mut entries = tokio::fs::read_dir(dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("10-") && name.ends_with(".conf") && !files.contains_key(&name)
+                {
+                    tokio::fs::remove_file(entry.path()).await?;
+                    changed = true;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+impl Default for DnsmasqDhcp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl DhcpPlugin for DnsmasqDhcp {
+    fn name(&self) -> &str {
+        "dnsmasq"
+    }
+
+    async fn apply_zone(
+        &self,
+        sdn: &SdnConfiguration,
+        zone: &str,
+        leases: &[IpAllocation],
+    ) -> Result<bool> {
+        let files = self.render_zone(sdn, zone, leases)?;
+        let changed = Self::write_files(&self.zone_dir(zone), &files, true).await?;
+        let service = Self::service(zone);
+
+        if changed {
+            info!(
+                "DHCP configuration of zone '{}' changed, restarting dnsmasq",
+                zone
+            );
+            self.systemctl(&["enable", &service]).await?;
+            self.systemctl(&["restart", &service]).await?;
+        } else {
+            let active = self
+                .executor
+                .query("systemctl", &["is-active", "--quiet", &service])
+                .await
+                .map(|output| output.is_success())
+                .unwrap_or(false);
+
+            if !active {
+                debug!("Starting dnsmasq for zone '{}'", zone);
+                self.systemctl(&["start", &service]).await?;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    async fn sync_leases(
+        &self,
+        sdn: &SdnConfiguration,
+        zone: &str,
+        leases: &[IpAllocation],
+    ) -> Result<bool> {
+        let (ethers, hosts) = Self::render_leases(sdn, zone, leases);
+        let files = BTreeMap::from([
+            (ETHERS_FILE.to_string(), ethers),
+            (HOSTS_FILE.to_string(), hosts),
+        ]);
+
+        let changed = Self::write_files(&self.zone_dir(zone), &files, false).await?;
+        if changed {
+            // SIGHUP rereads dhcp-hostsfile and addn-hosts
+            self.systemctl(&["reload", &Self::service(zone)]).await?;
+        }
+
+        Ok(changed)
+    }
+
+    async fn remove_zone(&self, zone: &str) -> Result<()> {
+        self.systemctl(&["disable", "--now", &Self::service(zone)])
+            .await?;
+
+        let dir = self.zone_dir(zone);
+        if dir.exists() {
+            tokio::fs::remove_dir_all(&dir)
+                .await
+                .with_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+
+        let leases = self.lease_dir.join(format!("dnsmasq.{}.leases", zone));
+        if leases.exists() {
+            tokio::fs::remove_file(&leases).await?;
+        }
+
+        info!("Removed dnsmasq instance of zone '{}'", zone);
+        Ok(())
+    }
+
+    async fn configured_zones(&self) -> Result<Vec<String>> {
+        let mut zones = Vec::new();
+
+        let mut entries = match tokio::fs::read_dir(&self.config_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(zones),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            // Only directories created by us, dnsmasq.d may hold others
+            let default = entry.path().join(DEFAULT_CONF);
+            let generated = tokio::fs::read_to_string(&default)
+                .await
+                .map(|content| content.starts_with(HEADER))
+                .unwrap_or(false);
+
+            if generated {
+                zones.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+
+        zones.sort();
+        Ok(zones)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use pve_sdn_core::{DhcpConfig, RecordingExecutor, VNetConfig, ZoneConfig, ZoneType};
+    use serde_json::json;
+
+    fn dhcp_sdn_config() -> SdnConfiguration {
+        let mut sdn = SdnConfiguration::new();
+
+        let mut zone = ZoneConfig::new(ZoneType::Simple, "dhcpz".to_string());
+        zone.options.insert("dhcp".to_string(), json!("dnsmasq"));
+        sdn.zones.insert("dhcpz".to_string(), zone);
+        sdn.vnets.insert(
+            "vnet1".to_string(),
+            VNetConfig::new("vnet1".to_string(), "dhcpz".to_string()),
+        );
+
+        let mut v4 = SubnetConfig::new(
+            "dhcpz-10.0.1.0-24".to_string(),
+            "vnet1".to_string(),
+            "10.0.1.0/24".parse().unwrap(),
+        );
+        v4.gateway = Some("10.0.1.1".parse().unwrap());
+        v4.dhcp = Some(DhcpConfig {
+            dhcp_range: Some(vec!["10.0.1.100,10.0.1.200".to_string()]),
+            dns_server: Some(vec![
+                "10.0.1.1".parse().unwrap(),
+                "fd00::53".parse().unwrap(),
+            ]),
+        });
+        sdn.subnets.insert(v4.subnet.clone(), v4);
+
+        let mut v6 = SubnetConfig::new(
+            "dhcpz-fd00:1::-64".to_string(),
+            "vnet1".to_string(),
+            "fd00:1::/64".parse().unwrap(),
+        );
+        v6.gateway = Some("fd00:1::1".parse().unwrap());
+        v6.dhcp = Some(DhcpConfig {
+            dhcp_range: Some(vec!["fd00:1::100,fd00:1::1ff".to_string()]),
+            dns_server: Some(vec!["fd00::53".parse().unwrap()]),
+        });
+        sdn.subnets.insert(v6.subnet.clone(), v6);
+
+        // Subnet without DHCP
+        let plain = SubnetConfig::new(
+            "dhcpz-10.0.2.0-24".to_string(),
+            "vnet1".to_string(),
+            "10.0.2.0/24".parse().unwrap(),
+        );
+        sdn.subnets.insert(plain.subnet.clone(), plain);
+
+        sdn
+    }
+
+    fn lease(subnet: &str, ip: &str, mac: Option<&str>, hostname: Option<&str>) -> IpAllocation {
+        IpAllocation {
+            ip: ip.parse().unwrap(),
+            subnet: subnet.to_string(),
+            vmid: Some(100),
+            hostname: hostname.map(str::to_string),
+            mac: mac.map(str::to_string),
+            description: None,
+            allocated_at: Utc::now(),
+        }
+    }
+
+    fn leases() -> Vec<IpAllocation> {
+        vec![
+            lease(
+                "dhcpz-10.0.1.0-24",
+                "10.0.1.20",
+                Some("BC:24:11:00:00:02"),
+                None,
+            ),
+            lease(
+                "dhcpz-10.0.1.0-24",
+                "10.0.1.10",
+                Some("bc:24:11:00:00:01"),
+                Some("vm100"),
+            ),
+            lease(
+                "dhcpz-fd00:1::-64",
+                "fd00:1::10",
+                Some("bc:24:11:00:00:01"),
+                Some("vm100"),
+            ),
+            // No MAC, not a lease
+            lease("dhcpz-10.0.1.0-24", "10.0.1.30", None, Some("reserved")),
+            // Lease without a usable name
+            lease(
+                "dhcpz-10.0.1.0-24",
+                "10.0.1.40",
+                Some("bc:24:11:00:00:04"),
+                Some("vm104\n10.0.1.1 gateway"),
+            ),
+            // Not a MAC address
+            lease(
+                "dhcpz-10.0.1.0-24",
+                "10.0.1.50",
+                Some("bc:24:11:00:00:05\n"),
+                Some("vm105"),
+            ),
+            // Subnet without DHCP
+            lease(
+                "dhcpz-10.0.2.0-24",
+                "10.0.2.10",
+                Some("bc:24:11:00:00:03"),
+                None,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_dnsmasq_render_zone() {
+        let dhcp = DnsmasqDhcp::new();
+        let files = dhcp
+            .render_zone(&dhcp_sdn_config(), "dhcpz", &leases())
+            .unwrap();
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec![
+                "00-default.conf",
+                "10-dhcpz-10-0-1-0-24.conf",
+                "10-dhcpz-fd00-1---64.conf",
+                "ethers",
+                "hosts"
+            ]
+        );
+
+        let default = &files["00-default.conf"];
+        assert!(default.contains("dhcp-leasefile=/var/lib/misc/dnsmasq.dhcpz.leases\n"));
+        assert!(default.contains("dhcp-hostsfile=/etc/dnsmasq.d/dhcpz/ethers\n"));
+        assert!(default.contains("addn-hosts=/etc/dnsmasq.d/dhcpz/hosts\n"));
+        assert!(default.contains("dhcp-ignore=tag:!known\n"));
+
+        assert_eq!(
+            files["10-dhcpz-10-0-1-0-24.conf"],
+            format!(
+                "{HEADER}\n\
+                 interface=vnet1\n\
+                 dhcp-range=set:dhcpz-10-0-1-0-24,10.0.1.100,10.0.1.200,255.255.255.0,infinite\n\
+                 dhcp-option=tag:dhcpz-10-0-1-0-24,option:router,10.0.1.1\n\
+                 dhcp-option=tag:dhcpz-10-0-1-0-24,option:dns-server,10.0.1.1\n"
+            )
+        );
+        assert_eq!(
+            files["10-dhcpz-fd00-1---64.conf"],
+            format!(
+                "{HEADER}\n\
+                 interface=vnet1\n\
+                 dhcp-range=set:dhcpz-fd00-1---64,fd00:1::100,fd00:1::1ff,64,infinite\n\
+                 dhcp-option=tag:dhcpz-fd00-1---64,option6:dns-server,[fd00::53]\n"
+            )
+        );
+
+        assert_eq!(
+            files["ethers"],
+            format!(
+                "{HEADER}\n\
+                 bc:24:11:00:00:01,10.0.1.10\n\
+                 bc:24:11:00:00:02,10.0.1.20\n\
+                 bc:24:11:00:00:04,10.0.1.40\n\
+                 bc:24:11:00:00:01,[fd00:1::10]\n"
+            )
+        );
+        assert_eq!(
+            files["hosts"],
+            format!("{HEADER}\n10.0.1.10 vm100\nfd00:1::10 vm100\n")
+        );
+    }
+
+    #[test]
+    fn test_dnsmasq_requires_gateway() {
+        let mut sdn = dhcp_sdn_config();
+        sdn.subnets.get_mut("dhcpz-10.0.1.0-24").unwrap().gateway = None;
+
+        let err = DnsmasqDhcp::new()
+            .render_zone(&sdn, "dhcpz", &[])
+            .unwrap_err();
+        assert!(err.to_string().contains("needs a gateway"));
+    }
+
+    #[tokio::test]
+    async fn test_dnsmasq_instance_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = Arc::new(RecordingExecutor::new());
+        let dhcp = DnsmasqDhcp::new()
+            .with_paths(dir.path().join("dnsmasq.d"), dir.path().join("leases"))
+            .with_executor(executor.clone());
+        let sdn = dhcp_sdn_config();
+
+        // First apply writes the files and restarts the instance
+        assert!(dhcp.apply_zone(&sdn, "dhcpz", &leases()).await.unwrap());
+        executor.assert_commands(&[
+            "systemctl enable dnsmasq@dhcpz",
+            "systemctl restart dnsmasq@dhcpz",
+        ]);
+        assert_eq!(dhcp.configured_zones().await.unwrap(), vec!["dhcpz"]);
+
+        // Unchanged configuration only makes sure it runs
+        executor.clear();
+        assert!(!dhcp.apply_zone(&sdn, "dhcpz", &leases()).await.unwrap());
+        executor.assert_commands(&["systemctl is-active --quiet dnsmasq@dhcpz"]);
+
+        // New leases reload the instance
+        executor.clear();
+        let mut updated = leases();
+        updated.pop();
+        updated.push(lease(
+            "dhcpz-10.0.1.0-24",
+            "10.0.1.40",
+            Some("bc:24:11:00:00:04"),
+            Some("ct101"),
+        ));
+        assert!(dhcp.sync_leases(&sdn, "dhcpz", &updated).await.unwrap());
+        assert!(!dhcp.sync_leases(&sdn, "dhcpz", &updated).await.unwrap());
+        executor.assert_commands(&["systemctl reload dnsmasq@dhcpz"]);
+
+        let hosts = std::fs::read_to_string(dhcp.zone_dir("dhcpz").join("hosts")).unwrap();
+        assert!(hosts.contains("10.0.1.40 ct101\n"));
+
+        // Stale subnet files are pruned
+        let mut sdn_v4 = sdn.clone();
+        sdn_v4.subnets.remove("dhcpz-fd00:1::-64");
+        assert!(dhcp.apply_zone(&sdn_v4, "dhcpz", &updated).await.unwrap());
+        assert!(!dhcp
+            .zone_dir("dhcpz")
+            .join("10-dhcpz-fd00-1---64.conf")
+            .exists());
+
+        // Removal stops the instance and deletes its configuration
+        executor.clear();
+        dhcp.remove_zone("dhcpz").await.unwrap();
+        executor.assert_commands(&["systemctl disable --now dnsmasq@dhcpz"]);
+        assert!(!dhcp.zone_dir("dhcpz").exists());
+        assert!(dhcp.configured_zones().await.unwrap().is_empty());
+    }
+}
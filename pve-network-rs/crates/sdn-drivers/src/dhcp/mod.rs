@@ -0,0 +1,148 @@
+//! SDN DHCP service
+//!
+//! Runs the DHCP plugin instances of the zones on the local node and keeps
+//! their static leases in sync with IPAM.
+
+pub mod dnsmasq;
+
+pub use dnsmasq::DnsmasqDhcp;
+
+use anyhow::{Context, Result};
+use log::{debug, info};
+use pve_sdn_core::dhcp::{collect_static_leases, subnet_dhcp_zone, zone_dhcp_plugin};
+use pve_sdn_core::{DhcpPlugin, IpamManager, SdnConfiguration, ZoneConfig};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Outcome of [`DhcpService::apply`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DhcpApplyResult {
+    /// Zones whose instance configuration changed
+    pub changed: Vec<String>,
+    /// Zones whose instance was removed
+    pub removed: Vec<String>,
+}
+
+/// DHCP instances of the local node
+pub struct DhcpService {
+    node: String,
+    plugins: HashMap<String, Arc<dyn DhcpPlugin>>,
+}
+
+impl DhcpService {
+    /// Create service for the given node with the dnsmasq plugin
+    pub fn new(node: &str) -> Self {
+        Self {
+            node: node.to_string(),
+            plugins: HashMap::new(),
+        }
+        .with_plugin(Arc::new(DnsmasqDhcp::new()))
+    }
+
+    /// Register a plugin, replacing one with the same name
+    pub fn with_plugin(mut self, plugin: Arc<dyn DhcpPlugin>) -> Self {
+        self.plugins.insert(plugin.name().to_string(), plugin);
+        self
+    }
+
+    /// Plugin registered as `name`
+    pub fn plugin(&self, name: &str) -> Result<Arc<dyn DhcpPlugin>> {
+        self.plugins
+            .get(name)
+            .cloned()
+            .with_context(|| format!("Unknown DHCP plugin '{}'", name))
+    }
+
+    fn is_local(&self, zone: &ZoneConfig) -> bool {
+        zone.nodes
+            .as_ref()
+            .map(|nodes| nodes.iter().any(|n| n == &self.node))
+            .unwrap_or(true)
+    }
+
+    /// Configure the DHCP zones of this node and remove stale instances
+    pub async fn apply(
+        &self,
+        sdn: &SdnConfiguration,
+        ipam: &IpamManager,
+    ) -> Result<DhcpApplyResult> {
+        let mut result = DhcpApplyResult::default();
+
+        let mut zones: Vec<(&String, &ZoneConfig)> = sdn
+            .zones
+            .iter()
+            .filter(|(_, zone)| self.is_local(zone))
+            .collect();
+        zones.sort_by(|a, b| a.0.cmp(b.0));
+
+        // Zone name -> plugin serving it
+        let mut active: HashMap<&str, &str> = HashMap::new();
+
+        for (name, zone) in zones {
+            let plugin_name = match zone_dhcp_plugin(zone) {
+                Some(plugin) => plugin,
+                None => continue,
+            };
+            let plugin = self.plugin(plugin_name)?;
+
+            let leases = collect_static_leases(ipam, sdn, name)
+                .await
+                .with_context(|| format!("Failed to collect DHCP leases of zone '{}'", name))?;
+
+            if plugin
+                .apply_zone(sdn, name, &leases)
+                .await
+                .with_context(|| format!("Failed to configure DHCP for zone '{}'", name))?
+            {
+                result.changed.push(name.clone());
+            }
+
+            active.insert(name, plugin_name);
+        }
+
+        let mut plugin_names: Vec<&String> = self.plugins.keys().collect();
+        plugin_names.sort();
+
+        for plugin_name in plugin_names {
+            let plugin = &self.plugins[plugin_name];
+
+            for zone in plugin.configured_zones().await? {
+                if active.get(zone.as_str()) == Some(&plugin_name.as_str()) {
+                    continue;
+                }
+
+                info!("Removing {} DHCP instance of zone '{}'", plugin_name, zone);
+                plugin.remove_zone(&zone).await?;
+                result.removed.push(zone);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Sync the static leases of the zone serving `subnet`
+    ///
+    /// Called after IPAM allocations change. Returns whether the leases
+    /// changed, subnets of zones without DHCP or not on this node are
+    /// ignored.
+    pub async fn sync_subnet(
+        &self,
+        sdn: &SdnConfiguration,
+        ipam: &IpamManager,
+        subnet: &str,
+    ) -> Result<bool> {
+        let zone = subnet_dhcp_zone(sdn, subnet).filter(|zone| self.is_local(zone));
+        let (zone, plugin_name) = match zone.and_then(|z| Some((z, zone_dhcp_plugin(z)?))) {
+            Some(found) => found,
+            None => {
+                debug!("Subnet '{}' is not served by DHCP on {}", subnet, self.node);
+                return Ok(false);
+            }
+        };
+
+        let plugin = self.plugin(plugin_name)?;
+        let leases = collect_static_leases(ipam, sdn, &zone.zone).await?;
+
+        plugin.sync_leases(sdn, &zone.zone, &leases).await
+    }
+}
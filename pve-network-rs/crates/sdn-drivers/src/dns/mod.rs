@@ -0,0 +1,8 @@
+//! DNS drivers
+
+pub mod powerdns;
+
+#[cfg(test)]
+mod tests;
+
+pub use powerdns::PowerDns;
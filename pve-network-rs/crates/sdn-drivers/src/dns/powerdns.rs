@@ -0,0 +1,256 @@
+//! PowerDNS DNS driver
+//!
+//! Talks to the PowerDNS authoritative server HTTP API. The configured URL
+//! points at the server, e.g. `http://127.0.0.1:8081/api/v1/servers/localhost`.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ipnet::IpNet;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::net::IpAddr;
+
+use pve_sdn_core::dns::{ptr_name, record_name, reverse_zone};
+use pve_sdn_core::{DnsConfig, DnsPlugin, DnsType};
+
+/// PowerDNS zone, as far as needed for record updates
+#[derive(Debug, Deserialize)]
+struct PowerDnsZone {
+    #[serde(default)]
+    rrsets: Vec<PowerDnsRrset>,
+}
+
+/// PowerDNS resource record set
+#[derive(Debug, Deserialize)]
+struct PowerDnsRrset {
+    name: String,
+    #[serde(rename = "type")]
+    rr_type: String,
+    #[serde(default)]
+    records: Vec<PowerDnsRecord>,
+}
+
+/// PowerDNS record
+#[derive(Debug, Deserialize)]
+struct PowerDnsRecord {
+    content: String,
+}
+
+/// PowerDNS implementation
+pub struct PowerDns {
+    name: String,
+    config: DnsConfig,
+    client: Client,
+    base_url: String,
+    key: String,
+}
+
+impl PowerDns {
+    /// Create new PowerDNS client
+    pub fn new(name: String, config: DnsConfig) -> Result<Self> {
+        let base_url = config
+            .url
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("PowerDNS URL is required"))?
+            .trim_end_matches('/')
+            .to_string();
+
+        let key = config
+            .key
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("PowerDNS API key is required"))?
+            .clone();
+
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self {
+            name,
+            config,
+            client,
+            base_url,
+            key,
+        })
+    }
+
+    /// Make authenticated API request, returning the body if there is one
+    async fn api_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<Option<serde_json::Value>> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header("X-API-Key", &self.key);
+
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+
+        if !status.is_success() {
+            anyhow::bail!("PowerDNS API request failed: {} - {}", status, text.trim());
+        }
+
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_str(&text)?))
+    }
+
+    fn zone_path(zone: &str) -> String {
+        format!("/zones/{}", urlencoding::encode(&absolute(zone)))
+    }
+
+    async fn get_zone(&self, zone: &str) -> Result<PowerDnsZone> {
+        let body = self
+            .api_request(reqwest::Method::GET, &Self::zone_path(zone), None)
+            .await?
+            .with_context(|| format!("PowerDNS returned no data for zone '{}'", zone))?;
+
+        Ok(serde_json::from_value(body)?)
+    }
+
+    /// Current contents of the `rr_type` records of `name`
+    async fn get_records(&self, zone: &str, name: &str, rr_type: &str) -> Result<Vec<String>> {
+        let name = absolute(name);
+
+        Ok(self
+            .get_zone(zone)
+            .await?
+            .rrsets
+            .into_iter()
+            .filter(|rrset| rrset.name == name && rrset.rr_type == rr_type)
+            .flat_map(|rrset| rrset.records)
+            .map(|record| record.content)
+            .collect())
+    }
+
+    /// Replace the `rr_type` records of `name`, deleting the set when empty
+    async fn set_records(
+        &self,
+        zone: &str,
+        name: &str,
+        rr_type: &str,
+        contents: &[String],
+    ) -> Result<()> {
+        let rrset = if contents.is_empty() {
+            json!({
+                "name": absolute(name),
+                "type": rr_type,
+                "changetype": "DELETE",
+            })
+        } else {
+            json!({
+                "name": absolute(name),
+                "type": rr_type,
+                "ttl": self.config.ttl(),
+                "changetype": "REPLACE",
+                "records": contents
+                    .iter()
+                    .map(|content| json!({ "content": content, "disabled": false }))
+                    .collect::<Vec<_>>(),
+            })
+        };
+
+        self.api_request(
+            reqwest::Method::PATCH,
+            &Self::zone_path(zone),
+            Some(&json!({ "rrsets": [rrset] })),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Name with the trailing dot PowerDNS expects
+fn absolute(name: &str) -> String {
+    format!("{}.", name.trim_end_matches('.'))
+}
+
+fn address_type(ip: &IpAddr) -> &'static str {
+    match ip {
+        IpAddr::V4(_) => "A",
+        IpAddr::V6(_) => "AAAA",
+    }
+}
+
+#[async_trait]
+impl DnsPlugin for PowerDns {
+    fn dns_type(&self) -> DnsType {
+        DnsType::PowerDns
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self, config: &DnsConfig) -> Result<()> {
+        if config.dns_type != DnsType::PowerDns {
+            anyhow::bail!("Invalid DNS type for PowerDNS plugin");
+        }
+
+        config.validate()
+    }
+
+    async fn verify_zone(&self, zone: &str) -> Result<()> {
+        self.get_zone(zone)
+            .await
+            .with_context(|| format!("PowerDNS zone '{}' does not exist", zone))?;
+        Ok(())
+    }
+
+    async fn add_a_record(&self, zone: &str, hostname: &str, ip: &IpAddr) -> Result<()> {
+        let name = record_name(zone, hostname);
+        let rr_type = address_type(ip);
+
+        let mut records = self.get_records(zone, &name, rr_type).await?;
+        if records.contains(&ip.to_string()) {
+            return Ok(());
+        }
+        records.push(ip.to_string());
+
+        self.set_records(zone, &name, rr_type, &records).await
+    }
+
+    async fn del_a_record(&self, zone: &str, hostname: &str, ip: &IpAddr) -> Result<()> {
+        let name = record_name(zone, hostname);
+        let rr_type = address_type(ip);
+
+        let records = self.get_records(zone, &name, rr_type).await?;
+        if !records.contains(&ip.to_string()) {
+            return Ok(());
+        }
+
+        let remaining: Vec<String> = records
+            .into_iter()
+            .filter(|record| record != &ip.to_string())
+            .collect();
+
+        self.set_records(zone, &name, rr_type, &remaining).await
+    }
+
+    async fn add_ptr_record(&self, reverse_zone: &str, fqdn: &str, ip: &IpAddr) -> Result<()> {
+        self.set_records(reverse_zone, &ptr_name(ip), "PTR", &[absolute(fqdn)])
+            .await
+    }
+
+    async fn del_ptr_record(&self, reverse_zone: &str, ip: &IpAddr) -> Result<()> {
+        self.set_records(reverse_zone, &ptr_name(ip), "PTR", &[])
+            .await
+    }
+
+    fn reverse_zone(&self, cidr: &IpNet) -> String {
+        reverse_zone(cidr, self.config.reversemaskv6())
+    }
+}
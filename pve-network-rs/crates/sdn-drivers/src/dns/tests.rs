@@ -0,0 +1,486 @@
+//! DNS driver tests
+//!
+//! The PowerDNS driver runs against a minimal in-process HTTP server that
+//! keeps zones in memory and applies `PATCH` requests like PowerDNS does.
+
+use pve_sdn_core::{
+    DnsConfig, DnsPlugin, DnsType, IpAllocation, IpAllocationRequest, IpamConfig, IpamManager,
+    IpamPlugin, IpamType, SdnConfiguration, Subnet, SubnetConfig,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::PowerDns;
+use crate::ipam::PveIpam;
+use crate::PluginFactory;
+
+const API_KEY: &str = "secret";
+const API_PATH: &str = "/api/v1/servers/localhost";
+
+/// Zone name -> rrsets
+type Zones = Arc<Mutex<HashMap<String, Vec<Value>>>>;
+
+/// In-memory PowerDNS API
+struct MockPowerDns {
+    addr: SocketAddr,
+    zones: Zones,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockPowerDns {
+    async fn start(zones: &[&str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let zones: Zones = Arc::new(Mutex::new(
+            zones
+                .iter()
+                .map(|zone| (format!("{}.", zone), Vec::new()))
+                .collect(),
+        ));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let (state, log) = (zones.clone(), requests.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (state, log) = (state.clone(), log.clone());
+                tokio::spawn(async move { handle(stream, state, log).await });
+            }
+        });
+
+        Self {
+            addr,
+            zones,
+            requests,
+        }
+    }
+
+    fn config(&self) -> DnsConfig {
+        let mut config = DnsConfig::new("pdns".to_string(), DnsType::PowerDns);
+        config.url = Some(format!("http://{}{}", self.addr, API_PATH));
+        config.key = Some(API_KEY.to_string());
+        config.ttl = Some(300);
+        config
+    }
+
+    fn plugin(&self) -> PowerDns {
+        PowerDns::new("pdns".to_string(), self.config()).unwrap()
+    }
+
+    /// Contents of the `rr_type` records of `name` in `zone`
+    fn records(&self, zone: &str, name: &str, rr_type: &str) -> Vec<String> {
+        let zones = self.zones.lock().unwrap();
+        zones[&format!("{}.", zone)]
+            .iter()
+            .filter(|rrset| rrset["name"] == name && rrset["type"] == rr_type)
+            .flat_map(|rrset| rrset["records"].as_array().cloned().unwrap_or_default())
+            .map(|record| record["content"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn take_requests(&self) -> Vec<String> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+}
+
+async fn handle(mut stream: TcpStream, zones: Zones, log: Arc<Mutex<Vec<String>>>) {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+
+    let (head, body) = loop {
+        let n = stream.read(&mut buf).await.unwrap();
+        if n == 0 {
+            return;
+        }
+        data.extend_from_slice(&buf[..n]);
+
+        let text = String::from_utf8_lossy(&data).to_string();
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if body.len() >= length {
+                break (head.to_string(), body.to_string());
+            }
+        }
+    };
+
+    let mut request_line = head.lines().next().unwrap().split_whitespace();
+    let method = request_line.next().unwrap().to_string();
+    let path = urlencoding::decode(request_line.next().unwrap())
+        .unwrap()
+        .to_string();
+    let authorized = head
+        .lines()
+        .any(|line| line.eq_ignore_ascii_case(&format!("x-api-key: {}", API_KEY)));
+
+    log.lock().unwrap().push(format!("{} {}", method, path));
+
+    let zone = path.strip_prefix(&format!("{}/zones/", API_PATH));
+    let (status, response) = match (authorized, method.as_str(), zone) {
+        (false, _, _) => (
+            "401 Unauthorized",
+            r#"{"error": "Unauthorized"}"#.to_string(),
+        ),
+        (true, "GET", Some(zone)) => match zones.lock().unwrap().get(zone) {
+            Some(rrsets) => (
+                "200 OK",
+                json!({ "name": zone, "rrsets": rrsets }).to_string(),
+            ),
+            None => ("404 Not Found", r#"{"error": "Not Found"}"#.to_string()),
+        },
+        (true, "PATCH", Some(zone)) => match zones.lock().unwrap().get_mut(zone) {
+            Some(rrsets) => {
+                let patch: Value = serde_json::from_str(&body).unwrap();
+                for change in patch["rrsets"].as_array().unwrap() {
+                    rrsets.retain(|rrset| {
+                        rrset["name"] != change["name"] || rrset["type"] != change["type"]
+                    });
+                    if change["changetype"] == "REPLACE" {
+                        rrsets.push(change.clone());
+                    }
+                }
+                ("204 No Content", String::new())
+            }
+            None => ("404 Not Found", r#"{"error": "Not Found"}"#.to_string()),
+        },
+        _ => ("404 Not Found", r#"{"error": "Not Found"}"#.to_string()),
+    };
+
+    let reply = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    );
+    let _ = stream.write_all(reply.as_bytes()).await;
+}
+
+#[tokio::test]
+async fn test_powerdns_records() {
+    let server = MockPowerDns::start(&["example.com", "0.10.in-addr.arpa"]).await;
+    let pdns = server.plugin();
+
+    pdns.verify_zone("example.com").await.unwrap();
+    assert!(pdns.verify_zone("missing.org").await.is_err());
+
+    // A records accumulate per name, AAAA records are kept apart
+    let ip1: IpAddr = "10.0.1.10".parse().unwrap();
+    let ip2: IpAddr = "10.0.1.11".parse().unwrap();
+    let ip6: IpAddr = "fd00::10".parse().unwrap();
+    pdns.add_a_record("example.com", "vm100", &ip1)
+        .await
+        .unwrap();
+    pdns.add_a_record("example.com", "vm100", &ip2)
+        .await
+        .unwrap();
+    pdns.add_a_record("example.com", "vm100", &ip1)
+        .await
+        .unwrap();
+    pdns.add_a_record("example.com", "vm100", &ip6)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.records("example.com", "vm100.example.com.", "A"),
+        vec!["10.0.1.10", "10.0.1.11"]
+    );
+    assert_eq!(
+        server.records("example.com", "vm100.example.com.", "AAAA"),
+        vec!["fd00::10"]
+    );
+
+    pdns.update_a_record("example.com", "vm100", &ip2, &"10.0.1.12".parse().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        server.records("example.com", "vm100.example.com.", "A"),
+        vec!["10.0.1.10", "10.0.1.12"]
+    );
+
+    pdns.del_a_record("example.com", "vm100", &ip1)
+        .await
+        .unwrap();
+    pdns.del_a_record("example.com", "vm100", &"10.0.1.12".parse().unwrap())
+        .await
+        .unwrap();
+    assert!(server
+        .records("example.com", "vm100.example.com.", "A")
+        .is_empty());
+
+    // Deleting a missing record does not touch the zone
+    server.take_requests();
+    pdns.del_a_record("example.com", "vm100", &ip1)
+        .await
+        .unwrap();
+    assert_eq!(
+        server.take_requests(),
+        vec![format!("GET {}/zones/example.com.", API_PATH)]
+    );
+
+    // PTR records are replaced, not accumulated
+    let reverse = pdns.reverse_zone(&"10.0.1.0/24".parse().unwrap());
+    assert_eq!(reverse, "1.0.10.in-addr.arpa");
+    pdns.add_ptr_record("0.10.in-addr.arpa", "vm100.example.com", &ip1)
+        .await
+        .unwrap();
+    pdns.add_ptr_record("0.10.in-addr.arpa", "vm101.example.com", &ip1)
+        .await
+        .unwrap();
+    assert_eq!(
+        server.records("0.10.in-addr.arpa", "10.1.0.10.in-addr.arpa.", "PTR"),
+        vec!["vm101.example.com."]
+    );
+
+    pdns.del_ptr_record("0.10.in-addr.arpa", &ip1)
+        .await
+        .unwrap();
+    assert!(server
+        .records("0.10.in-addr.arpa", "10.1.0.10.in-addr.arpa.", "PTR")
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_powerdns_config() {
+    let server = MockPowerDns::start(&["example.com"]).await;
+
+    let mut config = server.config();
+    config.key = Some("wrong".to_string());
+    let pdns = PowerDns::new("pdns".to_string(), config).unwrap();
+    let err = pdns.verify_zone("example.com").await.unwrap_err();
+    assert!(format!("{:#}", err).contains("401"));
+
+    let mut config = server.config();
+    config.key = None;
+    assert!(PowerDns::new("pdns".to_string(), config.clone()).is_err());
+    assert!(config.validate().is_err());
+
+    let mut config = server.config();
+    config.reversemaskv6 = Some(48);
+    let pdns = PowerDns::new("pdns".to_string(), config.clone()).unwrap();
+    pdns.validate_config(&config).await.unwrap();
+    assert_eq!(
+        pdns.reverse_zone(&"2001:db8:1::/64".parse().unwrap()),
+        "1.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+    );
+}
+
+#[tokio::test]
+async fn test_ipam_manager_dns_registration() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let server = MockPowerDns::start(&["example.com", "2.1.10.in-addr.arpa"]).await;
+
+    let mut manager = IpamManager::new();
+    manager.register_plugin(Arc::new(
+        PveIpam::new(
+            "pve".to_string(),
+            IpamConfig::new("pve".to_string(), IpamType::Pve),
+        )
+        .with_storage_path(dir.path()),
+    ));
+    manager.set_default_plugin("pve").unwrap();
+    manager.register_dns_plugin(Arc::new(server.plugin()));
+
+    let mut config = SubnetConfig::new(
+        "dns-10.1.2.0-24".to_string(),
+        "vnet1".to_string(),
+        "10.1.2.0/24".parse().unwrap(),
+    );
+    config.options.insert("dns".to_string(), json!("pdns"));
+    config
+        .options
+        .insert("dnszone".to_string(), json!("example.com"));
+    config
+        .options
+        .insert("reversedns".to_string(), json!("pdns"));
+
+    // The zones have to exist before the subnet can use them
+    let mut unknown = config.clone();
+    unknown.subnet = "dns-unknown".to_string();
+    unknown
+        .options
+        .insert("dnszone".to_string(), json!("missing.org"));
+    assert!(manager
+        .add_subnet(None, &Subnet::new(unknown))
+        .await
+        .is_err());
+    assert_eq!(manager.subnet_dns("dns-unknown"), None);
+
+    manager
+        .add_subnet(None, &Subnet::new(config.clone()))
+        .await
+        .unwrap();
+
+    let request = |hostname: Option<&str>, ip: &str| IpAllocationRequest {
+        subnet: "dns-10.1.2.0-24".to_string(),
+        vmid: Some(100),
+        hostname: hostname.map(str::to_string),
+        mac: Some("bc:24:11:00:00:01".to_string()),
+        description: None,
+        requested_ip: Some(ip.parse().unwrap()),
+    };
+
+    let allocation = manager
+        .allocate_ip(None, &request(Some("vm100"), "10.1.2.10"))
+        .await
+        .unwrap();
+    assert_eq!(
+        server.records("example.com", "vm100.example.com.", "A"),
+        vec!["10.1.2.10"]
+    );
+    assert_eq!(
+        server.records("2.1.10.in-addr.arpa", "10.2.1.10.in-addr.arpa.", "PTR"),
+        vec!["vm100.example.com."]
+    );
+
+    // Renaming moves both records
+    let renamed = IpAllocation {
+        hostname: Some("web".to_string()),
+        ..allocation.clone()
+    };
+    manager
+        .update_ip(None, "dns-10.1.2.0-24", &allocation.ip, &renamed)
+        .await
+        .unwrap();
+    assert!(server
+        .records("example.com", "vm100.example.com.", "A")
+        .is_empty());
+    assert_eq!(
+        server.records("example.com", "web.example.com.", "A"),
+        vec!["10.1.2.10"]
+    );
+    assert_eq!(
+        server.records("2.1.10.in-addr.arpa", "10.2.1.10.in-addr.arpa.", "PTR"),
+        vec!["web.example.com."]
+    );
+
+    // Without hostname there is nothing to register
+    server.take_requests();
+    let anonymous = manager
+        .allocate_ip(None, &request(None, "10.1.2.11"))
+        .await
+        .unwrap();
+    assert!(server.take_requests().is_empty());
+    manager
+        .release_ip(None, "dns-10.1.2.0-24", &anonymous.ip)
+        .await
+        .unwrap();
+
+    manager
+        .release_ip(None, "dns-10.1.2.0-24", &allocation.ip)
+        .await
+        .unwrap();
+    assert!(server
+        .records("example.com", "web.example.com.", "A")
+        .is_empty());
+    assert!(server
+        .records("2.1.10.in-addr.arpa", "10.2.1.10.in-addr.arpa.", "PTR")
+        .is_empty());
+
+    // A DNS failure rolls the allocation back
+    let mut broken = IpamManager::new();
+    broken.register_plugin(manager.get_plugin("pve").unwrap());
+    broken.set_default_plugin("pve").unwrap();
+    let mut config = server.config();
+    config.key = Some("wrong".to_string());
+    broken.register_dns_plugin(Arc::new(PowerDns::new("pdns".to_string(), config).unwrap()));
+    broken.set_subnet_dns("dns-10.1.2.0-24", manager.subnet_dns("dns-10.1.2.0-24"));
+
+    let ip: IpAddr = "10.1.2.12".parse().unwrap();
+    assert!(broken
+        .allocate_ip(None, &request(Some("vm102"), "10.1.2.12"))
+        .await
+        .is_err());
+    assert!(broken
+        .is_ip_available(None, "dns-10.1.2.0-24", &ip)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_ipam_manager_dns_from_sdn_configuration() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let server = MockPowerDns::start(&["example.com", "2.1.10.in-addr.arpa"]).await;
+
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_dns(server.config()).unwrap();
+    let mut subnet = SubnetConfig::new(
+        "dns-10.1.2.0-24".to_string(),
+        "vnet1".to_string(),
+        "10.1.2.0/24".parse().unwrap(),
+    );
+    subnet.options.insert("dns".to_string(), json!("pdns"));
+    subnet
+        .options
+        .insert("dnszone".to_string(), json!("example.com"));
+    subnet
+        .options
+        .insert("reversedns".to_string(), json!("pdns"));
+    sdn.subnets.insert(subnet.subnet.clone(), subnet.clone());
+
+    let ipam = Arc::new(
+        PveIpam::new(
+            "pve".to_string(),
+            IpamConfig::new("pve".to_string(), IpamType::Pve),
+        )
+        .with_storage_path(dir.path()),
+    );
+    ipam.add_subnet(&Subnet::new(subnet)).await.unwrap();
+
+    let mut manager = IpamManager::new();
+    manager.register_plugin(ipam);
+    manager.set_default_plugin("pve").unwrap();
+    manager.set_dns_plugins(PluginFactory::new().create_dns_plugins(&sdn).unwrap());
+    manager.load_subnet_dns(&sdn);
+
+    let request = |hostname: &str, ip: &str| IpAllocationRequest {
+        subnet: "dns-10.1.2.0-24".to_string(),
+        vmid: Some(100),
+        hostname: Some(hostname.to_string()),
+        mac: None,
+        description: None,
+        requested_ip: Some(ip.parse().unwrap()),
+    };
+
+    // Dotted names are still relative to the zone, in both directions
+    manager
+        .allocate_ip(None, &request("vm100.lab", "10.1.2.10"))
+        .await
+        .unwrap();
+    assert_eq!(
+        server.records("example.com", "vm100.lab.example.com.", "A"),
+        vec!["10.1.2.10"]
+    );
+    assert_eq!(
+        server.records("2.1.10.in-addr.arpa", "10.2.1.10.in-addr.arpa.", "PTR"),
+        vec!["vm100.lab.example.com."]
+    );
+
+    // Dropping the options stops the registration
+    sdn.subnets
+        .get_mut("dns-10.1.2.0-24")
+        .unwrap()
+        .options
+        .clear();
+    manager.load_subnet_dns(&sdn);
+    server.take_requests();
+    manager
+        .allocate_ip(None, &request("vm101", "10.1.2.11"))
+        .await
+        .unwrap();
+    assert!(server.take_requests().is_empty());
+
+    sdn.dns.clear();
+    manager.set_dns_plugins(PluginFactory::new().create_dns_plugins(&sdn).unwrap());
+    assert!(manager.get_dns_plugin("pdns").is_err());
+}
@@ -0,0 +1,154 @@
+//! Host side of the plugin connection
+
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use super::protocol::{Request, Response, DRIVER_ERROR};
+
+/// Time a plugin gets to answer a single request
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct PluginIo {
+    reader: Box<dyn AsyncBufRead + Send + Unpin>,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+}
+
+/// Connection to a running plugin
+pub(crate) struct PluginConnection {
+    /// Plugin path or name, used in error messages
+    label: String,
+    io: Mutex<PluginIo>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    /// Killed when the connection is dropped
+    _child: Option<Child>,
+}
+
+impl PluginConnection {
+    /// Spawn the plugin executable at `path`
+    pub(crate) fn spawn(path: &Path, timeout: Duration) -> Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {}", path.display()))?;
+
+        let stdin = child.stdin.take().context("Plugin stdin is not piped")?;
+        let stdout = child.stdout.take().context("Plugin stdout is not piped")?;
+
+        let mut connection = Self::from_io(
+            &path.display().to_string(),
+            BufReader::new(stdout),
+            stdin,
+            timeout,
+        );
+        connection._child = Some(child);
+        Ok(connection)
+    }
+
+    /// Connection over an existing transport
+    pub(crate) fn from_io<R, W>(label: &str, reader: R, writer: W, timeout: Duration) -> Self
+    where
+        R: AsyncBufRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        Self {
+            label: label.to_string(),
+            io: Mutex::new(PluginIo {
+                reader: Box::new(reader),
+                writer: Box::new(writer),
+            }),
+            next_id: AtomicU64::new(1),
+            timeout,
+            _child: None,
+        }
+    }
+
+    /// Call `method` and decode its result
+    pub(crate) async fn call<P, R>(&self, method: &str, params: P) -> Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = Request::new(id, method, serde_json::to_value(params)?);
+
+        let mut io = self.io.lock().await;
+        let response = tokio::time::timeout(self.timeout, exchange(&mut io, &request))
+            .await
+            .with_context(|| {
+                format!(
+                    "Plugin {} did not answer '{}' within {}s",
+                    self.label,
+                    method,
+                    self.timeout.as_secs()
+                )
+            })?
+            .with_context(|| format!("Plugin {} failed on '{}'", self.label, method))?;
+        drop(io);
+
+        if let Some(error) = response.error {
+            if error.code == DRIVER_ERROR {
+                bail!("{}", error.message);
+            }
+            bail!(
+                "Plugin {} rejected '{}': {} ({})",
+                self.label,
+                method,
+                error.message,
+                error.code
+            );
+        }
+
+        let result = response.result.unwrap_or_default();
+        serde_json::from_value(result).with_context(|| {
+            format!(
+                "Plugin {} returned an invalid result for '{}'",
+                self.label, method
+            )
+        })
+    }
+}
+
+/// Send `request` and wait for its response
+///
+/// Responses to earlier requests that timed out are skipped.
+async fn exchange(io: &mut PluginIo, request: &Request) -> Result<Response> {
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    io.writer.write_all(line.as_bytes()).await?;
+    io.writer.flush().await?;
+
+    loop {
+        let mut line = String::new();
+        if io.reader.read_line(&mut line).await? == 0 {
+            bail!("plugin closed its output");
+        }
+
+        let response: Response = match serde_json::from_str(&line) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Ignoring invalid plugin output '{}': {}", line.trim(), e);
+                continue;
+            }
+        };
+
+        match response.id {
+            Some(id) if id == request.id => return Ok(response),
+            None if response.error.is_some() => return Ok(response),
+            _ => warn!("Ignoring stale plugin response {:?}", response.id),
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Out-of-process SDN driver plugins
+//!
+//! Third-party zone, controller and IPAM drivers run as separate
+//! executables speaking JSON-RPC over stdio (see [`protocol`]). The host
+//! spawns the plugin, asks for its capabilities with `initialize` and
+//! registers proxy factories for every driver type the plugin provides.
+//! Plugin types must not shadow the built-in ones and are configured like
+//! them, e.g. `"type": "sample"` in a zone.
+//!
+//! Plugins written in Rust implement the driver traits and serve them with
+//! [`PluginServer`]; `pve-sdn-sample-plugin` is a complete example.
+
+mod client;
+pub mod protocol;
+mod proxy;
+mod server;
+
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::info;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncBufRead, AsyncWrite};
+
+use pve_sdn_core::{ControllerType, IpamType, ZoneType};
+
+use crate::plugin_factory::PluginFactory;
+use client::PluginConnection;
+use protocol::{methods, InitializeParams, NoArgs, PROTOCOL_VERSION};
+
+pub use client::DEFAULT_REQUEST_TIMEOUT;
+pub use protocol::PluginCapabilities;
+pub use proxy::{ExternalController, ExternalIpam, ExternalZone};
+pub use server::PluginServer;
+
+/// Handle of a running plugin
+#[derive(Clone)]
+pub struct ExternalPlugin {
+    connection: Arc<PluginConnection>,
+    capabilities: PluginCapabilities,
+}
+
+impl ExternalPlugin {
+    /// Start the plugin executable at `path` and perform the handshake
+    pub async fn spawn(path: &Path) -> Result<Self> {
+        Self::spawn_with_timeout(path, DEFAULT_REQUEST_TIMEOUT).await
+    }
+
+    /// Like `spawn`, giving the plugin `timeout` to answer each request
+    pub async fn spawn_with_timeout(path: &Path, timeout: Duration) -> Result<Self> {
+        let connection = PluginConnection::spawn(path, timeout)?;
+        Self::handshake(connection).await
+    }
+
+    /// Talk to a plugin over an existing transport and perform the handshake
+    pub async fn connect<R, W>(label: &str, reader: R, writer: W) -> Result<Self>
+    where
+        R: AsyncBufRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let connection = PluginConnection::from_io(label, reader, writer, DEFAULT_REQUEST_TIMEOUT);
+        Self::handshake(connection).await
+    }
+
+    async fn handshake(connection: PluginConnection) -> Result<Self> {
+        let params = InitializeParams {
+            protocol_version: PROTOCOL_VERSION,
+        };
+        let capabilities: PluginCapabilities = connection
+            .call(methods::INITIALIZE, params)
+            .await
+            .context("Plugin handshake failed")?;
+
+        if capabilities.protocol_version != PROTOCOL_VERSION {
+            bail!(
+                "Plugin '{}' speaks protocol version {}, expected {}",
+                capabilities.name,
+                capabilities.protocol_version,
+                PROTOCOL_VERSION
+            );
+        }
+
+        info!(
+            "Loaded SDN plugin '{}' {} (zones: {:?}, controllers: {:?}, ipams: {:?})",
+            capabilities.name,
+            capabilities.version,
+            capabilities.zones,
+            capabilities.controllers,
+            capabilities.ipams
+        );
+
+        Ok(Self {
+            connection: Arc::new(connection),
+            capabilities,
+        })
+    }
+
+    /// Name, version and driver types announced by the plugin
+    pub fn capabilities(&self) -> &PluginCapabilities {
+        &self.capabilities
+    }
+
+    /// Register proxy factories for all driver types of the plugin
+    ///
+    /// Nothing is registered if any type is built in or already provided by
+    /// another plugin.
+    pub fn register(&self, factory: &PluginFactory) -> Result<()> {
+        let plugin = &self.capabilities.name;
+
+        let zones: Vec<ZoneType> = self
+            .capabilities
+            .zones
+            .iter()
+            .map(|name| {
+                let zone_type = plugin_type(plugin, "zone", name, |t: &ZoneType| {
+                    matches!(t, ZoneType::External(_))
+                })?;
+                if factory.has_zone_driver(&zone_type) {
+                    bail!("Zone type '{}' is already registered", zone_type);
+                }
+                Ok(zone_type)
+            })
+            .collect::<Result<_>>()?;
+
+        let controllers: Vec<ControllerType> = self
+            .capabilities
+            .controllers
+            .iter()
+            .map(|name| {
+                let controller_type =
+                    plugin_type(plugin, "controller", name, |t: &ControllerType| {
+                        matches!(t, ControllerType::External(_))
+                    })?;
+                if factory.has_controller_driver(&controller_type) {
+                    bail!(
+                        "Controller type '{}' is already registered",
+                        controller_type
+                    );
+                }
+                Ok(controller_type)
+            })
+            .collect::<Result<_>>()?;
+
+        let ipams: Vec<IpamType> = self
+            .capabilities
+            .ipams
+            .iter()
+            .map(|name| {
+                let ipam_type = plugin_type(plugin, "IPAM", name, |t: &IpamType| {
+                    matches!(t, IpamType::External(_))
+                })?;
+                if factory.has_ipam_driver(&ipam_type) {
+                    bail!("IPAM type '{}' is already registered", ipam_type);
+                }
+                Ok(ipam_type)
+            })
+            .collect::<Result<_>>()?;
+
+        for zone_type in zones {
+            let connection = self.connection.clone();
+            let driver = zone_type.to_string();
+            factory.register_zone_driver(
+                zone_type,
+                Box::new(move |name| {
+                    Box::new(ExternalZone::new(connection.clone(), &driver, name))
+                }),
+            );
+        }
+
+        for controller_type in controllers {
+            let connection = self.connection.clone();
+            let driver = controller_type.to_string();
+            factory.register_controller_driver(
+                controller_type,
+                Box::new(move |name| {
+                    Box::new(ExternalController::new(connection.clone(), &driver, name))
+                }),
+            );
+        }
+
+        for ipam_type in ipams {
+            let connection = self.connection.clone();
+            let driver = ipam_type.to_string();
+            factory.register_ipam_driver(
+                ipam_type,
+                Box::new(move |name| {
+                    Box::new(ExternalIpam::new(connection.clone(), &driver, name))
+                }),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Ask the plugin to exit
+    ///
+    /// Drivers registered from this plugin fail afterwards.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.connection.call(methods::SHUTDOWN, NoArgs {}).await
+    }
+}
+
+impl std::fmt::Debug for ExternalPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalPlugin")
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
+}
+
+/// Driver type `name` announced by `plugin`, which must be an external one
+fn plugin_type<T>(plugin: &str, kind: &str, name: &str, is_external: fn(&T) -> bool) -> Result<T>
+where
+    T: DeserializeOwned + Display,
+{
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        bail!(
+            "Plugin '{}' announced invalid {} type '{}'",
+            plugin,
+            kind,
+            name
+        );
+    }
+
+    let driver_type: T = serde_json::from_value(serde_json::Value::String(name.to_string()))?;
+    if !is_external(&driver_type) {
+        bail!(
+            "Plugin '{}' cannot replace the built-in {} type '{}'",
+            plugin,
+            kind,
+            driver_type
+        );
+    }
+
+    Ok(driver_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_sdn_core::{Zone, ZoneConfig};
+    use std::collections::HashMap;
+    use tokio::io::BufReader;
+
+    struct EchoZone(String);
+
+    #[async_trait::async_trait]
+    impl Zone for EchoZone {
+        fn zone_type(&self) -> ZoneType {
+            ZoneType::External("echo".to_string())
+        }
+
+        fn name(&self) -> &str {
+            &self.0
+        }
+
+        async fn validate_config(&self, config: &ZoneConfig) -> Result<()> {
+            if config.bridge.is_none() {
+                bail!("Zone '{}' requires a bridge", config.zone);
+            }
+            Ok(())
+        }
+
+        async fn apply_config(&self, _config: &ZoneConfig) -> Result<()> {
+            Ok(())
+        }
+
+        async fn generate_config(&self, config: &ZoneConfig) -> Result<HashMap<String, String>> {
+            let mut files = HashMap::new();
+            files.insert(self.0.clone(), config.bridge.clone().unwrap_or_default());
+            Ok(files)
+        }
+    }
+
+    /// Plugin served in-process over an in-memory pipe
+    async fn connect(server: PluginServer) -> Result<ExternalPlugin> {
+        let (host, plugin) = tokio::io::duplex(64 * 1024);
+        let (plugin_read, plugin_write) = tokio::io::split(plugin);
+        tokio::spawn(async move {
+            server
+                .serve(BufReader::new(plugin_read), plugin_write)
+                .await
+        });
+
+        let (host_read, host_write) = tokio::io::split(host);
+        ExternalPlugin::connect("echo", BufReader::new(host_read), host_write).await
+    }
+
+    fn echo_server() -> PluginServer {
+        PluginServer::new("echo", "1.0").with_zone(
+            "echo",
+            Box::new(|name| Box::new(EchoZone(name)) as Box<dyn Zone>),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_external_zone_roundtrip() {
+        let plugin = connect(echo_server()).await.unwrap();
+        assert_eq!(plugin.capabilities().zones, vec!["echo".to_string()]);
+
+        let factory = PluginFactory::new();
+        plugin.register(&factory).unwrap();
+
+        let zone_type = ZoneType::External("echo".to_string());
+        let zone = factory
+            .create_zone(&zone_type, "zone1".to_string())
+            .unwrap();
+        assert_eq!(zone.zone_type(), zone_type);
+
+        let mut config = ZoneConfig::new(zone_type, "zone1".to_string());
+        let err = zone.validate_config(&config).await.unwrap_err();
+        assert_eq!(err.to_string(), "Zone 'zone1' requires a bridge");
+
+        config.bridge = Some("vmbr0".to_string());
+        zone.validate_config(&config).await.unwrap();
+        let files = zone.generate_config(&config).await.unwrap();
+        assert_eq!(files["zone1"], "vmbr0");
+
+        // Default trait methods are served by the plugin as well
+        let state = zone.observed_state(&config).await.unwrap();
+        assert_eq!(state.zone, "zone1");
+
+        // Registering the same types twice is refused
+        assert!(plugin.register(&factory).is_err());
+
+        plugin.shutdown().await.unwrap();
+        assert!(zone.validate_config(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_external_builtin_type_rejected() {
+        let server = PluginServer::new("rogue", "1.0").with_zone(
+            "simple",
+            Box::new(|name| Box::new(EchoZone(name)) as Box<dyn Zone>),
+        );
+        let plugin = connect(server).await.unwrap();
+
+        let factory = PluginFactory::new();
+        let err = plugin.register(&factory).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Plugin 'rogue' cannot replace the built-in zone type 'simple'"
+        );
+        assert_eq!(
+            factory
+                .create_zone(&ZoneType::Simple, "zone1".to_string())
+                .unwrap()
+                .zone_type(),
+            ZoneType::Simple
+        );
+    }
+}
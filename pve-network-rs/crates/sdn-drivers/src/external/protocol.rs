@@ -0,0 +1,258 @@
+//! Wire format of the external plugin protocol
+//!
+//! Messages are JSON-RPC 2.0 objects, one per line. The host sends requests
+//! on the plugin's stdin and reads the responses from its stdout; requests
+//! are answered in order, one at a time.
+//!
+//! Driver calls name the driver type and instance they target and carry the
+//! arguments of the trait method as named parameters, e.g.
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":3,"method":"zone.generate_config",
+//!  "params":{"type":"sample","name":"zone1","config":{...}}}
+//! ```
+//!
+//! Methods returning `Result<()>` answer with `null`.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use pve_sdn_core::controller::ControllerConfig;
+use pve_sdn_core::{
+    IpAllocation, IpAllocationRequest, IpamConfig, SdnConfiguration, Subnet, VNet, VNetConfig,
+    ZoneConfig, ZoneType,
+};
+
+/// Protocol version spoken by this host
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// JSON-RPC version tag
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// The request line is not valid JSON
+pub const PARSE_ERROR: i64 = -32700;
+/// The request is not a valid JSON-RPC request
+pub const INVALID_REQUEST: i64 = -32600;
+/// The plugin does not implement the method or driver type
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters do not match the method
+pub const INVALID_PARAMS: i64 = -32602;
+/// The driver returned an error
+pub const DRIVER_ERROR: i64 = -32000;
+
+/// Method names
+pub mod methods {
+    /// Handshake, see [`InitializeParams`](super::InitializeParams)
+    pub const INITIALIZE: &str = "initialize";
+    /// Ask the plugin to exit after answering
+    pub const SHUTDOWN: &str = "shutdown";
+
+    pub const ZONE_VALIDATE_CONFIG: &str = "zone.validate_config";
+    pub const ZONE_APPLY_CONFIG: &str = "zone.apply_config";
+    pub const ZONE_GENERATE_CONFIG: &str = "zone.generate_config";
+    pub const ZONE_GENERATE_NODE_CONFIG: &str = "zone.generate_node_config";
+    pub const ZONE_REMOVE_CONFIG: &str = "zone.remove_config";
+    pub const ZONE_OBSERVED_STATE: &str = "zone.observed_state";
+    pub const ZONE_OBSERVED_VNET_STATE: &str = "zone.observed_vnet_state";
+
+    pub const CONTROLLER_VALIDATE_CONFIGURATION: &str = "controller.validate_configuration";
+    pub const CONTROLLER_APPLY_CONFIGURATION: &str = "controller.apply_configuration";
+    pub const CONTROLLER_GENERATE_CONFIG: &str = "controller.generate_config";
+    pub const CONTROLLER_START: &str = "controller.start";
+    pub const CONTROLLER_STOP: &str = "controller.stop";
+    pub const CONTROLLER_STATUS: &str = "controller.status";
+    pub const CONTROLLER_RELOAD: &str = "controller.reload";
+
+    pub const IPAM_VALIDATE_CONFIG: &str = "ipam.validate_config";
+    pub const IPAM_ALLOCATE_IP: &str = "ipam.allocate_ip";
+    pub const IPAM_RELEASE_IP: &str = "ipam.release_ip";
+    pub const IPAM_UPDATE_IP: &str = "ipam.update_ip";
+    pub const IPAM_GET_IP: &str = "ipam.get_ip";
+    pub const IPAM_LIST_SUBNET_IPS: &str = "ipam.list_subnet_ips";
+    pub const IPAM_VALIDATE_SUBNET: &str = "ipam.validate_subnet";
+    pub const IPAM_ADD_SUBNET: &str = "ipam.add_subnet";
+    pub const IPAM_REMOVE_SUBNET: &str = "ipam.remove_subnet";
+    pub const IPAM_GET_NEXT_FREE_IP: &str = "ipam.get_next_free_ip";
+    pub const IPAM_IS_IP_AVAILABLE: &str = "ipam.is_ip_available";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub id: u64,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    /// Id of the request, `null` if it could not be parsed
+    pub id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    pub fn success(id: u64, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(id),
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(id: Option<u64>, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Parameters of `initialize`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitializeParams {
+    pub protocol_version: u32,
+}
+
+/// Result of `initialize`: who the plugin is and which drivers it provides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginCapabilities {
+    pub name: String,
+    pub version: String,
+    pub protocol_version: u32,
+    /// Zone types, e.g. `["sample"]`
+    #[serde(default)]
+    pub zones: Vec<String>,
+    /// Controller types
+    #[serde(default)]
+    pub controllers: Vec<String>,
+    /// IPAM types
+    #[serde(default)]
+    pub ipams: Vec<String>,
+}
+
+/// Parameters of a driver call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriverCall<A> {
+    /// Driver type
+    #[serde(rename = "type")]
+    pub driver: String,
+    /// Instance name
+    pub name: String,
+    #[serde(flatten)]
+    pub args: A,
+}
+
+/// Arguments of methods without any
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoArgs {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneConfigArgs {
+    pub config: ZoneConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneNodeConfigArgs {
+    pub config: ZoneConfig,
+    pub node: String,
+    pub sdn: SdnConfiguration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneVNetArgs {
+    pub config: ZoneConfig,
+    pub vnet: VNetConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControllerConfigArgs {
+    pub config: ControllerConfig,
+}
+
+/// Zone passed to a controller, by name and type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneRef {
+    pub zone: String,
+    #[serde(rename = "type")]
+    pub zone_type: ZoneType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControllerApplyArgs {
+    pub zones: Vec<ZoneRef>,
+    pub vnets: Vec<VNet>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpamConfigArgs {
+    pub config: IpamConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocateArgs {
+    pub request: IpAllocationRequest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpArgs {
+    pub subnet: String,
+    pub ip: IpAddr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateIpArgs {
+    pub subnet: String,
+    pub ip: IpAddr,
+    pub allocation: IpAllocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubnetNameArgs {
+    pub subnet: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubnetArgs {
+    pub subnet: Subnet,
+}
+
+/// Result of `zone.generate_config` and `controller.generate_config`
+pub type GeneratedFiles = HashMap<String, String>;
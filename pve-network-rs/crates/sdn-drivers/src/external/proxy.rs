@@ -0,0 +1,326 @@
+//! Drivers forwarding to an external plugin
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use pve_sdn_core::controller::{ControllerConfig, ControllerStatus};
+use pve_sdn_core::{
+    Controller, ControllerType, IpAllocation, IpAllocationRequest, IpamConfig, IpamPlugin,
+    IpamType, Subnet, VNet, VNetConfig, Zone, ZoneConfig, ZoneObservedState, ZoneRenderContext,
+    ZoneType,
+};
+
+use super::client::PluginConnection;
+use super::protocol::*;
+
+/// Driver instance served by a plugin
+struct Remote {
+    connection: Arc<PluginConnection>,
+    driver: String,
+    name: String,
+}
+
+impl Remote {
+    async fn call<A: Serialize, R: DeserializeOwned>(&self, method: &str, args: A) -> Result<R> {
+        let params = DriverCall {
+            driver: self.driver.clone(),
+            name: self.name.clone(),
+            args,
+        };
+        self.connection.call(method, params).await
+    }
+}
+
+/// Zone implemented by an external plugin
+pub struct ExternalZone {
+    remote: Remote,
+}
+
+impl ExternalZone {
+    pub(crate) fn new(connection: Arc<PluginConnection>, zone_type: &str, name: String) -> Self {
+        Self {
+            remote: Remote {
+                connection,
+                driver: zone_type.to_string(),
+                name,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Zone for ExternalZone {
+    fn zone_type(&self) -> ZoneType {
+        ZoneType::External(self.remote.driver.clone())
+    }
+
+    fn name(&self) -> &str {
+        &self.remote.name
+    }
+
+    async fn validate_config(&self, config: &ZoneConfig) -> Result<()> {
+        let args = ZoneConfigArgs {
+            config: config.clone(),
+        };
+        self.remote.call(methods::ZONE_VALIDATE_CONFIG, args).await
+    }
+
+    async fn apply_config(&self, config: &ZoneConfig) -> Result<()> {
+        let args = ZoneConfigArgs {
+            config: config.clone(),
+        };
+        self.remote.call(methods::ZONE_APPLY_CONFIG, args).await
+    }
+
+    async fn generate_config(&self, config: &ZoneConfig) -> Result<HashMap<String, String>> {
+        let args = ZoneConfigArgs {
+            config: config.clone(),
+        };
+        self.remote.call(methods::ZONE_GENERATE_CONFIG, args).await
+    }
+
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        let args = ZoneNodeConfigArgs {
+            config: config.clone(),
+            node: ctx.node.to_string(),
+            sdn: ctx.sdn.clone(),
+        };
+        self.remote
+            .call(methods::ZONE_GENERATE_NODE_CONFIG, args)
+            .await
+    }
+
+    async fn remove_config(&self, config: &ZoneConfig) -> Result<()> {
+        let args = ZoneConfigArgs {
+            config: config.clone(),
+        };
+        self.remote.call(methods::ZONE_REMOVE_CONFIG, args).await
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let args = ZoneConfigArgs {
+            config: config.clone(),
+        };
+        self.remote.call(methods::ZONE_OBSERVED_STATE, args).await
+    }
+
+    async fn observed_vnet_state(
+        &self,
+        config: &ZoneConfig,
+        vnet: &VNetConfig,
+    ) -> Result<ZoneObservedState> {
+        let args = ZoneVNetArgs {
+            config: config.clone(),
+            vnet: vnet.clone(),
+        };
+        self.remote
+            .call(methods::ZONE_OBSERVED_VNET_STATE, args)
+            .await
+    }
+}
+
+/// Controller implemented by an external plugin
+pub struct ExternalController {
+    remote: Remote,
+}
+
+impl ExternalController {
+    pub(crate) fn new(
+        connection: Arc<PluginConnection>,
+        controller_type: &str,
+        name: String,
+    ) -> Self {
+        Self {
+            remote: Remote {
+                connection,
+                driver: controller_type.to_string(),
+                name,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Controller for ExternalController {
+    fn controller_type(&self) -> ControllerType {
+        ControllerType::External(self.remote.driver.clone())
+    }
+
+    fn name(&self) -> &str {
+        &self.remote.name
+    }
+
+    async fn validate_configuration(&self, config: &ControllerConfig) -> Result<()> {
+        let args = ControllerConfigArgs {
+            config: config.clone(),
+        };
+        self.remote
+            .call(methods::CONTROLLER_VALIDATE_CONFIGURATION, args)
+            .await
+    }
+
+    async fn apply_configuration(&self, zones: &[Box<dyn Zone>], vnets: &[VNet]) -> Result<()> {
+        let args = ControllerApplyArgs {
+            zones: zones
+                .iter()
+                .map(|zone| ZoneRef {
+                    zone: zone.name().to_string(),
+                    zone_type: zone.zone_type(),
+                })
+                .collect(),
+            vnets: vnets.to_vec(),
+        };
+        self.remote
+            .call(methods::CONTROLLER_APPLY_CONFIGURATION, args)
+            .await
+    }
+
+    async fn generate_config(&self, config: &ControllerConfig) -> Result<HashMap<String, String>> {
+        let args = ControllerConfigArgs {
+            config: config.clone(),
+        };
+        self.remote
+            .call(methods::CONTROLLER_GENERATE_CONFIG, args)
+            .await
+    }
+
+    async fn start(&self) -> Result<()> {
+        self.remote.call(methods::CONTROLLER_START, NoArgs {}).await
+    }
+
+    async fn stop(&self) -> Result<()> {
+        self.remote.call(methods::CONTROLLER_STOP, NoArgs {}).await
+    }
+
+    async fn status(&self) -> Result<ControllerStatus> {
+        self.remote
+            .call(methods::CONTROLLER_STATUS, NoArgs {})
+            .await
+    }
+
+    async fn reload(&self) -> Result<()> {
+        self.remote
+            .call(methods::CONTROLLER_RELOAD, NoArgs {})
+            .await
+    }
+}
+
+/// IPAM implemented by an external plugin
+pub struct ExternalIpam {
+    remote: Remote,
+}
+
+impl ExternalIpam {
+    pub(crate) fn new(connection: Arc<PluginConnection>, ipam_type: &str, name: String) -> Self {
+        Self {
+            remote: Remote {
+                connection,
+                driver: ipam_type.to_string(),
+                name,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl IpamPlugin for ExternalIpam {
+    fn plugin_type(&self) -> IpamType {
+        IpamType::External(self.remote.driver.clone())
+    }
+
+    fn name(&self) -> &str {
+        &self.remote.name
+    }
+
+    async fn validate_config(&self, config: &IpamConfig) -> Result<()> {
+        let args = IpamConfigArgs {
+            config: config.clone(),
+        };
+        self.remote.call(methods::IPAM_VALIDATE_CONFIG, args).await
+    }
+
+    async fn allocate_ip(&self, request: &IpAllocationRequest) -> Result<IpAllocation> {
+        let args = AllocateArgs {
+            request: request.clone(),
+        };
+        self.remote.call(methods::IPAM_ALLOCATE_IP, args).await
+    }
+
+    async fn release_ip(&self, subnet: &str, ip: &IpAddr) -> Result<()> {
+        let args = IpArgs {
+            subnet: subnet.to_string(),
+            ip: *ip,
+        };
+        self.remote.call(methods::IPAM_RELEASE_IP, args).await
+    }
+
+    async fn update_ip(&self, subnet: &str, ip: &IpAddr, allocation: &IpAllocation) -> Result<()> {
+        let args = UpdateIpArgs {
+            subnet: subnet.to_string(),
+            ip: *ip,
+            allocation: allocation.clone(),
+        };
+        self.remote.call(methods::IPAM_UPDATE_IP, args).await
+    }
+
+    async fn get_ip(&self, subnet: &str, ip: &IpAddr) -> Result<Option<IpAllocation>> {
+        let args = IpArgs {
+            subnet: subnet.to_string(),
+            ip: *ip,
+        };
+        self.remote.call(methods::IPAM_GET_IP, args).await
+    }
+
+    async fn list_subnet_ips(&self, subnet: &str) -> Result<Vec<IpAllocation>> {
+        let args = SubnetNameArgs {
+            subnet: subnet.to_string(),
+        };
+        self.remote.call(methods::IPAM_LIST_SUBNET_IPS, args).await
+    }
+
+    async fn validate_subnet(&self, subnet: &Subnet) -> Result<()> {
+        let args = SubnetArgs {
+            subnet: subnet.clone(),
+        };
+        self.remote.call(methods::IPAM_VALIDATE_SUBNET, args).await
+    }
+
+    async fn add_subnet(&self, subnet: &Subnet) -> Result<()> {
+        let args = SubnetArgs {
+            subnet: subnet.clone(),
+        };
+        self.remote.call(methods::IPAM_ADD_SUBNET, args).await
+    }
+
+    async fn remove_subnet(&self, subnet_name: &str) -> Result<()> {
+        let args = SubnetNameArgs {
+            subnet: subnet_name.to_string(),
+        };
+        self.remote.call(methods::IPAM_REMOVE_SUBNET, args).await
+    }
+
+    async fn get_next_free_ip(&self, subnet: &str) -> Result<Option<IpAddr>> {
+        let args = SubnetNameArgs {
+            subnet: subnet.to_string(),
+        };
+        self.remote.call(methods::IPAM_GET_NEXT_FREE_IP, args).await
+    }
+
+    async fn is_ip_available(&self, subnet: &str, ip: &IpAddr) -> Result<bool> {
+        let args = IpArgs {
+            subnet: subnet.to_string(),
+            ip: *ip,
+        };
+        self.remote.call(methods::IPAM_IS_IP_AVAILABLE, args).await
+    }
+}
@@ -0,0 +1,373 @@
+//! Plugin side of the protocol
+//!
+//! [`PluginServer`] serves `Zone`, `Controller` and `IpamPlugin`
+//! implementations over stdio, so a plugin only has to implement the traits
+//! and register its factories.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use pve_sdn_core::{Controller, IpamPlugin, Zone, ZoneConfig, ZoneRenderContext, ZoneType};
+
+use super::protocol::*;
+use crate::plugin_factory::{ControllerFactory, IpamFactory, ZoneFactory};
+
+type Factories<T> = HashMap<String, Box<dyn Fn(String) -> Box<T> + Send + Sync>>;
+type Instances<T> = Mutex<HashMap<(String, String), Arc<T>>>;
+
+/// Serves drivers to the host over the plugin protocol
+pub struct PluginServer {
+    name: String,
+    version: String,
+    zones: HashMap<String, ZoneFactory>,
+    controllers: HashMap<String, ControllerFactory>,
+    ipams: HashMap<String, IpamFactory>,
+    zone_instances: Instances<dyn Zone>,
+    controller_instances: Instances<dyn Controller>,
+    ipam_instances: Instances<dyn IpamPlugin>,
+}
+
+impl PluginServer {
+    /// Create server for the plugin `name`
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            zones: HashMap::new(),
+            controllers: HashMap::new(),
+            ipams: HashMap::new(),
+            zone_instances: Mutex::new(HashMap::new()),
+            controller_instances: Mutex::new(HashMap::new()),
+            ipam_instances: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Provide the zone type `zone_type`
+    pub fn with_zone(mut self, zone_type: &str, factory: ZoneFactory) -> Self {
+        self.zones.insert(zone_type.to_string(), factory);
+        self
+    }
+
+    /// Provide the controller type `controller_type`
+    pub fn with_controller(mut self, controller_type: &str, factory: ControllerFactory) -> Self {
+        self.controllers
+            .insert(controller_type.to_string(), factory);
+        self
+    }
+
+    /// Provide the IPAM type `ipam_type`
+    pub fn with_ipam(mut self, ipam_type: &str, factory: IpamFactory) -> Self {
+        self.ipams.insert(ipam_type.to_string(), factory);
+        self
+    }
+
+    /// Capabilities announced in the handshake
+    pub fn capabilities(&self) -> PluginCapabilities {
+        let sorted = |types: Vec<&String>| {
+            let mut types: Vec<String> = types.into_iter().cloned().collect();
+            types.sort();
+            types
+        };
+
+        PluginCapabilities {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            protocol_version: PROTOCOL_VERSION,
+            zones: sorted(self.zones.keys().collect()),
+            controllers: sorted(self.controllers.keys().collect()),
+            ipams: sorted(self.ipams.keys().collect()),
+        }
+    }
+
+    /// Serve requests on stdin until it is closed or the host sends
+    /// `shutdown`
+    pub async fn serve_stdio(&self) -> Result<()> {
+        self.serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+            .await
+    }
+
+    /// Serve requests read from `reader` until EOF or `shutdown`
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = reader.lines();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut shutdown = false;
+            let response = match parse_request(&line) {
+                Ok(request) => {
+                    shutdown = request.method == methods::SHUTDOWN;
+                    match self.handle(&request.method, request.params).await {
+                        Ok(result) => Response::success(request.id, result),
+                        Err(error) => Response::failure(Some(request.id), error),
+                    }
+                }
+                Err(response) => response,
+            };
+
+            let mut output = serde_json::to_string(&response)?;
+            output.push('\n');
+            writer.write_all(output.as_bytes()).await?;
+            writer.flush().await?;
+
+            if shutdown {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            methods::INITIALIZE => {
+                let params: InitializeParams = decode(params)?;
+                if params.protocol_version != PROTOCOL_VERSION {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        format!(
+                            "Unsupported protocol version {}, expected {}",
+                            params.protocol_version, PROTOCOL_VERSION
+                        ),
+                    ));
+                }
+                encode(self.capabilities())
+            }
+            methods::SHUTDOWN => Ok(Value::Null),
+            _ if method.starts_with("zone.") => self.handle_zone(method, params).await,
+            _ if method.starts_with("controller.") => self.handle_controller(method, params).await,
+            _ if method.starts_with("ipam.") => self.handle_ipam(method, params).await,
+            _ => Err(method_not_found(method)),
+        }
+    }
+
+    async fn handle_zone(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let call: DriverCall<Map<String, Value>> = decode(params)?;
+        let zone = instance(&self.zone_instances, &self.zones, "Zone", &call)?;
+        let args = Value::Object(call.args);
+
+        match method {
+            methods::ZONE_VALIDATE_CONFIG => {
+                let args: ZoneConfigArgs = decode(args)?;
+                driver_result(zone.validate_config(&args.config).await)
+            }
+            methods::ZONE_APPLY_CONFIG => {
+                let args: ZoneConfigArgs = decode(args)?;
+                driver_result(zone.apply_config(&args.config).await)
+            }
+            methods::ZONE_GENERATE_CONFIG => {
+                let args: ZoneConfigArgs = decode(args)?;
+                driver_result(zone.generate_config(&args.config).await)
+            }
+            methods::ZONE_GENERATE_NODE_CONFIG => {
+                let args: ZoneNodeConfigArgs = decode(args)?;
+                let ctx = ZoneRenderContext::new(&args.node, &args.sdn);
+                driver_result(zone.generate_node_config(&args.config, &ctx).await)
+            }
+            methods::ZONE_REMOVE_CONFIG => {
+                let args: ZoneConfigArgs = decode(args)?;
+                driver_result(zone.remove_config(&args.config).await)
+            }
+            methods::ZONE_OBSERVED_STATE => {
+                let args: ZoneConfigArgs = decode(args)?;
+                driver_result(zone.observed_state(&args.config).await)
+            }
+            methods::ZONE_OBSERVED_VNET_STATE => {
+                let args: ZoneVNetArgs = decode(args)?;
+                driver_result(zone.observed_vnet_state(&args.config, &args.vnet).await)
+            }
+            _ => Err(method_not_found(method)),
+        }
+    }
+
+    async fn handle_controller(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let call: DriverCall<Map<String, Value>> = decode(params)?;
+        let controller = instance(
+            &self.controller_instances,
+            &self.controllers,
+            "Controller",
+            &call,
+        )?;
+        let args = Value::Object(call.args);
+
+        match method {
+            methods::CONTROLLER_VALIDATE_CONFIGURATION => {
+                let args: ControllerConfigArgs = decode(args)?;
+                driver_result(controller.validate_configuration(&args.config).await)
+            }
+            methods::CONTROLLER_APPLY_CONFIGURATION => {
+                let args: ControllerApplyArgs = decode(args)?;
+                let zones: Vec<Box<dyn Zone>> = args
+                    .zones
+                    .into_iter()
+                    .map(|zone| Box::new(HostZone(zone)) as Box<dyn Zone>)
+                    .collect();
+                driver_result(controller.apply_configuration(&zones, &args.vnets).await)
+            }
+            methods::CONTROLLER_GENERATE_CONFIG => {
+                let args: ControllerConfigArgs = decode(args)?;
+                driver_result(controller.generate_config(&args.config).await)
+            }
+            methods::CONTROLLER_START => driver_result(controller.start().await),
+            methods::CONTROLLER_STOP => driver_result(controller.stop().await),
+            methods::CONTROLLER_STATUS => driver_result(controller.status().await),
+            methods::CONTROLLER_RELOAD => driver_result(controller.reload().await),
+            _ => Err(method_not_found(method)),
+        }
+    }
+
+    async fn handle_ipam(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let call: DriverCall<Map<String, Value>> = decode(params)?;
+        let ipam = instance(&self.ipam_instances, &self.ipams, "IPAM", &call)?;
+        let args = Value::Object(call.args);
+
+        match method {
+            methods::IPAM_VALIDATE_CONFIG => {
+                let args: IpamConfigArgs = decode(args)?;
+                driver_result(ipam.validate_config(&args.config).await)
+            }
+            methods::IPAM_ALLOCATE_IP => {
+                let args: AllocateArgs = decode(args)?;
+                driver_result(ipam.allocate_ip(&args.request).await)
+            }
+            methods::IPAM_RELEASE_IP => {
+                let args: IpArgs = decode(args)?;
+                driver_result(ipam.release_ip(&args.subnet, &args.ip).await)
+            }
+            methods::IPAM_UPDATE_IP => {
+                let args: UpdateIpArgs = decode(args)?;
+                driver_result(
+                    ipam.update_ip(&args.subnet, &args.ip, &args.allocation)
+                        .await,
+                )
+            }
+            methods::IPAM_GET_IP => {
+                let args: IpArgs = decode(args)?;
+                driver_result(ipam.get_ip(&args.subnet, &args.ip).await)
+            }
+            methods::IPAM_LIST_SUBNET_IPS => {
+                let args: SubnetNameArgs = decode(args)?;
+                driver_result(ipam.list_subnet_ips(&args.subnet).await)
+            }
+            methods::IPAM_VALIDATE_SUBNET => {
+                let args: SubnetArgs = decode(args)?;
+                driver_result(ipam.validate_subnet(&args.subnet).await)
+            }
+            methods::IPAM_ADD_SUBNET => {
+                let args: SubnetArgs = decode(args)?;
+                driver_result(ipam.add_subnet(&args.subnet).await)
+            }
+            methods::IPAM_REMOVE_SUBNET => {
+                let args: SubnetNameArgs = decode(args)?;
+                driver_result(ipam.remove_subnet(&args.subnet).await)
+            }
+            methods::IPAM_GET_NEXT_FREE_IP => {
+                let args: SubnetNameArgs = decode(args)?;
+                driver_result(ipam.get_next_free_ip(&args.subnet).await)
+            }
+            methods::IPAM_IS_IP_AVAILABLE => {
+                let args: IpArgs = decode(args)?;
+                driver_result(ipam.is_ip_available(&args.subnet, &args.ip).await)
+            }
+            _ => Err(method_not_found(method)),
+        }
+    }
+}
+
+/// Zone of the host passed to a controller, known by name and type only
+struct HostZone(ZoneRef);
+
+#[async_trait]
+impl Zone for HostZone {
+    fn zone_type(&self) -> ZoneType {
+        self.0.zone_type.clone()
+    }
+
+    fn name(&self) -> &str {
+        &self.0.zone
+    }
+
+    async fn validate_config(&self, _config: &ZoneConfig) -> Result<()> {
+        Ok(())
+    }
+
+    async fn apply_config(&self, _config: &ZoneConfig) -> Result<()> {
+        anyhow::bail!("Zone '{}' is managed by the host", self.0.zone)
+    }
+
+    async fn generate_config(&self, _config: &ZoneConfig) -> Result<HashMap<String, String>> {
+        Ok(HashMap::new())
+    }
+}
+
+/// Driver instance for `call`, created on first use
+///
+/// Instances live as long as the plugin so drivers can keep state (e.g.
+/// IPAM allocations) between calls.
+fn instance<T: ?Sized, A>(
+    instances: &Instances<T>,
+    factories: &Factories<T>,
+    kind: &str,
+    call: &DriverCall<A>,
+) -> Result<Arc<T>, RpcError> {
+    let mut instances = instances.lock().unwrap();
+    let key = (call.driver.clone(), call.name.clone());
+
+    if let Some(instance) = instances.get(&key) {
+        return Ok(instance.clone());
+    }
+
+    let factory = factories.get(&call.driver).ok_or_else(|| {
+        RpcError::new(
+            METHOD_NOT_FOUND,
+            format!(
+                "{} type '{}' is not provided by this plugin",
+                kind, call.driver
+            ),
+        )
+    })?;
+    let instance: Arc<T> = Arc::from(factory(call.name.clone()));
+    instances.insert(key, instance.clone());
+    Ok(instance)
+}
+
+fn parse_request(line: &str) -> Result<Request, Response> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| Response::failure(None, RpcError::new(PARSE_ERROR, e.to_string())))?;
+    let id = value.get("id").and_then(Value::as_u64);
+
+    serde_json::from_value(value)
+        .map_err(|e| Response::failure(id, RpcError::new(INVALID_REQUEST, e.to_string())))
+}
+
+fn decode<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn encode<T: Serialize>(result: T) -> Result<Value, RpcError> {
+    serde_json::to_value(result).map_err(|e| RpcError::new(DRIVER_ERROR, e.to_string()))
+}
+
+fn driver_result<T: Serialize>(result: Result<T>) -> Result<Value, RpcError> {
+    match result {
+        Ok(result) => encode(result),
+        Err(e) => Err(RpcError::new(DRIVER_ERROR, format!("{:#}", e))),
+    }
+}
+
+fn method_not_found(method: &str) -> RpcError {
+    RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{}'", method))
+}
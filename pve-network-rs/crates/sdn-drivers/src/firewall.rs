@@ -0,0 +1,335 @@
+//! VNet firewall rendering
+//!
+//! Compiles vnet firewall rules into the SDN-owned `inet pve-sdn` table. The
+//! forward chain dispatches on the vnet bridge: traffic routed into a vnet
+//! jumps to `<vnet>-in`, traffic routed out of it to `<vnet>-out`. Both
+//! chains of a packet crossing two vnets are evaluated, so accepting rules
+//! `return` to the forward chain instead of ending evaluation with `accept`.
+
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+
+use pve_sdn_core::vnet::parse_ports;
+use pve_sdn_core::{FirewallAction, FirewallDirection, FirewallRule, VNetConfig, VNetFirewall};
+
+/// Render the firewall chains of `vnets`, `None` if none has an enabled
+/// firewall
+pub fn generate_firewall_nftables(vnets: &[&VNetConfig]) -> Result<Option<String>> {
+    let mut dispatch = String::new();
+    let mut chains = String::new();
+
+    for vnet in vnets {
+        let Some(firewall) = vnet.firewall.as_ref().filter(|fw| fw.enabled()) else {
+            continue;
+        };
+
+        firewall
+            .validate()
+            .with_context(|| format!("Invalid firewall of vnet '{}'", vnet.vnet))?;
+
+        dispatch.push_str(&format!(
+            "\t\toifname \"{name}\" jump {name}-in\n\
+             \t\tiifname \"{name}\" jump {name}-out\n",
+            name = vnet.vnet
+        ));
+
+        for direction in [FirewallDirection::In, FirewallDirection::Out] {
+            chains.push_str(&render_chain(&vnet.vnet, firewall, direction)?);
+        }
+    }
+
+    if dispatch.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(format!(
+        "table inet pve-sdn {{\n\
+         \tchain forward {{\n\
+         \t\ttype filter hook forward priority filter; policy accept;\n\
+         {dispatch}\
+         \t}}\n\
+         {chains}\
+         }}\n",
+    )))
+}
+
+fn render_chain(
+    vnet: &str,
+    firewall: &VNetFirewall,
+    direction: FirewallDirection,
+) -> Result<String> {
+    let mut chain = format!(
+        "\tchain {}-{} {{\n\
+         \t\tct state established,related accept\n\
+         \t\tct state invalid drop\n",
+        vnet, direction
+    );
+
+    for rule in firewall
+        .rules
+        .iter()
+        .filter(|rule| rule.enabled() && rule.direction == direction)
+    {
+        for line in render_rule(firewall, rule)? {
+            chain.push_str(&format!("\t\t{}\n", line));
+        }
+    }
+
+    match firewall.policy(direction) {
+        FirewallAction::Accept => {}
+        action => chain.push_str(&format!("\t\t{}\n", verdict(action))),
+    }
+    chain.push_str("\t}\n");
+
+    Ok(chain)
+}
+
+/// nftables statements of `rule`, one per address family it applies to
+pub fn render_rule(firewall: &VNetFirewall, rule: &FirewallRule) -> Result<Vec<String>> {
+    let source = rule
+        .source
+        .as_deref()
+        .map(|spec| firewall.resolve_addresses(spec))
+        .transpose()?;
+    let dest = rule
+        .dest
+        .as_deref()
+        .map(|spec| firewall.resolve_addresses(spec))
+        .transpose()?;
+
+    let protocol = render_protocol(rule)?;
+    let mut comment = String::new();
+    if let Some(text) = &rule.comment {
+        comment = format!(" comment \"{}\"", text.replace('"', "'"));
+    }
+
+    if source.is_none() && dest.is_none() {
+        return Ok(vec![format!(
+            "{}{}{}",
+            protocol,
+            verdict(rule.action),
+            comment
+        )]);
+    }
+
+    let mut lines = Vec::new();
+    for (family, ipv4) in [("ip", true), ("ip6", false)] {
+        let select = |networks: &Option<Vec<IpNet>>| {
+            networks.as_ref().map(|networks| {
+                networks
+                    .iter()
+                    .filter(|n| n.addr().is_ipv4() == ipv4)
+                    .map(IpNet::to_string)
+                    .collect::<Vec<_>>()
+            })
+        };
+        let (source, dest) = (select(&source), select(&dest));
+
+        // A rule only applies to the families all of its address fields have
+        if [&source, &dest]
+            .into_iter()
+            .flatten()
+            .any(|networks| networks.is_empty())
+        {
+            continue;
+        }
+
+        let mut line = String::new();
+        if let Some(source) = &source {
+            line.push_str(&format!("{} saddr {} ", family, set(source)));
+        }
+        if let Some(dest) = &dest {
+            line.push_str(&format!("{} daddr {} ", family, set(dest)));
+        }
+        line.push_str(&protocol);
+        line.push_str(verdict(rule.action));
+        line.push_str(&comment);
+        lines.push(line);
+    }
+
+    Ok(lines)
+}
+
+/// Protocol and port matches of `rule`, with a trailing space if not empty
+fn render_protocol(rule: &FirewallRule) -> Result<String> {
+    let Some(proto) = rule.protocol() else {
+        return Ok(String::new());
+    };
+
+    if rule.sport.is_none() && rule.dport.is_none() {
+        return Ok(format!("meta l4proto {} ", proto));
+    }
+
+    let mut matches = String::new();
+    for (field, ports) in [("sport", &rule.sport), ("dport", &rule.dport)] {
+        if let Some(ports) = ports {
+            let ports: Vec<String> = parse_ports(ports)?
+                .into_iter()
+                .map(|(first, last)| match first == last {
+                    true => first.to_string(),
+                    false => format!("{}-{}", first, last),
+                })
+                .collect();
+            matches.push_str(&format!("{} {} {} ", proto, field, set(&ports)));
+        }
+    }
+
+    Ok(matches)
+}
+
+fn verdict(action: FirewallAction) -> &'static str {
+    match action {
+        FirewallAction::Accept => "return",
+        FirewallAction::Drop => "drop",
+        FirewallAction::Reject => "reject",
+    }
+}
+
+/// Single element or anonymous set
+fn set(elements: &[String]) -> String {
+    match elements {
+        [element] => element.clone(),
+        elements => format!("{{ {} }}", elements.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pve_sdn_core::{FirewallAlias, FirewallIpSet};
+
+    fn firewall() -> VNetFirewall {
+        VNetFirewall {
+            enable: Some(true),
+            ipsets: vec![FirewallIpSet {
+                name: "admins".to_string(),
+                cidrs: vec![
+                    "192.0.2.0/24".parse().unwrap(),
+                    "2001:db8:1::/64".parse().unwrap(),
+                ],
+                comment: None,
+            }],
+            aliases: vec![FirewallAlias {
+                name: "web".to_string(),
+                cidr: "10.0.0.10/32".parse().unwrap(),
+                comment: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_rule() {
+        let firewall = firewall();
+
+        let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Accept);
+        rule.proto = Some("tcp".to_string());
+        rule.dport = Some("22,8000:8080".to_string());
+        rule.source = Some("+admins".to_string());
+        assert_eq!(
+            render_rule(&firewall, &rule).unwrap(),
+            vec![
+                "ip saddr 192.0.2.0/24 tcp dport { 22, 8000-8080 } return",
+                "ip6 saddr 2001:db8:1::/64 tcp dport { 22, 8000-8080 } return",
+            ]
+        );
+
+        // Families without addresses in every field are skipped
+        rule.dest = Some("web".to_string());
+        rule.comment = Some("ssh \"and\" app".to_string());
+        assert_eq!(
+            render_rule(&firewall, &rule).unwrap(),
+            vec![
+                "ip saddr 192.0.2.0/24 ip daddr 10.0.0.10/32 tcp dport { 22, 8000-8080 } return comment \"ssh 'and' app\""
+            ]
+        );
+
+        let mut rule = FirewallRule::new(FirewallDirection::Out, FirewallAction::Reject);
+        rule.proto = Some("icmpv6".to_string());
+        assert_eq!(
+            render_rule(&firewall, &rule).unwrap(),
+            vec!["meta l4proto ipv6-icmp reject"]
+        );
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let firewall = firewall();
+
+        let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Drop);
+        rule.dport = Some("80".to_string());
+        assert!(firewall.validate_rule(&rule).is_err());
+
+        rule.proto = Some("tcp".to_string());
+        assert!(firewall.validate_rule(&rule).is_ok());
+
+        rule.dport = Some("90:80".to_string());
+        assert!(firewall.validate_rule(&rule).is_err());
+
+        let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Drop);
+        rule.source = Some("+unknown".to_string());
+        assert!(firewall.validate_rule(&rule).is_err());
+
+        rule.source = Some("10.1.0.0/16, web".to_string());
+        assert!(firewall.validate_rule(&rule).is_ok());
+
+        rule.dest = Some("2001:db8::/32".to_string());
+        assert!(firewall.validate_rule(&rule).is_err());
+
+        let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Drop);
+        rule.proto = Some("icmp".to_string());
+        rule.dest = Some("2001:db8::/32".to_string());
+        assert!(firewall.validate_rule(&rule).is_err());
+
+        rule.proto = Some("bogus".to_string());
+        assert!(firewall.validate_rule(&rule).is_err());
+    }
+
+    #[test]
+    fn test_generate_firewall_nftables() {
+        let mut vnet = VNetConfig::new("vnet1".to_string(), "zone1".to_string());
+        let mut firewall = firewall();
+        firewall.policy_in = Some(FirewallAction::Drop);
+
+        let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Accept);
+        rule.proto = Some("tcp".to_string());
+        rule.dport = Some("443".to_string());
+        firewall.rules.push(rule);
+
+        let mut rule = FirewallRule::new(FirewallDirection::Out, FirewallAction::Drop);
+        rule.dest = Some("web".to_string());
+        rule.enable = Some(false);
+        firewall.rules.push(rule);
+
+        vnet.firewall = Some(firewall);
+
+        let unfiltered = VNetConfig::new("vnet2".to_string(), "zone1".to_string());
+        let ruleset = generate_firewall_nftables(&[&vnet, &unfiltered])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            ruleset,
+            "table inet pve-sdn {\n\
+             \tchain forward {\n\
+             \t\ttype filter hook forward priority filter; policy accept;\n\
+             \t\toifname \"vnet1\" jump vnet1-in\n\
+             \t\tiifname \"vnet1\" jump vnet1-out\n\
+             \t}\n\
+             \tchain vnet1-in {\n\
+             \t\tct state established,related accept\n\
+             \t\tct state invalid drop\n\
+             \t\ttcp dport 443 return\n\
+             \t\tdrop\n\
+             \t}\n\
+             \tchain vnet1-out {\n\
+             \t\tct state established,related accept\n\
+             \t\tct state invalid drop\n\
+             \t}\n\
+             }\n"
+        );
+
+        vnet.firewall.as_mut().unwrap().enable = Some(false);
+        assert_eq!(generate_firewall_nftables(&[&vnet]).unwrap(), None);
+    }
+}
@@ -6,6 +6,7 @@ use anyhow::Result;
 use std::sync::Arc;
 
 use super::{NetBoxIpam, PhpIpam, PveIpam};
+use crate::plugin_factory::PluginFactory;
 use pve_sdn_core::{IpamConfig, IpamPlugin, IpamType};
 
 /// IPAM plugin factory
@@ -13,7 +14,13 @@ pub struct IpamPluginFactory;
 
 impl IpamPluginFactory {
     /// Create IPAM plugin from configuration
-    pub fn create_plugin(config: &IpamConfig) -> Result<Arc<dyn IpamPlugin>> {
+    ///
+    /// IPAM types of external plugins are resolved through `factory`, the
+    /// one the plugin was loaded into.
+    pub fn create_plugin(
+        factory: &PluginFactory,
+        config: &IpamConfig,
+    ) -> Result<Arc<dyn IpamPlugin>> {
         match config.ipam_type {
             IpamType::Pve => {
                 let plugin = PveIpam::new(config.name.clone(), config.clone());
@@ -40,12 +47,20 @@ impl IpamPluginFactory {
                 let plugin = NetBoxIpam::new(config.name.clone(), config.clone())?;
                 Ok(Arc::new(plugin))
             }
+            IpamType::External(_) => {
+                // Registered by a loaded plugin, see `PluginFactory::load_external_plugin`
+                let plugin = factory.create_ipam(&config.ipam_type, config.name.clone())?;
+                Ok(Arc::from(plugin))
+            }
         }
     }
 
     /// Create and validate plugin
-    pub async fn create_and_validate_plugin(config: &IpamConfig) -> Result<Arc<dyn IpamPlugin>> {
-        let plugin = Self::create_plugin(config)?;
+    pub async fn create_and_validate_plugin(
+        factory: &PluginFactory,
+        config: &IpamConfig,
+    ) -> Result<Arc<dyn IpamPlugin>> {
+        let plugin = Self::create_plugin(factory, config)?;
 
         // Validate plugin configuration
         plugin.validate_config(config).await?;
@@ -55,6 +70,7 @@ impl IpamPluginFactory {
 
     /// Initialize IPAM manager with plugins from configuration
     pub async fn initialize_manager(
+        factory: &PluginFactory,
         ipam_configs: &std::collections::HashMap<String, IpamConfig>,
         default_plugin: Option<&str>,
     ) -> Result<pve_sdn_core::IpamManager> {
@@ -62,7 +78,7 @@ impl IpamPluginFactory {
 
         // Create and register all plugins
         for (name, config) in ipam_configs {
-            match Self::create_and_validate_plugin(config).await {
+            match Self::create_and_validate_plugin(factory, config).await {
                 Ok(plugin) => {
                     manager.register_plugin(plugin);
                     log::info!(
@@ -215,6 +231,8 @@ pub mod perl_compat {
 
                 Ok(())
             }
+            // Validated by the plugin itself
+            IpamType::External(_) => Ok(()),
         }
     }
 }
@@ -227,7 +245,7 @@ mod tests {
     #[tokio::test]
     async fn test_create_pve_plugin() {
         let config = IpamConfig::new("test-pve".to_string(), IpamType::Pve);
-        let plugin = IpamPluginFactory::create_plugin(&config).unwrap();
+        let plugin = IpamPluginFactory::create_plugin(&PluginFactory::new(), &config).unwrap();
 
         assert_eq!(plugin.name(), "test-pve");
         assert_eq!(plugin.plugin_type(), IpamType::Pve);
@@ -6,6 +6,7 @@ use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -13,6 +14,9 @@ use pve_sdn_core::{
     IpAllocation, IpAllocationRequest, IpamConfig, IpamError, IpamPlugin, IpamType, Subnet,
 };
 
+/// Default directory of the IPAM data in pmxcfs
+pub const DEFAULT_IPAM_STORAGE_PATH: &str = "/etc/pve/sdn/ipam";
+
 /// PVE IPAM storage entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PveIpamEntry {
@@ -44,24 +48,36 @@ pub struct PveIpam {
     // In production, this would be backed by pmxcfs
     allocations: Arc<RwLock<HashMap<String, HashMap<IpAddr, PveIpamEntry>>>>,
     subnets: Arc<RwLock<HashMap<String, PveSubnetInfo>>>,
+    storage_path: PathBuf,
 }
 
 impl PveIpam {
     /// Create new PVE IPAM
+    ///
+    /// Data is stored below `PVE_IPAM_STORAGE_PATH` if set, otherwise in
+    /// pmxcfs.
     pub fn new(name: String, config: IpamConfig) -> Self {
+        let storage_path = std::env::var("PVE_IPAM_STORAGE_PATH")
+            .unwrap_or_else(|_| DEFAULT_IPAM_STORAGE_PATH.to_string());
+
         Self {
             name,
             config,
             allocations: Arc::new(RwLock::new(HashMap::new())),
             subnets: Arc::new(RwLock::new(HashMap::new())),
+            storage_path: PathBuf::from(storage_path),
         }
     }
 
+    /// Store the IPAM data in `path` instead
+    pub fn with_storage_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.storage_path = path.as_ref().to_path_buf();
+        self
+    }
+
     /// Load allocations from storage (pmxcfs in production)
     pub async fn load_from_storage(&self) -> Result<()> {
-        // Use configurable storage path for testing
-        let ipam_dir = std::env::var("PVE_IPAM_STORAGE_PATH")
-            .unwrap_or_else(|_| "/etc/pve/sdn/ipam".to_string());
+        let ipam_dir = self.storage_path.display();
         let storage_path = format!("{}/{}.json", ipam_dir, self.name);
 
         match tokio::fs::read_to_string(&storage_path).await {
@@ -118,11 +134,9 @@ impl PveIpam {
 
     /// Save allocations to storage (pmxcfs in production)
     async fn save_to_storage(&self) -> Result<()> {
-        // Use configurable storage path for testing
-        let ipam_dir = std::env::var("PVE_IPAM_STORAGE_PATH")
-            .unwrap_or_else(|_| "/etc/pve/sdn/ipam".to_string());
+        let ipam_dir = self.storage_path.display();
 
-        if let Err(e) = tokio::fs::create_dir_all(&ipam_dir).await {
+        if let Err(e) = tokio::fs::create_dir_all(&self.storage_path).await {
             if e.kind() != std::io::ErrorKind::AlreadyExists {
                 return Err(e.into());
             }
@@ -489,6 +489,7 @@ async fn test_ipam_manager_perl_style_operations() {
 #[tokio::test]
 async fn test_ipam_factory_initialization() {
     use super::factory::IpamPluginFactory;
+    use crate::PluginFactory;
 
     let mut ipam_configs = std::collections::HashMap::new();
 
@@ -497,9 +498,10 @@ async fn test_ipam_factory_initialization() {
     ipam_configs.insert("pve".to_string(), pve_config);
 
     // Initialize manager
-    let manager = IpamPluginFactory::initialize_manager(&ipam_configs, Some("pve"))
-        .await
-        .unwrap();
+    let manager =
+        IpamPluginFactory::initialize_manager(&PluginFactory::new(), &ipam_configs, Some("pve"))
+            .await
+            .unwrap();
 
     // Test that manager is properly initialized
     let plugins = manager.list_plugins();
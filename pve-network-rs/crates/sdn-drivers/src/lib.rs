@@ -2,7 +2,12 @@
 //!
 //! SDN driver implementations
 
+pub mod apply;
 pub mod controllers;
+pub mod dhcp;
+pub mod dns;
+pub mod external;
+pub mod firewall;
 pub mod ipam;
 pub mod plugin_factory;
 pub mod zones;
@@ -10,7 +15,14 @@ pub mod zones;
 #[cfg(test)]
 mod tests;
 
+pub use apply::{GeneratedSdnConfig, SdnApplyPipeline, SdnApplyResult, SdnReloader};
 pub use controllers::*;
+pub use dhcp::{DhcpApplyResult, DhcpService, DnsmasqDhcp};
+pub use dns::PowerDns;
+pub use external::{ExternalPlugin, PluginServer};
+pub use firewall::generate_firewall_nftables;
 pub use ipam::*;
-pub use plugin_factory::{get_plugin_factory, init_plugin_factory, PluginFactory};
+pub use plugin_factory::{
+    get_plugin_factory, init_plugin_factory, PluginFactory, DEFAULT_PLUGIN_DIR,
+};
 pub use zones::*;
@@ -1,18 +1,30 @@
 //! Plugin factory for dynamic loading of SDN drivers
 //!
 //! This module provides a factory system for dynamically loading and creating
-//! SDN zone drivers, IPAM plugins, and controllers.
+//! SDN zone drivers, IPAM and DNS plugins, and controllers.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use log::{debug, info, warn};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 
-use pve_sdn_core::{Controller, ControllerType, IpamPlugin, IpamType, Zone, ZoneType};
+use pve_sdn_core::{
+    Controller, ControllerType, DnsConfig, DnsPlugin, DnsType, DriverRegistry, IpamPlugin,
+    IpamType, RealExecutor, SdnConfiguration, SdnReconciler, SdnStatusCollector, SystemExecutor,
+    Zone, ZoneResolver, ZoneType,
+};
 
-use crate::controllers::{BgpController, EvpnController, FaucetController};
+use crate::controllers::{BgpController, EvpnController, FaucetController, IsisController};
+use crate::dns::PowerDns;
+use crate::external::ExternalPlugin;
 use crate::ipam::{NetBoxIpam, PhpIpam, PveIpam};
-use crate::zones::{EvpnZone, QinQZone, SimpleZone, VlanZone, VxlanZone};
+use crate::zones::{
+    EvpnZone, GeneveZone, QinQZone, SimpleZone, VlanZone, VxlanZone, WireguardZone,
+};
+
+/// Directory external plugins are loaded from at startup
+pub const DEFAULT_PLUGIN_DIR: &str = "/usr/lib/pve-network/sdn-plugins";
 
 /// Zone factory function type
 pub type ZoneFactory = Box<dyn Fn(String) -> Box<dyn Zone> + Send + Sync>;
@@ -23,20 +35,36 @@ pub type ControllerFactory = Box<dyn Fn(String) -> Box<dyn Controller> + Send +
 /// IPAM factory function type
 pub type IpamFactory = Box<dyn Fn(String) -> Box<dyn IpamPlugin> + Send + Sync>;
 
+/// DNS factory function type
+///
+/// DNS plugins cannot do anything without their server settings, so they
+/// are created from the full configuration.
+pub type DnsFactory = Box<dyn Fn(&DnsConfig) -> Result<Box<dyn DnsPlugin>> + Send + Sync>;
+
 /// Plugin factory for creating SDN components
 pub struct PluginFactory {
     zone_factories: Arc<RwLock<HashMap<ZoneType, ZoneFactory>>>,
     controller_factories: Arc<RwLock<HashMap<ControllerType, ControllerFactory>>>,
     ipam_factories: Arc<RwLock<HashMap<IpamType, IpamFactory>>>,
+    dns_factories: Arc<RwLock<HashMap<DnsType, DnsFactory>>>,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl PluginFactory {
     /// Create new plugin factory with default drivers
     pub fn new() -> Self {
+        Self::with_executor(Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new plugin factory whose default drivers run system commands
+    /// through `executor`
+    pub fn with_executor(executor: Arc<dyn SystemExecutor>) -> Self {
         let factory = Self {
             zone_factories: Arc::new(RwLock::new(HashMap::new())),
             controller_factories: Arc::new(RwLock::new(HashMap::new())),
             ipam_factories: Arc::new(RwLock::new(HashMap::new())),
+            dns_factories: Arc::new(RwLock::new(HashMap::new())),
+            executor,
         };
 
         factory.register_default_drivers();
@@ -46,46 +74,61 @@ impl PluginFactory {
     /// Register default built-in drivers
     fn register_default_drivers(&self) {
         // Register zone drivers
-        self.register_zone_driver(
-            ZoneType::Simple,
-            Box::new(|name| Box::new(SimpleZone::new(name))),
-        );
+        self.register_zone_driver(ZoneType::Simple, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(SimpleZone::with_executor(name, executor.clone())))
+        });
 
         self.register_zone_driver(
             ZoneType::Vlan,
             Box::new(|name| Box::new(VlanZone::new(name))),
         );
 
-        self.register_zone_driver(
-            ZoneType::QinQ,
-            Box::new(|name| Box::new(QinQZone::new(name))),
-        );
+        self.register_zone_driver(ZoneType::QinQ, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(QinQZone::with_executor(name, executor.clone())))
+        });
 
-        self.register_zone_driver(
-            ZoneType::Vxlan,
-            Box::new(|name| Box::new(VxlanZone::new(name))),
-        );
+        self.register_zone_driver(ZoneType::Vxlan, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(VxlanZone::with_executor(name, executor.clone())))
+        });
 
-        self.register_zone_driver(
-            ZoneType::Evpn,
-            Box::new(|name| Box::new(EvpnZone::new(name))),
-        );
+        self.register_zone_driver(ZoneType::Evpn, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(EvpnZone::with_executor(name, executor.clone())))
+        });
 
-        // Register controller drivers
-        self.register_controller_driver(
-            ControllerType::Bgp,
-            Box::new(|name| Box::new(BgpController::new(name))),
-        );
+        self.register_zone_driver(ZoneType::Geneve, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(GeneveZone::with_executor(name, executor.clone())))
+        });
 
-        self.register_controller_driver(
-            ControllerType::Evpn,
-            Box::new(|name| Box::new(EvpnController::new(name))),
-        );
+        self.register_zone_driver(ZoneType::Wireguard, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(WireguardZone::with_executor(name, executor.clone())))
+        });
 
-        self.register_controller_driver(
-            ControllerType::Faucet,
-            Box::new(|name| Box::new(FaucetController::new(name))),
-        );
+        // Register controller drivers
+        self.register_controller_driver(ControllerType::Bgp, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(BgpController::with_executor(name, executor.clone())))
+        });
+
+        self.register_controller_driver(ControllerType::Evpn, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(EvpnController::with_executor(name, executor.clone())))
+        });
+
+        self.register_controller_driver(ControllerType::Faucet, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(FaucetController::with_executor(name, executor.clone())))
+        });
+
+        self.register_controller_driver(ControllerType::Isis, {
+            let executor = self.executor.clone();
+            Box::new(move |name| Box::new(IsisController::with_executor(name, executor.clone())))
+        });
 
         // Register IPAM drivers
         self.register_ipam_driver(
@@ -134,9 +177,43 @@ impl PluginFactory {
             }),
         );
 
+        // Register DNS drivers
+        self.register_dns_driver(
+            DnsType::PowerDns,
+            Box::new(|config| {
+                Ok(Box::new(PowerDns::new(
+                    config.name.clone(),
+                    config.clone(),
+                )?))
+            }),
+        );
+
         info!("Registered default SDN drivers");
     }
 
+    /// Executor used by the default drivers
+    pub fn executor(&self) -> Arc<dyn SystemExecutor> {
+        self.executor.clone()
+    }
+
+    /// Create a reconciler for `node` instantiating zones through this
+    /// factory
+    pub fn reconciler(self: Arc<Self>, node: &str) -> SdnReconciler {
+        let executor = self.executor.clone();
+        let resolver: ZoneResolver =
+            Arc::new(move |config| self.create_zone(&config.zone_type, config.zone.clone()));
+        SdnReconciler::new(executor, resolver).with_node(node)
+    }
+
+    /// Create a status collector for `node` instantiating zones through
+    /// this factory
+    pub fn status_collector(self: Arc<Self>, node: &str) -> SdnStatusCollector {
+        let executor = self.executor.clone();
+        let resolver: ZoneResolver =
+            Arc::new(move |config| self.create_zone(&config.zone_type, config.zone.clone()));
+        SdnStatusCollector::new(executor, resolver, node)
+    }
+
     /// Register a zone driver
     pub fn register_zone_driver(&self, zone_type: ZoneType, factory: ZoneFactory) {
         let mut factories = self.zone_factories.write().unwrap();
@@ -162,6 +239,13 @@ impl PluginFactory {
         debug!("Registered IPAM driver: {}", ipam_type);
     }
 
+    /// Register a DNS driver
+    pub fn register_dns_driver(&self, dns_type: DnsType, factory: DnsFactory) {
+        let mut factories = self.dns_factories.write().unwrap();
+        factories.insert(dns_type.clone(), factory);
+        debug!("Registered DNS driver: {}", dns_type);
+    }
+
     /// Create a zone instance
     pub fn create_zone(&self, zone_type: &ZoneType, name: String) -> Result<Box<dyn Zone>> {
         let factories = self.zone_factories.read().unwrap();
@@ -211,6 +295,35 @@ impl PluginFactory {
         }
     }
 
+    /// Create a DNS plugin instance
+    pub fn create_dns(&self, config: &DnsConfig) -> Result<Box<dyn DnsPlugin>> {
+        let factories = self.dns_factories.read().unwrap();
+
+        if let Some(factory) = factories.get(&config.dns_type) {
+            let dns = factory(config)?;
+            debug!(
+                "Created DNS '{}' of type '{}'",
+                config.name, config.dns_type
+            );
+            Ok(dns)
+        } else {
+            anyhow::bail!("No factory registered for DNS type '{}'", config.dns_type);
+        }
+    }
+
+    /// Create the DNS plugins configured in `config`
+    pub fn create_dns_plugins(&self, config: &SdnConfiguration) -> Result<Vec<Arc<dyn DnsPlugin>>> {
+        config
+            .dns
+            .values()
+            .map(|dns| {
+                self.create_dns(dns)
+                    .map(Arc::from)
+                    .with_context(|| format!("Failed to create DNS plugin '{}'", dns.name))
+            })
+            .collect()
+    }
+
     /// Get list of available zone types
     pub fn available_zone_types(&self) -> Vec<ZoneType> {
         let factories = self.zone_factories.read().unwrap();
@@ -229,29 +342,57 @@ impl PluginFactory {
         factories.keys().cloned().collect()
     }
 
-    /// Load external plugin (placeholder for future dynamic loading)
-    pub fn load_external_plugin(&self, plugin_path: &str) -> Result<()> {
-        // This is a placeholder for future dynamic plugin loading
-        // In a real implementation, this would use libloading or similar
-        // to dynamically load shared libraries containing plugin implementations
-
-        warn!(
-            "External plugin loading not yet implemented: {}",
-            plugin_path
-        );
+    /// Get list of available DNS types
+    pub fn available_dns_types(&self) -> Vec<DnsType> {
+        let factories = self.dns_factories.read().unwrap();
+        factories.keys().cloned().collect()
+    }
 
-        // For now, just validate that the path exists
-        if !std::path::Path::new(plugin_path).exists() {
+    /// Start the external plugin executable at `plugin_path` and register
+    /// the drivers it provides
+    ///
+    /// The plugin keeps running as long as drivers created from it are
+    /// registered, see [`crate::external`].
+    pub async fn load_external_plugin(&self, plugin_path: &str) -> Result<ExternalPlugin> {
+        let path = std::path::Path::new(plugin_path);
+        if !path.exists() {
             anyhow::bail!("Plugin file does not exist: {}", plugin_path);
         }
 
-        // Future implementation would:
-        // 1. Load the shared library
-        // 2. Look for plugin registration functions
-        // 3. Call the registration functions to register new drivers
-        // 4. Handle plugin lifecycle (unloading, etc.)
+        let plugin = ExternalPlugin::spawn(path).await?;
+        plugin.register(self)?;
+
+        Ok(plugin)
+    }
+
+    /// Load every plugin executable in `dir`
+    ///
+    /// A missing directory means no plugins are installed. Plugins failing
+    /// to start are logged and skipped, the built-in drivers keep working.
+    pub async fn load_plugin_dir(&self, dir: &Path) -> Result<Vec<ExternalPlugin>> {
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+
+        let mut plugins = Vec::new();
+        for path in paths {
+            match self.load_external_plugin(&path.to_string_lossy()).await {
+                Ok(plugin) => plugins.push(plugin),
+                Err(e) => warn!("Failed to load SDN plugin {}: {:#}", path.display(), e),
+            }
+        }
 
-        Ok(())
+        Ok(plugins)
     }
 
     /// Unregister a zone driver
@@ -293,6 +434,18 @@ impl PluginFactory {
         }
     }
 
+    /// Unregister a DNS driver
+    pub fn unregister_dns_driver(&self, dns_type: &DnsType) -> Result<()> {
+        let mut factories = self.dns_factories.write().unwrap();
+
+        if factories.remove(dns_type).is_some() {
+            debug!("Unregistered DNS driver: {}", dns_type);
+            Ok(())
+        } else {
+            anyhow::bail!("No DNS driver registered for type '{}'", dns_type);
+        }
+    }
+
     /// Check if a zone driver is registered
     pub fn has_zone_driver(&self, zone_type: &ZoneType) -> bool {
         let factories = self.zone_factories.read().unwrap();
@@ -310,6 +463,26 @@ impl PluginFactory {
         let factories = self.ipam_factories.read().unwrap();
         factories.contains_key(ipam_type)
     }
+
+    /// Check if a DNS driver is registered
+    pub fn has_dns_driver(&self, dns_type: &DnsType) -> bool {
+        let factories = self.dns_factories.read().unwrap();
+        factories.contains_key(dns_type)
+    }
+}
+
+impl DriverRegistry for PluginFactory {
+    fn has_zone_driver(&self, zone_type: &ZoneType) -> bool {
+        PluginFactory::has_zone_driver(self, zone_type)
+    }
+
+    fn has_controller_driver(&self, controller_type: &ControllerType) -> bool {
+        PluginFactory::has_controller_driver(self, controller_type)
+    }
+
+    fn has_ipam_driver(&self, ipam_type: &IpamType) -> bool {
+        PluginFactory::has_ipam_driver(self, ipam_type)
+    }
 }
 
 impl Default for PluginFactory {
@@ -379,16 +552,22 @@ mod tests {
         assert!(zone_types.contains(&ZoneType::QinQ));
         assert!(zone_types.contains(&ZoneType::Vxlan));
         assert!(zone_types.contains(&ZoneType::Evpn));
+        assert!(zone_types.contains(&ZoneType::Geneve));
+        assert!(zone_types.contains(&ZoneType::Wireguard));
 
         let controller_types = factory.available_controller_types();
         assert!(controller_types.contains(&ControllerType::Bgp));
         assert!(controller_types.contains(&ControllerType::Evpn));
         assert!(controller_types.contains(&ControllerType::Faucet));
+        assert!(controller_types.contains(&ControllerType::Isis));
 
         let ipam_types = factory.available_ipam_types();
         assert!(ipam_types.contains(&IpamType::Pve));
         assert!(ipam_types.contains(&IpamType::PhpIpam));
         assert!(ipam_types.contains(&IpamType::NetBox));
+
+        let dns_types = factory.available_dns_types();
+        assert!(dns_types.contains(&DnsType::PowerDns));
     }
 
     #[test]
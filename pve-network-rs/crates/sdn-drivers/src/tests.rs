@@ -1,7 +1,18 @@
 //! SDN Drivers tests
 
-use crate::zones::{SimpleZone, VlanZone};
-use pve_sdn_core::{Zone, ZoneConfig, ZoneType};
+use crate::apply::{GeneratedSdnConfig, SdnApplyPipeline};
+use crate::plugin_factory::PluginFactory;
+use crate::zones::wireguard::{NodeWireguardKeys, WireguardPeer};
+use crate::zones::{GeneveZone, SimpleZone, VlanZone, VxlanZone, WireguardKeyStore, WireguardZone};
+use pve_sdn_core::controller::ControllerConfig;
+use pve_sdn_core::reconciler::owner_alias;
+use pve_sdn_core::{
+    CommandOutput, ControllerType, DeploymentStatus, DryRunExecutor, ReconcileAction,
+    RecordingExecutor, SdnConfiguration, SubnetConfig, VNetConfig, Zone, ZoneConfig,
+    ZoneRenderContext, ZoneStatus, ZoneType,
+};
+use serde_json::json;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_simple_zone_driver() {
@@ -86,3 +97,1740 @@ async fn test_zone_config_generation() {
     assert!(vlan_bridge_config.contains("auto vmbr1"));
     assert!(vlan_bridge_config.contains("bridge_vlan_aware yes"));
 }
+
+fn vxlan_zone_config() -> ZoneConfig {
+    let mut config = ZoneConfig::new(ZoneType::Vxlan, "vxlan1".to_string());
+    config.bridge = Some("vxbr1".to_string());
+    config.peers = Some(vec!["10.0.0.2".to_string(), "10.0.0.3".to_string()]);
+    config.options.insert("vni".to_string(), json!(100));
+    config
+}
+
+#[tokio::test]
+async fn test_vxlan_zone_command_sequence() {
+    let executor = Arc::new(RecordingExecutor::new());
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+
+    let config = vxlan_zone_config();
+    let zone = VxlanZone::with_executor("vxlan1".to_string(), executor.clone());
+    zone.apply_config(&config).await.unwrap();
+
+    let alias = owner_alias(&config);
+    executor.assert_commands(&[
+        "ip link show vxlan100",
+        "ip -j addr show",
+        "ip link add vxlan100 type vxlan id 100 dstport 4789",
+        &format!("ip link set dev vxlan100 alias {}", alias),
+        "ip link set vxlan100 mtu 1450",
+        "ip link set vxlan100 up",
+        "bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.0.0.2",
+        "bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.0.0.3",
+        "ip link show vxbr1",
+        "ip link add name vxbr1 type bridge",
+        &format!("ip link set dev vxbr1 alias {}", alias),
+        "ip link set vxlan100 master vxbr1",
+        "ip link set vxbr1 up",
+    ]);
+
+    // Existing devices are left alone
+    executor.clear();
+    executor.respond("ip link show", CommandOutput::success(""));
+    zone.apply_config(&vxlan_zone_config()).await.unwrap();
+    executor.assert_commands(&["ip link show vxlan100", "ip link show vxbr1"]);
+}
+
+#[tokio::test]
+async fn test_vxlan_zone_head_end_replication() {
+    let executor = Arc::new(RecordingExecutor::new());
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+    executor.respond(
+        "ip -j addr show",
+        CommandOutput::success(
+            r#"[{"ifname":"lo","addr_info":[{"family":"inet","local":"127.0.0.1"}]},
+                {"ifname":"eno1","addr_info":[{"family":"inet","local":"10.0.0.2","prefixlen":24}]}]"#,
+        ),
+    );
+
+    // The peer list covers every node, including this one
+    let mut config = vxlan_zone_config();
+    config.bridge = None;
+    config.peers = Some(vec![
+        "10.0.0.1".to_string(),
+        "10.0.0.2".to_string(),
+        "10.0.0.3".to_string(),
+    ]);
+
+    let zone = VxlanZone::with_executor("vxlan1".to_string(), executor.clone());
+    zone.apply_config(&config).await.unwrap();
+
+    let lines = executor.command_lines();
+    assert!(lines.contains(
+        &"ip link add vxlan100 type vxlan id 100 dstport 4789 local 10.0.0.2".to_string()
+    ));
+    let fdb: Vec<&String> = lines
+        .iter()
+        .filter(|l| l.starts_with("bridge fdb"))
+        .collect();
+    assert_eq!(
+        fdb,
+        vec![
+            "bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.0.0.1",
+            "bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.0.0.3",
+        ]
+    );
+
+    // The generated config skips local-ip
+    config
+        .options
+        .insert("local-ip".to_string(), json!("10.0.0.2"));
+    let generated = zone.generate_config(&config).await.unwrap();
+    assert!(generated["vxlan"].contains("\tvxlan-local-tunnelip 10.0.0.2\n"));
+    assert!(generated["vxlan"].contains("\tvxlan-remoteip 10.0.0.1\n\tvxlan-remoteip 10.0.0.3\n"));
+    assert!(!generated["vxlan"].contains("vxlan-remoteip 10.0.0.2"));
+}
+
+#[tokio::test]
+async fn test_vxlan_zone_ipv6_underlay() {
+    let zone = VxlanZone::new("vxlan1".to_string());
+
+    let mut config = vxlan_zone_config();
+    config.peers = Some(vec!["fd00::1".to_string(), "fd00::2".to_string()]);
+    config
+        .options
+        .insert("local-ip".to_string(), json!("fd00::1"));
+    zone.validate_config(&config).await.unwrap();
+
+    // 70 bytes of overhead over IPv6
+    let generated = zone.generate_config(&config).await.unwrap();
+    assert!(generated["vxlan"].contains("\tvxlan-local-tunnelip fd00::1\n"));
+    assert!(generated["vxlan"].contains("\tvxlan-remoteip fd00::2\n"));
+    assert!(generated["vxlan"].contains("\tmtu 1430\n"));
+    assert!(generated["bridge"].contains("\tmtu 1430\n"));
+
+    // An explicit MTU wins
+    config.mtu = Some(9000);
+    let generated = zone.generate_config(&config).await.unwrap();
+    assert!(generated["vxlan"].contains("\tmtu 9000\n"));
+
+    // IPv6 multicast groups are accepted
+    config.peers = None;
+    config
+        .options
+        .insert("multicast-group".to_string(), json!("ff05::100"));
+    zone.validate_config(&config).await.unwrap();
+
+    // Mixed address families are not
+    config.options.remove("multicast-group");
+    config.peers = Some(vec!["10.0.0.2".to_string()]);
+    assert!(zone.validate_config(&config).await.is_err());
+}
+
+#[tokio::test]
+async fn test_geneve_zone_command_sequence() {
+    let executor = Arc::new(RecordingExecutor::new());
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+    executor.respond("ip -j -d link show", CommandOutput::success("[]"));
+    executor.respond(
+        "ip -j -d link show dev",
+        CommandOutput::failure(1, "does not exist"),
+    );
+    executor.respond(
+        "ip -j addr show",
+        CommandOutput::success(
+            r#"[{"ifname":"eno1","addr_info":[{"family":"inet6","local":"fd00::2","prefixlen":64}]}]"#,
+        ),
+    );
+
+    // One tunnel per remote peer, over IPv6 with room for OVN's option
+    let mut config = ZoneConfig::new(ZoneType::Geneve, "geneve1".to_string());
+    config.bridge = Some("gnvbr1".to_string());
+    config.peers = Some(vec![
+        "fd00::1".to_string(),
+        "fd00::2".to_string(),
+        "fd00::3".to_string(),
+    ]);
+    config.options.insert("vni".to_string(), json!(200));
+    config.options.insert("tlv-length".to_string(), json!(8));
+
+    let factory = PluginFactory::with_executor(executor.clone());
+    let zone = factory
+        .create_zone(&ZoneType::Geneve, "geneve1".to_string())
+        .unwrap();
+    zone.apply_config(&config).await.unwrap();
+
+    let alias = owner_alias(&config);
+    executor.assert_commands(&[
+        "ip link show gnvbr1",
+        "ip link add name gnvbr1 type bridge",
+        &format!("ip link set dev gnvbr1 alias {}", alias),
+        "ip link set gnvbr1 up",
+        "ip -j addr show",
+        "ip -j -d link show dev gnv13a8878dd0da",
+        "ip link add gnv13a8878dd0da type geneve id 200 remote fd00::1 dstport 6081",
+        &format!("ip link set dev gnv13a8878dd0da alias {}", alias),
+        "ip link set gnv13a8878dd0da mtu 1422",
+        "ip link set gnv13a8878dd0da master gnvbr1",
+        "bridge link set dev gnv13a8878dd0da isolated on",
+        "ip link set gnv13a8878dd0da up",
+        "ip -j -d link show dev gnv15a8878dd460",
+        "ip link add gnv15a8878dd460 type geneve id 200 remote fd00::3 dstport 6081",
+        &format!("ip link set dev gnv15a8878dd460 alias {}", alias),
+        "ip link set gnv15a8878dd460 mtu 1422",
+        "ip link set gnv15a8878dd460 master gnvbr1",
+        "bridge link set dev gnv15a8878dd460 isolated on",
+        "ip link set gnv15a8878dd460 up",
+        "ip -j -d link show",
+    ]);
+
+    // The tunnel to fd00::1 is kept although fd00::3 went away and a new
+    // peer came in front of it
+    config.peers = Some(vec![
+        "fd00::".to_string(),
+        "fd00::1".to_string(),
+        "fd00::2".to_string(),
+    ]);
+    let alias = owner_alias(&config);
+    let geneve_link = |name: &str, remote: &str, alias: &str| {
+        json!({
+            "ifname": name,
+            "flags": ["UP"],
+            "ifalias": alias,
+            "linkinfo": {"info_kind": "geneve", "info_data": {"id": 200, "remote6": remote}},
+        })
+    };
+    let old_alias = format!("pve-sdn:geneve1:{}", "0".repeat(16));
+    executor.clear();
+    executor.respond("ip link show", CommandOutput::success(""));
+    executor.respond(
+        "ip -j -d link show",
+        CommandOutput::success(
+            &json!([
+                geneve_link("gnv13a8878dd0da", "fd00::1", &old_alias),
+                geneve_link("gnv15a8878dd460", "fd00::3", &old_alias),
+                geneve_link("gnvforeign", "fd00::9", "uplink"),
+            ])
+            .to_string(),
+        ),
+    );
+    executor.respond(
+        "ip -j -d link show dev gnv13a8878dd0da",
+        CommandOutput::success(
+            &json!([geneve_link("gnv13a8878dd0da", "fd00::1", &old_alias)]).to_string(),
+        ),
+    );
+    // Same name, but leading elsewhere
+    executor.respond(
+        "ip -j -d link show dev gnv887bf93f2325",
+        CommandOutput::success(
+            &json!([geneve_link("gnv887bf93f2325", "fd00::7", &old_alias)]).to_string(),
+        ),
+    );
+
+    let zone = GeneveZone::with_executor("geneve1".to_string(), executor.clone());
+    zone.apply_config(&config).await.unwrap();
+    executor.assert_commands(&[
+        "ip link show gnvbr1",
+        "ip -j addr show",
+        "ip -j -d link show dev gnv887bf93f2325",
+        "ip link delete dev gnv887bf93f2325",
+        "ip link add gnv887bf93f2325 type geneve id 200 remote fd00:: dstport 6081",
+        &format!("ip link set dev gnv887bf93f2325 alias {}", alias),
+        "ip link set gnv887bf93f2325 mtu 1422",
+        "ip link set gnv887bf93f2325 master gnvbr1",
+        "bridge link set dev gnv887bf93f2325 isolated on",
+        "ip link set gnv887bf93f2325 up",
+        "ip -j -d link show dev gnv13a8878dd0da",
+        "ip -j -d link show",
+        "ip link delete dev gnv15a8878dd460",
+    ]);
+}
+
+#[tokio::test]
+async fn test_geneve_zone_through_sdn_api_types() {
+    // "geneve" parses to the built-in type, not to a plugin type
+    let config: ZoneConfig = serde_json::from_value(json!({
+        "type": "geneve",
+        "zone": "geneve1",
+        "bridge": "gnvbr1",
+        "peers": ["10.0.0.1", "10.0.0.2"],
+        "vni": 300,
+    }))
+    .unwrap();
+    assert_eq!(config.zone_type, ZoneType::Geneve);
+    assert_eq!(config.zone_type.to_string(), "geneve");
+
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_zone(config.clone()).unwrap();
+
+    let zone = PluginFactory::new()
+        .create_zone(&config.zone_type, config.zone.clone())
+        .unwrap();
+    zone.validate_config(&config).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_vxlan_zone_command_failure() {
+    let executor = Arc::new(RecordingExecutor::new());
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+    executor.respond(
+        "ip link add vxlan100",
+        CommandOutput::failure(2, "RTNETLINK answers: Operation not permitted"),
+    );
+
+    let zone = VxlanZone::with_executor("vxlan1".to_string(), executor.clone());
+    let err = zone.apply_config(&vxlan_zone_config()).await.unwrap_err();
+    assert!(err.to_string().contains("Operation not permitted"));
+}
+
+#[tokio::test]
+async fn test_dry_run_through_plugin_factory() {
+    let executor = Arc::new(DryRunExecutor::new());
+    let factory = PluginFactory::with_executor(executor.clone());
+
+    let zone = factory
+        .create_zone(&ZoneType::Vxlan, "vxlan1".to_string())
+        .unwrap();
+    zone.apply_config(&vxlan_zone_config()).await.unwrap();
+
+    let plan: Vec<String> = executor.take_plan().iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        plan.first().unwrap(),
+        "ip link add vxlan100 type vxlan id 100 dstport 4789"
+    );
+    assert!(plan.contains(&"ip link add name vxbr1 type bridge".to_string()));
+
+    let mut config = ControllerConfig::new(ControllerType::Bgp, "bgp1".to_string());
+    config.asn = Some(65000);
+    let controller = factory
+        .create_controller(&ControllerType::Bgp, "bgp1".to_string())
+        .unwrap();
+
+    // Nothing is inspected in a bare dry run, so FRR looks uninstalled
+    assert!(controller.start().await.is_err());
+    assert!(executor.plan().is_empty());
+    assert!(controller.validate_configuration(&config).await.is_ok());
+}
+
+fn link_json(name: &str, kind: &str, alias: Option<&str>) -> String {
+    serde_json::json!([{
+        "ifname": name,
+        "flags": ["BROADCAST", "MULTICAST", "UP", "LOWER_UP"],
+        "mtu": 1500,
+        "ifalias": alias,
+        "linkinfo": { "info_kind": kind },
+    }])
+    .to_string()
+}
+
+#[tokio::test]
+async fn test_vxlan_zone_remove_only_owned_devices() {
+    let config = vxlan_zone_config();
+    let alias = owner_alias(&config);
+
+    let executor = Arc::new(RecordingExecutor::new());
+    executor.respond(
+        "ip -j -d link show dev vxlan100",
+        CommandOutput::success(&link_json("vxlan100", "vxlan", Some(&alias))),
+    );
+    // The bridge already existed and was never claimed by the zone
+    executor.respond(
+        "ip -j -d link show dev vxbr1",
+        CommandOutput::success(&link_json("vxbr1", "bridge", None)),
+    );
+
+    let zone = VxlanZone::with_executor("vxlan1".to_string(), executor.clone());
+    assert_eq!(zone.status(&config).await.unwrap(), ZoneStatus::Available);
+
+    executor.clear();
+    zone.remove_config(&config).await.unwrap();
+    executor.assert_commands(&[
+        "ip -j -d link show dev vxlan100",
+        "ip -j -d link show dev vxbr1",
+        "ip link delete dev vxlan100",
+    ]);
+}
+
+#[tokio::test]
+async fn test_reconciler_garbage_collects_deleted_zone() {
+    let executor = Arc::new(RecordingExecutor::new());
+    let factory = Arc::new(PluginFactory::with_executor(executor.clone()));
+
+    let mut removed = vxlan_zone_config();
+    removed.zone = "old".to_string();
+    let links = serde_json::json!([
+        { "ifname": "eth0", "flags": ["UP"], "linkinfo": null },
+        { "ifname": "vmbr0", "flags": ["UP"], "linkinfo": { "info_kind": "bridge" } },
+        { "ifname": "vxlan100", "flags": ["UP"], "ifalias": owner_alias(&removed),
+          "linkinfo": { "info_kind": "vxlan" } },
+    ]);
+    executor.respond(
+        "ip -j -d link show",
+        CommandOutput::success(&links.to_string()),
+    );
+    executor.respond(
+        "ip -j -d link show dev",
+        CommandOutput::failure(1, "not found"),
+    );
+    executor.respond("ip link show", CommandOutput::failure(1, "not found"));
+
+    let mut desired = SdnConfiguration::new();
+    let mut zone = vxlan_zone_config();
+    zone.options.insert("vni".to_string(), json!(200));
+    desired.zones.insert("vxlan1".to_string(), zone);
+
+    let reconciler = factory.reconciler("node1");
+    let plan = reconciler.plan(&desired).await.unwrap();
+    // vxlan200 is created by ifupdown2 from the generated stanza
+    assert_eq!(
+        plan,
+        vec![ReconcileAction::Remove {
+            zone: "old".to_string(),
+            device: "vxlan100".to_string()
+        }]
+    );
+
+    executor.clear();
+    let report = reconciler.reconcile(&desired).await.unwrap();
+    assert!(report.failed.is_empty());
+
+    let commands = executor.command_lines();
+    assert!(!commands.iter().any(|c| c.starts_with("ip link add")));
+    assert!(commands.contains(&"ip link delete dev vxlan100".to_string()));
+    assert!(!commands
+        .iter()
+        .any(|c| c.contains("delete") && (c.contains("eth0") || c.contains("vmbr0"))));
+}
+
+#[tokio::test]
+async fn test_reconciler_handles_ifupdown_created_devices() {
+    let config = vxlan_zone_config();
+    let alias = owner_alias(&config);
+
+    // ifupdown2 sets the alias from the generated stanzas
+    let zone = VxlanZone::new("vxlan1".to_string());
+    let generated = zone.generate_config(&config).await.unwrap();
+    assert!(generated["vxlan"].contains(&format!("\talias {}\n", alias)));
+    assert!(generated["bridge"].contains(&format!("\talias {}\n", alias)));
+
+    let executor = Arc::new(RecordingExecutor::new());
+    let links = serde_json::json!([
+        { "ifname": "eth0", "flags": ["UP"], "linkinfo": null },
+        { "ifname": "vxlan100", "flags": ["UP"], "ifalias": alias,
+          "linkinfo": { "info_kind": "vxlan" } },
+        { "ifname": "vxbr1", "flags": ["UP"], "ifalias": alias,
+          "linkinfo": { "info_kind": "bridge" } },
+    ]);
+    executor.respond(
+        "ip -j -d link show",
+        CommandOutput::success(&links.to_string()),
+    );
+    let reconciler = Arc::new(PluginFactory::with_executor(executor.clone())).reconciler("node1");
+
+    // Unchanged zone, nothing to do
+    let mut desired = SdnConfiguration::new();
+    desired.zones.insert("vxlan1".to_string(), config.clone());
+    assert!(reconciler.plan(&desired).await.unwrap().is_empty());
+
+    // Changed zone, its devices are updated in place
+    let mut changed = config.clone();
+    changed.mtu = Some(1400);
+    desired.zones.insert("vxlan1".to_string(), changed);
+    assert_eq!(
+        reconciler.plan(&desired).await.unwrap(),
+        vec![ReconcileAction::Update {
+            zone: "vxlan1".to_string(),
+            devices: vec!["vxlan100".to_string(), "vxbr1".to_string()],
+        }]
+    );
+
+    // Deleted zone, its devices are collected
+    let plan = reconciler.plan(&SdnConfiguration::new()).await.unwrap();
+    assert_eq!(
+        plan,
+        vec![
+            ReconcileAction::Remove {
+                zone: "vxlan1".to_string(),
+                device: "vxlan100".to_string()
+            },
+            ReconcileAction::Remove {
+                zone: "vxlan1".to_string(),
+                device: "vxbr1".to_string()
+            },
+        ]
+    );
+}
+
+/// EVPN zone spanning node1-3 with node1 as primary and node2 as backup
+/// exit node
+fn evpn_exit_sdn_config() -> SdnConfiguration {
+    let mut sdn = SdnConfiguration::new();
+
+    let mut zone = ZoneConfig::new(ZoneType::Evpn, "tenant1".to_string());
+    zone.options.insert("vni".to_string(), json!(100));
+    zone.options.insert("rd".to_string(), json!("65000:100"));
+    zone.options
+        .insert("vtep-ip".to_string(), json!("192.168.1.1"));
+    zone.options
+        .insert("controller".to_string(), json!("evpn1"));
+    zone.options.insert("vrf-vxlan".to_string(), json!(10000));
+    zone.options
+        .insert("exitnodes".to_string(), json!("node1,node2"));
+    zone.options
+        .insert("exitnodes-primary".to_string(), json!("node1"));
+    zone.options
+        .insert("exitnodes-local-routing".to_string(), json!(true));
+    sdn.zones.insert("tenant1".to_string(), zone);
+
+    let mut controller = ControllerConfig::new(ControllerType::Evpn, "evpn1".to_string());
+    controller.asn = Some(65000);
+    controller
+        .options
+        .insert("vtep-ip".to_string(), json!("192.168.1.1"));
+    sdn.controllers.insert("evpn1".to_string(), controller);
+
+    let mut vnet = VNetConfig::new("vnet1".to_string(), "tenant1".to_string());
+    vnet.options.insert("vni".to_string(), json!(11000));
+    sdn.vnets.insert("vnet1".to_string(), vnet);
+
+    let mut subnet = SubnetConfig::new(
+        "10.0.1.0-24".to_string(),
+        "vnet1".to_string(),
+        "10.0.1.0/24".parse().unwrap(),
+    );
+    subnet.gateway = Some("10.0.1.1".parse().unwrap());
+    subnet.snat = Some(true);
+    sdn.subnets.insert("10.0.1.0-24".to_string(), subnet);
+
+    sdn
+}
+
+/// BGP instance of the tenant VRF in a rendered frr.conf
+fn vrf_bgp_block(frr: &str) -> &str {
+    let start = frr.find("router bgp 65000 vrf vrf_tenant1\n").unwrap();
+    let len = frr[start..].find("\nexit\n").unwrap();
+    &frr[start..start + len]
+}
+
+async fn generate_for_node(node: &str, sdn: &SdnConfiguration) -> GeneratedSdnConfig {
+    let dir = tempfile::tempdir().unwrap();
+    let factory = Arc::new(PluginFactory::with_executor(
+        Arc::new(DryRunExecutor::new()),
+    ));
+
+    SdnApplyPipeline::new(node)
+        .with_factory(factory)
+        .with_paths(
+            dir.path().join("sdn"),
+            dir.path().join("frr.conf"),
+            dir.path().join("frr.conf.local"),
+        )
+        .with_nftables_path(dir.path().join("pve-sdn.nft"))
+        .generate(sdn)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_frr_local_merged_into_evpn_sections() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("frr.conf.local"),
+        "router bgp 65000 vrf vrf_tenant1\n\
+         \x20bgp bestpath as-path multipath-relax\n\
+         \x20address-family l2vpn evpn\n\
+         \x20\x20advertise ipv4 unicast\n\
+         \x20\x20autort as\n\
+         \x20exit-address-family\n\
+         exit\n",
+    )
+    .unwrap();
+
+    let generated = SdnApplyPipeline::new("node1")
+        .with_factory(Arc::new(PluginFactory::with_executor(Arc::new(
+            DryRunExecutor::new(),
+        ))))
+        .with_paths(
+            dir.path().join("sdn"),
+            dir.path().join("frr.conf"),
+            dir.path().join("frr.conf.local"),
+        )
+        .with_nftables_path(dir.path().join("pve-sdn.nft"))
+        .generate(&evpn_exit_sdn_config())
+        .await
+        .unwrap();
+    let frr = generated.frr.unwrap();
+
+    // The tab indented EVPN sections take the local lines, nothing is appended
+    assert_eq!(frr.matches("router bgp 65000 vrf vrf_tenant1").count(), 1);
+    assert_eq!(frr.matches("address-family l2vpn evpn").count(), 2);
+    assert_eq!(frr.matches("advertise ipv4 unicast").count(), 1);
+
+    let block = vrf_bgp_block(&frr);
+    let relax = block.find("bgp bestpath as-path multipath-relax").unwrap();
+    let family = block.find("\taddress-family l2vpn evpn").unwrap();
+    let autort = block.find("autort as").unwrap();
+    let family_end = block.rfind("exit-address-family").unwrap();
+    assert!(relax < family);
+    assert!(family < autort && autort < family_end);
+}
+
+#[tokio::test]
+async fn test_evpn_exit_nodes_per_node_config() {
+    let sdn = evpn_exit_sdn_config();
+
+    let primary = generate_for_node("node1", &sdn).await;
+    let backup = generate_for_node("node2", &sdn).await;
+    let regular = generate_for_node("node3", &sdn).await;
+
+    let primary_frr = primary.frr.as_deref().unwrap();
+    let backup_frr = backup.frr.as_deref().unwrap();
+    let regular_frr = regular.frr.as_deref().unwrap();
+
+    // Default route injection only on exit nodes
+    assert!(primary_frr.contains("\t\tdefault-originate ipv4\n"));
+    assert!(backup_frr.contains("\t\tdefault-originate ipv4\n"));
+    assert!(!regular_frr.contains("default-originate"));
+
+    // Backup exit node advertises with a worse metric
+    assert!(!primary_frr.contains("MAP_EXIT_BACKUP_TENANT1"));
+    assert!(backup_frr.contains("route-map MAP_EXIT_BACKUP_TENANT1 permit 1\n\tset metric 200\n"));
+    assert!(backup_frr.contains("\t\tadvertise ipv4 unicast route-map MAP_EXIT_BACKUP_TENANT1\n"));
+    assert!(regular_frr.contains("\t\tadvertise ipv4 unicast\n"));
+
+    // VRF route leaking and subnet redistribution only on exit nodes
+    for frr in [primary_frr, backup_frr] {
+        assert!(frr.contains("\t\timport vrf default\n"));
+        assert!(frr.contains("\t\timport vrf vrf_tenant1\n"));
+        assert!(vrf_bgp_block(frr).contains("\t\tredistribute connected\n"));
+        assert!(frr.contains("ip route 10.0.1.0/24 10.255.255.2 xvrf_tenant1\n"));
+    }
+    assert!(!regular_frr.contains("import vrf"));
+    assert!(!vrf_bgp_block(regular_frr).contains("redistribute connected"));
+    assert!(!regular_frr.contains("xvrf_tenant1"));
+
+    // Local routing veth pair only on exit nodes
+    assert_eq!(primary.interfaces, backup.interfaces);
+    assert!(primary.interfaces.contains("iface xvrfp_tenant1\n"));
+    assert!(!regular.interfaces.contains("xvrf"));
+    assert!(regular.interfaces.contains("iface vrf_tenant1\n"));
+
+    // SNAT only on exit nodes
+    let expected_nft = "\t\tip saddr 10.0.1.0/24 ip daddr != { 10.0.1.0/24 } masquerade\n";
+    assert!(primary.nftables.as_deref().unwrap().contains(expected_nft));
+    assert_eq!(primary.nftables, backup.nftables);
+    assert!(regular.nftables.is_none());
+}
+
+#[tokio::test]
+async fn test_evpn_advertise_subnets_on_all_nodes() {
+    let mut sdn = evpn_exit_sdn_config();
+    sdn.zones
+        .get_mut("tenant1")
+        .unwrap()
+        .options
+        .insert("advertise-subnets".to_string(), json!(true));
+
+    let regular = generate_for_node("node3", &sdn).await;
+    let frr = regular.frr.unwrap();
+
+    assert!(vrf_bgp_block(&frr).contains("\t\tredistribute connected\n"));
+    assert!(!frr.contains("default-originate"));
+    assert!(!frr.contains("import vrf"));
+}
+
+#[tokio::test]
+async fn test_evpn_exit_nodes_validation() {
+    let zone = crate::zones::EvpnZone::new("tenant1".to_string());
+    let sdn = evpn_exit_sdn_config();
+    let config = &sdn.zones["tenant1"];
+    assert!(zone.validate_config(config).await.is_ok());
+
+    // Primary must be one of the exit nodes
+    let mut invalid = config.clone();
+    invalid
+        .options
+        .insert("exitnodes-primary".to_string(), json!("node3"));
+    assert!(zone.validate_config(&invalid).await.is_err());
+
+    // Exit nodes need a VRF
+    let mut invalid = config.clone();
+    invalid.options.remove("vrf-vxlan");
+    assert!(zone.validate_config(&invalid).await.is_err());
+
+    // Exit nodes must be part of the zone
+    let mut invalid = config.clone();
+    invalid.nodes = Some(vec!["node1".to_string(), "node3".to_string()]);
+    assert!(zone.validate_config(&invalid).await.is_err());
+
+    // Array form is accepted as well
+    let mut array = config.clone();
+    array
+        .options
+        .insert("exitnodes".to_string(), json!(["node1", "node2"]));
+    assert!(zone.validate_config(&array).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_evpn_vteps_from_fabric() {
+    use pve_sdn_core::{FabricConfig, FabricInterface, FabricNodeConfig, FabricProtocol};
+
+    let mut sdn = SdnConfiguration::new();
+
+    let mut fabric = FabricConfig::new("f1".to_string(), FabricProtocol::Openfabric);
+    for (node, loopback) in [
+        ("node1", "10.10.10.1"),
+        ("node2", "10.10.10.2"),
+        ("node3", "10.10.10.3"),
+    ] {
+        fabric.nodes.push(FabricNodeConfig {
+            node: node.to_string(),
+            loopback: loopback.parse().unwrap(),
+            interfaces: vec![FabricInterface {
+                name: "ens19".to_string(),
+                ip: None,
+            }],
+        });
+    }
+    sdn.add_fabric(fabric).unwrap();
+
+    let mut zone = ZoneConfig::new(ZoneType::Evpn, "evpn1".to_string());
+    zone.options.insert("vni".to_string(), json!(100));
+    zone.options.insert("rd".to_string(), json!("65000:100"));
+    zone.options.insert("controller".to_string(), json!("ctl1"));
+    zone.options.insert("fabric".to_string(), json!("f1"));
+    sdn.zones.insert("evpn1".to_string(), zone);
+
+    let mut controller = ControllerConfig::new(ControllerType::Evpn, "ctl1".to_string());
+    controller.asn = Some(65000);
+    controller.options.insert("fabric".to_string(), json!("f1"));
+    sdn.controllers.insert("ctl1".to_string(), controller);
+
+    let zone = crate::zones::EvpnZone::new("evpn1".to_string());
+    assert!(zone.validate_config(&sdn.zones["evpn1"]).await.is_ok());
+
+    let generated = generate_for_node("node2", &sdn).await;
+
+    assert!(generated
+        .interfaces
+        .contains("iface dummy_f1 inet static\n\taddress 10.10.10.2/32\n"));
+    assert!(generated
+        .interfaces
+        .contains("vxlan-local-tunnelip 10.10.10.2"));
+
+    let frr = generated.frr.unwrap();
+    assert!(frr.contains("router openfabric f1\n net 49.0001.0100.1001.0002.00\n"));
+    assert!(frr.contains(" neighbor 10.10.10.1 remote-as external\n"));
+    assert!(frr.contains(" neighbor 10.10.10.3 remote-as external\n"));
+    assert!(!frr.contains("neighbor 10.10.10.2 "));
+}
+
+#[tokio::test]
+async fn test_dhcp_service_syncs_ipam_leases() {
+    use crate::dhcp::{DhcpService, DnsmasqDhcp};
+    use crate::ipam::PveIpam;
+    use pve_sdn_core::{
+        DhcpConfig, IpAllocationRequest, IpamConfig, IpamManager, IpamType, Subnet,
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut sdn = SdnConfiguration::new();
+    let mut zone = ZoneConfig::new(ZoneType::Simple, "dhcpz".to_string());
+    zone.options.insert("dhcp".to_string(), json!("dnsmasq"));
+    sdn.zones.insert("dhcpz".to_string(), zone);
+    let mut remote = ZoneConfig::new(ZoneType::Simple, "remote".to_string());
+    remote.options.insert("dhcp".to_string(), json!("dnsmasq"));
+    remote.nodes = Some(vec!["node2".to_string()]);
+    sdn.zones.insert("remote".to_string(), remote);
+    sdn.vnets.insert(
+        "vnet1".to_string(),
+        VNetConfig::new("vnet1".to_string(), "dhcpz".to_string()),
+    );
+
+    let mut subnet = SubnetConfig::new(
+        "dhcpz-10.1.0.0-24".to_string(),
+        "vnet1".to_string(),
+        "10.1.0.0/24".parse().unwrap(),
+    );
+    subnet.gateway = Some("10.1.0.1".parse().unwrap());
+    subnet.dhcp = Some(DhcpConfig {
+        dhcp_range: Some(vec!["10.1.0.100,10.1.0.200".to_string()]),
+        dns_server: None,
+    });
+    sdn.subnets.insert(subnet.subnet.clone(), subnet.clone());
+
+    let mut ipam = IpamManager::new();
+    ipam.register_plugin(Arc::new(
+        PveIpam::new(
+            "pve".to_string(),
+            IpamConfig::new("pve".to_string(), IpamType::Pve),
+        )
+        .with_storage_path(dir.path().join("ipam")),
+    ));
+    ipam.set_default_plugin("pve").unwrap();
+    ipam.add_subnet(None, &Subnet::new(subnet)).await.unwrap();
+
+    let executor = Arc::new(RecordingExecutor::new());
+    let dnsmasq = DnsmasqDhcp::new()
+        .with_paths(dir.path().join("dnsmasq.d"), dir.path().join("leases"))
+        .with_executor(executor.clone());
+    let service = DhcpService::new("node1").with_plugin(Arc::new(dnsmasq));
+
+    let result = service.apply(&sdn, &ipam).await.unwrap();
+    assert_eq!(result.changed, vec!["dhcpz"]);
+    assert!(result.removed.is_empty());
+    executor.assert_commands(&[
+        "systemctl enable dnsmasq@dhcpz",
+        "systemctl restart dnsmasq@dhcpz",
+    ]);
+
+    // A new VM allocation becomes a static lease
+    executor.clear();
+    let allocation = ipam
+        .allocate_ip(
+            None,
+            &IpAllocationRequest {
+                subnet: "dhcpz-10.1.0.0-24".to_string(),
+                vmid: Some(100),
+                hostname: Some("vm100".to_string()),
+                mac: Some("BC:24:11:AA:00:01".to_string()),
+                description: None,
+                requested_ip: Some("10.1.0.10".parse().unwrap()),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(service
+        .sync_subnet(&sdn, &ipam, "dhcpz-10.1.0.0-24")
+        .await
+        .unwrap());
+    executor.assert_commands(&["systemctl reload dnsmasq@dhcpz"]);
+
+    let ethers_path = dir.path().join("dnsmasq.d/dhcpz/ethers");
+    let ethers = std::fs::read_to_string(&ethers_path).unwrap();
+    assert!(ethers.ends_with("bc:24:11:aa:00:01,10.1.0.10\n"));
+
+    // Releasing it drops the lease again
+    ipam.release_ip(None, "dhcpz-10.1.0.0-24", &allocation.ip)
+        .await
+        .unwrap();
+    assert!(service
+        .sync_subnet(&sdn, &ipam, "dhcpz-10.1.0.0-24")
+        .await
+        .unwrap());
+    let ethers = std::fs::read_to_string(&ethers_path).unwrap();
+    assert!(!ethers.contains("10.1.0.10"));
+
+    // Unknown subnets are ignored
+    assert!(!service.sync_subnet(&sdn, &ipam, "other").await.unwrap());
+
+    // Disabling DHCP removes the instance
+    executor.clear();
+    sdn.zones.get_mut("dhcpz").unwrap().options.remove("dhcp");
+    let result = service.apply(&sdn, &ipam).await.unwrap();
+    assert_eq!(result.removed, vec!["dhcpz"]);
+    executor.assert_commands(&["systemctl disable --now dnsmasq@dhcpz"]);
+    assert!(!dir.path().join("dnsmasq.d/dhcpz").exists());
+}
+
+fn simple_routed_sdn_config() -> SdnConfiguration {
+    let mut sdn = SdnConfiguration::new();
+
+    let mut zone = ZoneConfig::new(ZoneType::Simple, "routed".to_string());
+    zone.bridge = Some("sdnbr0".to_string());
+    sdn.zones.insert("routed".to_string(), zone);
+
+    for vnet in ["vnet1", "vnet2", "vnet3"] {
+        sdn.vnets.insert(
+            vnet.to_string(),
+            VNetConfig::new(vnet.to_string(), "routed".to_string()),
+        );
+    }
+
+    let subnets = [
+        (
+            "routed-10.0.1.0-24",
+            "vnet1",
+            "10.0.1.0/24",
+            "10.0.1.1",
+            true,
+        ),
+        (
+            "routed-fd00:1::-64",
+            "vnet1",
+            "fd00:1::/64",
+            "fd00:1::1",
+            true,
+        ),
+        (
+            "routed-10.0.2.0-24",
+            "vnet2",
+            "10.0.2.0/24",
+            "10.0.2.1",
+            false,
+        ),
+    ];
+    for (name, vnet, cidr, gateway, snat) in subnets {
+        let mut subnet =
+            SubnetConfig::new(name.to_string(), vnet.to_string(), cidr.parse().unwrap());
+        subnet.gateway = Some(gateway.parse().unwrap());
+        subnet.snat = Some(snat);
+        sdn.subnets.insert(name.to_string(), subnet);
+    }
+
+    sdn
+}
+
+#[tokio::test]
+async fn test_simple_zone_gateway_and_snat() {
+    let mut sdn = simple_routed_sdn_config();
+    let generated = generate_for_node("node1", &sdn).await;
+
+    // Gateways on the vnet bridges, forwarding per address family
+    assert!(generated.interfaces.contains(
+        "auto vnet1\n\
+         iface vnet1\n    \
+         address 10.0.1.1/24\n    \
+         address fd00:1::1/64\n    \
+         bridge_ports none\n    \
+         bridge_stp off\n    \
+         bridge_fd 0\n    \
+         ip-forward on\n    \
+         ip6-forward on\n"
+    ));
+    assert!(generated.interfaces.contains(
+        "iface vnet2\n    address 10.0.2.1/24\n    bridge_ports none\n    bridge_stp off\n    bridge_fd 0\n    ip-forward on\n"
+    ));
+    // A vnet without subnets stays a plain isolated bridge
+    assert!(generated
+        .interfaces
+        .contains("iface vnet3\n    bridge_ports none\n"));
+    assert!(!generated.interfaces.contains("iface vnet3\n    address"));
+
+    // Only snat subnets are masqueraded, never towards other zone subnets
+    assert_eq!(
+        generated.nftables.as_deref().unwrap(),
+        "#!/usr/sbin/nft -f\n\
+         \n\
+         table inet pve-sdn\n\
+         delete table inet pve-sdn\n\
+         \n\
+         table inet pve-sdn {\n\
+         \tchain postrouting {\n\
+         \t\ttype nat hook postrouting priority srcnat; policy accept;\n\
+         \t\tip saddr 10.0.1.0/24 ip daddr != { 10.0.1.0/24, 10.0.2.0/24 } masquerade\n\
+         \t\tip6 saddr fd00:1::/64 ip6 daddr != { fd00:1::/64 } masquerade\n\
+         \t}\n\
+         }\n"
+    );
+
+    // Without snat subnets no ruleset is rendered
+    for subnet in sdn.subnets.values_mut() {
+        subnet.snat = None;
+    }
+    assert!(generate_for_node("node1", &sdn).await.nftables.is_none());
+
+    // SNAT needs the gateway to route through
+    let subnet = sdn.subnets.get_mut("routed-10.0.2.0-24").unwrap();
+    subnet.snat = Some(true);
+    subnet.gateway = None;
+    let err = SdnApplyPipeline::new("node1")
+        .with_factory(Arc::new(PluginFactory::with_executor(Arc::new(
+            DryRunExecutor::new(),
+        ))))
+        .generate(&sdn)
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("needs a gateway"));
+}
+
+#[tokio::test]
+async fn test_simple_zone_snat_ruleset_replaced_on_apply() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct NftCounter(AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl crate::apply::SdnReloader for NftCounter {
+        async fn reload_interfaces(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn reload_frr(&self, _frr_config: &std::path::Path) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn reload_nftables(&self, _ruleset: &std::path::Path) -> anyhow::Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn reload_wireguard(
+            &self,
+            _interface: &str,
+            _wg_config: &std::path::Path,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let reloader = Arc::new(NftCounter::default());
+    let pipeline = SdnApplyPipeline::new("node1")
+        .with_factory(Arc::new(PluginFactory::with_executor(Arc::new(
+            DryRunExecutor::new(),
+        ))))
+        .with_paths(
+            dir.path().join("sdn"),
+            dir.path().join("frr.conf"),
+            dir.path().join("frr.conf.local"),
+        )
+        .with_nftables_path(dir.path().join("pve-sdn.nft"))
+        .with_reloader(reloader.clone());
+
+    let mut sdn = simple_routed_sdn_config();
+    assert!(pipeline.apply(&sdn).await.unwrap().nftables_reloaded);
+    assert!(!pipeline.apply(&sdn).await.unwrap().nftables_reloaded);
+    assert_eq!(reloader.0.load(Ordering::SeqCst), 1);
+
+    // Dropping SNAT flushes the table instead of leaving stale rules
+    for subnet in sdn.subnets.values_mut() {
+        subnet.snat = Some(false);
+    }
+    assert!(pipeline.apply(&sdn).await.unwrap().nftables_reloaded);
+    let ruleset = std::fs::read_to_string(dir.path().join("pve-sdn.nft")).unwrap();
+    assert!(ruleset.contains("delete table inet pve-sdn\n"));
+    assert!(!ruleset.contains("masquerade"));
+}
+
+#[tokio::test]
+async fn test_vnet_firewall_rendered_for_active_zones() {
+    use pve_sdn_core::{FirewallAction, FirewallDirection, FirewallRule, VNetFirewall};
+
+    let dir = tempfile::tempdir().unwrap();
+    let pipeline = SdnApplyPipeline::new("node1")
+        .with_factory(Arc::new(PluginFactory::with_executor(Arc::new(
+            DryRunExecutor::new(),
+        ))))
+        .with_paths(
+            dir.path().join("sdn"),
+            dir.path().join("frr.conf"),
+            dir.path().join("frr.conf.local"),
+        )
+        .with_nftables_path(dir.path().join("pve-sdn.nft"));
+
+    let mut sdn = simple_routed_sdn_config();
+    let mut rule = FirewallRule::new(FirewallDirection::In, FirewallAction::Accept);
+    rule.proto = Some("tcp".to_string());
+    rule.dport = Some("22".to_string());
+    rule.source = Some("10.0.1.0/24".to_string());
+    sdn.vnets.get_mut("vnet2").unwrap().firewall = Some(VNetFirewall {
+        enable: Some(true),
+        policy_in: Some(FirewallAction::Drop),
+        rules: vec![rule],
+        ..Default::default()
+    });
+
+    let ruleset = pipeline.generate(&sdn).await.unwrap().nftables.unwrap();
+    assert!(ruleset.contains("delete table inet pve-sdn\n"));
+    assert!(ruleset.contains("masquerade"));
+    assert!(ruleset.contains("\t\toifname \"vnet2\" jump vnet2-in\n"));
+    assert!(ruleset.contains("\t\tip saddr 10.0.1.0/24 tcp dport 22 return\n"));
+    assert!(!ruleset.contains("vnet1-in"));
+
+    // Invalid rules fail generation instead of loading a partial ruleset
+    sdn.vnets
+        .get_mut("vnet2")
+        .unwrap()
+        .firewall
+        .as_mut()
+        .unwrap()
+        .rules[0]
+        .source = Some("+missing".to_string());
+    assert!(pipeline.generate(&sdn).await.is_err());
+
+    // Zones not configured on the node carry no firewall
+    sdn.zones.get_mut("routed").unwrap().nodes = Some(vec!["node2".to_string()]);
+    assert!(pipeline.generate(&sdn).await.unwrap().nftables.is_none());
+}
+
+#[tokio::test]
+async fn test_evpn_node_status() {
+    let sdn = evpn_exit_sdn_config();
+
+    let executor = Arc::new(RecordingExecutor::new());
+    for (name, kind) in [
+        ("vxlan100", "vxlan"),
+        ("vxlan_vnet1", "vxlan"),
+        ("vnet1", "bridge"),
+    ] {
+        executor.respond(
+            &format!("ip -j -d link show dev {}", name),
+            CommandOutput::success(&link_json(name, kind, None)),
+        );
+    }
+    executor.respond(
+        "vtysh -c show evpn vni json",
+        CommandOutput::success(r#"{"10000": {"vni": 10000, "type": "L3"}}"#),
+    );
+
+    let factory = Arc::new(PluginFactory::with_executor(executor.clone()));
+    let collector = factory.status_collector("node1");
+
+    // The L3VNI is up, the L2VNI of the vnet is not known to zebra yet
+    let status = collector.collect(&sdn, Some(1)).await.unwrap();
+    assert_eq!(status.zones[0].status, DeploymentStatus::Available);
+    assert_eq!(status.vnets[0].status, DeploymentStatus::Error);
+    assert_eq!(
+        status.vnets[0].message.as_deref(),
+        Some("VNI 11000 is not known to FRR")
+    );
+
+    executor.respond(
+        "vtysh -c show evpn vni json",
+        CommandOutput::success(r#"{"10000": {"vni": 10000}, "11000": {"vni": 11000}}"#),
+    );
+    let status = collector.collect(&sdn, Some(1)).await.unwrap();
+    assert_eq!(status.vnets[0].status, DeploymentStatus::Available);
+
+    executor.respond(
+        "ip -j -d link show dev vnet1",
+        CommandOutput::failure(1, "Device \"vnet1\" does not exist."),
+    );
+    let status = collector.collect(&sdn, Some(1)).await.unwrap();
+    assert_eq!(status.zones[0].status, DeploymentStatus::Available);
+    assert_eq!(
+        status.vnets[0].message.as_deref(),
+        Some("bridge 'vnet1' is missing")
+    );
+}
+
+const NODE1_PRIVATE_KEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+const NODE1_PUBLIC_KEY: &str = "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw=";
+const NODE2_PUBLIC_KEY: &str = "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=";
+
+/// pmxcfs with the cluster members and the key of node2 published
+async fn wireguard_pmxcfs(dir: &std::path::Path) -> std::path::PathBuf {
+    let pmxcfs = dir.join("pve");
+    std::fs::create_dir_all(&pmxcfs).unwrap();
+    std::fs::write(
+        pmxcfs.join(".members"),
+        json!({
+            "nodename": "node1",
+            "version": 5,
+            "cluster": {"name": "dc1", "version": 2, "nodes": 2, "quorate": 1},
+            "nodelist": {
+                "node1": {"id": 1, "online": 1, "ip": "192.0.2.1"},
+                "node2": {"id": 2, "online": 1, "ip": "192.0.2.2"},
+            },
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let mut node2 = NodeWireguardKeys {
+        node: "node2".to_string(),
+        ..Default::default()
+    };
+    node2.zones.insert(
+        "secure".to_string(),
+        WireguardPeer {
+            public_key: NODE2_PUBLIC_KEY.to_string(),
+            address: "10.255.0.2".parse().unwrap(),
+            endpoint: "192.0.2.2".parse().unwrap(),
+            created: chrono::Utc::now(),
+            next: None,
+        },
+    );
+    WireguardKeyStore::with_base_path(&pmxcfs)
+        .publish(&node2)
+        .await
+        .unwrap();
+
+    pmxcfs
+}
+
+fn wireguard_executor() -> Arc<RecordingExecutor> {
+    let executor = Arc::new(RecordingExecutor::new());
+    executor.respond("hostname", CommandOutput::success("node1.example.com\n"));
+    executor.respond(
+        "wg genkey",
+        CommandOutput::success(&format!("{}\n", NODE1_PRIVATE_KEY)),
+    );
+    executor.respond(
+        "sh -c wg pubkey",
+        CommandOutput::success(&format!("{}\n", NODE1_PUBLIC_KEY)),
+    );
+    executor
+}
+
+fn wireguard_zone_config() -> ZoneConfig {
+    let mut config = ZoneConfig::new(ZoneType::Wireguard, "secure".to_string());
+    config.bridge = Some("wgbr0".to_string());
+    config.nodes = Some(vec!["node1".to_string(), "node2".to_string()]);
+    config.options.insert("vni".to_string(), json!(100));
+    config
+        .options
+        .insert("wg-network".to_string(), json!("10.255.0.0/24"));
+    config
+}
+
+#[tokio::test]
+async fn test_wireguard_zone_key_distribution() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let pmxcfs = wireguard_pmxcfs(dir.path()).await;
+    let wireguard = dir.path().join("wireguard");
+    let executor = wireguard_executor();
+    let config = wireguard_zone_config();
+
+    let zone = WireguardZone::with_executor("secure".to_string(), executor.clone())
+        .with_paths(&pmxcfs, &wireguard);
+    let configs = zone.generate_config(&config).await.unwrap();
+
+    // Generating neither creates nor publishes keys
+    let private_key = wireguard.join("private/secure.key");
+    let store = WireguardKeyStore::with_base_path(&pmxcfs);
+    assert!(!private_key.exists());
+    assert!(store.read("node1").await.unwrap().zones.is_empty());
+    assert!(!executor.command_lines().contains(&"wg genkey".to_string()));
+
+    assert_eq!(
+        configs["wg/wg_secure"],
+        format!(
+            "[Interface]\n\
+             ListenPort = 51820\n\
+             \n\
+             [Peer]\n\
+             # node2\n\
+             PublicKey = {}\n\
+             Endpoint = 192.0.2.2:51820\n\
+             AllowedIPs = 10.255.0.2/32\n\
+             PersistentKeepalive = 25\n",
+            NODE2_PUBLIC_KEY
+        )
+    );
+    assert_eq!(
+        configs["wireguard"],
+        format!(
+            "auto wg_secure\n\
+             iface wg_secure inet static\n\
+             \taddress 10.255.0.1/24\n\
+             \tpre-up ip link add wg_secure type wireguard\n\
+             \tpre-up wg setconf wg_secure {wg}/wg_secure.conf\n\
+             \tpre-up wg set wg_secure private-key {wg}/private/secure.key\n\
+             \tpost-down ip link del wg_secure\n\
+             \tmtu 1420\n\
+             \talias {alias}\n",
+            wg = wireguard.display(),
+            alias = owner_alias(&config)
+        )
+    );
+    assert!(configs["vxlan"].contains("\tvxlan-local-tunnelip 10.255.0.1\n"));
+    assert!(configs["vxlan"].contains("\tvxlan-remoteip 10.255.0.2\n"));
+    assert!(configs["vxlan"].contains("\tmtu 1370\n"));
+
+    // The key step creates the key pair, the private key stays on the
+    // node, readable by root only
+    let keys = zone.key_manager("node1");
+    let local = keys.sync_key(&config, 1).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&private_key).unwrap(),
+        format!("{}\n", NODE1_PRIVATE_KEY)
+    );
+    let mode = std::fs::metadata(&private_key)
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    // The public key is published with the address of node1's position
+    // among the zone nodes
+    assert_eq!(store.read("node1").await.unwrap().zones["secure"], local);
+    assert_eq!(local.public_key, NODE1_PUBLIC_KEY);
+    assert_eq!(
+        local.address,
+        "10.255.0.1".parse::<std::net::IpAddr>().unwrap()
+    );
+    assert_eq!(
+        local.endpoint,
+        "192.0.2.1".parse::<std::net::IpAddr>().unwrap()
+    );
+    assert!(local.next.is_none());
+
+    // Until the key lifetime ends the key is kept
+    let mut config = config;
+    config.options.insert("key-lifetime".to_string(), json!(30));
+    assert_eq!(keys.sync_key(&config, 1).await.unwrap(), local);
+
+    // An expired key gets a successor staged for the next configuration
+    // version, the current key stays in use
+    let mut expired = store.read("node1").await.unwrap();
+    expired.zones.get_mut("secure").unwrap().created =
+        chrono::Utc::now() - chrono::Duration::days(31);
+    store.publish(&expired).await.unwrap();
+
+    executor.respond(
+        "wg genkey",
+        CommandOutput::success("UDmC0yMqgiTzXX7p5UqCFkxvjBg4JApGSyG7KlcTqHg=\n"),
+    );
+    executor.respond(
+        "sh -c wg pubkey",
+        CommandOutput::success("TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0=\n"),
+    );
+    executor.respond("ip link show", CommandOutput::success(""));
+    executor.clear();
+    let staged = keys.sync_key(&config, 1).await.unwrap();
+    assert_eq!(staged.public_key, NODE1_PUBLIC_KEY);
+    let next = staged.next.clone().unwrap();
+    assert_eq!(
+        next.public_key,
+        "TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0="
+    );
+    assert_eq!(next.version, 2);
+    assert_eq!(
+        std::fs::read_to_string(&private_key).unwrap(),
+        format!("{}\n", NODE1_PRIVATE_KEY)
+    );
+    assert!(!executor
+        .command_lines()
+        .iter()
+        .any(|line| line.starts_with("wg set")));
+
+    // Staging again keeps the staged key
+    assert_eq!(keys.sync_key(&config, 1).await.unwrap(), staged);
+
+    // The other nodes keep the old key for the running version and switch
+    // with the next one
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_zone(config.clone()).unwrap();
+    sdn.version = Some(1);
+    let configs = zone
+        .generate_node_config(&config, &ZoneRenderContext::new("node2", &sdn))
+        .await
+        .unwrap();
+    assert!(configs["wg/wg_secure"].contains(NODE1_PUBLIC_KEY));
+    assert!(configs["wireguard"].contains("\taddress 10.255.0.2/24\n"));
+    sdn.version = Some(2);
+    let configs = zone
+        .generate_node_config(&config, &ZoneRenderContext::new("node2", &sdn))
+        .await
+        .unwrap();
+    assert!(configs["wg/wg_secure"].contains(&next.public_key));
+
+    // So does node1, loading the new key into the running interface
+    executor.clear();
+    let rotated = keys.sync_key(&config, 2).await.unwrap();
+    assert_eq!(rotated.public_key, next.public_key);
+    assert_eq!(rotated.created, next.created);
+    assert_eq!(rotated.address, local.address);
+    assert!(rotated.next.is_none());
+    assert_eq!(store.read("node1").await.unwrap().zones["secure"], rotated);
+    assert_eq!(
+        std::fs::read_to_string(&private_key).unwrap(),
+        "UDmC0yMqgiTzXX7p5UqCFkxvjBg4JApGSyG7KlcTqHg=\n"
+    );
+    assert!(!wireguard.join("private/secure.next.key").exists());
+    assert!(executor.command_lines().contains(&format!(
+        "wg set wg_secure private-key {}",
+        private_key.display()
+    )));
+
+    keys.remove_key("secure").await.unwrap();
+    assert!(!private_key.exists());
+    assert!(store.read("node1").await.unwrap().zones.is_empty());
+}
+
+#[tokio::test]
+async fn test_wireguard_zone_command_sequence() {
+    let dir = tempfile::tempdir().unwrap();
+    let pmxcfs = wireguard_pmxcfs(dir.path()).await;
+    let wireguard = dir.path().join("wireguard");
+    let executor = wireguard_executor();
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+    let config = wireguard_zone_config();
+
+    let zone = WireguardZone::with_executor("secure".to_string(), executor.clone())
+        .with_paths(&pmxcfs, &wireguard);
+    zone.apply_config(&config).await.unwrap();
+
+    let alias = owner_alias(&config);
+    let private_key = wireguard.join("private/secure.key");
+    executor.assert_commands(&[
+        "hostname",
+        "wg genkey",
+        &format!("sh -c wg pubkey < \"$1\" sh {}", private_key.display()),
+        "ip link show wg_secure",
+        "ip link show wg_secure",
+        "ip link add wg_secure type wireguard",
+        &format!("ip link set dev wg_secure alias {}", alias),
+        "ip address add 10.255.0.1/24 dev wg_secure",
+        "ip link set wg_secure mtu 1420",
+        &format!(
+            "wg set wg_secure listen-port 51820 private-key {}",
+            private_key.display()
+        ),
+        &format!(
+            "wg set wg_secure peer {} endpoint 192.0.2.2:51820 allowed-ips 10.255.0.2/32 persistent-keepalive 25",
+            NODE2_PUBLIC_KEY
+        ),
+        "ip link set wg_secure up",
+        "ip link show vxlan100",
+        "ip link add vxlan100 type vxlan id 100 dstport 4789 local 10.255.0.1 dev wg_secure",
+        &format!("ip link set dev vxlan100 alias {}", alias),
+        "ip link set vxlan100 mtu 1370",
+        "ip link set vxlan100 up",
+        "bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.255.0.2",
+        "ip link show wgbr0",
+        "ip link add name wgbr0 type bridge",
+        &format!("ip link set dev wgbr0 alias {}", alias),
+        "ip link set vxlan100 master wgbr0",
+        "ip link set wgbr0 up",
+    ]);
+
+    // Existing devices get their keys, peers and flood entries synced
+    executor.clear();
+    existing_wireguard_devices(&executor, &[NODE2_PUBLIC_KEY], &["10.255.0.2"]);
+    zone.apply_config(&config).await.unwrap();
+    executor.assert_commands(&[
+        "hostname",
+        "ip link show wg_secure",
+        "ip -j addr show dev wg_secure",
+        &format!(
+            "wg set wg_secure listen-port 51820 private-key {}",
+            private_key.display()
+        ),
+        "wg show wg_secure peers",
+        &format!(
+            "wg set wg_secure peer {} endpoint 192.0.2.2:51820 allowed-ips 10.255.0.2/32 persistent-keepalive 25",
+            NODE2_PUBLIC_KEY
+        ),
+        "ip link show vxlan100",
+        "bridge -j fdb show dev vxlan100",
+        "ip link show wgbr0",
+    ]);
+}
+
+/// Devices of the zone exist on node1 with `peers` and flood entries to
+/// `destinations`
+fn existing_wireguard_devices(executor: &RecordingExecutor, peers: &[&str], destinations: &[&str]) {
+    executor.respond("ip link show", CommandOutput::success(""));
+    executor.respond(
+        "ip -j addr show dev wg_secure",
+        CommandOutput::success(
+            &json!([{ "ifname": "wg_secure", "addr_info": [{ "local": "10.255.0.1" }] }])
+                .to_string(),
+        ),
+    );
+    executor.respond(
+        "wg show wg_secure peers",
+        CommandOutput::success(&format!("{}\n", peers.join("\n"))),
+    );
+    let fdb: Vec<_> = destinations
+        .iter()
+        .map(|dst| json!({ "mac": "00:00:00:00:00:00", "dst": dst }))
+        .collect();
+    executor.respond(
+        "bridge -j fdb show dev vxlan100",
+        CommandOutput::success(&json!(fdb).to_string()),
+    );
+}
+
+#[tokio::test]
+async fn test_wireguard_zone_node_joins() {
+    const NODE0_PUBLIC_KEY: &str = "6dNOm8Zt3Dp6ODsYLVAgcE3YJrr9q1iOzKr8Q+0UZ1E=";
+
+    let dir = tempfile::tempdir().unwrap();
+    let pmxcfs = wireguard_pmxcfs(dir.path()).await;
+    let wireguard = dir.path().join("wireguard");
+    let executor = wireguard_executor();
+    executor.respond("ip link show", CommandOutput::failure(1, "does not exist"));
+    let mut config = wireguard_zone_config();
+
+    let zone = WireguardZone::with_executor("secure".to_string(), executor.clone())
+        .with_paths(&pmxcfs, &wireguard);
+    zone.apply_config(&config).await.unwrap();
+
+    // node0 sorts first, but the address of node1 is already in use
+    config.nodes = Some(vec![
+        "node0".to_string(),
+        "node1".to_string(),
+        "node2".to_string(),
+    ]);
+    let store = WireguardKeyStore::with_base_path(&pmxcfs);
+    let others = store
+        .zone_peers("secure", &["node1".to_string(), "node2".to_string()])
+        .await
+        .unwrap();
+    let address = WireguardZone::assign_mesh_address(&config, "node0", None, &others).unwrap();
+    assert_eq!(address, "10.255.0.3".parse::<std::net::IpAddr>().unwrap());
+
+    let mut node0 = NodeWireguardKeys {
+        node: "node0".to_string(),
+        ..Default::default()
+    };
+    node0.zones.insert(
+        "secure".to_string(),
+        WireguardPeer {
+            public_key: NODE0_PUBLIC_KEY.to_string(),
+            address,
+            endpoint: "192.0.2.10".parse().unwrap(),
+            created: chrono::Utc::now(),
+            next: None,
+        },
+    );
+    store.publish(&node0).await.unwrap();
+
+    // node1 keeps its address, and picks up the new peer and flood entry
+    // on its existing devices
+    executor.clear();
+    existing_wireguard_devices(&executor, &[NODE2_PUBLIC_KEY], &["10.255.0.2"]);
+    zone.apply_config(&config).await.unwrap();
+    let commands = executor.command_lines();
+    assert_eq!(
+        store.read("node1").await.unwrap().zones["secure"].address,
+        "10.255.0.1".parse::<std::net::IpAddr>().unwrap()
+    );
+    assert!(!commands.iter().any(|line| line.starts_with("ip address")));
+    assert!(commands.contains(&format!(
+        "wg set wg_secure peer {} endpoint 192.0.2.10:51820 allowed-ips 10.255.0.3/32 persistent-keepalive 25",
+        NODE0_PUBLIC_KEY
+    )));
+    assert!(commands
+        .contains(&"bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.255.0.3".to_string()));
+    assert!(!commands
+        .contains(&"bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.255.0.2".to_string()));
+
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_zone(config.clone()).unwrap();
+    let configs = zone
+        .generate_node_config(&config, &ZoneRenderContext::new("node1", &sdn))
+        .await
+        .unwrap();
+    assert!(configs["wireguard"].contains("\taddress 10.255.0.1/24\n"));
+    assert!(configs["vxlan"].contains("\tvxlan-remoteip 10.255.0.3\n"));
+
+    // node2 leaves, its peer and flood entry are removed
+    config.nodes = Some(vec!["node0".to_string(), "node1".to_string()]);
+    executor.clear();
+    existing_wireguard_devices(
+        &executor,
+        &[NODE0_PUBLIC_KEY, NODE2_PUBLIC_KEY],
+        &["10.255.0.2", "10.255.0.3"],
+    );
+    zone.apply_config(&config).await.unwrap();
+    let commands = executor.command_lines();
+    assert!(commands.contains(&format!(
+        "wg set wg_secure peer {} remove",
+        NODE2_PUBLIC_KEY
+    )));
+    assert!(!commands.contains(&format!(
+        "wg set wg_secure peer {} remove",
+        NODE0_PUBLIC_KEY
+    )));
+    assert!(commands
+        .contains(&"bridge fdb del 00:00:00:00:00:00 dev vxlan100 dst 10.255.0.2".to_string()));
+    assert!(!commands
+        .iter()
+        .any(|line| line.starts_with("bridge fdb append")));
+}
+
+#[tokio::test]
+async fn test_wireguard_zone_apply_pipeline() {
+    let dir = tempfile::tempdir().unwrap();
+    let pmxcfs = wireguard_pmxcfs(dir.path()).await;
+    let wireguard = dir.path().join("wireguard");
+    let executor = wireguard_executor();
+
+    let factory = Arc::new(PluginFactory::with_executor(executor.clone()));
+    factory.register_zone_driver(ZoneType::Wireguard, {
+        let executor = executor.clone();
+        let (pmxcfs, wireguard) = (pmxcfs.clone(), wireguard.clone());
+        Box::new(move |name| {
+            Box::new(
+                WireguardZone::with_executor(name, executor.clone())
+                    .with_paths(&pmxcfs, &wireguard),
+            )
+        })
+    });
+    let pipeline = SdnApplyPipeline::new("node1")
+        .with_factory(factory)
+        .with_paths(
+            dir.path().join("sdn"),
+            dir.path().join("frr.conf"),
+            dir.path().join("frr.conf.local"),
+        )
+        .with_nftables_path(dir.path().join("pve-sdn.nft"))
+        .with_wireguard_path(&wireguard);
+
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_zone(wireguard_zone_config()).unwrap();
+
+    // The WireGuard config is rendered separately from the interfaces file
+    let generated = pipeline.generate(&sdn).await.unwrap();
+    assert!(generated.wireguard["wg_secure"].contains(NODE2_PUBLIC_KEY));
+    assert!(!generated.interfaces.contains("[Peer]"));
+    assert!(generated.interfaces.contains("iface wg_secure inet static"));
+
+    // Only applying creates the key pair
+    let private_key = wireguard.join("private/secure.key");
+    assert!(!private_key.exists());
+
+    let result = pipeline.apply(&sdn).await.unwrap();
+    assert!(private_key.exists());
+    assert!(result.wireguard[0].changed);
+    assert!(result.wireguard_reloaded);
+    let wg_config = wireguard.join("wg_secure.conf");
+    assert_eq!(
+        std::fs::read_to_string(&wg_config).unwrap(),
+        generated.wireguard["wg_secure"]
+    );
+    assert!(executor
+        .command_lines()
+        .contains(&format!("wg syncconf wg_secure {}", wg_config.display())));
+
+    // Unchanged configs are not synced again
+    let result = pipeline.apply(&sdn).await.unwrap();
+    assert!(!result.changed());
+    assert!(!result.wireguard_reloaded);
+
+    // New peers reach the running interface
+    let store = WireguardKeyStore::with_base_path(&pmxcfs);
+    let mut node2 = store.read("node2").await.unwrap();
+    node2.zones.get_mut("secure").unwrap().endpoint = "192.0.2.22".parse().unwrap();
+    store.publish(&node2).await.unwrap();
+    let result = pipeline.apply(&sdn).await.unwrap();
+    assert!(result.wireguard_reloaded);
+    assert!(!result.interfaces_reloaded);
+}
+
+#[tokio::test]
+async fn test_wireguard_zone_through_sdn_api_types() {
+    let config: ZoneConfig = serde_json::from_value(json!({
+        "type": "wireguard",
+        "zone": "secure",
+        "nodes": ["node1", "node2"],
+        "vni": 100,
+        "wg-network": "10.255.0.0/24",
+        "mtu": 1500,
+    }))
+    .unwrap();
+    assert_eq!(config.zone_type, ZoneType::Wireguard);
+    assert_eq!(config.zone_type.to_string(), "wireguard");
+
+    // A 1500 byte overlay does not fit into a 1500 byte underlay
+    let zone = PluginFactory::new()
+        .create_zone(&config.zone_type, config.zone.clone())
+        .unwrap();
+    assert!(zone.validate_config(&config).await.is_err());
+
+    // WireGuard zones take part in the VNI uniqueness check
+    let mut sdn = SdnConfiguration::new();
+    sdn.add_zone(config).unwrap();
+    let mut vxlan = ZoneConfig::new(ZoneType::Vxlan, "vxlan1".to_string());
+    vxlan.options.insert("vni".to_string(), json!(100));
+    sdn.add_zone(vxlan).unwrap();
+    assert!(sdn.validate_vni_uniqueness().is_err());
+}
+
+/// Stanzas of a rendered interfaces file, by interface name
+fn interface_stanzas(interfaces: &str) -> Vec<(&str, &str)> {
+    interfaces
+        .split("\n\n")
+        .filter_map(|stanza| {
+            let start = stanza.find("iface ")?;
+            let stanza = &stanza[start..];
+            let name = stanza[6..].split_whitespace().next()?;
+            Some((name, stanza))
+        })
+        .collect()
+}
+
+/// Whether a bridge forwards frames between two of its ports
+///
+/// The kernel drops frames between two isolated ports and forwards all
+/// others. A gateway on the bridge device itself is not a port and stays
+/// reachable from every port.
+fn bridge_forwards(from_isolated: bool, to_isolated: bool) -> bool {
+    !(from_isolated && to_isolated)
+}
+
+#[tokio::test]
+async fn test_isolated_guests_reach_the_gateway() {
+    let mut sdn = evpn_exit_sdn_config();
+    sdn.vnets.get_mut("vnet1").unwrap().isolate_ports = Some(true);
+
+    let mut vlan = ZoneConfig::new(ZoneType::Vlan, "vlanz".to_string());
+    vlan.bridge = Some("vmbr0".to_string());
+    sdn.add_zone(vlan).unwrap();
+    let mut qinq = ZoneConfig::new(ZoneType::QinQ, "qinqz".to_string());
+    qinq.bridge = Some("vmbr1".to_string());
+    qinq.tag = Some(20);
+    sdn.add_zone(qinq).unwrap();
+    let mut vxlan = ZoneConfig::new(ZoneType::Vxlan, "vxlanz".to_string());
+    vxlan.options.insert("vni".to_string(), json!(200));
+    vxlan
+        .options
+        .insert("local-ip".to_string(), json!("192.0.2.1"));
+    vxlan.peers = Some(vec!["192.0.2.1".to_string(), "192.0.2.2".to_string()]);
+    sdn.add_zone(vxlan).unwrap();
+
+    for (vnet, zone) in [("vnet2", "vlanz"), ("vnet3", "qinqz"), ("vnet4", "vxlanz")] {
+        let mut config = VNetConfig::new(vnet.to_string(), zone.to_string());
+        config.tag = Some(30);
+        config.isolate_ports = Some(true);
+        sdn.add_vnet(config).unwrap();
+    }
+    sdn.validate().unwrap();
+
+    let generated = generate_for_node("node1", &sdn).await;
+    let stanzas = interface_stanzas(&generated.interfaces);
+    assert!(stanzas.iter().any(|(name, _)| *name == "vxlan200"));
+    assert!(stanzas.iter().any(|(name, _)| *name == "vxlan_vnet1"));
+
+    for vnet in sdn.vnets.values() {
+        // Guests are plugged into the vnet bridge isolated
+        let guest = vnet.isolate_ports();
+        assert!(guest);
+        assert!(!bridge_forwards(guest, guest), "{}", vnet.vnet);
+    }
+
+    // Uplinks and tunnels, and the gateways behind them, stay reachable
+    for (name, stanza) in &stanzas {
+        let isolated = stanza.contains("bridge-port-isolation on");
+        assert!(bridge_forwards(true, isolated), "{} is isolated", name);
+    }
+
+    // EVPN routes through the anycast gateway on the vnet bridge itself
+    let (_, vnet1) = stanzas.iter().find(|(name, _)| *name == "vnet1").unwrap();
+    assert!(vnet1.contains("address 10.0.1.1/24"));
+    assert!(vnet1.contains("bridge_ports vxlan_vnet1"));
+
+    // Geneve tunnels are isolated for split horizon, isolated guests could
+    // not reach them, so the vnet is rejected
+    let mut geneve = ZoneConfig::new(ZoneType::Geneve, "geneve1".to_string());
+    geneve.bridge = Some("gnvbr1".to_string());
+    geneve.options.insert("vni".to_string(), json!(300));
+    geneve
+        .options
+        .insert("local-ip".to_string(), json!("192.0.2.1"));
+    geneve.peers = Some(vec!["192.0.2.1".to_string(), "192.0.2.2".to_string()]);
+    sdn.add_zone(geneve).unwrap();
+
+    let generated = generate_for_node("node1", &sdn).await;
+    let stanzas = interface_stanzas(&generated.interfaces);
+    let (_, tunnel) = stanzas
+        .iter()
+        .find(|(_, stanza)| stanza.contains("type geneve"))
+        .unwrap();
+    assert!(!bridge_forwards(
+        true,
+        tunnel.contains("bridge-port-isolation on")
+    ));
+
+    let mut isolated = VNetConfig::new("vnet5".to_string(), "geneve1".to_string());
+    isolated.isolate_ports = Some(true);
+    assert!(sdn.add_vnet(isolated).is_err());
+}
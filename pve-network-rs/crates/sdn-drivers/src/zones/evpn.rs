@@ -4,12 +4,21 @@
 //! This enables scalable multi-tenant networks with advanced features like
 //! MAC mobility, ARP suppression, and integrated routing.
 
+use super::snat::generate_snat_nftables;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use ipnet::IpNet;
 use log::{debug, info, warn};
-use pve_sdn_core::{Zone, ZoneConfig, ZoneType};
+use pve_sdn_core::reconciler::{
+    mark_owned, observe_device, owner_alias_attribute, remove_owned_devices,
+};
+use pve_sdn_core::{
+    RealExecutor, SdnConfiguration, SubnetConfig, SystemExecutor, VNetConfig, Zone, ZoneConfig,
+    ZoneObservedState, ZoneRenderContext, ZoneType,
+};
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::Arc;
 
 /// EVPN zone implementation
 ///
@@ -23,12 +32,18 @@ use std::net::IpAddr;
 /// - Integrated Layer 3 routing (IRB - Integrated Routing and Bridging)
 pub struct EvpnZone {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl EvpnZone {
     /// Create new EVPN zone
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new EVPN zone running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
     }
 
     /// Validate EVPN-specific configuration parameters
@@ -93,8 +108,11 @@ impl EvpnZone {
                     )
                 })?;
             }
-        } else {
-            anyhow::bail!("EVPN zone '{}' requires a VTEP IP address", self.name);
+        } else if !config.options.contains_key("fabric") {
+            anyhow::bail!(
+                "EVPN zone '{}' requires a VTEP IP address or a fabric",
+                self.name
+            );
         }
 
         // Validate MAC-VRF settings
@@ -106,6 +124,94 @@ impl EvpnZone {
             }
         }
 
+        // Validate symmetric IRB settings
+        if let Some(l3vni) = config.options.get("vrf-vxlan") {
+            match l3vni.as_u64() {
+                Some(l3vni) if (1..=16777215).contains(&l3vni) => {}
+                _ => anyhow::bail!(
+                    "EVPN zone '{}' vrf-vxlan must be a VNI between 1 and 16777215",
+                    self.name
+                ),
+            }
+
+            for interface in [
+                self.vrf_name(),
+                self.vrf_bridge_name(),
+                self.vrf_vxlan_name(),
+            ] {
+                if interface.len() > MAX_INTERFACE_NAME_LEN {
+                    anyhow::bail!(
+                        "EVPN zone name '{}' is too long, '{}' exceeds {} characters",
+                        self.name,
+                        interface,
+                        MAX_INTERFACE_NAME_LEN
+                    );
+                }
+            }
+        }
+
+        // Validate exit node settings
+        let exitnodes = list_option(config, "exitnodes");
+        if config.options.contains_key("exitnodes") && exitnodes.is_empty() {
+            anyhow::bail!("EVPN zone '{}' has an empty exitnodes list", self.name);
+        }
+
+        if (!exitnodes.is_empty() || bool_option(config, "advertise-subnets"))
+            && !config.options.contains_key("vrf-vxlan")
+        {
+            anyhow::bail!(
+                "EVPN zone '{}' requires vrf-vxlan for exitnodes and advertise-subnets",
+                self.name
+            );
+        }
+
+        if let Some(nodes) = &config.nodes {
+            if let Some(node) = exitnodes.iter().find(|n| !nodes.contains(n)) {
+                anyhow::bail!(
+                    "Exit node '{}' of EVPN zone '{}' is not in the zone's nodes",
+                    node,
+                    self.name
+                );
+            }
+        }
+
+        if let Some(primary) = config.options.get("exitnodes-primary") {
+            match primary.as_str() {
+                Some(primary) if exitnodes.iter().any(|n| n == primary) => {}
+                _ => anyhow::bail!(
+                    "exitnodes-primary of EVPN zone '{}' must be one of its exitnodes",
+                    self.name
+                ),
+            }
+        }
+
+        if bool_option(config, "exitnodes-local-routing") {
+            if exitnodes.is_empty() {
+                anyhow::bail!(
+                    "EVPN zone '{}' requires exitnodes for exitnodes-local-routing",
+                    self.name
+                );
+            }
+
+            let (xvrf, xvrfp) = self.local_routing_names();
+            for interface in [xvrf, xvrfp] {
+                if interface.len() > MAX_INTERFACE_NAME_LEN {
+                    anyhow::bail!(
+                        "EVPN zone name '{}' is too long, '{}' exceeds {} characters",
+                        self.name,
+                        interface,
+                        MAX_INTERFACE_NAME_LEN
+                    );
+                }
+            }
+        }
+
+        if let Some(mac) = config.options.get("mac") {
+            if !mac.as_str().map(is_valid_mac).unwrap_or(false) {
+                anyhow::bail!("EVPN zone '{}' has an invalid anycast MAC", self.name);
+            }
+        }
+
         // Validate advertise settings
         if let Some(advertise_pip) = config.options.get("advertise-pip") {
             if advertise_pip.as_bool().is_none() {
@@ -202,6 +308,347 @@ impl EvpnZone {
         Ok(())
     }
 
+    /// VRF of the zone
+    fn vrf_name(&self) -> String {
+        format!("vrf_{}", self.name)
+    }
+
+    /// Bridge carrying the L3VNI
+    fn vrf_bridge_name(&self) -> String {
+        format!("vrfbr_{}", self.name)
+    }
+
+    /// VXLAN device of the L3VNI
+    fn vrf_vxlan_name(&self) -> String {
+        format!("vrfvx_{}", self.name)
+    }
+
+    /// Anycast gateway MAC shared by all nodes
+    ///
+    /// Uses the zone's `mac` option, or a locally administered address
+    /// derived from the zone name so every node computes the same one.
+    fn anycast_mac(&self, config: &ZoneConfig) -> String {
+        if let Some(mac) = config.options.get("mac").and_then(|v| v.as_str()) {
+            return mac.to_lowercase();
+        }
+
+        let mut hash: u32 = 0x811c9dc5;
+        for byte in self.name.bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+        let bytes = hash.to_be_bytes();
+        format!(
+            "02:00:{:02x}:{:02x}:{:02x}:{:02x}",
+            bytes[0], bytes[1], bytes[2], bytes[3]
+        )
+    }
+
+    /// ASN of the controller referenced by the zone
+    fn controller_asn(&self, config: &ZoneConfig, sdn: &SdnConfiguration) -> Result<u32> {
+        let controller = config
+            .options
+            .get("controller")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+
+        sdn.controllers
+            .get(controller)
+            .and_then(|c| c.asn)
+            .with_context(|| {
+                format!(
+                    "EVPN zone '{}' references controller '{}' without an ASN",
+                    self.name, controller
+                )
+            })
+    }
+
+    /// VTEP address of the local node
+    ///
+    /// Zones using a fabric get it filled in by `resolve_fabric_vteps`; it is
+    /// missing if the fabric was not resolved for this node.
+    fn vtep_ip<'c>(&self, config: &'c ZoneConfig) -> Result<&'c str> {
+        config
+            .options
+            .get("vtep-ip")
+            .and_then(|v| v.as_str())
+            .with_context(
+                || match config.options.get("fabric").and_then(|f| f.as_str()) {
+                    Some(fabric) => format!(
+                    "EVPN zone '{}' has no VTEP address, fabric '{}' is not resolved for this node",
+                    self.name, fabric
+                ),
+                    None => format!("EVPN zone '{}' has no VTEP address", self.name),
+                },
+            )
+    }
+
+    /// Generate VRF, L3VNI and SVI interfaces for symmetric IRB
+    fn generate_irb_interfaces_config(
+        &self,
+        config: &ZoneConfig,
+        l3vni: u32,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<String> {
+        let vtep_ip = self.vtep_ip(config)?;
+        let vrf = self.vrf_name();
+        let vrf_bridge = self.vrf_bridge_name();
+        let vrf_vxlan = self.vrf_vxlan_name();
+        let mac = self.anycast_mac(config);
+        let mtu = config
+            .mtu
+            .map(|mtu| format!("\tmtu {}\n", mtu))
+            .unwrap_or_default();
+
+        let mut irb_config = format!(
+            "auto {vrf}\n\
+             iface {vrf}\n\
+             \tvrf-table auto\n\
+             \n\
+             auto {vrf_vxlan}\n\
+             iface {vrf_vxlan}\n\
+             \tvxlan-id {l3vni}\n\
+             \tvxlan-local-tunnelip {vtep_ip}\n\
+             \tbridge-learning off\n\
+             {mtu}\
+             \n\
+             auto {vrf_bridge}\n\
+             iface {vrf_bridge}\n\
+             \tbridge_ports {vrf_vxlan}\n\
+             \tbridge_stp off\n\
+             \tbridge_fd 0\n\
+             {mtu}\
+             \tvrf {vrf}\n",
+        );
+
+        for vnet in ctx.vnets(&self.name) {
+            let vni = vnet.vni().with_context(|| {
+                format!(
+                    "VNet '{}' in EVPN zone '{}' requires a VNI",
+                    vnet.vnet, self.name
+                )
+            })?;
+            let vnet_vxlan = format!("vxlan_{}", vnet.vnet);
+            let neigh_suppress = if vnet.neigh_suppress() { "on" } else { "off" };
+
+            irb_config.push_str(&format!(
+                "\n\
+                 auto {vnet_vxlan}\n\
+                 iface {vnet_vxlan}\n\
+                 \tvxlan-id {vni}\n\
+                 \tvxlan-local-tunnelip {vtep_ip}\n\
+                 \tbridge-learning off\n\
+                 \tbridge-arp-nd-suppress {neigh_suppress}\n",
+            ));
+
+            irb_config.push_str(&format!(
+                "{mtu}\
+                 \n\
+                 auto {bridge}\n\
+                 iface {bridge}\n",
+                bridge = vnet.vnet,
+            ));
+
+            for subnet in ctx.subnets(&vnet.vnet) {
+                if let Some(gateway) = subnet.gateway {
+                    irb_config.push_str(&format!(
+                        "\taddress {}/{}\n",
+                        gateway,
+                        subnet.cidr.prefix_len()
+                    ));
+                }
+            }
+
+            irb_config.push_str(&format!(
+                "\thwaddress {mac}\n\
+                 \tbridge_ports {vnet_vxlan}\n\
+                 \tbridge_stp off\n\
+                 \tbridge_fd 0\n\
+                 {mtu}\
+                 \tvrf {vrf}\n\
+                 \tip-forward on\n\
+                 \tip6-forward on\n\
+                 \tarp-accept on\n",
+            ));
+        }
+
+        Ok(irb_config)
+    }
+
+    /// Role of `node` for traffic leaving the zone
+    fn exit_role(&self, config: &ZoneConfig, node: &str) -> ExitRole {
+        if !list_option(config, "exitnodes").iter().any(|n| n == node) {
+            return ExitRole::None;
+        }
+
+        match config
+            .options
+            .get("exitnodes-primary")
+            .and_then(|v| v.as_str())
+        {
+            Some(primary) if primary != node => ExitRole::Backup,
+            _ => ExitRole::Primary,
+        }
+    }
+
+    /// Veth pair connecting the default VRF with the zone VRF
+    fn local_routing_names(&self) -> (String, String) {
+        (
+            format!("xvrf_{}", self.name),
+            format!("xvrfp_{}", self.name),
+        )
+    }
+
+    /// Route-map lowering the preference of routes from backup exit nodes
+    fn backup_route_map_name(&self) -> String {
+        format!("MAP_EXIT_BACKUP_{}", self.name.to_uppercase())
+    }
+
+    /// Generate the veth pair letting an exit node reach the zone VRF
+    fn generate_local_routing_interfaces(&self) -> String {
+        let vrf = self.vrf_name();
+        let (xvrf, xvrfp) = self.local_routing_names();
+
+        format!(
+            "\n\
+             auto {xvrf}\n\
+             iface {xvrf}\n\
+             \tlink-type veth\n\
+             \taddress {LOCAL_ROUTING_DEFAULT_IP}/30\n\
+             \tveth-peer-name {xvrfp}\n\
+             \n\
+             auto {xvrfp}\n\
+             iface {xvrfp}\n\
+             \tlink-type veth\n\
+             \taddress {LOCAL_ROUTING_VRF_IP}/30\n\
+             \tveth-peer-name {xvrf}\n\
+             \tvrf {vrf}\n",
+        )
+    }
+
+    /// Generate SNAT rules for the zone's subnets on an exit node
+    fn generate_snat_nftables(&self, ctx: &ZoneRenderContext<'_>) -> Option<String> {
+        let subnets: Vec<&SubnetConfig> = ctx
+            .vnets(&self.name)
+            .into_iter()
+            .flat_map(|vnet| ctx.subnets(&vnet.vnet))
+            .collect();
+
+        generate_snat_nftables(&subnets)
+    }
+
+    /// Generate the FRR VRF and per-VRF BGP instance for symmetric IRB
+    ///
+    /// Exit nodes redistribute the tenant subnets, originate a default route
+    /// into the zone and leak routes between the zone VRF and the default
+    /// VRF. Backup exit nodes advertise their routes with a worse metric.
+    fn generate_irb_frr_config(
+        &self,
+        config: &ZoneConfig,
+        l3vni: u32,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<String> {
+        let asn = self.controller_asn(config, ctx.sdn)?;
+        let vrf = self.vrf_name();
+        let role = self.exit_role(config, ctx.node);
+        let exit = role != ExitRole::None;
+        let leak = exit || bool_option(config, "vrf-leak-default");
+        let redistribute = exit || bool_option(config, "advertise-subnets");
+
+        let mut frr_config = format!(
+            "!\n\
+             vrf {vrf}\n\
+             \tvni {l3vni}\n\
+             exit-vrf\n\
+             !\n",
+        );
+
+        let advertise_suffix = if role == ExitRole::Backup {
+            let route_map = self.backup_route_map_name();
+            frr_config.push_str(&format!(
+                "route-map {route_map} permit 1\n\
+                 \tset metric 200\n\
+                 exit\n\
+                 !\n",
+            ));
+            format!(" route-map {}", route_map)
+        } else {
+            String::new()
+        };
+
+        let mut unicast = String::new();
+        if redistribute {
+            unicast.push_str("\t\tredistribute connected\n");
+        }
+        if leak {
+            unicast.push_str("\t\timport vrf default\n");
+        }
+
+        frr_config.push_str(&format!(
+            "router bgp {asn} vrf {vrf}\n\
+             \tno bgp ebgp-requires-policy\n\
+             \t!\n",
+        ));
+        for af in ["ipv4", "ipv6"] {
+            if !unicast.is_empty() {
+                frr_config.push_str(&format!(
+                    "\taddress-family {af} unicast\n\
+                     {unicast}\
+                     \texit-address-family\n\
+                     \t!\n",
+                ));
+            }
+        }
+        frr_config.push_str(&format!(
+            "\taddress-family l2vpn evpn\n\
+             \t\tadvertise ipv4 unicast{advertise_suffix}\n\
+             \t\tadvertise ipv6 unicast{advertise_suffix}\n",
+        ));
+        if exit {
+            frr_config.push_str(
+                "\t\tdefault-originate ipv4\n\
+                 \t\tdefault-originate ipv6\n",
+            );
+        }
+        frr_config.push_str(
+            "\texit-address-family\n\
+             exit\n\
+             !\n",
+        );
+
+        // Leak the tenant routes into the default VRF
+        if leak {
+            frr_config.push_str(&format!(
+                "router bgp {asn}\n\
+                 \taddress-family ipv4 unicast\n\
+                 \t\timport vrf {vrf}\n\
+                 \texit-address-family\n\
+                 \t!\n\
+                 \taddress-family ipv6 unicast\n\
+                 \t\timport vrf {vrf}\n\
+                 \texit-address-family\n\
+                 exit\n\
+                 !\n",
+            ));
+        }
+
+        // Route locally originated traffic into the VRF through the veth pair
+        if exit && bool_option(config, "exitnodes-local-routing") {
+            let (xvrf, _) = self.local_routing_names();
+            for vnet in ctx.vnets(&self.name) {
+                for subnet in ctx.subnets(&vnet.vnet) {
+                    if let IpNet::V4(cidr) = subnet.cidr {
+                        frr_config
+                            .push_str(&format!("ip route {cidr} {LOCAL_ROUTING_VRF_IP} {xvrf}\n",));
+                    }
+                }
+            }
+            frr_config.push_str("!\n");
+        }
+
+        Ok(frr_config)
+    }
+
     /// Get VXLAN interface name for EVPN
     fn get_vxlan_interface_name(&self, vni: u32) -> String {
         format!("vxlan{}", vni)
@@ -212,7 +659,7 @@ impl EvpnZone {
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let vxlan_interface = self.get_vxlan_interface_name(vni);
         let vxlan_port = config.vxlan_port.unwrap_or(4789);
-        let vtep_ip = config.options.get("vtep-ip").unwrap().as_str().unwrap();
+        let vtep_ip = self.vtep_ip(config)?;
 
         let mut vxlan_config = format!(
             "auto {vxlan_interface}\n\
@@ -233,6 +680,8 @@ impl EvpnZone {
             vxlan_config.push_str(&format!("\tmtu {}\n", mtu));
         }
 
+        vxlan_config.push_str(&owner_alias_attribute(config));
+
         // EVPN-specific settings
         vxlan_config.push_str("\t# EVPN settings\n");
         vxlan_config.push_str("\tvxlan-ageing 0\n"); // Disable MAC aging for EVPN
@@ -263,6 +712,8 @@ impl EvpnZone {
             bridge_config.push_str(&format!("\tmtu {}\n", mtu));
         }
 
+        bridge_config.push_str(&owner_alias_attribute(config));
+
         // EVPN-specific bridge settings
         bridge_config.push_str("\t# EVPN bridge settings\n");
         bridge_config.push_str("\tbridge_ageing 0\n"); // Disable MAC aging
@@ -274,7 +725,7 @@ impl EvpnZone {
     fn generate_frr_config(&self, config: &ZoneConfig) -> Result<String> {
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let rd = config.options.get("rd").unwrap().as_str().unwrap();
-        let vtep_ip = config.options.get("vtep-ip").unwrap().as_str().unwrap();
+        let vtep_ip = self.vtep_ip(config)?;
 
         let mut frr_config = format!(
             "!\n\
@@ -345,7 +796,7 @@ impl EvpnZone {
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let vxlan_interface = self.get_vxlan_interface_name(vni);
         let vxlan_port = config.vxlan_port.unwrap_or(4789);
-        let vtep_ip = config.options.get("vtep-ip").unwrap().as_str().unwrap();
+        let vtep_ip = self.vtep_ip(config)?;
 
         let systemd_config = format!(
             "[NetDev]\n\
@@ -412,14 +863,14 @@ impl Zone for EvpnZone {
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let vxlan_interface = self.get_vxlan_interface_name(vni);
         let vxlan_port = config.vxlan_port.unwrap_or(4789);
-        let vtep_ip = config.options.get("vtep-ip").unwrap().as_str().unwrap();
+        let vtep_ip = self.vtep_ip(config)?;
 
         // Check if VXLAN interface already exists
-        let interface_exists = tokio::process::Command::new("ip")
-            .args(&["link", "show", &vxlan_interface])
-            .output()
+        let interface_exists = self
+            .executor
+            .query("ip", &["link", "show", &vxlan_interface])
             .await
-            .map(|output| output.status.success())
+            .map(|output| output.is_success())
             .unwrap_or(false);
 
         if !interface_exists {
@@ -446,9 +897,9 @@ impl Zone for EvpnZone {
                 "proxy",      // Enable ARP/ND proxy
             ];
 
-            let output = tokio::process::Command::new("ip")
-                .args(&cmd_args)
-                .output()
+            let output = self
+                .executor
+                .execute("ip", &cmd_args)
                 .await
                 .with_context(|| {
                     format!(
@@ -457,8 +908,8 @@ impl Zone for EvpnZone {
                     )
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to create EVPN VXLAN interface '{}': {}",
                     vxlan_interface,
@@ -466,11 +917,16 @@ impl Zone for EvpnZone {
                 );
             }
 
+            mark_owned(self.executor.as_ref(), &vxlan_interface, config).await?;
+
             // Set MTU if specified
             if let Some(mtu) = config.mtu {
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", &vxlan_interface, "mtu", &mtu.to_string()])
-                    .output()
+                let output = self
+                    .executor
+                    .execute(
+                        "ip",
+                        &["link", "set", &vxlan_interface, "mtu", &mtu.to_string()],
+                    )
                     .await
                     .with_context(|| {
                         format!(
@@ -479,8 +935,8 @@ impl Zone for EvpnZone {
                         )
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     warn!(
                         "Failed to set MTU {} on EVPN VXLAN interface '{}': {}",
                         mtu, vxlan_interface, stderr
@@ -489,22 +945,25 @@ impl Zone for EvpnZone {
             }
 
             // Disable MAC aging for EVPN
-            let output = tokio::process::Command::new("ip")
-                .args(&[
-                    "link",
-                    "set",
-                    &vxlan_interface,
-                    "type",
-                    "vxlan",
-                    "ageing",
-                    "0",
-                ])
-                .output()
+            let output = self
+                .executor
+                .execute(
+                    "ip",
+                    &[
+                        "link",
+                        "set",
+                        &vxlan_interface,
+                        "type",
+                        "vxlan",
+                        "ageing",
+                        "0",
+                    ],
+                )
                 .await;
 
             if let Ok(output) = output {
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     warn!(
                         "Failed to disable MAC aging on EVPN VXLAN interface '{}': {}",
                         vxlan_interface, stderr
@@ -513,9 +972,9 @@ impl Zone for EvpnZone {
             }
 
             // Bring interface up
-            let output = tokio::process::Command::new("ip")
-                .args(&["link", "set", &vxlan_interface, "up"])
-                .output()
+            let output = self
+                .executor
+                .execute("ip", &["link", "set", &vxlan_interface, "up"])
                 .await
                 .with_context(|| {
                     format!(
@@ -524,8 +983,8 @@ impl Zone for EvpnZone {
                     )
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to bring up EVPN VXLAN interface '{}': {}",
                     vxlan_interface,
@@ -536,19 +995,19 @@ impl Zone for EvpnZone {
 
         // Create bridge if specified
         if let Some(bridge) = &config.bridge {
-            let bridge_exists = tokio::process::Command::new("ip")
-                .args(&["link", "show", bridge])
-                .output()
+            let bridge_exists = self
+                .executor
+                .query("ip", &["link", "show", bridge])
                 .await
-                .map(|output| output.status.success())
+                .map(|output| output.is_success())
                 .unwrap_or(false);
 
             if !bridge_exists {
                 info!("Creating bridge '{}' for EVPN zone '{}'", bridge, self.name);
 
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "add", "name", bridge, "type", "bridge"])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "add", "name", bridge, "type", "bridge"])
                     .await
                     .with_context(|| {
                         format!(
@@ -557,15 +1016,17 @@ impl Zone for EvpnZone {
                         )
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     anyhow::bail!("Failed to create bridge '{}': {}", bridge, stderr);
                 }
 
+                mark_owned(self.executor.as_ref(), bridge, config).await?;
+
                 // Add VXLAN interface to bridge
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", &vxlan_interface, "master", bridge])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "set", &vxlan_interface, "master", bridge])
                     .await
                     .with_context(|| {
                         format!(
@@ -574,8 +1035,8 @@ impl Zone for EvpnZone {
                         )
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     anyhow::bail!(
                         "Failed to add EVPN VXLAN interface '{}' to bridge '{}': {}",
                         vxlan_interface,
@@ -585,24 +1046,27 @@ impl Zone for EvpnZone {
                 }
 
                 // Enable VLAN filtering
-                let output = tokio::process::Command::new("ip")
-                    .args(&[
-                        "link",
-                        "set",
-                        bridge,
-                        "type",
-                        "bridge",
-                        "vlan_filtering",
-                        "1",
-                    ])
-                    .output()
+                let output = self
+                    .executor
+                    .execute(
+                        "ip",
+                        &[
+                            "link",
+                            "set",
+                            bridge,
+                            "type",
+                            "bridge",
+                            "vlan_filtering",
+                            "1",
+                        ],
+                    )
                     .await
                     .with_context(|| {
                         format!("Failed to enable VLAN filtering on bridge '{}'", bridge)
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     warn!(
                         "Failed to enable VLAN filtering on bridge '{}': {}",
                         bridge, stderr
@@ -610,14 +1074,17 @@ impl Zone for EvpnZone {
                 }
 
                 // Disable MAC aging on bridge for EVPN
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", bridge, "type", "bridge", "ageing_time", "0"])
-                    .output()
+                let output = self
+                    .executor
+                    .execute(
+                        "ip",
+                        &["link", "set", bridge, "type", "bridge", "ageing_time", "0"],
+                    )
                     .await;
 
                 if let Ok(output) = output {
-                    if !output.status.success() {
-                        let stderr = String::from_utf8_lossy(&output.stderr);
+                    if !output.is_success() {
+                        let stderr = output.stderr.trim();
                         warn!(
                             "Failed to disable MAC aging on bridge '{}': {}",
                             bridge, stderr
@@ -626,14 +1093,14 @@ impl Zone for EvpnZone {
                 }
 
                 // Bring bridge up
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", bridge, "up"])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "set", bridge, "up"])
                     .await
                     .with_context(|| format!("Failed to bring up bridge '{}'", bridge))?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     anyhow::bail!("Failed to bring up bridge '{}': {}", bridge, stderr);
                 }
             }
@@ -697,7 +1164,7 @@ impl Zone for EvpnZone {
         // Generate zone-specific metadata
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let rd = config.options.get("rd").unwrap().as_str().unwrap();
-        let vtep_ip = config.options.get("vtep-ip").unwrap().as_str().unwrap();
+        let vtep_ip = self.vtep_ip(config)?;
 
         let metadata = format!(
             "# EVPN Zone Configuration\n\
@@ -741,6 +1208,178 @@ impl Zone for EvpnZone {
         );
         Ok(configs)
     }
+
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        let mut configs = self.generate_config(config).await?;
+
+        let l3vni = match config.options.get("vrf-vxlan").and_then(|v| v.as_u64()) {
+            Some(l3vni) => l3vni as u32,
+            None => return Ok(configs),
+        };
+
+        ctx.sdn.validate_vni_uniqueness()?;
+
+        let irb_config = self
+            .generate_irb_interfaces_config(config, l3vni, ctx)
+            .with_context(|| {
+                format!(
+                    "Failed to generate IRB interfaces for EVPN zone '{}'",
+                    self.name
+                )
+            })?;
+        configs.insert("irb".to_string(), irb_config);
+
+        let irb_frr = self.generate_irb_frr_config(config, l3vni, ctx)?;
+        configs
+            .entry("frr".to_string())
+            .or_default()
+            .push_str(&irb_frr);
+
+        if self.exit_role(config, ctx.node) != ExitRole::None {
+            debug!("Node {} is an exit node of zone '{}'", ctx.node, self.name);
+
+            if bool_option(config, "exitnodes-local-routing") {
+                configs
+                    .entry("irb".to_string())
+                    .or_default()
+                    .push_str(&self.generate_local_routing_interfaces());
+            }
+
+            if let Some(nftables) = self.generate_snat_nftables(ctx) {
+                configs.insert("nftables".to_string(), nftables);
+            }
+        }
+
+        Ok(configs)
+    }
+
+    async fn remove_config(&self, config: &ZoneConfig) -> Result<()> {
+        let state = self.observed_state(config).await?;
+        let removed = remove_owned_devices(self.executor.as_ref(), &state).await?;
+
+        info!("Removed devices {:?} of EVPN zone '{}'", removed, self.name);
+        Ok(())
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        if let Some(vni) = config.options.get("vni").and_then(|v| v.as_u64()) {
+            let vxlan_interface = self.get_vxlan_interface_name(vni as u32);
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), &vxlan_interface, "vxlan").await?);
+        }
+
+        if let Some(bridge) = &config.bridge {
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), bridge, "bridge").await?);
+        }
+
+        Ok(state)
+    }
+
+    async fn observed_vnet_state(
+        &self,
+        config: &ZoneConfig,
+        vnet: &VNetConfig,
+    ) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        // Per-vnet devices are only rendered for zones with IRB
+        if config.options.contains_key("vrf-vxlan") {
+            let vnet_vxlan = format!("vxlan_{}", vnet.vnet);
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), &vnet_vxlan, "vxlan").await?);
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), &vnet.vnet, "bridge").await?);
+        }
+
+        Ok(state)
+    }
+}
+
+/// Maximum length of a Linux interface name
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// Default VRF side of the exit node local routing veth pair
+const LOCAL_ROUTING_DEFAULT_IP: &str = "10.255.255.1";
+
+/// Zone VRF side of the exit node local routing veth pair
+const LOCAL_ROUTING_VRF_IP: &str = "10.255.255.2";
+
+/// Role of a node for traffic leaving an EVPN zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitRole {
+    /// Not an exit node
+    None,
+    /// Exit node, preferred when a primary is configured
+    Primary,
+    /// Exit node standing in for the configured primary
+    Backup,
+}
+
+fn bool_option(config: &ZoneConfig, key: &str) -> bool {
+    config
+        .options
+        .get(key)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// List option given either as array or as comma separated string
+fn list_option(config: &ZoneConfig, key: &str) -> Vec<String> {
+    match config.options.get(key) {
+        Some(serde_json::Value::String(list)) => list
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_valid_mac(mac: &str) -> bool {
+    let parts: Vec<&str> = mac.split(':').collect();
+    parts.len() == 6
+        && parts
+            .iter()
+            .all(|part| part.len() == 2 && part.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// VNIs of an EVPN zone: its L3VNI followed by the L2 VNIs of its vnets
+pub fn zone_vnis(sdn: &SdnConfiguration, zone: &str) -> Vec<u32> {
+    let mut vnis: Vec<u32> = sdn
+        .vnets
+        .values()
+        .filter(|vnet| vnet.zone == zone)
+        .filter_map(VNetConfig::vni)
+        .collect();
+    vnis.sort_unstable();
+
+    if let Some(l3vni) = sdn
+        .zones
+        .get(zone)
+        .and_then(|z| z.options.get("vrf-vxlan"))
+        .and_then(|v| v.as_u64())
+    {
+        vnis.insert(0, l3vni as u32);
+    }
+
+    vnis
 }
 
 #[cfg(test)]
@@ -837,6 +1476,26 @@ mod tests {
         assert!(frr_config.contains("route-target export 65000:100"));
     }
 
+    #[tokio::test]
+    async fn test_evpn_unresolved_fabric_vtep() {
+        let zone = EvpnZone::new("test-evpn".to_string());
+
+        let mut config = ZoneConfig::new(ZoneType::Evpn, "test-evpn".to_string());
+        config.options.insert("vni".to_string(), json!(100));
+        config.options.insert("rd".to_string(), json!("65000:100"));
+        config.options.insert("fabric".to_string(), json!("f1"));
+        config
+            .options
+            .insert("controller".to_string(), json!("evpn1"));
+        config.bridge = Some("vmbr0".to_string());
+
+        // Fabric VTEPs are only filled in for the local node, an unresolved
+        // one is an error rather than a panic
+        assert!(zone.validate_config(&config).await.is_ok());
+        let error = zone.generate_config(&config).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("fabric 'f1' is not resolved"));
+    }
+
     #[test]
     fn test_route_distinguisher_validation() {
         let zone = EvpnZone::new("test".to_string());
@@ -867,4 +1526,211 @@ mod tests {
         assert!(zone.validate_route_target("invalid").is_err());
         assert!(zone.validate_route_target("65000:100 invalid").is_err());
     }
+
+    fn irb_sdn_config() -> SdnConfiguration {
+        use pve_sdn_core::controller::ControllerConfig;
+        use pve_sdn_core::ControllerType;
+
+        let mut sdn = SdnConfiguration::new();
+
+        let mut config = ZoneConfig::new(ZoneType::Evpn, "tenant1".to_string());
+        config.options.insert("vni".to_string(), json!(100));
+        config.options.insert("rd".to_string(), json!("65000:100"));
+        config
+            .options
+            .insert("vtep-ip".to_string(), json!("192.168.1.1"));
+        config
+            .options
+            .insert("controller".to_string(), json!("evpn1"));
+        config.options.insert("vrf-vxlan".to_string(), json!(10000));
+        config
+            .options
+            .insert("mac".to_string(), json!("BC:24:11:00:00:01"));
+        config.bridge = Some("vmbr0".to_string());
+        sdn.zones.insert("tenant1".to_string(), config);
+
+        let mut controller = ControllerConfig::new(ControllerType::Evpn, "evpn1".to_string());
+        controller.asn = Some(65000);
+        sdn.controllers.insert("evpn1".to_string(), controller);
+
+        for (vnet, vni, cidr, gateway) in [
+            ("vnet1", 11000, "10.0.1.0/24", "10.0.1.1"),
+            ("vnet2", 12000, "2001:db8::/64", "2001:db8::1"),
+        ] {
+            let mut vnet_config = VNetConfig::new(vnet.to_string(), "tenant1".to_string());
+            vnet_config.options.insert("vni".to_string(), json!(vni));
+            sdn.vnets.insert(vnet.to_string(), vnet_config);
+
+            let mut subnet =
+                SubnetConfig::new(cidr.to_string(), vnet.to_string(), cidr.parse().unwrap());
+            subnet.gateway = Some(gateway.parse().unwrap());
+            sdn.subnets.insert(cidr.to_string(), subnet);
+        }
+
+        sdn
+    }
+
+    #[tokio::test]
+    async fn test_evpn_symmetric_irb_generation() {
+        let mut sdn = irb_sdn_config();
+        sdn.zones
+            .get_mut("tenant1")
+            .unwrap()
+            .options
+            .insert("vrf-leak-default".to_string(), json!(true));
+
+        let zone = EvpnZone::new("tenant1".to_string());
+        let config = &sdn.zones["tenant1"];
+        let ctx = ZoneRenderContext::new("node1", &sdn);
+
+        assert!(zone.validate_config(config).await.is_ok());
+        let configs = zone.generate_node_config(config, &ctx).await.unwrap();
+
+        let irb = &configs["irb"];
+        assert!(irb.contains("iface vrf_tenant1\n\tvrf-table auto\n"));
+        assert!(irb.contains("iface vrfvx_tenant1\n\tvxlan-id 10000\n"));
+        assert!(irb.contains("\tbridge_ports vrfvx_tenant1\n"));
+        assert!(irb.contains("iface vxlan_vnet1\n\tvxlan-id 11000\n"));
+        assert!(irb.contains("iface vnet1\n\taddress 10.0.1.1/24\n\thwaddress bc:24:11:00:00:01\n"));
+        assert!(irb.contains("iface vnet2\n\taddress 2001:db8::1/64\n"));
+        assert_eq!(irb.matches("\tvrf vrf_tenant1\n").count(), 3);
+
+        let frr = &configs["frr"];
+        assert!(frr.contains("vrf vrf_tenant1\n\tvni 10000\nexit-vrf\n"));
+        assert!(frr.contains("router bgp 65000 vrf vrf_tenant1\n"));
+        assert!(frr.contains("\t\tadvertise ipv4 unicast\n\t\tadvertise ipv6 unicast\n"));
+        assert!(frr.contains("\t\timport vrf default\n"));
+        assert!(frr.contains("\t\timport vrf vrf_tenant1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_evpn_vnet_port_isolation_and_neigh_suppress() {
+        let mut sdn = irb_sdn_config();
+        let vnet1 = sdn.vnets.get_mut("vnet1").unwrap();
+        vnet1.isolate_ports = Some(true);
+        vnet1.neigh_suppress = Some(false);
+
+        let zone = EvpnZone::new("tenant1".to_string());
+        let ctx = ZoneRenderContext::new("node1", &sdn);
+        let configs = zone
+            .generate_node_config(&sdn.zones["tenant1"], &ctx)
+            .await
+            .unwrap();
+
+        let irb = &configs["irb"];
+        assert!(irb.contains(
+            "iface vxlan_vnet1\n\tvxlan-id 11000\n\tvxlan-local-tunnelip 192.168.1.1\n\
+             \tbridge-learning off\n\tbridge-arp-nd-suppress off\n"
+        ));
+        assert!(irb.contains("\tbridge-learning off\n\tbridge-arp-nd-suppress on\n\nauto vnet2\n"));
+
+        // Only guest ports are isolated, remote guests stay behind the VXLAN port
+        assert!(!irb.contains("bridge-port-isolation"));
+    }
+
+    #[tokio::test]
+    async fn test_evpn_without_irb_has_no_vrf() {
+        let mut sdn = irb_sdn_config();
+        sdn.zones
+            .get_mut("tenant1")
+            .unwrap()
+            .options
+            .remove("vrf-vxlan");
+
+        let zone = EvpnZone::new("tenant1".to_string());
+        let config = &sdn.zones["tenant1"];
+        let configs = zone
+            .generate_node_config(config, &ZoneRenderContext::new("node1", &sdn))
+            .await
+            .unwrap();
+
+        assert!(!configs.contains_key("irb"));
+        assert!(!configs["frr"].contains("vrf_tenant1"));
+    }
+
+    #[tokio::test]
+    async fn test_evpn_anycast_mac_is_stable() {
+        let mut sdn = irb_sdn_config();
+        sdn.zones.get_mut("tenant1").unwrap().options.remove("mac");
+
+        let zone = EvpnZone::new("tenant1".to_string());
+        let config = &sdn.zones["tenant1"];
+        let mac = zone.anycast_mac(config);
+
+        assert_eq!(
+            mac,
+            EvpnZone::new("tenant1".to_string()).anycast_mac(config)
+        );
+        assert!(is_valid_mac(&mac));
+        assert!(mac.starts_with("02:"));
+
+        let mut invalid = config.clone();
+        invalid
+            .options
+            .insert("mac".to_string(), json!("bc:24:11:00:00"));
+        assert!(zone.validate_config(&invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_vni_and_vrf_uniqueness() {
+        let sdn = irb_sdn_config();
+        assert!(sdn.validate_vni_uniqueness().is_ok());
+
+        // L3VNI clashing with a vnet VNI
+        let mut clash = sdn.clone();
+        clash
+            .zones
+            .get_mut("tenant1")
+            .unwrap()
+            .options
+            .insert("vrf-vxlan".to_string(), json!(11000));
+        let err = clash.validate_vni_uniqueness().unwrap_err().to_string();
+        assert!(err.contains("VNI 11000"));
+
+        // Two zones sharing a VRF VNI
+        let mut clash = sdn.clone();
+        let mut other = clash.zones["tenant1"].clone();
+        other.zone = "tenant2".to_string();
+        other.options.insert("vni".to_string(), json!(200));
+        clash.zones.insert("tenant2".to_string(), other);
+        assert!(clash.validate_vni_uniqueness().is_err());
+
+        let zone = EvpnZone::new("tenant1".to_string());
+        let result = zone
+            .generate_node_config(
+                &clash.zones["tenant1"],
+                &ZoneRenderContext::new("node1", &clash),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_zone_vnis() {
+        let mut sdn = irb_sdn_config();
+        assert_eq!(zone_vnis(&sdn, "tenant1"), vec![10000, 11000, 12000]);
+
+        sdn.zones
+            .get_mut("tenant1")
+            .unwrap()
+            .options
+            .remove("vrf-vxlan");
+        assert_eq!(zone_vnis(&sdn, "tenant1"), vec![11000, 12000]);
+        assert!(zone_vnis(&sdn, "missing").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_evpn_irb_requires_controller_asn() {
+        let mut sdn = irb_sdn_config();
+        sdn.controllers.get_mut("evpn1").unwrap().asn = None;
+
+        let zone = EvpnZone::new("tenant1".to_string());
+        let result = zone
+            .generate_node_config(
+                &sdn.zones["tenant1"],
+                &ZoneRenderContext::new("node1", &sdn),
+            )
+            .await;
+        assert!(result.is_err());
+    }
 }
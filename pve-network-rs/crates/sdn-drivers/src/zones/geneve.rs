@@ -0,0 +1,944 @@
+//! Geneve zone driver
+//!
+//! Geneve zones provide Layer 2 overlay networks like VXLAN zones, using the
+//! Geneve encapsulation (RFC 8926) spoken by OVN-based deployments.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use pve_sdn_core::reconciler::{
+    alias_owner, delete_device, list_links, mark_owned, observe_device, owner_alias_attribute,
+    remove_owned_devices, stable_hash,
+};
+use pve_sdn_core::{
+    RealExecutor, SystemExecutor, Zone, ZoneConfig, ZoneObservedState, ZoneRenderContext, ZoneType,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use super::vxlan::parse_local_addresses;
+
+/// Default Geneve UDP port
+const DEFAULT_GENEVE_PORT: u16 = 6081;
+
+/// MTU of the underlay assumed when the zone sets none
+const DEFAULT_UNDERLAY_MTU: u16 = 1500;
+
+/// Geneve encapsulation overhead over an IPv4 underlay, without options
+pub const GENEVE_IPV4_OVERHEAD: u16 = 50;
+
+/// Geneve encapsulation overhead over an IPv6 underlay, without options
+pub const GENEVE_IPV6_OVERHEAD: u16 = 70;
+
+/// Maximum length of the option TLVs in a Geneve header
+pub const GENEVE_MAX_TLV_LENGTH: u16 = 252;
+
+/// Remote address of the Geneve link in `ip -j -d link show dev` output
+fn parse_tunnel_remote(json: &str) -> Option<IpAddr> {
+    let links: Vec<serde_json::Value> = serde_json::from_str(json).ok()?;
+    let info = links.first()?.get("linkinfo")?.get("info_data")?;
+    info.get("remote")
+        .or_else(|| info.get("remote6"))?
+        .as_str()?
+        .parse()
+        .ok()
+}
+
+/// Encapsulation overhead for the given underlay family and option length
+pub fn geneve_overhead(ipv6_underlay: bool, tlv_length: u16) -> u16 {
+    let base = if ipv6_underlay {
+        GENEVE_IPV6_OVERHEAD
+    } else {
+        GENEVE_IPV4_OVERHEAD
+    };
+    base + tlv_length
+}
+
+/// Geneve zone implementation
+///
+/// Geneve zones create Layer 2 overlay networks using Geneve encapsulation.
+/// Key features:
+/// - 24-bit Virtual Network Identifier (VNI) for network isolation
+/// - UDP encapsulation (default port 6081)
+/// - One tunnel per remote peer, isolated from each other on the zone bridge
+/// - Room for option TLVs (`tlv-length`) in the MTU, e.g. 8 bytes for OVN
+pub struct GeneveZone {
+    name: String,
+    executor: Arc<dyn SystemExecutor>,
+}
+
+impl GeneveZone {
+    /// Create new Geneve zone
+    pub fn new(name: String) -> Self {
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new Geneve zone running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
+    }
+
+    fn ip_option(config: &ZoneConfig, key: &str) -> Option<IpAddr> {
+        config.options.get(key)?.as_str()?.parse().ok()
+    }
+
+    fn vni(config: &ZoneConfig) -> Option<u32> {
+        config
+            .options
+            .get("vni")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+    }
+
+    fn geneve_port(config: &ZoneConfig) -> u16 {
+        config
+            .options
+            .get("geneve-port")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u16)
+            .unwrap_or(DEFAULT_GENEVE_PORT)
+    }
+
+    fn tlv_length(config: &ZoneConfig) -> u16 {
+        config
+            .options
+            .get("tlv-length")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u16)
+            .unwrap_or(0)
+    }
+
+    fn peer_addresses(config: &ZoneConfig) -> Vec<IpAddr> {
+        config
+            .peers
+            .iter()
+            .flatten()
+            .filter_map(|peer| peer.parse().ok())
+            .collect()
+    }
+
+    /// Whether the zone runs over an IPv6 underlay
+    ///
+    /// Determined by `local-ip`, then the peers.
+    fn is_ipv6_underlay(config: &ZoneConfig) -> bool {
+        Self::ip_option(config, "local-ip")
+            .or_else(|| Self::peer_addresses(config).first().copied())
+            .map(|addr| addr.is_ipv6())
+            .unwrap_or(false)
+    }
+
+    /// MTU of the tunnels and the bridge
+    ///
+    /// Defaults to the underlay MTU minus the encapsulation overhead.
+    fn geneve_mtu(config: &ZoneConfig) -> u16 {
+        config.mtu.unwrap_or_else(|| {
+            DEFAULT_UNDERLAY_MTU
+                - geneve_overhead(Self::is_ipv6_underlay(config), Self::tlv_length(config))
+        })
+    }
+
+    /// Peers a tunnel is created to
+    ///
+    /// The zone `peers` usually list every node including the local one, so
+    /// `local-ip` and the addresses in `local` are left out.
+    fn remote_peers(config: &ZoneConfig, local: &[IpAddr]) -> Vec<IpAddr> {
+        let local_ip = Self::ip_option(config, "local-ip");
+
+        let mut peers: Vec<IpAddr> = Vec::new();
+        for peer in Self::peer_addresses(config) {
+            if Some(peer) != local_ip && !local.contains(&peer) && !peers.contains(&peer) {
+                peers.push(peer);
+            }
+        }
+        peers
+    }
+
+    /// Addresses configured on the node, empty if they cannot be queried
+    async fn local_addresses(&self) -> Vec<IpAddr> {
+        match self.executor.query("ip", &["-j", "addr", "show"]).await {
+            Ok(output) if output.is_success() => parse_local_addresses(&output.stdout),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Addresses of this node to leave out of the peers
+    ///
+    /// `local-ip` is left out anyway; without it the peer configured on this
+    /// node is found among the addresses of the node.
+    async fn own_addresses(&self, config: &ZoneConfig) -> Vec<IpAddr> {
+        if config.options.contains_key("local-ip") {
+            Vec::new()
+        } else {
+            self.local_addresses().await
+        }
+    }
+
+    /// Validate Geneve-specific configuration parameters
+    fn validate_geneve_config(&self, config: &ZoneConfig) -> Result<()> {
+        // Geneve has no multicast mode, tunnels go to the peers
+        if config.peers.as_ref().map(|p| p.is_empty()).unwrap_or(true)
+            && !config.options.contains_key("fabric")
+        {
+            anyhow::bail!("Geneve zone '{}' requires peers or a fabric", self.name);
+        }
+
+        // Tunnels are joined on the zone bridge
+        if config.bridge.is_none() {
+            anyhow::bail!("Geneve zone '{}' requires a bridge", self.name);
+        }
+
+        // Validate Geneve port
+        if let Some(port) = config.options.get("geneve-port") {
+            match port.as_u64() {
+                Some(port) if port > 0 && port <= u16::MAX as u64 => {}
+                _ => anyhow::bail!(
+                    "Geneve zone '{}' port must be between 1 and 65535",
+                    self.name
+                ),
+            }
+        }
+
+        // Validate VNI (Virtual Network Identifier)
+        if let Some(vni) = config.options.get("vni") {
+            if let Some(vni_num) = vni.as_u64() {
+                if vni_num == 0 || vni_num > 16777215 {
+                    anyhow::bail!(
+                        "Geneve zone '{}' VNI must be between 1 and 16777215",
+                        self.name
+                    );
+                }
+            } else {
+                anyhow::bail!("Geneve zone '{}' VNI must be a number", self.name);
+            }
+        } else {
+            anyhow::bail!(
+                "Geneve zone '{}' requires a VNI (Virtual Network Identifier)",
+                self.name
+            );
+        }
+
+        // Option TLVs come in 4 byte units, at most 63 of them
+        if let Some(length) = config.options.get("tlv-length") {
+            match length.as_u64() {
+                Some(length) if length % 4 == 0 && length <= GENEVE_MAX_TLV_LENGTH as u64 => {}
+                _ => anyhow::bail!(
+                    "Geneve zone '{}' TLV length must be a multiple of 4 up to {}",
+                    self.name,
+                    GENEVE_MAX_TLV_LENGTH
+                ),
+            }
+        }
+
+        // Validate peers if specified
+        if let Some(peers) = &config.peers {
+            for peer in peers {
+                let _addr: IpAddr = peer.parse().with_context(|| {
+                    format!(
+                        "Invalid peer address '{}' for Geneve zone '{}'",
+                        peer, self.name
+                    )
+                })?;
+            }
+        }
+
+        // Validate local IP if specified
+        if let Some(local_ip) = config.options.get("local-ip") {
+            if let Some(local_str) = local_ip.as_str() {
+                let _addr: IpAddr = local_str.parse().with_context(|| {
+                    format!(
+                        "Invalid local IP '{}' for Geneve zone '{}'",
+                        local_str, self.name
+                    )
+                })?;
+            }
+        }
+
+        // The underlay must be a single address family
+        let ipv6 = Self::is_ipv6_underlay(config);
+        let underlay = Self::ip_option(config, "local-ip")
+            .into_iter()
+            .chain(Self::peer_addresses(config));
+        for addr in underlay {
+            if addr.is_ipv6() != ipv6 {
+                anyhow::bail!(
+                    "Geneve zone '{}' mixes IPv4 and IPv6 underlay addresses ({})",
+                    self.name,
+                    addr
+                );
+            }
+        }
+
+        // Validate MTU considerations for Geneve overhead
+        if let Some(mtu) = config.mtu {
+            let overhead = geneve_overhead(ipv6, Self::tlv_length(config));
+            if mtu > DEFAULT_UNDERLAY_MTU - overhead {
+                warn!(
+                    "Geneve zone '{}' MTU {} needs an underlay MTU of at least {} ({} bytes Geneve overhead)",
+                    self.name,
+                    mtu,
+                    mtu + overhead,
+                    overhead
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the name of the tunnel with `vni` to `peer`
+    ///
+    /// Derived from the VNI and the peer address only, so a tunnel keeps its
+    /// name when other peers come and go. Both do not fit into the 15
+    /// characters of an interface name, hence the hash.
+    fn get_geneve_interface_name(&self, vni: u32, peer: &IpAddr) -> String {
+        let hash = stable_hash(&format!("{}/{}", vni, peer));
+        format!("gnv{:012x}", (hash ^ (hash >> 48)) & 0xffff_ffff_ffff)
+    }
+
+    /// Tunnel interfaces and their remote peers
+    fn tunnels(&self, config: &ZoneConfig, local: &[IpAddr]) -> Vec<(String, IpAddr)> {
+        let vni = Self::vni(config).unwrap_or_default();
+        Self::remote_peers(config, local)
+            .into_iter()
+            .map(|peer| (self.get_geneve_interface_name(vni, &peer), peer))
+            .collect()
+    }
+
+    /// Generate the tunnel and bridge configuration, leaving out the peers
+    /// with an address in `local`
+    fn generate_zone_config(
+        &self,
+        config: &ZoneConfig,
+        local: &[IpAddr],
+    ) -> Result<HashMap<String, String>> {
+        debug!(
+            "Generating configuration files for Geneve zone '{}'",
+            self.name
+        );
+
+        let tunnels = self.tunnels(config, local);
+        let mut configs = HashMap::new();
+
+        // Generate tunnel interface configuration
+        let geneve_config = self
+            .generate_geneve_interface_config(config, &tunnels)
+            .with_context(|| {
+                format!(
+                    "Failed to generate Geneve interface config for zone '{}'",
+                    self.name
+                )
+            })?;
+        configs.insert("geneve".to_string(), geneve_config);
+
+        // Generate bridge configuration
+        let bridge_config = self
+            .generate_bridge_config(config, &tunnels)
+            .with_context(|| {
+                format!(
+                    "Failed to generate bridge config for Geneve zone '{}'",
+                    self.name
+                )
+            })?;
+        configs.insert("bridge".to_string(), bridge_config);
+
+        // Generate zone-specific metadata
+        let metadata = format!(
+            "# Geneve Zone Configuration\n\
+             # Zone: {}\n\
+             # Type: Geneve\n\
+             # VNI: {}\n\
+             # Port: {}\n\
+             # TLV Length: {}\n\
+             # Bridge: {}\n\
+             # Peers: {}\n",
+            self.name,
+            Self::vni(config).context("VNI is missing")?,
+            Self::geneve_port(config),
+            Self::tlv_length(config),
+            config.bridge.as_deref().unwrap_or("none"),
+            config
+                .peers
+                .as_ref()
+                .map(|p| p.join(", "))
+                .unwrap_or_else(|| "none".to_string())
+        );
+        configs.insert("metadata".to_string(), metadata);
+
+        info!(
+            "Generated configuration files for Geneve zone '{}'",
+            self.name
+        );
+        Ok(configs)
+    }
+
+    /// Generate Geneve tunnel interface configuration
+    ///
+    /// ifupdown2 has no Geneve support, the tunnels are created by `pre-up`.
+    fn generate_geneve_interface_config(
+        &self,
+        config: &ZoneConfig,
+        tunnels: &[(String, IpAddr)],
+    ) -> Result<String> {
+        let vni = Self::vni(config).context("VNI is missing")?;
+        let geneve_port = Self::geneve_port(config);
+        let mtu = Self::geneve_mtu(config);
+
+        let mut geneve_config = String::new();
+        for (tunnel, peer) in tunnels {
+            if !geneve_config.is_empty() {
+                geneve_config.push('\n');
+            }
+            geneve_config.push_str(&format!(
+                "auto {tunnel}\n\
+                 iface {tunnel} inet manual\n\
+                 \tpre-up ip link add {tunnel} type geneve id {vni} remote {peer} dstport {geneve_port}\n\
+                 \tpost-down ip link del {tunnel}\n\
+                 \tmtu {mtu}\n\
+                 \tbridge-port-isolation on\n",
+            ));
+            geneve_config.push_str(&owner_alias_attribute(config));
+        }
+
+        Ok(geneve_config)
+    }
+
+    /// Generate bridge configuration for Geneve
+    fn generate_bridge_config(
+        &self,
+        config: &ZoneConfig,
+        tunnels: &[(String, IpAddr)],
+    ) -> Result<String> {
+        let bridge = config.bridge.as_ref().context("bridge is missing")?;
+        let ports: Vec<&str> = tunnels.iter().map(|(tunnel, _)| tunnel.as_str()).collect();
+        let bridge_ports = if ports.is_empty() {
+            "none".to_string()
+        } else {
+            ports.join(" ")
+        };
+
+        let mut bridge_config = format!(
+            "auto {bridge}\n\
+             iface {bridge} inet manual\n\
+             \tbridge_ports {bridge_ports}\n\
+             \tbridge_stp off\n\
+             \tbridge_fd 0\n",
+        );
+
+        // Add VLAN awareness if specified
+        if config.vlan_aware.unwrap_or(false) {
+            bridge_config.push_str("\tbridge_vlan_aware yes\n");
+        }
+
+        bridge_config.push_str(&format!("\tmtu {}\n", Self::geneve_mtu(config)));
+        bridge_config.push_str(&owner_alias_attribute(config));
+
+        Ok(bridge_config)
+    }
+
+    /// Create the zone bridge unless it exists
+    async fn ensure_bridge(&self, config: &ZoneConfig, bridge: &str) -> Result<()> {
+        let bridge_exists = self
+            .executor
+            .query("ip", &["link", "show", bridge])
+            .await
+            .map(|output| output.is_success())
+            .unwrap_or(false);
+
+        if bridge_exists {
+            return Ok(());
+        }
+
+        info!(
+            "Creating bridge '{}' for Geneve zone '{}'",
+            bridge, self.name
+        );
+
+        let output = self
+            .executor
+            .execute("ip", &["link", "add", "name", bridge, "type", "bridge"])
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to create bridge '{}' for Geneve zone '{}'",
+                    bridge, self.name
+                )
+            })?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            anyhow::bail!("Failed to create bridge '{}': {}", bridge, stderr);
+        }
+
+        mark_owned(self.executor.as_ref(), bridge, config).await?;
+
+        // Configure VLAN awareness if specified
+        if config.vlan_aware.unwrap_or(false) {
+            let output = self
+                .executor
+                .execute(
+                    "ip",
+                    &[
+                        "link",
+                        "set",
+                        bridge,
+                        "type",
+                        "bridge",
+                        "vlan_filtering",
+                        "1",
+                    ],
+                )
+                .await
+                .with_context(|| {
+                    format!("Failed to enable VLAN filtering on bridge '{}'", bridge)
+                })?;
+
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
+                warn!(
+                    "Failed to enable VLAN filtering on bridge '{}': {}",
+                    bridge, stderr
+                );
+            }
+        }
+
+        // Bring bridge up
+        let output = self
+            .executor
+            .execute("ip", &["link", "set", bridge, "up"])
+            .await
+            .with_context(|| format!("Failed to bring up bridge '{}'", bridge))?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            anyhow::bail!("Failed to bring up bridge '{}': {}", bridge, stderr);
+        }
+
+        Ok(())
+    }
+
+    /// Create the tunnel to `peer` unless it exists and attach it to `bridge`
+    ///
+    /// An existing tunnel leading elsewhere is recreated.
+    async fn ensure_tunnel(
+        &self,
+        config: &ZoneConfig,
+        tunnel: &str,
+        peer: &IpAddr,
+        bridge: &str,
+    ) -> Result<()> {
+        let existing = match self
+            .executor
+            .query("ip", &["-j", "-d", "link", "show", "dev", tunnel])
+            .await
+        {
+            Ok(output) if output.is_success() => Some(parse_tunnel_remote(&output.stdout)),
+            _ => None,
+        };
+
+        match existing {
+            Some(remote) if remote == Some(*peer) => return Ok(()),
+            Some(remote) => {
+                info!(
+                    "Recreating Geneve tunnel '{}' of zone '{}', it leads to {:?} instead of '{}'",
+                    tunnel, self.name, remote, peer
+                );
+                delete_device(self.executor.as_ref(), tunnel).await?;
+            }
+            None => {}
+        }
+
+        info!(
+            "Creating Geneve tunnel '{}' to '{}' for zone '{}'",
+            tunnel, peer, self.name
+        );
+
+        let vni_str = Self::vni(config).unwrap_or_default().to_string();
+        let peer_str = peer.to_string();
+        let geneve_port_str = Self::geneve_port(config).to_string();
+        let output = self
+            .executor
+            .execute(
+                "ip",
+                &[
+                    "link",
+                    "add",
+                    tunnel,
+                    "type",
+                    "geneve",
+                    "id",
+                    &vni_str,
+                    "remote",
+                    &peer_str,
+                    "dstport",
+                    &geneve_port_str,
+                ],
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to create Geneve tunnel '{}' for zone '{}'",
+                    tunnel, self.name
+                )
+            })?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            anyhow::bail!("Failed to create Geneve tunnel '{}': {}", tunnel, stderr);
+        }
+
+        mark_owned(self.executor.as_ref(), tunnel, config).await?;
+
+        // Set MTU, leaving room for the encapsulation overhead
+        let mtu = Self::geneve_mtu(config);
+        let output = self
+            .executor
+            .execute("ip", &["link", "set", tunnel, "mtu", &mtu.to_string()])
+            .await
+            .with_context(|| format!("Failed to set MTU on Geneve tunnel '{}'", tunnel))?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            warn!(
+                "Failed to set MTU {} on Geneve tunnel '{}': {}",
+                mtu, tunnel, stderr
+            );
+        }
+
+        // Add tunnel to bridge
+        let output = self
+            .executor
+            .execute("ip", &["link", "set", tunnel, "master", bridge])
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to add Geneve tunnel '{}' to bridge '{}'",
+                    tunnel, bridge
+                )
+            })?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            anyhow::bail!(
+                "Failed to add Geneve tunnel '{}' to bridge '{}': {}",
+                tunnel,
+                bridge,
+                stderr
+            );
+        }
+
+        // Split horizon: traffic from one peer is never sent to another,
+        // every node reaches every other directly
+        let output = self
+            .executor
+            .execute("bridge", &["link", "set", "dev", tunnel, "isolated", "on"])
+            .await
+            .with_context(|| format!("Failed to isolate Geneve tunnel '{}'", tunnel))?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            anyhow::bail!("Failed to isolate Geneve tunnel '{}': {}", tunnel, stderr);
+        }
+
+        // Bring tunnel up
+        let output = self
+            .executor
+            .execute("ip", &["link", "set", tunnel, "up"])
+            .await
+            .with_context(|| format!("Failed to bring up Geneve tunnel '{}'", tunnel))?;
+
+        if !output.is_success() {
+            let stderr = output.stderr.trim();
+            anyhow::bail!("Failed to bring up Geneve tunnel '{}': {}", tunnel, stderr);
+        }
+
+        Ok(())
+    }
+
+    /// Delete the tunnels of the zone that lead to none of `tunnels`
+    ///
+    /// Left behind when peers are removed from the zone. Nothing is removed
+    /// if the links cannot be listed, e.g. in a dry run.
+    async fn remove_stale_tunnels(
+        &self,
+        config: &ZoneConfig,
+        tunnels: &[(String, IpAddr)],
+    ) -> Result<()> {
+        let links = match list_links(self.executor.as_ref()).await {
+            Ok(links) => links,
+            Err(e) => {
+                warn!(
+                    "Not looking for stale tunnels of Geneve zone '{}': {:#}",
+                    self.name, e
+                );
+                return Ok(());
+            }
+        };
+
+        for link in links {
+            let owned = link
+                .alias
+                .as_deref()
+                .and_then(alias_owner)
+                .map(|(zone, _)| zone == config.zone)
+                .unwrap_or(false);
+            let expected = tunnels.iter().any(|(tunnel, _)| *tunnel == link.name);
+
+            if link.kind == "geneve" && owned && !expected {
+                info!(
+                    "Removing Geneve tunnel '{}' of zone '{}', its peer is gone",
+                    link.name, self.name
+                );
+                delete_device(self.executor.as_ref(), &link.name).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Zone for GeneveZone {
+    fn zone_type(&self) -> ZoneType {
+        ZoneType::Geneve
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self, config: &ZoneConfig) -> Result<()> {
+        debug!("Validating Geneve zone '{}' configuration", self.name);
+
+        // Basic validation
+        config
+            .validate()
+            .with_context(|| format!("Basic validation failed for Geneve zone '{}'", self.name))?;
+
+        // Geneve-specific validation
+        self.validate_geneve_config(config).with_context(|| {
+            format!("Geneve-specific validation failed for zone '{}'", self.name)
+        })?;
+
+        info!(
+            "Geneve zone '{}' configuration validation successful",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn apply_config(&self, config: &ZoneConfig) -> Result<()> {
+        debug!("Applying Geneve zone '{}' configuration", self.name);
+
+        // Validate configuration first
+        self.validate_config(config).await.with_context(|| {
+            format!(
+                "Configuration validation failed for Geneve zone '{}'",
+                self.name
+            )
+        })?;
+
+        let bridge = config.bridge.as_deref().context("bridge is missing")?;
+        self.ensure_bridge(config, bridge).await?;
+
+        let tunnels = self.tunnels(config, &self.own_addresses(config).await);
+        for (tunnel, peer) in &tunnels {
+            self.ensure_tunnel(config, tunnel, peer, bridge).await?;
+        }
+        self.remove_stale_tunnels(config, &tunnels).await?;
+
+        info!(
+            "Geneve zone '{}' configuration applied successfully",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn generate_config(&self, config: &ZoneConfig) -> Result<HashMap<String, String>> {
+        self.generate_zone_config(config, &[])
+    }
+
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        _ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        // Without local-ip the peer configured on this node must not get a
+        // tunnel to itself
+        self.generate_zone_config(config, &self.own_addresses(config).await)
+    }
+
+    async fn remove_config(&self, config: &ZoneConfig) -> Result<()> {
+        let state = self.observed_state(config).await?;
+        let removed = remove_owned_devices(self.executor.as_ref(), &state).await?;
+
+        info!(
+            "Removed devices {:?} of Geneve zone '{}'",
+            removed, self.name
+        );
+        Ok(())
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        if Self::vni(config).is_some() {
+            let local_addresses = self.own_addresses(config).await;
+            for (tunnel, _) in self.tunnels(config, &local_addresses) {
+                state
+                    .devices
+                    .push(observe_device(self.executor.as_ref(), &tunnel, "geneve").await?);
+            }
+        }
+
+        if let Some(bridge) = &config.bridge {
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), bridge, "bridge").await?);
+        }
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn geneve_zone_config() -> ZoneConfig {
+        let mut config = ZoneConfig::new(ZoneType::Geneve, "test-geneve".to_string());
+        config.bridge = Some("gnvbr0".to_string());
+        config.peers = Some(vec!["192.168.1.1".to_string(), "192.168.1.2".to_string()]);
+        config.options.insert("vni".to_string(), json!(100));
+        config
+            .options
+            .insert("local-ip".to_string(), json!("192.168.1.1"));
+        config
+    }
+
+    #[tokio::test]
+    async fn test_geneve_zone_validation() {
+        let zone = GeneveZone::new("test-geneve".to_string());
+
+        let mut config = geneve_zone_config();
+        assert!(zone.validate_config(&config).await.is_ok());
+
+        // Test invalid VNI
+        config.options.insert("vni".to_string(), json!(0));
+        assert!(zone.validate_config(&config).await.is_err());
+
+        config.options.insert("vni".to_string(), json!(16777216));
+        assert!(zone.validate_config(&config).await.is_err());
+
+        // Test missing VNI
+        config.options.remove("vni");
+        assert!(zone.validate_config(&config).await.is_err());
+        config.options.insert("vni".to_string(), json!(100));
+
+        // Test invalid port and TLV length
+        config.options.insert("geneve-port".to_string(), json!(0));
+        assert!(zone.validate_config(&config).await.is_err());
+        config
+            .options
+            .insert("geneve-port".to_string(), json!(6081));
+
+        config.options.insert("tlv-length".to_string(), json!(6));
+        assert!(zone.validate_config(&config).await.is_err());
+        config.options.insert("tlv-length".to_string(), json!(256));
+        assert!(zone.validate_config(&config).await.is_err());
+        config.options.insert("tlv-length".to_string(), json!(8));
+        assert!(zone.validate_config(&config).await.is_ok());
+
+        // Test missing bridge
+        config.bridge = None;
+        assert!(zone.validate_config(&config).await.is_err());
+        config.bridge = Some("gnvbr0".to_string());
+
+        // Test missing peers
+        config.peers = None;
+        assert!(zone.validate_config(&config).await.is_err());
+    }
+
+    #[test]
+    fn test_geneve_overhead() {
+        assert_eq!(geneve_overhead(false, 0), 50);
+        assert_eq!(geneve_overhead(true, 0), 70);
+        // OVN carries one 8 byte metadata option
+        assert_eq!(geneve_overhead(false, 8), 58);
+    }
+
+    #[tokio::test]
+    async fn test_geneve_config_generation() {
+        let zone = GeneveZone::new("test-geneve".to_string());
+
+        let mut config = geneve_zone_config();
+        config.peers = Some(vec![
+            "192.168.1.1".to_string(),
+            "192.168.1.2".to_string(),
+            "192.168.1.3".to_string(),
+        ]);
+        config.options.insert("tlv-length".to_string(), json!(8));
+
+        let configs = zone.generate_config(&config).await.unwrap();
+
+        assert!(configs.contains_key("geneve"));
+        assert!(configs.contains_key("bridge"));
+        assert!(configs.contains_key("metadata"));
+
+        assert_eq!(
+            configs["geneve"],
+            format!(
+                "auto gnvead083dbc863\n\
+             iface gnvead083dbc863 inet manual\n\
+             \tpre-up ip link add gnvead083dbc863 type geneve id 100 remote 192.168.1.2 dstport 6081\n\
+             \tpost-down ip link del gnvead083dbc863\n\
+             \tmtu 1442\n\
+             \tbridge-port-isolation on\n\
+             {alias}\
+             \n\
+             auto gnve9d083dbcedc\n\
+             iface gnve9d083dbcedc inet manual\n\
+             \tpre-up ip link add gnve9d083dbcedc type geneve id 100 remote 192.168.1.3 dstport 6081\n\
+             \tpost-down ip link del gnve9d083dbcedc\n\
+             \tmtu 1442\n\
+             \tbridge-port-isolation on\n\
+             {alias}",
+                alias = owner_alias_attribute(&config)
+            )
+        );
+
+        let bridge_config = configs.get("bridge").unwrap();
+        assert!(bridge_config.contains("bridge_ports gnvead083dbc863 gnve9d083dbcedc\n"));
+        assert!(bridge_config.contains("\tmtu 1442\n"));
+    }
+
+    #[tokio::test]
+    async fn test_geneve_node_config_skips_own_address() {
+        use pve_sdn_core::{CommandOutput, RecordingExecutor, SdnConfiguration};
+
+        let executor = Arc::new(RecordingExecutor::new());
+        executor.respond(
+            "ip -j addr show",
+            CommandOutput::success(
+                r#"[{"ifname":"eno1","addr_info":[{"family":"inet","local":"192.168.1.2","prefixlen":24}]}]"#,
+            ),
+        );
+        let zone = GeneveZone::with_executor("test-geneve".to_string(), executor);
+
+        let mut config = geneve_zone_config();
+        config.options.remove("local-ip");
+        config.peers = Some(vec!["192.168.1.3".to_string(), "192.168.1.2".to_string()]);
+
+        // Without local-ip only the other node remains a peer, under the
+        // same name as with it
+        let sdn = SdnConfiguration::new();
+        let ctx = ZoneRenderContext::new("node2", &sdn);
+        let configs = zone.generate_node_config(&config, &ctx).await.unwrap();
+        assert!(!configs["geneve"].contains("remote 192.168.1.2"));
+        assert!(configs["geneve"].contains(
+            "pre-up ip link add gnve9d083dbcedc type geneve id 100 remote 192.168.1.3 dstport 6081\n"
+        ));
+        assert!(configs["bridge"].contains("bridge_ports gnve9d083dbcedc\n"));
+    }
+}
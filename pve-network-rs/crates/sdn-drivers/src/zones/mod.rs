@@ -1,13 +1,18 @@
 //! SDN Zone drivers
 
 pub mod evpn;
+pub mod geneve;
 pub mod qinq;
 pub mod simple;
+pub mod snat;
 pub mod vlan;
 pub mod vxlan;
+pub mod wireguard;
 
-pub use evpn::EvpnZone;
+pub use evpn::{zone_vnis, EvpnZone};
+pub use geneve::GeneveZone;
 pub use qinq::QinQZone;
 pub use simple::SimpleZone;
 pub use vlan::VlanZone;
 pub use vxlan::VxlanZone;
+pub use wireguard::{WireguardKeyManager, WireguardKeyStore, WireguardZone};
@@ -6,8 +6,12 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use log::{debug, info, warn};
-use pve_sdn_core::{Zone, ZoneConfig, ZoneType};
+use pve_sdn_core::reconciler::{
+    mark_owned, observe_device, owner_alias_attribute, remove_owned_devices,
+};
+use pve_sdn_core::{RealExecutor, SystemExecutor, Zone, ZoneConfig, ZoneObservedState, ZoneType};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// QinQ zone implementation
 ///
@@ -16,12 +20,18 @@ use std::collections::HashMap;
 /// - Customer VLAN (C-VLAN) is the inner tag managed by the customer
 pub struct QinQZone {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl QinQZone {
     /// Create new QinQ zone
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new QinQ zone running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
     }
 
     /// Validate QinQ-specific configuration parameters
@@ -102,6 +112,8 @@ impl QinQZone {
             bridge_config.push_str(&format!("\tmtu {}\n", mtu));
         }
 
+        bridge_config.push_str(&owner_alias_attribute(config));
+
         // Add service VLAN configuration
         bridge_config.push_str(&format!("\t# QinQ Service VLAN {}\n", tag));
 
@@ -199,20 +211,20 @@ impl Zone for QinQZone {
         let _tag = config.tag.unwrap();
 
         // Check if bridge exists
-        let bridge_exists = tokio::process::Command::new("ip")
-            .args(&["link", "show", bridge])
-            .output()
+        let bridge_exists = self
+            .executor
+            .query("ip", &["link", "show", bridge])
             .await
-            .map(|output| output.status.success())
+            .map(|output| output.is_success())
             .unwrap_or(false);
 
         if !bridge_exists {
             info!("Creating QinQ bridge '{}' for zone '{}'", bridge, self.name);
 
             // Create bridge
-            let output = tokio::process::Command::new("ip")
-                .args(&["link", "add", "name", bridge, "type", "bridge"])
-                .output()
+            let output = self
+                .executor
+                .execute("ip", &["link", "add", "name", bridge, "type", "bridge"])
                 .await
                 .with_context(|| {
                     format!(
@@ -221,30 +233,35 @@ impl Zone for QinQZone {
                     )
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!("Failed to create bridge '{}': {}", bridge, stderr);
             }
 
+            mark_owned(self.executor.as_ref(), bridge, config).await?;
+
             // Enable VLAN filtering
-            let output = tokio::process::Command::new("ip")
-                .args(&[
-                    "link",
-                    "set",
-                    bridge,
-                    "type",
-                    "bridge",
-                    "vlan_filtering",
-                    "1",
-                ])
-                .output()
+            let output = self
+                .executor
+                .execute(
+                    "ip",
+                    &[
+                        "link",
+                        "set",
+                        bridge,
+                        "type",
+                        "bridge",
+                        "vlan_filtering",
+                        "1",
+                    ],
+                )
                 .await
                 .with_context(|| {
                     format!("Failed to enable VLAN filtering on bridge '{}'", bridge)
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to enable VLAN filtering on bridge '{}': {}",
                     bridge,
@@ -260,24 +277,27 @@ impl Zone for QinQZone {
                 .unwrap_or("802.1ad");
 
             if vlan_protocol == "802.1ad" {
-                let output = tokio::process::Command::new("ip")
-                    .args(&[
-                        "link",
-                        "set",
-                        bridge,
-                        "type",
-                        "bridge",
-                        "vlan_protocol",
-                        "802.1ad",
-                    ])
-                    .output()
+                let output = self
+                    .executor
+                    .execute(
+                        "ip",
+                        &[
+                            "link",
+                            "set",
+                            bridge,
+                            "type",
+                            "bridge",
+                            "vlan_protocol",
+                            "802.1ad",
+                        ],
+                    )
                     .await
                     .with_context(|| {
                         format!("Failed to set VLAN protocol on bridge '{}'", bridge)
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     warn!(
                         "Failed to set VLAN protocol to 802.1ad on bridge '{}': {}",
                         bridge, stderr
@@ -287,14 +307,14 @@ impl Zone for QinQZone {
 
             // Set MTU if specified
             if let Some(mtu) = config.mtu {
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", bridge, "mtu", &mtu.to_string()])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "set", bridge, "mtu", &mtu.to_string()])
                     .await
                     .with_context(|| format!("Failed to set MTU on bridge '{}'", bridge))?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     warn!(
                         "Failed to set MTU {} on bridge '{}': {}",
                         mtu, bridge, stderr
@@ -303,14 +323,14 @@ impl Zone for QinQZone {
             }
 
             // Bring bridge up
-            let output = tokio::process::Command::new("ip")
-                .args(&["link", "set", bridge, "up"])
-                .output()
+            let output = self
+                .executor
+                .execute("ip", &["link", "set", bridge, "up"])
                 .await
                 .with_context(|| format!("Failed to bring up bridge '{}'", bridge))?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!("Failed to bring up bridge '{}': {}", bridge, stderr);
             }
         }
@@ -382,6 +402,26 @@ impl Zone for QinQZone {
         );
         Ok(configs)
     }
+
+    async fn remove_config(&self, config: &ZoneConfig) -> Result<()> {
+        let state = self.observed_state(config).await?;
+        let removed = remove_owned_devices(self.executor.as_ref(), &state).await?;
+
+        info!("Removed devices {:?} of QinQ zone '{}'", removed, self.name);
+        Ok(())
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        if let Some(bridge) = &config.bridge {
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), bridge, "bridge").await?);
+        }
+
+        Ok(state)
+    }
 }
 
 #[cfg(test)]
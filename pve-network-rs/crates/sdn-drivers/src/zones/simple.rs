@@ -1,22 +1,82 @@
 //! Simple zone driver
 
+use super::snat::generate_snat_nftables;
 use anyhow::Result;
 use async_trait::async_trait;
-use pve_sdn_core::{Zone, ZoneConfig, ZoneType};
+use pve_sdn_core::reconciler::observe_device;
+use pve_sdn_core::{
+    RealExecutor, SubnetConfig, SystemExecutor, VNetConfig, Zone, ZoneConfig, ZoneObservedState,
+    ZoneRenderContext, ZoneType,
+};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Simple zone implementation
 ///
 /// Simple zones provide basic SDN functionality without VLAN tagging.
 /// They use a single bridge for all VNets in the zone.
+///
+/// Each vnet additionally gets an isolated bridge routed by the node: the
+/// subnet gateways are assigned to it and forwarding is enabled, subnets
+/// with `snat` are masqueraded when leaving the zone.
 pub struct SimpleZone {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl SimpleZone {
     /// Create new simple zone
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new simple zone running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
+    }
+
+    /// Generate the routed bridge of a vnet
+    fn generate_vnet_config(
+        &self,
+        config: &ZoneConfig,
+        vnet: &VNetConfig,
+        subnets: &[&SubnetConfig],
+    ) -> String {
+        let gateways: Vec<String> = subnets
+            .iter()
+            .filter_map(|subnet| {
+                subnet
+                    .gateway
+                    .map(|gateway| format!("{}/{}", gateway, subnet.cidr.prefix_len()))
+            })
+            .collect();
+        let ipv4 = subnets
+            .iter()
+            .any(|subnet| subnet.gateway.is_some() && subnet.cidr.addr().is_ipv4());
+        let ipv6 = subnets
+            .iter()
+            .any(|subnet| subnet.gateway.is_some() && subnet.cidr.addr().is_ipv6());
+
+        let mut vnet_config = format!("auto {}\niface {}\n", vnet.vnet, vnet.vnet);
+        for gateway in &gateways {
+            vnet_config.push_str(&format!("    address {}\n", gateway));
+        }
+        vnet_config.push_str("    bridge_ports none\n");
+        vnet_config.push_str("    bridge_stp off\n");
+        vnet_config.push_str("    bridge_fd 0\n");
+
+        if ipv4 {
+            vnet_config.push_str("    ip-forward on\n");
+        }
+        if ipv6 {
+            vnet_config.push_str("    ip6-forward on\n");
+        }
+
+        if let Some(mtu) = config.mtu {
+            vnet_config.push_str(&format!("    mtu {}\n", mtu));
+        }
+
+        vnet_config
     }
 }
 
@@ -96,4 +156,63 @@ impl Zone for SimpleZone {
 
         Ok(configs)
     }
+
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        let mut configs = self.generate_config(config).await?;
+        let mut zone_subnets = Vec::new();
+
+        for vnet in ctx.vnets(&self.name) {
+            let subnets = ctx.subnets(&vnet.vnet);
+            if let Some(subnet) = subnets
+                .iter()
+                .find(|s| s.snat == Some(true) && s.gateway.is_none())
+            {
+                anyhow::bail!(
+                    "Subnet '{}' needs a gateway to use SNAT in simple zone '{}'",
+                    subnet.subnet,
+                    self.name
+                );
+            }
+
+            configs.insert(
+                format!("vnet_{}", vnet.vnet),
+                self.generate_vnet_config(config, vnet, &subnets),
+            );
+            zone_subnets.extend(subnets);
+        }
+
+        if let Some(nftables) = generate_snat_nftables(&zone_subnets) {
+            configs.insert("nftables".to_string(), nftables);
+        }
+
+        Ok(configs)
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        if let Some(bridge) = &config.bridge {
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), bridge, "bridge").await?);
+        }
+
+        Ok(state)
+    }
+
+    async fn observed_vnet_state(
+        &self,
+        _config: &ZoneConfig,
+        vnet: &VNetConfig,
+    ) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+        state
+            .devices
+            .push(observe_device(self.executor.as_ref(), &vnet.vnet, "bridge").await?);
+        Ok(state)
+    }
 }
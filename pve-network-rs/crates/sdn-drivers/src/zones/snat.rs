@@ -0,0 +1,48 @@
+//! Source NAT for routed zone subnets
+//!
+//! Zones routing their subnets on the node (Simple zones, EVPN exit nodes)
+//! masquerade traffic of `snat` subnets leaving the zone. The rules live in
+//! the SDN-owned `inet pve-sdn` table, which the apply pipeline replaces as
+//! a whole on every load.
+
+use ipnet::IpNet;
+use pve_sdn_core::SubnetConfig;
+
+/// Render the postrouting chain masquerading the `snat` subnets among
+/// `subnets`, `None` if there are none
+///
+/// Traffic between the subnets of the zone stays routed and is not
+/// translated.
+pub fn generate_snat_nftables(subnets: &[&SubnetConfig]) -> Option<String> {
+    let mut rules = String::new();
+    for subnet in subnets.iter().filter(|s| s.snat == Some(true)) {
+        let family = match subnet.cidr {
+            IpNet::V4(_) => "ip",
+            IpNet::V6(_) => "ip6",
+        };
+        let zone_subnets: Vec<String> = subnets
+            .iter()
+            .filter(|s| s.cidr.addr().is_ipv4() == subnet.cidr.addr().is_ipv4())
+            .map(|s| s.cidr.to_string())
+            .collect();
+
+        rules.push_str(&format!(
+            "\t\t{family} saddr {} {family} daddr != {{ {} }} masquerade\n",
+            subnet.cidr,
+            zone_subnets.join(", ")
+        ));
+    }
+
+    if rules.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "table inet pve-sdn {{\n\
+         \tchain postrouting {{\n\
+         \t\ttype nat hook postrouting priority srcnat; policy accept;\n\
+         {rules}\
+         \t}}\n\
+         }}\n",
+    ))
+}
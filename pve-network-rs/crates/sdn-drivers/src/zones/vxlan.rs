@@ -6,9 +6,61 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use log::{debug, info, warn};
-use pve_sdn_core::{Zone, ZoneConfig, ZoneType};
+use pve_sdn_core::reconciler::{
+    mark_owned, observe_device, owner_alias_attribute, remove_owned_devices,
+};
+use pve_sdn_core::{
+    RealExecutor, SystemExecutor, Zone, ZoneConfig, ZoneObservedState, ZoneRenderContext, ZoneType,
+};
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Default VXLAN UDP port
+const DEFAULT_VXLAN_PORT: u16 = 4789;
+
+/// MTU of the underlay assumed when the zone sets none
+const DEFAULT_UNDERLAY_MTU: u16 = 1500;
+
+/// FDB address of the head-end replication (flood) entries
+pub const FLOOD_MAC: &str = "00:00:00:00:00:00";
+
+/// VXLAN encapsulation overhead over an IPv4 underlay
+pub const VXLAN_IPV4_OVERHEAD: u16 = 50;
+
+/// VXLAN encapsulation overhead over an IPv6 underlay
+pub const VXLAN_IPV6_OVERHEAD: u16 = 70;
+
+/// Encapsulation overhead for the given underlay family
+pub fn vxlan_overhead(ipv6_underlay: bool) -> u16 {
+    if ipv6_underlay {
+        VXLAN_IPV6_OVERHEAD
+    } else {
+        VXLAN_IPV4_OVERHEAD
+    }
+}
+
+/// Addresses configured on the node, from `ip -j addr show`
+pub fn parse_local_addresses(json: &str) -> Vec<IpAddr> {
+    let links: Vec<serde_json::Value> = serde_json::from_str(json).unwrap_or_default();
+
+    links
+        .iter()
+        .filter_map(|link| link.get("addr_info")?.as_array())
+        .flatten()
+        .filter_map(|addr| addr.get("local")?.as_str()?.parse().ok())
+        .collect()
+}
+
+/// Destinations BUM traffic is flooded to, from `bridge -j fdb show`
+pub fn parse_flood_destinations(json: &str) -> Vec<IpAddr> {
+    serde_json::from_str::<Vec<serde_json::Value>>(json)
+        .unwrap_or_default()
+        .iter()
+        .filter(|entry| entry.get("mac").and_then(|m| m.as_str()) == Some(FLOOD_MAC))
+        .filter_map(|entry| entry.get("dst")?.as_str()?.parse().ok())
+        .collect()
+}
 
 /// VXLAN zone implementation
 ///
@@ -20,28 +72,137 @@ use std::net::IpAddr;
 /// - Support for both kernel and hardware VXLAN offload
 pub struct VxlanZone {
     name: String,
+    executor: Arc<dyn SystemExecutor>,
 }
 
 impl VxlanZone {
     /// Create new VXLAN zone
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new VXLAN zone running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self { name, executor }
+    }
+
+    fn ip_option(config: &ZoneConfig, key: &str) -> Option<IpAddr> {
+        config.options.get(key)?.as_str()?.parse().ok()
+    }
+
+    fn peer_addresses(config: &ZoneConfig) -> Vec<IpAddr> {
+        config
+            .peers
+            .iter()
+            .flatten()
+            .filter_map(|peer| peer.parse().ok())
+            .collect()
+    }
+
+    /// Whether the zone runs over an IPv6 underlay
+    ///
+    /// Determined by `local-ip`, then the peers, then the multicast group.
+    fn is_ipv6_underlay(config: &ZoneConfig) -> bool {
+        Self::ip_option(config, "local-ip")
+            .or_else(|| Self::peer_addresses(config).first().copied())
+            .or_else(|| Self::ip_option(config, "multicast-group"))
+            .map(|addr| addr.is_ipv6())
+            .unwrap_or(false)
+    }
+
+    /// MTU of the VXLAN device and bridge
+    ///
+    /// Defaults to the underlay MTU minus the encapsulation overhead.
+    fn vxlan_mtu(config: &ZoneConfig) -> u16 {
+        config.mtu.unwrap_or_else(|| {
+            DEFAULT_UNDERLAY_MTU - vxlan_overhead(Self::is_ipv6_underlay(config))
+        })
+    }
+
+    /// Peers BUM traffic is replicated to
+    ///
+    /// The zone `peers` usually list every node including the local one, so
+    /// `local-ip` and the addresses in `local` are left out.
+    fn remote_peers(config: &ZoneConfig, local: &[IpAddr]) -> Vec<IpAddr> {
+        let local_ip = Self::ip_option(config, "local-ip");
+
+        let mut peers: Vec<IpAddr> = Vec::new();
+        for peer in Self::peer_addresses(config) {
+            if Some(peer) != local_ip && !local.contains(&peer) && !peers.contains(&peer) {
+                peers.push(peer);
+            }
+        }
+        peers
+    }
+
+    /// Local tunnel address, `local-ip` or the peer configured on this node
+    fn local_tunnel_ip(config: &ZoneConfig, local: &[IpAddr]) -> Option<IpAddr> {
+        Self::ip_option(config, "local-ip").or_else(|| {
+            Self::peer_addresses(config)
+                .into_iter()
+                .find(|peer| local.contains(peer))
+        })
+    }
+
+    /// Addresses configured on the node, empty if they cannot be queried
+    async fn local_addresses(&self) -> Vec<IpAddr> {
+        match self.executor.query("ip", &["-j", "addr", "show"]).await {
+            Ok(output) if output.is_success() => parse_local_addresses(&output.stdout),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Destinations BUM traffic of `interface` is currently flooded to
+    async fn flood_destinations(&self, interface: &str) -> Vec<IpAddr> {
+        let output = match self
+            .executor
+            .query("bridge", &["-j", "fdb", "show", "dev", interface])
+            .await
+        {
+            Ok(output) if output.is_success() => output,
+            _ => return Vec::new(),
+        };
+
+        parse_flood_destinations(&output.stdout)
+    }
+
+    /// Run an `ip`/`bridge` command changing an existing device, failures
+    /// are only logged
+    async fn update_link(&self, program: &str, args: &[&str]) {
+        match self.executor.execute(program, args).await {
+            Ok(output) if output.is_success() => {}
+            Ok(output) => warn!(
+                "{} {} failed for VXLAN zone '{}': {}",
+                program,
+                args.join(" "),
+                self.name,
+                output.stderr.trim()
+            ),
+            Err(e) => warn!(
+                "{} {} failed for VXLAN zone '{}': {}",
+                program,
+                args.join(" "),
+                self.name,
+                e
+            ),
+        }
     }
 
     /// Validate VXLAN-specific configuration parameters
     fn validate_vxlan_config(&self, config: &ZoneConfig) -> Result<()> {
         // VXLAN requires peers for unicast mode or multicast group
-        if config.peers.is_none() || config.peers.as_ref().unwrap().is_empty() {
-            if config.options.get("multicast-group").is_none() {
-                anyhow::bail!(
-                    "VXLAN zone '{}' requires either peers or multicast-group",
-                    self.name
-                );
-            }
+        if (config.peers.is_none() || config.peers.as_ref().unwrap().is_empty())
+            && !config.options.contains_key("multicast-group")
+            && !config.options.contains_key("fabric")
+        {
+            anyhow::bail!(
+                "VXLAN zone '{}' requires peers, multicast-group or a fabric",
+                self.name
+            );
         }
 
         // Validate VXLAN port
-        let vxlan_port = config.vxlan_port.unwrap_or(4789);
+        let vxlan_port = config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT);
         if vxlan_port == 0 {
             anyhow::bail!("VXLAN zone '{}' port cannot be 0", self.name);
         }
@@ -75,16 +236,12 @@ impl VxlanZone {
                     )
                 })?;
 
-                if let IpAddr::V4(ipv4) = addr {
-                    if !ipv4.is_multicast() {
-                        anyhow::bail!(
-                            "VXLAN zone '{}' multicast group '{}' is not a valid multicast address",
-                            self.name,
-                            group_str
-                        );
-                    }
-                } else {
-                    anyhow::bail!("VXLAN zone '{}' multicast group must be IPv4", self.name);
+                if !addr.is_multicast() {
+                    anyhow::bail!(
+                        "VXLAN zone '{}' multicast group '{}' is not a valid multicast address",
+                        self.name,
+                        group_str
+                    );
                 }
             }
         }
@@ -113,12 +270,32 @@ impl VxlanZone {
             }
         }
 
+        // The underlay must be a single address family
+        let ipv6 = Self::is_ipv6_underlay(config);
+        let underlay = Self::ip_option(config, "local-ip")
+            .into_iter()
+            .chain(Self::peer_addresses(config))
+            .chain(Self::ip_option(config, "multicast-group"));
+        for addr in underlay {
+            if addr.is_ipv6() != ipv6 {
+                anyhow::bail!(
+                    "VXLAN zone '{}' mixes IPv4 and IPv6 underlay addresses ({})",
+                    self.name,
+                    addr
+                );
+            }
+        }
+
         // Validate MTU considerations for VXLAN overhead
         if let Some(mtu) = config.mtu {
-            if mtu > 1450 {
+            let overhead = vxlan_overhead(ipv6);
+            if mtu > DEFAULT_UNDERLAY_MTU - overhead {
                 warn!(
-                    "VXLAN zone '{}' MTU {} may be too large considering VXLAN overhead (50 bytes)",
-                    self.name, mtu
+                    "VXLAN zone '{}' MTU {} needs an underlay MTU of at least {} ({} bytes VXLAN overhead)",
+                    self.name,
+                    mtu,
+                    mtu + overhead,
+                    overhead
                 );
             }
         }
@@ -131,11 +308,98 @@ impl VxlanZone {
         format!("vxlan{}", vni)
     }
 
+    /// Render the zone for a node owning `local` addresses
+    fn generate_zone_config(
+        &self,
+        config: &ZoneConfig,
+        local: &[IpAddr],
+    ) -> Result<HashMap<String, String>> {
+        debug!(
+            "Generating configuration files for VXLAN zone '{}'",
+            self.name
+        );
+
+        let mut configs = HashMap::new();
+
+        // Generate VXLAN interface configuration
+        let vxlan_config = self
+            .generate_vxlan_interface_config(config, local)
+            .with_context(|| {
+                format!(
+                    "Failed to generate VXLAN interface config for zone '{}'",
+                    self.name
+                )
+            })?;
+        configs.insert("vxlan".to_string(), vxlan_config);
+
+        // Generate bridge configuration if bridge is specified
+        if config.bridge.is_some() {
+            let bridge_config = self.generate_bridge_config(config).with_context(|| {
+                format!(
+                    "Failed to generate bridge config for VXLAN zone '{}'",
+                    self.name
+                )
+            })?;
+            configs.insert("bridge".to_string(), bridge_config);
+        }
+
+        // Generate systemd network configuration
+        let systemd_config = self
+            .generate_systemd_config(config, local)
+            .with_context(|| {
+                format!(
+                    "Failed to generate systemd config for VXLAN zone '{}'",
+                    self.name
+                )
+            })?;
+        configs.insert("systemd".to_string(), systemd_config);
+
+        // Generate zone-specific metadata
+        let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
+        let metadata = format!(
+            "# VXLAN Zone Configuration\n\
+             # Zone: {}\n\
+             # Type: VXLAN\n\
+             # VNI: {}\n\
+             # Port: {}\n\
+             # Interface: {}\n\
+             # Bridge: {}\n\
+             # Multicast Group: {}\n\
+             # Peers: {}\n",
+            self.name,
+            vni,
+            config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT),
+            self.get_vxlan_interface_name(vni),
+            config.bridge.as_deref().unwrap_or("none"),
+            config
+                .options
+                .get("multicast-group")
+                .and_then(|v| v.as_str())
+                .unwrap_or("none"),
+            config
+                .peers
+                .as_ref()
+                .map(|p| p.join(", "))
+                .unwrap_or_else(|| "none".to_string())
+        );
+        configs.insert("metadata".to_string(), metadata);
+
+        info!(
+            "Generated configuration files for VXLAN zone '{}'",
+            self.name
+        );
+        Ok(configs)
+    }
+
     /// Generate VXLAN interface configuration
-    fn generate_vxlan_interface_config(&self, config: &ZoneConfig) -> Result<String> {
+    fn generate_vxlan_interface_config(
+        &self,
+        config: &ZoneConfig,
+        local: &[IpAddr],
+    ) -> Result<String> {
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let vxlan_interface = self.get_vxlan_interface_name(vni);
-        let vxlan_port = config.vxlan_port.unwrap_or(4789);
+        let vxlan_port = config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT);
 
         let mut vxlan_config = format!(
             "auto {vxlan_interface}\n\
@@ -147,11 +411,8 @@ impl VxlanZone {
             vxlan_port = vxlan_port
         );
 
-        // Add local IP if specified
-        if let Some(local_ip) = config.options.get("local-ip") {
-            if let Some(local_str) = local_ip.as_str() {
-                vxlan_config.push_str(&format!("\tvxlan-local-tunnelip {}\n", local_str));
-            }
+        if let Some(local_ip) = Self::local_tunnel_ip(config, local) {
+            vxlan_config.push_str(&format!("\tvxlan-local-tunnelip {}\n", local_ip));
         }
 
         // Configure multicast or unicast mode
@@ -166,17 +427,16 @@ impl VxlanZone {
                     }
                 }
             }
-        } else if let Some(peers) = &config.peers {
-            // Unicast mode with static peers
-            for peer in peers {
+        } else {
+            // Unicast mode, head-end replication to every remote peer
+            for peer in Self::remote_peers(config, local) {
                 vxlan_config.push_str(&format!("\tvxlan-remoteip {}\n", peer));
             }
         }
 
-        // Add MTU if specified
-        if let Some(mtu) = config.mtu {
-            vxlan_config.push_str(&format!("\tmtu {}\n", mtu));
-        }
+        vxlan_config.push_str(&format!("\tmtu {}\n", Self::vxlan_mtu(config)));
+
+        vxlan_config.push_str(&owner_alias_attribute(config));
 
         // Additional VXLAN options
         if let Some(learning) = config.options.get("learning") {
@@ -222,19 +482,17 @@ impl VxlanZone {
             bridge_config.push_str("\tbridge_vlan_aware yes\n");
         }
 
-        // Add MTU if specified
-        if let Some(mtu) = config.mtu {
-            bridge_config.push_str(&format!("\tmtu {}\n", mtu));
-        }
+        bridge_config.push_str(&format!("\tmtu {}\n", Self::vxlan_mtu(config)));
+        bridge_config.push_str(&owner_alias_attribute(config));
 
         Ok(bridge_config)
     }
 
     /// Generate systemd network configuration for VXLAN
-    fn generate_systemd_config(&self, config: &ZoneConfig) -> Result<String> {
+    fn generate_systemd_config(&self, config: &ZoneConfig, local: &[IpAddr]) -> Result<String> {
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let vxlan_interface = self.get_vxlan_interface_name(vni);
-        let vxlan_port = config.vxlan_port.unwrap_or(4789);
+        let vxlan_port = config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT);
 
         let mut systemd_config = format!(
             "[NetDev]\n\
@@ -249,11 +507,8 @@ impl VxlanZone {
             vxlan_port = vxlan_port
         );
 
-        // Add local IP if specified
-        if let Some(local_ip) = config.options.get("local-ip") {
-            if let Some(local_str) = local_ip.as_str() {
-                systemd_config.push_str(&format!("Local={}\n", local_str));
-            }
+        if let Some(local_ip) = Self::local_tunnel_ip(config, local) {
+            systemd_config.push_str(&format!("Local={}\n", local_ip));
         }
 
         // Configure multicast or unicast mode
@@ -317,14 +572,14 @@ impl Zone for VxlanZone {
 
         let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
         let vxlan_interface = self.get_vxlan_interface_name(vni);
-        let vxlan_port = config.vxlan_port.unwrap_or(4789);
+        let vxlan_port = config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT);
 
         // Check if VXLAN interface already exists
-        let interface_exists = tokio::process::Command::new("ip")
-            .args(&["link", "show", &vxlan_interface])
-            .output()
+        let interface_exists = self
+            .executor
+            .query("ip", &["link", "show", &vxlan_interface])
             .await
-            .map(|output| output.status.success())
+            .map(|output| output.is_success())
             .unwrap_or(false);
 
         if !interface_exists {
@@ -333,6 +588,14 @@ impl Zone for VxlanZone {
                 vxlan_interface, self.name
             );
 
+            // Without local-ip the peer configured on this node is the
+            // tunnel source and is skipped for head-end replication
+            let local_addresses = if config.options.contains_key("local-ip") {
+                Vec::new()
+            } else {
+                self.local_addresses().await
+            };
+
             let vni_str = vni.to_string();
             let vxlan_port_str = vxlan_port.to_string();
             let mut cmd_args = vec![
@@ -348,11 +611,8 @@ impl Zone for VxlanZone {
             ];
 
             // Prepare string arguments to avoid lifetime issues
-            let local_ip_str = config
-                .options
-                .get("local-ip")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
+            let local_ip_str =
+                Self::local_tunnel_ip(config, &local_addresses).map(|ip| ip.to_string());
 
             let group_str = config
                 .options
@@ -394,9 +654,9 @@ impl Zone for VxlanZone {
                 }
             }
 
-            let output = tokio::process::Command::new("ip")
-                .args(&cmd_args)
-                .output()
+            let output = self
+                .executor
+                .execute("ip", &cmd_args)
                 .await
                 .with_context(|| {
                     format!(
@@ -405,8 +665,8 @@ impl Zone for VxlanZone {
                     )
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to create VXLAN interface '{}': {}",
                     vxlan_interface,
@@ -414,36 +674,40 @@ impl Zone for VxlanZone {
                 );
             }
 
-            // Set MTU if specified
-            if let Some(mtu) = config.mtu {
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", &vxlan_interface, "mtu", &mtu.to_string()])
-                    .output()
-                    .await
-                    .with_context(|| {
-                        format!("Failed to set MTU on VXLAN interface '{}'", vxlan_interface)
-                    })?;
+            mark_owned(self.executor.as_ref(), &vxlan_interface, config).await?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    warn!(
-                        "Failed to set MTU {} on VXLAN interface '{}': {}",
-                        mtu, vxlan_interface, stderr
-                    );
-                }
+            // Set MTU, leaving room for the encapsulation overhead
+            let mtu = Self::vxlan_mtu(config);
+            let output = self
+                .executor
+                .execute(
+                    "ip",
+                    &["link", "set", &vxlan_interface, "mtu", &mtu.to_string()],
+                )
+                .await
+                .with_context(|| {
+                    format!("Failed to set MTU on VXLAN interface '{}'", vxlan_interface)
+                })?;
+
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
+                warn!(
+                    "Failed to set MTU {} on VXLAN interface '{}': {}",
+                    mtu, vxlan_interface, stderr
+                );
             }
 
             // Bring interface up
-            let output = tokio::process::Command::new("ip")
-                .args(&["link", "set", &vxlan_interface, "up"])
-                .output()
+            let output = self
+                .executor
+                .execute("ip", &["link", "set", &vxlan_interface, "up"])
                 .await
                 .with_context(|| {
                     format!("Failed to bring up VXLAN interface '{}'", vxlan_interface)
                 })?;
 
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.is_success() {
+                let stderr = output.stderr.trim();
                 anyhow::bail!(
                     "Failed to bring up VXLAN interface '{}': {}",
                     vxlan_interface,
@@ -451,44 +715,34 @@ impl Zone for VxlanZone {
                 );
             }
 
-            // Add static peers for unicast mode
+            // Head-end replication: flood BUM traffic to every remote peer
             if config.options.get("multicast-group").is_none() {
-                if let Some(peers) = &config.peers {
-                    for peer in peers {
-                        let output = tokio::process::Command::new("bridge")
-                            .args(&[
-                                "fdb",
-                                "append",
-                                "00:00:00:00:00:00",
-                                "dev",
-                                &vxlan_interface,
-                                "dst",
-                                peer,
-                            ])
-                            .output()
-                            .await;
-
-                        if let Ok(output) = output {
-                            if !output.status.success() {
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                warn!(
-                                    "Failed to add peer '{}' to VXLAN interface '{}': {}",
-                                    peer, vxlan_interface, stderr
-                                );
-                            }
-                        }
-                    }
+                for peer in Self::remote_peers(config, &local_addresses) {
+                    let peer = peer.to_string();
+                    self.update_link(
+                        "bridge",
+                        &[
+                            "fdb",
+                            "append",
+                            FLOOD_MAC,
+                            "dev",
+                            &vxlan_interface,
+                            "dst",
+                            &peer,
+                        ],
+                    )
+                    .await;
                 }
             }
         }
 
         // Create bridge if specified
         if let Some(bridge) = &config.bridge {
-            let bridge_exists = tokio::process::Command::new("ip")
-                .args(&["link", "show", bridge])
-                .output()
+            let bridge_exists = self
+                .executor
+                .query("ip", &["link", "show", bridge])
                 .await
-                .map(|output| output.status.success())
+                .map(|output| output.is_success())
                 .unwrap_or(false);
 
             if !bridge_exists {
@@ -497,9 +751,9 @@ impl Zone for VxlanZone {
                     bridge, self.name
                 );
 
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "add", "name", bridge, "type", "bridge"])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "add", "name", bridge, "type", "bridge"])
                     .await
                     .with_context(|| {
                         format!(
@@ -508,15 +762,17 @@ impl Zone for VxlanZone {
                         )
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     anyhow::bail!("Failed to create bridge '{}': {}", bridge, stderr);
                 }
 
+                mark_owned(self.executor.as_ref(), bridge, config).await?;
+
                 // Add VXLAN interface to bridge
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", &vxlan_interface, "master", bridge])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "set", &vxlan_interface, "master", bridge])
                     .await
                     .with_context(|| {
                         format!(
@@ -525,8 +781,8 @@ impl Zone for VxlanZone {
                         )
                     })?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     anyhow::bail!(
                         "Failed to add VXLAN interface '{}' to bridge '{}': {}",
                         vxlan_interface,
@@ -537,24 +793,27 @@ impl Zone for VxlanZone {
 
                 // Configure VLAN awareness if specified
                 if config.vlan_aware.unwrap_or(false) {
-                    let output = tokio::process::Command::new("ip")
-                        .args(&[
-                            "link",
-                            "set",
-                            bridge,
-                            "type",
-                            "bridge",
-                            "vlan_filtering",
-                            "1",
-                        ])
-                        .output()
+                    let output = self
+                        .executor
+                        .execute(
+                            "ip",
+                            &[
+                                "link",
+                                "set",
+                                bridge,
+                                "type",
+                                "bridge",
+                                "vlan_filtering",
+                                "1",
+                            ],
+                        )
                         .await
                         .with_context(|| {
                             format!("Failed to enable VLAN filtering on bridge '{}'", bridge)
                         })?;
 
-                    if !output.status.success() {
-                        let stderr = String::from_utf8_lossy(&output.stderr);
+                    if !output.is_success() {
+                        let stderr = output.stderr.trim();
                         warn!(
                             "Failed to enable VLAN filtering on bridge '{}': {}",
                             bridge, stderr
@@ -563,14 +822,14 @@ impl Zone for VxlanZone {
                 }
 
                 // Bring bridge up
-                let output = tokio::process::Command::new("ip")
-                    .args(&["link", "set", bridge, "up"])
-                    .output()
+                let output = self
+                    .executor
+                    .execute("ip", &["link", "set", bridge, "up"])
                     .await
                     .with_context(|| format!("Failed to bring up bridge '{}'", bridge))?;
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.is_success() {
+                    let stderr = output.stderr.trim();
                     anyhow::bail!("Failed to bring up bridge '{}': {}", bridge, stderr);
                 }
             }
@@ -584,85 +843,142 @@ impl Zone for VxlanZone {
     }
 
     async fn generate_config(&self, config: &ZoneConfig) -> Result<HashMap<String, String>> {
-        debug!(
-            "Generating configuration files for VXLAN zone '{}'",
-            self.name
-        );
+        self.generate_zone_config(config, &[])
+    }
 
-        let mut configs = HashMap::new();
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        _ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        // Without local-ip the peer configured on this node is the tunnel
+        // source, it must not end up in its own replication list
+        let local_addresses = if config.options.contains_key("local-ip") {
+            Vec::new()
+        } else {
+            self.local_addresses().await
+        };
+        self.generate_zone_config(config, &local_addresses)
+    }
 
-        // Generate VXLAN interface configuration
-        let vxlan_config = self
-            .generate_vxlan_interface_config(config)
-            .with_context(|| {
-                format!(
-                    "Failed to generate VXLAN interface config for zone '{}'",
-                    self.name
-                )
-            })?;
-        configs.insert("vxlan".to_string(), vxlan_config);
+    async fn update_config(&self, config: &ZoneConfig) -> Result<()> {
+        // Creates missing devices, existing ones are updated below
+        self.apply_config(config).await?;
 
-        // Generate bridge configuration if bridge is specified
-        if config.bridge.is_some() {
-            let bridge_config = self.generate_bridge_config(config).with_context(|| {
-                format!(
-                    "Failed to generate bridge config for VXLAN zone '{}'",
-                    self.name
+        let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
+        let vxlan_interface = self.get_vxlan_interface_name(vni);
+        let mtu = Self::vxlan_mtu(config).to_string();
+
+        self.update_link("ip", &["link", "set", &vxlan_interface, "mtu", &mtu])
+            .await;
+        if let Some(bridge) = &config.bridge {
+            if config.mtu.is_some() {
+                self.update_link("ip", &["link", "set", bridge, "mtu", &mtu])
+                    .await;
+            }
+            let vlan_filtering = if config.vlan_aware.unwrap_or(false) {
+                "1"
+            } else {
+                "0"
+            };
+            self.update_link(
+                "ip",
+                &[
+                    "link",
+                    "set",
+                    bridge,
+                    "type",
+                    "bridge",
+                    "vlan_filtering",
+                    vlan_filtering,
+                ],
+            )
+            .await;
+        }
+
+        // Sync the head-end replication list with the configured peers
+        if !config.options.contains_key("multicast-group") {
+            let local_addresses = if config.options.contains_key("local-ip") {
+                Vec::new()
+            } else {
+                self.local_addresses().await
+            };
+            let peers = Self::remote_peers(config, &local_addresses);
+            let current = self.flood_destinations(&vxlan_interface).await;
+
+            for stale in current.iter().filter(|dst| !peers.contains(dst)) {
+                let dst = stale.to_string();
+                self.update_link(
+                    "bridge",
+                    &[
+                        "fdb",
+                        "del",
+                        FLOOD_MAC,
+                        "dev",
+                        &vxlan_interface,
+                        "dst",
+                        &dst,
+                    ],
                 )
-            })?;
-            configs.insert("bridge".to_string(), bridge_config);
+                .await;
+            }
+            for peer in peers.iter().filter(|peer| !current.contains(peer)) {
+                let dst = peer.to_string();
+                self.update_link(
+                    "bridge",
+                    &[
+                        "fdb",
+                        "append",
+                        FLOOD_MAC,
+                        "dev",
+                        &vxlan_interface,
+                        "dst",
+                        &dst,
+                    ],
+                )
+                .await;
+            }
         }
 
-        // Generate systemd network configuration
-        let systemd_config = self.generate_systemd_config(config).with_context(|| {
-            format!(
-                "Failed to generate systemd config for VXLAN zone '{}'",
-                self.name
-            )
-        })?;
-        configs.insert("systemd".to_string(), systemd_config);
+        info!("VXLAN zone '{}' updated in place", self.name);
+        Ok(())
+    }
 
-        // Generate zone-specific metadata
-        let vni = config.options.get("vni").unwrap().as_u64().unwrap() as u32;
-        let metadata = format!(
-            "# VXLAN Zone Configuration\n\
-             # Zone: {}\n\
-             # Type: VXLAN\n\
-             # VNI: {}\n\
-             # Port: {}\n\
-             # Interface: {}\n\
-             # Bridge: {}\n\
-             # Multicast Group: {}\n\
-             # Peers: {}\n",
-            self.name,
-            vni,
-            config.vxlan_port.unwrap_or(4789),
-            self.get_vxlan_interface_name(vni),
-            config.bridge.as_deref().unwrap_or("none"),
-            config
-                .options
-                .get("multicast-group")
-                .and_then(|v| v.as_str())
-                .unwrap_or("none"),
-            config
-                .peers
-                .as_ref()
-                .map(|p| p.join(", "))
-                .unwrap_or_else(|| "none".to_string())
-        );
-        configs.insert("metadata".to_string(), metadata);
+    async fn remove_config(&self, config: &ZoneConfig) -> Result<()> {
+        let state = self.observed_state(config).await?;
+        let removed = remove_owned_devices(self.executor.as_ref(), &state).await?;
 
         info!(
-            "Generated configuration files for VXLAN zone '{}'",
-            self.name
+            "Removed devices {:?} of VXLAN zone '{}'",
+            removed, self.name
         );
-        Ok(configs)
+        Ok(())
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        if let Some(vni) = config.options.get("vni").and_then(|v| v.as_u64()) {
+            let vxlan_interface = self.get_vxlan_interface_name(vni as u32);
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), &vxlan_interface, "vxlan").await?);
+        }
+
+        if let Some(bridge) = &config.bridge {
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), bridge, "bridge").await?);
+        }
+
+        Ok(state)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pve_sdn_core::{CommandOutput, RecordingExecutor};
     use serde_json::json;
 
     #[tokio::test]
@@ -731,4 +1047,88 @@ mod tests {
         let bridge_config = configs.get("bridge").unwrap();
         assert!(bridge_config.contains("bridge_ports vxlan100"));
     }
+
+    #[tokio::test]
+    async fn test_vxlan_update_syncs_flood_list() {
+        let executor = Arc::new(RecordingExecutor::new());
+        let zone = VxlanZone::with_executor("test-vxlan".to_string(), executor.clone());
+
+        let mut config = ZoneConfig::new(ZoneType::Vxlan, "test-vxlan".to_string());
+        config.options.insert("vni".to_string(), json!(100));
+        config
+            .options
+            .insert("local-ip".to_string(), json!("10.0.0.1"));
+        config.peers = Some(vec![
+            "10.0.0.1".to_string(),
+            "10.0.0.2".to_string(),
+            "10.0.0.3".to_string(),
+        ]);
+        config.bridge = Some("vmbr0".to_string());
+        config.mtu = Some(1450);
+
+        let fdb = json!([
+            { "mac": "00:00:00:00:00:00", "dst": "10.0.0.2" },
+            { "mac": "00:00:00:00:00:00", "dst": "10.0.0.9" },
+            { "mac": "aa:bb:cc:dd:ee:ff", "dst": "10.0.0.9" },
+        ]);
+        executor.respond(
+            "bridge -j fdb show dev vxlan100",
+            CommandOutput::success(&fdb.to_string()),
+        );
+
+        zone.update_config(&config).await.unwrap();
+
+        let commands = executor.command_lines();
+        assert!(!commands.iter().any(|c| c.contains("link delete")));
+        assert!(commands.contains(&"ip link set vxlan100 mtu 1450".to_string()));
+        assert!(commands
+            .contains(&"bridge fdb del 00:00:00:00:00:00 dev vxlan100 dst 10.0.0.9".to_string()));
+        assert!(commands.contains(
+            &"bridge fdb append 00:00:00:00:00:00 dev vxlan100 dst 10.0.0.3".to_string()
+        ));
+        // Existing entries and the local address are left alone
+        for dst in ["10.0.0.1", "10.0.0.2"] {
+            assert!(!commands
+                .iter()
+                .any(|c| c.starts_with("bridge fdb") && c.ends_with(&format!("dst {}", dst))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vxlan_node_config_without_local_ip() {
+        let executor = Arc::new(RecordingExecutor::new());
+        executor.respond(
+            "ip -j addr show",
+            CommandOutput::success(
+                &json!([
+                    { "ifname": "lo", "addr_info": [{ "local": "127.0.0.1" }] },
+                    { "ifname": "eno1", "addr_info": [{ "local": "10.0.0.1" }] },
+                ])
+                .to_string(),
+            ),
+        );
+        let zone = VxlanZone::with_executor("test-vxlan".to_string(), executor);
+
+        let mut config = ZoneConfig::new(ZoneType::Vxlan, "test-vxlan".to_string());
+        config.options.insert("vni".to_string(), json!(100));
+        config.peers = Some(vec![
+            "10.0.0.1".to_string(),
+            "10.0.0.2".to_string(),
+            "10.0.0.3".to_string(),
+        ]);
+
+        let sdn = pve_sdn_core::SdnConfiguration::new();
+        let configs = zone
+            .generate_node_config(&config, &ZoneRenderContext::new("node1", &sdn))
+            .await
+            .unwrap();
+
+        // The node's own peer is the tunnel source, not a flood destination
+        let vxlan_config = &configs["vxlan"];
+        assert!(vxlan_config.contains("vxlan-local-tunnelip 10.0.0.1"));
+        assert!(!vxlan_config.contains("vxlan-remoteip 10.0.0.1"));
+        assert!(vxlan_config.contains("vxlan-remoteip 10.0.0.2"));
+        assert!(vxlan_config.contains("vxlan-remoteip 10.0.0.3"));
+        assert!(configs["systemd"].contains("Local=10.0.0.1"));
+    }
 }
@@ -0,0 +1,1631 @@
+//! WireGuard zone driver
+//!
+//! WireGuard zones run a VXLAN overlay over an encrypted WireGuard mesh
+//! between the nodes of the zone, for clusters whose nodes are connected by
+//! untrusted links. Every node generates its own key pair: the private key
+//! never leaves the node, the public key, mesh address and endpoint are
+//! published to pmxcfs where the other nodes pick them up.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use log::{debug, info, warn};
+use pve_sdn_core::reconciler::{
+    mark_owned, observe_device, owner_alias_attribute, remove_owned_devices,
+};
+use pve_sdn_core::status::{SdnStatusStore, DEFAULT_PMXCFS_PATH};
+use pve_sdn_core::{
+    RealExecutor, SdnConfiguration, SystemCommand, SystemExecutor, Zone, ZoneConfig,
+    ZoneObservedState, ZoneRenderContext, ZoneType,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::vxlan::{parse_flood_destinations, parse_local_addresses, vxlan_overhead, FLOOD_MAC};
+
+/// Default WireGuard listen port
+const DEFAULT_WIREGUARD_PORT: u16 = 51820;
+
+/// Default VXLAN UDP port inside the mesh
+const DEFAULT_VXLAN_PORT: u16 = 4789;
+
+/// MTU of the underlay assumed when the zone sets no `underlay-mtu`
+const DEFAULT_UNDERLAY_MTU: u16 = 1500;
+
+/// WireGuard encapsulation overhead, assuming an IPv6 underlay like wg-quick
+pub const WIREGUARD_OVERHEAD: u16 = 80;
+
+/// Keepalive interval keeping NAT and firewall state of the tunnels alive
+const PERSISTENT_KEEPALIVE: u16 = 25;
+
+/// Node-local directory of the rendered WireGuard configs and private keys
+pub const DEFAULT_SDN_WIREGUARD_PATH: &str = "/etc/wireguard/sdn";
+
+/// Name of the key file in the pmxcfs directory of each node
+pub const WIREGUARD_KEYS_FILE_NAME: &str = "sdn-wireguard.json";
+
+/// Prefix of generated config keys holding a WireGuard config, followed by
+/// the interface name
+pub const WIREGUARD_CONFIG_KEY_PREFIX: &str = "wg/";
+
+/// Name of the WireGuard interface of `zone`
+pub fn wireguard_interface_name(zone: &str) -> String {
+    format!("wg_{}", zone)
+}
+
+/// Whether `key` looks like a base64 encoded WireGuard key
+fn is_wireguard_key(key: &str) -> bool {
+    key.len() == 44
+        && key.ends_with('=')
+        && key[..43]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/')
+}
+
+/// Public WireGuard identity of a node in a zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WireguardPeer {
+    /// Base64 encoded public key
+    pub public_key: String,
+    /// Address of the node inside the mesh
+    pub address: IpAddr,
+    /// Underlay address the other nodes connect to
+    pub endpoint: IpAddr,
+    /// When the key pair was generated
+    pub created: DateTime<Utc>,
+    /// Key pair replacing the current one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<StagedWireguardKey>,
+}
+
+impl WireguardPeer {
+    /// Identity as of configuration `version`, with a staged key pair that
+    /// took over in place of the current one
+    pub fn at_version(&self, version: u64) -> WireguardPeer {
+        match &self.next {
+            Some(next) if next.version <= version => WireguardPeer {
+                public_key: next.public_key.clone(),
+                created: next.created,
+                next: None,
+                ..self.clone()
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Key pair staged to replace the current one of a node
+///
+/// All nodes switch to it when they apply configuration `version`, the
+/// old key pair stays in use until then.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct StagedWireguardKey {
+    /// Base64 encoded public key
+    pub public_key: String,
+    /// Configuration version the key pair is used from
+    pub version: u64,
+    /// When the key pair was generated
+    pub created: DateTime<Utc>,
+}
+
+/// WireGuard identities of a node, as published to pmxcfs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeWireguardKeys {
+    /// Node name
+    pub node: String,
+    /// Identity of the node in each zone
+    pub zones: BTreeMap<String, WireguardPeer>,
+}
+
+/// Published WireGuard identities in pmxcfs
+///
+/// Each node only writes its own file, so nodes never race for the same
+/// file.
+pub struct WireguardKeyStore {
+    base_path: PathBuf,
+}
+
+impl WireguardKeyStore {
+    /// Create store using the default pmxcfs mount point
+    pub fn new() -> Self {
+        Self::with_base_path(DEFAULT_PMXCFS_PATH)
+    }
+
+    /// Create store rooted at `base_path` instead of `/etc/pve`
+    pub fn with_base_path(base_path: impl Into<PathBuf>) -> Self {
+        Self {
+            base_path: base_path.into(),
+        }
+    }
+
+    /// Path of the key file of `node`
+    pub fn keys_path(&self, node: &str) -> PathBuf {
+        self.base_path
+            .join("nodes")
+            .join(node)
+            .join(WIREGUARD_KEYS_FILE_NAME)
+    }
+
+    /// Identities published by `node`, empty if it has not published any
+    pub async fn read(&self, node: &str) -> Result<NodeWireguardKeys> {
+        let path = self.keys_path(node);
+        let content = match tokio::fs::read(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(NodeWireguardKeys {
+                    node: node.to_string(),
+                    zones: BTreeMap::new(),
+                })
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Publish the identities of a node
+    pub async fn publish(&self, keys: &NodeWireguardKeys) -> Result<()> {
+        let path = self.keys_path(&keys.node);
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        // Readers on other nodes must never see a partially written file
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(keys)?)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Identities of `nodes` in `zone`, nodes without one are left out
+    pub async fn zone_peers(
+        &self,
+        zone: &str,
+        nodes: &[String],
+    ) -> Result<BTreeMap<String, WireguardPeer>> {
+        let mut peers = BTreeMap::new();
+        for node in nodes {
+            if let Some(peer) = self.read(node).await?.zones.remove(zone) {
+                peers.insert(node.clone(), peer);
+            }
+        }
+        Ok(peers)
+    }
+
+    /// Cluster address of `node`, from the pmxcfs member list
+    pub async fn cluster_address(&self, node: &str) -> Result<IpAddr> {
+        let path = self.base_path.join(".members");
+        let content = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let members: serde_json::Value = serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        members
+            .get("nodelist")
+            .and_then(|nodes| nodes.get(node))
+            .and_then(|member| member.get("ip"))
+            .and_then(|ip| ip.as_str())
+            .and_then(|ip| ip.parse().ok())
+            .with_context(|| format!("Node '{}' has no cluster address", node))
+    }
+}
+
+impl Default for WireguardKeyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Key pairs of the local node
+///
+/// Private keys are generated with `wg genkey` and stored in
+/// `<wireguard path>/private`, readable by root only. Zones with a
+/// `key-lifetime` (in days) get a new key pair once the current one is
+/// older, staged next to it until the next configuration version.
+pub struct WireguardKeyManager {
+    node: String,
+    executor: Arc<dyn SystemExecutor>,
+    store: WireguardKeyStore,
+    wireguard_path: PathBuf,
+}
+
+impl WireguardKeyManager {
+    /// Create key manager of `node` using the default paths
+    pub fn new(node: &str, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self {
+            node: node.to_string(),
+            executor,
+            store: WireguardKeyStore::new(),
+            wireguard_path: PathBuf::from(DEFAULT_SDN_WIREGUARD_PATH),
+        }
+    }
+
+    /// Override the pmxcfs mount point and the node-local key location
+    pub fn with_paths(
+        mut self,
+        pmxcfs_path: impl Into<PathBuf>,
+        wireguard_path: impl Into<PathBuf>,
+    ) -> Self {
+        self.store = WireguardKeyStore::with_base_path(pmxcfs_path);
+        self.wireguard_path = wireguard_path.into();
+        self
+    }
+
+    /// Published identities
+    pub fn store(&self) -> &WireguardKeyStore {
+        &self.store
+    }
+
+    /// Path of the private key of `zone`
+    pub fn private_key_path(&self, zone: &str) -> PathBuf {
+        self.wireguard_path
+            .join("private")
+            .join(format!("{}.key", zone))
+    }
+
+    /// Path of the staged private key of `zone`
+    pub fn next_private_key_path(&self, zone: &str) -> PathBuf {
+        self.wireguard_path
+            .join("private")
+            .join(format!("{}.next.key", zone))
+    }
+
+    /// Bring the key pair of the local node in the zone up to date for
+    /// configuration `version` and publish its identity
+    ///
+    /// A node without a key pair generates one. An expired key pair is not
+    /// replaced right away: its successor is staged and published next to
+    /// it, and every node switches to it with the next configuration
+    /// version, so the mesh keeps working until the cluster reloads.
+    pub async fn sync_key(&self, config: &ZoneConfig, version: u64) -> Result<WireguardPeer> {
+        let zone = &config.zone;
+        let private_key = self.private_key_path(zone);
+        let key_exists = tokio::fs::try_exists(&private_key).await.unwrap_or(false);
+        let published = self.store.read(&self.node).await?.zones.remove(zone);
+
+        let others = self
+            .store
+            .zone_peers(zone, &WireguardZone::other_nodes(config, &self.node))
+            .await?;
+        let address = WireguardZone::assign_mesh_address(
+            config,
+            &self.node,
+            published.as_ref().map(|peer| peer.address),
+            &others,
+        )?;
+        let endpoint = self.store.cluster_address(&self.node).await?;
+
+        let mut peer = match (key_exists, &published) {
+            (true, Some(peer)) => peer.clone(),
+            (true, None) => WireguardPeer {
+                public_key: self.public_key(&private_key).await?,
+                address,
+                endpoint,
+                created: Utc::now(),
+                next: None,
+            },
+            (false, _) => {
+                // A lost private key cannot be replaced gracefully, the
+                // other nodes pick up the new key with their next apply
+                self.generate_private_key(&private_key).await?;
+                let public_key = self.public_key(&private_key).await?;
+                self.load_private_key(zone).await?;
+                info!(
+                    "Generated WireGuard key of node '{}' in zone '{}'",
+                    self.node, zone
+                );
+                WireguardPeer {
+                    public_key,
+                    address,
+                    endpoint,
+                    created: Utc::now(),
+                    next: None,
+                }
+            }
+        };
+
+        match &peer.next {
+            Some(next) if next.version <= version => {
+                self.activate_key(zone).await?;
+                peer = peer.at_version(version);
+            }
+            None if key_expired(config, &peer) => {
+                peer.next = Some(self.stage_key(zone, version + 1).await?);
+            }
+            _ => {}
+        }
+
+        // Keep endpoint and address current
+        peer.address = address;
+        peer.endpoint = endpoint;
+        if published.as_ref() != Some(&peer) {
+            self.publish(zone, &peer).await?;
+        }
+
+        Ok(peer)
+    }
+
+    /// Generate the key pair replacing the current one in `zone` from
+    /// configuration `version` on
+    async fn stage_key(&self, zone: &str, version: u64) -> Result<StagedWireguardKey> {
+        let private_key = self.next_private_key_path(zone);
+        self.generate_private_key(&private_key).await?;
+
+        info!(
+            "Staged new WireGuard key of node '{}' in zone '{}' for configuration version {}",
+            self.node, zone, version
+        );
+        Ok(StagedWireguardKey {
+            public_key: self.public_key(&private_key).await?,
+            version,
+            created: Utc::now(),
+        })
+    }
+
+    /// Replace the key pair in `zone` with the staged one
+    ///
+    /// The running interface switches to the new private key right away,
+    /// the other nodes load the new public key with the same configuration
+    /// version.
+    async fn activate_key(&self, zone: &str) -> Result<()> {
+        let private_key = self.private_key_path(zone);
+        let next = self.next_private_key_path(zone);
+        tokio::fs::rename(&next, &private_key)
+            .await
+            .with_context(|| format!("Failed to activate {}", next.display()))?;
+        self.load_private_key(zone).await?;
+
+        info!(
+            "Switched to the new WireGuard key of node '{}' in zone '{}'",
+            self.node, zone
+        );
+        Ok(())
+    }
+
+    /// Load the private key of `zone` into its running interface, if any
+    async fn load_private_key(&self, zone: &str) -> Result<()> {
+        let private_key = self.private_key_path(zone);
+        let interface = wireguard_interface_name(zone);
+        let interface_exists = self
+            .executor
+            .query("ip", &["link", "show", &interface])
+            .await
+            .map(|output| output.is_success())
+            .unwrap_or(false);
+        if interface_exists {
+            let private_key = private_key.to_string_lossy();
+            let output = self
+                .executor
+                .execute("wg", &["set", &interface, "private-key", &private_key])
+                .await?;
+            if !output.is_success() {
+                anyhow::bail!(
+                    "Failed to load the new private key into '{}': {}",
+                    interface,
+                    output.stderr.trim()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delete the key pair of the local node in `zone`
+    pub async fn remove_key(&self, zone: &str) -> Result<()> {
+        for private_key in [
+            self.private_key_path(zone),
+            self.next_private_key_path(zone),
+        ] {
+            match tokio::fs::remove_file(&private_key).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to remove {}", private_key.display()))
+                }
+            }
+        }
+
+        let mut keys = self.store.read(&self.node).await?;
+        if keys.zones.remove(zone).is_some() {
+            self.store.publish(&keys).await?;
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, zone: &str, peer: &WireguardPeer) -> Result<()> {
+        let mut keys = self.store.read(&self.node).await?;
+        keys.node = self.node.clone();
+        keys.zones.insert(zone.to_string(), peer.clone());
+        self.store.publish(&keys).await
+    }
+
+    async fn generate_private_key(&self, path: &Path) -> Result<()> {
+        let output = self
+            .executor
+            .query("wg", &["genkey"])
+            .await
+            .context("Failed to generate WireGuard key")?;
+        let key = output.stdout.trim();
+        if !output.is_success() || !is_wireguard_key(key) {
+            anyhow::bail!("Failed to generate WireGuard key: {}", output.stderr.trim());
+        }
+
+        write_private_key(path, key).await
+    }
+
+    async fn public_key(&self, private_key: &Path) -> Result<String> {
+        // wg pubkey only reads the private key from stdin
+        let private_key = private_key.to_string_lossy();
+        let output = self
+            .executor
+            .query("sh", &["-c", "wg pubkey < \"$1\"", "sh", &private_key])
+            .await
+            .context("Failed to derive WireGuard public key")?;
+        let key = output.stdout.trim();
+        if !output.is_success() || !is_wireguard_key(key) {
+            anyhow::bail!(
+                "Failed to derive WireGuard public key: {}",
+                output.stderr.trim()
+            );
+        }
+
+        Ok(key.to_string())
+    }
+}
+
+/// Whether the key pair of `peer` is older than the zone's `key-lifetime`
+fn key_expired(config: &ZoneConfig, peer: &WireguardPeer) -> bool {
+    match config.options.get("key-lifetime").and_then(|v| v.as_u64()) {
+        Some(days) => peer.created + chrono::Duration::days(days as i64) <= Utc::now(),
+        None => false,
+    }
+}
+
+/// Write a private key readable by root only
+async fn write_private_key(path: &Path, key: &str) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::io::AsyncWriteExt;
+
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        tokio::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .await
+            .with_context(|| format!("Failed to restrict {}", dir.display()))?;
+    }
+
+    let tmp = path.with_extension("key.tmp");
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .await
+        .with_context(|| format!("Failed to create {}", tmp.display()))?;
+    file.write_all(format!("{}\n", key).as_bytes())
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    file.sync_all().await?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+/// WireGuard zone implementation
+///
+/// WireGuard zones create an encrypted Layer 2 overlay between their nodes.
+/// Key features:
+/// - Full WireGuard mesh between the zone `nodes`, addressed from `wg-network`
+/// - VXLAN (VNI from `vni`) between the mesh addresses
+/// - Per-node key pairs with public keys distributed through pmxcfs
+/// - Optional key rotation after `key-lifetime` days
+/// - MTU validated against both encapsulations over `underlay-mtu`
+pub struct WireguardZone {
+    name: String,
+    executor: Arc<dyn SystemExecutor>,
+    pmxcfs_path: PathBuf,
+    wireguard_path: PathBuf,
+}
+
+impl WireguardZone {
+    /// Create new WireGuard zone
+    pub fn new(name: String) -> Self {
+        Self::with_executor(name, Arc::new(RealExecutor::new()))
+    }
+
+    /// Create new WireGuard zone running system commands through `executor`
+    pub fn with_executor(name: String, executor: Arc<dyn SystemExecutor>) -> Self {
+        Self {
+            name,
+            executor,
+            pmxcfs_path: PathBuf::from(DEFAULT_PMXCFS_PATH),
+            wireguard_path: PathBuf::from(DEFAULT_SDN_WIREGUARD_PATH),
+        }
+    }
+
+    /// Override the pmxcfs mount point and the node-local WireGuard directory
+    pub fn with_paths(
+        mut self,
+        pmxcfs_path: impl Into<PathBuf>,
+        wireguard_path: impl Into<PathBuf>,
+    ) -> Self {
+        self.pmxcfs_path = pmxcfs_path.into();
+        self.wireguard_path = wireguard_path.into();
+        self
+    }
+
+    /// Key manager of `node`
+    pub fn key_manager(&self, node: &str) -> WireguardKeyManager {
+        WireguardKeyManager::new(node, self.executor.clone())
+            .with_paths(&self.pmxcfs_path, &self.wireguard_path)
+    }
+
+    fn vni(config: &ZoneConfig) -> Option<u32> {
+        config
+            .options
+            .get("vni")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+    }
+
+    fn mesh_network(config: &ZoneConfig) -> Option<IpNet> {
+        config.options.get("wg-network")?.as_str()?.parse().ok()
+    }
+
+    /// Preferred mesh address of `node`
+    ///
+    /// The host addresses of `wg-network` in the order of the sorted node
+    /// names, so nodes set up together get distinct addresses without
+    /// coordinating with each other.
+    pub fn mesh_address(config: &ZoneConfig, node: &str) -> Result<IpAddr> {
+        let network = Self::mesh_network(config)
+            .with_context(|| format!("Zone '{}' has no valid wg-network", config.zone))?;
+        let mut nodes: Vec<&String> = config.nodes.iter().flatten().collect();
+        nodes.sort();
+        nodes.dedup();
+
+        let index = nodes
+            .iter()
+            .position(|n| n.as_str() == node)
+            .with_context(|| format!("Node '{}' is not part of zone '{}'", node, config.zone))?;
+        network.hosts().nth(index).with_context(|| {
+            format!(
+                "No address left in {} for node '{}' in zone '{}'",
+                network, node, config.zone
+            )
+        })
+    }
+
+    /// Mesh address of `node`, given its published address and the
+    /// identities published by the other zone nodes
+    ///
+    /// A node keeps the address it published, so nodes joining or leaving
+    /// the zone never renumber the others. A node without one takes its
+    /// preferred address, or else the first one no other node uses. Of two
+    /// nodes publishing the same address the one sorting first keeps it.
+    pub fn assign_mesh_address(
+        config: &ZoneConfig,
+        node: &str,
+        published: Option<IpAddr>,
+        others: &BTreeMap<String, WireguardPeer>,
+    ) -> Result<IpAddr> {
+        let network = Self::mesh_network(config)
+            .with_context(|| format!("Zone '{}' has no valid wg-network", config.zone))?;
+
+        if let Some(address) = published.filter(|address| network.contains(address)) {
+            let claimed = others
+                .iter()
+                .any(|(other, peer)| peer.address == address && other.as_str() < node);
+            if !claimed {
+                return Ok(address);
+            }
+        }
+
+        let used: Vec<IpAddr> = others.values().map(|peer| peer.address).collect();
+        let preferred = Self::mesh_address(config, node)?;
+        if !used.contains(&preferred) {
+            return Ok(preferred);
+        }
+
+        network
+            .hosts()
+            .find(|address| !used.contains(address))
+            .with_context(|| {
+                format!(
+                    "No address left in {} for node '{}' in zone '{}'",
+                    network, node, config.zone
+                )
+            })
+    }
+
+    /// Zone nodes other than `node`
+    fn other_nodes(config: &ZoneConfig, node: &str) -> Vec<String> {
+        config
+            .nodes
+            .iter()
+            .flatten()
+            .filter(|n| n.as_str() != node)
+            .cloned()
+            .collect()
+    }
+
+    fn wireguard_port(config: &ZoneConfig) -> u16 {
+        config
+            .options
+            .get("wg-port")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u16)
+            .unwrap_or(DEFAULT_WIREGUARD_PORT)
+    }
+
+    fn underlay_mtu(config: &ZoneConfig) -> u16 {
+        config
+            .options
+            .get("underlay-mtu")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u16)
+            .unwrap_or(DEFAULT_UNDERLAY_MTU)
+    }
+
+    /// VXLAN overhead inside the mesh
+    fn mesh_vxlan_overhead(config: &ZoneConfig) -> u16 {
+        vxlan_overhead(
+            Self::mesh_network(config)
+                .map(|net| matches!(net, IpNet::V6(_)))
+                .unwrap_or(false),
+        )
+    }
+
+    /// MTU of the WireGuard interface
+    fn wireguard_mtu(config: &ZoneConfig) -> u16 {
+        Self::underlay_mtu(config) - WIREGUARD_OVERHEAD
+    }
+
+    /// MTU of the VXLAN device and bridge
+    ///
+    /// Defaults to the WireGuard MTU minus the VXLAN overhead.
+    fn vxlan_mtu(config: &ZoneConfig) -> u16 {
+        config
+            .mtu
+            .unwrap_or_else(|| Self::wireguard_mtu(config) - Self::mesh_vxlan_overhead(config))
+    }
+
+    fn get_vxlan_interface_name(&self, vni: u32) -> String {
+        format!("vxlan{}", vni)
+    }
+
+    /// Node name of the local node
+    async fn local_node(&self) -> Result<String> {
+        let output = self
+            .executor
+            .query("hostname", &[])
+            .await
+            .context("Failed to get the node name")?;
+        let hostname = output.stdout.trim();
+        if !output.is_success() || hostname.is_empty() {
+            anyhow::bail!("Failed to get the node name: {}", output.stderr.trim());
+        }
+
+        Ok(hostname.split('.').next().unwrap_or(hostname).to_string())
+    }
+
+    /// Configuration version the cluster runs, from pmxcfs
+    async fn running_version(&self) -> Result<u64> {
+        let running = SdnStatusStore::with_base_path(&self.pmxcfs_path)
+            .read_running_config()
+            .await?;
+        Ok(running
+            .map(|config| config.running_version())
+            .unwrap_or_else(|| SdnConfiguration::new().running_version()))
+    }
+
+    /// Mesh address of `node` and the identities of the other zone nodes
+    /// that published one, as of configuration `version`
+    ///
+    /// Only reads the published identities, the keys of `node` are managed
+    /// by `WireguardKeyManager::sync_key`.
+    async fn mesh(
+        &self,
+        config: &ZoneConfig,
+        node: &str,
+        version: u64,
+    ) -> Result<(IpAddr, BTreeMap<String, WireguardPeer>)> {
+        let keys = self.key_manager(node);
+        let others = Self::other_nodes(config, node);
+        let remotes: BTreeMap<String, WireguardPeer> = keys
+            .store()
+            .zone_peers(&config.zone, &others)
+            .await?
+            .into_iter()
+            .map(|(other, peer)| (other, peer.at_version(version)))
+            .collect();
+        for other in &others {
+            if !remotes.contains_key(other) {
+                warn!(
+                    "Node '{}' has not published a WireGuard key for zone '{}' yet",
+                    other, self.name
+                );
+            }
+        }
+
+        let published = keys.store().read(node).await?.zones.remove(&config.zone);
+        let address =
+            Self::assign_mesh_address(config, node, published.map(|peer| peer.address), &remotes)?;
+
+        Ok((address, remotes))
+    }
+
+    /// Validate WireGuard-specific configuration parameters
+    fn validate_wireguard_config(&self, config: &ZoneConfig) -> Result<()> {
+        let interface = wireguard_interface_name(&config.zone);
+        if interface.len() > 15 {
+            anyhow::bail!(
+                "WireGuard zone '{}' name is too long for interface '{}'",
+                self.name,
+                interface
+            );
+        }
+
+        // The mesh spans the zone nodes
+        let nodes = config.nodes.as_deref().unwrap_or_default();
+        if nodes.is_empty() {
+            anyhow::bail!("WireGuard zone '{}' requires nodes", self.name);
+        }
+
+        // Validate VNI (VXLAN Network Identifier)
+        match config.options.get("vni").map(|v| v.as_u64()) {
+            Some(Some(vni)) if vni > 0 && vni <= 16777215 => {}
+            Some(_) => anyhow::bail!(
+                "WireGuard zone '{}' VNI must be between 1 and 16777215",
+                self.name
+            ),
+            None => anyhow::bail!("WireGuard zone '{}' requires a VNI", self.name),
+        }
+
+        // Validate the mesh network, every node needs an address
+        let network_str = config
+            .options
+            .get("wg-network")
+            .and_then(|v| v.as_str())
+            .with_context(|| format!("WireGuard zone '{}' requires a wg-network", self.name))?;
+        let network: IpNet = network_str.parse().with_context(|| {
+            format!(
+                "Invalid wg-network '{}' for WireGuard zone '{}'",
+                network_str, self.name
+            )
+        })?;
+        if network.hosts().take(nodes.len()).count() < nodes.len() {
+            anyhow::bail!(
+                "WireGuard zone '{}' wg-network {} is too small for {} nodes",
+                self.name,
+                network,
+                nodes.len()
+            );
+        }
+
+        // Validate WireGuard port
+        if let Some(port) = config.options.get("wg-port") {
+            match port.as_u64() {
+                Some(port) if port > 0 && port <= u16::MAX as u64 => {}
+                _ => anyhow::bail!(
+                    "WireGuard zone '{}' port must be between 1 and 65535",
+                    self.name
+                ),
+            }
+        }
+
+        if let Some(lifetime) = config.options.get("key-lifetime") {
+            match lifetime.as_u64() {
+                Some(days) if days > 0 => {}
+                _ => anyhow::bail!(
+                    "WireGuard zone '{}' key lifetime must be a positive number of days",
+                    self.name
+                ),
+            }
+        }
+
+        // Both encapsulations have to fit into the underlay MTU
+        if let Some(underlay_mtu) = config.options.get("underlay-mtu") {
+            match underlay_mtu.as_u64() {
+                Some(mtu) if (1280..=65535).contains(&mtu) => {}
+                _ => anyhow::bail!(
+                    "WireGuard zone '{}' underlay MTU must be between 1280 and 65535",
+                    self.name
+                ),
+            }
+        }
+
+        if let Some(mtu) = config.mtu {
+            let underlay_mtu = Self::underlay_mtu(config);
+            let vxlan = Self::mesh_vxlan_overhead(config);
+            if mtu as u32 + vxlan as u32 + WIREGUARD_OVERHEAD as u32 > underlay_mtu as u32 {
+                anyhow::bail!(
+                    "WireGuard zone '{}' MTU {} needs an underlay MTU of at least {} ({} bytes VXLAN and {} bytes WireGuard overhead), the underlay MTU is {}",
+                    self.name,
+                    mtu,
+                    mtu as u32 + vxlan as u32 + WIREGUARD_OVERHEAD as u32,
+                    vxlan,
+                    WIREGUARD_OVERHEAD,
+                    underlay_mtu
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate the WireGuard config loaded with `wg setconf`
+    ///
+    /// The private key is loaded separately from the node-local key file, so
+    /// the config only holds public information.
+    fn generate_wireguard_config(
+        &self,
+        config: &ZoneConfig,
+        remotes: &BTreeMap<String, WireguardPeer>,
+    ) -> String {
+        let port = Self::wireguard_port(config);
+        let mut wg_config = format!("[Interface]\nListenPort = {}\n", port);
+
+        for (node, peer) in remotes {
+            wg_config.push_str(&format!(
+                "\n\
+                 [Peer]\n\
+                 # {node}\n\
+                 PublicKey = {public_key}\n\
+                 Endpoint = {endpoint}\n\
+                 AllowedIPs = {allowed_ips}\n\
+                 PersistentKeepalive = {keepalive}\n",
+                public_key = peer.public_key,
+                endpoint = SocketAddr::new(peer.endpoint, port),
+                allowed_ips = IpNet::from(peer.address),
+                keepalive = PERSISTENT_KEEPALIVE,
+            ));
+        }
+
+        wg_config
+    }
+
+    /// Generate the WireGuard interface configuration
+    fn generate_wireguard_interface_config(
+        &self,
+        config: &ZoneConfig,
+        local: IpAddr,
+    ) -> Result<String> {
+        let network = Self::mesh_network(config).context("wg-network is missing")?;
+        let interface = wireguard_interface_name(&config.zone);
+        let family = if network.addr().is_ipv6() {
+            "inet6"
+        } else {
+            "inet"
+        };
+
+        Ok(format!(
+            "auto {interface}\n\
+             iface {interface} {family} static\n\
+             \taddress {address}/{prefix}\n\
+             \tpre-up ip link add {interface} type wireguard\n\
+             \tpre-up wg setconf {interface} {wg_config}\n\
+             \tpre-up wg set {interface} private-key {private_key}\n\
+             \tpost-down ip link del {interface}\n\
+             \tmtu {mtu}\n\
+             {alias}",
+            address = local,
+            prefix = network.prefix_len(),
+            wg_config = self
+                .wireguard_path
+                .join(format!("{}.conf", interface))
+                .display(),
+            private_key = self
+                .key_manager("")
+                .private_key_path(&config.zone)
+                .display(),
+            mtu = Self::wireguard_mtu(config),
+            alias = owner_alias_attribute(config),
+        ))
+    }
+
+    /// Generate the VXLAN interface configuration over the mesh
+    fn generate_vxlan_interface_config(
+        &self,
+        config: &ZoneConfig,
+        local: IpAddr,
+        remotes: &BTreeMap<String, WireguardPeer>,
+    ) -> Result<String> {
+        let vni = Self::vni(config).context("VNI is missing")?;
+        let vxlan_interface = self.get_vxlan_interface_name(vni);
+
+        let mut vxlan_config = format!(
+            "auto {vxlan_interface}\n\
+             iface {vxlan_interface} inet manual\n\
+             \tvxlan-id {vni}\n\
+             \tvxlan-port {vxlan_port}\n\
+             \tvxlan-local-tunnelip {local}\n",
+            vxlan_port = config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT),
+            local = local,
+        );
+
+        for peer in remotes.values() {
+            vxlan_config.push_str(&format!("\tvxlan-remoteip {}\n", peer.address));
+        }
+
+        vxlan_config.push_str(&format!("\tmtu {}\n", Self::vxlan_mtu(config)));
+        vxlan_config.push_str(&owner_alias_attribute(config));
+
+        Ok(vxlan_config)
+    }
+
+    /// Generate bridge configuration for the overlay
+    fn generate_bridge_config(&self, config: &ZoneConfig, bridge: &str) -> Result<String> {
+        let vni = Self::vni(config).context("VNI is missing")?;
+
+        let mut bridge_config = format!(
+            "auto {bridge}\n\
+             iface {bridge} inet manual\n\
+             \tbridge_ports {vxlan_interface}\n\
+             \tbridge_stp off\n\
+             \tbridge_fd 0\n",
+            vxlan_interface = self.get_vxlan_interface_name(vni),
+        );
+
+        // Add VLAN awareness if specified
+        if config.vlan_aware.unwrap_or(false) {
+            bridge_config.push_str("\tbridge_vlan_aware yes\n");
+        }
+
+        bridge_config.push_str(&format!("\tmtu {}\n", Self::vxlan_mtu(config)));
+        bridge_config.push_str(&owner_alias_attribute(config));
+
+        Ok(bridge_config)
+    }
+
+    async fn link_exists(&self, name: &str) -> bool {
+        self.executor
+            .query("ip", &["link", "show", name])
+            .await
+            .map(|output| output.is_success())
+            .unwrap_or(false)
+    }
+
+    /// Addresses configured on `interface`
+    async fn interface_addresses(&self, interface: &str) -> Vec<IpAddr> {
+        match self
+            .executor
+            .query("ip", &["-j", "addr", "show", "dev", interface])
+            .await
+        {
+            Ok(output) if output.is_success() => parse_local_addresses(&output.stdout),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Public keys of the peers configured on `interface`
+    async fn configured_peers(&self, interface: &str) -> Result<Vec<String>> {
+        let output = self
+            .executor
+            .query("wg", &["show", interface, "peers"])
+            .await
+            .with_context(|| format!("Failed to list the peers of '{}'", interface))?;
+        if !output.is_success() {
+            anyhow::bail!(
+                "Failed to list the peers of '{}': {}",
+                interface,
+                output.stderr.trim()
+            );
+        }
+
+        Ok(output
+            .stdout
+            .lines()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Destinations BUM traffic of `interface` is currently flooded to
+    async fn flood_destinations(&self, interface: &str) -> Vec<IpAddr> {
+        match self
+            .executor
+            .query("bridge", &["-j", "fdb", "show", "dev", interface])
+            .await
+        {
+            Ok(output) if output.is_success() => parse_flood_destinations(&output.stdout),
+            _ => Vec::new(),
+        }
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> Result<()> {
+        let output = self
+            .executor
+            .execute(program, args)
+            .await
+            .with_context(|| format!("Failed to run {}", SystemCommand::new(program, args)))?;
+
+        if !output.is_success() {
+            anyhow::bail!(
+                "{} failed: {}",
+                SystemCommand::new(program, args),
+                output.stderr.trim()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Zone for WireguardZone {
+    fn zone_type(&self) -> ZoneType {
+        ZoneType::Wireguard
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate_config(&self, config: &ZoneConfig) -> Result<()> {
+        debug!("Validating WireGuard zone '{}' configuration", self.name);
+
+        // Basic validation
+        config.validate().with_context(|| {
+            format!("Basic validation failed for WireGuard zone '{}'", self.name)
+        })?;
+
+        // WireGuard-specific validation
+        self.validate_wireguard_config(config).with_context(|| {
+            format!(
+                "WireGuard-specific validation failed for zone '{}'",
+                self.name
+            )
+        })?;
+
+        info!(
+            "WireGuard zone '{}' configuration validation successful",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn apply_config(&self, config: &ZoneConfig) -> Result<()> {
+        debug!("Applying WireGuard zone '{}' configuration", self.name);
+
+        // Validate configuration first
+        self.validate_config(config).await.with_context(|| {
+            format!(
+                "Configuration validation failed for WireGuard zone '{}'",
+                self.name
+            )
+        })?;
+
+        let node = self.local_node().await?;
+        let version = self.running_version().await?;
+        self.key_manager(&node).sync_key(config, version).await?;
+        let (local, remotes) = self.mesh(config, &node, version).await?;
+        let network = Self::mesh_network(config).context("wg-network is missing")?;
+        let port = Self::wireguard_port(config).to_string();
+        let interface = wireguard_interface_name(&config.zone);
+
+        let created = !self.link_exists(&interface).await;
+        if created {
+            info!(
+                "Creating WireGuard interface '{}' for zone '{}'",
+                interface, self.name
+            );
+
+            self.run("ip", &["link", "add", &interface, "type", "wireguard"])
+                .await?;
+            mark_owned(self.executor.as_ref(), &interface, config).await?;
+
+            let address = format!("{}/{}", local, network.prefix_len());
+            self.run("ip", &["address", "add", &address, "dev", &interface])
+                .await?;
+
+            let mtu = Self::wireguard_mtu(config).to_string();
+            self.run("ip", &["link", "set", &interface, "mtu", &mtu])
+                .await?;
+        }
+
+        // Existing interfaces follow a changed address, e.g. a new wg-network
+        let readdressed = !created && !self.interface_addresses(&interface).await.contains(&local);
+        if readdressed {
+            let address = format!("{}/{}", local, network.prefix_len());
+            self.run("ip", &["address", "flush", "dev", &interface])
+                .await?;
+            self.run("ip", &["address", "add", &address, "dev", &interface])
+                .await?;
+        }
+
+        // Keys and peers are refreshed on every apply, so rotated keys of
+        // any node take effect
+        let private_key = self.key_manager(&node).private_key_path(&config.zone);
+        let private_key = private_key.to_string_lossy();
+        self.run(
+            "wg",
+            &[
+                "set",
+                &interface,
+                "listen-port",
+                &port,
+                "private-key",
+                &private_key,
+            ],
+        )
+        .await?;
+
+        // Peers of departed nodes and replaced keys are removed
+        if !created {
+            for key in self.configured_peers(&interface).await? {
+                if !remotes.values().any(|peer| peer.public_key == key) {
+                    self.run("wg", &["set", &interface, "peer", &key, "remove"])
+                        .await?;
+                }
+            }
+        }
+
+        for peer in remotes.values() {
+            let endpoint = SocketAddr::new(peer.endpoint, Self::wireguard_port(config)).to_string();
+            let allowed_ips = IpNet::from(peer.address).to_string();
+            let keepalive = PERSISTENT_KEEPALIVE.to_string();
+            self.run(
+                "wg",
+                &[
+                    "set",
+                    &interface,
+                    "peer",
+                    &peer.public_key,
+                    "endpoint",
+                    &endpoint,
+                    "allowed-ips",
+                    &allowed_ips,
+                    "persistent-keepalive",
+                    &keepalive,
+                ],
+            )
+            .await?;
+        }
+
+        if created {
+            self.run("ip", &["link", "set", &interface, "up"]).await?;
+        }
+
+        // VXLAN between the mesh addresses
+        let vni = Self::vni(config).context("VNI is missing")?;
+        let vxlan_interface = self.get_vxlan_interface_name(vni);
+        let vxlan_created = !self.link_exists(&vxlan_interface).await;
+        if vxlan_created {
+            info!(
+                "Creating VXLAN interface '{}' for WireGuard zone '{}'",
+                vxlan_interface, self.name
+            );
+
+            let vni_str = vni.to_string();
+            let vxlan_port = config.vxlan_port.unwrap_or(DEFAULT_VXLAN_PORT).to_string();
+            let local_str = local.to_string();
+            self.run(
+                "ip",
+                &[
+                    "link",
+                    "add",
+                    &vxlan_interface,
+                    "type",
+                    "vxlan",
+                    "id",
+                    &vni_str,
+                    "dstport",
+                    &vxlan_port,
+                    "local",
+                    &local_str,
+                    "dev",
+                    &interface,
+                ],
+            )
+            .await?;
+            mark_owned(self.executor.as_ref(), &vxlan_interface, config).await?;
+
+            let mtu = Self::vxlan_mtu(config).to_string();
+            self.run("ip", &["link", "set", &vxlan_interface, "mtu", &mtu])
+                .await?;
+            self.run("ip", &["link", "set", &vxlan_interface, "up"])
+                .await?;
+        } else if readdressed {
+            let local_str = local.to_string();
+            self.run(
+                "ip",
+                &[
+                    "link",
+                    "set",
+                    &vxlan_interface,
+                    "type",
+                    "vxlan",
+                    "local",
+                    &local_str,
+                ],
+            )
+            .await?;
+        }
+
+        // Head-end replication: flood BUM traffic to every remote node,
+        // synced on every apply as nodes join and leave
+        let destinations: Vec<IpAddr> = remotes.values().map(|peer| peer.address).collect();
+        let current = if vxlan_created {
+            Vec::new()
+        } else {
+            self.flood_destinations(&vxlan_interface).await
+        };
+        for stale in current.iter().filter(|dst| !destinations.contains(dst)) {
+            let dst = stale.to_string();
+            self.run(
+                "bridge",
+                &[
+                    "fdb",
+                    "del",
+                    FLOOD_MAC,
+                    "dev",
+                    &vxlan_interface,
+                    "dst",
+                    &dst,
+                ],
+            )
+            .await?;
+        }
+        for peer in destinations.iter().filter(|dst| !current.contains(dst)) {
+            let dst = peer.to_string();
+            self.run(
+                "bridge",
+                &[
+                    "fdb",
+                    "append",
+                    FLOOD_MAC,
+                    "dev",
+                    &vxlan_interface,
+                    "dst",
+                    &dst,
+                ],
+            )
+            .await?;
+        }
+
+        // Create bridge if specified
+        if let Some(bridge) = &config.bridge {
+            if !self.link_exists(bridge).await {
+                info!(
+                    "Creating bridge '{}' for WireGuard zone '{}'",
+                    bridge, self.name
+                );
+
+                self.run("ip", &["link", "add", "name", bridge, "type", "bridge"])
+                    .await?;
+                mark_owned(self.executor.as_ref(), bridge, config).await?;
+                self.run("ip", &["link", "set", &vxlan_interface, "master", bridge])
+                    .await?;
+
+                // Configure VLAN awareness if specified
+                if config.vlan_aware.unwrap_or(false) {
+                    self.run(
+                        "ip",
+                        &[
+                            "link",
+                            "set",
+                            bridge,
+                            "type",
+                            "bridge",
+                            "vlan_filtering",
+                            "1",
+                        ],
+                    )
+                    .await?;
+                }
+
+                self.run("ip", &["link", "set", bridge, "up"]).await?;
+            }
+        }
+
+        info!(
+            "WireGuard zone '{}' configuration applied successfully",
+            self.name
+        );
+        Ok(())
+    }
+
+    async fn generate_config(&self, config: &ZoneConfig) -> Result<HashMap<String, String>> {
+        let node = self.local_node().await?;
+        let sdn = SdnConfiguration::new();
+        self.generate_node_config(config, &ZoneRenderContext::new(&node, &sdn))
+            .await
+    }
+
+    async fn generate_node_config(
+        &self,
+        config: &ZoneConfig,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<HashMap<String, String>> {
+        debug!(
+            "Generating configuration files for WireGuard zone '{}' on node '{}'",
+            self.name, ctx.node
+        );
+
+        self.validate_config(config).await?;
+        let (local, remotes) = self
+            .mesh(config, ctx.node, ctx.sdn.running_version())
+            .await?;
+
+        let mut configs = HashMap::new();
+
+        let interface = wireguard_interface_name(&config.zone);
+        configs.insert(
+            format!("{}{}", WIREGUARD_CONFIG_KEY_PREFIX, interface),
+            self.generate_wireguard_config(config, &remotes),
+        );
+        configs.insert(
+            "wireguard".to_string(),
+            self.generate_wireguard_interface_config(config, local)?,
+        );
+        configs.insert(
+            "vxlan".to_string(),
+            self.generate_vxlan_interface_config(config, local, &remotes)?,
+        );
+        if let Some(bridge) = &config.bridge {
+            configs.insert(
+                "bridge".to_string(),
+                self.generate_bridge_config(config, bridge)?,
+            );
+        }
+
+        // Generate zone-specific metadata
+        let metadata = format!(
+            "# WireGuard Zone Configuration\n\
+             # Zone: {}\n\
+             # Type: WireGuard\n\
+             # VNI: {}\n\
+             # Interface: {}\n\
+             # Address: {}\n\
+             # Peers: {}\n",
+            self.name,
+            Self::vni(config).unwrap_or_default(),
+            interface,
+            local,
+            remotes.keys().cloned().collect::<Vec<_>>().join(", "),
+        );
+        configs.insert("metadata".to_string(), metadata);
+
+        info!(
+            "Generated configuration files for WireGuard zone '{}'",
+            self.name
+        );
+        Ok(configs)
+    }
+
+    async fn prepare_node_config(
+        &self,
+        config: &ZoneConfig,
+        ctx: &ZoneRenderContext<'_>,
+    ) -> Result<()> {
+        self.key_manager(ctx.node)
+            .sync_key(config, ctx.sdn.running_version())
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_config(&self, config: &ZoneConfig) -> Result<()> {
+        let state = self.observed_state(config).await?;
+        let removed = remove_owned_devices(self.executor.as_ref(), &state).await?;
+
+        info!(
+            "Removed devices {:?} of WireGuard zone '{}'",
+            removed, self.name
+        );
+        Ok(())
+    }
+
+    async fn observed_state(&self, config: &ZoneConfig) -> Result<ZoneObservedState> {
+        let mut state = ZoneObservedState::new(&self.name);
+
+        let interface = wireguard_interface_name(&config.zone);
+        state
+            .devices
+            .push(observe_device(self.executor.as_ref(), &interface, "wireguard").await?);
+
+        if let Some(vni) = Self::vni(config) {
+            let vxlan_interface = self.get_vxlan_interface_name(vni);
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), &vxlan_interface, "vxlan").await?);
+        }
+
+        if let Some(bridge) = &config.bridge {
+            state
+                .devices
+                .push(observe_device(self.executor.as_ref(), bridge, "bridge").await?);
+        }
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn wireguard_zone_config() -> ZoneConfig {
+        let mut config = ZoneConfig::new(ZoneType::Wireguard, "secure".to_string());
+        config.bridge = Some("wgbr0".to_string());
+        config.nodes = Some(vec!["node1".to_string(), "node2".to_string()]);
+        config.options.insert("vni".to_string(), json!(100));
+        config
+            .options
+            .insert("wg-network".to_string(), json!("10.255.0.0/24"));
+        config
+    }
+
+    #[tokio::test]
+    async fn test_wireguard_zone_validation() {
+        let zone = WireguardZone::new("secure".to_string());
+
+        let mut config = wireguard_zone_config();
+        assert!(zone.validate_config(&config).await.is_ok());
+
+        // Test invalid VNI
+        config.options.insert("vni".to_string(), json!(0));
+        assert!(zone.validate_config(&config).await.is_err());
+        config.options.insert("vni".to_string(), json!(100));
+
+        // Test mesh network too small for the nodes
+        config
+            .options
+            .insert("wg-network".to_string(), json!("10.255.0.0/31"));
+        config.nodes = Some(vec![
+            "node1".to_string(),
+            "node2".to_string(),
+            "node3".to_string(),
+        ]);
+        assert!(zone.validate_config(&config).await.is_err());
+        config
+            .options
+            .insert("wg-network".to_string(), json!("10.255.0.0/24"));
+
+        // Test missing nodes
+        config.nodes = None;
+        assert!(zone.validate_config(&config).await.is_err());
+        config.nodes = Some(vec!["node1".to_string()]);
+
+        // Test zone name too long for the interface
+        config.zone = "averylongzone".to_string();
+        assert!(zone.validate_config(&config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wireguard_mtu_stacking() {
+        let zone = WireguardZone::new("secure".to_string());
+        let mut config = wireguard_zone_config();
+
+        // 80 bytes WireGuard plus 50 bytes VXLAN overhead
+        assert_eq!(WireguardZone::wireguard_mtu(&config), 1420);
+        assert_eq!(WireguardZone::vxlan_mtu(&config), 1370);
+
+        config.mtu = Some(1370);
+        assert!(zone.validate_config(&config).await.is_ok());
+
+        config.mtu = Some(1400);
+        let err = zone.validate_config(&config).await.unwrap_err();
+        assert!(format!("{:#}", err).contains(
+            "MTU 1400 needs an underlay MTU of at least 1530 (50 bytes VXLAN and 80 bytes WireGuard overhead), the underlay MTU is 1500"
+        ));
+
+        // Jumbo frames on the underlay leave room for it
+        config
+            .options
+            .insert("underlay-mtu".to_string(), json!(9000));
+        assert!(zone.validate_config(&config).await.is_ok());
+
+        // An IPv6 mesh needs 70 bytes for VXLAN
+        config.mtu = None;
+        config.options.remove("underlay-mtu");
+        config
+            .options
+            .insert("wg-network".to_string(), json!("fd00:255::/64"));
+        assert_eq!(WireguardZone::vxlan_mtu(&config), 1350);
+    }
+
+    #[test]
+    fn test_wireguard_mesh_address() {
+        let mut config = wireguard_zone_config();
+        config.nodes = Some(vec![
+            "node3".to_string(),
+            "node1".to_string(),
+            "node2".to_string(),
+        ]);
+
+        // Addresses follow the sorted node names, not the configured order
+        let address = |node| WireguardZone::mesh_address(&config, node).unwrap();
+        assert_eq!(address("node1"), "10.255.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(address("node2"), "10.255.0.2".parse::<IpAddr>().unwrap());
+        assert_eq!(address("node3"), "10.255.0.3".parse::<IpAddr>().unwrap());
+
+        assert!(WireguardZone::mesh_address(&config, "node4").is_err());
+    }
+
+    #[test]
+    fn test_wireguard_assign_mesh_address() {
+        let mut config = wireguard_zone_config();
+        config.nodes = Some(vec![
+            "node1".to_string(),
+            "node2".to_string(),
+            "node3".to_string(),
+        ]);
+        let ip = |address: &str| address.parse::<IpAddr>().unwrap();
+        let peer = |address: &str| WireguardPeer {
+            public_key: String::new(),
+            address: ip(address),
+            endpoint: ip("192.0.2.1"),
+            created: Utc::now(),
+            next: None,
+        };
+
+        // The published address is kept, wherever the node sorts
+        let mut others = BTreeMap::new();
+        others.insert("node1".to_string(), peer("10.255.0.1"));
+        let assign = |node, published: Option<&str>, others: &BTreeMap<_, _>| {
+            WireguardZone::assign_mesh_address(&config, node, published.map(ip), others).unwrap()
+        };
+        assert_eq!(
+            assign("node3", Some("10.255.0.7"), &others),
+            ip("10.255.0.7")
+        );
+
+        // New nodes take their preferred address or the first free one
+        assert_eq!(assign("node2", None, &others), ip("10.255.0.2"));
+        others.insert("node3".to_string(), peer("10.255.0.2"));
+        assert_eq!(assign("node2", None, &others), ip("10.255.0.3"));
+
+        // Of two nodes claiming an address the first one keeps it
+        assert_eq!(
+            assign("node2", Some("10.255.0.1"), &others),
+            ip("10.255.0.3")
+        );
+        others.remove("node3");
+        others.insert("node2".to_string(), peer("10.255.0.1"));
+        others.remove("node1");
+        assert_eq!(
+            assign("node1", Some("10.255.0.1"), &others),
+            ip("10.255.0.1")
+        );
+
+        // Addresses outside a changed wg-network are given up
+        assert_eq!(
+            assign("node3", Some("10.254.0.3"), &others),
+            ip("10.255.0.3")
+        );
+    }
+
+    #[test]
+    fn test_wireguard_key_format() {
+        assert!(is_wireguard_key(
+            "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk="
+        ));
+        assert!(!is_wireguard_key("not-a-key"));
+        assert!(!is_wireguard_key(
+            "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBm-="
+        ));
+    }
+}
@@ -0,0 +1,244 @@
+//! Integration tests running the sample out-of-process plugin
+
+use std::net::IpAddr;
+
+use pve_sdn_core::controller::ControllerConfig;
+use pve_sdn_core::{
+    ControllerType, IpAllocationRequest, IpamConfig, IpamType, SdnConfiguration, Subnet,
+    SubnetConfig, VNet, VNetConfig, ZoneConfig, ZoneRenderContext, ZoneType,
+};
+use pve_sdn_drivers::ipam::IpamPluginFactory;
+use pve_sdn_drivers::{get_plugin_factory, ExternalPlugin, PluginFactory};
+
+const SAMPLE_PLUGIN: &str = env!("CARGO_BIN_EXE_pve-sdn-sample-plugin");
+
+fn sample() -> String {
+    "sample".to_string()
+}
+
+fn allocation_request(subnet: &str, requested_ip: Option<IpAddr>) -> IpAllocationRequest {
+    IpAllocationRequest {
+        subnet: subnet.to_string(),
+        vmid: Some(100),
+        hostname: Some("ct100".to_string()),
+        mac: None,
+        description: None,
+        requested_ip,
+    }
+}
+
+#[tokio::test]
+async fn test_sample_plugin_handshake() {
+    let plugin = ExternalPlugin::spawn(SAMPLE_PLUGIN.as_ref()).await.unwrap();
+    let capabilities = plugin.capabilities();
+
+    assert_eq!(capabilities.name, "sample");
+    assert_eq!(capabilities.zones, vec![sample()]);
+    assert_eq!(capabilities.controllers, vec![sample()]);
+    assert_eq!(capabilities.ipams, vec![sample()]);
+
+    plugin.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_sample_plugin_zone() {
+    let factory = PluginFactory::new();
+    factory.load_external_plugin(SAMPLE_PLUGIN).await.unwrap();
+
+    let zone_type = ZoneType::External(sample());
+    assert!(factory.has_zone_driver(&zone_type));
+
+    let mut sdn = SdnConfiguration::new();
+    let mut zone_config = ZoneConfig::new(zone_type.clone(), "iso".to_string());
+    zone_config.mtu = Some(1400);
+    sdn.add_zone(zone_config.clone()).unwrap();
+    for vnet in ["isonet1", "isonet2"] {
+        sdn.add_vnet(VNetConfig::new(vnet.to_string(), "iso".to_string()))
+            .unwrap();
+    }
+
+    // Plugin types are only valid where the plugin is loaded
+    let err = sdn.validate().unwrap_err();
+    assert_eq!(err.to_string(), "Unknown zone type 'sample'");
+    sdn.validate_with(&factory).unwrap();
+    assert!(sdn.validate_with(&PluginFactory::new()).is_err());
+
+    // The zone type survives a round trip through the configuration format
+    let json = serde_json::to_value(&zone_config).unwrap();
+    assert_eq!(json["type"], "sample");
+    let parsed: ZoneConfig = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.zone_type, zone_type);
+
+    let zone = factory.create_zone(&zone_type, "iso".to_string()).unwrap();
+    zone.validate_config(&zone_config).await.unwrap();
+
+    let ctx = ZoneRenderContext::new("node1", &sdn);
+    let configs = zone.generate_node_config(&zone_config, &ctx).await.unwrap();
+    assert_eq!(configs.len(), 2);
+    assert_eq!(
+        configs["vnet_isonet1"],
+        "auto isonet1\niface isonet1 inet manual\n    bridge_ports none\n    bridge_stp off\n    bridge_fd 0\n    mtu 1400\n"
+    );
+
+    // Driver errors keep their message
+    zone_config.bridge = Some("vmbr0".to_string());
+    let err = zone.validate_config(&zone_config).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Zone 'iso': sample zones have no uplink bridge"
+    );
+}
+
+#[tokio::test]
+async fn test_sample_plugin_controller() {
+    let factory = PluginFactory::new();
+    factory.load_external_plugin(SAMPLE_PLUGIN).await.unwrap();
+
+    let controller_type = ControllerType::External(sample());
+    let controller = factory
+        .create_controller(&controller_type, "ctl".to_string())
+        .unwrap();
+    assert_eq!(controller.controller_type(), controller_type);
+
+    let mut config = ControllerConfig::new(controller_type, "ctl".to_string());
+    controller.validate_configuration(&config).await.unwrap();
+    config.asn = Some(65000);
+    assert!(controller.validate_configuration(&config).await.is_err());
+
+    assert!(!controller.status().await.unwrap().running);
+    controller.start().await.unwrap();
+    assert!(controller.status().await.unwrap().running);
+    controller.stop().await.unwrap();
+    assert!(!controller.status().await.unwrap().running);
+
+    let zones = vec![
+        factory
+            .create_zone(&ZoneType::External(sample()), "iso".to_string())
+            .unwrap(),
+        factory
+            .create_zone(&ZoneType::Simple, "simple1".to_string())
+            .unwrap(),
+    ];
+    let vnets = vec![VNet::new(VNetConfig::new(
+        "isonet1".to_string(),
+        "iso".to_string(),
+    ))];
+    let err = controller
+        .apply_configuration(&zones, &vnets)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Zone 'simple1' is not a sample zone");
+    controller
+        .apply_configuration(&zones[..1], &vnets)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_sample_plugin_ipam() {
+    let factory = PluginFactory::new();
+    let plugin = factory.load_external_plugin(SAMPLE_PLUGIN).await.unwrap();
+
+    let ipam_type = IpamType::External(sample());
+    let ipam = factory.create_ipam(&ipam_type, "ext".to_string()).unwrap();
+    assert_eq!(ipam.plugin_type(), ipam_type);
+
+    let mut subnet_config = SubnetConfig::new(
+        "iso-10.0.0.0-29".to_string(),
+        "isonet1".to_string(),
+        "10.0.0.0/29".parse().unwrap(),
+    );
+    subnet_config.gateway = Some("10.0.0.1".parse().unwrap());
+    let subnet = Subnet::new(subnet_config);
+    ipam.add_subnet(&subnet).await.unwrap();
+
+    // The gateway is skipped
+    let first = ipam
+        .allocate_ip(&allocation_request("iso-10.0.0.0-29", None))
+        .await
+        .unwrap();
+    assert_eq!(first.ip, "10.0.0.2".parse::<IpAddr>().unwrap());
+
+    let err = ipam
+        .allocate_ip(&allocation_request("iso-10.0.0.0-29", Some(first.ip)))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "IP address 10.0.0.2 already allocated in subnet iso-10.0.0.0-29"
+    );
+
+    // Instances with the same name share the allocations kept by the plugin
+    let same = factory.create_ipam(&ipam_type, "ext".to_string()).unwrap();
+    let listed = same.list_subnet_ips("iso-10.0.0.0-29").await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].hostname.as_deref(), Some("ct100"));
+    assert_eq!(
+        same.get_next_free_ip("iso-10.0.0.0-29").await.unwrap(),
+        Some("10.0.0.3".parse().unwrap())
+    );
+
+    ipam.release_ip("iso-10.0.0.0-29", &first.ip).await.unwrap();
+    assert!(ipam
+        .is_ip_available("iso-10.0.0.0-29", &first.ip)
+        .await
+        .unwrap());
+    assert!(ipam
+        .get_ip("iso-10.0.0.0-29", &first.ip)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Drivers fail once the plugin is gone
+    plugin.shutdown().await.unwrap();
+    assert!(ipam.list_subnet_ips("iso-10.0.0.0-29").await.is_err());
+}
+
+#[tokio::test]
+async fn test_sample_plugin_ipam_factory() {
+    let factory = PluginFactory::new();
+    let config = IpamConfig::new("ext".to_string(), IpamType::External(sample()));
+    assert!(IpamPluginFactory::create_plugin(&factory, &config).is_err());
+
+    // IPAM configurations of a plugin type resolve through the factory the
+    // plugin was loaded into
+    factory.load_external_plugin(SAMPLE_PLUGIN).await.unwrap();
+    let ipam = IpamPluginFactory::create_plugin(&factory, &config).unwrap();
+    assert_eq!(ipam.plugin_type(), IpamType::External(sample()));
+    assert!(ipam.list_subnet_ips("unknown").await.is_err());
+    assert!(IpamPluginFactory::create_plugin(get_plugin_factory(), &config).is_err());
+}
+
+#[tokio::test]
+async fn test_missing_plugin() {
+    let factory = PluginFactory::new();
+    let err = factory
+        .load_external_plugin("/nonexistent/sdn-plugin")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Plugin file does not exist: /nonexistent/sdn-plugin"
+    );
+}
+
+#[tokio::test]
+async fn test_plugin_dir_loading() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::copy(SAMPLE_PLUGIN, dir.path().join("sample")).unwrap();
+    std::fs::write(dir.path().join("broken"), "not a plugin").unwrap();
+
+    let factory = PluginFactory::new();
+    let plugins = factory.load_plugin_dir(dir.path()).await.unwrap();
+
+    // The broken plugin is skipped, the sample one registers its drivers
+    assert_eq!(plugins.len(), 1);
+    assert!(factory.has_zone_driver(&ZoneType::External(sample())));
+
+    let missing = dir.path().join("missing");
+    assert!(factory.load_plugin_dir(&missing).await.unwrap().is_empty());
+
+    for plugin in plugins {
+        plugin.shutdown().await.unwrap();
+    }
+}
@@ -9,7 +9,7 @@ use std::sync::Arc;
 use pve_sdn_core::{
     IpAllocationRequest, IpamConfig, IpamManager, IpamType, Subnet, SubnetConfig, SubnetType,
 };
-use pve_sdn_drivers::{IpamPluginFactory, NetBoxIpam, PhpIpam, PveIpam};
+use pve_sdn_drivers::{IpamPluginFactory, NetBoxIpam, PhpIpam, PluginFactory, PveIpam};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -272,7 +272,9 @@ async fn demonstrate_perl_compatibility() -> Result<()> {
     let pve_config = IpamConfig::new("pve".to_string(), IpamType::Pve);
     ipam_configs.insert("pve".to_string(), pve_config);
 
-    let manager = IpamPluginFactory::initialize_manager(&ipam_configs, Some("pve")).await?;
+    let manager =
+        IpamPluginFactory::initialize_manager(&PluginFactory::new(), &ipam_configs, Some("pve"))
+            .await?;
     println!("✓ Initialized IPAM manager using factory pattern");
 
     // Create subnet for Perl compatibility demo